@@ -19,6 +19,8 @@ fn make_query(contents: &str, values: SerializedValues) -> query::Query<'_> {
             page_size: None,
             paging_state: PagingState::start(),
             timestamp: None,
+            keyspace: None,
+            now_in_seconds: None,
         },
     }
 }
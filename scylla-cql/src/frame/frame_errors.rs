@@ -14,6 +14,7 @@ pub use super::request::{
 use super::response::result::TableSpec;
 use super::response::CqlResponseKind;
 use super::TryFromPrimitiveError;
+use crate::types::serialize::limits::ValueSizeLimitError;
 use thiserror::Error;
 
 /// An error returned by `parse_response_body_extensions`.
@@ -53,6 +54,11 @@ pub enum FrameBodyExtensionsParseError {
     /// Failed to decompress frame body (lz4).
     #[error("Error decompressing lz4 data {0}")]
     Lz4DecompressError(Arc<dyn Error + Sync + Send>),
+
+    /// Failed to decompress frame body (zstd).
+    #[cfg(feature = "zstd")]
+    #[error("Zstd decompression error: {0}")]
+    ZstdDecompressError(Arc<dyn Error + Sync + Send>),
 }
 
 /// An error that occurred during frame header deserialization.
@@ -73,7 +79,7 @@ pub enum FrameHeaderParseError {
     FrameFromServer,
 
     /// Received a frame with unsupported version.
-    #[error("Received a frame from version {0}, but only 4 is supported")]
+    #[error("Received a frame from version {0}, but only 4 and 5 are supported")]
     VersionNotSupported(u8),
 
     /// Received unknown response opcode.
@@ -87,6 +93,22 @@ pub enum FrameHeaderParseError {
     /// Connection was closed before whole frame was read.
     #[error("Connection was closed before body was read: missing {0} out of {1}")]
     ConnectionClosed(usize, usize),
+
+    /// Failed to read a protocol v5 segment from the socket.
+    #[error("Failed to read a segment: {0}")]
+    SegmentIoError(std::io::Error),
+
+    /// The checksum of a protocol v5 segment header doesn't match its contents.
+    #[error("Segment header checksum mismatch")]
+    SegmentHeaderChecksumMismatch,
+
+    /// The checksum of a protocol v5 segment payload doesn't match its contents.
+    #[error("Segment payload checksum mismatch")]
+    SegmentPayloadChecksumMismatch,
+
+    /// Failed to decompress a protocol v5 segment.
+    #[error("Failed to decompress a segment: {0}")]
+    SegmentDecompressError(lz4_flex::block::DecompressError),
 }
 
 /// An error that occurred during CQL request serialization.
@@ -124,6 +146,15 @@ pub enum CqlRequestSerializationError {
     /// Request body compression failed.
     #[error("Snap compression error: {0}")]
     SnapCompressError(Arc<dyn Error + Sync + Send>),
+
+    /// Request body compression failed.
+    #[cfg(feature = "zstd")]
+    #[error("Zstd compression error: {0}")]
+    ZstdCompressError(Arc<dyn Error + Sync + Send>),
+
+    /// The request or one of the values bound to it exceeds the configured size limits.
+    #[error("Request exceeds size limits: {0}")]
+    ValueSizeLimitExceeded(#[from] ValueSizeLimitError),
 }
 
 /// An error type returned when deserialization of CQL
@@ -297,6 +328,8 @@ pub enum ClusterChangeEventParseError {
 pub enum PreparedParseError {
     #[error("Malformed prepared statement's id length: {0}")]
     IdLengthParseError(LowLevelDeserializationError),
+    #[error("Malformed result metadata id: {0}")]
+    ResultMetadataIdParseError(LowLevelDeserializationError),
     #[error("Invalid result metadata: {0}")]
     ResultMetadataParseError(ResultMetadataParseError),
     #[error("Invalid prepared metadata: {0}")]
@@ -369,6 +402,16 @@ pub enum ResultMetadataAndRowsCountParseError {
     /// Received malformed rows count from the server.
     #[error("Malformed rows count: {0}")]
     RowsCountParseError(LowLevelDeserializationError),
+
+    /// The server sent no metadata, but the number of columns differs
+    /// from the metadata cached in the prepared statement.
+    #[error("Cached result metadata is outdated: it has {cached} columns, but the result has {received} columns")]
+    OutdatedCachedMetadata {
+        /// Number of columns in the cached metadata.
+        cached: usize,
+        /// Number of columns in the result.
+        received: usize,
+    },
 }
 
 /// An error type returned when deserialization
@@ -388,6 +431,10 @@ pub enum ResultMetadataParseError {
     #[error("Malformed paging state: {0}")]
     PagingStateParseError(LowLevelDeserializationError),
 
+    /// Failed to parse the id of changed result metadata.
+    #[error("Malformed new result metadata id: {0}")]
+    MetadataIdParseError(LowLevelDeserializationError),
+
     /// Failed to parse global table spec.
     #[error("Invalid global table spec: {0}")]
     GlobalTableSpecParseError(#[from] TableSpecParseError),
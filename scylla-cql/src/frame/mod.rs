@@ -2,6 +2,7 @@ pub mod frame_errors;
 pub mod protocol_features;
 pub mod request;
 pub mod response;
+pub mod segment;
 pub mod server_event_type;
 pub mod types;
 pub mod value;
@@ -26,6 +27,51 @@ use response::ResponseOpcode;
 
 const HEADER_SIZE: usize = 9;
 
+/// The version of the CQL native protocol spoken on a connection.
+#[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug, Default)]
+#[non_exhaustive]
+pub enum ProtocolVersion {
+    /// Protocol v4, supported by all versions of ScyllaDB and Cassandra 2.2 and newer.
+    #[default]
+    V4,
+    /// Protocol v5, supported by Cassandra 4.0 and newer.
+    ///
+    /// After the handshake, frames are sent in segments protected with checksums,
+    /// see [`segment`].
+    V5,
+}
+
+impl ProtocolVersion {
+    /// Returns the version number sent in frame headers.
+    pub fn as_u8(self) -> u8 {
+        match self {
+            ProtocolVersion::V4 => 4,
+            ProtocolVersion::V5 => 5,
+        }
+    }
+}
+
+impl TryFrom<u8> for ProtocolVersion {
+    type Error = TryFromPrimitiveError<u8>;
+
+    fn try_from(value: u8) -> Result<Self, Self::Error> {
+        match value {
+            4 => Ok(Self::V4),
+            5 => Ok(Self::V5),
+            _ => Err(TryFromPrimitiveError {
+                enum_name: "ProtocolVersion",
+                primitive: value,
+            }),
+        }
+    }
+}
+
+impl Display for ProtocolVersion {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "v{}", self.as_u8())
+    }
+}
+
 // Frame flags
 const FLAG_COMPRESSION: u8 = 0x01;
 const FLAG_TRACING: u8 = 0x02;
@@ -44,11 +90,15 @@ pub enum Authenticator {
 
 /// The wire protocol compression algorithm.
 #[derive(Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Debug)]
+#[non_exhaustive]
 pub enum Compression {
     /// LZ4 compression algorithm.
     Lz4,
     /// Snappy compression algorithm.
     Snappy,
+    /// Zstandard compression algorithm. Requires the `zstd` feature.
+    #[cfg(feature = "zstd")]
+    Zstd,
 }
 
 impl Compression {
@@ -56,6 +106,8 @@ impl Compression {
         match self {
             Compression::Lz4 => "lz4",
             Compression::Snappy => "snappy",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => "zstd",
         }
     }
 }
@@ -68,6 +120,7 @@ impl Display for Compression {
 
 pub struct SerializedRequest {
     data: Vec<u8>,
+    uncompressed_body_len: usize,
 }
 
 impl SerializedRequest {
@@ -76,22 +129,54 @@ impl SerializedRequest {
         compression: Option<Compression>,
         tracing: bool,
     ) -> Result<SerializedRequest, CqlRequestSerializationError> {
-        let mut flags = 0;
-        let mut data = vec![0; HEADER_SIZE];
+        Self::make_in(req, compression, tracing, Vec::new())
+    }
 
-        if let Some(compression) = compression {
+    /// Serializes the request into the given buffer, reusing its allocation.
+    /// The previous contents of the buffer are discarded.
+    pub fn make_in<R: SerializableRequest>(
+        req: &R,
+        compression: Option<Compression>,
+        tracing: bool,
+        data: Vec<u8>,
+    ) -> Result<SerializedRequest, CqlRequestSerializationError> {
+        Self::make_versioned_in(req, ProtocolVersion::V4, compression, 0, tracing, data)
+    }
+
+    /// Serializes the request for the given protocol version into the given buffer,
+    /// reusing its allocation. The previous contents of the buffer are discarded.
+    ///
+    /// The body is compressed only if it has at least `min_compressed_size` bytes,
+    /// smaller bodies are sent uncompressed.
+    /// Protocol v5 compresses whole segments rather than frames,
+    /// so `compression` should be `None` for it.
+    pub fn make_versioned_in<R: SerializableRequest>(
+        req: &R,
+        version: ProtocolVersion,
+        compression: Option<Compression>,
+        min_compressed_size: usize,
+        tracing: bool,
+        mut data: Vec<u8>,
+    ) -> Result<SerializedRequest, CqlRequestSerializationError> {
+        let mut flags = 0;
+        data.clear();
+        data.resize(HEADER_SIZE, 0);
+
+        req.serialize_versioned(&mut data, version)?;
+        let uncompressed_body_len = data.len() - HEADER_SIZE;
+        if let Some(compression) =
+            compression.filter(|_| uncompressed_body_len >= min_compressed_size)
+        {
             flags |= FLAG_COMPRESSION;
-            let body = req.to_bytes()?;
+            let body = data.split_off(HEADER_SIZE);
             compress_append(&body, compression, &mut data)?;
-        } else {
-            req.serialize(&mut data)?;
         }
 
         if tracing {
             flags |= FLAG_TRACING;
         }
 
-        data[0] = 4; // We only support version 4 for now
+        data[0] = version.as_u8();
         data[1] = flags;
         // Leave space for the stream number
         data[4] = R::OPCODE as u8;
@@ -99,7 +184,10 @@ impl SerializedRequest {
         let req_size = (data.len() - HEADER_SIZE) as u32;
         data[5..9].copy_from_slice(&req_size.to_be_bytes());
 
-        Ok(Self { data })
+        Ok(Self {
+            data,
+            uncompressed_body_len,
+        })
     }
 
     pub fn set_stream(&mut self, stream: i16) {
@@ -109,6 +197,26 @@ impl SerializedRequest {
     pub fn get_data(&self) -> &[u8] {
         &self.data[..]
     }
+
+    /// Returns true if the body of the request is compressed.
+    pub fn is_compressed(&self) -> bool {
+        self.data[1] & FLAG_COMPRESSION != 0
+    }
+
+    /// Returns the size of the body of the request, as sent.
+    pub fn get_body_len(&self) -> usize {
+        self.data.len() - HEADER_SIZE
+    }
+
+    /// Returns the size of the body of the request before compression.
+    pub fn get_uncompressed_body_len(&self) -> usize {
+        self.uncompressed_body_len
+    }
+
+    /// Returns the buffer holding the serialized request, so that it can be reused.
+    pub fn into_buffer(self) -> Vec<u8> {
+        self.data
+    }
 }
 
 // Parts of the frame header which are not determined by the request/response type.
@@ -132,20 +240,100 @@ impl Default for FrameParams {
 pub async fn read_response_frame(
     reader: &mut (impl AsyncRead + Unpin),
 ) -> Result<(FrameParams, ResponseOpcode, Bytes), FrameHeaderParseError> {
+    let (frame_params, opcode, length) = read_response_header(reader).await?;
+    let body = read_response_body(reader, length).await?;
+    Ok((frame_params, opcode, body))
+}
+
+/// The body of a frame read by [`read_response_frame_with_limit`].
+#[derive(Debug)]
+pub enum ResponseFrameBody {
+    /// The body fits in the limit and was read in full.
+    Received(Bytes),
+
+    /// The body is larger than the limit and was discarded while being read,
+    /// without being buffered.
+    Discarded {
+        /// The size of the body, in bytes.
+        len: usize,
+        /// The limit the body exceeded, in bytes.
+        limit: usize,
+    },
+}
+
+/// Reads a response frame like [`read_response_frame`], but discards the body
+/// if it is larger than the limit which `max_body_len` returns for the frame.
+pub async fn read_response_frame_with_limit(
+    reader: &mut (impl AsyncRead + Unpin),
+    max_body_len: impl FnOnce(&FrameParams) -> Option<usize>,
+) -> Result<(FrameParams, ResponseOpcode, ResponseFrameBody), FrameHeaderParseError> {
+    let (frame_params, opcode, length) = read_response_header(reader).await?;
+    let body = match max_body_len(&frame_params) {
+        Some(limit) if length > limit => {
+            let discarded = tokio::io::copy(
+                &mut (&mut *reader).take(length as u64),
+                &mut tokio::io::sink(),
+            )
+            .await
+            .map_err(|err| FrameHeaderParseError::BodyChunkIoError(length, err))?
+                as usize;
+            if discarded < length {
+                return Err(FrameHeaderParseError::ConnectionClosed(
+                    length - discarded,
+                    length,
+                ));
+            }
+            ResponseFrameBody::Discarded { len: length, limit }
+        }
+        _ => ResponseFrameBody::Received(read_response_body(reader, length).await?),
+    };
+    Ok((frame_params, opcode, body))
+}
+
+async fn read_response_header(
+    reader: &mut (impl AsyncRead + Unpin),
+) -> Result<(FrameParams, ResponseOpcode, usize), FrameHeaderParseError> {
     let mut raw_header = [0u8; HEADER_SIZE];
     reader
         .read_exact(&mut raw_header[..])
         .await
         .map_err(FrameHeaderParseError::HeaderIoError)?;
 
-    let mut buf = &raw_header[..];
+    parse_response_header(&raw_header)
+}
+
+async fn read_response_body(
+    reader: &mut (impl AsyncRead + Unpin),
+    length: usize,
+) -> Result<Bytes, FrameHeaderParseError> {
+    let mut raw_body = Vec::with_capacity(length).limit(length);
+    while raw_body.has_remaining_mut() {
+        let n = reader.read_buf(&mut raw_body).await.map_err(|err| {
+            FrameHeaderParseError::BodyChunkIoError(raw_body.remaining_mut(), err)
+        })?;
+        if n == 0 {
+            // EOF, too early
+            return Err(FrameHeaderParseError::ConnectionClosed(
+                raw_body.remaining_mut(),
+                length,
+            ));
+        }
+    }
+
+    Ok(raw_body.into_inner().into())
+}
 
-    // TODO: Validate version
+// Parses the header of a response frame, returning the length of its body.
+fn parse_response_header(
+    mut buf: &[u8],
+) -> Result<(FrameParams, ResponseOpcode, usize), FrameHeaderParseError> {
     let version = buf.get_u8();
     if version & 0x80 != 0x80 {
         return Err(FrameHeaderParseError::FrameFromClient);
     }
-    if version & 0x7F != 0x04 {
+    // The version of the response is not checked against the version of the connection,
+    // as a server which doesn't support the requested version responds with its own one.
+    if ProtocolVersion::try_from(version & 0x7F).is_err() {
         return Err(FrameHeaderParseError::VersionNotSupported(version & 0x7f));
     }
 
@@ -159,25 +347,9 @@ pub async fn read_response_frame(
     };
 
     let opcode = ResponseOpcode::try_from(buf.get_u8())?;
-
-    // TODO: Guard from frames that are too large
     let length = buf.get_u32() as usize;
 
-    let mut raw_body = Vec::with_capacity(length).limit(length);
-    while raw_body.has_remaining_mut() {
-        let n = reader.read_buf(&mut raw_body).await.map_err(|err| {
-            FrameHeaderParseError::BodyChunkIoError(raw_body.remaining_mut(), err)
-        })?;
-        if n == 0 {
-            // EOF, too early
-            return Err(FrameHeaderParseError::ConnectionClosed(
-                raw_body.remaining_mut(),
-                length,
-            ));
-        }
-    }
-
-    Ok((frame_params, opcode, raw_body.into_inner().into()))
+    Ok((frame_params, opcode, length))
 }
 
 pub struct ResponseBodyWithExtensions {
@@ -265,6 +437,13 @@ fn compress_append(
             out.truncate(old_size + compressed_size);
             Ok(())
         }
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => {
+            let tmp = zstd::bulk::compress(uncomp_body, zstd::DEFAULT_COMPRESSION_LEVEL)
+                .map_err(|err| CqlRequestSerializationError::ZstdCompressError(Arc::new(err)))?;
+            out.extend_from_slice(&tmp[..]);
+            Ok(())
+        }
     }
 }
 
@@ -282,6 +461,9 @@ fn decompress(
         Compression::Snappy => snap::raw::Decoder::new()
             .decompress_vec(comp_body)
             .map_err(|err| FrameBodyExtensionsParseError::SnapDecompressError(Arc::new(err))),
+        #[cfg(feature = "zstd")]
+        Compression::Zstd => zstd::stream::decode_all(comp_body)
+            .map_err(|err| FrameBodyExtensionsParseError::ZstdDecompressError(Arc::new(err))),
     }
 }
 
@@ -320,4 +502,94 @@ mod test {
         assert_eq!(32, comp_body.len());
         assert_eq!(uncomp_body.as_bytes(), result);
     }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_roundtrip() {
+        let mut comp_body = Vec::new();
+        let uncomp_body = "Hello, World!".repeat(100);
+        let compression = Compression::Zstd;
+        compress_append(uncomp_body.as_bytes(), compression, &mut comp_body).unwrap();
+        assert!(comp_body.len() < uncomp_body.len());
+        let result = decompress(&comp_body[..], compression).unwrap();
+        assert_eq!(uncomp_body.as_bytes(), result);
+    }
+
+    #[test]
+    fn test_min_compressed_size() {
+        let query = request::query::Query {
+            contents: std::borrow::Cow::Borrowed("SELECT * FROM ks.tab"),
+            parameters: Default::default(),
+        };
+        let body_len = SerializedRequest::make(&query, None, false)
+            .unwrap()
+            .get_uncompressed_body_len();
+
+        for (min_compressed_size, expect_compressed) in
+            [(0, true), (body_len, true), (body_len + 1, false)]
+        {
+            let request = SerializedRequest::make_versioned_in(
+                &query,
+                ProtocolVersion::V4,
+                Some(Compression::Lz4),
+                min_compressed_size,
+                false,
+                Vec::new(),
+            )
+            .unwrap();
+            assert_eq!(request.is_compressed(), expect_compressed);
+            assert_eq!(request.get_uncompressed_body_len(), body_len);
+        }
+    }
+
+    fn response_frame(stream: i16, body: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x84, 0x00];
+        frame.put_i16(stream);
+        frame.put_u8(ResponseOpcode::Result as u8);
+        frame.put_u32(body.len() as u32);
+        frame.extend_from_slice(body);
+        frame
+    }
+
+    #[tokio::test]
+    async fn test_read_response_frame_with_limit() {
+        let large_body = vec![0xAB; 100_000];
+        let mut stream = response_frame(1, &large_body);
+        stream.extend(response_frame(2, b"small"));
+        let limit_of_first = |params: &FrameParams| (params.stream == 1).then_some(1000);
+
+        let mut input = &stream[..];
+        let (params, _, body) = read_response_frame_with_limit(&mut input, limit_of_first)
+            .await
+            .unwrap();
+        assert_eq!(params.stream, 1);
+        assert!(matches!(
+            body,
+            ResponseFrameBody::Discarded {
+                len: 100_000,
+                limit: 1000
+            }
+        ));
+        let (params, _, body) = read_response_frame_with_limit(&mut input, limit_of_first)
+            .await
+            .unwrap();
+        assert_eq!(params.stream, 2);
+        assert!(matches!(body, ResponseFrameBody::Received(body) if body == b"small"[..]));
+        assert!(input.is_empty());
+
+        // A body which fits in the limit exactly is received.
+        let (_, _, body) = read_response_frame_with_limit(&mut &stream[..], |_| Some(100_000))
+            .await
+            .unwrap();
+        assert!(matches!(body, ResponseFrameBody::Received(body) if body.len() == 100_000));
+
+        // The connection is closed while the body is being discarded.
+        let err = read_response_frame_with_limit(&mut &stream[..50_000], |_| Some(1000))
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FrameHeaderParseError::ConnectionClosed(_, 100_000)
+        ));
+    }
 }
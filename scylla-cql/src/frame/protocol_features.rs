@@ -1,6 +1,8 @@
 use std::borrow::Cow;
 use std::collections::HashMap;
 
+use super::ProtocolVersion;
+
 const RATE_LIMIT_ERROR_EXTENSION: &str = "SCYLLA_RATE_LIMIT_ERROR";
 pub const SCYLLA_LWT_ADD_METADATA_MARK_EXTENSION: &str = "SCYLLA_LWT_ADD_METADATA_MARK";
 pub const LWT_OPTIMIZATION_META_BIT_MASK_KEY: &str = "LWT_OPTIMIZATION_META_BIT_MASK";
@@ -12,6 +14,9 @@ pub struct ProtocolFeatures {
     pub rate_limit_error: Option<i32>,
     pub lwt_optimization_meta_bit_mask: Option<u32>,
     pub tablets_v1_supported: bool,
+    /// The protocol version negotiated on the connection,
+    /// which decides the format of some responses.
+    pub protocol_version: ProtocolVersion,
 }
 
 // TODO: Log information about options which failed to parse
@@ -24,6 +29,7 @@ impl ProtocolFeatures {
                 supported,
             ),
             tablets_v1_supported: Self::check_tablets_routing_v1_support(supported),
+            protocol_version: ProtocolVersion::default(),
         }
     }
 
@@ -7,6 +7,7 @@ use crate::{
         frame_errors::CqlRequestSerializationError,
         request::{RequestOpcode, SerializableRequest},
         types::{self, SerialConsistency},
+        ProtocolVersion,
     },
     types::serialize::{
         raw_batch::{RawBatchValues, RawBatchValuesIterator},
@@ -22,6 +23,9 @@ const FLAG_WITH_SERIAL_CONSISTENCY: u8 = 0x10;
 const FLAG_WITH_DEFAULT_TIMESTAMP: u8 = 0x20;
 const ALL_FLAGS: u8 = FLAG_WITH_SERIAL_CONSISTENCY | FLAG_WITH_DEFAULT_TIMESTAMP;
 
+// Batch flags introduced in protocol v5, which sends the flags as an int
+const FLAG_WITH_NOW_IN_SECONDS: u32 = 0x100;
+
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 pub struct Batch<'b, Statement, Values>
 where
@@ -34,6 +38,9 @@ where
     pub consistency: types::Consistency,
     pub serial_consistency: Option<types::SerialConsistency>,
     pub timestamp: Option<i64>,
+    /// The current time, in seconds since the Unix epoch, to be used by the database
+    /// for the batch. Requires protocol v5.
+    pub now_in_seconds: Option<i32>,
     pub values: Values,
 }
 
@@ -77,7 +84,15 @@ where
     Statement: Clone,
     Values: RawBatchValues,
 {
-    fn do_serialize(&self, buf: &mut Vec<u8>) -> Result<(), BatchSerializationError> {
+    fn do_serialize(
+        &self,
+        buf: &mut Vec<u8>,
+        version: ProtocolVersion,
+    ) -> Result<(), BatchSerializationError> {
+        if version < ProtocolVersion::V5 && self.now_in_seconds.is_some() {
+            return Err(BatchSerializationError::NowInSecondsUnsupported(version));
+        }
+
         // Serializing type of batch
         buf.put_u8(self.batch_type as u8);
 
@@ -163,7 +178,15 @@ where
             flags |= FLAG_WITH_DEFAULT_TIMESTAMP;
         }
 
-        buf.put_u8(flags);
+        if version < ProtocolVersion::V5 {
+            buf.put_u8(flags);
+        } else {
+            let mut flags = flags as u32;
+            if self.now_in_seconds.is_some() {
+                flags |= FLAG_WITH_NOW_IN_SECONDS;
+            }
+            buf.put_u32(flags);
+        }
 
         if let Some(serial_consistency) = self.serial_consistency {
             types::write_serial_consistency(serial_consistency, buf);
@@ -171,6 +194,9 @@ where
         if let Some(timestamp) = self.timestamp {
             types::write_long(timestamp, buf);
         }
+        if let Some(now_in_seconds) = self.now_in_seconds {
+            types::write_int(now_in_seconds, buf);
+        }
 
         Ok(())
     }
@@ -185,7 +211,15 @@ where
     const OPCODE: RequestOpcode = RequestOpcode::Batch;
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
-        self.do_serialize(buf)?;
+        self.serialize_versioned(buf, ProtocolVersion::V4)
+    }
+
+    fn serialize_versioned(
+        &self,
+        buf: &mut Vec<u8>,
+        version: ProtocolVersion,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.do_serialize(buf, version)?;
         Ok(())
     }
 }
@@ -293,6 +327,7 @@ impl<'b> DeserializableRequest for Batch<'b, BatchStatement<'b>, Vec<SerializedV
             consistency,
             serial_consistency,
             timestamp,
+            now_in_seconds: None,
             statements: Cow::Owned(statements),
             values,
         })
@@ -327,6 +362,10 @@ pub enum BatchSerializationError {
         n_announced_statements: usize,
         n_serialized_statements: usize,
     },
+
+    /// The current time can't be sent with the protocol version of the connection.
+    #[error("now_in_seconds can't be sent along with a batch in protocol {0}")]
+    NowInSecondsUnsupported(ProtocolVersion),
 }
 
 /// An error type returned when serialization of one of the
@@ -6,7 +6,7 @@ use thiserror::Error;
 
 use crate::{
     frame::request::{query, RequestOpcode, SerializableRequest},
-    frame::types,
+    frame::{types, ProtocolVersion},
 };
 
 use super::{
@@ -17,6 +17,9 @@ use super::{
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 pub struct Execute<'a> {
     pub id: Bytes,
+    /// The id of the result metadata known to the client, sent with protocol v5
+    /// (empty if `None`). If it's outdated, the database responds with the new metadata.
+    pub result_metadata_id: Option<Bytes>,
     pub parameters: query::QueryParameters<'a>,
 }
 
@@ -24,13 +27,28 @@ impl SerializableRequest for Execute<'_> {
     const OPCODE: RequestOpcode = RequestOpcode::Execute;
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_versioned(buf, ProtocolVersion::V4)
+    }
+
+    fn serialize_versioned(
+        &self,
+        buf: &mut Vec<u8>,
+        version: ProtocolVersion,
+    ) -> Result<(), CqlRequestSerializationError> {
         // Serializing statement id
         types::write_short_bytes(&self.id[..], buf)
             .map_err(ExecuteSerializationError::StatementIdSerialization)?;
 
+        // Serializing result metadata id
+        if version >= ProtocolVersion::V5 {
+            let result_metadata_id = self.result_metadata_id.as_deref().unwrap_or_default();
+            types::write_short_bytes(result_metadata_id, buf)
+                .map_err(ExecuteSerializationError::StatementIdSerialization)?;
+        }
+
         // Serializing params
         self.parameters
-            .serialize(buf)
+            .serialize_versioned(buf, version)
             .map_err(ExecuteSerializationError::QueryParametersSerialization)?;
         Ok(())
     }
@@ -41,7 +59,11 @@ impl DeserializableRequest for Execute<'_> {
         let id = types::read_short_bytes(buf)?.to_vec().into();
         let parameters = QueryParameters::deserialize(buf)?;
 
-        Ok(Self { id, parameters })
+        Ok(Self {
+            id,
+            result_metadata_id: None,
+            parameters,
+        })
     }
 }
 
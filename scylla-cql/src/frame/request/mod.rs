@@ -26,7 +26,7 @@ use self::batch::BatchStatement;
 
 use super::frame_errors::{CqlRequestSerializationError, LowLevelDeserializationError};
 use super::types::SerialConsistency;
-use super::TryFromPrimitiveError;
+use super::{ProtocolVersion, TryFromPrimitiveError};
 
 /// Possible requests sent by the client.
 #[derive(Debug, Copy, Clone)]
@@ -98,6 +98,18 @@ pub trait SerializableRequest {
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError>;
 
+    /// Serializes the request for the given protocol version.
+    ///
+    /// [`Self::serialize`] uses protocol v4, so only the requests which
+    /// differ between the versions need to override this method.
+    fn serialize_versioned(
+        &self,
+        buf: &mut Vec<u8>,
+        _version: ProtocolVersion,
+    ) -> Result<(), CqlRequestSerializationError> {
+        self.serialize(buf)
+    }
+
     fn to_bytes(&self) -> Result<Bytes, CqlRequestSerializationError> {
         let mut v = Vec::new();
         self.serialize(&mut v)?;
@@ -181,26 +193,42 @@ impl Request<'_> {
 
 #[cfg(test)]
 mod tests {
-    use std::{borrow::Cow, ops::Deref};
+    use std::{borrow::Cow, collections::HashMap, ops::Deref};
 
+    use assert_matches::assert_matches;
     use bytes::Bytes;
 
     use crate::{
         frame::{
+            frame_errors::CqlRequestSerializationError,
             request::{
-                batch::{Batch, BatchStatement, BatchType},
+                batch::{
+                    Batch, BatchSerializationError, BatchStatement,
+                    BatchStatementSerializationError, BatchType,
+                },
                 execute::Execute,
-                query::{Query, QueryParameters},
+                prepare::{Prepare, PrepareSerializationError},
+                query::{
+                    Query, QueryParameters, QueryParametersSerializationError,
+                    QuerySerializationError,
+                },
                 DeserializableRequest, SerializableRequest,
             },
-            response::result::ColumnType,
+            response::result::{ColumnSpec, ColumnType, TableSpec},
             types::{self, SerialConsistency},
+            ProtocolVersion,
+        },
+        types::serialize::{
+            raw_batch::RawBatchValuesAdapter,
+            row::{
+                BuiltinTypeCheckError, BuiltinTypeCheckErrorKind, RowSerializationContext,
+                SerializedValues,
+            },
         },
-        types::serialize::row::SerializedValues,
         Consistency,
     };
 
-    use super::query::PagingState;
+    use super::query::{PagingState, PagingStateTokenError};
 
     #[test]
     fn request_ser_de_identity() {
@@ -213,6 +241,8 @@ mod tests {
             page_size: Some(323),
             paging_state: PagingState::new_from_raw_bytes(&[2_u8, 1, 3, 7] as &[u8]),
             skip_metadata: false,
+            keyspace: None,
+            now_in_seconds: None,
             values: {
                 let mut vals = SerializedValues::new();
                 vals.add_value(&2137, &ColumnType::Int).unwrap();
@@ -241,6 +271,8 @@ mod tests {
             page_size: None,
             paging_state: PagingState::start(),
             skip_metadata: false,
+            keyspace: None,
+            now_in_seconds: None,
             values: {
                 let mut vals = SerializedValues::new();
                 vals.add_value(&42, &ColumnType::Int).unwrap();
@@ -248,7 +280,11 @@ mod tests {
                 Cow::Owned(vals)
             },
         };
-        let execute = Execute { id, parameters };
+        let execute = Execute {
+            id,
+            result_metadata_id: None,
+            parameters,
+        };
         {
             let mut buf = Vec::new();
             execute.serialize(&mut buf).unwrap();
@@ -272,6 +308,7 @@ mod tests {
             consistency: Consistency::EachQuorum,
             serial_consistency: Some(SerialConsistency::LocalSerial),
             timestamp: Some(32432),
+            now_in_seconds: None,
 
             // Not execute's values, because named values are not supported in batches.
             values: vec![
@@ -299,6 +336,8 @@ mod tests {
             page_size: None,
             paging_state: PagingState::start(),
             skip_metadata: false,
+            keyspace: None,
+            now_in_seconds: None,
             values: Cow::Borrowed(SerializedValues::EMPTY),
         };
         let query = Query {
@@ -347,6 +386,7 @@ mod tests {
             consistency: Consistency::EachQuorum,
             serial_consistency: None,
             timestamp: None,
+            now_in_seconds: None,
 
             values: vec![query.parameters.values.deref().clone()],
         };
@@ -370,4 +410,175 @@ mod tests {
             let _parse_error = Batch::deserialize(&mut &buf[..]).unwrap_err();
         }
     }
+
+    #[test]
+    fn v5_request_serialization() {
+        let query = Query {
+            contents: Cow::Borrowed("SELECT 1"),
+            parameters: QueryParameters {
+                keyspace: Some(Cow::Borrowed("ks")),
+                now_in_seconds: Some(1000),
+                ..Default::default()
+            },
+        };
+        let mut buf = Vec::new();
+        query
+            .serialize_versioned(&mut buf, ProtocolVersion::V5)
+            .unwrap();
+        let mut expected = Vec::new();
+        types::write_long_string("SELECT 1", &mut expected).unwrap();
+        types::write_consistency(Consistency::default(), &mut expected);
+        expected.extend(0x0180_u32.to_be_bytes());
+        types::write_string("ks", &mut expected).unwrap();
+        expected.extend(1000_i32.to_be_bytes());
+        assert_eq!(buf, expected);
+
+        // Protocol v4 can't send the keyspace nor the current time.
+        assert_matches!(
+            query.serialize(&mut Vec::new()),
+            Err(CqlRequestSerializationError::QuerySerialization(
+                QuerySerializationError::QueryParametersSerialization(
+                    QueryParametersSerializationError::UnsupportedByProtocolVersion {
+                        parameter: "keyspace",
+                        version: ProtocolVersion::V4,
+                    }
+                )
+            ))
+        );
+
+        let execute = Execute {
+            id: vec![1, 2].into(),
+            result_metadata_id: Some(vec![3, 4, 5].into()),
+            parameters: Default::default(),
+        };
+        let mut buf = Vec::new();
+        execute
+            .serialize_versioned(&mut buf, ProtocolVersion::V5)
+            .unwrap();
+        assert_eq!(
+            buf,
+            [0, 2, 1, 2, 0, 3, 3, 4, 5, 0, 6, 0, 0, 0, 0],
+            "id, result metadata id, consistency, int flags"
+        );
+
+        let prepare = Prepare {
+            query: "SELECT 1",
+            keyspace: Some("ks"),
+        };
+        let mut buf = Vec::new();
+        prepare
+            .serialize_versioned(&mut buf, ProtocolVersion::V5)
+            .unwrap();
+        assert_eq!(&buf[12..], [0, 0, 0, 1, 0, 2, b'k', b's']);
+        assert_matches!(
+            prepare.serialize(&mut Vec::new()),
+            Err(CqlRequestSerializationError::PrepareSerialization(
+                PrepareSerializationError::KeyspaceUnsupported(ProtocolVersion::V4)
+            ))
+        );
+    }
+
+    #[test]
+    fn batch_with_named_values() {
+        let specs = [
+            ColumnSpec::borrowed("a", ColumnType::Int, TableSpec::borrowed("ks", "tbl")),
+            ColumnSpec::borrowed("b", ColumnType::Int, TableSpec::borrowed("ks", "tbl")),
+        ];
+        let id: Bytes = vec![2, 4, 5, 2, 6, 7, 3, 1].into();
+        let statements = vec![
+            BatchStatement::Prepared {
+                id: Cow::Borrowed(&id),
+            },
+            BatchStatement::Prepared {
+                id: Cow::Borrowed(&id),
+            },
+        ];
+        let serialize = |values: Vec<Vec<(&str, i32)>>| {
+            let values: Vec<HashMap<_, _>> = values
+                .into_iter()
+                .map(|named| named.into_iter().collect())
+                .collect();
+            let contexts = std::iter::repeat_with(|| RowSerializationContext { columns: &specs });
+            let batch = Batch {
+                statements: Cow::Borrowed(&statements),
+                batch_type: BatchType::Logged,
+                consistency: Consistency::One,
+                serial_consistency: None,
+                timestamp: None,
+                now_in_seconds: None,
+                values: RawBatchValuesAdapter::new(values, contexts),
+            };
+            let mut buf = Vec::new();
+            batch.serialize(&mut buf).map(|()| buf)
+        };
+        let typeck_err = |err| {
+            let CqlRequestSerializationError::BatchSerialization(
+                BatchSerializationError::StatementSerialization {
+                    statement_idx,
+                    error: BatchStatementSerializationError::ValuesSerialiation(err),
+                },
+            ) = err
+            else {
+                panic!("unexpected error: {}", err)
+            };
+            let err = err.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+            (statement_idx, err.kind.clone())
+        };
+
+        // Values are matched with the bind markers by name and sent in their order.
+        let buf = serialize(vec![vec![("b", 2), ("a", 1)], vec![("a", 3), ("b", 4)]]).unwrap();
+        let batch = Batch::deserialize(&mut &buf[..]).unwrap();
+        for (values, expected) in batch.values.iter().zip([[1, 2], [3, 4]]) {
+            let mut expected_values = SerializedValues::new();
+            for value in expected {
+                expected_values.add_value(&value, &ColumnType::Int).unwrap();
+            }
+            assert_eq!(values, &expected_values);
+        }
+
+        // A missing value is reported along with the index of the statement.
+        let err = serialize(vec![vec![("a", 1), ("b", 2)], vec![("a", 3)]]).unwrap_err();
+        assert_matches!(
+            typeck_err(err),
+            (1, BuiltinTypeCheckErrorKind::ValueMissingForColumn { name }) if name == "b"
+        );
+
+        // So is a value that doesn't correspond to any bind marker.
+        let err = serialize(vec![
+            vec![("a", 1), ("b", 2), ("c", 5)],
+            vec![("a", 3), ("b", 4)],
+        ])
+        .unwrap_err();
+        assert_matches!(
+            typeck_err(err),
+            (0, BuiltinTypeCheckErrorKind::NoColumnWithName { name }) if name == "c"
+        );
+    }
+
+    #[test]
+    fn paging_state_token_roundtrip() {
+        let state = PagingState::new_from_raw_bytes(&[0xfb, 0xff, 0x00, 0x42][..]);
+        let token = state.to_token();
+        assert!(token
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'));
+        assert_eq!(PagingState::from_token(&token).unwrap(), state);
+
+        let start = PagingState::start();
+        assert_eq!(PagingState::from_token(&start.to_token()).unwrap(), start);
+
+        assert_eq!(
+            PagingState::from_token("not a token!"),
+            Err(PagingStateTokenError::BadEncoding)
+        );
+        assert_eq!(
+            PagingState::from_token(""),
+            Err(PagingStateTokenError::BadEncoding)
+        );
+        // A token of an unknown future version, [2, 1, 2, 3].
+        assert_eq!(
+            PagingState::from_token("AgECAw"),
+            Err(PagingStateTokenError::UnsupportedVersion(2))
+        );
+    }
 }
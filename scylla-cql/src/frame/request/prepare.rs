@@ -1,24 +1,54 @@
 use std::num::TryFromIntError;
 
+use bytes::BufMut;
 use thiserror::Error;
 
 use crate::frame::frame_errors::CqlRequestSerializationError;
 
 use crate::{
     frame::request::{RequestOpcode, SerializableRequest},
-    frame::types,
+    frame::{types, ProtocolVersion},
 };
 
+// Prepare flags, sent since protocol v5
+const FLAG_WITH_KEYSPACE: u32 = 0x01;
+
 pub struct Prepare<'a> {
     pub query: &'a str,
+    /// The keyspace the statement is prepared in. Requires protocol v5.
+    pub keyspace: Option<&'a str>,
 }
 
 impl SerializableRequest for Prepare<'_> {
     const OPCODE: RequestOpcode = RequestOpcode::Prepare;
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_versioned(buf, ProtocolVersion::V4)
+    }
+
+    fn serialize_versioned(
+        &self,
+        buf: &mut Vec<u8>,
+        version: ProtocolVersion,
+    ) -> Result<(), CqlRequestSerializationError> {
         types::write_long_string(self.query, buf)
             .map_err(PrepareSerializationError::StatementStringSerialization)?;
+
+        if version < ProtocolVersion::V5 {
+            if self.keyspace.is_some() {
+                return Err(PrepareSerializationError::KeyspaceUnsupported(version).into());
+            }
+            return Ok(());
+        }
+
+        match self.keyspace {
+            Some(keyspace) => {
+                buf.put_u32(FLAG_WITH_KEYSPACE);
+                types::write_string(keyspace, buf)
+                    .map_err(PrepareSerializationError::KeyspaceSerialization)?;
+            }
+            None => buf.put_u32(0),
+        }
         Ok(())
     }
 }
@@ -30,4 +60,12 @@ pub enum PrepareSerializationError {
     /// Failed to serialize the CQL statement string.
     #[error("Failed to serialize statement contents: {0}")]
     StatementStringSerialization(TryFromIntError),
+
+    /// Failed to serialize the keyspace name.
+    #[error("Failed to serialize keyspace name: {0}")]
+    KeyspaceSerialization(TryFromIntError),
+
+    /// The keyspace can't be sent with the protocol version of the connection.
+    #[error("Keyspace can't be sent along with a statement in protocol {0}")]
+    KeyspaceUnsupported(ProtocolVersion),
 }
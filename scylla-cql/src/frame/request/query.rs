@@ -1,9 +1,12 @@
 use std::{borrow::Cow, num::TryFromIntError, ops::ControlFlow, sync::Arc};
 
 use crate::{
-    frame::{frame_errors::CqlRequestSerializationError, types::SerialConsistency},
+    frame::{
+        frame_errors::CqlRequestSerializationError, types::SerialConsistency, ProtocolVersion,
+    },
     types::serialize::row::SerializedValues,
 };
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
 use bytes::{Buf, BufMut};
 use thiserror::Error;
 
@@ -30,6 +33,10 @@ const ALL_FLAGS: u8 = FLAG_VALUES
     | FLAG_WITH_DEFAULT_TIMESTAMP
     | FLAG_WITH_NAMES_FOR_VALUES;
 
+// Query flags introduced in protocol v5, which sends the flags as an int
+const FLAG_WITH_KEYSPACE: u32 = 0x80;
+const FLAG_WITH_NOW_IN_SECONDS: u32 = 0x100;
+
 #[cfg_attr(test, derive(Debug, PartialEq, Eq))]
 pub struct Query<'q> {
     pub contents: Cow<'q, str>,
@@ -40,10 +47,18 @@ impl SerializableRequest for Query<'_> {
     const OPCODE: RequestOpcode = RequestOpcode::Query;
 
     fn serialize(&self, buf: &mut Vec<u8>) -> Result<(), CqlRequestSerializationError> {
+        self.serialize_versioned(buf, ProtocolVersion::V4)
+    }
+
+    fn serialize_versioned(
+        &self,
+        buf: &mut Vec<u8>,
+        version: ProtocolVersion,
+    ) -> Result<(), CqlRequestSerializationError> {
         types::write_long_string(&self.contents, buf)
             .map_err(QuerySerializationError::StatementStringSerialization)?;
         self.parameters
-            .serialize(buf)
+            .serialize_versioned(buf, version)
             .map_err(QuerySerializationError::QueryParametersSerialization)?;
         Ok(())
     }
@@ -70,6 +85,11 @@ pub struct QueryParameters<'a> {
     pub paging_state: PagingState,
     pub skip_metadata: bool,
     pub values: Cow<'a, SerializedValues>,
+    /// The keyspace the statement is executed in. Requires protocol v5.
+    pub keyspace: Option<Cow<'a, str>>,
+    /// The current time, in seconds since the Unix epoch, to be used by the database
+    /// for the statement. Requires protocol v5.
+    pub now_in_seconds: Option<i32>,
 }
 
 impl Default for QueryParameters<'_> {
@@ -82,6 +102,8 @@ impl Default for QueryParameters<'_> {
             paging_state: PagingState::start(),
             skip_metadata: false,
             values: Cow::Borrowed(SerializedValues::EMPTY),
+            keyspace: None,
+            now_in_seconds: None,
         }
     }
 }
@@ -91,6 +113,32 @@ impl QueryParameters<'_> {
         &self,
         buf: &mut impl BufMut,
     ) -> Result<(), QueryParametersSerializationError> {
+        self.serialize_versioned(buf, ProtocolVersion::V4)
+    }
+
+    /// Serializes the parameters for the given protocol version.
+    pub fn serialize_versioned(
+        &self,
+        buf: &mut impl BufMut,
+        version: ProtocolVersion,
+    ) -> Result<(), QueryParametersSerializationError> {
+        if version < ProtocolVersion::V5 {
+            let unsupported = |parameter| {
+                Err(
+                    QueryParametersSerializationError::UnsupportedByProtocolVersion {
+                        parameter,
+                        version,
+                    },
+                )
+            };
+            if self.keyspace.is_some() {
+                return unsupported("keyspace");
+            }
+            if self.now_in_seconds.is_some() {
+                return unsupported("now_in_seconds");
+            }
+        }
+
         types::write_consistency(self.consistency, buf);
 
         let paging_state_bytes = self.paging_state.as_bytes_slice();
@@ -120,7 +168,18 @@ impl QueryParameters<'_> {
             flags |= FLAG_WITH_DEFAULT_TIMESTAMP;
         }
 
-        buf.put_u8(flags);
+        if version < ProtocolVersion::V5 {
+            buf.put_u8(flags);
+        } else {
+            let mut flags = flags as u32;
+            if self.keyspace.is_some() {
+                flags |= FLAG_WITH_KEYSPACE;
+            }
+            if self.now_in_seconds.is_some() {
+                flags |= FLAG_WITH_NOW_IN_SECONDS;
+            }
+            buf.put_u32(flags);
+        }
 
         if !self.values.is_empty() {
             self.values.write_to_request(buf);
@@ -142,6 +201,15 @@ impl QueryParameters<'_> {
             types::write_long(timestamp, buf);
         }
 
+        if let Some(keyspace) = &self.keyspace {
+            types::write_string(keyspace, buf)
+                .map_err(QueryParametersSerializationError::KeyspaceSerialization)?;
+        }
+
+        if let Some(now_in_seconds) = self.now_in_seconds {
+            types::write_int(now_in_seconds, buf);
+        }
+
         Ok(())
     }
 }
@@ -207,6 +275,8 @@ impl QueryParameters<'_> {
             paging_state,
             skip_metadata,
             values,
+            keyspace: None,
+            now_in_seconds: None,
         })
     }
 }
@@ -246,6 +316,15 @@ impl PagingStateResponse {
         }
     }
 
+    /// Returns the token of the paging state to resume the query from,
+    /// or `None` if there are no more pages. See [PagingState::to_token].
+    pub fn to_token(&self) -> Option<String> {
+        match self {
+            Self::HasMorePages { state } => Some(state.to_token()),
+            Self::NoMorePages => None,
+        }
+    }
+
     /// Swaps the paging state response with PagingStateResponse::NoMorePages.
     ///
     /// Only for use in driver's inner code, as an optimisation.
@@ -288,12 +367,75 @@ impl PagingState {
     }
 }
 
+// Version of the format of paging state tokens, stored as their first byte.
+const PAGING_STATE_TOKEN_VERSION: u8 = 1;
+
+impl PagingState {
+    /// Encodes the paging state as an opaque, URL-safe token.
+    ///
+    /// The token can be handed over to another process, e.g. to the client
+    /// of a REST API as a pagination cursor, and turned back into a paging state
+    /// with [Self::from_token] to fetch the next page of the same query.
+    /// Its format is versioned, so tokens remain valid after upgrading the driver.
+    pub fn to_token(&self) -> String {
+        let raw = self.0.as_deref().unwrap_or_default();
+        let mut bytes = Vec::with_capacity(1 + raw.len());
+        bytes.push(PAGING_STATE_TOKEN_VERSION);
+        bytes.extend_from_slice(raw);
+        URL_SAFE_NO_PAD.encode(bytes)
+    }
+
+    /// Decodes a paging state from a token created with [Self::to_token].
+    ///
+    /// The token is only checked to be well-formed. A token of a different query
+    /// is rejected by the database, or results in undefined paging behaviour.
+    pub fn from_token(token: &str) -> Result<Self, PagingStateTokenError> {
+        let bytes = URL_SAFE_NO_PAD
+            .decode(token)
+            .map_err(|_| PagingStateTokenError::BadEncoding)?;
+        match bytes.split_first() {
+            Some((&PAGING_STATE_TOKEN_VERSION, [])) => Ok(Self::start()),
+            Some((&PAGING_STATE_TOKEN_VERSION, raw)) => Ok(Self::new_from_raw_bytes(raw)),
+            Some((&version, _)) => Err(PagingStateTokenError::UnsupportedVersion(version)),
+            None => Err(PagingStateTokenError::BadEncoding),
+        }
+    }
+}
+
 impl Default for PagingState {
     fn default() -> Self {
         Self::start()
     }
 }
 
+#[cfg(feature = "serde")]
+impl serde::Serialize for PagingState {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_token())
+    }
+}
+
+#[cfg(feature = "serde")]
+impl<'de> serde::Deserialize<'de> for PagingState {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let token = String::deserialize(deserializer)?;
+        Self::from_token(&token).map_err(serde::de::Error::custom)
+    }
+}
+
+/// An error returned when a [PagingState] can't be decoded from a token.
+#[non_exhaustive]
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum PagingStateTokenError {
+    /// The token is not a valid paging state token.
+    #[error("Malformed paging state token")]
+    BadEncoding,
+
+    /// The token was created by a newer version of the driver, in an unknown format.
+    #[error("Unsupported paging state token version: {0}")]
+    UnsupportedVersion(u8),
+}
+
 /// An error type returned when serialization of QUERY request fails.
 #[non_exhaustive]
 #[derive(Error, Debug, Clone)]
@@ -314,4 +456,15 @@ pub enum QueryParametersSerializationError {
     /// Failed to serialize paging state.
     #[error("Malformed paging state: {0}")]
     BadPagingState(#[from] TryFromIntError),
+
+    /// Failed to serialize the keyspace name.
+    #[error("Failed to serialize keyspace name: {0}")]
+    KeyspaceSerialization(TryFromIntError),
+
+    /// A parameter can't be sent with the protocol version of the connection.
+    #[error("Parameter {parameter} is not supported by protocol {version}")]
+    UnsupportedByProtocolVersion {
+        parameter: &'static str,
+        version: ProtocolVersion,
+    },
 }
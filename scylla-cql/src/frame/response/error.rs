@@ -1,6 +1,7 @@
 use crate::frame::frame_errors::{CqlErrorParseError, LowLevelDeserializationError};
 use crate::frame::protocol_features::ProtocolFeatures;
 use crate::frame::types;
+use crate::frame::ProtocolVersion;
 use crate::Consistency;
 use byteorder::ReadBytesExt;
 use bytes::Bytes;
@@ -24,6 +25,31 @@ fn make_error_field_err(
     }
 }
 
+// Protocol v5 replaced the number of failures with a map from the addresses
+// of the failed replicas to failure codes. Only the number of its entries is kept.
+fn read_num_failures(
+    buf: &mut &[u8],
+    features: &ProtocolFeatures,
+    db_error: &'static str,
+) -> Result<i32, CqlErrorParseError> {
+    if features.protocol_version < ProtocolVersion::V5 {
+        return types::read_int(buf)
+            .map_err(|err| make_error_field_err(db_error, "NUM_FAILURES", err));
+    }
+
+    let err = |err| make_error_field_err(db_error, "REASON_MAP", err);
+    let num_failures = types::read_int(buf).map_err(err)?;
+    for _ in 0..num_failures.max(0) {
+        let addr_len = buf.read_u8().map_err(err)? as usize;
+        if buf.len() < addr_len + 2 {
+            return Err(err(std::io::ErrorKind::UnexpectedEof.into()));
+        }
+        // The address of the replica and the failure code.
+        *buf = &buf[addr_len + 2..];
+    }
+    Ok(num_failures)
+}
+
 impl Error {
     pub fn deserialize(
         features: &ProtocolFeatures,
@@ -81,8 +107,7 @@ impl Error {
                     .map_err(|err| make_error_field_err("READ_FAILURE", "RECEIVED", err))?,
                 required: types::read_int(buf)
                     .map_err(|err| make_error_field_err("READ_FAILURE", "REQUIRED", err))?,
-                numfailures: types::read_int(buf)
-                    .map_err(|err| make_error_field_err("READ_FAILURE", "NUM_FAILURES", err))?,
+                numfailures: read_num_failures(buf, features, "READ_FAILURE")?,
                 data_present: buf
                     .read_u8()
                     .map_err(|err| make_error_field_err("READ_FAILURE", "DATA_PRESENT", err))?
@@ -105,8 +130,7 @@ impl Error {
                     .map_err(|err| make_error_field_err("WRITE_FAILURE", "RECEIVED", err))?,
                 required: types::read_int(buf)
                     .map_err(|err| make_error_field_err("WRITE_FAILURE", "REQUIRED", err))?,
-                numfailures: types::read_int(buf)
-                    .map_err(|err| make_error_field_err("WRITE_FAILURE", "NUM_FAILURES", err))?,
+                numfailures: read_num_failures(buf, features, "WRITE_FAILURE")?,
                 write_type: WriteType::from(
                     types::read_string(buf)
                         .map_err(|err| make_error_field_err("WRITE_FAILURE", "WRITE_TYPE", err))?,
@@ -472,6 +496,7 @@ impl WriteType {
 mod tests {
     use super::{DbError, Error, OperationType, WriteType};
     use crate::frame::protocol_features::ProtocolFeatures;
+    use crate::frame::ProtocolVersion;
     use crate::Consistency;
     use bytes::Bytes;
     use std::convert::TryInto;
@@ -616,6 +641,41 @@ mod tests {
         assert_eq!(error.reason, "message 2");
     }
 
+    #[test]
+    fn deserialize_read_failure_v5() {
+        let features = ProtocolFeatures {
+            protocol_version: ProtocolVersion::V5,
+            ..Default::default()
+        };
+
+        let mut bytes = make_error_request_bytes(0x1300, "message 2");
+        bytes.extend(0x0003_i16.to_be_bytes());
+        bytes.extend(4_i32.to_be_bytes());
+        bytes.extend(5_i32.to_be_bytes());
+        // Reason map: an IPv4 and an IPv6 address with failure codes.
+        bytes.extend(2_i32.to_be_bytes());
+        bytes.push(4);
+        bytes.extend([127, 0, 0, 1]);
+        bytes.extend(0x0001_u16.to_be_bytes());
+        bytes.push(16);
+        bytes.extend([0; 16]);
+        bytes.extend(0x0002_u16.to_be_bytes());
+        bytes.push(0);
+
+        let error: Error = Error::deserialize(&features, &mut bytes.as_slice()).unwrap();
+
+        assert_eq!(
+            error.error,
+            DbError::ReadFailure {
+                consistency: Consistency::Three,
+                received: 4,
+                required: 5,
+                numfailures: 2,
+                data_present: false,
+            }
+        );
+    }
+
     #[test]
     fn deserialize_function_failure() {
         let features = ProtocolFeatures::default();
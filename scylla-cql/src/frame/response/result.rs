@@ -13,6 +13,7 @@ use crate::frame::types;
 use crate::frame::value::{
     Counter, CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlTimeuuid, CqlVarint,
 };
+use crate::frame::ProtocolVersion;
 use crate::types::deserialize::result::{RawRowIterator, TypedRowIterator};
 use crate::types::deserialize::row::DeserializeRow;
 use crate::types::deserialize::value::{
@@ -21,6 +22,7 @@ use crate::types::deserialize::value::{
 use crate::types::deserialize::{DeserializationError, FrameSlice, TypeCheckError};
 use bytes::{Buf, Bytes};
 use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Debug;
 use std::sync::Arc;
 use std::{net::IpAddr, result::Result as StdResult, str};
@@ -34,6 +36,8 @@ pub struct SetKeyspace {
 #[derive(Debug)]
 pub struct Prepared {
     pub id: Bytes,
+    /// The id of the result metadata, sent since protocol v5.
+    pub result_metadata_id: Option<Bytes>,
     pub prepared_metadata: PreparedMetadata,
     pub result_metadata: ResultMetadata<'static>,
 }
@@ -616,6 +620,59 @@ impl Row {
     pub fn into_typed<RowT: FromRow>(self) -> StdResult<RowT, FromRowError> {
         RowT::from_row(self)
     }
+
+    /// Returns the value of the column with the given name, looked up in `index`,
+    /// which must have been built from the metadata of the result the row comes from.
+    ///
+    /// Returns `None` if there is no such column, and `Some(None)` if its value is `NULL`.
+    #[inline]
+    pub fn get_by_name(&self, index: &ColumnIndex, name: &str) -> Option<&Option<CqlValue>> {
+        index
+            .position(name)
+            .and_then(|position| self.columns.get(position))
+    }
+}
+
+/// Positions of columns by their names, for accessing columns by name in O(1).
+///
+/// Looking a column up by name in the column specs requires a linear scan,
+/// so when columns of many rows are accessed by name, the index should be built
+/// once per result (or per prepared statement, if its result metadata does not change)
+/// and reused for all of them.
+///
+/// If several columns share a name, the name refers to the first of them.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ColumnIndex {
+    positions: HashMap<String, usize>,
+}
+
+impl ColumnIndex {
+    /// Builds the index of the given columns.
+    pub fn new(specs: &[ColumnSpec<'_>]) -> Self {
+        let mut positions = HashMap::with_capacity(specs.len());
+        for (position, spec) in specs.iter().enumerate() {
+            positions.entry(spec.name().to_owned()).or_insert(position);
+        }
+        Self { positions }
+    }
+
+    /// Returns the position of the column with the given name.
+    #[inline]
+    pub fn position(&self, name: &str) -> Option<usize> {
+        self.positions.get(name).copied()
+    }
+
+    /// Returns the number of distinct column names.
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.positions.len()
+    }
+
+    /// Returns `true` if there are no columns.
+    #[inline]
+    pub fn is_empty(&self) -> bool {
+        self.positions.is_empty()
+    }
 }
 
 /// RESULT:Rows response, in partially serialized form.
@@ -628,6 +685,9 @@ pub struct RawMetadataAndRawRows {
     col_count: usize,
     global_tables_spec: bool,
     no_metadata: bool,
+    /// Whether the remaining part starts with the id of the new result metadata,
+    /// sent in protocol v5 when the metadata id in the EXECUTE request was outdated.
+    metadata_changed: bool,
 
     /// The remaining part of the RESULT frame.
     raw_metadata_and_rows: Bytes,
@@ -651,6 +711,7 @@ impl RawMetadataAndRawRows {
             col_count: 0,
             global_tables_spec: false,
             no_metadata: false,
+            metadata_changed: false,
             raw_metadata_and_rows,
             cached_metadata: None,
         }
@@ -661,6 +722,53 @@ impl RawMetadataAndRawRows {
     pub fn metadata_and_rows_bytes_size(&self) -> usize {
         self.raw_metadata_and_rows.len()
     }
+
+    /// Returns the new result metadata along with its id, if the server reported
+    /// that the metadata id sent in the EXECUTE request is outdated.
+    ///
+    /// This happens only in protocol v5, e.g. after a column was added to the table
+    /// of a `SELECT *` statement. The returned metadata should replace the one
+    /// cached in the prepared statement.
+    pub fn changed_metadata(
+        &self,
+    ) -> Option<StdResult<(Bytes, ResultMetadata<'static>), ResultMetadataParseError>> {
+        if !self.metadata_changed {
+            return None;
+        }
+
+        let parse = || {
+            let (new_metadata_id, metadata_and_rows) = self.split_new_metadata_id()?;
+            let buf = &mut &*metadata_and_rows;
+            let global_table_spec = self
+                .global_tables_spec
+                .then(|| deser_table_spec(buf))
+                .transpose()?;
+            let col_specs = deser_col_specs_owned(buf, global_table_spec, self.col_count)?;
+
+            Ok((
+                new_metadata_id,
+                ResultMetadata {
+                    col_count: self.col_count,
+                    col_specs,
+                },
+            ))
+        };
+        Some(parse())
+    }
+
+    /// Splits the id of the new result metadata, if present, off the remaining part of the frame.
+    fn split_new_metadata_id(&self) -> StdResult<(Bytes, Bytes), ResultMetadataParseError> {
+        let mut raw = self.raw_metadata_and_rows.clone();
+        if !self.metadata_changed {
+            return Ok((Bytes::new(), raw));
+        }
+        let buf = &mut &*raw;
+        let id_len = types::read_short_bytes(buf)
+            .map_err(ResultMetadataParseError::MetadataIdParseError)?
+            .len();
+        let metadata_and_rows = raw.split_off(2 + id_len);
+        Ok((raw.split_off(2), metadata_and_rows))
+    }
 }
 
 mod self_borrowed_metadata {
@@ -836,13 +944,17 @@ impl DeserializedMetadataAndRawRows {
     where
         'frame: 'metadata,
     {
-        let frame_slice = FrameSlice::new(&self.raw_rows);
-        let raw = RawRowIterator::new(
+        TypedRowIterator::new(self.raw_rows_iter())
+    }
+
+    /// Creates an iterator over the raw rows in the result.
+    #[inline]
+    pub fn raw_rows_iter(&self) -> RawRowIterator<'_, '_> {
+        RawRowIterator::new(
             self.rows_count,
             self.metadata.inner().col_specs(),
-            frame_slice,
-        );
-        TypedRowIterator::new(raw)
+            FrameSlice::new(&self.raw_rows),
+        )
     }
 }
 
@@ -1052,6 +1164,7 @@ fn deser_result_metadata(
     let global_tables_spec = flags & 0x0001 != 0;
     let has_more_pages = flags & 0x0002 != 0;
     let no_metadata = flags & 0x0004 != 0;
+    let metadata_changed = flags & 0x0008 != 0;
 
     let col_count =
         types::read_int_length(buf).map_err(ResultMetadataParseError::ColumnCountParseError)?;
@@ -1062,6 +1175,11 @@ fn deser_result_metadata(
 
     let paging_state = PagingStateResponse::new_from_raw_bytes(raw_paging_state);
 
+    // Protocol v5 sends the id of the new metadata, which follows it.
+    if metadata_changed {
+        types::read_short_bytes(buf).map_err(ResultMetadataParseError::MetadataIdParseError)?;
+    }
+
     let col_specs = if no_metadata {
         vec![]
     } else {
@@ -1091,6 +1209,7 @@ impl RawMetadataAndRawRows {
         let global_tables_spec = flags & 0x0001 != 0;
         let has_more_pages = flags & 0x0002 != 0;
         let no_metadata = flags & 0x0004 != 0;
+        let metadata_changed = flags & 0x0008 != 0;
 
         let col_count = types::read_int_length(frame.as_slice_mut())
             .map_err(RawRowsAndPagingStateResponseParseError::ColumnCountParseError)?;
@@ -1104,10 +1223,14 @@ impl RawMetadataAndRawRows {
 
         let paging_state = PagingStateResponse::new_from_raw_bytes(raw_paging_state);
 
+        // Protocol v5 sends the id of the new metadata when the client's one is outdated.
+        // The id stays in the raw part, followed by the new metadata, which is used
+        // instead of the cached one.
         let raw_rows = Self {
             col_count,
             global_tables_spec,
             no_metadata,
+            metadata_changed,
             raw_metadata_and_rows: frame.to_bytes(),
             cached_metadata,
         };
@@ -1156,13 +1279,23 @@ impl RawMetadataAndRawRows {
     pub fn deserialize_metadata(
         self,
     ) -> StdResult<DeserializedMetadataAndRawRows, ResultMetadataAndRowsCountParseError> {
+        let (_, raw_metadata_and_rows) = self.split_new_metadata_id()?;
         let (metadata_deserialized, row_count_and_raw_rows) = match self.cached_metadata {
+            Some(cached) if self.no_metadata && cached.col_count() != self.col_count => {
+                // Deserializing the rows with outdated metadata would yield garbage.
+                return Err(
+                    ResultMetadataAndRowsCountParseError::OutdatedCachedMetadata {
+                        cached: cached.col_count(),
+                        received: self.col_count,
+                    },
+                );
+            }
             Some(cached) if self.no_metadata => {
                 // Server sent no metadata, but we have metadata cached. This means that we asked the server
                 // not to send metadata in the response as an optimization. We use cached metadata instead.
                 (
                     ResultMetadataHolder::SharedCached(cached),
-                    self.raw_metadata_and_rows,
+                    raw_metadata_and_rows,
                 )
             }
             None if self.no_metadata => {
@@ -1170,10 +1303,7 @@ impl RawMetadataAndRawRows {
                 // we wouldn't have asked the server for skipping metadata. Therefore, this is most probably
                 // not a SELECT, because in such case the server would send empty metadata both in Prepared
                 // and in Result responses.
-                (
-                    ResultMetadataHolder::mock_empty(),
-                    self.raw_metadata_and_rows,
-                )
+                (ResultMetadataHolder::mock_empty(), raw_metadata_and_rows)
             }
             Some(_) | None => {
                 // Two possibilities:
@@ -1187,7 +1317,7 @@ impl RawMetadataAndRawRows {
 
                 let (metadata_container, raw_rows_with_count) =
                     self_borrowed_metadata::SelfBorrowedMetadataContainer::make_deserialized_metadata(
-                        self.raw_metadata_and_rows,
+                        raw_metadata_and_rows,
                         Self::metadata_deserializer(self.col_count, self.global_tables_spec),
                     )?;
                 (
@@ -1423,12 +1553,22 @@ fn deser_set_keyspace(buf: &mut &[u8]) -> StdResult<SetKeyspace, SetKeyspacePars
     Ok(SetKeyspace { keyspace_name })
 }
 
-fn deser_prepared(buf: &mut &[u8]) -> StdResult<Prepared, PreparedParseError> {
+fn deser_prepared(
+    buf: &mut &[u8],
+    protocol_version: ProtocolVersion,
+) -> StdResult<Prepared, PreparedParseError> {
     let id_len = types::read_short(buf)
         .map_err(|err| PreparedParseError::IdLengthParseError(err.into()))?
         as usize;
     let id: Bytes = buf[0..id_len].to_owned().into();
     buf.advance(id_len);
+    let result_metadata_id = (protocol_version >= ProtocolVersion::V5)
+        .then(|| {
+            types::read_short_bytes(buf)
+                .map(Bytes::copy_from_slice)
+                .map_err(PreparedParseError::ResultMetadataIdParseError)
+        })
+        .transpose()?;
     let prepared_metadata =
         deser_prepared_metadata(buf).map_err(PreparedParseError::PreparedMetadataParseError)?;
     let (result_metadata, paging_state_response) =
@@ -1444,6 +1584,7 @@ fn deser_prepared(buf: &mut &[u8]) -> StdResult<Prepared, PreparedParseError> {
 
     Ok(Prepared {
         id,
+        result_metadata_id,
         prepared_metadata,
         result_metadata,
     })
@@ -1458,6 +1599,7 @@ fn deser_schema_change(buf: &mut &[u8]) -> StdResult<SchemaChange, SchemaChangeE
 pub fn deserialize(
     buf_bytes: Bytes,
     cached_metadata: Option<&Arc<ResultMetadata<'static>>>,
+    protocol_version: ProtocolVersion,
 ) -> StdResult<Result, CqlResultParseError> {
     let buf = &mut &*buf_bytes;
     use self::Result::*;
@@ -1468,7 +1610,7 @@ pub fn deserialize(
             0x0001 => Void,
             0x0002 => Rows(deser_rows(buf_bytes.slice_ref(buf), cached_metadata)?),
             0x0003 => SetKeyspace(deser_set_keyspace(buf)?),
-            0x0004 => Prepared(deser_prepared(buf)?),
+            0x0004 => Prepared(deser_prepared(buf, protocol_version)?),
             0x0005 => SchemaChange(deser_schema_change(buf)?),
             id => return Err(CqlResultParseError::UnknownResultId(id)),
         },
@@ -2518,4 +2660,107 @@ mod tests {
             }
         }
     }
+
+    #[test]
+    fn column_index() {
+        use super::{ColumnIndex, ColumnSpec, Row, TableSpec};
+
+        let table = TableSpec::borrowed("ks", "tbl");
+        let specs = [
+            ColumnSpec::borrowed("a", ColumnType::Int, table.clone()),
+            ColumnSpec::borrowed("b", ColumnType::Text, table.clone()),
+            ColumnSpec::borrowed("a", ColumnType::Text, table),
+        ];
+        let index = ColumnIndex::new(&specs);
+        assert_eq!(index.len(), 2);
+        assert_eq!(index.position("a"), Some(0));
+        assert_eq!(index.position("b"), Some(1));
+        assert_eq!(index.position("c"), None);
+
+        let row = Row {
+            columns: vec![
+                Some(CqlValue::Int(7)),
+                None,
+                Some(CqlValue::Text("x".into())),
+            ],
+        };
+        assert_eq!(row.get_by_name(&index, "a"), Some(&Some(CqlValue::Int(7))));
+        assert_eq!(row.get_by_name(&index, "b"), Some(&None));
+        assert_eq!(row.get_by_name(&index, "c"), None);
+    }
+
+    #[test]
+    fn outdated_cached_metadata() {
+        use super::{ColumnSpec, RawMetadataAndRawRows, ResultMetadata, TableSpec};
+        use crate::frame::frame_errors::ResultMetadataAndRowsCountParseError;
+        use crate::types::deserialize::FrameSlice;
+        use std::sync::Arc;
+
+        let table = TableSpec::borrowed("ks", "tbl");
+        let cached = Arc::new(ResultMetadata::new_for_test(
+            1,
+            vec![ColumnSpec::borrowed("a", ColumnType::Int, table.clone())],
+        ));
+        let deserialize = |frame: Vec<u8>| {
+            let (rows, _) = RawMetadataAndRawRows::deserialize(
+                &mut FrameSlice::new(&bytes::Bytes::from(frame)),
+                Some(cached.clone()),
+            )
+            .unwrap();
+            rows
+        };
+
+        // No metadata flag, the given number of columns, no paging state and no rows.
+        let no_metadata = |col_count: i32| {
+            deserialize(
+                [0x0004_i32, col_count, 0]
+                    .iter()
+                    .flat_map(|int| int.to_be_bytes())
+                    .collect(),
+            )
+        };
+
+        let up_to_date = no_metadata(1);
+        assert!(up_to_date.changed_metadata().is_none());
+        assert_eq!(
+            up_to_date
+                .deserialize_metadata()
+                .unwrap()
+                .metadata()
+                .col_count(),
+            1
+        );
+
+        let outdated = no_metadata(2);
+        assert!(matches!(
+            outdated.deserialize_metadata(),
+            Err(
+                ResultMetadataAndRowsCountParseError::OutdatedCachedMetadata {
+                    cached: 1,
+                    received: 2
+                }
+            )
+        ));
+
+        // Metadata changed and global tables spec flags, one column, the new metadata id,
+        // the new metadata and no rows.
+        let mut frame = Vec::new();
+        frame.extend_from_slice(&0x0009_i32.to_be_bytes());
+        frame.extend_from_slice(&1_i32.to_be_bytes());
+        frame.extend_from_slice(&[0, 2, 0xab, 0xcd]);
+        frame.extend_from_slice(&[0, 2, b'k', b's', 0, 3, b't', b'b', b'l']);
+        frame.extend_from_slice(&[0, 1, b'b', 0, 0x09]);
+        frame.extend_from_slice(&0_i32.to_be_bytes());
+        let changed = deserialize(frame);
+
+        let (new_id, new_metadata) = changed.changed_metadata().unwrap().unwrap();
+        assert_eq!(&new_id[..], &[0xab, 0xcd]);
+        assert_eq!(
+            new_metadata.col_specs(),
+            &[ColumnSpec::borrowed("b", ColumnType::Int, table)]
+        );
+        // The metadata sent by the server is used instead of the cached one.
+        let deserialized = changed.deserialize_metadata().unwrap();
+        assert_eq!(deserialized.metadata().col_specs()[0].name(), "b");
+    }
 }
@@ -0,0 +1,379 @@
+//! Framing of protocol v5 connections.
+//!
+//! Starting with protocol v5, frames are no longer written directly to the
+//! connection once the STARTUP handshake is over. Instead, they are wrapped
+//! in segments, whose header and payload are protected with checksums.
+//! A segment either contains one or more whole frames (it is self-contained),
+//! or a part of a frame which is too large to fit in a single segment.
+//! If compression was negotiated, the segments are compressed with LZ4
+//! instead of the frames.
+
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use tokio::io::{AsyncRead, AsyncReadExt};
+
+use super::frame_errors::FrameHeaderParseError;
+use super::response::ResponseOpcode;
+use super::{parse_response_header, FrameParams, ResponseFrameBody, HEADER_SIZE};
+
+/// The maximum size of an uncompressed segment payload.
+pub const MAX_SEGMENT_PAYLOAD_SIZE: usize = (1 << 17) - 1;
+
+const UNCOMPRESSED_HEADER_SIZE: usize = 3;
+const COMPRESSED_HEADER_SIZE: usize = 5;
+const HEADER_CRC_SIZE: usize = 3;
+const PAYLOAD_CRC_SIZE: usize = 4;
+
+const CRC24_INIT: u32 = 0x875060;
+const CRC24_POLY: u32 = 0x1974F0B;
+
+// Fed into the payload checksum before the payload itself.
+const CRC32_INITIAL_BYTES: [u8; 4] = [0xFA, 0x2D, 0x55, 0xCA];
+
+const CRC32_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 != 0 {
+                (crc >> 1) ^ 0xEDB88320
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+fn crc24(bytes: &[u8]) -> u32 {
+    let mut crc = CRC24_INIT;
+    for &byte in bytes {
+        crc ^= (byte as u32) << 16;
+        for _ in 0..8 {
+            crc <<= 1;
+            if crc & 0x1000000 != 0 {
+                crc ^= CRC24_POLY;
+            }
+        }
+    }
+    crc
+}
+
+fn crc32(payload: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for &byte in CRC32_INITIAL_BYTES.iter().chain(payload) {
+        crc = CRC32_TABLE[((crc ^ byte as u32) & 0xFF) as usize] ^ (crc >> 8);
+    }
+    !crc
+}
+
+fn header_size(compressed: bool) -> usize {
+    if compressed {
+        COMPRESSED_HEADER_SIZE
+    } else {
+        UNCOMPRESSED_HEADER_SIZE
+    }
+}
+
+/// Wraps a serialized frame in segments and appends them to `out`.
+///
+/// A frame which fits in a single segment is sent in a self-contained segment,
+/// larger frames are split among several segments.
+pub fn write_segments(frame: &[u8], compressed: bool, out: &mut Vec<u8>) {
+    let self_contained = frame.len() <= MAX_SEGMENT_PAYLOAD_SIZE;
+    for chunk in frame.chunks(MAX_SEGMENT_PAYLOAD_SIZE) {
+        write_segment(chunk, compressed, self_contained, out);
+    }
+}
+
+fn write_segment(payload: &[u8], compressed: bool, self_contained: bool, out: &mut Vec<u8>) {
+    let self_contained_flag = self_contained as u64;
+    let (header, payload) = if compressed {
+        let compressed_payload = lz4_flex::block::compress(payload);
+        // A payload which doesn't shrink is sent as is, marked with zero uncompressed length.
+        let (payload, uncompressed_len) = if compressed_payload.len() < payload.len() {
+            (compressed_payload, payload.len() as u64)
+        } else {
+            (payload.to_vec(), 0)
+        };
+        let header = payload.len() as u64 | uncompressed_len << 17 | self_contained_flag << 34;
+        (header, payload)
+    } else {
+        let header = payload.len() as u64 | self_contained_flag << 17;
+        (header, payload.to_vec())
+    };
+
+    let header_bytes = &header.to_le_bytes()[..header_size(compressed)];
+    out.extend_from_slice(header_bytes);
+    out.extend_from_slice(&crc24(header_bytes).to_le_bytes()[..HEADER_CRC_SIZE]);
+    out.extend_from_slice(&payload);
+    out.put_u32_le(crc32(&payload));
+}
+
+/// Reads a single segment and returns its uncompressed payload.
+async fn read_segment(
+    reader: &mut (impl AsyncRead + Unpin),
+    compressed: bool,
+) -> Result<Vec<u8>, FrameHeaderParseError> {
+    let header_size = header_size(compressed);
+    let mut raw_header = [0u8; COMPRESSED_HEADER_SIZE + HEADER_CRC_SIZE];
+    let raw_header = &mut raw_header[..header_size + HEADER_CRC_SIZE];
+    reader
+        .read_exact(raw_header)
+        .await
+        .map_err(FrameHeaderParseError::SegmentIoError)?;
+
+    let (header_bytes, mut crc_bytes) = raw_header.split_at(header_size);
+    if crc24(header_bytes) != crc_bytes.get_uint_le(HEADER_CRC_SIZE) as u32 {
+        return Err(FrameHeaderParseError::SegmentHeaderChecksumMismatch);
+    }
+    let header = (&*header_bytes).get_uint_le(header_size);
+    let payload_len = (header & MAX_SEGMENT_PAYLOAD_SIZE as u64) as usize;
+    let uncompressed_len = if compressed {
+        ((header >> 17) & MAX_SEGMENT_PAYLOAD_SIZE as u64) as usize
+    } else {
+        0
+    };
+
+    let mut payload = vec![0u8; payload_len + PAYLOAD_CRC_SIZE];
+    reader
+        .read_exact(&mut payload)
+        .await
+        .map_err(FrameHeaderParseError::SegmentIoError)?;
+    let mut crc_bytes = &payload[payload_len..];
+    if crc32(&payload[..payload_len]) != crc_bytes.get_u32_le() {
+        return Err(FrameHeaderParseError::SegmentPayloadChecksumMismatch);
+    }
+    payload.truncate(payload_len);
+
+    if uncompressed_len == 0 {
+        Ok(payload)
+    } else {
+        lz4_flex::block::decompress(&payload, uncompressed_len)
+            .map_err(FrameHeaderParseError::SegmentDecompressError)
+    }
+}
+
+/// Reads response frames from a connection which has switched to segments.
+///
+/// Frames can be spread over several segments and a segment can contain several
+/// frames, so the payloads which don't form a whole frame yet are buffered.
+#[derive(Debug)]
+pub struct SegmentedFrameReader {
+    compressed: bool,
+    buffer: BytesMut,
+}
+
+impl SegmentedFrameReader {
+    /// Creates a reader of segments, which are compressed with LZ4
+    /// if `compressed` is true.
+    pub fn new(compressed: bool) -> Self {
+        Self {
+            compressed,
+            buffer: BytesMut::new(),
+        }
+    }
+
+    /// Reads the next response frame, like [`read_response_frame`](super::read_response_frame)
+    /// does for connections which don't use segments.
+    pub async fn read_response_frame(
+        &mut self,
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> Result<(FrameParams, ResponseOpcode, Bytes), FrameHeaderParseError> {
+        let (params, opcode, body) = self
+            .read_response_frame_with_limit(reader, |_| None)
+            .await?;
+        match body {
+            ResponseFrameBody::Received(body) => Ok((params, opcode, body)),
+            ResponseFrameBody::Discarded { .. } => unreachable!("no limit was set"),
+        }
+    }
+
+    /// Reads the next response frame, like [`read_response_frame_with_limit`](super::read_response_frame_with_limit)
+    /// does for connections which don't use segments.
+    ///
+    /// The part of a discarded frame which has already been buffered is dropped,
+    /// and the segments holding the rest of it are dropped as soon as they are read.
+    pub async fn read_response_frame_with_limit(
+        &mut self,
+        reader: &mut (impl AsyncRead + Unpin),
+        max_body_len: impl FnOnce(&FrameParams) -> Option<usize>,
+    ) -> Result<(FrameParams, ResponseOpcode, ResponseFrameBody), FrameHeaderParseError> {
+        while self.buffer.len() < HEADER_SIZE {
+            self.read_segment(reader).await?;
+        }
+        let (params, opcode, length) = parse_response_header(&self.buffer[..HEADER_SIZE])?;
+        let frame_len = HEADER_SIZE + length;
+
+        if let Some(limit) = max_body_len(&params).filter(|limit| length > *limit) {
+            let mut remaining = frame_len;
+            loop {
+                let discarded = remaining.min(self.buffer.len());
+                self.buffer.advance(discarded);
+                remaining -= discarded;
+                if remaining == 0 {
+                    return Ok((
+                        params,
+                        opcode,
+                        ResponseFrameBody::Discarded { len: length, limit },
+                    ));
+                }
+                self.read_segment(reader).await?;
+            }
+        }
+
+        while self.buffer.len() < frame_len {
+            self.read_segment(reader).await?;
+        }
+        let mut frame = self.buffer.split_to(frame_len);
+        frame.advance(HEADER_SIZE);
+        Ok((params, opcode, ResponseFrameBody::Received(frame.freeze())))
+    }
+
+    async fn read_segment(
+        &mut self,
+        reader: &mut (impl AsyncRead + Unpin),
+    ) -> Result<(), FrameHeaderParseError> {
+        let payload = read_segment(reader, self.compressed).await?;
+        self.buffer.extend_from_slice(&payload);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::frame::response::ResponseOpcode;
+
+    fn response_frame(stream: i16, body: &[u8]) -> Vec<u8> {
+        let mut frame = vec![0x85, 0x00];
+        frame.put_i16(stream);
+        frame.put_u8(ResponseOpcode::Result as u8);
+        frame.put_u32(body.len() as u32);
+        frame.extend_from_slice(body);
+        frame
+    }
+
+    #[test]
+    fn test_checksums() {
+        assert_eq!(crc24(&[0x05, 0x00, 0x02]), 0x9A9919);
+        assert_eq!(crc32(b"Hello, World!"), 0x75530F38);
+    }
+
+    #[test]
+    fn test_uncompressed_segment_layout() {
+        let mut out = Vec::new();
+        write_segments(b"Hello", false, &mut out);
+        // Payload length 5 with the self-contained bit, then CRC24 of the header.
+        assert_eq!(&out[..3], &[0x05, 0x00, 0x02]);
+        assert_eq!(&out[3..6], &[0x19, 0x99, 0x9A]);
+        assert_eq!(&out[6..11], b"Hello");
+        assert_eq!(out.len(), 6 + 5 + 4);
+    }
+
+    #[tokio::test]
+    async fn test_segments_roundtrip() {
+        for compressed in [false, true] {
+            let small = response_frame(1, b"small");
+            let large_body = "Hello, World!".repeat(20000);
+            let large = response_frame(2, large_body.as_bytes());
+            let incompressible = response_frame(
+                3,
+                &(0..1000u32)
+                    .map(|i| (i.wrapping_mul(2654435761) >> 13) as u8)
+                    .collect::<Vec<_>>(),
+            );
+
+            let mut stream = Vec::new();
+            write_segments(&small, compressed, &mut stream);
+            write_segments(&large, compressed, &mut stream);
+            write_segments(&incompressible, compressed, &mut stream);
+
+            let mut reader = SegmentedFrameReader::new(compressed);
+            let mut input = &stream[..];
+            for (stream_id, frame) in [(1, &small), (2, &large), (3, &incompressible)] {
+                let (params, opcode, body) = reader.read_response_frame(&mut input).await.unwrap();
+                assert_eq!(params.stream, stream_id);
+                assert_eq!(params.version, 0x85);
+                assert_eq!(opcode, ResponseOpcode::Result);
+                assert_eq!(&body[..], &frame[HEADER_SIZE..]);
+            }
+            assert!(input.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_segments_with_limit() {
+        for compressed in [false, true] {
+            let small = response_frame(1, b"small");
+            let large_body = "Hello, World!".repeat(50000);
+            let large = response_frame(2, large_body.as_bytes());
+
+            let mut stream = Vec::new();
+            // The small frames share segments with the beginning and the end
+            // of the large one.
+            let mut frames = small.clone();
+            frames.extend_from_slice(&large);
+            frames.extend_from_slice(&small);
+            write_segments(&frames, compressed, &mut stream);
+
+            let mut reader = SegmentedFrameReader::new(compressed);
+            let mut input = &stream[..];
+            let limit = |_: &FrameParams| Some(1000);
+            for (stream_id, expected_body) in [
+                (1, Some(&b"small"[..])),
+                (2, None),
+                (1, Some(&b"small"[..])),
+            ] {
+                let (params, _, body) = reader
+                    .read_response_frame_with_limit(&mut input, limit)
+                    .await
+                    .unwrap();
+                assert_eq!(params.stream, stream_id);
+                match (body, expected_body) {
+                    (ResponseFrameBody::Received(body), Some(expected)) => {
+                        assert_eq!(&body[..], expected)
+                    }
+                    (ResponseFrameBody::Discarded { len, limit }, None) => {
+                        assert_eq!(len, large_body.len());
+                        assert_eq!(limit, 1000);
+                    }
+                    (body, _) => panic!("unexpected body: {:?}", body),
+                }
+            }
+            assert!(input.is_empty());
+        }
+    }
+
+    #[tokio::test]
+    async fn test_corrupted_segment() {
+        let mut stream = Vec::new();
+        write_segments(&response_frame(1, b"body"), false, &mut stream);
+
+        let mut corrupted_header = stream.clone();
+        corrupted_header[0] ^= 0x01;
+        let err = SegmentedFrameReader::new(false)
+            .read_response_frame(&mut &corrupted_header[..])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FrameHeaderParseError::SegmentHeaderChecksumMismatch
+        ));
+
+        let mut corrupted_payload = stream;
+        corrupted_payload[10] ^= 0x01;
+        let err = SegmentedFrameReader::new(false)
+            .read_response_frame(&mut &corrupted_payload[..])
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            FrameHeaderParseError::SegmentPayloadChecksumMismatch
+        ));
+    }
+}
@@ -116,6 +116,15 @@ impl TryFrom<Consistency> for SerialConsistency {
     }
 }
 
+impl From<SerialConsistency> for Consistency {
+    fn from(sc: SerialConsistency) -> Self {
+        match sc {
+            SerialConsistency::Serial => Consistency::Serial,
+            SerialConsistency::LocalSerial => Consistency::LocalSerial,
+        }
+    }
+}
+
 impl std::fmt::Display for Consistency {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{:?}", self)
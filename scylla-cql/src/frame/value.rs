@@ -25,13 +25,24 @@ pub struct Unset;
 pub struct Counter(pub i64);
 
 /// Enum providing a way to represent a value that might be unset
-#[derive(Clone, Copy, Default)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum MaybeUnset<V> {
     #[default]
     Unset,
     Set(V),
 }
 
+impl<V> MaybeUnset<V> {
+    /// Converts an `Option` into a value which is unset instead of null if `None`.
+    #[inline]
+    pub fn from_option(opt: Option<V>) -> Self {
+        match opt {
+            Some(v) => Self::Set(v),
+            None => Self::Unset,
+        }
+    }
+}
+
 /// Represents timeuuid (uuid V1) value
 ///
 /// This type has custom comparison logic which follows Scylla/Cassandra semantics.
@@ -457,7 +468,7 @@ impl From<CqlVarintBorrowed<'_>> for num_bigint_04::BigInt {
 /// Notice that [constructors](CqlDecimal#impl-CqlDecimal)
 /// don't perform any normalization on the provided data.
 /// For more details, see [`CqlVarint`] documentation.
-#[derive(Clone, PartialEq, Eq, Debug)]
+#[derive(Clone, PartialEq, Eq, Hash, Debug)]
 pub struct CqlDecimal {
     int_val: CqlVarint,
     scale: i32,
@@ -575,22 +586,185 @@ impl TryFrom<bigdecimal_04::BigDecimal> for CqlDecimal {
     }
 }
 
+/// Native CQL `text` / `ascii` representation backed by [`Bytes`](bytes::Bytes).
+///
+/// Unlike [`String`], deserializing a [`CqlStr`] does not copy the value out of
+/// the response frame, but keeps a reference to it. Large text values can be
+/// therefore retained cheaply, although note that the whole frame is kept alive
+/// as long as any of its values is.
+///
+/// The underlying bytes are always valid UTF-8.
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord, Default)]
+pub struct CqlStr(bytes::Bytes);
+
+impl CqlStr {
+    /// Creates a [`CqlStr`] from bytes, checking that they are valid UTF-8.
+    pub fn from_utf8(bytes: bytes::Bytes) -> Result<Self, std::str::Utf8Error> {
+        std::str::from_utf8(&bytes)?;
+        Ok(Self(bytes))
+    }
+
+    /// Creates a [`CqlStr`] from a static string, without copying it.
+    pub const fn from_static(s: &'static str) -> Self {
+        Self(bytes::Bytes::from_static(s.as_bytes()))
+    }
+
+    /// Returns the string.
+    pub fn as_str(&self) -> &str {
+        // SAFETY: the bytes are validated to be UTF-8 by all constructors.
+        unsafe { std::str::from_utf8_unchecked(&self.0) }
+    }
+
+    /// Returns the UTF-8 bytes of the string.
+    pub fn as_bytes(&self) -> &bytes::Bytes {
+        &self.0
+    }
+
+    /// Converts [`CqlStr`] into the underlying UTF-8 bytes.
+    pub fn into_bytes(self) -> bytes::Bytes {
+        self.0
+    }
+}
+
+impl std::ops::Deref for CqlStr {
+    type Target = str;
+
+    fn deref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl AsRef<str> for CqlStr {
+    fn as_ref(&self) -> &str {
+        self.as_str()
+    }
+}
+
+// Hashes like `str`, as required by the `Borrow<str>` implementation.
+impl std::hash::Hash for CqlStr {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        self.as_str().hash(state)
+    }
+}
+
+impl std::borrow::Borrow<str> for CqlStr {
+    fn borrow(&self) -> &str {
+        self.as_str()
+    }
+}
+
+impl PartialEq<str> for CqlStr {
+    fn eq(&self, other: &str) -> bool {
+        self.as_str() == other
+    }
+}
+
+impl PartialEq<&str> for CqlStr {
+    fn eq(&self, other: &&str) -> bool {
+        self.as_str() == *other
+    }
+}
+
+impl From<String> for CqlStr {
+    fn from(s: String) -> Self {
+        Self(bytes::Bytes::from(s))
+    }
+}
+
+impl From<&'static str> for CqlStr {
+    fn from(s: &'static str) -> Self {
+        Self::from_static(s)
+    }
+}
+
+impl From<CqlStr> for String {
+    fn from(s: CqlStr) -> Self {
+        s.as_str().to_owned()
+    }
+}
+
+impl std::fmt::Debug for CqlStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        std::fmt::Debug::fmt(self.as_str(), f)
+    }
+}
+
+impl std::fmt::Display for CqlStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// A cell value kept in its serialized form, as bytes encoded
+/// according to the type of the column.
+///
+/// Deserializing a [`RawCell`] accepts columns of any type and borrows the bytes
+/// from the response frame, and serializing it writes the bytes verbatim,
+/// without checking them against the column type. This allows values to be
+/// copied, e.g. between tables or clusters, without decoding and encoding them,
+/// as long as the types of the source and the target columns are the same.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub struct RawCell<'a>(Option<&'a [u8]>);
+
+impl<'a> RawCell<'a> {
+    /// Creates a [`RawCell`] holding the given serialized value.
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self(Some(bytes))
+    }
+
+    /// Creates a [`RawCell`] representing a null value.
+    pub fn null() -> Self {
+        Self(None)
+    }
+
+    /// Returns whether the value is null.
+    pub fn is_null(&self) -> bool {
+        self.0.is_none()
+    }
+
+    /// Returns the serialized value, or `None` if the value is null.
+    pub fn as_bytes(&self) -> Option<&'a [u8]> {
+        self.0
+    }
+}
+
+/// Serializes the items of an iterator as a CQL list or set, without
+/// collecting them into a collection first.
+///
+/// The number of items is written before the items themselves, so the iterator
+/// must know its exact length. It is cloned on each serialization, so `I` is
+/// usually a cheap adapter over borrowed data, e.g. `SetOrListIter(ids.iter().map(|id| id.0))`.
+#[derive(Clone, Copy, Debug)]
+pub struct SetOrListIter<I>(pub I);
+
+/// Serializes a number into any CQL numeric type which can represent its value exactly,
+/// instead of only into the type matching its width.
+///
+/// For example, `Coerced(5_i32)` can be sent to a `bigint` or `smallint` column.
+/// This helps applications in the middle of a schema migration which changed
+/// the type of a column. Integers can be sent to `tinyint`, `smallint`, `int`,
+/// `bigint`, `varint`, `decimal`, `float` and `double` columns, and floating-point
+/// numbers to `float` and `double` columns. A value which doesn't fit into
+/// the column type, or would lose precision, fails to serialize.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Coerced<T>(pub T);
+
 /// Native CQL date representation that allows for a bigger range of dates (-262145-1-1 to 262143-12-31).
 ///
 /// Represented as number of days since -5877641-06-23 i.e. 2^31 days before unix epoch.
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct CqlDate(pub u32);
 
 /// Native CQL timestamp representation that allows full supported timestamp range.
 ///
 /// Represented as signed milliseconds since unix epoch.
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct CqlTimestamp(pub i64);
 
 /// Native CQL time representation.
 ///
 /// Represented as nanoseconds since midnight.
-#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub struct CqlTime(pub i64);
 
 #[cfg(feature = "chrono-04")]
@@ -1,7 +1,7 @@
 // TODO: remove this once deprecated items are deleted.
 #![allow(deprecated)]
 
-use crate::frame::value::{CqlTimeuuid, CqlVarint};
+use crate::frame::value::{CqlStr, CqlTimeuuid, CqlVarint};
 use crate::frame::{response::result::CqlValue, types::RawValue, value::LegacyBatchValuesIterator};
 use crate::types::serialize::batch::{BatchValues, BatchValuesIterator, LegacyBatchValuesAdapter};
 use crate::types::serialize::row::{RowSerializationContext, SerializeRow};
@@ -1693,3 +1693,23 @@ fn check_batch_values_iterator_is_not_lending() {
     f(((10,), (11,)));
     g(((10,), (11,)));
 }
+
+#[test]
+fn cql_str_lookup_by_str() {
+    assert_eq!(
+        compute_hash(&CqlStr::from("foo")),
+        compute_hash(&CqlStr::from("foo".to_owned()))
+    );
+    assert_eq!(compute_hash(&CqlStr::from("foo")), compute_hash(&"foo"));
+
+    let map: HashMap<CqlStr, i32> = [(CqlStr::from("foo"), 1), (CqlStr::from("bar"), 2)]
+        .into_iter()
+        .collect();
+    assert_eq!(map.get("foo"), Some(&1));
+    assert_eq!(map.get("bar"), Some(&2));
+    assert_eq!(map.get("baz"), None);
+
+    let set: HashSet<CqlStr> = [CqlStr::from("foo".to_owned())].into_iter().collect();
+    assert!(set.contains("foo"));
+    assert!(!set.contains("fo"));
+}
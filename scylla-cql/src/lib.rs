@@ -6,6 +6,7 @@ pub mod macros {
     pub use scylla_macros::FromRow;
     pub use scylla_macros::FromUserType;
     pub use scylla_macros::IntoUserType;
+    pub use scylla_macros::ScyllaEntity;
     pub use scylla_macros::SerializeRow;
     pub use scylla_macros::SerializeValue;
     pub use scylla_macros::ValueList;
@@ -42,6 +43,7 @@ pub mod _macro_internal {
         LegacySerializedValues, SerializedResult, Value, ValueList, ValueTooBig,
     };
     pub use crate::macros::*;
+    pub use uuid::Uuid;
 
     pub use crate::types::deserialize::row::{
         deser_error_replace_rust_name as row_deser_error_replace_rust_name,
@@ -49,11 +51,12 @@ pub mod _macro_internal {
         BuiltinDeserializationError as BuiltinRowDeserializationError,
         BuiltinDeserializationErrorKind as BuiltinRowDeserializationErrorKind,
         BuiltinTypeCheckErrorKind as DeserBuiltinRowTypeCheckErrorKind, ColumnIterator,
-        DeserializeRow,
+        DeserializeRow, DeserializeRowByName, RawColumn,
     };
     pub use crate::types::deserialize::value::{
         deser_error_replace_rust_name as value_deser_error_replace_rust_name,
         mk_deser_err as mk_value_deser_err, mk_typck_err as mk_value_typck_err,
+        typck_error_replace_rust_name as value_typck_error_replace_rust_name,
         BuiltinDeserializationError as BuiltinTypeDeserializationError,
         BuiltinDeserializationErrorKind as BuiltinTypeDeserializationErrorKind,
         BuiltinTypeCheckErrorKind as DeserBuiltinTypeTypeCheckErrorKind, DeserializeValue,
@@ -66,10 +69,10 @@ pub mod _macro_internal {
         BuiltinSerializationErrorKind as BuiltinRowSerializationErrorKind,
         BuiltinTypeCheckError as BuiltinRowTypeCheckError,
         BuiltinTypeCheckErrorKind as BuiltinRowTypeCheckErrorKind, RowSerializationContext,
-        SerializeRow,
+        SerializeRow, SerializeRowByName,
     };
     pub use crate::types::serialize::value::{
-        BuiltinSerializationError as BuiltinTypeSerializationError,
+        serialize_unset_if_none, BuiltinSerializationError as BuiltinTypeSerializationError,
         BuiltinSerializationErrorKind as BuiltinTypeSerializationErrorKind,
         BuiltinTypeCheckError as BuiltinTypeTypeCheckError,
         BuiltinTypeCheckErrorKind as BuiltinTypeTypeCheckErrorKind, SerializeValue,
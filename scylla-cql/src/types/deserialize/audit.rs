@@ -0,0 +1,151 @@
+//! Auditing the data for empty values.
+//!
+//! Apart from `NULL`, CQL has a notion of _empty_ values: a value of any type,
+//! e.g. `int`, may be represented by zero bytes (see [`Emptiable`](super::value::Emptiable)).
+//! Such values are rarely written on purpose nowadays, but they are common in data
+//! written by legacy Thrift-era applications, and they fail to deserialize into most types.
+//!
+//! [`EmptyValueAudit`] counts `NULL` and empty cells per column, so that such values can be
+//! found and cleaned up before switching to types which don't accept them. Note that
+//! for types like `text` or `blob` empty values are perfectly valid, non-`NULL` values.
+
+use std::collections::BTreeMap;
+
+use super::result::RawRowIterator;
+use super::row::ColumnIterator;
+use super::DeserializationError;
+
+/// Counters of cells of a single column.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ColumnAudit {
+    /// Number of audited cells.
+    pub cells: u64,
+
+    /// Number of `NULL` cells.
+    pub nulls: u64,
+
+    /// Number of empty, but not `NULL`, cells.
+    pub empty: u64,
+}
+
+/// Counts `NULL` and empty cells per column, over any number of rows.
+///
+/// Columns are identified by their names, so rows of several results
+/// (e.g. consecutive pages of the same query) can be recorded by the same audit.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EmptyValueAudit {
+    columns: BTreeMap<String, ColumnAudit>,
+}
+
+impl EmptyValueAudit {
+    /// Creates an audit with no rows recorded.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the cells of a single row.
+    pub fn record_row(&mut self, row: ColumnIterator<'_, '_>) -> Result<(), DeserializationError> {
+        for column in row {
+            let column = column?;
+            let audit = match self.columns.get_mut(column.spec.name()) {
+                Some(audit) => audit,
+                None => self
+                    .columns
+                    .entry(column.spec.name().to_owned())
+                    .or_default(),
+            };
+            audit.cells += 1;
+            match column.slice {
+                None => audit.nulls += 1,
+                Some(slice) if slice.is_empty() => audit.empty += 1,
+                Some(_) => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Records the cells of all the remaining rows.
+    pub fn record_rows(
+        &mut self,
+        rows: RawRowIterator<'_, '_>,
+    ) -> Result<(), DeserializationError> {
+        for row in rows {
+            self.record_row(row?)?;
+        }
+        Ok(())
+    }
+
+    /// Returns the counters of the column with the given name.
+    pub fn column(&self, name: &str) -> Option<&ColumnAudit> {
+        self.columns.get(name)
+    }
+
+    /// Returns the counters of all the columns, ordered by column name.
+    pub fn columns(&self) -> impl Iterator<Item = (&str, &ColumnAudit)> {
+        self.columns
+            .iter()
+            .map(|(name, audit)| (name.as_str(), audit))
+    }
+
+    /// Returns the names of the columns in which empty values were encountered.
+    pub fn columns_with_empty_values(&self) -> impl Iterator<Item = &str> {
+        self.columns()
+            .filter(|(_, audit)| audit.empty > 0)
+            .map(|(name, _)| name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ColumnAudit, EmptyValueAudit};
+    use crate::frame::response::result::ColumnType;
+    use crate::types::deserialize::result::RawRowIterator;
+    use crate::types::deserialize::tests::{serialize_cells, spec};
+    use crate::types::deserialize::FrameSlice;
+
+    #[test]
+    fn test_empty_value_audit() {
+        let specs = [spec("id", ColumnType::Int), spec("name", ColumnType::Text)];
+        let int = |v: i32| Some(v.to_be_bytes().to_vec());
+        let empty = || Some(Vec::new());
+
+        let mut audit = EmptyValueAudit::new();
+        let page1 = serialize_cells([int(1), Some(b"ann".to_vec()), empty(), None]);
+        audit
+            .record_rows(RawRowIterator::new(2, &specs, FrameSlice::new(&page1)))
+            .unwrap();
+        let page2 = serialize_cells([int(3), empty()]);
+        audit
+            .record_rows(RawRowIterator::new(1, &specs, FrameSlice::new(&page2)))
+            .unwrap();
+
+        assert_eq!(
+            audit.column("id"),
+            Some(&ColumnAudit {
+                cells: 3,
+                nulls: 0,
+                empty: 1,
+            })
+        );
+        assert_eq!(
+            audit.column("name"),
+            Some(&ColumnAudit {
+                cells: 3,
+                nulls: 1,
+                empty: 1,
+            })
+        );
+        assert_eq!(audit.column("other"), None);
+        assert_eq!(
+            audit.columns_with_empty_values().collect::<Vec<_>>(),
+            ["id", "name"]
+        );
+
+        // Malformed rows are reported.
+        let malformed = serialize_cells([int(1)]);
+        audit
+            .record_rows(RawRowIterator::new(1, &specs, FrameSlice::new(&malformed)))
+            .unwrap_err();
+    }
+}
@@ -210,9 +210,14 @@
 //! }
 //! ```
 
+pub mod audit;
 pub mod frame_slice;
 pub mod result;
 pub mod row;
+#[cfg(feature = "serde")]
+pub mod serde_row;
+#[cfg(feature = "serde")]
+pub mod serde_value;
 pub mod value;
 
 pub use frame_slice::FrameSlice;
@@ -232,6 +232,66 @@ impl RawRowLendingIterator {
     }
 }
 
+/// A typed version of [RawRowLendingIterator] which deserializes the rows
+/// into types that do not borrow from the result.
+///
+/// Unlike [TypedRowIterator], it keeps ownership of the serialized result,
+/// so it can be returned from functions or moved into spawned tasks.
+#[derive(Debug)]
+pub struct OwnedTypedRowIterator<R> {
+    inner: RawRowLendingIterator,
+    _phantom: PhantomData<R>,
+}
+
+impl<R> OwnedTypedRowIterator<R>
+where
+    R: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>,
+{
+    /// Creates a new [OwnedTypedRowIterator] from given [RawRowLendingIterator].
+    ///
+    /// Calls `R::type_check` and fails if the type check fails.
+    #[inline]
+    pub fn new(raw: RawRowLendingIterator) -> Result<Self, TypeCheckError> {
+        R::type_check(raw.metadata().col_specs())?;
+        Ok(Self {
+            inner: raw,
+            _phantom: PhantomData,
+        })
+    }
+
+    /// Returns information about the columns of rows that are iterated over.
+    #[inline]
+    pub fn specs(&self) -> &[ColumnSpec<'_>] {
+        self.inner.metadata().col_specs()
+    }
+
+    /// Returns the remaining number of rows that this iterator is supposed
+    /// to return.
+    #[inline]
+    pub fn rows_remaining(&self) -> usize {
+        self.inner.rows_remaining()
+    }
+}
+
+impl<R> Iterator for OwnedTypedRowIterator<R>
+where
+    R: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>,
+{
+    type Item = Result<R, DeserializationError>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner
+            .next()
+            .map(|raw| raw.and_then(|raw| R::deserialize(raw)))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
 #[cfg(test)]
 mod tests {
 
@@ -244,8 +304,8 @@ mod tests {
 
     use super::super::tests::{serialize_cells, spec, CELL1, CELL2};
     use super::{
-        ColumnIterator, DeserializationError, FrameSlice, RawRowIterator, RawRowLendingIterator,
-        TypedRowIterator,
+        ColumnIterator, DeserializationError, FrameSlice, OwnedTypedRowIterator, RawRowIterator,
+        RawRowLendingIterator, TypedRowIterator,
     };
 
     trait LendingIterator {
@@ -385,4 +445,33 @@ mod tests {
         let iter = RawRowIterator::new(0, &specs, FrameSlice::new(&raw_data));
         assert!(TypedRowIterator::<'_, '_, (i32, i64)>::new(iter).is_err());
     }
+
+    #[test]
+    fn test_owned_typed_row_iterator() {
+        let raw_data = serialize_cells([Some(CELL1), Some(CELL2), Some(CELL2), Some(CELL1)]);
+        let specs = [spec("b1", ColumnType::Blob), spec("b2", ColumnType::Blob)];
+        let raw_rows = || {
+            RawRowLendingIterator::new(DeserializedMetadataAndRawRows::new_for_test(
+                ResultMetadata::new_for_test(specs.len(), specs.to_vec()),
+                2,
+                raw_data.clone(),
+            ))
+        };
+
+        // The iterator does not borrow the result, so it can be moved to another thread.
+        let iter = OwnedTypedRowIterator::<(Vec<u8>, Bytes)>::new(raw_rows()).unwrap();
+        let rows = std::thread::spawn(move || iter.collect::<Result<Vec<_>, _>>())
+            .join()
+            .unwrap()
+            .unwrap();
+        assert_eq!(
+            rows,
+            [
+                (CELL1.to_vec(), Bytes::from_static(CELL2)),
+                (CELL2.to_vec(), Bytes::from_static(CELL1)),
+            ]
+        );
+
+        assert!(OwnedTypedRowIterator::<(i32, i64)>::new(raw_rows()).is_err());
+    }
 }
@@ -1,12 +1,14 @@
 //! Provides types for dealing with row deserialization.
 
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Display;
+use std::hash::BuildHasher;
 
 use thiserror::Error;
 
 use super::value::DeserializeValue;
 use super::{make_error_replace_rust_name, DeserializationError, FrameSlice, TypeCheckError};
-use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue, Row};
+use crate::frame::response::result::{ColumnIndex, ColumnSpec, ColumnType, CqlValue, Row};
 
 /// Represents a raw, unparsed column value.
 #[non_exhaustive]
@@ -45,6 +47,50 @@ impl<'frame, 'metadata> ColumnIterator<'frame, 'metadata> {
     pub fn columns_remaining(&self) -> usize {
         self.specs.len()
     }
+
+    /// Type checks and deserializes the value of the column with the given name,
+    /// looked up in `index`, which must have been built from the metadata
+    /// of the result the row comes from.
+    ///
+    /// Only the columns that the iterator has not returned yet are considered,
+    /// and the iterator itself is not advanced, so several columns can be
+    /// retrieved by name from the same row.
+    pub fn get_by_name<T>(&self, index: &ColumnIndex, name: &str) -> Result<T, DeserializationError>
+    where
+        T: DeserializeValue<'frame, 'metadata>,
+    {
+        let no_such_column = || {
+            mk_deser_err::<T>(BuiltinDeserializationErrorKind::NoColumnWithName {
+                column_name: name.to_owned(),
+            })
+        };
+        let position = index.position(name).ok_or_else(no_such_column)?;
+        let column = self
+            .clone()
+            .find_map(|column| match column {
+                Ok(column) if column.index != position => None,
+                column => Some(column),
+            })
+            .ok_or_else(no_such_column)??;
+
+        let typ = column.spec.typ();
+        T::type_check(typ).map_err(|err| {
+            mk_deser_err::<T>(BuiltinDeserializationErrorKind::ColumnTypeCheckFailed {
+                column_index: column.index,
+                column_name: name.to_owned(),
+                err,
+            })
+        })?;
+        T::deserialize(typ, column.slice).map_err(|err| {
+            mk_deser_err::<T>(
+                BuiltinDeserializationErrorKind::ColumnDeserializationFailed {
+                    column_index: column.index,
+                    column_name: name.to_owned(),
+                    err,
+                },
+            )
+        })
+    }
 }
 
 impl<'frame, 'metadata> Iterator for ColumnIterator<'frame, 'metadata> {
@@ -106,6 +152,52 @@ where
     fn deserialize(row: ColumnIterator<'frame, 'metadata>) -> Result<Self, DeserializationError>;
 }
 
+/// Deserialization of a row, split into processing of individual columns
+/// matched to the fields by name.
+///
+/// It is implemented by the `DeserializeRow` derive macro in the default
+/// `match_by_name` flavor, and allows to `#[scylla(flatten)]` a struct into
+/// another struct deriving `DeserializeRow`. It's not supposed to be implemented
+/// manually, and it's not a part of the stable API.
+#[doc(hidden)]
+pub trait DeserializeRowByName<'frame, 'metadata>
+where
+    Self: Sized,
+{
+    /// Tracks which fields have been type checked.
+    type TypeCheckState;
+
+    /// Holds the deserialized fields until the row is complete.
+    type Partial;
+
+    fn new_type_check_state() -> Self::TypeCheckState;
+
+    /// Type checks the column against the field it corresponds to.
+    /// Returns `false` if there is no such field.
+    fn type_check_column(
+        state: &mut Self::TypeCheckState,
+        column_index: usize,
+        spec: &ColumnSpec,
+    ) -> Result<bool, BuiltinTypeCheckErrorKind>;
+
+    /// Appends the names of the columns missing for the fields.
+    fn missing_columns(state: &Self::TypeCheckState, missing: &mut Vec<&'static str>);
+
+    fn new_partial() -> Self::Partial;
+
+    /// Deserializes the column into the field it corresponds to.
+    /// Returns `false` if there is no such field.
+    fn deserialize_column(
+        partial: &mut Self::Partial,
+        column: &RawColumn<'frame, 'metadata>,
+    ) -> Result<bool, BuiltinDeserializationErrorKind>;
+
+    /// Builds the value out of the deserialized fields.
+    ///
+    /// The implementation may assume that the row was type checked.
+    fn finish(partial: Self::Partial) -> Self;
+}
+
 // raw deserialization as ColumnIterator
 
 // What is the purpose of implementing DeserializeRow for ColumnIterator?
@@ -151,33 +243,92 @@ impl<'frame, 'metadata> DeserializeRow<'frame, 'metadata> for Row {
     }
 
     #[inline]
-    fn deserialize(
-        mut row: ColumnIterator<'frame, 'metadata>,
-    ) -> Result<Self, DeserializationError> {
+    fn deserialize(row: ColumnIterator<'frame, 'metadata>) -> Result<Self, DeserializationError> {
         let mut columns = Vec::with_capacity(row.size_hint().0);
-        while let Some(column) = row
-            .next()
-            .transpose()
-            .map_err(deser_error_replace_rust_name::<Self>)?
-        {
-            columns.push(
-                <Option<CqlValue>>::deserialize(column.spec.typ(), column.slice).map_err(
-                    |err| {
-                        mk_deser_err::<Self>(
-                            BuiltinDeserializationErrorKind::ColumnDeserializationFailed {
-                                column_index: column.index,
-                                column_name: column.spec.name().to_owned(),
-                                err,
-                            },
-                        )
-                    },
-                )?,
-            );
-        }
+        deserialize_cql_values::<Self>(row, |_, value| columns.push(value))?;
         Ok(Self { columns })
     }
 }
 
+// dynamic deserialization as a map from column names to CqlValues
+//
+/// A row can be deserialized into a map from column names to their values,
+/// which is useful for handling results of arbitrary queries, e.g. in export tools.
+///
+/// Null columns are not present in the map. If the result contains several columns
+/// of the same name, the value of the last one is kept.
+impl<'frame, 'metadata, S> DeserializeRow<'frame, 'metadata> for HashMap<String, CqlValue, S>
+where
+    S: BuildHasher + Default,
+{
+    #[inline]
+    fn type_check(_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        // CqlValues accept all types, no type checking needed.
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize(row: ColumnIterator<'frame, 'metadata>) -> Result<Self, DeserializationError> {
+        let mut map = HashMap::with_capacity_and_hasher(row.size_hint().0, S::default());
+        deserialize_cql_values::<Self>(row, |spec, value| {
+            if let Some(value) = value {
+                map.insert(spec.name().to_owned(), value);
+            }
+        })?;
+        Ok(map)
+    }
+}
+
+/// A row can be deserialized into a map from column names to their values,
+/// which is useful for handling results of arbitrary queries, e.g. in export tools.
+///
+/// Null columns are not present in the map. If the result contains several columns
+/// of the same name, the value of the last one is kept.
+impl<'frame, 'metadata> DeserializeRow<'frame, 'metadata> for BTreeMap<String, CqlValue> {
+    #[inline]
+    fn type_check(_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        // CqlValues accept all types, no type checking needed.
+        Ok(())
+    }
+
+    #[inline]
+    fn deserialize(row: ColumnIterator<'frame, 'metadata>) -> Result<Self, DeserializationError> {
+        let mut map = BTreeMap::new();
+        deserialize_cql_values::<Self>(row, |spec, value| {
+            if let Some(value) = value {
+                map.insert(spec.name().to_owned(), value);
+            }
+        })?;
+        Ok(map)
+    }
+}
+
+/// Deserializes all columns of the row as CqlValues, passing them to `f`
+/// along with their specs. Errors are reported in the name of `T`.
+pub(super) fn deserialize_cql_values<'frame, 'metadata, T>(
+    mut row: ColumnIterator<'frame, 'metadata>,
+    mut f: impl FnMut(&'metadata ColumnSpec<'metadata>, Option<CqlValue>),
+) -> Result<(), DeserializationError> {
+    while let Some(column) = row
+        .next()
+        .transpose()
+        .map_err(deser_error_replace_rust_name::<T>)?
+    {
+        let value =
+            <Option<CqlValue>>::deserialize(column.spec.typ(), column.slice).map_err(|err| {
+                mk_deser_err::<T>(
+                    BuiltinDeserializationErrorKind::ColumnDeserializationFailed {
+                        column_index: column.index,
+                        column_name: column.spec.name().to_owned(),
+                        err,
+                    },
+                )
+            })?;
+        f(column.spec, value);
+    }
+    Ok(())
+}
+
 // tuples
 //
 /// This is the new encouraged way for deserializing a row.
@@ -465,6 +616,25 @@ pub enum BuiltinDeserializationErrorKind {
         /// The error that caused the raw column deserialization to fail.
         err: DeserializationError,
     },
+
+    /// The row has no column with the requested name.
+    NoColumnWithName {
+        /// Name of the requested column.
+        column_name: String,
+    },
+
+    /// The type of the requested column does not match the Rust type
+    /// it was requested as.
+    ColumnTypeCheckFailed {
+        /// Index of the column.
+        column_index: usize,
+
+        /// Name of the column.
+        column_name: String,
+
+        /// Inner type check error due to the type mismatch.
+        err: TypeCheckError,
+    },
 }
 
 impl Display for BuiltinDeserializationErrorKind {
@@ -490,6 +660,19 @@ impl Display for BuiltinDeserializationErrorKind {
                     "failed to deserialize raw column {column_name} at index {column_index} (most probably due to invalid column structure inside a row): {err}"
                 )
             }
+            BuiltinDeserializationErrorKind::NoColumnWithName { column_name } => {
+                write!(f, "the row has no column named {column_name}")
+            }
+            BuiltinDeserializationErrorKind::ColumnTypeCheckFailed {
+                column_index,
+                column_name,
+                err,
+            } => {
+                write!(
+                    f,
+                    "mismatched types in column {column_name} at index {column_index}: {err}"
+                )
+            }
         }
     }
 }
@@ -541,3 +724,55 @@ fn _test_struct_deserialization_skip_rename_collision_with_field() {}
 /// }
 /// ```
 fn _test_struct_deserialization_rename_collision_with_another_rename() {}
+
+/// ```compile_fail
+///
+/// #[derive(scylla_macros::DeserializeRow)]
+/// #[scylla(crate = scylla_cql)]
+/// struct Audit {
+///     created_by: String,
+/// }
+///
+/// #[derive(scylla_macros::DeserializeRow)]
+/// #[scylla(crate = scylla_cql, flavor = "enforce_order")]
+/// struct TestRow {
+///     a: i32,
+///     #[scylla(flatten)]
+///     audit: Audit,
+/// }
+/// ```
+fn _test_struct_deserialization_flatten_requires_match_by_name() {}
+
+/// ```compile_fail
+///
+/// #[derive(scylla_macros::DeserializeRow)]
+/// #[scylla(crate = scylla_cql)]
+/// struct Audit {
+///     created_by: String,
+/// }
+///
+/// #[derive(scylla_macros::DeserializeRow)]
+/// #[scylla(crate = scylla_cql)]
+/// struct TestRow {
+///     a: i32,
+///     #[scylla(flatten, rename = "b")]
+///     audit: Audit,
+/// }
+/// ```
+fn _test_struct_deserialization_flatten_conflicts_with_rename() {}
+
+/// ```compile_fail
+///
+/// #[derive(scylla_macros::DeserializeRow)]
+/// #[scylla(crate = scylla_cql)]
+/// struct TestRow(i32, String);
+/// ```
+fn _test_struct_deserialization_tuple_struct_requires_positional() {}
+
+/// ```compile_fail
+///
+/// #[derive(scylla_macros::DeserializeRow)]
+/// #[scylla(crate = scylla_cql, positional, rename_all = "camelCase")]
+/// struct TestRow(i32, String);
+/// ```
+fn _test_struct_deserialization_positional_conflicts_with_rename_all() {}
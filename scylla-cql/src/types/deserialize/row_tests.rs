@@ -1,8 +1,9 @@
 use assert_matches::assert_matches;
 use bytes::Bytes;
 use scylla_macros::DeserializeRow;
+use std::collections::{BTreeMap, HashMap};
 
-use crate::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
+use crate::frame::response::result::{ColumnIndex, ColumnSpec, ColumnType, TableSpec};
 use crate::types::deserialize::row::BuiltinDeserializationErrorKind;
 use crate::types::deserialize::{value, DeserializationError, FrameSlice};
 
@@ -81,6 +82,99 @@ fn test_deserialization_as_column_iterator() {
     assert!(iter.next().is_none());
 }
 
+#[test]
+fn test_column_iterator_get_by_name() {
+    let col_specs = [
+        spec("i1", ColumnType::Int),
+        spec("i2", ColumnType::Text),
+        spec("i3", ColumnType::Int),
+    ];
+    let index = ColumnIndex::new(&col_specs);
+    let serialized_values = serialize_cells([val_int(123), val_str("ScyllaDB"), None]);
+    let mut iter = deserialize::<ColumnIterator>(&col_specs, &serialized_values).unwrap();
+
+    assert_eq!(iter.get_by_name::<&str>(&index, "i2").unwrap(), "ScyllaDB");
+    assert_eq!(iter.get_by_name::<i32>(&index, "i1").unwrap(), 123);
+    assert_eq!(iter.get_by_name::<Option<i32>>(&index, "i3").unwrap(), None);
+
+    // Wrong type
+    let err = iter.get_by_name::<String>(&index, "i1").unwrap_err();
+    let err = get_deser_err(&err);
+    assert_eq!(err.rust_name, std::any::type_name::<String>());
+    assert_matches!(
+        &err.kind,
+        BuiltinDeserializationErrorKind::ColumnTypeCheckFailed {
+            column_index: 0,
+            column_name,
+            ..
+        } if column_name == "i1"
+    );
+
+    // Non-null type for a null value
+    let err = iter.get_by_name::<i32>(&index, "i3").unwrap_err();
+    assert_matches!(
+        &get_deser_err(&err).kind,
+        BuiltinDeserializationErrorKind::ColumnDeserializationFailed {
+            column_index: 2,
+            ..
+        }
+    );
+
+    // Missing column
+    let err = iter.get_by_name::<i32>(&index, "i4").unwrap_err();
+    assert_matches!(
+        &get_deser_err(&err).kind,
+        BuiltinDeserializationErrorKind::NoColumnWithName { column_name } if column_name == "i4"
+    );
+
+    // Columns already returned by the iterator are not available anymore
+    iter.next().unwrap().unwrap();
+    let err = iter.get_by_name::<i32>(&index, "i1").unwrap_err();
+    assert_matches!(
+        &get_deser_err(&err).kind,
+        BuiltinDeserializationErrorKind::NoColumnWithName { column_name } if column_name == "i1"
+    );
+    assert_eq!(iter.get_by_name::<&str>(&index, "i2").unwrap(), "ScyllaDB");
+}
+
+#[test]
+fn test_deserialization_as_map() {
+    let col_specs = [
+        spec("i", ColumnType::Int),
+        spec("s", ColumnType::Text),
+        spec("n", ColumnType::Counter),
+    ];
+    let serialized_values = serialize_cells([val_int(123), val_str("ScyllaDB"), None]);
+    let expected = [
+        ("i".to_owned(), CqlValue::Int(123)),
+        ("s".to_owned(), CqlValue::Text("ScyllaDB".to_owned())),
+    ];
+
+    let hash_map =
+        deserialize::<HashMap<String, CqlValue>>(&col_specs, &serialized_values).unwrap();
+    assert_eq!(hash_map, HashMap::from(expected.clone()));
+
+    let btree_map =
+        deserialize::<BTreeMap<String, CqlValue>>(&col_specs, &serialized_values).unwrap();
+    assert_eq!(btree_map, BTreeMap::from(expected));
+
+    // Failures are reported in the name of the map.
+    let err = deserialize::<BTreeMap<String, CqlValue>>(
+        &[spec("i", ColumnType::BigInt)],
+        &serialize_cells([val_int(123)]),
+    )
+    .unwrap_err();
+    let err = get_deser_err(&err);
+    assert_eq!(
+        err.rust_name,
+        std::any::type_name::<BTreeMap<String, CqlValue>>()
+    );
+    assert_matches!(
+        err.kind,
+        BuiltinDeserializationErrorKind::ColumnDeserializationFailed { .. }
+    );
+}
+
 // Do not remove. It's not used in tests but we keep it here to check that
 // we properly ignore warnings about unused variables, unnecessary `mut`s
 // etc. that usually pop up when generating code for empty structs.
@@ -215,6 +309,174 @@ fn test_struct_deserialization_no_name_check() {
     );
 }
 
+#[test]
+fn test_struct_deserialization_positional() {
+    #[derive(DeserializeRow, PartialEq, Eq, Debug)]
+    #[scylla(crate = "crate", positional)]
+    struct MyRow<'a>(&'a str, Option<i32>, #[scylla(skip)] String);
+
+    // The names of the columns are not checked at all
+    let specs = &[
+        spec("a", ColumnType::Text),
+        spec("count(*)", ColumnType::Int),
+    ];
+    let byts = serialize_cells([val_str("abc"), val_int(123)]);
+    let row = deserialize::<MyRow<'_>>(specs, &byts).unwrap();
+    assert_eq!(row, MyRow("abc", Some(123), String::new()));
+
+    // Wrong column count
+    let specs = &[spec("a", ColumnType::Text)];
+    MyRow::type_check(specs).unwrap_err();
+
+    // Wrong order of columns
+    let specs = &[spec("a", ColumnType::Int), spec("b", ColumnType::Text)];
+    MyRow::type_check(specs).unwrap_err();
+}
+
+#[test]
+fn test_struct_deserialization_strict_column_set() {
+    #[derive(DeserializeRow, PartialEq, Eq, Debug)]
+    #[scylla(crate = "crate")]
+    struct MyRow {
+        a: i32,
+        b: Option<String>,
+    }
+
+    let specs = &[spec("b", ColumnType::Text), spec("a", ColumnType::Int)];
+    let byts = serialize_cells([val_str("abc"), val_int(123)]);
+    let row = deserialize::<MyRow>(specs, &byts).unwrap();
+    assert_eq!(
+        row,
+        MyRow {
+            a: 123,
+            b: Some("abc".to_owned()),
+        }
+    );
+
+    // Extra column, e.g. added to the table queried with `SELECT *`
+    let specs = &[
+        spec("a", ColumnType::Int),
+        spec("b", ColumnType::Text),
+        spec("c", ColumnType::Int),
+    ];
+    let err = MyRow::type_check(specs).unwrap_err();
+    assert_matches!(
+        get_typck_err_inner(err.0.as_ref()).kind,
+        BuiltinTypeCheckErrorKind::ColumnWithUnknownName {
+            column_index: 2,
+            ..
+        }
+    );
+
+    // Missing column
+    let specs = &[spec("a", ColumnType::Int)];
+    let err = MyRow::type_check(specs).unwrap_err();
+    assert_matches!(
+        get_typck_err_inner(err.0.as_ref()).kind,
+        BuiltinTypeCheckErrorKind::ValuesMissingForColumns { .. }
+    );
+}
+
+#[test]
+fn test_struct_deserialization_flatten() {
+    #[derive(DeserializeRow, PartialEq, Eq, Debug)]
+    #[scylla(crate = "crate")]
+    struct Audit<'a> {
+        created_by: &'a str,
+        #[scylla(rename = "updated")]
+        updated_at: i32,
+    }
+
+    #[derive(DeserializeRow, PartialEq, Eq, Debug)]
+    #[scylla(crate = "crate")]
+    struct MyRow<'a> {
+        id: i32,
+        #[scylla(flatten)]
+        audit: Audit<'a>,
+        #[scylla(skip)]
+        x: String,
+    }
+
+    // Columns of the flattened struct may be interleaved with the other columns.
+    let specs = &[
+        spec("updated", ColumnType::Int),
+        spec("id", ColumnType::Int),
+        spec("created_by", ColumnType::Text),
+    ];
+    let byts = serialize_cells([val_int(7), val_int(123), val_str("ann")]);
+    let row = deserialize::<MyRow>(specs, &byts).unwrap();
+    assert_eq!(
+        row,
+        MyRow {
+            id: 123,
+            audit: Audit {
+                created_by: "ann",
+                updated_at: 7,
+            },
+            x: String::new(),
+        }
+    );
+
+    // Columns of the flattened struct are required as well.
+    let specs = &[
+        spec("id", ColumnType::Int),
+        spec("updated", ColumnType::Int),
+    ];
+    let err = MyRow::type_check(specs).unwrap_err();
+    let err = get_typck_err_inner(err.0.as_ref());
+    assert_eq!(err.rust_name, std::any::type_name::<MyRow>());
+    assert_matches!(
+        &err.kind,
+        BuiltinTypeCheckErrorKind::ValuesMissingForColumns { column_names }
+            if column_names == &["created_by"]
+    );
+
+    // Errors of the flattened fields are reported as errors of the whole row.
+    let specs = &[
+        spec("id", ColumnType::Int),
+        spec("created_by", ColumnType::Int),
+        spec("updated", ColumnType::Int),
+    ];
+    let err = MyRow::type_check(specs).unwrap_err();
+    assert_matches!(
+        get_typck_err_inner(err.0.as_ref()).kind,
+        BuiltinTypeCheckErrorKind::ColumnTypeCheckFailed {
+            column_index: 1,
+            ..
+        }
+    );
+
+    let specs = &[
+        spec("id", ColumnType::Int),
+        spec("created_by", ColumnType::Text),
+        spec("updated", ColumnType::Int),
+        spec("created_by", ColumnType::Text),
+    ];
+    let err = MyRow::type_check(specs).unwrap_err();
+    assert_matches!(
+        get_typck_err_inner(err.0.as_ref()).kind,
+        BuiltinTypeCheckErrorKind::DuplicatedColumn {
+            column_index: 3,
+            column_name: "created_by",
+        }
+    );
+
+    let specs = &[
+        spec("id", ColumnType::Int),
+        spec("created_by", ColumnType::Text),
+        spec("updated", ColumnType::Int),
+        spec("deleted", ColumnType::Int),
+    ];
+    let err = MyRow::type_check(specs).unwrap_err();
+    assert_matches!(
+        get_typck_err_inner(err.0.as_ref()).kind,
+        BuiltinTypeCheckErrorKind::ColumnWithUnknownName {
+            column_index: 3,
+            ..
+        }
+    );
+}
+
 #[test]
 fn test_struct_deserialization_cross_rename_fields() {
     #[derive(scylla_macros::DeserializeRow, PartialEq, Eq, Debug)]
@@ -243,6 +505,205 @@ fn test_struct_deserialization_cross_rename_fields() {
     }
 }
 
+#[test]
+fn test_struct_deserialization_rename_all() {
+    #[derive(scylla_macros::DeserializeRow, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate, rename_all = "camelCase")]
+    struct TestRow {
+        user_id: i32,
+        #[scylla(rename = "name")]
+        display_name: String,
+    }
+
+    let row_bytes = serialize_cells(["Alice".as_bytes(), &42_i32.to_be_bytes()].map(Some));
+    let specs = [
+        spec("name", ColumnType::Text),
+        spec("userId", ColumnType::Int),
+    ];
+    let row = deserialize::<TestRow>(&specs, &row_bytes).unwrap();
+    assert_eq!(
+        row,
+        TestRow {
+            user_id: 42,
+            display_name: "Alice".to_owned(),
+        }
+    );
+
+    // The names are matched exactly.
+    let specs = [
+        spec("name", ColumnType::Text),
+        spec("user_id", ColumnType::Int),
+    ];
+    deserialize::<TestRow>(&specs, &row_bytes).unwrap_err();
+}
+
+#[test]
+fn test_struct_deserialization_generic() {
+    #[derive(scylla_macros::DeserializeRow, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate)]
+    struct Keyed<'a, K, V = i32>
+    where
+        K: Clone,
+    {
+        key: K,
+        name: &'a str,
+        value: Option<V>,
+    }
+
+    #[derive(scylla_macros::DeserializeRow, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate, flavor = "enforce_order")]
+    struct Ordered<T> {
+        a: T,
+        b: T,
+    }
+
+    let specs = [
+        spec("name", ColumnType::Text),
+        spec("key", ColumnType::BigInt),
+        spec("value", ColumnType::Int),
+    ];
+    let row_bytes = serialize_cells([Some("abc".as_bytes()), Some(&7_i64.to_be_bytes()), None]);
+    let row = deserialize::<Keyed<i64>>(&specs, &row_bytes).unwrap();
+    assert_eq!(
+        row,
+        Keyed {
+            key: 7,
+            name: "abc",
+            value: None,
+        }
+    );
+
+    // The type parameters are type checked as well.
+    deserialize::<Keyed<String>>(&specs, &row_bytes).unwrap_err();
+
+    let specs = [spec("a", ColumnType::Text), spec("b", ColumnType::Text)];
+    let row_bytes = serialize_cells(["x".as_bytes(), "y".as_bytes()].map(Some));
+    let row = deserialize::<Ordered<&str>>(&specs, &row_bytes).unwrap();
+    assert_eq!(row, Ordered { a: "x", b: "y" });
+}
+
+#[test]
+fn test_entity_roundtrip() {
+    #[derive(scylla_macros::ScyllaEntity, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate, rename_all = "camelCase")]
+    struct TestRow {
+        user_id: i32,
+        #[scylla(rename = "name")]
+        display_name: Option<String>,
+    }
+
+    let specs = [
+        spec("name", ColumnType::Text),
+        spec("userId", ColumnType::Int),
+    ];
+    let row = TestRow {
+        user_id: 42,
+        display_name: Some("Alice".to_owned()),
+    };
+    let row_bytes = Bytes::from(crate::types::serialize::row::tests::do_serialize(
+        &row, &specs,
+    ));
+    assert_eq!(row_bytes, serialize_cells([val_str("Alice"), val_int(42)]));
+    assert_eq!(deserialize::<TestRow>(&specs, &row_bytes).unwrap(), row);
+}
+
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+pub(crate) enum Priority {
+    Low,
+    High,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("invalid priority {0}")]
+pub(crate) struct InvalidPriority(pub(crate) i32);
+
+// Stores `Priority` as an int.
+pub(crate) mod priority_as_int {
+    use super::{InvalidPriority, Priority};
+    use crate::frame::response::result::ColumnType;
+    use crate::types::deserialize::value::DeserializeValue;
+    use crate::types::deserialize::{DeserializationError, FrameSlice, TypeCheckError};
+    use crate::types::serialize::value::SerializeValue;
+    use crate::types::serialize::writers::{CellWriter, WrittenCellProof};
+    use crate::types::serialize::SerializationError;
+
+    pub(crate) fn serialize<'b>(
+        priority: &Priority,
+        typ: &ColumnType,
+        writer: CellWriter<'b>,
+    ) -> Result<WrittenCellProof<'b>, SerializationError> {
+        let value = match priority {
+            Priority::Low => 0_i32,
+            Priority::High => 1,
+        };
+        value.serialize(typ, writer)
+    }
+
+    pub(crate) fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        <i32 as DeserializeValue>::type_check(typ)
+    }
+
+    pub(crate) fn deserialize<'frame, 'metadata>(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Priority, DeserializationError> {
+        match <i32 as DeserializeValue>::deserialize(typ, v)? {
+            0 => Ok(Priority::Low),
+            1 => Ok(Priority::High),
+            other => Err(DeserializationError::new(InvalidPriority(other))),
+        }
+    }
+}
+
+#[test]
+fn test_struct_deserialization_with() {
+    #[derive(scylla_macros::ScyllaEntity, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate)]
+    struct Task {
+        id: i32,
+        #[scylla(with = "priority_as_int")]
+        priority: Priority,
+    }
+
+    let specs = [
+        spec("priority", ColumnType::Int),
+        spec("id", ColumnType::Int),
+    ];
+    let task = Task {
+        id: 7,
+        priority: Priority::High,
+    };
+    let row_bytes = Bytes::from(crate::types::serialize::row::tests::do_serialize(
+        &task, &specs,
+    ));
+    assert_eq!(row_bytes, serialize_cells([val_int(1), val_int(7)]));
+    assert_eq!(deserialize::<Task>(&specs, &row_bytes).unwrap(), task);
+
+    // The type check of the module is used.
+    let specs = [
+        spec("priority", ColumnType::Text),
+        spec("id", ColumnType::Int),
+    ];
+    Task::type_check(&specs).unwrap_err();
+
+    // So are the errors of the module.
+    let specs = [
+        spec("priority", ColumnType::Int),
+        spec("id", ColumnType::Int),
+    ];
+    let row_bytes = serialize_cells([val_int(5), val_int(7)]);
+    let err = deserialize::<Task>(&specs, &row_bytes).unwrap_err();
+    let err = get_deser_err(&err);
+    let BuiltinDeserializationErrorKind::ColumnDeserializationFailed {
+        column_name, err, ..
+    } = &err.kind
+    else {
+        panic!("unexpected error kind: {:?}", err.kind)
+    };
+    assert_eq!(column_name, "priority");
+    assert_matches!(err.0.downcast_ref(), Some(InvalidPriority(5)));
+}
+
 fn val_int(i: i32) -> Option<Vec<u8>> {
     Some(i.to_be_bytes().to_vec())
 }
@@ -0,0 +1,252 @@
+//! Bridge between rows and the [`serde`] data model.
+//!
+//! [`RowDeserializer`] implements [`serde::Deserializer`] on top of a row, so that
+//! any type implementing [`serde::Deserialize`] can be populated from a query result,
+//! without deriving the driver's traits. [`SerdeRow<T>`] does it for any `T` implementing
+//! [`serde::de::DeserializeOwned`] upon row deserialization.
+//!
+//! The row is presented either as a map from column names to column values, e.g. to structs,
+//! or as a sequence of column values, e.g. to tuples. Column values are presented
+//! as described in the [`serde_value`](super::serde_value) module.
+
+use serde::de::{DeserializeOwned, Visitor};
+use serde::forward_to_deserialize_any;
+use thiserror::Error;
+
+use super::row::{deserialize_cql_values, ColumnIterator, DeserializeRow};
+use super::serde_value::{visit_map, visit_seq, SerdeBridgeError};
+use super::{DeserializationError, TypeCheckError};
+use crate::frame::response::result::{ColumnSpec, CqlValue};
+
+/// A row whose columns are deserialized as [`CqlValue`]s, which implements
+/// [`serde::Deserializer`] by reference.
+///
+/// ```rust
+/// # use scylla_cql::types::deserialize::serde_row::RowDeserializer;
+/// # use scylla_cql::types::deserialize::DeserializationError;
+/// #[derive(serde::Deserialize)]
+/// struct User {
+///     id: i32,
+///     name: Option<String>,
+/// }
+///
+/// fn to_user(row: &RowDeserializer<'_>) -> Result<User, DeserializationError> {
+///     row.deserialize_into()
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct RowDeserializer<'metadata> {
+    columns: Vec<(&'metadata str, Option<CqlValue>)>,
+}
+
+impl<'metadata> RowDeserializer<'metadata> {
+    /// Returns the names and values of the columns, in the order of the result.
+    pub fn columns(&self) -> &[(&'metadata str, Option<CqlValue>)] {
+        &self.columns
+    }
+
+    /// Deserializes the row into `T`, using its [`serde::Deserialize`] implementation.
+    pub fn deserialize_into<'de, T>(&'de self) -> Result<T, DeserializationError>
+    where
+        T: serde::Deserialize<'de>,
+    {
+        T::deserialize(self).map_err(|err| {
+            DeserializationError::new(SerdeRowError {
+                rust_name: std::any::type_name::<T>(),
+                message: err.0,
+            })
+        })
+    }
+}
+
+impl<'frame, 'metadata> DeserializeRow<'frame, 'metadata> for RowDeserializer<'metadata> {
+    #[inline]
+    fn type_check(_specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        // CqlValues accept all types, no type checking needed.
+        Ok(())
+    }
+
+    fn deserialize(row: ColumnIterator<'frame, 'metadata>) -> Result<Self, DeserializationError> {
+        let mut columns = Vec::with_capacity(row.size_hint().0);
+        deserialize_cql_values::<Self>(row, |spec, value| columns.push((spec.name(), value)))?;
+        Ok(Self { columns })
+    }
+}
+
+impl<'de> serde::Deserializer<'de> for &'de RowDeserializer<'_> {
+    type Error = SerdeBridgeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visit_map(
+            visitor,
+            self.columns
+                .iter()
+                .map(|(name, value)| (*name, value.as_ref())),
+        )
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visit_seq(
+            visitor,
+            self.columns.iter().map(|(_, value)| value.as_ref()),
+        )
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+/// A wrapper that deserializes a row into any type implementing
+/// [`serde::Deserialize`].
+///
+/// See the [module documentation](self) for how rows are mapped
+/// onto the serde data model.
+///
+/// It also serializes any type implementing [`serde::Serialize`] as the values
+/// of a statement, as described in the [`serialize::serde_row`](crate::types::serialize::serde_row) module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SerdeRow<T>(pub T);
+
+impl<T> SerdeRow<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'frame, 'metadata, T> DeserializeRow<'frame, 'metadata> for SerdeRow<T>
+where
+    T: DeserializeOwned,
+{
+    #[inline]
+    fn type_check(specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        <RowDeserializer<'metadata> as DeserializeRow<'frame, 'metadata>>::type_check(specs)
+    }
+
+    fn deserialize(row: ColumnIterator<'frame, 'metadata>) -> Result<Self, DeserializationError> {
+        let mut columns = Vec::with_capacity(row.size_hint().0);
+        deserialize_cql_values::<Self>(row, |spec, value| columns.push((spec.name(), value)))?;
+        RowDeserializer { columns }.deserialize_into().map(SerdeRow)
+    }
+}
+
+/// Failed to deserialize a row into a serde-compatible type.
+#[derive(Debug, Clone, Error)]
+#[error("Failed to deserialize the row into {rust_name} via serde: {message}")]
+pub struct SerdeRowError {
+    /// Name of the Rust type being deserialized.
+    pub rust_name: &'static str,
+
+    /// The error reported by the type's `Deserialize` implementation.
+    pub message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use assert_matches::assert_matches;
+    use serde::Deserialize;
+
+    use super::{RowDeserializer, SerdeRow, SerdeRowError};
+    use crate::frame::response::result::{ColumnType, CqlValue};
+    use crate::types::deserialize::row::tests::deserialize;
+    use crate::types::deserialize::tests::{serialize_cells, spec};
+
+    #[derive(Deserialize, Debug, PartialEq)]
+    struct User {
+        id: i32,
+        name: Option<String>,
+        #[serde(default)]
+        tags: Vec<String>,
+    }
+
+    fn cells(id: i32, name: Option<&str>) -> bytes::Bytes {
+        serialize_cells([
+            Some(id.to_be_bytes().to_vec()),
+            name.map(|name| name.as_bytes().to_vec()),
+        ])
+    }
+
+    #[test]
+    fn test_struct_and_tuple() {
+        let specs = [spec("name", ColumnType::Text), spec("id", ColumnType::Int)];
+        let specs_by_id = [spec("id", ColumnType::Int), spec("name", ColumnType::Text)];
+
+        // Columns are matched by name.
+        let bytes = serialize_cells([Some(b"Ann".to_vec()), Some(7_i32.to_be_bytes().to_vec())]);
+        let SerdeRow(user) = deserialize::<SerdeRow<User>>(&specs, &bytes).unwrap();
+        assert_eq!(
+            user,
+            User {
+                id: 7,
+                name: Some("Ann".to_owned()),
+                tags: vec![],
+            }
+        );
+
+        let bytes = cells(8, None);
+        let user = deserialize::<SerdeRow<User>>(&specs_by_id, &bytes)
+            .unwrap()
+            .into_inner();
+        assert_eq!(user.name, None);
+
+        // Tuples are matched by position.
+        let SerdeRow(tuple) =
+            deserialize::<SerdeRow<(i64, Option<String>)>>(&specs_by_id, &bytes).unwrap();
+        assert_eq!(tuple, (8, None));
+
+        // The row can be inspected before choosing the type.
+        let row = deserialize::<RowDeserializer>(&specs_by_id, &bytes).unwrap();
+        assert_eq!(
+            row.columns(),
+            &[("id", Some(CqlValue::Int(8))), ("name", None)]
+        );
+        assert_eq!(row.deserialize_into::<User>().unwrap().id, 8);
+    }
+
+    #[test]
+    fn test_errors() {
+        let specs = [spec("id", ColumnType::Int), spec("name", ColumnType::Text)];
+        let bytes = cells(7, Some("Ann"));
+
+        #[derive(Deserialize, Debug)]
+        #[allow(dead_code)]
+        struct Missing {
+            id: i32,
+            email: String,
+        }
+        let err = deserialize::<SerdeRow<Missing>>(&specs, &bytes).unwrap_err();
+        let err = err.0.downcast_ref::<SerdeRowError>().unwrap();
+        assert_eq!(err.rust_name, std::any::type_name::<Missing>());
+        assert!(err.message.contains("email"), "{}", err.message);
+
+        let err = deserialize::<SerdeRow<(String, String)>>(&specs, &bytes).unwrap_err();
+        assert_matches!(err.0.downcast_ref::<SerdeRowError>(), Some(_));
+    }
+}
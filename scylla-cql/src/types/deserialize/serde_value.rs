@@ -0,0 +1,539 @@
+//! Bridge between CQL values and the [`serde`] data model.
+//!
+//! [`SerdeValue<T>`] can be deserialized from any CQL value by any `T` that
+//! implements [`serde::de::DeserializeOwned`]. The CQL value is presented to
+//! `T` as follows:
+//!
+//! | CQL type                              | serde data model                                  |
+//! |---------------------------------------|---------------------------------------------------|
+//! | `ascii`, `text`                       | string                                            |
+//! | `boolean`                             | bool                                              |
+//! | `blob`                                | bytes                                             |
+//! | `tinyint`, `smallint`, `int`, `bigint`, `counter` | the corresponding signed integer      |
+//! | `varint`                              | `i64` or `i128` if it fits, string otherwise      |
+//! | `decimal`                             | string, e.g. `"-12.345"`                          |
+//! | `float`, `double`                     | `f32`, `f64`                                      |
+//! | `timestamp`                           | `i64` - milliseconds since the Unix epoch         |
+//! | `date`                                | `u32` - days since -5877641-06-23 (2^31 days before the Unix epoch) |
+//! | `time`                                | `i64` - nanoseconds since midnight                |
+//! | `duration`                            | map with `months`, `days` and `nanoseconds` keys  |
+//! | `uuid`, `timeuuid`, `inet`            | string                                            |
+//! | `list`, `set`, `tuple`                | sequence                                          |
+//! | `map`                                 | map                                               |
+//! | UDT                                   | map from field names to field values              |
+//! | null                                  | `None` (or unit, if an option is not expected)    |
+//!
+//! Textual values can also be deserialized as unit variants of enums.
+
+use std::fmt::Display;
+
+use serde::de::value::{BorrowedStrDeserializer, MapDeserializer, SeqDeserializer};
+use serde::de::{DeserializeOwned, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
+use thiserror::Error;
+
+use super::value::{deser_error_replace_rust_name, typck_error_replace_rust_name};
+use super::{DeserializationError, DeserializeValue, FrameSlice, TypeCheckError};
+use crate::frame::response::result::{ColumnType, CqlValue};
+
+/// A wrapper that deserializes a CQL value into any type implementing
+/// [`serde::Deserialize`].
+///
+/// See the [module documentation](self) for how CQL values are mapped
+/// onto the serde data model.
+///
+/// It also serializes any type implementing [`serde::Serialize`] into a CQL value,
+/// as described in the [`serialize::serde_value`](crate::types::serialize::serde_value) module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Default)]
+pub struct SerdeValue<T>(pub T);
+
+impl<T> SerdeValue<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'frame, 'metadata, T> DeserializeValue<'frame, 'metadata> for SerdeValue<T>
+where
+    T: DeserializeOwned,
+{
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        <Option<CqlValue> as DeserializeValue<'frame, 'metadata>>::type_check(typ)
+            .map_err(typck_error_replace_rust_name::<Self>)
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let value = <Option<CqlValue> as DeserializeValue<'frame, 'metadata>>::deserialize(typ, v)
+            .map_err(deser_error_replace_rust_name::<Self>)?;
+        T::deserialize(CqlValueDeserializer(value.as_ref()))
+            .map(SerdeValue)
+            .map_err(|err| {
+                DeserializationError::new(SerdeValueError {
+                    rust_name: std::any::type_name::<T>(),
+                    cql_type: typ.clone().into_owned(),
+                    message: err.0,
+                })
+            })
+    }
+}
+
+/// Failed to deserialize a CQL value into a serde-compatible type.
+#[derive(Debug, Clone, Error)]
+#[error("Failed to deserialize CQL type {cql_type:?} into {rust_name} via serde: {message}")]
+pub struct SerdeValueError {
+    /// Name of the Rust type being deserialized.
+    pub rust_name: &'static str,
+
+    /// The CQL type of the value.
+    pub cql_type: ColumnType<'static>,
+
+    /// The error reported by the type's `Deserialize` implementation.
+    pub message: String,
+}
+
+/// The error type of the [`serde::Deserializer`] implementations bridging CQL values
+/// and rows to serde, carrying the message reported by a `Deserialize` implementation.
+///
+/// [`SerdeValue`] and [`SerdeRow`](super::serde_row::SerdeRow) wrap it
+/// into errors providing more context.
+#[derive(Debug)]
+pub struct SerdeBridgeError(pub String);
+
+impl Display for SerdeBridgeError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerdeBridgeError {}
+
+impl serde::de::Error for SerdeBridgeError {
+    fn custom<M: Display>(msg: M) -> Self {
+        SerdeBridgeError(msg.to_string())
+    }
+}
+
+// `None` represents a null.
+pub(super) struct CqlValueDeserializer<'a>(pub(super) Option<&'a CqlValue>);
+
+impl<'de> IntoDeserializer<'de, SerdeBridgeError> for CqlValueDeserializer<'de> {
+    type Deserializer = Self;
+
+    fn into_deserializer(self) -> Self::Deserializer {
+        self
+    }
+}
+
+pub(super) fn visit_seq<'de, V, I>(visitor: V, elements: I) -> Result<V::Value, SerdeBridgeError>
+where
+    V: Visitor<'de>,
+    I: Iterator<Item = Option<&'de CqlValue>>,
+{
+    let mut seq = SeqDeserializer::<_, SerdeBridgeError>::new(elements.map(CqlValueDeserializer));
+    let value = visitor.visit_seq(&mut seq)?;
+    seq.end()?;
+    Ok(value)
+}
+
+pub(super) fn visit_map<'de, V, K, I>(visitor: V, entries: I) -> Result<V::Value, SerdeBridgeError>
+where
+    V: Visitor<'de>,
+    K: IntoDeserializer<'de, SerdeBridgeError>,
+    I: Iterator<Item = (K, Option<&'de CqlValue>)>,
+{
+    let mut map = MapDeserializer::<_, SerdeBridgeError>::new(
+        entries.map(|(k, v)| (k, CqlValueDeserializer(v))),
+    );
+    let value = visitor.visit_map(&mut map)?;
+    map.end()?;
+    Ok(value)
+}
+
+impl<'de> serde::Deserializer<'de> for CqlValueDeserializer<'de> {
+    type Error = SerdeBridgeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        let Some(value) = self.0 else {
+            return visitor.visit_unit();
+        };
+        match value {
+            CqlValue::Ascii(s) | CqlValue::Text(s) => visitor.visit_borrowed_str(s),
+            CqlValue::Boolean(b) => visitor.visit_bool(*b),
+            CqlValue::Blob(b) => visitor.visit_borrowed_bytes(b),
+            CqlValue::TinyInt(i) => visitor.visit_i8(*i),
+            CqlValue::SmallInt(i) => visitor.visit_i16(*i),
+            CqlValue::Int(i) => visitor.visit_i32(*i),
+            CqlValue::BigInt(i) => visitor.visit_i64(*i),
+            CqlValue::Counter(c) => visitor.visit_i64(c.0),
+            CqlValue::Varint(v) => {
+                let bytes = v.as_signed_bytes_be_slice();
+                if bytes.len() <= 8 {
+                    visitor.visit_i64(signed_be_bytes_to_i128(bytes) as i64)
+                } else if bytes.len() <= 16 {
+                    visitor.visit_i128(signed_be_bytes_to_i128(bytes))
+                } else {
+                    visitor.visit_string(varint_to_string(bytes))
+                }
+            }
+            CqlValue::Decimal(d) => {
+                let (bytes, scale) = d.as_signed_be_bytes_slice_and_exponent();
+                visitor.visit_string(decimal_to_string(bytes, scale))
+            }
+            CqlValue::Float(f) => visitor.visit_f32(*f),
+            CqlValue::Double(d) => visitor.visit_f64(*d),
+            CqlValue::Timestamp(t) => visitor.visit_i64(t.0),
+            CqlValue::Date(d) => visitor.visit_u32(d.0),
+            CqlValue::Time(t) => visitor.visit_i64(t.0),
+            CqlValue::Duration(d) => {
+                let entries = [
+                    ("months", i64::from(d.months)),
+                    ("days", i64::from(d.days)),
+                    ("nanoseconds", d.nanoseconds),
+                ];
+                let mut map = MapDeserializer::<_, SerdeBridgeError>::new(entries.into_iter());
+                let value = visitor.visit_map(&mut map)?;
+                map.end()?;
+                Ok(value)
+            }
+            CqlValue::Uuid(u) => visitor.visit_string(u.to_string()),
+            CqlValue::Timeuuid(u) => visitor.visit_string(u.to_string()),
+            CqlValue::Inet(ip) => visitor.visit_string(ip.to_string()),
+            CqlValue::List(elements) | CqlValue::Set(elements) => {
+                visit_seq(visitor, elements.iter().map(Some))
+            }
+            CqlValue::Tuple(elements) => visit_seq(visitor, elements.iter().map(Option::as_ref)),
+            CqlValue::Map(entries) => visit_map(
+                visitor,
+                entries
+                    .iter()
+                    .map(|(k, v)| (CqlValueDeserializer(Some(k)), Some(v))),
+            ),
+            CqlValue::UserDefinedType { fields, .. } => visit_map(
+                visitor,
+                fields
+                    .iter()
+                    .map(|(name, value)| (name.as_str(), value.as_ref())),
+            ),
+            CqlValue::Empty => visitor.visit_unit(),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Some(_) => visitor.visit_some(self),
+            None => visitor.visit_none(),
+        }
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self.0 {
+            Some(CqlValue::Ascii(s) | CqlValue::Text(s)) => {
+                visitor.visit_enum(BorrowedStrDeserializer::new(s))
+            }
+            _ => self.deserialize_any(visitor).map_err(|_| {
+                serde::de::Error::custom(format_args!(
+                    "enum {} can only be deserialized from a textual value, \
+                     which must be one of {:?}",
+                    name, variants
+                ))
+            }),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct seq tuple tuple_struct map struct
+        identifier ignored_any
+    }
+}
+
+// `bytes` must be at most 16 bytes long.
+fn signed_be_bytes_to_i128(bytes: &[u8]) -> i128 {
+    let negative = bytes.first().is_some_and(|b| b & 0x80 != 0);
+    let mut buf = if negative { [0xff; 16] } else { [0; 16] };
+    buf[16 - bytes.len()..].copy_from_slice(bytes);
+    i128::from_be_bytes(buf)
+}
+
+// Formats a two's complement, big-endian integer of arbitrary length
+// as a decimal number.
+fn varint_to_string(bytes: &[u8]) -> String {
+    let negative = bytes.first().is_some_and(|b| b & 0x80 != 0);
+    let mut magnitude = bytes.to_vec();
+    if negative {
+        // Negate: invert all bits and add one.
+        for b in magnitude.iter_mut() {
+            *b = !*b;
+        }
+        for b in magnitude.iter_mut().rev() {
+            let (sum, overflow) = b.overflowing_add(1);
+            *b = sum;
+            if !overflow {
+                break;
+            }
+        }
+    }
+
+    // Repeatedly divide the magnitude by 10^9, collecting the remainders.
+    const CHUNK: u64 = 1_000_000_000;
+    let mut chunks = Vec::new();
+    while magnitude.iter().any(|b| *b != 0) {
+        let mut remainder = 0u64;
+        for b in magnitude.iter_mut() {
+            let current = (remainder << 8) | u64::from(*b);
+            *b = (current / CHUNK) as u8;
+            remainder = current % CHUNK;
+        }
+        chunks.push(remainder);
+    }
+
+    let mut result = String::from(if negative { "-" } else { "" });
+    match chunks.split_last() {
+        None => result.push('0'),
+        Some((most_significant, rest)) => {
+            result.push_str(&most_significant.to_string());
+            for chunk in rest.iter().rev() {
+                result.push_str(&format!("{:09}", chunk));
+            }
+        }
+    }
+    result
+}
+
+fn decimal_to_string(bytes: &[u8], scale: i32) -> String {
+    let unscaled = varint_to_string(bytes);
+    let (sign, digits) = match unscaled.strip_prefix('-') {
+        Some(digits) => ("-", digits),
+        None => ("", unscaled.as_str()),
+    };
+    if scale <= 0 {
+        if digits == "0" {
+            return "0".to_owned();
+        }
+        let zeros = "0".repeat(scale.unsigned_abs() as usize);
+        return format!("{}{}{}", sign, digits, zeros);
+    }
+
+    let scale = scale as usize;
+    let digits = if digits.len() <= scale {
+        format!("{}{}", "0".repeat(scale + 1 - digits.len()), digits)
+    } else {
+        digits.to_owned()
+    };
+    let (integral, fractional) = digits.split_at(digits.len() - scale);
+    format!("{}{}.{}", sign, integral, fractional)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::{BTreeMap, HashMap};
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use assert_matches::assert_matches;
+    use bytes::Bytes;
+    use serde::Deserialize;
+    use uuid::Uuid;
+
+    use super::{decimal_to_string, varint_to_string, SerdeValue, SerdeValueError};
+    use crate::frame::response::result::{ColumnType, CqlValue};
+    use crate::frame::value::{CqlDecimal, CqlDuration, CqlVarint};
+    use crate::types::deserialize::value::tests::{deserialize, udt_def_with_fields};
+    use crate::types::serialize::value::SerializeValue;
+    use crate::types::serialize::CellWriter;
+
+    fn serialize(typ: &ColumnType, value: &dyn SerializeValue) -> Bytes {
+        let mut v = Vec::new();
+        value.serialize(typ, CellWriter::new(&mut v)).unwrap();
+        v.into()
+    }
+
+    #[test]
+    fn test_big_number_formatting() {
+        assert_eq!(varint_to_string(&[]), "0");
+        assert_eq!(varint_to_string(&[0x00]), "0");
+        assert_eq!(varint_to_string(&[0x7f]), "127");
+        assert_eq!(varint_to_string(&[0x80]), "-128");
+        assert_eq!(varint_to_string(&[0xff, 0x00]), "-256");
+        assert_eq!(
+            varint_to_string(&i128::MIN.to_be_bytes()),
+            i128::MIN.to_string()
+        );
+        // 2^128
+        let mut bytes = vec![0x01];
+        bytes.extend([0; 16]);
+        assert_eq!(
+            varint_to_string(&bytes),
+            "340282366920938463463374607431768211456"
+        );
+
+        assert_eq!(decimal_to_string(&[0x30, 0x39], 2), "123.45");
+        assert_eq!(decimal_to_string(&[0x30, 0x39], 6), "0.012345");
+        assert_eq!(decimal_to_string(&[0xcf, 0xc7], 2), "-123.45");
+        assert_eq!(decimal_to_string(&[0xcf, 0xc7], 5), "-0.12345");
+        assert_eq!(decimal_to_string(&[0x05], -3), "5000");
+        assert_eq!(decimal_to_string(&[0x00], -3), "0");
+        assert_eq!(decimal_to_string(&[0x00], 2), "0.00");
+    }
+
+    #[test]
+    fn test_scalars() {
+        let bytes = serialize(&ColumnType::Int, &42_i32);
+        let v = deserialize::<SerdeValue<i64>>(&ColumnType::Int, &bytes).unwrap();
+        assert_eq!(v.into_inner(), 42);
+
+        let bytes = serialize(&ColumnType::Text, &"foo");
+        let v = deserialize::<SerdeValue<String>>(&ColumnType::Text, &bytes).unwrap();
+        assert_eq!(v.0, "foo");
+
+        let uuid = Uuid::from_u128(0x1234_5678);
+        let bytes = serialize(&ColumnType::Uuid, &uuid);
+        let v = deserialize::<SerdeValue<String>>(&ColumnType::Uuid, &bytes).unwrap();
+        assert_eq!(v.0, uuid.to_string());
+
+        let ip = IpAddr::V4(Ipv4Addr::new(10, 0, 0, 1));
+        let bytes = serialize(&ColumnType::Inet, &ip);
+        let v = deserialize::<SerdeValue<String>>(&ColumnType::Inet, &bytes).unwrap();
+        assert_eq!(v.0, "10.0.0.1");
+
+        let varint = CqlVarint::from_signed_bytes_be(vec![0x01, 0x00]);
+        let bytes = serialize(&ColumnType::Varint, &varint);
+        let v = deserialize::<SerdeValue<u16>>(&ColumnType::Varint, &bytes).unwrap();
+        assert_eq!(v.0, 256);
+
+        let decimal = CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x30, 0x39], 2);
+        let bytes = serialize(&ColumnType::Decimal, &decimal);
+        let v = deserialize::<SerdeValue<String>>(&ColumnType::Decimal, &bytes).unwrap();
+        assert_eq!(v.0, "123.45");
+
+        // Nulls
+        let bytes = serialize(&ColumnType::Int, &None::<i32>);
+        let v = deserialize::<SerdeValue<Option<i32>>>(&ColumnType::Int, &bytes).unwrap();
+        assert_eq!(v.0, None);
+        let SerdeValue(()) = deserialize::<SerdeValue<()>>(&ColumnType::Int, &bytes).unwrap();
+    }
+
+    #[test]
+    fn test_collections_and_structs() {
+        #[derive(Deserialize, Debug, PartialEq)]
+        #[serde(rename_all = "lowercase")]
+        enum Color {
+            Red,
+            Green,
+        }
+
+        #[derive(Deserialize, Debug, PartialEq)]
+        struct Address {
+            street: String,
+            number: Option<i32>,
+            tags: Vec<String>,
+            color: Color,
+        }
+
+        let list_typ = ColumnType::List(Box::new(ColumnType::BigInt));
+        let bytes = serialize(&list_typ, &vec![1_i64, 2, 3]);
+        let v = deserialize::<SerdeValue<Vec<i64>>>(&list_typ, &bytes).unwrap();
+        assert_eq!(v.0, vec![1, 2, 3]);
+
+        let map_typ = ColumnType::Map(Box::new(ColumnType::Text), Box::new(ColumnType::Int));
+        let map = BTreeMap::from([("a", 1_i32), ("b", 2)]);
+        let bytes = serialize(&map_typ, &map);
+        let v = deserialize::<SerdeValue<HashMap<String, i32>>>(&map_typ, &bytes).unwrap();
+        assert_eq!(
+            v.0,
+            HashMap::from([("a".to_owned(), 1), ("b".to_owned(), 2)])
+        );
+
+        let tuple_typ = ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Text]);
+        let bytes = serialize(&tuple_typ, &(7_i32, "seven"));
+        let v = deserialize::<SerdeValue<(i32, String)>>(&tuple_typ, &bytes).unwrap();
+        assert_eq!(v.0, (7, "seven".to_owned()));
+
+        let duration = CqlDuration {
+            months: 1,
+            days: 2,
+            nanoseconds: 3,
+        };
+        let bytes = serialize(&ColumnType::Duration, &duration);
+        let v = deserialize::<SerdeValue<BTreeMap<String, i64>>>(&ColumnType::Duration, &bytes)
+            .unwrap();
+        assert_eq!(
+            v.0,
+            BTreeMap::from([
+                ("days".to_owned(), 2),
+                ("months".to_owned(), 1),
+                ("nanoseconds".to_owned(), 3)
+            ])
+        );
+
+        let udt_typ = udt_def_with_fields([
+            ("street", ColumnType::Text),
+            ("number", ColumnType::Int),
+            ("tags", ColumnType::Set(Box::new(ColumnType::Text))),
+            ("color", ColumnType::Ascii),
+        ]);
+        let udt = CqlValue::UserDefinedType {
+            keyspace: "ks".to_owned(),
+            type_name: "udt".to_owned(),
+            fields: vec![
+                (
+                    "street".to_owned(),
+                    Some(CqlValue::Text("Main St".to_owned())),
+                ),
+                ("number".to_owned(), None),
+                (
+                    "tags".to_owned(),
+                    Some(CqlValue::Set(vec![CqlValue::Text("home".to_owned())])),
+                ),
+                (
+                    "color".to_owned(),
+                    Some(CqlValue::Ascii("green".to_owned())),
+                ),
+            ],
+        };
+        let bytes = serialize(&udt_typ, &udt);
+        let v = deserialize::<SerdeValue<Address>>(&udt_typ, &bytes).unwrap();
+        assert_eq!(
+            v.0,
+            Address {
+                street: "Main St".to_owned(),
+                number: None,
+                tags: vec!["home".to_owned()],
+                color: Color::Green,
+            }
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let bytes = serialize(&ColumnType::Text, &"foo");
+        let err = deserialize::<SerdeValue<i32>>(&ColumnType::Text, &bytes).unwrap_err();
+        let err = err.0.downcast_ref::<SerdeValueError>().unwrap();
+        assert_eq!(err.rust_name, std::any::type_name::<i32>());
+        assert_eq!(err.cql_type, ColumnType::Text);
+
+        let bytes = serialize(&ColumnType::Int, &300_i32);
+        let err = deserialize::<SerdeValue<u8>>(&ColumnType::Int, &bytes).unwrap_err();
+        assert_matches!(err.0.downcast_ref::<SerdeValueError>(), Some(_));
+
+        let list_typ = ColumnType::List(Box::new(ColumnType::Int));
+        let bytes = serialize(&list_typ, &vec![1_i32, 2, 3]);
+        let err = deserialize::<SerdeValue<(i32, i32)>>(&list_typ, &bytes).unwrap_err();
+        assert_matches!(err.0.downcast_ref::<SerdeValueError>(), Some(_));
+    }
+}
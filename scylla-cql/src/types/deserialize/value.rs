@@ -4,6 +4,7 @@ use std::{
     borrow::Cow,
     collections::{BTreeMap, BTreeSet, HashMap, HashSet},
     hash::{BuildHasher, Hash},
+    marker::PhantomData,
     net::IpAddr,
 };
 
@@ -17,7 +18,8 @@ use thiserror::Error;
 use super::{make_error_replace_rust_name, DeserializationError, FrameSlice, TypeCheckError};
 use crate::frame::types;
 use crate::frame::value::{
-    Counter, CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlTimeuuid, CqlVarint,
+    Counter, CqlDate, CqlDecimal, CqlDuration, CqlStr, CqlTime, CqlTimestamp, CqlTimeuuid,
+    CqlVarint, RawCell,
 };
 use crate::frame::{frame_errors::LowLevelDeserializationError, value::CqlVarintBorrowed};
 use crate::frame::{
@@ -69,6 +71,23 @@ impl<'frame, 'metadata> DeserializeValue<'frame, 'metadata> for CqlValue {
     }
 }
 
+impl<'frame, 'metadata> DeserializeValue<'frame, 'metadata> for RawCell<'frame> {
+    fn type_check(_typ: &ColumnType) -> Result<(), TypeCheckError> {
+        // RawCell accepts all possible CQL types
+        Ok(())
+    }
+
+    fn deserialize(
+        _typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        Ok(match v {
+            Some(slice) => RawCell::new(slice.as_slice()),
+            None => RawCell::null(),
+        })
+    }
+}
+
 // Option represents nullability of CQL values:
 // None corresponds to null,
 // Some(val) to non-null values.
@@ -132,6 +151,79 @@ where
     }
 }
 
+/// A value of an [Emptiable] type, which is deserialized as `T::default()`
+/// if it is empty.
+///
+/// Useful for columns in which empty values have the same meaning as
+/// some value of the Rust type, e.g. 0. A null value is still an error;
+/// use `Option<EmptyAsDefault<T>>` to accept it.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct EmptyAsDefault<T>(pub T);
+
+impl<T> EmptyAsDefault<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'frame, 'metadata, T> DeserializeValue<'frame, 'metadata> for EmptyAsDefault<T>
+where
+    T: DeserializeValue<'frame, 'metadata> + Emptiable + Default,
+{
+    #[inline]
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        <T as DeserializeValue<'frame, 'metadata>>::type_check(typ)
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let val = ensure_not_null_slice::<Self>(typ, v)?;
+        if val.is_empty() {
+            Ok(EmptyAsDefault(T::default()))
+        } else {
+            <T as DeserializeValue<'frame, 'metadata>>::deserialize(typ, v).map(EmptyAsDefault)
+        }
+    }
+}
+
+/// A value of an [Emptiable] type, which is deserialized as `None`
+/// if it is either empty or null.
+///
+/// Useful for columns in which empty values have the same meaning as null.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct EmptyAsNone<T>(pub Option<T>);
+
+impl<T> EmptyAsNone<T> {
+    pub fn into_inner(self) -> Option<T> {
+        self.0
+    }
+}
+
+impl<'frame, 'metadata, T> DeserializeValue<'frame, 'metadata> for EmptyAsNone<T>
+where
+    T: DeserializeValue<'frame, 'metadata> + Emptiable,
+{
+    #[inline]
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        <T as DeserializeValue<'frame, 'metadata>>::type_check(typ)
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        match v {
+            Some(slice) if !slice.is_empty() => {
+                <T as DeserializeValue<'frame, 'metadata>>::deserialize(typ, v)
+                    .map(|v| EmptyAsNone(Some(v)))
+            }
+            _ => Ok(EmptyAsNone(None)),
+        }
+    }
+}
+
 macro_rules! impl_strict_type {
     ($t:ty, [$($cql:ident)|+], $conv:expr $(, $l:lifetime)?) => {
         impl<$($l,)? 'frame, 'metadata> DeserializeValue<'frame, 'metadata> for $t
@@ -381,8 +473,250 @@ impl_string_type!(
         Ok(s.to_string())
     }
 );
+impl_string_type!(
+    CqlStr,
+    |typ: &'metadata ColumnType<'metadata>, v: Option<FrameSlice<'frame>>| {
+        let val = ensure_not_null_owned::<Self>(typ, v)?;
+        check_ascii::<CqlStr>(typ, &val)?;
+        CqlStr::from_utf8(val).map_err(|err| {
+            mk_deser_err::<Self>(typ, BuiltinDeserializationErrorKind::InvalidUtf8(err))
+        })
+    }
+);
+
+/// A value stored in a text column and parsed with [`FromStr`](std::str::FromStr)
+/// upon deserialization.
+///
+/// This is handy for types which have a canonical textual representation,
+/// e.g. typed identifiers. If parsing fails, the parse error is returned
+/// in [`BuiltinDeserializationErrorKind::ParseFailed`].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct Parsed<T>(pub T);
+
+impl<T> Parsed<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'frame, 'metadata, T> DeserializeValue<'frame, 'metadata> for Parsed<T>
+where
+    T: std::str::FromStr,
+    T::Err: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        exact_type_check!(typ, Ascii, Text);
+        Ok(())
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let s = <&str as DeserializeValue<'frame, 'metadata>>::deserialize(typ, v)
+            .map_err(deser_error_replace_rust_name::<Self>)?;
+        s.parse().map(Parsed).map_err(|err: T::Err| {
+            mk_deser_err::<Self>(
+                typ,
+                BuiltinDeserializationErrorKind::ParseFailed(err.into()),
+            )
+        })
+    }
+}
+
+/// A value deserialized as `Raw` and then converted to `T` with [`TryFrom`].
+///
+/// This allows domain types which uphold some invariants, e.g. a non-empty
+/// string or a validated e-mail address, to be used directly as fields of
+/// deserialized rows and UDTs. If the conversion fails, the conversion error
+/// is returned in [`BuiltinDeserializationErrorKind::ConversionFailed`].
+///
+/// ```rust
+/// # use scylla_cql::types::deserialize::value::Via;
+/// struct NonEmptyString(String);
+///
+/// impl TryFrom<String> for NonEmptyString {
+///     type Error = &'static str;
+///
+///     fn try_from(s: String) -> Result<Self, Self::Error> {
+///         if s.is_empty() {
+///             Err("the string is empty")
+///         } else {
+///             Ok(NonEmptyString(s))
+///         }
+///     }
+/// }
+///
+/// // Deserialize a text column as `Via<String, NonEmptyString>`.
+/// # fn check(_: Via<String, NonEmptyString>) {}
+/// ```
+pub struct Via<Raw, T>(pub T, PhantomData<fn() -> Raw>);
+
+impl<Raw, T> Via<Raw, T> {
+    pub fn new(value: T) -> Self {
+        Via(value, PhantomData)
+    }
+
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+// Implemented by hand, so that no bounds are put on `Raw`.
+impl<Raw, T: std::fmt::Debug> std::fmt::Debug for Via<Raw, T> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("Via").field(&self.0).finish()
+    }
+}
+
+impl<Raw, T: Clone> Clone for Via<Raw, T> {
+    fn clone(&self) -> Self {
+        Via::new(self.0.clone())
+    }
+}
+
+impl<Raw, T: PartialEq> PartialEq for Via<Raw, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.0 == other.0
+    }
+}
+
+impl<Raw, T: Eq> Eq for Via<Raw, T> {}
+
+impl<'frame, 'metadata, Raw, T> DeserializeValue<'frame, 'metadata> for Via<Raw, T>
+where
+    Raw: DeserializeValue<'frame, 'metadata>,
+    T: TryFrom<Raw>,
+    T::Error: Into<Box<dyn std::error::Error + Send + Sync>>,
+{
+    #[inline]
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        <Raw as DeserializeValue<'frame, 'metadata>>::type_check(typ)
+            .map_err(typck_error_replace_rust_name::<Self>)
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let raw = <Raw as DeserializeValue<'frame, 'metadata>>::deserialize(typ, v)
+            .map_err(deser_error_replace_rust_name::<Self>)?;
+        T::try_from(raw).map(Via::new).map_err(|err: T::Error| {
+            mk_deser_err::<Self>(
+                typ,
+                BuiltinDeserializationErrorKind::ConversionFailed(err.into()),
+            )
+        })
+    }
+}
+
+// custom types
+
+/// A decoder of values of custom types, i.e. types implemented on the server side
+/// by a Java class which the protocol does not know. Columns of such types are
+/// described by [`ColumnType::Custom`], holding the fully qualified class name.
+///
+/// Implementing the trait registers the Rust type as the decoder of the custom types
+/// listed in [`CLASS_NAMES`](CustomTypeDeserializer::CLASS_NAMES). Values of such
+/// columns are then deserialized through the [`Custom`] wrapper, e.g. as
+/// `Custom<MyType>`, or `Option<Custom<MyType>>` if the column can be null.
+///
+/// Duration columns are recognized by the driver, so they are not described
+/// as custom types, but they can be decoded by a decoder registered for
+/// `org.apache.cassandra.db.marshal.DurationType` as well.
+///
+/// ```rust
+/// # use bytes::Bytes;
+/// # use scylla_cql::frame::response::result::ColumnType;
+/// # use scylla_cql::types::deserialize::DeserializationError;
+/// # use scylla_cql::types::deserialize::value::{Custom, CustomTypeDeserializer};
+/// # use scylla_cql::types::deserialize::FrameSlice;
+/// # use scylla_cql::types::deserialize::value::DeserializeValue;
+/// struct Point3D(i32, i32, i32);
+///
+/// impl<'frame> CustomTypeDeserializer<'frame> for Point3D {
+///     const CLASS_NAMES: &'static [&'static str] = &["com.example.db.marshal.Point3DType"];
+///
+///     fn deserialize_custom(
+///         _class_name: &str,
+///         bytes: &'frame [u8],
+///     ) -> Result<Self, DeserializationError> {
+///         if bytes.len() != 12 {
+///             return Err(DeserializationError::new(std::fmt::Error));
+///         }
+///         let coordinate = |i: usize| i32::from_be_bytes(bytes[4 * i..4 * i + 4].try_into().unwrap());
+///         Ok(Point3D(coordinate(0), coordinate(1), coordinate(2)))
+///     }
+/// }
+///
+/// let typ = ColumnType::Custom("com.example.db.marshal.Point3DType".into());
+/// let bytes = Bytes::from_static(&[0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0, 3]);
+/// Custom::<Point3D>::type_check(&typ).unwrap();
+/// let Custom(Point3D(x, y, z)) =
+///     Custom::<Point3D>::deserialize(&typ, Some(FrameSlice::new(&bytes))).unwrap();
+/// assert_eq!((x, y, z), (1, 2, 3));
+/// ```
+pub trait CustomTypeDeserializer<'frame>: Sized {
+    /// The fully qualified names of the Java classes implementing
+    /// the custom types which this decoder handles.
+    const CLASS_NAMES: &'static [&'static str];
+
+    /// Decodes a non-null value of the custom type with the given class name,
+    /// which is one of [`CLASS_NAMES`](CustomTypeDeserializer::CLASS_NAMES).
+    fn deserialize_custom(
+        class_name: &str,
+        bytes: &'frame [u8],
+    ) -> Result<Self, DeserializationError>;
+}
+
+const DURATION_CLASS_NAME: &str = "org.apache.cassandra.db.marshal.DurationType";
+
+// Returns the name of the Java class implementing the type
+// if it can be handled by a custom type decoder.
+fn custom_class_name<'a>(typ: &'a ColumnType) -> Option<&'a str> {
+    match typ {
+        ColumnType::Custom(class_name) => Some(class_name),
+        ColumnType::Duration => Some(DURATION_CLASS_NAME),
+        _ => None,
+    }
+}
+
+/// A value of a custom type, decoded by `T`; see [`CustomTypeDeserializer`].
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Clone, Copy, Default)]
+pub struct Custom<T>(pub T);
 
-// TODO: Consider support for deserialization of string::String<Bytes>
+impl<T> Custom<T> {
+    pub fn into_inner(self) -> T {
+        self.0
+    }
+}
+
+impl<'frame, 'metadata, T> DeserializeValue<'frame, 'metadata> for Custom<T>
+where
+    T: CustomTypeDeserializer<'frame>,
+{
+    fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+        match custom_class_name(typ) {
+            Some(class_name) if T::CLASS_NAMES.contains(&class_name) => Ok(()),
+            _ => Err(mk_typck_err::<Self>(
+                typ,
+                BuiltinTypeCheckErrorKind::MismatchedCustomType {
+                    expected: T::CLASS_NAMES,
+                },
+            )),
+        }
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        let bytes = ensure_not_null_slice::<Self>(typ, v)?;
+        // Checked by type_check.
+        let class_name = custom_class_name(typ).unwrap_or_default();
+        T::deserialize_custom(class_name, bytes).map(Custom)
+    }
+}
 
 // counter
 
@@ -1086,6 +1420,7 @@ macro_rules! impl_tuple {
                         typ,
                         TupleTypeCheckErrorKind::FieldTypeCheckFailed {
                             position: $idx,
+                            field_type: $idf.clone().into_owned(),
                             err,
                         }
                     ))?;
@@ -1111,6 +1446,7 @@ macro_rules! impl_tuple {
                                 typ,
                                 TupleDeserializationErrorKind::FieldDeserializationFailed {
                                     position: $idx,
+                                    field_type: $idf.clone().into_owned(),
                                     err,
                                 }
                             )
@@ -1471,6 +1807,13 @@ pub enum BuiltinTypeCheckErrorKind {
         expected: &'static [ColumnType<'static>],
     },
 
+    /// Expected a custom type implemented by one from a list of Java classes.
+    MismatchedCustomType {
+        /// The names of the classes implementing the custom types
+        /// that the Rust type can deserialize from.
+        expected: &'static [&'static str],
+    },
+
     /// A type check failure specific to a CQL set or list.
     SetOrListError(SetOrListTypeCheckErrorKind),
 
@@ -1518,6 +1861,9 @@ impl Display for BuiltinTypeCheckErrorKind {
             BuiltinTypeCheckErrorKind::MismatchedType { expected } => {
                 write!(f, "expected one of the CQL types: {expected:?}")
             }
+            BuiltinTypeCheckErrorKind::MismatchedCustomType { expected } => {
+                write!(f, "expected one of the custom types: {expected:?}")
+            }
             BuiltinTypeCheckErrorKind::SetOrListError(err) => err.fmt(f),
             BuiltinTypeCheckErrorKind::MapError(err) => err.fmt(f),
             BuiltinTypeCheckErrorKind::TupleError(err) => err.fmt(f),
@@ -1599,10 +1945,14 @@ pub enum TupleTypeCheckErrorKind {
     },
 
     /// The CQL type and the Rust type of a tuple field failed to type check against each other.
+    #[non_exhaustive]
     FieldTypeCheckFailed {
         /// The index of the field whose type check failed.
         position: usize,
 
+        /// The CQL type of the field whose type check failed.
+        field_type: ColumnType<'static>,
+
         /// The type check error that occurred.
         err: TypeCheckError,
     },
@@ -1623,9 +1973,10 @@ impl Display for TupleTypeCheckErrorKind {
                 "wrong tuple element count: CQL type has {cql_type_el_count}, the Rust tuple has {rust_type_el_count}"
             ),
 
-            TupleTypeCheckErrorKind::FieldTypeCheckFailed { position, err } => write!(
+            TupleTypeCheckErrorKind::FieldTypeCheckFailed { position, field_type, err } => write!(
                 f,
-                "the CQL type and the Rust type of the tuple field {} failed to type check against each other: {}",
+                "the CQL type ({:?}) and the Rust type of the tuple field {} failed to type check against each other: {}",
+                field_type,
                 position,
                 err
             )
@@ -1640,6 +1991,15 @@ pub enum UdtTypeCheckErrorKind {
     /// The CQL type is not a user defined type.
     NotUdt,
 
+    /// The name or the keyspace of the UDT does not match those expected by the Rust type.
+    NameMismatch {
+        /// Keyspace in which the UDT was defined.
+        keyspace: String,
+
+        /// Name of the UDT.
+        type_name: String,
+    },
+
     /// The CQL UDT type does not have some fields that is required in the Rust struct.
     ValuesMissingForUdtFields {
         /// Names of fields that the Rust struct requires but are missing in the CQL UDT.
@@ -1694,6 +2054,13 @@ impl Display for UdtTypeCheckErrorKind {
                 f,
                 "the CQL type the Rust type was attempted to be type checked against is not a UDT"
             ),
+            UdtTypeCheckErrorKind::NameMismatch {
+                keyspace,
+                type_name,
+            } => write!(
+                f,
+                "the Rust UDT name does not match the actual CQL UDT name ({keyspace}.{type_name})"
+            ),
             UdtTypeCheckErrorKind::ValuesMissingForUdtFields { field_names } => {
                 write!(f, "the fields {field_names:?} are missing from the DB data but are required by the Rust type")
             },
@@ -1793,6 +2160,12 @@ pub enum BuiltinDeserializationErrorKind {
     /// Invalid UTF-8 string.
     InvalidUtf8(std::str::Utf8Error),
 
+    /// Failed to parse the string into the Rust type.
+    ParseFailed(Box<dyn std::error::Error + Send + Sync>),
+
+    /// Failed to convert the deserialized value into the Rust type.
+    ConversionFailed(Box<dyn std::error::Error + Send + Sync>),
+
     /// The read value is out of range supported by the Rust type.
     // TODO: consider storing additional info here (what exactly did not fit and why)
     ValueOverflow,
@@ -1811,6 +2184,12 @@ pub enum BuiltinDeserializationErrorKind {
 
     /// A deserialization failure specific to a CQL UDT.
     UdtError(UdtDeserializationErrorKind),
+
+    /// The value does not correspond to any variant of the Rust enum.
+    UnknownVariant {
+        /// The received value: the variant name or the discriminant.
+        value: String,
+    },
 }
 
 impl Display for BuiltinDeserializationErrorKind {
@@ -1831,6 +2210,12 @@ impl Display for BuiltinDeserializationErrorKind {
                 f.write_str("expected a valid ASCII string")
             }
             BuiltinDeserializationErrorKind::InvalidUtf8(err) => err.fmt(f),
+            BuiltinDeserializationErrorKind::ParseFailed(err) => {
+                write!(f, "failed to parse the string: {}", err)
+            }
+            BuiltinDeserializationErrorKind::ConversionFailed(err) => {
+                write!(f, "failed to convert the value: {}", err)
+            }
             BuiltinDeserializationErrorKind::ValueOverflow => {
                 // TODO: consider storing Arc<dyn Display/Debug> of the offending value
                 // inside this variant for debug purposes.
@@ -1845,6 +2230,9 @@ impl Display for BuiltinDeserializationErrorKind {
             BuiltinDeserializationErrorKind::TupleError(err) => err.fmt(f),
             BuiltinDeserializationErrorKind::UdtError(err) => err.fmt(f),
             BuiltinDeserializationErrorKind::CustomTypeNotSupported(typ) => write!(f, "Support for custom types is not yet implemented: {}", typ),
+            BuiltinDeserializationErrorKind::UnknownVariant { value } => {
+                write!(f, "the value {} does not correspond to any enum variant", value)
+            }
         }
     }
 }
@@ -1921,10 +2309,14 @@ impl From<MapDeserializationErrorKind> for BuiltinDeserializationErrorKind {
 #[non_exhaustive]
 pub enum TupleDeserializationErrorKind {
     /// One of the tuple fields failed to deserialize.
+    #[non_exhaustive]
     FieldDeserializationFailed {
         /// Index of the tuple field that failed to deserialize.
         position: usize,
 
+        /// The CQL type of the tuple field that failed to deserialize.
+        field_type: ColumnType<'static>,
+
         /// The error that caused the tuple field deserialization to fail.
         err: DeserializationError,
     },
@@ -1935,9 +2327,13 @@ impl Display for TupleDeserializationErrorKind {
         match self {
             TupleDeserializationErrorKind::FieldDeserializationFailed {
                 position: index,
+                field_type,
                 err,
             } => {
-                write!(f, "field no. {index} failed to deserialize: {err}")
+                write!(
+                    f,
+                    "field no. {index} of CQL type {field_type:?} failed to deserialize: {err}"
+                )
             }
         }
     }
@@ -10,20 +10,24 @@ use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
 
 use crate::frame::response::result::{ColumnType, CqlValue};
 use crate::frame::value::{
-    Counter, CqlDate, CqlDecimal, CqlDecimalBorrowed, CqlDuration, CqlTime, CqlTimestamp,
-    CqlTimeuuid, CqlVarint, CqlVarintBorrowed,
+    Counter, CqlDate, CqlDecimal, CqlDecimalBorrowed, CqlDuration, CqlStr, CqlTime, CqlTimestamp,
+    CqlTimeuuid, CqlVarint, CqlVarintBorrowed, RawCell,
 };
 use crate::types::deserialize::value::{TupleDeserializationErrorKind, TupleTypeCheckErrorKind};
 use crate::types::deserialize::{DeserializationError, FrameSlice, TypeCheckError};
-use crate::types::serialize::value::SerializeValue;
+use crate::types::serialize::value::{
+    BuiltinSerializationError, BuiltinSerializationErrorKind,
+    BuiltinTypeCheckError as SerBuiltinTypeCheckError,
+    BuiltinTypeCheckErrorKind as SerBuiltinTypeCheckErrorKind, SerializeValue,
+};
 use crate::types::serialize::CellWriter;
 
 use super::{
     mk_deser_err, BuiltinDeserializationError, BuiltinDeserializationErrorKind,
-    BuiltinTypeCheckError, BuiltinTypeCheckErrorKind, DeserializeValue, ListlikeIterator,
-    MapDeserializationErrorKind, MapIterator, MapTypeCheckErrorKind, MaybeEmpty,
-    SetOrListDeserializationErrorKind, SetOrListTypeCheckErrorKind, UdtDeserializationErrorKind,
-    UdtTypeCheckErrorKind,
+    BuiltinTypeCheckError, BuiltinTypeCheckErrorKind, Custom, CustomTypeDeserializer,
+    DeserializeValue, EmptyAsDefault, EmptyAsNone, ListlikeIterator, MapDeserializationErrorKind,
+    MapIterator, MapTypeCheckErrorKind, MaybeEmpty, Parsed, SetOrListDeserializationErrorKind,
+    SetOrListTypeCheckErrorKind, UdtDeserializationErrorKind, UdtTypeCheckErrorKind, Via,
 };
 
 #[test]
@@ -58,9 +62,11 @@ fn test_deserialize_ascii() {
     for typ in [ColumnType::Ascii, ColumnType::Text].iter() {
         let decoded_str = deserialize::<&str>(typ, &ascii).unwrap();
         let decoded_string = deserialize::<String>(typ, &ascii).unwrap();
+        let decoded_cql_str = deserialize::<CqlStr>(typ, &ascii).unwrap();
 
         assert_eq!(decoded_str, ASCII_TEXT);
         assert_eq!(decoded_string, ASCII_TEXT);
+        assert_eq!(decoded_cql_str, ASCII_TEXT);
 
         // ser/de identity
 
@@ -71,6 +77,7 @@ fn test_deserialize_ascii() {
         // Nonempty string
         assert_ser_de_identity(typ, &ASCII_TEXT, &mut Bytes::new());
         assert_ser_de_identity(typ, &ASCII_TEXT.to_owned(), &mut Bytes::new());
+        assert_ser_de_identity(typ, &CqlStr::from(ASCII_TEXT), &mut Bytes::new());
     }
 }
 
@@ -83,12 +90,21 @@ fn test_deserialize_text() {
     // Should fail because it's not an ASCII string
     deserialize::<&str>(&ColumnType::Ascii, &unicode).unwrap_err();
     deserialize::<String>(&ColumnType::Ascii, &unicode).unwrap_err();
+    deserialize::<CqlStr>(&ColumnType::Ascii, &unicode).unwrap_err();
 
     let decoded_text_str = deserialize::<&str>(&ColumnType::Text, &unicode).unwrap();
     let decoded_text_string = deserialize::<String>(&ColumnType::Text, &unicode).unwrap();
     assert_eq!(decoded_text_str, UNICODE_TEXT);
     assert_eq!(decoded_text_string, UNICODE_TEXT);
 
+    // CqlStr shares the buffer of the frame instead of copying the value out of it.
+    let decoded_text_cql_str = deserialize::<CqlStr>(&ColumnType::Text, &unicode).unwrap();
+    assert_eq!(decoded_text_cql_str, UNICODE_TEXT);
+    assert_eq!(
+        decoded_text_cql_str.as_bytes().as_ptr(),
+        unicode[4..].as_ptr()
+    );
+
     // ser/de identity
 
     assert_ser_de_identity(&ColumnType::Text, &UNICODE_TEXT, &mut Bytes::new());
@@ -352,6 +368,167 @@ fn test_maybe_empty() {
     assert_eq!(decoded_non_empty, MaybeEmpty::Value(0x01));
 }
 
+#[test]
+fn test_empty_policies() {
+    let empty = make_bytes(&[]);
+    let non_empty = make_bytes(&[0x01]);
+    let null = make_null();
+
+    let decoded = deserialize::<EmptyAsDefault<i8>>(&ColumnType::TinyInt, &empty).unwrap();
+    assert_eq!(decoded, EmptyAsDefault(0));
+    let decoded = deserialize::<EmptyAsDefault<i8>>(&ColumnType::TinyInt, &non_empty).unwrap();
+    assert_eq!(decoded.into_inner(), 0x01);
+    let decoded = deserialize::<Option<EmptyAsDefault<i8>>>(&ColumnType::TinyInt, &null).unwrap();
+    assert_eq!(decoded, None);
+
+    let decoded = deserialize::<EmptyAsNone<i8>>(&ColumnType::TinyInt, &empty).unwrap();
+    assert_eq!(decoded, EmptyAsNone(None));
+    let decoded = deserialize::<EmptyAsNone<i8>>(&ColumnType::TinyInt, &null).unwrap();
+    assert_eq!(decoded, EmptyAsNone(None));
+    let decoded = deserialize::<EmptyAsNone<i8>>(&ColumnType::TinyInt, &non_empty).unwrap();
+    assert_eq!(decoded.into_inner(), Some(0x01));
+
+    // Works in collections, where the null representation is not allowed.
+    let mut list = BytesMut::new();
+    list.put_i32(2);
+    append_bytes(&mut list, &[]);
+    append_bytes(&mut list, &[0x00, 0x00, 0x00, 0x07]);
+    let list = make_bytes(&list);
+    let list_typ = ColumnType::List(Box::new(ColumnType::Int));
+    let decoded = deserialize::<Vec<EmptyAsDefault<i32>>>(&list_typ, &list).unwrap();
+    assert_eq!(decoded, vec![EmptyAsDefault(0), EmptyAsDefault(7)]);
+    let decoded = deserialize::<Vec<EmptyAsNone<i32>>>(&list_typ, &list).unwrap();
+    assert_eq!(decoded, vec![EmptyAsNone(None), EmptyAsNone(Some(7))]);
+}
+
+#[test]
+fn test_parsed() {
+    let number = make_bytes(b"1234");
+    for typ in [ColumnType::Ascii, ColumnType::Text] {
+        let decoded = deserialize::<Parsed<u32>>(&typ, &number).unwrap();
+        assert_eq!(decoded, Parsed(1234));
+    }
+
+    let addr = make_bytes(b"127.0.0.1");
+    let decoded = deserialize::<Parsed<IpAddr>>(&ColumnType::Text, &addr).unwrap();
+    assert_eq!(decoded.into_inner(), IpAddr::V4(Ipv4Addr::LOCALHOST));
+
+    let decoded = deserialize::<Option<Parsed<u32>>>(&ColumnType::Text, &make_null()).unwrap();
+    assert_eq!(decoded, None);
+}
+
+#[derive(Debug, PartialEq, Eq)]
+struct NonEmptyString(String);
+
+impl TryFrom<String> for NonEmptyString {
+    type Error = &'static str;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        if s.is_empty() {
+            Err("the string is empty")
+        } else {
+            Ok(NonEmptyString(s))
+        }
+    }
+}
+
+#[test]
+fn test_via() {
+    let name = make_bytes(b"alice");
+    let decoded = deserialize::<Via<String, NonEmptyString>>(&ColumnType::Text, &name).unwrap();
+    assert_eq!(decoded.into_inner(), NonEmptyString("alice".to_owned()));
+
+    // Standard library conversions work as well.
+    let number = make_bytes(&7i64.to_be_bytes());
+    let decoded = deserialize::<Via<i64, u8>>(&ColumnType::BigInt, &number).unwrap();
+    assert_eq!(decoded, Via::new(7));
+
+    let decoded =
+        deserialize::<Option<Via<String, NonEmptyString>>>(&ColumnType::Text, &make_null())
+            .unwrap();
+    assert_eq!(decoded, None);
+}
+
+#[cfg(feature = "secrecy-08")]
+#[test]
+fn test_secrecy() {
+    use secrecy_08::{ExposeSecret, SecretString, SecretVec};
+
+    let password = make_bytes(b"hunter2");
+    let decoded = deserialize::<SecretString>(&ColumnType::Text, &password).unwrap();
+    assert_eq!(decoded.expose_secret(), "hunter2");
+    assert!(!format!("{:?}", decoded).contains("hunter2"));
+
+    let decoded = deserialize::<SecretVec<u8>>(&ColumnType::Blob, &password).unwrap();
+    assert_eq!(decoded.expose_secret(), b"hunter2");
+
+    // Neither do errors disclose the value.
+    let invalid = make_bytes(b"hunter2\xff");
+    for typ in [ColumnType::Ascii, ColumnType::Text] {
+        let err = deserialize::<SecretString>(&typ, &invalid).unwrap_err();
+        assert!(!format!("{} {:?}", err, err).contains("hunter"));
+    }
+}
+
+// A decoder of vendor types, borrowing the value from the frame.
+#[derive(Debug, PartialEq, Eq)]
+struct VendorBytes<'frame> {
+    class_name: String,
+    bytes: &'frame [u8],
+}
+
+impl<'frame> CustomTypeDeserializer<'frame> for VendorBytes<'frame> {
+    const CLASS_NAMES: &'static [&'static str] = &[
+        "com.example.db.marshal.FooType",
+        "com.example.db.marshal.BarType",
+        "org.apache.cassandra.db.marshal.DurationType",
+    ];
+
+    fn deserialize_custom(
+        class_name: &str,
+        bytes: &'frame [u8],
+    ) -> Result<Self, DeserializationError> {
+        if bytes == b"invalid" {
+            return Err(DeserializationError::new(std::fmt::Error));
+        }
+        Ok(VendorBytes {
+            class_name: class_name.to_owned(),
+            bytes,
+        })
+    }
+}
+
+#[test]
+fn test_custom_types() {
+    let value = make_bytes(&[1, 2, 3]);
+    for class_name in [
+        "com.example.db.marshal.FooType",
+        "com.example.db.marshal.BarType",
+    ] {
+        let typ = ColumnType::Custom(class_name.into());
+        let decoded = deserialize::<Custom<VendorBytes>>(&typ, &value).unwrap();
+        assert_eq!(
+            decoded.into_inner(),
+            VendorBytes {
+                class_name: class_name.to_owned(),
+                bytes: &[1, 2, 3]
+            }
+        );
+    }
+
+    // Durations are recognized by the driver, but can be decoded by a custom decoder too.
+    let decoded = deserialize::<Custom<VendorBytes>>(&ColumnType::Duration, &value).unwrap();
+    assert_eq!(
+        decoded.0.class_name,
+        "org.apache.cassandra.db.marshal.DurationType"
+    );
+
+    let typ = ColumnType::Custom("com.example.db.marshal.FooType".into());
+    let null = make_null();
+    let decoded = deserialize::<Option<Custom<VendorBytes>>>(&typ, &null).unwrap();
+    assert_eq!(decoded, None);
+}
+
 #[test]
 fn test_cql_value() {
     assert_ser_de_identity(
@@ -386,6 +563,33 @@ fn test_cql_value() {
     );
 }
 
+#[test]
+fn test_raw_cell() {
+    // Any type is accepted, and the bytes are passed through verbatim.
+    let list_type = ColumnType::List(Box::new(ColumnType::Int));
+    let list = serialize(&list_type, &vec![1_i32, 2, 3]);
+    let raw = deserialize::<RawCell>(&list_type, &list).unwrap();
+    assert_eq!(raw.as_bytes(), Some(&list[4..]));
+    assert_eq!(serialize(&list_type, &raw), list);
+
+    // The bytes are not validated against the type.
+    let text = make_bytes(b"kremowka");
+    let raw = deserialize::<RawCell>(&ColumnType::Int, &text).unwrap();
+    assert_eq!(raw, RawCell::new(b"kremowka"));
+    assert_eq!(serialize(&ColumnType::Int, &raw), text);
+
+    // Empty values are distinct from nulls.
+    let empty = make_bytes(&[]);
+    let raw = deserialize::<RawCell>(&ColumnType::Int, &empty).unwrap();
+    assert_eq!(raw, RawCell::new(&[]));
+    assert_eq!(serialize(&ColumnType::Int, &raw), empty);
+
+    let null = make_null();
+    let raw = deserialize::<RawCell>(&ColumnType::Int, &null).unwrap();
+    assert!(raw.is_null());
+    assert_eq!(serialize(&ColumnType::Int, &raw), null);
+}
+
 #[test]
 fn test_list_and_set() {
     let mut collection_contents = BytesMut::new();
@@ -598,6 +802,52 @@ fn test_tuples() {
     );
 }
 
+#[test]
+fn test_tuple_max_arity() {
+    type Tuple16 = (
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+        i32,
+    );
+
+    let typ = ColumnType::Tuple(vec![ColumnType::Int; 16]);
+    let v: Tuple16 = (0, 1, 2, 3, 4, 5, 6, 7, 8, 9, 10, 11, 12, 13, 14, 15);
+    let bytes = serialize(&typ, &v);
+    let decoded = deserialize::<Tuple16>(&typ, &bytes).unwrap();
+    // Tuples of this size implement neither PartialEq nor Debug.
+    assert_eq!((decoded.0, decoded.7, decoded.15), (0, 7, 15));
+
+    // The error points at the last element, together with its CQL type.
+    let mut typ_with_bad_last = vec![ColumnType::Int; 15];
+    typ_with_bad_last.push(ColumnType::Text);
+    let typ_with_bad_last = ColumnType::Tuple(typ_with_bad_last);
+    let Err(err) = deserialize::<Tuple16>(&typ_with_bad_last, &Bytes::new()) else {
+        panic!("type check should have failed");
+    };
+    let err = get_typeck_err(&err);
+    assert_matches!(
+        err.kind,
+        BuiltinTypeCheckErrorKind::TupleError(TupleTypeCheckErrorKind::FieldTypeCheckFailed {
+            position: 15,
+            field_type: ColumnType::Text,
+            ..
+        })
+    );
+}
+
 pub(crate) fn udt_def_with_fields(
     fields: impl IntoIterator<Item = (impl Into<Cow<'static, str>>, ColumnType<'static>)>,
 ) -> ColumnType<'static> {
@@ -1024,6 +1274,444 @@ fn test_udt_cross_rename_fields() {
     }
 }
 
+#[test]
+fn test_udt_rename_all() {
+    #[derive(scylla_macros::DeserializeValue, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate, rename_all = "SCREAMING_SNAKE_CASE")]
+    struct TestUdt {
+        street_name: String,
+        #[scylla(rename = "zip")]
+        zip_code: i32,
+    }
+
+    let udt_bytes = UdtSerializer::new()
+        .field(&42_i32.to_be_bytes())
+        .field("Main St".as_bytes())
+        .finalize();
+    let typ = udt_def_with_fields([("zip", ColumnType::Int), ("STREET_NAME", ColumnType::Text)]);
+
+    let udt = deserialize::<TestUdt>(&typ, &udt_bytes).unwrap();
+    assert_eq!(
+        udt,
+        TestUdt {
+            street_name: "Main St".to_owned(),
+            zip_code: 42,
+        }
+    );
+}
+
+#[test]
+fn test_udt_generic() {
+    #[derive(scylla_macros::DeserializeValue, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate)]
+    struct Tagged<'a, T: Clone, D = i32> {
+        tag: &'a str,
+        value: T,
+        extra: D,
+    }
+
+    let udt_bytes = UdtSerializer::new()
+        .field("label".as_bytes())
+        .field(&42_i64.to_be_bytes())
+        .field(&7_i32.to_be_bytes())
+        .finalize();
+    let typ = udt_def_with_fields([
+        ("tag", ColumnType::Text),
+        ("value", ColumnType::BigInt),
+        ("extra", ColumnType::Int),
+    ]);
+
+    let udt = deserialize::<Tagged<i64>>(&typ, &udt_bytes).unwrap();
+    assert_eq!(
+        udt,
+        Tagged {
+            tag: "label",
+            value: 42,
+            extra: 7,
+        }
+    );
+
+    // The type parameters are type checked as well.
+    deserialize::<Tagged<i32>>(&typ, &udt_bytes).unwrap_err();
+}
+
+#[test]
+fn test_udt_generic_default_when_null() {
+    // A field of a generic type defaulted when null requires its type to implement Default.
+    #[derive(scylla_macros::DeserializeValue, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate)]
+    struct Counted<T> {
+        #[scylla(default_when_null)]
+        count: T,
+    }
+
+    let typ = udt_def_with_fields([("count", ColumnType::BigInt)]);
+
+    let udt_bytes = UdtSerializer::new().null_field().finalize();
+    let udt = deserialize::<Counted<i64>>(&typ, &udt_bytes).unwrap();
+    assert_eq!(udt, Counted { count: 0 });
+
+    let udt_bytes = UdtSerializer::new().field(&5_i64.to_be_bytes()).finalize();
+    let udt = deserialize::<Counted<i64>>(&typ, &udt_bytes).unwrap();
+    assert_eq!(udt, Counted { count: 5 });
+}
+
+#[test]
+fn test_udt_with() {
+    use crate::types::deserialize::row::tests::{InvalidPriority, Priority};
+
+    #[derive(scylla_macros::ScyllaEntity, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate, udt, flavor = "enforce_order")]
+    struct Task {
+        id: i32,
+        #[scylla(with = "crate::types::deserialize::row::tests::priority_as_int")]
+        priority: Priority,
+    }
+
+    let typ = udt_def_with_fields([("id", ColumnType::Int), ("priority", ColumnType::Int)]);
+    let task = Task {
+        id: 7,
+        priority: Priority::Low,
+    };
+    let udt_bytes = serialize(&typ, &task);
+    assert_eq!(deserialize::<Task>(&typ, &udt_bytes).unwrap(), task);
+
+    // The type check of the module is used.
+    let typ_bad = udt_def_with_fields([("id", ColumnType::Int), ("priority", ColumnType::Text)]);
+    <Task as DeserializeValue>::type_check(&typ_bad).unwrap_err();
+
+    // So are the errors of the module.
+    let udt_bytes = UdtSerializer::new()
+        .field(&7_i32.to_be_bytes())
+        .field(&5_i32.to_be_bytes())
+        .finalize();
+    let err = deserialize::<Task>(&typ, &udt_bytes).unwrap_err();
+    let err = get_deser_err(&err);
+    let BuiltinDeserializationErrorKind::UdtError(
+        UdtDeserializationErrorKind::FieldDeserializationFailed { field_name, err },
+    ) = &err.kind
+    else {
+        panic!("unexpected error kind: {:?}", err.kind)
+    };
+    assert_eq!(field_name, "priority");
+    assert_matches!(err.0.downcast_ref(), Some(InvalidPriority(5)));
+}
+
+#[test]
+fn test_udt_entity_roundtrip() {
+    #[derive(scylla_macros::ScyllaEntity, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate, udt, forbid_excess_udt_fields)]
+    struct TestUdt {
+        street: String,
+        #[scylla(default)]
+        zip_code: Option<i32>,
+    }
+
+    let typ = udt_def_with_fields([("zip_code", ColumnType::Int), ("street", ColumnType::Text)]);
+    let udt = TestUdt {
+        street: "Main St".to_owned(),
+        zip_code: Some(42),
+    };
+    let udt_bytes = serialize(&typ, &udt);
+    assert_eq!(deserialize::<TestUdt>(&typ, &udt_bytes).unwrap(), udt);
+
+    // Attributes are shared by serialization and deserialization.
+    let typ = udt_def_with_fields([("street", ColumnType::Text)]);
+    let udt_bytes = serialize(&typ, &udt);
+    assert_eq!(
+        deserialize::<TestUdt>(&typ, &udt_bytes).unwrap(),
+        TestUdt {
+            street: "Main St".to_owned(),
+            zip_code: None,
+        }
+    );
+}
+
+#[test]
+fn test_udt_name_check() {
+    #[derive(scylla_macros::ScyllaEntity, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate, udt, udt_name = "address", keyspace = "ks")]
+    struct Address {
+        street: String,
+    }
+
+    #[derive(scylla_macros::DeserializeValue, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate, flavor = "enforce_order", udt_name = "address")]
+    struct OrderedAddress {
+        street: String,
+    }
+
+    let udt_type = |keyspace: &'static str, type_name: &'static str| ColumnType::UserDefinedType {
+        type_name: type_name.into(),
+        keyspace: keyspace.into(),
+        field_types: vec![("street".into(), ColumnType::Text)],
+    };
+    let address = Address {
+        street: "Main St".to_owned(),
+    };
+
+    let typ = udt_type("ks", "address");
+    let udt_bytes = serialize(&typ, &address);
+    assert_eq!(deserialize::<Address>(&typ, &udt_bytes).unwrap(), address);
+    OrderedAddress::type_check(&udt_type("other_ks", "address")).unwrap();
+
+    // A UDT with the same fields, but a different identity.
+    for (keyspace, type_name) in [("ks", "location"), ("other_ks", "address")] {
+        let err = Address::type_check(&udt_type(keyspace, type_name)).unwrap_err();
+        let err = get_typeck_err_inner(err.0.as_ref());
+        let BuiltinTypeCheckErrorKind::UdtError(UdtTypeCheckErrorKind::NameMismatch {
+            keyspace: got_keyspace,
+            type_name: got_type_name,
+        }) = &err.kind
+        else {
+            panic!("unexpected error kind: {}", err.kind)
+        };
+        assert_eq!(got_keyspace, keyspace);
+        assert_eq!(got_type_name, type_name);
+    }
+
+    let err = OrderedAddress::type_check(&udt_type("ks", "location")).unwrap_err();
+    assert_matches!(
+        get_typeck_err_inner(err.0.as_ref()).kind,
+        BuiltinTypeCheckErrorKind::UdtError(UdtTypeCheckErrorKind::NameMismatch { .. })
+    );
+}
+
+#[test]
+fn test_udt_default_fields() {
+    #[derive(scylla_macros::DeserializeValue, PartialEq, Eq, Debug)]
+    #[scylla(crate = crate)]
+    struct TestUdt {
+        a: i32,
+        #[scylla(default)]
+        b: String,
+        #[scylla(default, rename = "x")]
+        c: i64,
+    }
+
+    // All fields present.
+    {
+        let udt_bytes = UdtSerializer::new()
+            .field(&42_i32.to_be_bytes())
+            .field("The quick brown fox".as_bytes())
+            .field(&2137_i64.to_be_bytes())
+            .finalize();
+        let typ = udt_def_with_fields([
+            ("a", ColumnType::Int),
+            ("b", ColumnType::Text),
+            ("x", ColumnType::BigInt),
+        ]);
+
+        let udt = deserialize::<TestUdt>(&typ, &udt_bytes).unwrap();
+        assert_eq!(
+            udt,
+            TestUdt {
+                a: 42,
+                b: "The quick brown fox".to_owned(),
+                c: 2137,
+            }
+        );
+    }
+
+    // The UDT definition lacks `b` and the serialized value lacks `x`,
+    // e.g. it was written before `x` was added with ALTER TYPE.
+    {
+        let udt_bytes = UdtSerializer::new().field(&42_i32.to_be_bytes()).finalize();
+        let typ = udt_def_with_fields([("a", ColumnType::Int), ("x", ColumnType::BigInt)]);
+
+        let udt = deserialize::<TestUdt>(&typ, &udt_bytes).unwrap();
+        assert_eq!(
+            udt,
+            TestUdt {
+                a: 42,
+                b: String::new(),
+                c: 0,
+            }
+        );
+    }
+
+    // Fields without the attribute are still required.
+    {
+        let udt_bytes = UdtSerializer::new()
+            .field("The quick brown fox".as_bytes())
+            .finalize();
+        let typ = udt_def_with_fields([("b", ColumnType::Text), ("x", ColumnType::BigInt)]);
+        let err = deserialize::<TestUdt>(&typ, &udt_bytes).unwrap_err();
+        let err = get_typeck_err(&err);
+        assert_matches!(
+            err.kind,
+            BuiltinTypeCheckErrorKind::UdtError(
+                UdtTypeCheckErrorKind::ValuesMissingForUdtFields { .. }
+            )
+        );
+    }
+}
+
+#[test]
+fn test_enum_text_repr() {
+    #[derive(
+        scylla_macros::SerializeValue, scylla_macros::DeserializeValue, PartialEq, Eq, Debug,
+    )]
+    #[scylla(crate = crate)]
+    enum Status {
+        Active,
+        #[scylla(rename = "on_hold")]
+        OnHold,
+        r#Closed,
+    }
+
+    for typ in [ColumnType::Text, ColumnType::Ascii] {
+        for status in [Status::Active, Status::OnHold, Status::Closed] {
+            assert_ser_de_identity(&typ, &status, &mut Bytes::new());
+        }
+    }
+
+    assert_eq!(
+        serialize(&ColumnType::Text, &Status::OnHold),
+        make_bytes(b"on_hold")
+    );
+    assert_eq!(
+        deserialize::<Status>(&ColumnType::Text, &make_bytes(b"Closed")).unwrap(),
+        Status::Closed
+    );
+
+    // Names are matched exactly.
+    let bytes = make_bytes(b"OnHold");
+    let err = deserialize::<Status>(&ColumnType::Text, &bytes).unwrap_err();
+    let err = get_deser_err(&err);
+    assert_eq!(err.rust_name, std::any::type_name::<Status>());
+    assert_matches!(
+        &err.kind,
+        BuiltinDeserializationErrorKind::UnknownVariant { value } if value == "OnHold"
+    );
+
+    let err = deserialize::<Status>(&ColumnType::Int, &bytes).unwrap_err();
+    let err = get_typeck_err(&err);
+    assert_eq!(err.rust_name, std::any::type_name::<Status>());
+    assert_matches!(err.kind, BuiltinTypeCheckErrorKind::MismatchedType { .. });
+
+    let err = Status::Active
+        .serialize(&ColumnType::Int, CellWriter::new(&mut Vec::new()))
+        .unwrap_err();
+    let err = err.downcast_ref::<SerBuiltinTypeCheckError>().unwrap();
+    assert_eq!(err.rust_name, std::any::type_name::<Status>());
+    assert_matches!(
+        err.kind,
+        SerBuiltinTypeCheckErrorKind::MismatchedType { .. }
+    );
+}
+
+#[test]
+fn test_enum_int_repr() {
+    #[derive(
+        scylla_macros::SerializeValue, scylla_macros::DeserializeValue, PartialEq, Eq, Debug,
+    )]
+    #[scylla(crate = crate, repr = "int")]
+    enum Priority {
+        Low = -1,
+        Normal,
+        High = 10,
+        Critical = 1000,
+    }
+
+    for typ in [ColumnType::SmallInt, ColumnType::Int, ColumnType::BigInt] {
+        for priority in [
+            Priority::Low,
+            Priority::Normal,
+            Priority::High,
+            Priority::Critical,
+        ] {
+            assert_ser_de_identity(&typ, &priority, &mut Bytes::new());
+        }
+    }
+
+    assert_eq!(
+        serialize(&ColumnType::TinyInt, &Priority::Normal),
+        make_bytes(&0_i8.to_be_bytes())
+    );
+    assert_eq!(
+        deserialize::<Priority>(&ColumnType::TinyInt, &make_bytes(&10_i8.to_be_bytes())).unwrap(),
+        Priority::High
+    );
+
+    // The discriminant does not fit in the column type.
+    let err = Priority::Critical
+        .serialize(&ColumnType::TinyInt, CellWriter::new(&mut Vec::new()))
+        .unwrap_err();
+    let err = err.downcast_ref::<BuiltinSerializationError>().unwrap();
+    assert_eq!(err.rust_name, std::any::type_name::<Priority>());
+    assert_matches!(err.kind, BuiltinSerializationErrorKind::ValueOverflow);
+
+    let bytes = make_bytes(&5_i32.to_be_bytes());
+    let err = deserialize::<Priority>(&ColumnType::Int, &bytes).unwrap_err();
+    let err = get_deser_err(&err);
+    assert_eq!(err.rust_name, std::any::type_name::<Priority>());
+    assert_matches!(
+        &err.kind,
+        BuiltinDeserializationErrorKind::UnknownVariant { value } if value == "5"
+    );
+
+    let err = deserialize::<Priority>(&ColumnType::Text, &bytes).unwrap_err();
+    let err = get_typeck_err(&err);
+    assert_eq!(err.rust_name, std::any::type_name::<Priority>());
+    assert_matches!(err.kind, BuiltinTypeCheckErrorKind::MismatchedType { .. });
+}
+
+#[test]
+fn test_transparent_newtypes() {
+    #[derive(
+        scylla_macros::SerializeValue, scylla_macros::DeserializeValue, PartialEq, Eq, Debug,
+    )]
+    #[scylla(crate = crate, transparent)]
+    struct UserId(Uuid);
+
+    #[derive(
+        scylla_macros::SerializeValue, scylla_macros::DeserializeValue, PartialEq, Eq, Debug,
+    )]
+    #[scylla(crate = crate, transparent)]
+    struct Name<'a>(&'a str);
+
+    #[derive(
+        scylla_macros::SerializeValue, scylla_macros::DeserializeValue, PartialEq, Eq, Debug,
+    )]
+    #[scylla(transparent, crate = crate)]
+    struct Wrapper<T>(T);
+
+    #[derive(
+        scylla_macros::SerializeValue, scylla_macros::DeserializeValue, PartialEq, Eq, Debug,
+    )]
+    #[scylla(crate = crate, transparent)]
+    struct Score {
+        value: i32,
+    }
+
+    let id = UserId(Uuid::from_u128(0x1234));
+    assert_ser_de_identity(&ColumnType::Uuid, &id, &mut Bytes::new());
+    assert_eq!(
+        serialize(&ColumnType::Uuid, &id),
+        serialize(&ColumnType::Uuid, &id.0)
+    );
+
+    let bytes = make_bytes(b"alice");
+    assert_eq!(
+        deserialize::<Name>(&ColumnType::Text, &bytes).unwrap(),
+        Name("alice")
+    );
+    assert_ser_de_identity(&ColumnType::Ascii, &Name("bob"), &mut Bytes::new());
+
+    let list_typ = ColumnType::List(Box::new(ColumnType::BigInt));
+    assert_ser_de_identity(&list_typ, &Wrapper(vec![1_i64, 2, 3]), &mut Bytes::new());
+    assert_ser_de_identity(&ColumnType::Int, &Wrapper(Some(42_i32)), &mut Bytes::new());
+
+    assert_ser_de_identity(&ColumnType::Int, &Score { value: 7 }, &mut Bytes::new());
+
+    // Type checking is delegated to the inner type as well.
+    let err = deserialize::<UserId>(&ColumnType::Text, &bytes).unwrap_err();
+    let err = get_typeck_err(&err);
+    assert_eq!(err.rust_name, std::any::type_name::<Uuid>());
+    assert_matches!(err.kind, BuiltinTypeCheckErrorKind::MismatchedType { .. });
+}
+
 #[test]
 fn test_custom_type_parser() {
     #[derive(Default, Debug, PartialEq, Eq)]
@@ -1249,9 +1937,150 @@ fn test_native_errors() {
             ColumnType::Text,
             BuiltinDeserializationErrorKind::InvalidUtf8(_)
         );
+
+        assert_deser_error!(
+            &bytes,
+            CqlStr,
+            ColumnType::Ascii,
+            BuiltinDeserializationErrorKind::ExpectedAscii
+        );
+
+        assert_deser_error!(
+            &bytes,
+            CqlStr,
+            ColumnType::Text,
+            BuiltinDeserializationErrorKind::InvalidUtf8(_)
+        );
     }
 }
 
+#[test]
+fn test_parsed_errors() {
+    assert_type_check_error!(
+        &Bytes::new(),
+        Parsed<u32>,
+        ColumnType::Int,
+        BuiltinTypeCheckErrorKind::MismatchedType {
+            expected: &[ColumnType::Ascii, ColumnType::Text],
+        }
+    );
+
+    assert_deser_error!(
+        &make_null(),
+        Parsed<u32>,
+        ColumnType::Text,
+        BuiltinDeserializationErrorKind::ExpectedNonNull
+    );
+
+    let unicode = make_bytes("Zażółć".as_bytes());
+    assert_deser_error!(
+        &unicode,
+        Parsed<u32>,
+        ColumnType::Ascii,
+        BuiltinDeserializationErrorKind::ExpectedAscii
+    );
+
+    // The parse error is available to the caller.
+    let not_a_number = make_bytes(b"12a");
+    let err = deserialize::<Parsed<u32>>(&ColumnType::Text, &not_a_number).unwrap_err();
+    let err = get_deser_err(&err);
+    assert_eq!(err.rust_name, std::any::type_name::<Parsed<u32>>());
+    let BuiltinDeserializationErrorKind::ParseFailed(parse_err) = &err.kind else {
+        panic!("unexpected error kind: {:?}", err.kind);
+    };
+    assert_eq!(
+        parse_err.downcast_ref::<std::num::ParseIntError>().unwrap(),
+        &"12a".parse::<u32>().unwrap_err()
+    );
+}
+
+#[test]
+fn test_via_errors() {
+    // Type checking and deserialization are delegated to the raw type.
+    assert_type_check_error!(
+        &Bytes::new(),
+        Via<String, NonEmptyString>,
+        ColumnType::Int,
+        BuiltinTypeCheckErrorKind::MismatchedType {
+            expected: &[ColumnType::Ascii, ColumnType::Text],
+        }
+    );
+    assert_deser_error!(
+        &make_null(),
+        Via<String, NonEmptyString>,
+        ColumnType::Text,
+        BuiltinDeserializationErrorKind::ExpectedNonNull
+    );
+
+    // The conversion error is available to the caller.
+    let err = deserialize::<Via<String, NonEmptyString>>(&ColumnType::Text, &make_bytes(b""))
+        .unwrap_err();
+    let err = get_deser_err(&err);
+    assert_eq!(
+        err.rust_name,
+        std::any::type_name::<Via<String, NonEmptyString>>()
+    );
+    let BuiltinDeserializationErrorKind::ConversionFailed(conversion_err) = &err.kind else {
+        panic!("unexpected error kind: {:?}", err.kind);
+    };
+    assert_eq!(conversion_err.to_string(), "the string is empty");
+
+    let number = make_bytes(&300i64.to_be_bytes());
+    let err = deserialize::<Via<i64, u8>>(&ColumnType::BigInt, &number).unwrap_err();
+    let err = get_deser_err(&err);
+    let BuiltinDeserializationErrorKind::ConversionFailed(conversion_err) = &err.kind else {
+        panic!("unexpected error kind: {:?}", err.kind);
+    };
+    assert!(conversion_err
+        .downcast_ref::<std::num::TryFromIntError>()
+        .is_some());
+}
+
+#[test]
+fn test_empty_policies_errors() {
+    assert_deser_error!(
+        &make_null(),
+        EmptyAsDefault<i8>,
+        ColumnType::TinyInt,
+        BuiltinDeserializationErrorKind::ExpectedNonNull
+    );
+
+    // Type checking is delegated to the inner type.
+    let err = deserialize::<EmptyAsNone<i8>>(&ColumnType::Int, &make_bytes(&[])).unwrap_err();
+    let err = get_typeck_err(&err);
+    assert_eq!(err.rust_name, std::any::type_name::<i8>());
+    assert_matches!(err.kind, BuiltinTypeCheckErrorKind::MismatchedType { .. });
+}
+
+#[test]
+fn test_custom_type_errors() {
+    for typ in [
+        ColumnType::Custom("com.example.db.marshal.BazType".into()),
+        ColumnType::Blob,
+    ] {
+        assert_type_check_error!(
+            &Bytes::new(),
+            Custom<VendorBytes>,
+            typ,
+            BuiltinTypeCheckErrorKind::MismatchedCustomType {
+                expected: &["com.example.db.marshal.FooType", _, _]
+            }
+        );
+    }
+
+    let typ = ColumnType::Custom("com.example.db.marshal.FooType".into());
+    assert_deser_error!(
+        &make_null(),
+        Custom<VendorBytes>,
+        typ,
+        BuiltinDeserializationErrorKind::ExpectedNonNull
+    );
+
+    // Errors of the decoder are passed through.
+    let err = deserialize::<Custom<VendorBytes>>(&typ, &make_bytes(b"invalid")).unwrap_err();
+    assert!(err.downcast_ref::<std::fmt::Error>().is_some());
+}
+
 #[test]
 fn test_set_or_list_errors() {
     // Not a set or list
@@ -1534,12 +2363,17 @@ fn test_tuple_errors() {
             assert_eq!(err.rust_name, std::any::type_name::<(i64,)>());
             assert_eq!(err.cql_type, ColumnType::Tuple(vec![ColumnType::SmallInt]));
             let BuiltinTypeCheckErrorKind::TupleError(
-                TupleTypeCheckErrorKind::FieldTypeCheckFailed { ref err, position },
+                TupleTypeCheckErrorKind::FieldTypeCheckFailed {
+                    ref err,
+                    position,
+                    ref field_type,
+                },
             ) = err.kind
             else {
                 panic!("unexpected error kind: {}", err.kind)
             };
             assert_eq!(position, 0);
+            assert_eq!(field_type, &ColumnType::SmallInt);
             let err = get_typeck_err_inner(err.0.as_ref());
             assert_eq!(err.rust_name, std::any::type_name::<i64>());
             assert_eq!(err.cql_type, ColumnType::SmallInt);
@@ -1573,12 +2407,14 @@ fn test_tuple_errors() {
                 TupleDeserializationErrorKind::FieldDeserializationFailed {
                     ref err,
                     position: index,
+                    ref field_type,
                 },
             ) = err.kind
             else {
                 panic!("unexpected error kind: {}", err.kind)
             };
             assert_eq!(index, 1);
+            assert_eq!(field_type, &ColumnType::Double);
             let err = get_deser_err(err);
             assert_eq!(err.rust_name, std::any::type_name::<f64>());
             assert_eq!(err.cql_type, ColumnType::Double);
@@ -0,0 +1,366 @@
+//! Limits on the size of serialized values, checked before sending a request.
+//!
+//! The database rejects too large values only after receiving the whole request,
+//! with a generic error. Checking the limits on the client side avoids sending
+//! the request at all and reports which column is at fault.
+
+use thiserror::Error;
+
+use crate::frame::response::result::ColumnType;
+use crate::frame::types::RawValue;
+
+use super::raw_batch::{RawBatchValues, RawBatchValuesIterator};
+use super::row::{RowSerializationContext, SerializedValues};
+use super::{RowWriter, SerializationError};
+
+/// Limits on the size of values bound to a statement and of the whole request.
+///
+/// All limits are disabled by default.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ValueSizeLimits {
+    /// The maximum size (in bytes) of a single serialized value.
+    pub max_cell_size: Option<usize>,
+
+    /// The maximum number of elements of a list, set or map bound to a statement.
+    /// Only the top-level collections are checked, not the ones nested in them.
+    pub max_collection_elements: Option<usize>,
+
+    /// The maximum size (in bytes) of a serialized request, including the frame header.
+    pub max_request_size: Option<usize>,
+}
+
+impl ValueSizeLimits {
+    /// Returns `true` if none of the limits is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.max_cell_size.is_none()
+            && self.max_collection_elements.is_none()
+            && self.max_request_size.is_none()
+    }
+
+    /// Checks the values bound to a statement against the cell size
+    /// and collection element limits.
+    ///
+    /// The values are matched with the columns of the context by position.
+    /// Values without a matching column are only checked against the cell size limit.
+    pub fn check_values(
+        &self,
+        ctx: &RowSerializationContext<'_>,
+        values: &SerializedValues,
+    ) -> Result<(), ValueSizeLimitError> {
+        if self.max_cell_size.is_none() && self.max_collection_elements.is_none() {
+            return Ok(());
+        }
+
+        let mut columns = ctx.columns().iter();
+        for (idx, value) in values.iter().enumerate() {
+            let column = columns.next();
+            let RawValue::Value(bytes) = value else {
+                continue;
+            };
+            let column_name = || match column {
+                Some(spec) => spec.name().to_owned(),
+                None => format!("#{}", idx),
+            };
+
+            if let Some(limit) = self.max_cell_size {
+                if bytes.len() > limit {
+                    return Err(ValueSizeLimitError::CellTooLarge {
+                        column: column_name(),
+                        size: bytes.len(),
+                        limit,
+                    });
+                }
+            }
+
+            if let (Some(limit), Some(spec)) = (self.max_collection_elements, column) {
+                let is_collection = matches!(
+                    spec.typ(),
+                    ColumnType::List(_) | ColumnType::Set(_) | ColumnType::Map(_, _)
+                );
+                if let (true, Some(count)) = (is_collection, bytes.get(..4)) {
+                    let count = i32::from_be_bytes(count.try_into().unwrap()).max(0) as usize;
+                    if count > limit {
+                        return Err(ValueSizeLimitError::TooManyCollectionElements {
+                            column: column_name(),
+                            count,
+                            limit,
+                        });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Checks the size of a serialized request against the request size limit.
+    pub fn check_request_size(&self, size: usize) -> Result<(), ValueSizeLimitError> {
+        match self.max_request_size {
+            Some(limit) if size > limit => {
+                Err(ValueSizeLimitError::RequestTooLarge { size, limit })
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Wraps a [`RawBatchValues`] and checks the values of each statement of the batch
+/// against the [`ValueSizeLimits`] while they are serialized.
+///
+/// A violation is returned as a [`SerializationError`] containing
+/// a [`ValueSizeLimitError`], so that it is reported for the offending statement.
+pub struct LimitCheckingRawBatchValues<RBV, CTX> {
+    values: RBV,
+    contexts: CTX,
+    limits: ValueSizeLimits,
+}
+
+impl<RBV, CTX> LimitCheckingRawBatchValues<RBV, CTX> {
+    /// Creates a new `LimitCheckingRawBatchValues` object. The contexts are used
+    /// for naming the columns of the statements in errors.
+    #[inline]
+    pub fn new(values: RBV, contexts: CTX, limits: ValueSizeLimits) -> Self {
+        Self {
+            values,
+            contexts,
+            limits,
+        }
+    }
+}
+
+impl<'ctx, RBV, CTX> RawBatchValues for LimitCheckingRawBatchValues<RBV, CTX>
+where
+    RBV: RawBatchValues,
+    CTX: Iterator<Item = RowSerializationContext<'ctx>> + Clone,
+{
+    type RawBatchValuesIter<'r>
+        = LimitCheckingRawBatchValuesIterator<RBV::RawBatchValuesIter<'r>, CTX>
+    where
+        Self: 'r;
+
+    #[inline]
+    fn batch_values_iter(&self) -> Self::RawBatchValuesIter<'_> {
+        LimitCheckingRawBatchValuesIterator {
+            values_iterator: self.values.batch_values_iter(),
+            contexts: self.contexts.clone(),
+            limits: self.limits,
+        }
+    }
+}
+
+/// An iterator over the values of [`LimitCheckingRawBatchValues`].
+pub struct LimitCheckingRawBatchValuesIterator<RBVI, CTX> {
+    values_iterator: RBVI,
+    contexts: CTX,
+    limits: ValueSizeLimits,
+}
+
+impl<'rbvi, 'ctx, RBVI, CTX> RawBatchValuesIterator<'rbvi>
+    for LimitCheckingRawBatchValuesIterator<RBVI, CTX>
+where
+    RBVI: RawBatchValuesIterator<'rbvi>,
+    CTX: Iterator<Item = RowSerializationContext<'ctx>>,
+{
+    fn serialize_next(&mut self, writer: &mut RowWriter) -> Option<Result<(), SerializationError>> {
+        let ctx = self
+            .contexts
+            .next()
+            .unwrap_or(RowSerializationContext::empty());
+        if self.limits.max_cell_size.is_none() && self.limits.max_collection_elements.is_none() {
+            return self.values_iterator.serialize_next(writer);
+        }
+
+        // The values are serialized into a separate buffer first,
+        // because the writer doesn't allow reading them back.
+        let serialized = SerializedValues::from_closure(|writer| {
+            self.values_iterator
+                .serialize_next(writer)
+                .transpose()
+                .map(|present| present.is_some())
+        });
+        match serialized {
+            Ok((_, false)) => None,
+            Ok((values, true)) => Some(
+                self.limits
+                    .check_values(&ctx, &values)
+                    .map(|()| writer.append_serialize_row(&values))
+                    .map_err(SerializationError::new),
+            ),
+            Err(err) => Some(Err(err)),
+        }
+    }
+
+    #[inline]
+    fn is_empty_next(&mut self) -> Option<bool> {
+        let _ = self.contexts.next();
+        self.values_iterator.is_empty_next()
+    }
+
+    #[inline]
+    fn skip_next(&mut self) -> Option<()> {
+        let _ = self.contexts.next();
+        self.values_iterator.skip_next()
+    }
+}
+
+/// An error returned when a request exceeds one of the [`ValueSizeLimits`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueSizeLimitError {
+    /// A value bound to a statement is too large.
+    #[error("Value of column {column} has {size} bytes, which exceeds the limit of {limit} bytes")]
+    CellTooLarge {
+        /// Name of the column, or `#` followed by the index of the value
+        /// if the column is not known.
+        column: String,
+        /// Size of the serialized value, in bytes.
+        size: usize,
+        /// The configured limit.
+        limit: usize,
+    },
+
+    /// A collection bound to a statement has too many elements.
+    #[error(
+        "Collection in column {column} has {count} elements, which exceeds the limit of {limit} elements"
+    )]
+    TooManyCollectionElements {
+        /// Name of the column.
+        column: String,
+        /// Number of elements of the collection.
+        count: usize,
+        /// The configured limit.
+        limit: usize,
+    },
+
+    /// The whole serialized request is too large.
+    #[error("Request has {size} bytes, which exceeds the limit of {limit} bytes")]
+    RequestTooLarge {
+        /// Size of the serialized request, in bytes.
+        size: usize,
+        /// The configured limit.
+        limit: usize,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
+    use crate::types::serialize::raw_batch::{
+        RawBatchValues, RawBatchValuesAdapter, RawBatchValuesIterator,
+    };
+    use crate::types::serialize::row::{RowSerializationContext, SerializedValues};
+    use crate::types::serialize::RowWriter;
+
+    use super::{LimitCheckingRawBatchValues, ValueSizeLimitError, ValueSizeLimits};
+
+    fn col(name: &str, typ: ColumnType<'static>) -> ColumnSpec<'static> {
+        ColumnSpec::owned(name.to_owned(), typ, TableSpec::borrowed("ks", "tbl"))
+    }
+
+    #[test]
+    fn test_value_size_limits() {
+        let specs = [
+            col("id", ColumnType::Int),
+            col("body", ColumnType::Blob),
+            col("tags", ColumnType::Set(Box::new(ColumnType::Text))),
+        ];
+        let ctx = RowSerializationContext { columns: &specs };
+        let values = SerializedValues::from_serializable(
+            &ctx,
+            &(1_i32, vec![0_u8; 100], vec!["a", "b", "c"]),
+        )
+        .unwrap();
+
+        ValueSizeLimits::default()
+            .check_values(&ctx, &values)
+            .unwrap();
+
+        let limits = ValueSizeLimits {
+            max_cell_size: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.check_values(&ctx, &values),
+            Err(ValueSizeLimitError::CellTooLarge {
+                column: "body".to_owned(),
+                size: 100,
+                limit: 50,
+            })
+        );
+
+        let limits = ValueSizeLimits {
+            max_collection_elements: Some(2),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.check_values(&ctx, &values),
+            Err(ValueSizeLimitError::TooManyCollectionElements {
+                column: "tags".to_owned(),
+                count: 3,
+                limit: 2,
+            })
+        );
+
+        // Values without a known column are named by their index.
+        let limits = ValueSizeLimits {
+            max_cell_size: Some(50),
+            ..Default::default()
+        };
+        assert_eq!(
+            limits.check_values(&RowSerializationContext::empty(), &values),
+            Err(ValueSizeLimitError::CellTooLarge {
+                column: "#1".to_owned(),
+                size: 100,
+                limit: 50,
+            })
+        );
+
+        let limits = ValueSizeLimits {
+            max_request_size: Some(1000),
+            ..Default::default()
+        };
+        limits.check_request_size(1000).unwrap();
+        assert_eq!(
+            limits.check_request_size(1001),
+            Err(ValueSizeLimitError::RequestTooLarge {
+                size: 1001,
+                limit: 1000,
+            })
+        );
+    }
+
+    #[test]
+    fn test_limit_checking_batch_values() {
+        let specs = [col("body", ColumnType::Blob)];
+        let contexts =
+            std::iter::repeat_with(|| RowSerializationContext { columns: &specs }).take(2);
+        let values = vec![(vec![0_u8; 10],), (vec![0_u8; 100],)];
+        let limits = ValueSizeLimits {
+            max_cell_size: Some(50),
+            ..Default::default()
+        };
+        let batch_values = LimitCheckingRawBatchValues::new(
+            RawBatchValuesAdapter::new(&values, contexts.clone()),
+            contexts,
+            limits,
+        );
+
+        let mut data = Vec::new();
+        let mut writer = RowWriter::new(&mut data);
+        let mut iter = batch_values.batch_values_iter();
+        iter.serialize_next(&mut writer).unwrap().unwrap();
+        assert_eq!(writer.value_count(), 1);
+        assert_eq!(data.len(), 4 + 10);
+
+        let mut writer = RowWriter::new(&mut data);
+        let err = iter.serialize_next(&mut writer).unwrap().unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<ValueSizeLimitError>(),
+            Some(&ValueSizeLimitError::CellTooLarge {
+                column: "body".to_owned(),
+                size: 100,
+                limit: 50,
+            })
+        );
+        assert!(iter.serialize_next(&mut writer).is_none());
+    }
+}
@@ -7,8 +7,13 @@ use std::{error::Error, sync::Arc};
 use thiserror::Error;
 
 pub mod batch;
+pub mod limits;
 pub mod raw_batch;
 pub mod row;
+#[cfg(feature = "serde")]
+pub mod serde_row;
+#[cfg(feature = "serde")]
+pub mod serde_value;
 pub mod value;
 pub mod writers;
 
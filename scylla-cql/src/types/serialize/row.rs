@@ -85,6 +85,33 @@ pub trait SerializeRow {
     fn is_empty(&self) -> bool;
 }
 
+/// Serialization of a row, split into serialization of individual columns
+/// matched to the fields by name.
+///
+/// It is implemented by the `SerializeRow` derive macro in the default
+/// `match_by_name` flavor, and allows to `#[scylla(flatten)]` a struct into
+/// another struct deriving `SerializeRow`. It's not supposed to be implemented
+/// manually, and it's not a part of the stable API.
+#[doc(hidden)]
+pub trait SerializeRowByName {
+    /// Tracks which fields have been serialized.
+    type State;
+
+    fn new_state() -> Self::State;
+
+    /// Serializes the field corresponding to the column.
+    /// Returns `false` if there is no such field.
+    fn serialize_column(
+        &self,
+        state: &mut Self::State,
+        spec: &ColumnSpec,
+        writer: &mut RowWriter,
+    ) -> Result<bool, SerializationError>;
+
+    /// Returns the name of a column whose field has not been serialized, if any.
+    fn missing_column(state: &Self::State) -> Option<&'static str>;
+}
+
 macro_rules! fallback_impl_contents {
     () => {
         fn serialize(
@@ -941,12 +968,134 @@ mod doctests {
     /// }
     /// ```
     fn _test_struct_deserialization_rename_collision_with_another_rename() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql, flavor = "enforce_order", skip_name_checks, rename_all = "camelCase")]
+    /// struct TestRow {
+    ///     a_b: i32,
+    /// }
+    /// ```
+    fn _test_struct_serialization_skip_name_check_conflicts_with_rename_all() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql, rename_all = "camelCase")]
+    /// struct TestRow {
+    ///     a_b: i32,
+    ///     #[scylla(rename = "aB")]
+    ///     c: String,
+    /// }
+    /// ```
+    fn _test_struct_serialization_rename_all_collision_with_rename() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql)]
+    /// struct Audit {
+    ///     created_by: String,
+    /// }
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql, flavor = "enforce_order")]
+    /// struct TestRow {
+    ///     a: i32,
+    ///     #[scylla(flatten)]
+    ///     audit: Audit,
+    /// }
+    /// ```
+    fn _test_struct_serialization_flatten_requires_match_by_name() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql)]
+    /// struct TestRow(i32, String);
+    /// ```
+    fn _test_struct_serialization_tuple_struct_requires_positional() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql, positional)]
+    /// struct TestRow {
+    ///     #[scylla(rename = "b")]
+    ///     a: i32,
+    /// }
+    /// ```
+    fn _test_struct_serialization_positional_conflicts_with_rename() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql)]
+    /// struct Audit {
+    ///     created_by: String,
+    /// }
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql)]
+    /// struct TestRow {
+    ///     a: i32,
+    ///     #[scylla(flatten, rename = "b")]
+    ///     audit: Audit,
+    /// }
+    /// ```
+    fn _test_struct_serialization_flatten_conflicts_with_rename() {}
+
+    /// ```compile_fail
+    ///
+    /// use scylla_cql::frame::response::result::ColumnType;
+    /// use scylla_cql::types::serialize::writers::{CellWriter, WrittenCellProof};
+    /// use scylla_cql::types::serialize::SerializationError;
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql)]
+    /// struct Audit {
+    ///     created_by: String,
+    /// }
+    ///
+    /// mod audit_as_text {
+    ///     use super::*;
+    ///     pub fn serialize<'b>(
+    ///         audit: &Audit,
+    ///         typ: &ColumnType,
+    ///         writer: CellWriter<'b>,
+    ///     ) -> Result<WrittenCellProof<'b>, SerializationError> {
+    ///         scylla_cql::types::serialize::value::SerializeValue::serialize(&audit.created_by, typ, writer)
+    ///     }
+    /// }
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql)]
+    /// struct TestRow {
+    ///     a: i32,
+    ///     #[scylla(flatten, with = "audit_as_text")]
+    ///     audit: Audit,
+    /// }
+    /// ```
+    fn _test_struct_serialization_flatten_conflicts_with_with() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeRow)]
+    /// #[scylla(crate = scylla_cql)]
+    /// struct TestRow {
+    ///     #[scylla(unset_if_none)]
+    ///     a: i32,
+    /// }
+    /// ```
+    fn _test_struct_serialization_unset_if_none_requires_option() {}
 }
 
 #[cfg(test)]
 pub(crate) mod tests {
     use std::borrow::Cow;
-    use std::collections::BTreeMap;
+    use std::collections::{BTreeMap, HashMap};
+    use std::sync::Arc;
 
     use crate::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
     use crate::frame::types::RawValue;
@@ -1070,6 +1219,47 @@ pub(crate) mod tests {
         assert_eq!(typed_data, erased_data);
     }
 
+    #[test]
+    fn test_map_of_dyn_values() {
+        let spec = [col("a", ColumnType::Int), col("b", ColumnType::Text)];
+        let reference = do_serialize((123_i32, "Ala ma kota"), &spec);
+
+        // Values of different types, collected at runtime
+        let mut boxed: HashMap<String, Box<dyn SerializeValue>> = HashMap::new();
+        boxed.insert("a".to_owned(), Box::new(123_i32));
+        boxed.insert("b".to_owned(), Box::new("Ala ma kota"));
+        assert_eq!(do_serialize(&boxed, &spec), reference);
+
+        let a = 123_i32;
+        let b = "Ala ma kota".to_owned();
+        let borrowed: BTreeMap<&str, &dyn SerializeValue> =
+            [("a", &a as &dyn SerializeValue), ("b", &b)]
+                .into_iter()
+                .collect();
+        assert_eq!(do_serialize(&borrowed, &spec), reference);
+
+        let shared: HashMap<&str, Arc<dyn SerializeValue + Send + Sync>> = [
+            (
+                "a",
+                Arc::new(123_i32) as Arc<dyn SerializeValue + Send + Sync>,
+            ),
+            ("b", Arc::new("Ala ma kota")),
+        ]
+        .into_iter()
+        .collect();
+        assert_eq!(do_serialize(&shared, &spec), reference);
+
+        // Type errors are reported like for statically typed values
+        let spec = [col("a", ColumnType::Text), col("b", ColumnType::Text)];
+        let err = do_serialize_err(&boxed, &spec);
+        let err = get_ser_err(&err);
+        let BuiltinSerializationErrorKind::ColumnSerializationFailed { name, err: _ } = &err.kind
+        else {
+            panic!("Expected BuiltinSerializationErrorKind::ColumnSerializationFailed")
+        };
+        assert_eq!(name, "a");
+    }
+
     pub(crate) fn do_serialize<T: SerializeRow>(t: T, columns: &[ColumnSpec]) -> Vec<u8> {
         let ctx = RowSerializationContext { columns };
         let mut ret = Vec::new();
@@ -1572,6 +1762,33 @@ pub(crate) mod tests {
         assert_eq!(reference, row);
     }
 
+    #[derive(SerializeRow, Debug)]
+    #[scylla(crate = crate, rename_all = "PascalCase")]
+    struct TestRowWithRenameAll {
+        first_name: String,
+        #[scylla(rename = "x")]
+        b: i32,
+    }
+
+    #[test]
+    fn test_row_serialization_with_rename_all() {
+        let spec = [
+            col("x", ColumnType::Int),
+            col("FirstName", ColumnType::Text),
+        ];
+
+        let reference = do_serialize((42i32, "Ala ma kota"), &spec);
+        let row = do_serialize(
+            TestRowWithRenameAll {
+                first_name: "Ala ma kota".to_owned(),
+                b: 42,
+            },
+            &spec,
+        );
+
+        assert_eq!(reference, row);
+    }
+
     #[test]
     fn test_row_serialization_with_column_rename_and_enforce_order() {
         let spec = [col("a", ColumnType::Text), col("x", ColumnType::Int)];
@@ -1611,6 +1828,63 @@ pub(crate) mod tests {
         assert_eq!(reference, row);
     }
 
+    #[derive(SerializeRow, Debug)]
+    #[scylla(crate = crate, positional)]
+    struct TestPositionalRow(
+        String,
+        // Only checks that skipped fields don't take a column.
+        #[allow(dead_code)]
+        #[scylla(skip)]
+        bool,
+        i32,
+    );
+
+    #[test]
+    fn test_row_serialization_positional() {
+        let spec = [col("a", ColumnType::Text), col("count(*)", ColumnType::Int)];
+
+        let reference = do_serialize(("Ala ma kota", 42i32), &spec);
+        let row = do_serialize(TestPositionalRow("Ala ma kota".to_owned(), true, 42), &spec);
+
+        assert_eq!(reference, row);
+
+        // Too few columns
+        let spec = [col("a", ColumnType::Text)];
+        let err = do_serialize_err(TestPositionalRow("Ala ma kota".to_owned(), true, 42), &spec);
+        let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+        assert_matches!(
+            &err.kind,
+            BuiltinTypeCheckErrorKind::ValueMissingForColumn { name } if name == "2"
+        );
+    }
+
+    #[test]
+    fn test_row_serialization_with_unset_if_none() {
+        #[derive(SerializeRow)]
+        #[scylla(crate = crate)]
+        struct TestRowWithUnset {
+            a: Option<i32>,
+            #[scylla(unset_if_none)]
+            b: Option<String>,
+        }
+
+        let spec = [col("a", ColumnType::Int), col("b", ColumnType::Text)];
+
+        let reference = do_serialize((None::<i32>, MaybeUnset::<&str>::Unset), &spec);
+        let row = do_serialize(TestRowWithUnset { a: None, b: None }, &spec);
+        assert_eq!(reference, row);
+
+        let reference = do_serialize((42i32, "Ala ma kota"), &spec);
+        let row = do_serialize(
+            TestRowWithUnset {
+                a: Some(42),
+                b: Some("Ala ma kota".to_owned()),
+            },
+            &spec,
+        );
+        assert_eq!(reference, row);
+    }
+
     #[test]
     fn test_row_serialization_with_not_rust_idents() {
         #[derive(SerializeRow, Debug)]
@@ -1668,6 +1942,85 @@ pub(crate) mod tests {
         assert_eq!(reference, row);
     }
 
+    #[derive(SerializeRow, Debug)]
+    #[scylla(crate = crate)]
+    struct TestAuditColumns {
+        created_by: String,
+        #[scylla(rename = "updated")]
+        updated_at: i32,
+    }
+
+    #[derive(SerializeRow, Debug)]
+    #[scylla(crate = crate)]
+    struct TestRowWithFlattenedField {
+        id: i32,
+        #[scylla(flatten)]
+        audit: TestAuditColumns,
+    }
+
+    #[test]
+    fn test_row_serialization_with_flattened_field() {
+        let row = TestRowWithFlattenedField {
+            id: 42,
+            audit: TestAuditColumns {
+                created_by: "Ala".to_owned(),
+                updated_at: 123,
+            },
+        };
+        assert!(!row.is_empty());
+
+        // Columns of the flattened struct may be interleaved with the other columns.
+        let spec = [
+            col("updated", ColumnType::Int),
+            col("id", ColumnType::Int),
+            col("created_by", ColumnType::Text),
+        ];
+        let reference = do_serialize((123i32, 42i32, "Ala"), &spec);
+        assert_eq!(reference, do_serialize(&row, &spec));
+
+        // Columns of the flattened struct are required as well.
+        let spec = [col("id", ColumnType::Int), col("updated", ColumnType::Int)];
+        let err = do_serialize_err(&row, &spec);
+        let err = get_typeck_err(&err);
+        assert_eq!(
+            err.rust_name,
+            std::any::type_name::<TestRowWithFlattenedField>()
+        );
+        assert_matches!(
+            &err.kind,
+            BuiltinTypeCheckErrorKind::ValueMissingForColumn { name } if name == "created_by"
+        );
+
+        // Errors of the flattened fields are reported as errors of the whole row.
+        let spec = [
+            col("id", ColumnType::Int),
+            col("created_by", ColumnType::Int),
+            col("updated", ColumnType::Int),
+        ];
+        let err = do_serialize_err(&row, &spec);
+        let err = get_ser_err(&err);
+        assert_eq!(
+            err.rust_name,
+            std::any::type_name::<TestRowWithFlattenedField>()
+        );
+        assert_matches!(
+            &err.kind,
+            BuiltinSerializationErrorKind::ColumnSerializationFailed { name, .. } if name == "created_by"
+        );
+
+        let spec = [
+            col("id", ColumnType::Int),
+            col("created_by", ColumnType::Text),
+            col("updated", ColumnType::Int),
+            col("deleted", ColumnType::Int),
+        ];
+        let err = do_serialize_err(&row, &spec);
+        assert_matches!(
+            &get_typeck_err(&err).kind,
+            BuiltinTypeCheckErrorKind::NoColumnWithName { name } if name == "deleted"
+        );
+    }
+
     #[test]
     fn test_row_serialization_with_boxed_tuple() {
         let spec = [col("a", ColumnType::Int), col("b", ColumnType::Int)];
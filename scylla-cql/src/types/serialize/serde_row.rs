@@ -0,0 +1,556 @@
+//! Bridge between the [`serde`] data model and rows.
+//!
+//! [`SerdeRow<T>`] can be serialized as the values of a statement from any `T`
+//! that implements [`serde::Serialize`]. Structs and maps are matched with the
+//! bind markers by name, while tuples and sequences are matched by position.
+//! Values are serialized as described in the [`serde_value`](super::serde_value) module.
+
+use serde::ser::{Impossible, Serialize};
+use thiserror::Error;
+
+use super::row::{
+    BuiltinSerializationError, BuiltinSerializationErrorKind, BuiltinTypeCheckError,
+    BuiltinTypeCheckErrorKind, RowSerializationContext, SerializeRow,
+};
+use super::serde_value::{to_cql_value, SerError, SerdeValueError};
+use super::value::SerializeValue;
+use super::{RowWriter, SerializationError};
+use crate::frame::response::result::{ColumnSpec, ColumnType, CqlValue};
+
+pub use crate::types::deserialize::serde_row::SerdeRow;
+
+impl<T: Serialize> SerializeRow for SerdeRow<T> {
+    fn serialize(
+        &self,
+        ctx: &RowSerializationContext<'_>,
+        writer: &mut RowWriter,
+    ) -> Result<(), SerializationError> {
+        let columns = ctx.columns();
+        let values = self
+            .0
+            .serialize(RowSerializer { columns })
+            .map_err(|err| match err {
+                RowError::Message(message) => SerializationError::new(SerdeRowError {
+                    rust_name: std::any::type_name::<T>(),
+                    message,
+                }),
+                RowError::TypeCheck(kind) => SerializationError::new(BuiltinTypeCheckError {
+                    rust_name: std::any::type_name::<T>(),
+                    kind,
+                }),
+                RowError::Serialization(kind) => {
+                    SerializationError::new(BuiltinSerializationError {
+                        rust_name: std::any::type_name::<T>(),
+                        kind,
+                    })
+                }
+            })?;
+
+        for (spec, value) in columns.iter().zip(values) {
+            value.serialize(spec.typ(), writer.make_cell_writer())?;
+        }
+        Ok(())
+    }
+
+    // Finding out requires serializing the value.
+    #[inline]
+    fn is_empty(&self) -> bool {
+        false
+    }
+}
+
+/// Failed to serialize a serde-compatible type as a row.
+#[derive(Debug, Clone, Error)]
+#[error("Failed to serialize {rust_name} as a row via serde: {message}")]
+pub struct SerdeRowError {
+    /// Name of the Rust type being serialized.
+    pub rust_name: &'static str,
+
+    /// The reason why the value couldn't be serialized.
+    pub message: String,
+}
+
+#[derive(Debug)]
+enum RowError {
+    Message(String),
+    TypeCheck(BuiltinTypeCheckErrorKind),
+    Serialization(BuiltinSerializationErrorKind),
+}
+
+impl std::fmt::Display for RowError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RowError::Message(message) => f.write_str(message),
+            RowError::TypeCheck(kind) => kind.fmt(f),
+            RowError::Serialization(kind) => kind.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for RowError {}
+
+impl serde::ser::Error for RowError {
+    fn custom<M: std::fmt::Display>(msg: M) -> Self {
+        RowError::Message(msg.to_string())
+    }
+}
+
+fn unsupported(what: &str) -> RowError {
+    RowError::Message(format!(
+        "cannot serialize {} as a row, expected a struct, a map, a tuple or a sequence",
+        what
+    ))
+}
+
+// Serializes the value of a column, wrapping the error with the column's name.
+fn column_value<V: Serialize + ?Sized>(
+    spec: &ColumnSpec,
+    value: &V,
+) -> Result<Option<CqlValue>, RowError> {
+    to_cql_value(value, spec.typ()).map_err(|SerError(message)| {
+        RowError::Serialization(BuiltinSerializationErrorKind::ColumnSerializationFailed {
+            name: spec.name().to_owned(),
+            err: SerializationError::new(SerdeValueError {
+                rust_name: std::any::type_name::<V>(),
+                cql_type: spec.typ().clone().into_owned(),
+                message,
+            }),
+        })
+    })
+}
+
+// Produces the values of the columns, in the order of the bind markers.
+struct RowSerializer<'t> {
+    columns: &'t [ColumnSpec<'t>],
+}
+
+impl<'t> serde::Serializer for RowSerializer<'t> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowError;
+    type SerializeSeq = PositionalSerializer<'t>;
+    type SerializeTuple = PositionalSerializer<'t>;
+    type SerializeTupleStruct = PositionalSerializer<'t>;
+    type SerializeTupleVariant = Impossible<Self::Ok, RowError>;
+    type SerializeMap = ByNameSerializer<'t>;
+    type SerializeStruct = ByNameSerializer<'t>;
+    type SerializeStructVariant = Impossible<Self::Ok, RowError>;
+
+    fn serialize_bool(self, _v: bool) -> Result<Self::Ok, RowError> {
+        Err(unsupported("a bool"))
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an integer"))
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an integer"))
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an integer"))
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an integer"))
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an integer"))
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an integer"))
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an integer"))
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an integer"))
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Self::Ok, RowError> {
+        Err(unsupported("a float"))
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Self::Ok, RowError> {
+        Err(unsupported("a float"))
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Self::Ok, RowError> {
+        Err(unsupported("a char"))
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<Self::Ok, RowError> {
+        Err(unsupported("a string"))
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Self::Ok, RowError> {
+        Err(unsupported("bytes"))
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, RowError> {
+        Err(unsupported("None"))
+    }
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<Self::Ok, RowError> {
+        value.serialize(self)
+    }
+
+    // The unit is an empty row.
+    fn serialize_unit(self) -> Result<Self::Ok, RowError> {
+        PositionalSerializer::new(self.columns).finish()
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, RowError> {
+        self.serialize_unit()
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an enum"))
+    }
+
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &V,
+    ) -> Result<Self::Ok, RowError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &V,
+    ) -> Result<Self::Ok, RowError> {
+        Err(unsupported("an enum"))
+    }
+
+    fn serialize_seq(self, _len: Option<usize>) -> Result<PositionalSerializer<'t>, RowError> {
+        Ok(PositionalSerializer::new(self.columns))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<PositionalSerializer<'t>, RowError> {
+        Ok(PositionalSerializer::new(self.columns))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<PositionalSerializer<'t>, RowError> {
+        Ok(PositionalSerializer::new(self.columns))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, RowError> {
+        Err(unsupported("an enum"))
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<ByNameSerializer<'t>, RowError> {
+        Ok(ByNameSerializer {
+            columns: self.columns,
+            values: vec![None; self.columns.len()],
+            pending_column: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ByNameSerializer<'t>, RowError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, RowError> {
+        Err(unsupported("an enum"))
+    }
+}
+
+// Matches the values with the bind markers by position.
+struct PositionalSerializer<'t> {
+    columns: &'t [ColumnSpec<'t>],
+    values: Vec<Option<CqlValue>>,
+    // The number of values, which can exceed the number of columns.
+    count: usize,
+}
+
+impl<'t> PositionalSerializer<'t> {
+    fn new(columns: &'t [ColumnSpec<'t>]) -> Self {
+        Self {
+            columns,
+            values: Vec::with_capacity(columns.len()),
+            count: 0,
+        }
+    }
+
+    fn push<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), RowError> {
+        if let Some(spec) = self.columns.get(self.count) {
+            self.values.push(column_value(spec, value)?);
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<Option<CqlValue>>, RowError> {
+        if self.count != self.columns.len() {
+            return Err(RowError::TypeCheck(
+                BuiltinTypeCheckErrorKind::WrongColumnCount {
+                    rust_cols: self.count,
+                    cql_cols: self.columns.len(),
+                },
+            ));
+        }
+        Ok(self.values)
+    }
+}
+
+impl serde::ser::SerializeSeq for PositionalSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowError;
+
+    fn serialize_element<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), RowError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, RowError> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeTuple for PositionalSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowError;
+
+    fn serialize_element<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), RowError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, RowError> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeTupleStruct for PositionalSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowError;
+
+    fn serialize_field<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), RowError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, RowError> {
+        self.finish()
+    }
+}
+
+// Matches the values with the bind markers by name.
+struct ByNameSerializer<'t> {
+    columns: &'t [ColumnSpec<'t>],
+    // `None` for the columns which haven't been serialized yet.
+    values: Vec<Option<Option<CqlValue>>>,
+    // The index of the column named by the last serialized map key.
+    pending_column: Option<usize>,
+}
+
+impl ByNameSerializer<'_> {
+    fn set_column(&mut self, name: &str) -> Result<(), RowError> {
+        let index = self
+            .columns
+            .iter()
+            .position(|spec| spec.name() == name)
+            .ok_or_else(|| {
+                RowError::TypeCheck(BuiltinTypeCheckErrorKind::NoColumnWithName {
+                    name: name.to_owned(),
+                })
+            })?;
+        self.pending_column = Some(index);
+        Ok(())
+    }
+
+    fn set_value<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), RowError> {
+        let index = self
+            .pending_column
+            .take()
+            .ok_or_else(|| RowError::Message("map value serialized before its key".to_owned()))?;
+        self.values[index] = Some(column_value(&self.columns[index], value)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Vec<Option<CqlValue>>, RowError> {
+        self.columns
+            .iter()
+            .zip(self.values)
+            .map(|(spec, value)| {
+                value.ok_or_else(|| {
+                    RowError::TypeCheck(BuiltinTypeCheckErrorKind::ValueMissingForColumn {
+                        name: spec.name().to_owned(),
+                    })
+                })
+            })
+            .collect()
+    }
+}
+
+impl serde::ser::SerializeMap for ByNameSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowError;
+
+    fn serialize_key<K: Serialize + ?Sized>(&mut self, key: &K) -> Result<(), RowError> {
+        match to_cql_value(key, &ColumnType::Text) {
+            Ok(Some(CqlValue::Text(name))) => self.set_column(&name),
+            _ => Err(RowError::Message(
+                "the keys of a map serialized as a row must be strings".to_owned(),
+            )),
+        }
+    }
+
+    fn serialize_value<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), RowError> {
+        self.set_value(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, RowError> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeStruct for ByNameSerializer<'_> {
+    type Ok = Vec<Option<CqlValue>>;
+    type Error = RowError;
+
+    fn serialize_field<V: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), RowError> {
+        self.set_column(key)?;
+        self.set_value(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, RowError> {
+        self.finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use assert_matches::assert_matches;
+    use serde::Serialize;
+
+    use super::{SerdeRow, SerdeRowError};
+    use crate::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
+    use crate::types::serialize::row::tests::do_serialize;
+    use crate::types::serialize::row::{
+        BuiltinSerializationError, BuiltinSerializationErrorKind, BuiltinTypeCheckError,
+        BuiltinTypeCheckErrorKind, RowSerializationContext, SerializeRow,
+    };
+    use crate::types::serialize::serde_value::SerdeValueError;
+    use crate::types::serialize::{RowWriter, SerializationError};
+
+    #[derive(Serialize)]
+    struct User {
+        id: i32,
+        name: Option<String>,
+    }
+
+    fn col<'a>(name: &'a str, typ: ColumnType<'a>) -> ColumnSpec<'a> {
+        ColumnSpec::borrowed(name, typ, TableSpec::borrowed("ks", "tbl"))
+    }
+
+    fn serialize_err<T: SerializeRow>(row: T, columns: &[ColumnSpec]) -> SerializationError {
+        let ctx = RowSerializationContext { columns };
+        let mut data = Vec::new();
+        row.serialize(&ctx, &mut RowWriter::new(&mut data))
+            .unwrap_err()
+    }
+
+    #[test]
+    fn test_struct_and_tuple() {
+        let specs = [col("name", ColumnType::Text), col("id", ColumnType::BigInt)];
+
+        // Structs and maps are matched by name.
+        let user = User {
+            id: 7,
+            name: Some("Ann".to_owned()),
+        };
+        let expected = do_serialize(("Ann", 7_i64), &specs);
+        assert_eq!(do_serialize(SerdeRow(&user), &specs), expected);
+        let map = BTreeMap::from([("id", Some(7_i64)), ("name", None)]);
+        assert_eq!(
+            do_serialize(SerdeRow(&map), &specs),
+            do_serialize((None::<String>, 7_i64), &specs)
+        );
+
+        // Tuples are matched by position.
+        assert_eq!(do_serialize(SerdeRow(("Ann", 7)), &specs), expected);
+        assert_eq!(do_serialize(SerdeRow(()), &[]), Vec::<u8>::new());
+    }
+
+    #[test]
+    fn test_errors() {
+        let specs = [col("id", ColumnType::Int), col("name", ColumnType::Text)];
+
+        let err = serialize_err(SerdeRow(BTreeMap::from([("id", 1_i32)])), &specs);
+        let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+        assert_matches!(
+            &err.kind,
+            BuiltinTypeCheckErrorKind::ValueMissingForColumn { name } if name == "name"
+        );
+
+        let err = serialize_err(
+            SerdeRow(BTreeMap::from([("id", "1"), ("email", "")])),
+            &specs,
+        );
+        let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+        assert_matches!(
+            &err.kind,
+            BuiltinTypeCheckErrorKind::NoColumnWithName { name } if name == "email"
+        );
+
+        let err = serialize_err(SerdeRow((1_i32,)), &specs);
+        let err = err.0.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+        assert_matches!(
+            err.kind,
+            BuiltinTypeCheckErrorKind::WrongColumnCount {
+                rust_cols: 1,
+                cql_cols: 2
+            }
+        );
+
+        let err = serialize_err(SerdeRow(("Ann", "Ann")), &specs);
+        let err = err.0.downcast_ref::<BuiltinSerializationError>().unwrap();
+        let BuiltinSerializationErrorKind::ColumnSerializationFailed { name, err } = &err.kind
+        else {
+            panic!("unexpected error kind: {}", err.kind)
+        };
+        assert_eq!(name, "id");
+        assert_matches!(err.0.downcast_ref::<SerdeValueError>(), Some(_));
+
+        let err = serialize_err(SerdeRow(42_i32), &specs);
+        let err = err.0.downcast_ref::<SerdeRowError>().unwrap();
+        assert_eq!(err.rust_name, std::any::type_name::<i32>());
+    }
+}
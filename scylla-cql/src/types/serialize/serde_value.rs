@@ -0,0 +1,959 @@
+//! Bridge between the [`serde`] data model and CQL values.
+//!
+//! [`SerdeValue<T>`] can be serialized to a CQL value from any `T` that
+//! implements [`serde::Serialize`]. The serde data model is mapped onto
+//! the CQL type of the bind marker as follows:
+//!
+//! | CQL type                              | serde data model                                  |
+//! |---------------------------------------|---------------------------------------------------|
+//! | `ascii`, `text`                       | string or char                                    |
+//! | `boolean`                             | bool                                              |
+//! | `blob`                                | bytes, or a sequence of `u8`                      |
+//! | `tinyint`, `smallint`, `int`, `bigint`, `counter` | any integer within the range of the type |
+//! | `varint`, `decimal`                   | any integer, or a string, e.g. `"-12.345"`        |
+//! | `float`, `double`                     | `f32` or `f64`                                    |
+//! | `timestamp`                           | integer - milliseconds since the Unix epoch       |
+//! | `date`                                | integer - days since -5877641-06-23 (2^31 days before the Unix epoch) |
+//! | `time`                                | integer - nanoseconds since midnight              |
+//! | `duration`                            | map or struct with `months`, `days` and `nanoseconds` keys |
+//! | `uuid`, `timeuuid`, `inet`            | string                                            |
+//! | `list`, `set`                         | sequence                                          |
+//! | `tuple`                               | tuple or sequence of the same length              |
+//! | `map`                                 | map                                               |
+//! | UDT                                   | struct or map from field names to field values; missing fields are null |
+//! | null                                  | `None` or unit                                    |
+//!
+//! Unit variants of enums are serialized as their names. This is the inverse
+//! of the mapping used by [`SerdeValue`]'s `DeserializeValue` implementation.
+
+use std::borrow::Cow;
+use std::fmt::Display;
+use std::net::IpAddr;
+
+use serde::ser::{Impossible, Serialize};
+use thiserror::Error;
+use uuid::Uuid;
+
+use super::value::SerializeValue;
+use super::writers::WrittenCellProof;
+use super::{CellWriter, SerializationError};
+use crate::frame::response::result::{ColumnType, CqlValue};
+use crate::frame::value::{
+    Counter, CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlTimeuuid, CqlVarint,
+};
+
+pub use crate::types::deserialize::serde_value::SerdeValue;
+
+impl<T: Serialize> SerializeValue for SerdeValue<T> {
+    fn serialize<'b>(
+        &self,
+        typ: &ColumnType,
+        writer: CellWriter<'b>,
+    ) -> Result<WrittenCellProof<'b>, SerializationError> {
+        let value = to_cql_value(&self.0, typ).map_err(|err| {
+            SerializationError::new(SerdeValueError {
+                rust_name: std::any::type_name::<T>(),
+                cql_type: typ.clone().into_owned(),
+                message: err.0,
+            })
+        })?;
+        value.serialize(typ, writer)
+    }
+}
+
+/// Failed to serialize a serde-compatible type into a CQL value.
+#[derive(Debug, Clone, Error)]
+#[error("Failed to serialize {rust_name} into CQL type {cql_type:?} via serde: {message}")]
+pub struct SerdeValueError {
+    /// Name of the Rust type being serialized.
+    pub rust_name: &'static str,
+
+    /// The CQL type of the bind marker.
+    pub cql_type: ColumnType<'static>,
+
+    /// The reason why the value couldn't be serialized.
+    pub message: String,
+}
+
+// Converts the value into a `CqlValue` of the given type, `None` being a null.
+pub(super) fn to_cql_value<T: Serialize + ?Sized>(
+    value: &T,
+    typ: &ColumnType,
+) -> Result<Option<CqlValue>, SerError> {
+    value.serialize(CqlValueSerializer { typ })
+}
+
+// The error type of the serializers, carrying the message
+// to be put into `SerdeValueError`.
+#[derive(Debug)]
+pub(super) struct SerError(pub(super) String);
+
+impl Display for SerError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for SerError {}
+
+impl serde::ser::Error for SerError {
+    fn custom<M: Display>(msg: M) -> Self {
+        SerError(msg.to_string())
+    }
+}
+
+fn mismatch(what: &str, typ: &ColumnType) -> SerError {
+    SerError(format!("cannot serialize {} as CQL type {:?}", what, typ))
+}
+
+fn out_of_range(value: impl Display, typ: &ColumnType) -> SerError {
+    SerError(format!("{} is out of range of CQL type {:?}", value, typ))
+}
+
+// Types used to serialize the elements of blobs, the field names of UDTs
+// and durations, and the fields of durations.
+const BLOB_ELEMENT_TYPE: ColumnType<'static> = ColumnType::Int;
+const TEXT_TYPE: ColumnType<'static> = ColumnType::Text;
+const DURATION_FIELD_TYPES: [(&str, ColumnType<'static>); 3] = [
+    ("months", ColumnType::Int),
+    ("days", ColumnType::Int),
+    ("nanoseconds", ColumnType::BigInt),
+];
+
+struct CqlValueSerializer<'t> {
+    typ: &'t ColumnType<'t>,
+}
+
+impl CqlValueSerializer<'_> {
+    fn serialize_integer(self, v: i128) -> Result<Option<CqlValue>, SerError> {
+        let typ = self.typ;
+        let value = match typ {
+            ColumnType::TinyInt => {
+                CqlValue::TinyInt(v.try_into().map_err(|_| out_of_range(v, typ))?)
+            }
+            ColumnType::SmallInt => {
+                CqlValue::SmallInt(v.try_into().map_err(|_| out_of_range(v, typ))?)
+            }
+            ColumnType::Int => CqlValue::Int(v.try_into().map_err(|_| out_of_range(v, typ))?),
+            ColumnType::BigInt => CqlValue::BigInt(v.try_into().map_err(|_| out_of_range(v, typ))?),
+            ColumnType::Counter => {
+                CqlValue::Counter(Counter(v.try_into().map_err(|_| out_of_range(v, typ))?))
+            }
+            ColumnType::Timestamp => CqlValue::Timestamp(CqlTimestamp(
+                v.try_into().map_err(|_| out_of_range(v, typ))?,
+            )),
+            ColumnType::Time => {
+                CqlValue::Time(CqlTime(v.try_into().map_err(|_| out_of_range(v, typ))?))
+            }
+            ColumnType::Date => {
+                CqlValue::Date(CqlDate(v.try_into().map_err(|_| out_of_range(v, typ))?))
+            }
+            ColumnType::Varint => {
+                CqlValue::Varint(CqlVarint::from_signed_bytes_be(i128_to_signed_be_bytes(v)))
+            }
+            ColumnType::Decimal => CqlValue::Decimal(
+                CqlDecimal::from_signed_be_bytes_and_exponent(i128_to_signed_be_bytes(v), 0),
+            ),
+            _ => return Err(mismatch("an integer", typ)),
+        };
+        Ok(Some(value))
+    }
+}
+
+impl<'t> serde::Serializer for CqlValueSerializer<'t> {
+    type Ok = Option<CqlValue>;
+    type Error = SerError;
+    type SerializeSeq = SeqSerializer<'t>;
+    type SerializeTuple = SeqSerializer<'t>;
+    type SerializeTupleStruct = SeqSerializer<'t>;
+    type SerializeTupleVariant = Impossible<Self::Ok, SerError>;
+    type SerializeMap = MapSerializer<'t>;
+    type SerializeStruct = MapSerializer<'t>;
+    type SerializeStructVariant = Impossible<Self::Ok, SerError>;
+
+    fn serialize_bool(self, v: bool) -> Result<Self::Ok, SerError> {
+        match self.typ {
+            ColumnType::Boolean => Ok(Some(CqlValue::Boolean(v))),
+            typ => Err(mismatch("a bool", typ)),
+        }
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_i128(self, v: i128) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v)
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Self::Ok, SerError> {
+        self.serialize_integer(v.into())
+    }
+
+    fn serialize_u128(self, v: u128) -> Result<Self::Ok, SerError> {
+        let v = i128::try_from(v).map_err(|_| out_of_range(v, self.typ))?;
+        self.serialize_integer(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Self::Ok, SerError> {
+        match self.typ {
+            ColumnType::Float => Ok(Some(CqlValue::Float(v))),
+            ColumnType::Double => Ok(Some(CqlValue::Double(v.into()))),
+            typ => Err(mismatch("a float", typ)),
+        }
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Self::Ok, SerError> {
+        match self.typ {
+            ColumnType::Float => Ok(Some(CqlValue::Float(v as f32))),
+            ColumnType::Double => Ok(Some(CqlValue::Double(v))),
+            typ => Err(mismatch("a float", typ)),
+        }
+    }
+
+    fn serialize_char(self, v: char) -> Result<Self::Ok, SerError> {
+        self.serialize_str(v.encode_utf8(&mut [0; 4]))
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Self::Ok, SerError> {
+        let typ = self.typ;
+        let invalid = |what: &str| SerError(format!("{:?} is not a valid {}", v, what));
+        let value = match typ {
+            ColumnType::Ascii if v.is_ascii() => CqlValue::Ascii(v.to_owned()),
+            ColumnType::Ascii => return Err(invalid("ASCII string")),
+            ColumnType::Text => CqlValue::Text(v.to_owned()),
+            ColumnType::Uuid => CqlValue::Uuid(v.parse().map_err(|_| invalid("UUID"))?),
+            ColumnType::Timeuuid => CqlValue::Timeuuid(CqlTimeuuid::from(
+                v.parse::<Uuid>().map_err(|_| invalid("UUID"))?,
+            )),
+            ColumnType::Inet => {
+                CqlValue::Inet(v.parse::<IpAddr>().map_err(|_| invalid("IP address"))?)
+            }
+            ColumnType::Varint => {
+                let bytes = parse_varint(v).ok_or_else(|| invalid("integer"))?;
+                CqlValue::Varint(CqlVarint::from_signed_bytes_be(bytes))
+            }
+            ColumnType::Decimal => {
+                let (bytes, scale) = parse_decimal(v).ok_or_else(|| invalid("decimal"))?;
+                CqlValue::Decimal(CqlDecimal::from_signed_be_bytes_and_exponent(bytes, scale))
+            }
+            _ => return Err(mismatch("a string", typ)),
+        };
+        Ok(Some(value))
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Self::Ok, SerError> {
+        match self.typ {
+            ColumnType::Blob => Ok(Some(CqlValue::Blob(v.to_vec()))),
+            typ => Err(mismatch("bytes", typ)),
+        }
+    }
+
+    fn serialize_none(self) -> Result<Self::Ok, SerError> {
+        Ok(None)
+    }
+
+    fn serialize_some<V: Serialize + ?Sized>(self, value: &V) -> Result<Self::Ok, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Self::Ok, SerError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Self::Ok, SerError> {
+        Ok(None)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Self::Ok, SerError> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<V: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &V,
+    ) -> Result<Self::Ok, SerError> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<V: Serialize + ?Sized>(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _value: &V,
+    ) -> Result<Self::Ok, SerError> {
+        Err(mismatch(
+            &format!("enum variant {}::{}", name, variant),
+            self.typ,
+        ))
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<SeqSerializer<'t>, SerError> {
+        match self.typ {
+            ColumnType::List(_) | ColumnType::Set(_) | ColumnType::Tuple(_) | ColumnType::Blob => {
+                Ok(SeqSerializer {
+                    typ: self.typ,
+                    elements: Vec::with_capacity(len.unwrap_or(0)),
+                })
+            }
+            typ => Err(mismatch("a sequence", typ)),
+        }
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<SeqSerializer<'t>, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<SeqSerializer<'t>, SerError> {
+        self.serialize_seq(Some(len))
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, SerError> {
+        Err(mismatch(
+            &format!("enum variant {}::{}", name, variant),
+            self.typ,
+        ))
+    }
+
+    fn serialize_map(self, len: Option<usize>) -> Result<MapSerializer<'t>, SerError> {
+        let kind = match self.typ {
+            ColumnType::Map(key_type, value_type) => MapKind::Map {
+                key_type,
+                value_type,
+                entries: Vec::with_capacity(len.unwrap_or(0)),
+            },
+            ColumnType::UserDefinedType {
+                type_name,
+                keyspace,
+                field_types,
+            } => MapKind::Udt {
+                type_name,
+                keyspace,
+                field_types,
+                values: vec![None; field_types.len()],
+            },
+            ColumnType::Duration => MapKind::Duration { values: [None; 3] },
+            typ => return Err(mismatch("a map or a struct", typ)),
+        };
+        Ok(MapSerializer {
+            kind,
+            pending_key: None,
+        })
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<MapSerializer<'t>, SerError> {
+        self.serialize_map(Some(len))
+    }
+
+    fn serialize_struct_variant(
+        self,
+        name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, SerError> {
+        Err(mismatch(
+            &format!("enum variant {}::{}", name, variant),
+            self.typ,
+        ))
+    }
+}
+
+// Serializes lists, sets, tuples and blobs.
+pub(super) struct SeqSerializer<'t> {
+    typ: &'t ColumnType<'t>,
+    elements: Vec<Option<CqlValue>>,
+}
+
+impl SeqSerializer<'_> {
+    fn push<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), SerError> {
+        let element_type = match self.typ {
+            ColumnType::List(element_type) | ColumnType::Set(element_type) => element_type,
+            ColumnType::Tuple(element_types) => {
+                element_types.get(self.elements.len()).ok_or_else(|| {
+                    SerError(format!("too many elements for CQL type {:?}", self.typ))
+                })?
+            }
+            _ => &BLOB_ELEMENT_TYPE,
+        };
+        self.elements.push(to_cql_value(value, element_type)?);
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<CqlValue>, SerError> {
+        let typ = self.typ;
+        let non_null = |elements: Vec<Option<CqlValue>>| {
+            elements
+                .into_iter()
+                .map(|element| {
+                    element.ok_or_else(|| {
+                        SerError(format!(
+                            "null elements are not allowed in CQL type {:?}",
+                            typ
+                        ))
+                    })
+                })
+                .collect::<Result<Vec<_>, _>>()
+        };
+        let value = match typ {
+            ColumnType::List(_) => CqlValue::List(non_null(self.elements)?),
+            ColumnType::Set(_) => CqlValue::Set(non_null(self.elements)?),
+            ColumnType::Tuple(element_types) => {
+                if self.elements.len() != element_types.len() {
+                    return Err(SerError(format!(
+                        "expected {} elements for CQL type {:?}, got {}",
+                        element_types.len(),
+                        typ,
+                        self.elements.len()
+                    )));
+                }
+                CqlValue::Tuple(self.elements)
+            }
+            _ => CqlValue::Blob(
+                non_null(self.elements)?
+                    .into_iter()
+                    .map(|element| match element {
+                        CqlValue::Int(byte) => {
+                            u8::try_from(byte).map_err(|_| out_of_range(byte, &ColumnType::Blob))
+                        }
+                        _ => Err(mismatch("a sequence of non-integers", &ColumnType::Blob)),
+                    })
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        Ok(Some(value))
+    }
+}
+
+impl serde::ser::SerializeSeq for SeqSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerError;
+
+    fn serialize_element<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeTuple for SeqSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerError;
+
+    fn serialize_element<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeTupleStruct for SeqSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerError;
+
+    fn serialize_field<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), SerError> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        self.finish()
+    }
+}
+
+enum MapKind<'t> {
+    Map {
+        key_type: &'t ColumnType<'t>,
+        value_type: &'t ColumnType<'t>,
+        entries: Vec<(CqlValue, CqlValue)>,
+    },
+    Udt {
+        type_name: &'t Cow<'t, str>,
+        keyspace: &'t Cow<'t, str>,
+        field_types: &'t [(Cow<'t, str>, ColumnType<'t>)],
+        values: Vec<Option<CqlValue>>,
+    },
+    Duration {
+        values: [Option<i64>; 3],
+    },
+}
+
+// Serializes maps, UDTs and durations.
+pub(super) struct MapSerializer<'t> {
+    kind: MapKind<'t>,
+    // For maps, the serialized key, and for UDTs and durations, the index of the field.
+    pending_key: Option<PendingKey>,
+}
+
+enum PendingKey {
+    Value(CqlValue),
+    Field(usize),
+}
+
+impl MapSerializer<'_> {
+    fn set_key<K: Serialize + ?Sized>(&mut self, key: &K) -> Result<(), SerError> {
+        let pending_key = match &self.kind {
+            MapKind::Map { key_type, .. } => PendingKey::Value(
+                to_cql_value(key, key_type)?
+                    .ok_or_else(|| SerError("null map keys are not allowed".to_owned()))?,
+            ),
+            MapKind::Udt { .. } | MapKind::Duration { .. } => {
+                match to_cql_value(key, &TEXT_TYPE)? {
+                    Some(CqlValue::Text(name)) => PendingKey::Field(self.field_index(&name)?),
+                    _ => return Err(SerError("field names must be strings".to_owned())),
+                }
+            }
+        };
+        self.pending_key = Some(pending_key);
+        Ok(())
+    }
+
+    fn field_index(&self, name: &str) -> Result<usize, SerError> {
+        let index = match &self.kind {
+            MapKind::Udt { field_types, .. } => field_types.iter().position(|(n, _)| n == name),
+            MapKind::Duration { .. } => DURATION_FIELD_TYPES.iter().position(|(n, _)| *n == name),
+            MapKind::Map { .. } => None,
+        };
+        index.ok_or_else(|| SerError(format!("no field named {:?} in the CQL type", name)))
+    }
+
+    fn set_value<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), SerError> {
+        let key = self
+            .pending_key
+            .take()
+            .ok_or_else(|| SerError("map value serialized before its key".to_owned()))?;
+        match (&mut self.kind, key) {
+            (
+                MapKind::Map {
+                    value_type,
+                    entries,
+                    ..
+                },
+                PendingKey::Value(key),
+            ) => {
+                let value = to_cql_value(value, value_type)?
+                    .ok_or_else(|| SerError("null map values are not allowed".to_owned()))?;
+                entries.push((key, value));
+            }
+            (
+                MapKind::Udt {
+                    field_types,
+                    values,
+                    ..
+                },
+                PendingKey::Field(index),
+            ) => {
+                values[index] = to_cql_value(value, &field_types[index].1)?;
+            }
+            (MapKind::Duration { values }, PendingKey::Field(index)) => {
+                let (name, typ) = &DURATION_FIELD_TYPES[index];
+                values[index] = match to_cql_value(value, typ)? {
+                    Some(CqlValue::Int(v)) => Some(v.into()),
+                    Some(CqlValue::BigInt(v)) => Some(v),
+                    _ => return Err(SerError(format!("duration field {} is null", name))),
+                };
+            }
+            _ => unreachable!("the key kind always matches the map kind"),
+        }
+        Ok(())
+    }
+
+    fn finish(self) -> Result<Option<CqlValue>, SerError> {
+        let value = match self.kind {
+            MapKind::Map { entries, .. } => CqlValue::Map(entries),
+            MapKind::Udt {
+                type_name,
+                keyspace,
+                field_types,
+                values,
+            } => CqlValue::UserDefinedType {
+                keyspace: keyspace.to_string(),
+                type_name: type_name.to_string(),
+                fields: field_types
+                    .iter()
+                    .map(|(name, _)| name.to_string())
+                    .zip(values)
+                    .collect(),
+            },
+            MapKind::Duration { values } => {
+                let [Some(months), Some(days), Some(nanoseconds)] = values else {
+                    return Err(SerError(
+                        "a duration requires months, days and nanoseconds".to_owned(),
+                    ));
+                };
+                CqlValue::Duration(CqlDuration {
+                    months: months as i32,
+                    days: days as i32,
+                    nanoseconds,
+                })
+            }
+        };
+        Ok(Some(value))
+    }
+}
+
+impl serde::ser::SerializeMap for MapSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerError;
+
+    fn serialize_key<K: Serialize + ?Sized>(&mut self, key: &K) -> Result<(), SerError> {
+        self.set_key(key)
+    }
+
+    fn serialize_value<V: Serialize + ?Sized>(&mut self, value: &V) -> Result<(), SerError> {
+        self.set_value(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        self.finish()
+    }
+}
+
+impl serde::ser::SerializeStruct for MapSerializer<'_> {
+    type Ok = Option<CqlValue>;
+    type Error = SerError;
+
+    fn serialize_field<V: Serialize + ?Sized>(
+        &mut self,
+        key: &'static str,
+        value: &V,
+    ) -> Result<(), SerError> {
+        self.set_key(key)?;
+        self.set_value(value)
+    }
+
+    fn end(self) -> Result<Self::Ok, SerError> {
+        self.finish()
+    }
+}
+
+// Strips the leading bytes of a two's complement, big-endian integer
+// which only extend its sign.
+fn strip_sign_extension(bytes: &[u8]) -> &[u8] {
+    let redundant = bytes
+        .windows(2)
+        .take_while(|pair| {
+            (pair[0] == 0x00 && pair[1] & 0x80 == 0) || (pair[0] == 0xff && pair[1] & 0x80 != 0)
+        })
+        .count();
+    &bytes[redundant..]
+}
+
+fn i128_to_signed_be_bytes(v: i128) -> Vec<u8> {
+    strip_sign_extension(&v.to_be_bytes()).to_vec()
+}
+
+// Parses a decimal integer of arbitrary length into its two's complement,
+// big-endian representation.
+fn parse_varint(s: &str) -> Option<Vec<u8>> {
+    let (negative, digits) = match s.strip_prefix('-') {
+        Some(digits) => (true, digits),
+        None => (false, s.strip_prefix('+').unwrap_or(s)),
+    };
+    if digits.is_empty() || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+
+    // Multiply the magnitude by 10 and add each digit, least significant byte last.
+    let mut magnitude = vec![0u8];
+    for digit in digits.bytes() {
+        let mut carry = u16::from(digit - b'0');
+        for b in magnitude.iter_mut().rev() {
+            let current = u16::from(*b) * 10 + carry;
+            *b = current as u8;
+            carry = current >> 8;
+        }
+        if carry != 0 {
+            magnitude.insert(0, carry as u8);
+        }
+    }
+    // Make room for the sign bit.
+    if magnitude[0] & 0x80 != 0 {
+        magnitude.insert(0, 0);
+    }
+
+    if negative {
+        // Negate: invert all bits and add one.
+        for b in magnitude.iter_mut() {
+            *b = !*b;
+        }
+        for b in magnitude.iter_mut().rev() {
+            let (sum, overflow) = b.overflowing_add(1);
+            *b = sum;
+            if !overflow {
+                break;
+            }
+        }
+    }
+    Some(strip_sign_extension(&magnitude).to_vec())
+}
+
+// Parses a decimal number, e.g. `-12.345`, into its unscaled value and scale.
+fn parse_decimal(s: &str) -> Option<(Vec<u8>, i32)> {
+    let (integral, fractional) = s.split_once('.').unwrap_or((s, ""));
+    if !fractional.bytes().all(|b| b.is_ascii_digit()) {
+        return None;
+    }
+    let unscaled = parse_varint(&format!("{}{}", integral, fractional))?;
+    Some((unscaled, fractional.len().try_into().ok()?))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::BTreeMap;
+
+    use assert_matches::assert_matches;
+    use serde::Serialize;
+
+    use super::{
+        i128_to_signed_be_bytes, parse_decimal, parse_varint, SerdeValue, SerdeValueError,
+    };
+    use crate::frame::response::result::{ColumnType, CqlValue};
+    use crate::frame::value::{CqlDecimal, CqlDuration, CqlVarint};
+    use crate::types::deserialize::value::tests::udt_def_with_fields;
+    use crate::types::serialize::value::tests::do_serialize;
+    use crate::types::serialize::value::SerializeValue;
+    use crate::types::serialize::{CellWriter, SerializationError};
+
+    fn serialize_err<T: SerializeValue>(typ: &ColumnType, value: T) -> SerializationError {
+        let mut v = Vec::new();
+        value.serialize(typ, CellWriter::new(&mut v)).unwrap_err()
+    }
+
+    #[test]
+    fn test_big_number_parsing() {
+        assert_eq!(parse_varint("0").unwrap(), [0x00]);
+        assert_eq!(parse_varint("127").unwrap(), [0x7f]);
+        assert_eq!(parse_varint("128").unwrap(), [0x00, 0x80]);
+        assert_eq!(parse_varint("-128").unwrap(), [0x80]);
+        assert_eq!(parse_varint("-256").unwrap(), [0xff, 0x00]);
+        assert_eq!(
+            parse_varint(&i128::MIN.to_string()).unwrap(),
+            i128::MIN.to_be_bytes()
+        );
+        assert_eq!(parse_varint(""), None);
+        assert_eq!(parse_varint("12a"), None);
+
+        assert_eq!(parse_decimal("123.45").unwrap(), (vec![0x30, 0x39], 2));
+        assert_eq!(parse_decimal("-123.45").unwrap(), (vec![0xcf, 0xc7], 2));
+        assert_eq!(parse_decimal("5").unwrap(), (vec![0x05], 0));
+        assert_eq!(parse_decimal("1.2.3"), None);
+
+        assert_eq!(i128_to_signed_be_bytes(0), [0x00]);
+        assert_eq!(i128_to_signed_be_bytes(-1), [0xff]);
+        assert_eq!(i128_to_signed_be_bytes(128), [0x00, 0x80]);
+        assert_eq!(i128_to_signed_be_bytes(-129), [0xff, 0x7f]);
+    }
+
+    // The column type, the value serialized through serde and the expected value.
+    type ScalarCase = (
+        ColumnType<'static>,
+        Box<dyn SerializeValue>,
+        Box<dyn SerializeValue>,
+    );
+
+    #[test]
+    fn test_scalars() {
+        let cases: Vec<ScalarCase> = vec![
+            (
+                ColumnType::BigInt,
+                Box::new(SerdeValue(42_u8)),
+                Box::new(42_i64),
+            ),
+            (ColumnType::Text, Box::new(SerdeValue('x')), Box::new("x")),
+            (
+                ColumnType::Varint,
+                Box::new(SerdeValue("-256")),
+                Box::new(CqlVarint::from_signed_bytes_be(vec![0xff, 0x00])),
+            ),
+            (
+                ColumnType::Decimal,
+                Box::new(SerdeValue("123.45")),
+                Box::new(CqlDecimal::from_signed_be_bytes_and_exponent(
+                    vec![0x30, 0x39],
+                    2,
+                )),
+            ),
+            (
+                ColumnType::Uuid,
+                Box::new(SerdeValue("00000000-0000-0000-0000-000012345678")),
+                Box::new(uuid::Uuid::from_u128(0x1234_5678)),
+            ),
+            (
+                ColumnType::Double,
+                Box::new(SerdeValue(1.5_f32)),
+                Box::new(1.5_f64),
+            ),
+            (
+                ColumnType::Int,
+                Box::new(SerdeValue(None::<i32>)),
+                Box::new(None::<i32>),
+            ),
+            (
+                ColumnType::Int,
+                Box::new(SerdeValue(())),
+                Box::new(None::<i32>),
+            ),
+        ];
+        for (typ, value, expected) in cases {
+            assert_eq!(
+                do_serialize(value, &typ),
+                do_serialize(expected, &typ),
+                "{:?}",
+                typ
+            );
+        }
+    }
+
+    #[test]
+    fn test_collections_and_structs() {
+        #[derive(Serialize)]
+        #[serde(rename_all = "lowercase")]
+        enum Color {
+            Red,
+        }
+
+        #[derive(Serialize)]
+        struct Address {
+            street: String,
+            tags: Vec<String>,
+            color: Color,
+        }
+
+        let list_typ = ColumnType::List(Box::new(ColumnType::BigInt));
+        assert_eq!(
+            do_serialize(SerdeValue(vec![1_i32, 2, 3]), &list_typ),
+            do_serialize(vec![1_i64, 2, 3], &list_typ)
+        );
+
+        let map_typ = ColumnType::Map(Box::new(ColumnType::Text), Box::new(ColumnType::Int));
+        let map = BTreeMap::from([("a", 1_i32), ("b", 2)]);
+        assert_eq!(
+            do_serialize(SerdeValue(&map), &map_typ),
+            do_serialize(&map, &map_typ)
+        );
+
+        let tuple_typ = ColumnType::Tuple(vec![ColumnType::Int, ColumnType::Text]);
+        assert_eq!(
+            do_serialize(SerdeValue((1_i32, None::<String>)), &tuple_typ),
+            do_serialize((1_i32, None::<String>), &tuple_typ)
+        );
+
+        let blob_typ = ColumnType::Blob;
+        assert_eq!(
+            do_serialize(SerdeValue(vec![1_u8, 2, 255]), &blob_typ),
+            do_serialize(vec![1_u8, 2, 255], &blob_typ)
+        );
+
+        let duration = CqlDuration {
+            months: 1,
+            days: 2,
+            nanoseconds: 3,
+        };
+        let duration_map = BTreeMap::from([("months", 1_i64), ("days", 2), ("nanoseconds", 3)]);
+        assert_eq!(
+            do_serialize(SerdeValue(duration_map), &ColumnType::Duration),
+            do_serialize(duration, &ColumnType::Duration)
+        );
+
+        // Fields are matched by name, missing ones are null.
+        let udt_typ = udt_def_with_fields([
+            ("color", ColumnType::Text),
+            ("street", ColumnType::Text),
+            ("number", ColumnType::Int),
+            ("tags", ColumnType::List(Box::new(ColumnType::Text))),
+        ]);
+        let address = Address {
+            street: "Main".to_owned(),
+            tags: vec!["home".to_owned()],
+            color: Color::Red,
+        };
+        let expected = CqlValue::UserDefinedType {
+            keyspace: "ks".to_owned(),
+            type_name: "udt".to_owned(),
+            fields: vec![
+                ("color".to_owned(), Some(CqlValue::Text("red".to_owned()))),
+                ("street".to_owned(), Some(CqlValue::Text("Main".to_owned()))),
+                ("number".to_owned(), None),
+                (
+                    "tags".to_owned(),
+                    Some(CqlValue::List(vec![CqlValue::Text("home".to_owned())])),
+                ),
+            ],
+        };
+        assert_eq!(
+            do_serialize(SerdeValue(address), &udt_typ),
+            do_serialize(expected, &udt_typ)
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let err = serialize_err(&ColumnType::TinyInt, SerdeValue(300_i32));
+        let err = err.0.downcast_ref::<SerdeValueError>().unwrap();
+        assert_eq!(err.rust_name, std::any::type_name::<i32>());
+        assert_eq!(err.cql_type, ColumnType::TinyInt);
+        assert!(err.message.contains("out of range"), "{}", err.message);
+
+        let err = serialize_err(&ColumnType::Int, SerdeValue("foo"));
+        assert_matches!(err.0.downcast_ref::<SerdeValueError>(), Some(_));
+
+        let list_typ = ColumnType::List(Box::new(ColumnType::Int));
+        let err = serialize_err(&list_typ, SerdeValue(vec![Some(1_i32), None]));
+        let err = err.0.downcast_ref::<SerdeValueError>().unwrap();
+        assert!(err.message.contains("null"), "{}", err.message);
+
+        let udt_typ = udt_def_with_fields([("a", ColumnType::Int)]);
+        let err = serialize_err(&udt_typ, SerdeValue(BTreeMap::from([("b", 1_i32)])));
+        let err = err.0.downcast_ref::<SerdeValueError>().unwrap();
+        assert!(err.message.contains("\"b\""), "{}", err.message);
+    }
+}
@@ -17,8 +17,9 @@ use crate::frame::response::result::{ColumnType, CqlValue};
 use crate::frame::types::vint_encode;
 #[allow(deprecated)]
 use crate::frame::value::{
-    Counter, CqlDate, CqlDecimal, CqlDecimalBorrowed, CqlDuration, CqlTime, CqlTimestamp,
-    CqlTimeuuid, CqlVarint, CqlVarintBorrowed, MaybeUnset, Unset, Value,
+    Coerced, Counter, CqlDate, CqlDecimal, CqlDecimalBorrowed, CqlDuration, CqlStr, CqlTime,
+    CqlTimestamp, CqlTimeuuid, CqlVarint, CqlVarintBorrowed, MaybeUnset, RawCell, SetOrListIter,
+    Unset, Value,
 };
 
 #[cfg(feature = "chrono-04")]
@@ -344,6 +345,25 @@ impl SerializeValue for String {
             .map_err(|_| mk_ser_err::<Self>(typ, BuiltinSerializationErrorKind::SizeOverflow))?
     });
 }
+impl SerializeValue for CqlStr {
+    impl_serialize_via_writer!(|me, typ, writer| {
+        exact_type_check!(typ, Ascii, Text);
+        writer
+            .set_value(me.as_str().as_bytes())
+            .map_err(|_| mk_ser_err::<Self>(typ, BuiltinSerializationErrorKind::SizeOverflow))?
+    });
+}
+impl SerializeValue for RawCell<'_> {
+    impl_serialize_via_writer!(|me, typ, writer| {
+        // The value is assumed to be already serialized according to the type.
+        match me.as_bytes() {
+            Some(bytes) => writer.set_value(bytes).map_err(|_| {
+                mk_ser_err::<Self>(typ, BuiltinSerializationErrorKind::SizeOverflow)
+            })?,
+            None => writer.set_null(),
+        }
+    });
+}
 impl<T: SerializeValue> SerializeValue for Option<T> {
     fn serialize<'b>(
         &self,
@@ -388,6 +408,20 @@ impl<V: SerializeValue> SerializeValue for MaybeUnset<V> {
         }
     }
 }
+
+/// Serializes the value if it's `Some`, or sets the cell to unset (instead of null) if it's `None`.
+///
+/// Used by the `#[scylla(unset_if_none)]` field attribute of the `SerializeRow` derive macro.
+pub fn serialize_unset_if_none<'b, T: SerializeValue>(
+    value: &Option<T>,
+    typ: &ColumnType,
+    writer: CellWriter<'b>,
+) -> Result<WrittenCellProof<'b>, SerializationError> {
+    match value {
+        Some(v) => v.serialize(typ, writer),
+        None => Ok(writer.set_unset()),
+    }
+}
 impl<T: SerializeValue + ?Sized> SerializeValue for &T {
     fn serialize<'b>(
         &self,
@@ -406,6 +440,15 @@ impl<T: SerializeValue + ?Sized> SerializeValue for Box<T> {
         T::serialize(&**self, typ, writer)
     }
 }
+impl<T: SerializeValue + ?Sized> SerializeValue for Arc<T> {
+    fn serialize<'b>(
+        &self,
+        typ: &ColumnType,
+        writer: CellWriter<'b>,
+    ) -> Result<WrittenCellProof<'b>, SerializationError> {
+        T::serialize(&**self, typ, writer)
+    }
+}
 impl<V: SerializeValue, S: BuildHasher + Default> SerializeValue for HashSet<V, S> {
     fn serialize<'b>(
         &self,
@@ -481,6 +524,151 @@ impl<T: SerializeValue> SerializeValue for Vec<T> {
         )
     }
 }
+impl<I> SerializeValue for SetOrListIter<I>
+where
+    I: IntoIterator + Clone,
+    I::IntoIter: ExactSizeIterator,
+    I::Item: SerializeValue,
+{
+    fn serialize<'b>(
+        &self,
+        typ: &ColumnType,
+        writer: CellWriter<'b>,
+    ) -> Result<WrittenCellProof<'b>, SerializationError> {
+        let iter = self.0.clone().into_iter();
+        serialize_sequence(std::any::type_name::<Self>(), iter.len(), iter, typ, writer)
+    }
+}
+macro_rules! impl_serialize_coerced_int {
+    ($($t:ty),*) => {
+        $(
+            impl SerializeValue for Coerced<$t> {
+                impl_serialize_via_writer!(|me, typ, writer| {
+                    serialize_coerced_int::<Self>(me.0.into(), typ, writer)?
+                });
+            }
+        )*
+    };
+}
+impl_serialize_coerced_int!(i8, i16, i32, i64, u8, u16, u32);
+impl SerializeValue for Coerced<f32> {
+    impl_serialize_via_writer!(|me, typ, writer| {
+        serialize_coerced_float::<Self>(me.0.into(), typ, writer)?
+    });
+}
+impl SerializeValue for Coerced<f64> {
+    impl_serialize_via_writer!(|me, typ, writer| {
+        serialize_coerced_float::<Self>(me.0, typ, writer)?
+    });
+}
+
+fn serialize_coerced_int<'b, T>(
+    value: i64,
+    typ: &ColumnType,
+    writer: CellWriter<'b>,
+) -> Result<WrittenCellProof<'b>, SerializationError> {
+    let overflow = || mk_ser_err::<T>(typ, BuiltinSerializationErrorKind::ValueOverflow);
+    let bytes = value.to_be_bytes();
+
+    let proof = match typ {
+        ColumnType::TinyInt => {
+            let value = i8::try_from(value).map_err(|_| overflow())?;
+            writer.set_value(&value.to_be_bytes())
+        }
+        ColumnType::SmallInt => {
+            let value = i16::try_from(value).map_err(|_| overflow())?;
+            writer.set_value(&value.to_be_bytes())
+        }
+        ColumnType::Int => {
+            let value = i32::try_from(value).map_err(|_| overflow())?;
+            writer.set_value(&value.to_be_bytes())
+        }
+        ColumnType::BigInt => writer.set_value(&bytes),
+        ColumnType::Varint => writer.set_value(strip_sign_extension(&bytes)),
+        ColumnType::Decimal => {
+            let mut builder = writer.into_value_builder();
+            builder.append_bytes(&0_i32.to_be_bytes());
+            builder.append_bytes(strip_sign_extension(&bytes));
+            builder.finish()
+        }
+        ColumnType::Float => {
+            // Converting back through i128 doesn't saturate, unlike through i64.
+            let converted = value as f32;
+            if converted as i128 != value as i128 {
+                return Err(overflow());
+            }
+            writer.set_value(&converted.to_be_bytes())
+        }
+        ColumnType::Double => {
+            let converted = value as f64;
+            if converted as i128 != value as i128 {
+                return Err(overflow());
+            }
+            writer.set_value(&converted.to_be_bytes())
+        }
+        _ => {
+            return Err(mk_typck_err::<T>(
+                typ,
+                BuiltinTypeCheckErrorKind::MismatchedType {
+                    expected: &[
+                        ColumnType::TinyInt,
+                        ColumnType::SmallInt,
+                        ColumnType::Int,
+                        ColumnType::BigInt,
+                        ColumnType::Varint,
+                        ColumnType::Decimal,
+                        ColumnType::Float,
+                        ColumnType::Double,
+                    ],
+                },
+            ))
+        }
+    };
+    // The values above are at most a few bytes long.
+    Ok(proof.unwrap())
+}
+
+fn serialize_coerced_float<'b, T>(
+    value: f64,
+    typ: &ColumnType,
+    writer: CellWriter<'b>,
+) -> Result<WrittenCellProof<'b>, SerializationError> {
+    let proof = match typ {
+        ColumnType::Float => {
+            let narrowed = value as f32;
+            if f64::from(narrowed) != value && !value.is_nan() {
+                return Err(mk_ser_err::<T>(
+                    typ,
+                    BuiltinSerializationErrorKind::ValueOverflow,
+                ));
+            }
+            writer.set_value(&narrowed.to_be_bytes())
+        }
+        ColumnType::Double => writer.set_value(&value.to_be_bytes()),
+        _ => {
+            return Err(mk_typck_err::<T>(
+                typ,
+                BuiltinTypeCheckErrorKind::MismatchedType {
+                    expected: &[ColumnType::Float, ColumnType::Double],
+                },
+            ))
+        }
+    };
+    Ok(proof.unwrap())
+}
+
+// Strips the leading bytes of a two's complement big-endian number
+// which only extend its sign, as varints are encoded in the shortest form.
+fn strip_sign_extension(bytes: &[u8]) -> &[u8] {
+    let redundant = bytes
+        .windows(2)
+        .take_while(|pair| {
+            (pair[0] == 0x00 && pair[1] & 0x80 == 0) || (pair[0] == 0xff && pair[1] & 0x80 != 0)
+        })
+        .count();
+    &bytes[redundant..]
+}
+
 impl<'a, T: SerializeValue + 'a> SerializeValue for &'a [T] {
     fn serialize<'b>(
         &self,
@@ -812,10 +1000,10 @@ impl_tuples!(
     16
 );
 
-fn serialize_sequence<'t, 'b, T: SerializeValue + 't>(
+fn serialize_sequence<'b, T: SerializeValue>(
     rust_name: &'static str,
     len: usize,
-    iter: impl Iterator<Item = &'t T>,
+    iter: impl Iterator<Item = T>,
     typ: &ColumnType,
     writer: CellWriter<'b>,
 ) -> Result<WrittenCellProof<'b>, SerializationError> {
@@ -841,14 +1029,28 @@ fn serialize_sequence<'t, 'b, T: SerializeValue + 't>(
     })?;
     builder.append_bytes(&element_count.to_be_bytes());
 
+    let mut actual_count = 0usize;
     for el in iter {
-        T::serialize(el, elt, builder.make_sub_writer()).map_err(|err| {
-            mk_ser_err_named(
-                rust_name,
-                typ,
-                SetOrListSerializationErrorKind::ElementSerializationFailed(err),
-            )
-        })?;
+        el.serialize(elt, builder.make_sub_writer())
+            .map_err(|err| {
+                mk_ser_err_named(
+                    rust_name,
+                    typ,
+                    SetOrListSerializationErrorKind::ElementSerializationFailed(err),
+                )
+            })?;
+        actual_count += 1;
+    }
+    // Only iterators can report a wrong length.
+    if actual_count != len {
+        return Err(mk_ser_err_named(
+            rust_name,
+            typ,
+            SetOrListSerializationErrorKind::ElementCountMismatch {
+                announced: len,
+                actual: actual_count,
+            },
+        ));
     }
 
     builder
@@ -1336,6 +1538,14 @@ pub enum SetOrListSerializationErrorKind {
 
     /// One of the elements of the set/list failed to serialize.
     ElementSerializationFailed(SerializationError),
+
+    /// The iterator yielded a different number of elements than its length announced.
+    ElementCountMismatch {
+        /// The length reported by the iterator.
+        announced: usize,
+        /// The number of elements actually yielded.
+        actual: usize,
+    },
 }
 
 impl Display for SetOrListSerializationErrorKind {
@@ -1347,6 +1557,12 @@ impl Display for SetOrListSerializationErrorKind {
             SetOrListSerializationErrorKind::ElementSerializationFailed(err) => {
                 write!(f, "failed to serialize one of the elements: {err}")
             }
+            SetOrListSerializationErrorKind::ElementCountMismatch { announced, actual } => {
+                write!(
+                    f,
+                    "the iterator announced {announced} elements, but yielded {actual}"
+                )
+            }
         }
     }
 }
@@ -1581,6 +1797,28 @@ mod doctests {
     /// ```
     fn _test_udt_bad_attributes_rename_collision_with_another_rename() {}
 
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeValue)]
+    /// #[scylla(crate = scylla_cql, flavor = "enforce_order", skip_name_checks, rename_all = "camelCase")]
+    /// struct TestUdt {
+    ///     a_b: i32,
+    /// }
+    /// ```
+    fn _test_udt_bad_attributes_skip_name_check_conflicts_with_rename_all() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeValue)]
+    /// #[scylla(crate = scylla_cql, rename_all = "kebab-case")]
+    /// struct TestUdt {
+    ///     a_b: i32,
+    ///     #[scylla(rename = "a-b")]
+    ///     c: String,
+    /// }
+    /// ```
+    fn _test_udt_bad_attributes_rename_all_collision_with_rename() {}
+
     /// ```compile_fail
     ///
     /// #[derive(scylla_macros::SerializeValue)]
@@ -1631,6 +1869,75 @@ mod doctests {
     /// }
     /// ```
     fn _test_udt_default_when_null_is_accepted() {}
+
+    /// ```
+    /// #[derive(scylla_macros::SerializeValue, scylla_macros::DeserializeValue)]
+    /// #[scylla(crate = scylla_cql)]
+    /// struct TestUdt {
+    ///     a: i32,
+    ///     #[scylla(default)]
+    ///     b: bool,
+    /// }
+    /// ```
+    fn _test_udt_default_is_accepted() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeValue)]
+    /// #[scylla(crate = scylla_cql)]
+    /// enum TestEnum {
+    ///     A,
+    ///     B(i32),
+    /// }
+    /// ```
+    fn _test_enum_bad_variant_with_fields() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeValue)]
+    /// #[scylla(crate = scylla_cql)]
+    /// enum TestEnum {
+    ///     #[scylla(rename = "B")]
+    ///     A,
+    ///     B,
+    /// }
+    /// ```
+    fn _test_enum_bad_attributes_rename_collision() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeValue)]
+    /// #[scylla(crate = scylla_cql, repr = "int")]
+    /// enum TestEnum {
+    ///     #[scylla(rename = "a")]
+    ///     A,
+    /// }
+    /// ```
+    fn _test_enum_bad_attributes_rename_with_int_repr() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeValue)]
+    /// #[scylla(crate = scylla_cql, transparent)]
+    /// struct TestNewtype(i32, i32);
+    /// ```
+    fn _test_transparent_bad_two_fields() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeValue)]
+    /// #[scylla(crate = scylla_cql, transparent)]
+    /// struct TestNewtype(#[scylla(rename = "a")] i32);
+    /// ```
+    fn _test_transparent_bad_field_attributes() {}
+
+    /// ```compile_fail
+    ///
+    /// #[derive(scylla_macros::SerializeValue)]
+    /// #[scylla(crate = scylla_cql, transparent, flavor = "enforce_order")]
+    /// struct TestNewtype(i32);
+    /// ```
+    fn _test_transparent_bad_struct_attributes() {}
 }
 
 #[cfg(test)]
@@ -1639,7 +1946,10 @@ pub(crate) mod tests {
 
     use crate::frame::response::result::{ColumnType, CqlValue};
     #[allow(deprecated)]
-    use crate::frame::value::{Counter, MaybeUnset, Unset, Value, ValueTooBig};
+    use crate::frame::value::{
+        Coerced, Counter, CqlDecimal, CqlVarint, MaybeUnset, SetOrListIter, Unset, Value,
+        ValueTooBig,
+    };
     #[allow(deprecated)]
     use crate::types::serialize::value::{
         BuiltinSerializationError, BuiltinSerializationErrorKind, BuiltinTypeCheckError,
@@ -1840,6 +2150,140 @@ pub(crate) mod tests {
         );
     }
 
+    #[test]
+    fn test_set_or_list_iter() {
+        let ids = [1_i64, 2, 3];
+        let typ = ColumnType::List(Box::new(ColumnType::Int));
+        let iter = SetOrListIter(ids.iter().map(|id| *id as i32));
+        assert_eq!(
+            do_serialize(iter, &typ),
+            do_serialize(vec![1_i32, 2, 3], &typ)
+        );
+
+        let typ = ColumnType::Set(Box::new(ColumnType::Text));
+        let iter = SetOrListIter(["a", "b"]);
+        assert_eq!(do_serialize(iter, &typ), do_serialize(vec!["a", "b"], &typ));
+
+        // An iterator reporting a wrong length
+        #[derive(Clone)]
+        struct Liar;
+        impl Iterator for Liar {
+            type Item = i32;
+            fn next(&mut self) -> Option<i32> {
+                None
+            }
+            fn size_hint(&self) -> (usize, Option<usize>) {
+                (1, Some(1))
+            }
+        }
+        impl ExactSizeIterator for Liar {}
+
+        let typ = ColumnType::List(Box::new(ColumnType::Int));
+        let err = do_serialize_err(SetOrListIter(Liar), &typ);
+        let err = get_ser_err(&err);
+        assert_matches!(
+            err.kind,
+            BuiltinSerializationErrorKind::SetOrListError(
+                SetOrListSerializationErrorKind::ElementCountMismatch {
+                    announced: 1,
+                    actual: 0
+                }
+            )
+        );
+    }
+
+    #[test]
+    fn test_coerced_numbers() {
+        assert_eq!(
+            do_serialize(Coerced(5_i32), &ColumnType::BigInt),
+            do_serialize(5_i64, &ColumnType::BigInt)
+        );
+        assert_eq!(
+            do_serialize(Coerced(-5_i64), &ColumnType::TinyInt),
+            do_serialize(-5_i8, &ColumnType::TinyInt)
+        );
+        assert_eq!(
+            do_serialize(Coerced(40_000_u16), &ColumnType::Int),
+            do_serialize(40_000_i32, &ColumnType::Int)
+        );
+        assert_eq!(
+            do_serialize(Coerced(-129_i16), &ColumnType::Varint),
+            do_serialize(
+                CqlVarint::from_signed_bytes_be(vec![0xff, 0x7f]),
+                &ColumnType::Varint
+            )
+        );
+        assert_eq!(
+            do_serialize(Coerced(-128_i64), &ColumnType::Varint),
+            do_serialize(
+                CqlVarint::from_signed_bytes_be(vec![0x80]),
+                &ColumnType::Varint
+            )
+        );
+        assert_eq!(
+            do_serialize(Coerced(255_u8), &ColumnType::Decimal),
+            do_serialize(
+                CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x00, 0xff], 0),
+                &ColumnType::Decimal
+            )
+        );
+        assert_eq!(
+            do_serialize(Coerced(1_i32 << 24), &ColumnType::Float),
+            do_serialize(16_777_216_f32, &ColumnType::Float)
+        );
+        assert_eq!(
+            do_serialize(Coerced(1.5_f32), &ColumnType::Double),
+            do_serialize(1.5_f64, &ColumnType::Double)
+        );
+        assert_eq!(
+            do_serialize(Coerced(0.25_f64), &ColumnType::Float),
+            do_serialize(0.25_f32, &ColumnType::Float)
+        );
+
+        // Values which don't fit into the column type
+        for (err, typ) in [
+            (
+                do_serialize_err(Coerced(128_i32), &ColumnType::TinyInt),
+                ColumnType::TinyInt,
+            ),
+            (
+                do_serialize_err(Coerced(i64::MAX), &ColumnType::Int),
+                ColumnType::Int,
+            ),
+            (
+                do_serialize_err(Coerced((1_i32 << 24) + 1), &ColumnType::Float),
+                ColumnType::Float,
+            ),
+            (
+                do_serialize_err(Coerced(i64::MAX), &ColumnType::Double),
+                ColumnType::Double,
+            ),
+            (
+                do_serialize_err(Coerced(0.1_f64), &ColumnType::Float),
+                ColumnType::Float,
+            ),
+        ] {
+            let err = get_ser_err(&err);
+            assert_eq!(err.got, typ);
+            assert_matches!(err.kind, BuiltinSerializationErrorKind::ValueOverflow);
+        }
+
+        // Non-numeric columns
+        let err = do_serialize_err(Coerced(1_i32), &ColumnType::Text);
+        let err = get_typeck_err(&err);
+        assert_eq!(err.rust_name, std::any::type_name::<Coerced<i32>>());
+        assert_matches!(err.kind, BuiltinTypeCheckErrorKind::MismatchedType { .. });
+
+        let err = do_serialize_err(Coerced(1.0_f32), &ColumnType::Int);
+        let err = get_typeck_err(&err);
+        assert_matches!(
+            err.kind,
+            BuiltinTypeCheckErrorKind::MismatchedType {
+                expected: &[ColumnType::Float, ColumnType::Double],
+            }
+        );
+    }
+
     #[test]
     fn test_map_errors() {
         // Not a map
@@ -2343,6 +2787,62 @@ pub(crate) mod tests {
         assert_eq!(result_2, result_3);
     }
 
+    #[test]
+    fn test_udt_serialization_name_check() {
+        #[derive(SerializeValue, Debug)]
+        #[scylla(crate = crate, udt_name = "address", keyspace = "ks")]
+        struct Address {
+            street: String,
+        }
+
+        #[derive(SerializeValue, Debug)]
+        #[scylla(crate = crate, flavor = "enforce_order", udt_name = "address")]
+        struct OrderedAddress {
+            street: String,
+        }
+
+        let udt_type =
+            |keyspace: &'static str, type_name: &'static str| ColumnType::UserDefinedType {
+                type_name: type_name.into(),
+                keyspace: keyspace.into(),
+                field_types: vec![("street".into(), ColumnType::Text)],
+            };
+        let address = Address {
+            street: "Main St".to_owned(),
+        };
+        let ordered_address = OrderedAddress {
+            street: "Main St".to_owned(),
+        };
+
+        let typ = udt_type("ks", "address");
+        assert_eq!(
+            do_serialize(&address, &typ),
+            do_serialize(&ordered_address, &typ)
+        );
+        do_serialize(&ordered_address, &udt_type("other_ks", "address"));
+
+        // A UDT with the same fields, but a different identity.
+        for (keyspace, type_name) in [("ks", "location"), ("other_ks", "address")] {
+            let err = do_serialize_err(&address, &udt_type(keyspace, type_name));
+            let err = get_typeck_err(&err);
+            let BuiltinTypeCheckErrorKind::UdtError(UdtTypeCheckErrorKind::NameMismatch {
+                keyspace: got_keyspace,
+                type_name: got_type_name,
+            }) = &err.kind
+            else {
+                panic!("unexpected error kind: {}", err.kind)
+            };
+            assert_eq!(got_keyspace, keyspace);
+            assert_eq!(got_type_name, type_name);
+        }
+
+        let err = do_serialize_err(&ordered_address, &udt_type("ks", "location"));
+        assert_matches!(
+            get_typeck_err(&err).kind,
+            BuiltinTypeCheckErrorKind::UdtError(UdtTypeCheckErrorKind::NameMismatch { .. })
+        );
+    }
+
     #[test]
     fn test_udt_serialization_failing_type_check() {
         let typ_not_udt = ColumnType::Ascii;
@@ -2659,6 +3159,46 @@ pub(crate) mod tests {
         assert_eq!(reference, udt);
     }
 
+    #[derive(SerializeValue, Debug)]
+    #[scylla(crate = crate, rename_all = "camelCase")]
+    struct TestUdtWithRenameAll {
+        street_name: String,
+        #[scylla(rename = "x")]
+        b: i32,
+    }
+
+    #[test]
+    fn test_udt_serialization_with_rename_all() {
+        let typ = ColumnType::UserDefinedType {
+            type_name: "typ".into(),
+            keyspace: "ks".into(),
+            field_types: vec![
+                ("x".into(), ColumnType::Int),
+                ("streetName".into(), ColumnType::Text),
+            ],
+        };
+
+        let mut reference = Vec::new();
+        // Total length of the struct is 23
+        reference.extend_from_slice(&23i32.to_be_bytes());
+        // Field 'x'
+        reference.extend_from_slice(&4i32.to_be_bytes());
+        reference.extend_from_slice(&42i32.to_be_bytes());
+        // Field 'streetName'
+        reference.extend_from_slice(&("Ala ma kota".len() as i32).to_be_bytes());
+        reference.extend_from_slice("Ala ma kota".as_bytes());
+
+        let udt = do_serialize(
+            TestUdtWithRenameAll {
+                street_name: "Ala ma kota".to_owned(),
+                b: 42,
+            },
+            &typ,
+        );
+
+        assert_eq!(reference, udt);
+    }
+
     #[test]
     fn test_udt_serialization_with_field_rename_and_enforce_order() {
         let typ = ColumnType::UserDefinedType {
@@ -0,0 +1,247 @@
+// A minimal CQL tokenizer, used both for the schema file and for the checked
+// statements, together with a cursor for writing recursive descent parsers.
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum TokenKind {
+    // A keyword, an unquoted identifier or a number.
+    Word,
+    // An identifier in double quotes.
+    QuotedName,
+    // A string literal, either in single quotes or in `$$`.
+    String,
+    // Any other character, or one of the `<=`, `>=`, `!=` operators.
+    Symbol,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub(super) struct Token<'a> {
+    pub(super) kind: TokenKind,
+    pub(super) text: &'a str,
+    // Byte offset of the token in the source.
+    pub(super) start: usize,
+}
+
+impl Token<'_> {
+    pub(super) fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    pub(super) fn is_symbol(&self, symbol: &str) -> bool {
+        self.kind == TokenKind::Symbol && self.text == symbol
+    }
+
+    // Returns the identifier, if the token is one. Unquoted identifiers are
+    // case-insensitive, so they are converted to lowercase.
+    pub(super) fn name(&self) -> Option<String> {
+        match self.kind {
+            TokenKind::Word => Some(self.text.to_lowercase()),
+            TokenKind::QuotedName => Some(self.text[1..self.text.len() - 1].replace("\"\"", "\"")),
+            TokenKind::String | TokenKind::Symbol => None,
+        }
+    }
+}
+
+// Splits the source into tokens, skipping whitespace and comments.
+pub(super) fn tokenize(cql: &str) -> Result<Vec<Token<'_>>, String> {
+    let bytes = cql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut pos = 0;
+
+    let find_from =
+        |from: usize, terminator: &str| cql[from..].find(terminator).map(|idx| from + idx);
+
+    while pos < bytes.len() {
+        let rest = &cql[pos..];
+        let c = rest.chars().next().unwrap();
+        let (kind, end) = if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        } else if rest.starts_with("--") || rest.starts_with("//") {
+            pos = find_from(pos, "\n").unwrap_or(cql.len());
+            continue;
+        } else if rest.starts_with("/*") {
+            pos = find_from(pos + 2, "*/").ok_or("unterminated comment")? + 2;
+            continue;
+        } else if rest.starts_with("$$") {
+            let end = find_from(pos + 2, "$$").ok_or("unterminated string literal")? + 2;
+            (TokenKind::String, end)
+        } else if c == '\'' || c == '"' {
+            // The quote character is escaped by doubling it.
+            let (quote, unterminated) = if c == '\'' {
+                ("'", "unterminated string literal")
+            } else {
+                ("\"", "unterminated quoted identifier")
+            };
+            let mut end = pos + 1;
+            loop {
+                end = find_from(end, quote).ok_or(unterminated)? + 1;
+                if end >= bytes.len() || bytes[end] != c as u8 {
+                    break;
+                }
+                end += 1;
+            }
+            let kind = if c == '\'' {
+                TokenKind::String
+            } else {
+                TokenKind::QuotedName
+            };
+            (kind, end)
+        } else if c.is_alphanumeric() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (TokenKind::Word, pos + len)
+        } else if ["<=", ">=", "!="].iter().any(|op| rest.starts_with(op)) {
+            (TokenKind::Symbol, pos + 2)
+        } else {
+            (TokenKind::Symbol, pos + c.len_utf8())
+        };
+        tokens.push(Token {
+            kind,
+            text: &cql[pos..end],
+            start: pos,
+        });
+        pos = end;
+    }
+    Ok(tokens)
+}
+
+// Position in a sequence of tokens, with helpers for consuming them.
+pub(super) struct Cursor<'t, 'a> {
+    tokens: &'t [Token<'a>],
+    pos: usize,
+}
+
+impl<'t, 'a> Cursor<'t, 'a> {
+    pub(super) fn new(tokens: &'t [Token<'a>]) -> Self {
+        Self { tokens, pos: 0 }
+    }
+
+    pub(super) fn peek(&self) -> Option<&'t Token<'a>> {
+        self.tokens.get(self.pos)
+    }
+
+    pub(super) fn peek_nth(&self, n: usize) -> Option<&'t Token<'a>> {
+        self.tokens.get(self.pos + n)
+    }
+
+    pub(super) fn next(&mut self) -> Option<&'t Token<'a>> {
+        let token = self.tokens.get(self.pos)?;
+        self.pos += 1;
+        Some(token)
+    }
+
+    pub(super) fn is_at_end(&self) -> bool {
+        self.pos >= self.tokens.len()
+    }
+
+    pub(super) fn peek_keyword(&self, keyword: &str) -> bool {
+        self.peek().is_some_and(|token| token.is_keyword(keyword))
+    }
+
+    pub(super) fn peek_symbol(&self, symbol: &str) -> bool {
+        self.peek().is_some_and(|token| token.is_symbol(symbol))
+    }
+
+    pub(super) fn eat_keyword(&mut self, keyword: &str) -> bool {
+        let matches = self.peek_keyword(keyword);
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    pub(super) fn eat_keywords(&mut self, keywords: &[&str]) -> bool {
+        let matches = keywords
+            .iter()
+            .enumerate()
+            .all(|(idx, keyword)| self.peek_nth(idx).is_some_and(|t| t.is_keyword(keyword)));
+        if matches {
+            self.pos += keywords.len();
+        }
+        matches
+    }
+
+    pub(super) fn eat_symbol(&mut self, symbol: &str) -> bool {
+        let matches = self.peek_symbol(symbol);
+        if matches {
+            self.pos += 1;
+        }
+        matches
+    }
+
+    pub(super) fn expect_keyword(&mut self, keyword: &str) -> Result<(), String> {
+        if self.eat_keyword(keyword) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", keyword.to_ascii_uppercase())))
+        }
+    }
+
+    pub(super) fn expect_symbol(&mut self, symbol: &str) -> Result<(), String> {
+        if self.eat_symbol(symbol) {
+            Ok(())
+        } else {
+            Err(self.unexpected(&format!("`{}`", symbol)))
+        }
+    }
+
+    pub(super) fn parse_name(&mut self) -> Result<String, String> {
+        match self.peek().and_then(Token::name) {
+            Some(name) => {
+                self.pos += 1;
+                Ok(name)
+            }
+            None => Err(self.unexpected("a name")),
+        }
+    }
+
+    // Parses a name optionally qualified with a keyspace, e.g. `ks.tab`.
+    pub(super) fn parse_qualified_name(&mut self) -> Result<(Option<String>, String), String> {
+        let name = self.parse_name()?;
+        if self.eat_symbol(".") {
+            Ok((Some(name), self.parse_name()?))
+        } else {
+            Ok((None, name))
+        }
+    }
+
+    // Parses a comma-separated list of names in parentheses.
+    pub(super) fn parse_name_list(&mut self) -> Result<Vec<String>, String> {
+        self.expect_symbol("(")?;
+        let mut names = vec![self.parse_name()?];
+        while self.eat_symbol(",") {
+            names.push(self.parse_name()?);
+        }
+        self.expect_symbol(")")?;
+        Ok(names)
+    }
+
+    // Skips a parenthesized, bracketed or braced group, returning whether it
+    // contained any bind markers.
+    pub(super) fn skip_group(&mut self) -> Result<bool, String> {
+        let mut depth = 0_usize;
+        let mut has_bind_markers = false;
+        loop {
+            let token = self.next().ok_or("unbalanced parentheses")?;
+            match token.text {
+                _ if token.kind != TokenKind::Symbol => {}
+                "(" | "[" | "{" => depth += 1,
+                ")" | "]" | "}" => depth -= 1,
+                "?" => has_bind_markers = true,
+                _ => {}
+            }
+            if depth == 0 {
+                return Ok(has_bind_markers);
+            }
+        }
+    }
+
+    // Builds an error message for an unexpected token.
+    pub(super) fn unexpected(&self, expected: &str) -> String {
+        match self.peek() {
+            Some(token) => format!("expected {}, found `{}`", expected, token.text),
+            None => format!("expected {}, found the end of the statement", expected),
+        }
+    }
+}
@@ -0,0 +1,242 @@
+use std::path::PathBuf;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::parse::{Parse, ParseStream};
+use syn::{LitStr, Token};
+
+mod lexer;
+mod schema;
+mod statement;
+
+use schema::{CqlType, NativeType, Schema};
+use statement::ValueType;
+
+// Environment variable with the path of the schema file,
+// used if the `schema` argument is not given.
+const SCHEMA_PATH_ENV: &str = "SCYLLA_SCHEMA_PATH";
+
+// Tuples of up to this length implement `SerializeRow` and `DeserializeRow`.
+const MAX_TUPLE_LEN: usize = 16;
+
+struct Input {
+    statement: LitStr,
+    schema: Option<LitStr>,
+    keyspace: Option<LitStr>,
+    crate_path: Option<syn::Path>,
+}
+
+impl Parse for Input {
+    fn parse(input: ParseStream) -> syn::Result<Self> {
+        let mut result = Self {
+            statement: input.parse()?,
+            schema: None,
+            keyspace: None,
+            crate_path: None,
+        };
+        while input.parse::<Option<Token![,]>>()?.is_some() && !input.is_empty() {
+            if input.peek(Token![crate]) {
+                input.parse::<Token![crate]>()?;
+                input.parse::<Token![=]>()?;
+                result.crate_path = Some(input.parse()?);
+                continue;
+            }
+            let name: syn::Ident = input.parse()?;
+            input.parse::<Token![=]>()?;
+            let slot = match name.to_string().as_str() {
+                "schema" => &mut result.schema,
+                "keyspace" => &mut result.keyspace,
+                _ => {
+                    return Err(syn::Error::new(
+                        name.span(),
+                        "unknown argument, expected `schema`, `keyspace` or `crate`",
+                    ))
+                }
+            };
+            *slot = Some(input.parse()?);
+        }
+        if !input.is_empty() {
+            return Err(input.error("expected `,`"));
+        }
+        Ok(result)
+    }
+}
+
+// checked_query!("SELECT ..."): checks the statement against a schema file
+// at compile time and creates a `CheckedQuery` with the types of its bind
+// markers and of its result columns.
+pub(crate) fn checked_query(
+    tokens_input: TokenStream,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let input: Input = syn::parse(tokens_input)?;
+    let crate_path = input
+        .crate_path
+        .unwrap_or_else(|| syn::parse_quote!(scylla));
+
+    let schema_path = resolve_schema_path(&input.schema, &input.statement)?;
+    let schema_source = std::fs::read_to_string(&schema_path).map_err(|err| {
+        syn::Error::new(
+            input.statement.span(),
+            format!(
+                "failed to read the schema file {}: {}",
+                schema_path.display(),
+                err
+            ),
+        )
+    })?;
+    let schema = Schema::parse(&schema_source).map_err(|err| {
+        syn::Error::new(
+            input.statement.span(),
+            format!("invalid schema file {}: {}", schema_path.display(), err),
+        )
+    })?;
+
+    let keyspace = input.keyspace.as_ref().map(LitStr::value);
+    let checked =
+        statement::check_statement(&input.statement.value(), &schema, keyspace.as_deref())
+            .map_err(|err| syn::Error::new(input.statement.span(), err))?;
+
+    for (values, what) in [
+        (&checked.bind_markers, "bind markers"),
+        (&checked.columns, "returned columns"),
+    ] {
+        if values.len() > MAX_TUPLE_LEN {
+            return Err(syn::Error::new(
+                input.statement.span(),
+                format!(
+                    "the statement has {} {}, but at most {} are supported",
+                    values.len(),
+                    what,
+                    MAX_TUPLE_LEN
+                ),
+            ));
+        }
+    }
+
+    if let Some(value) = checked
+        .bind_markers
+        .iter()
+        .chain(&checked.columns)
+        .find(|value| !map_keys_hashable(&value.typ))
+    {
+        return Err(syn::Error::new(
+            input.statement.span(),
+            format!(
+                "unsupported type {}: maps are represented by HashMap, so their keys must not \
+                 contain float, double, duration, user defined types or maps",
+                value.typ
+            ),
+        ));
+    }
+
+    let bind_types = checked
+        .bind_markers
+        .iter()
+        .map(|value| value_rust_type(value, &crate_path));
+    let column_types = checked
+        .columns
+        .iter()
+        .map(|value| value_rust_type(value, &crate_path));
+    let schema_path = schema_path.to_string_lossy();
+    let statement = &input.statement;
+
+    Ok(quote! {
+        {
+            // Makes the compiler rebuild the crate when the schema changes.
+            const _: &[u8] = ::std::include_bytes!(#schema_path);
+            #crate_path::statement::checked::CheckedQuery::<(#(#bind_types,)*), (#(#column_types,)*)>::new_checked(#statement)
+        }
+    })
+}
+
+// Schema paths are relative to the directory of the manifest of the crate being compiled.
+fn resolve_schema_path(schema: &Option<LitStr>, statement: &LitStr) -> Result<PathBuf, syn::Error> {
+    let path = match schema {
+        Some(schema) => schema.value(),
+        None => std::env::var(SCHEMA_PATH_ENV).map_err(|_| {
+            syn::Error::new(
+                statement.span(),
+                format!(
+                    "no schema file: either pass `schema = \"path/to/schema.cql\"` or set the {} environment variable",
+                    SCHEMA_PATH_ENV
+                ),
+            )
+        })?,
+    };
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    Ok(PathBuf::from(manifest_dir).join(path))
+}
+
+// Maps are represented by `HashMap`, so the Rust types of their keys must implement `Hash` and `Eq`.
+fn map_keys_hashable(typ: &CqlType) -> bool {
+    match typ {
+        CqlType::Map(key, value) => is_hashable(key) && map_keys_hashable(value),
+        CqlType::List(elem) | CqlType::Set(elem) => map_keys_hashable(elem),
+        CqlType::Tuple(elems) => elems.iter().all(map_keys_hashable),
+        CqlType::Native(_) | CqlType::Udt => true,
+    }
+}
+
+fn is_hashable(typ: &CqlType) -> bool {
+    match typ {
+        CqlType::Native(NativeType::Double | NativeType::Float | NativeType::Duration) => false,
+        CqlType::Native(_) => true,
+        CqlType::List(elem) | CqlType::Set(elem) => is_hashable(elem),
+        // The standard library implements `Hash` for tuples of up to 12 elements.
+        CqlType::Tuple(elems) => elems.len() <= 12 && elems.iter().all(is_hashable),
+        CqlType::Map(..) | CqlType::Udt => false,
+    }
+}
+
+fn value_rust_type(value: &ValueType, crate_path: &syn::Path) -> proc_macro2::TokenStream {
+    let typ = rust_type(&value.typ, crate_path);
+    if value.nullable {
+        quote!(::std::option::Option<#typ>)
+    } else {
+        typ
+    }
+}
+
+fn rust_type(typ: &CqlType, crate_path: &syn::Path) -> proc_macro2::TokenStream {
+    let value_type = |name: &str| {
+        let name = syn::Ident::new(name, proc_macro2::Span::call_site());
+        quote!(#crate_path::frame::value::#name)
+    };
+    match typ {
+        CqlType::Native(native) => match native {
+            NativeType::Ascii | NativeType::Text => quote!(::std::string::String),
+            NativeType::BigInt => quote!(i64),
+            NativeType::Blob => quote!(::std::vec::Vec<u8>),
+            NativeType::Boolean => quote!(bool),
+            NativeType::Counter => value_type("Counter"),
+            NativeType::Date => value_type("CqlDate"),
+            NativeType::Decimal => value_type("CqlDecimal"),
+            NativeType::Double => quote!(f64),
+            NativeType::Duration => value_type("CqlDuration"),
+            NativeType::Float => quote!(f32),
+            NativeType::Inet => quote!(::std::net::IpAddr),
+            NativeType::Int => quote!(i32),
+            NativeType::SmallInt => quote!(i16),
+            NativeType::Time => value_type("CqlTime"),
+            NativeType::Timestamp => value_type("CqlTimestamp"),
+            NativeType::Timeuuid => value_type("CqlTimeuuid"),
+            NativeType::TinyInt => quote!(i8),
+            NativeType::Uuid => quote!(#crate_path::_macro_internal::Uuid),
+            NativeType::Varint => value_type("CqlVarint"),
+        },
+        CqlType::List(elem) | CqlType::Set(elem) => {
+            let elem = rust_type(elem, crate_path);
+            quote!(::std::vec::Vec<#elem>)
+        }
+        CqlType::Map(key, value) => {
+            let key = rust_type(key, crate_path);
+            let value = rust_type(value, crate_path);
+            quote!(::std::collections::HashMap<#key, #value>)
+        }
+        CqlType::Tuple(elems) => {
+            let elems = elems.iter().map(|elem| rust_type(elem, crate_path));
+            quote!((#(#elems,)*))
+        }
+        CqlType::Udt => quote!(#crate_path::frame::response::result::CqlValue),
+    }
+}
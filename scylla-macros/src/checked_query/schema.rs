@@ -0,0 +1,457 @@
+use std::collections::{HashMap, HashSet};
+use std::fmt::{self, Display};
+
+use super::lexer::{tokenize, Cursor, Token};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum NativeType {
+    Ascii,
+    BigInt,
+    Blob,
+    Boolean,
+    Counter,
+    Date,
+    Decimal,
+    Double,
+    Duration,
+    Float,
+    Inet,
+    Int,
+    SmallInt,
+    Text,
+    Time,
+    Timestamp,
+    Timeuuid,
+    TinyInt,
+    Uuid,
+    Varint,
+}
+
+impl NativeType {
+    fn from_name(name: &str) -> Option<Self> {
+        Some(match name {
+            "ascii" => Self::Ascii,
+            "bigint" => Self::BigInt,
+            "blob" => Self::Blob,
+            "boolean" => Self::Boolean,
+            "counter" => Self::Counter,
+            "date" => Self::Date,
+            "decimal" => Self::Decimal,
+            "double" => Self::Double,
+            "duration" => Self::Duration,
+            "float" => Self::Float,
+            "inet" => Self::Inet,
+            "int" => Self::Int,
+            "smallint" => Self::SmallInt,
+            "text" | "varchar" => Self::Text,
+            "time" => Self::Time,
+            "timestamp" => Self::Timestamp,
+            "timeuuid" => Self::Timeuuid,
+            "tinyint" => Self::TinyInt,
+            "uuid" => Self::Uuid,
+            "varint" => Self::Varint,
+            _ => return None,
+        })
+    }
+}
+
+impl Display for NativeType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Ascii => "ascii",
+            Self::BigInt => "bigint",
+            Self::Blob => "blob",
+            Self::Boolean => "boolean",
+            Self::Counter => "counter",
+            Self::Date => "date",
+            Self::Decimal => "decimal",
+            Self::Double => "double",
+            Self::Duration => "duration",
+            Self::Float => "float",
+            Self::Inet => "inet",
+            Self::Int => "int",
+            Self::SmallInt => "smallint",
+            Self::Text => "text",
+            Self::Time => "time",
+            Self::Timestamp => "timestamp",
+            Self::Timeuuid => "timeuuid",
+            Self::TinyInt => "tinyint",
+            Self::Uuid => "uuid",
+            Self::Varint => "varint",
+        })
+    }
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub(super) enum CqlType {
+    Native(NativeType),
+    List(Box<CqlType>),
+    Set(Box<CqlType>),
+    Map(Box<CqlType>, Box<CqlType>),
+    Tuple(Vec<CqlType>),
+    // Fields of UDTs are not tracked, as UDTs are represented by `CqlValue`.
+    Udt,
+}
+
+impl Display for CqlType {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Native(native) => write!(f, "{}", native),
+            Self::List(elem) => write!(f, "list<{}>", elem),
+            Self::Set(elem) => write!(f, "set<{}>", elem),
+            Self::Map(key, value) => write!(f, "map<{}, {}>", key, value),
+            Self::Tuple(elems) => {
+                f.write_str("tuple<")?;
+                for (idx, elem) in elems.iter().enumerate() {
+                    if idx > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write!(f, "{}", elem)?;
+                }
+                f.write_str(">")
+            }
+            Self::Udt => f.write_str("user defined type"),
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(super) enum ColumnKind {
+    PartitionKey,
+    Clustering,
+    Static,
+    Regular,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Column {
+    pub(super) name: String,
+    pub(super) typ: CqlType,
+    pub(super) kind: ColumnKind,
+}
+
+impl Column {
+    pub(super) fn is_primary_key(&self) -> bool {
+        matches!(self.kind, ColumnKind::PartitionKey | ColumnKind::Clustering)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct Table {
+    pub(super) keyspace: String,
+    pub(super) name: String,
+    // In the order of `SELECT *`: the partition key, the clustering key,
+    // then static and regular columns, each sorted by name.
+    pub(super) columns: Vec<Column>,
+}
+
+impl Table {
+    pub(super) fn column(&self, name: &str) -> Result<&Column, String> {
+        self.columns
+            .iter()
+            .find(|column| column.name == name)
+            .ok_or_else(|| format!("unknown column `{}` in table `{}`", name, self))
+    }
+
+    fn sort_columns(&mut self, partition_key: &[String], clustering_key: &[String]) {
+        let key_position = |column: &Column| match column.kind {
+            ColumnKind::PartitionKey => partition_key.iter().position(|n| *n == column.name),
+            ColumnKind::Clustering => clustering_key.iter().position(|n| *n == column.name),
+            ColumnKind::Static | ColumnKind::Regular => None,
+        };
+        let kind_order = |kind: ColumnKind| kind as u8;
+        self.columns.sort_by(|a, b| {
+            kind_order(a.kind)
+                .cmp(&kind_order(b.kind))
+                .then_with(|| key_position(a).cmp(&key_position(b)))
+                .then_with(|| a.name.cmp(&b.name))
+        });
+    }
+}
+
+impl std::fmt::Display for Table {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.keyspace, self.name)
+    }
+}
+
+// Tables and UDTs defined by a schema file.
+#[derive(Default, Debug)]
+pub(super) struct Schema {
+    tables: HashMap<(String, String), Table>,
+    udts: HashSet<(String, String)>,
+}
+
+impl Schema {
+    // Parses a sequence of CQL statements, such as the output of `DESCRIBE SCHEMA`
+    // or a series of migrations. `CREATE TABLE`, `CREATE TYPE`, `ALTER TABLE`
+    // with `ADD` and `DROP`, `DROP TABLE`, `DROP TYPE` and `USE` are taken into
+    // account, all the other statements are ignored.
+    pub(super) fn parse(source: &str) -> Result<Self, String> {
+        let tokens = tokenize(source)?;
+        let mut schema = Self::default();
+        let mut current_keyspace = None;
+        for statement in tokens.split(|token| token.is_symbol(";")) {
+            let Some(first) = statement.first() else {
+                continue;
+            };
+            schema
+                .apply_statement(statement, &mut current_keyspace)
+                .map_err(|err| {
+                    let line = source[..first.start].matches('\n').count() + 1;
+                    format!("line {}: {}", line, err)
+                })?;
+        }
+        Ok(schema)
+    }
+
+    pub(super) fn table(
+        &self,
+        keyspace: Option<&str>,
+        name: &str,
+        default_keyspace: Option<&str>,
+    ) -> Result<&Table, String> {
+        let keyspace = keyspace.or(default_keyspace).ok_or_else(|| {
+            format!(
+                "table `{}` is not qualified with a keyspace, and no default keyspace was given",
+                name
+            )
+        })?;
+        self.tables
+            .get(&(keyspace.to_owned(), name.to_owned()))
+            .ok_or_else(|| format!("unknown table `{}.{}`", keyspace, name))
+    }
+
+    fn apply_statement(
+        &mut self,
+        statement: &[Token],
+        current_keyspace: &mut Option<String>,
+    ) -> Result<(), String> {
+        let mut cursor = Cursor::new(statement);
+        if cursor.eat_keyword("use") {
+            *current_keyspace = Some(cursor.parse_name()?);
+        } else if cursor.eat_keywords(&["create", "table"]) {
+            cursor.eat_keywords(&["if", "not", "exists"]);
+            let (keyspace, name) = self.qualify(&mut cursor, current_keyspace)?;
+            let table = self.parse_table_definition(&mut cursor, keyspace, name)?;
+            self.tables
+                .insert((table.keyspace.clone(), table.name.clone()), table);
+        } else if cursor.eat_keywords(&["create", "type"]) {
+            cursor.eat_keywords(&["if", "not", "exists"]);
+            let (keyspace, name) = self.qualify(&mut cursor, current_keyspace)?;
+            cursor.expect_symbol("(")?;
+            loop {
+                cursor.parse_name()?;
+                self.parse_type(&mut cursor, &keyspace)?;
+                if !cursor.eat_symbol(",") {
+                    break;
+                }
+            }
+            cursor.expect_symbol(")")?;
+            self.udts.insert((keyspace, name));
+        } else if cursor.eat_keywords(&["alter", "table"]) {
+            let key = self.qualify(&mut cursor, current_keyspace)?;
+            let mut table = self
+                .tables
+                .remove(&key)
+                .ok_or_else(|| format!("unknown table `{}.{}`", key.0, key.1))?;
+            let result = self.alter_table(&mut cursor, &mut table);
+            self.tables.insert(key, table);
+            result?;
+        } else if cursor.eat_keywords(&["drop", "table"]) {
+            cursor.eat_keywords(&["if", "exists"]);
+            let key = self.qualify(&mut cursor, current_keyspace)?;
+            self.tables.remove(&key);
+        } else if cursor.eat_keywords(&["drop", "type"]) {
+            cursor.eat_keywords(&["if", "exists"]);
+            let key = self.qualify(&mut cursor, current_keyspace)?;
+            self.udts.remove(&key);
+        }
+        Ok(())
+    }
+
+    fn qualify(
+        &self,
+        cursor: &mut Cursor,
+        current_keyspace: &Option<String>,
+    ) -> Result<(String, String), String> {
+        let (keyspace, name) = cursor.parse_qualified_name()?;
+        let keyspace = keyspace
+            .or_else(|| current_keyspace.clone())
+            .ok_or_else(|| {
+                format!(
+                    "`{}` is not qualified with a keyspace, and no keyspace was set with USE",
+                    name
+                )
+            })?;
+        Ok((keyspace, name))
+    }
+
+    fn parse_table_definition(
+        &self,
+        cursor: &mut Cursor,
+        keyspace: String,
+        name: String,
+    ) -> Result<Table, String> {
+        let mut columns = Vec::new();
+        let mut partition_key = Vec::new();
+        let mut clustering_key = Vec::new();
+
+        cursor.expect_symbol("(")?;
+        loop {
+            if cursor.eat_keywords(&["primary", "key"]) {
+                cursor.expect_symbol("(")?;
+                if cursor.peek_symbol("(") {
+                    partition_key = cursor.parse_name_list()?;
+                } else {
+                    partition_key = vec![cursor.parse_name()?];
+                }
+                while cursor.eat_symbol(",") {
+                    clustering_key.push(cursor.parse_name()?);
+                }
+                cursor.expect_symbol(")")?;
+            } else {
+                let column_name = cursor.parse_name()?;
+                let typ = self.parse_type(cursor, &keyspace)?;
+                let kind = if cursor.eat_keyword("static") {
+                    ColumnKind::Static
+                } else {
+                    ColumnKind::Regular
+                };
+                if cursor.eat_keywords(&["primary", "key"]) {
+                    partition_key = vec![column_name.clone()];
+                }
+                columns.push(Column {
+                    name: column_name,
+                    typ,
+                    kind,
+                });
+            }
+            if !cursor.eat_symbol(",") {
+                break;
+            }
+        }
+        cursor.expect_symbol(")")?;
+
+        if partition_key.is_empty() {
+            return Err(format!("table `{}.{}` has no primary key", keyspace, name));
+        }
+        for (key, kind) in partition_key
+            .iter()
+            .map(|key| (key, ColumnKind::PartitionKey))
+            .chain(
+                clustering_key
+                    .iter()
+                    .map(|key| (key, ColumnKind::Clustering)),
+            )
+        {
+            let column = columns
+                .iter_mut()
+                .find(|column| column.name == *key)
+                .ok_or_else(|| format!("unknown primary key column `{}`", key))?;
+            column.kind = kind;
+        }
+
+        let mut table = Table {
+            keyspace,
+            name,
+            columns,
+        };
+        table.sort_columns(&partition_key, &clustering_key);
+        Ok(table)
+    }
+
+    fn alter_table(&self, cursor: &mut Cursor, table: &mut Table) -> Result<(), String> {
+        if cursor.eat_keyword("add") {
+            let parenthesized = cursor.eat_symbol("(");
+            loop {
+                let name = cursor.parse_name()?;
+                let typ = self.parse_type(cursor, &table.keyspace)?;
+                let kind = if cursor.eat_keyword("static") {
+                    ColumnKind::Static
+                } else {
+                    ColumnKind::Regular
+                };
+                table.columns.push(Column { name, typ, kind });
+                if !cursor.eat_symbol(",") {
+                    break;
+                }
+            }
+            if parenthesized {
+                cursor.expect_symbol(")")?;
+            }
+        } else if cursor.eat_keyword("drop") {
+            let names = if cursor.peek_symbol("(") {
+                cursor.parse_name_list()?
+            } else {
+                vec![cursor.parse_name()?]
+            };
+            for name in names {
+                if table.column(&name)?.is_primary_key() {
+                    return Err(format!("cannot drop primary key column `{}`", name));
+                }
+                table.columns.retain(|column| column.name != name);
+            }
+        } else {
+            return Ok(());
+        }
+
+        let key_names = |kind| {
+            table
+                .columns
+                .iter()
+                .filter(|column| column.kind == kind)
+                .map(|column| column.name.clone())
+                .collect::<Vec<_>>()
+        };
+        let partition_key = key_names(ColumnKind::PartitionKey);
+        let clustering_key = key_names(ColumnKind::Clustering);
+        table.sort_columns(&partition_key, &clustering_key);
+        Ok(())
+    }
+
+    fn parse_type(&self, cursor: &mut Cursor, keyspace: &str) -> Result<CqlType, String> {
+        let (type_keyspace, name) = cursor.parse_qualified_name()?;
+        if type_keyspace.is_none() {
+            let mut parse_params = |count: Option<usize>| -> Result<Vec<CqlType>, String> {
+                cursor.expect_symbol("<")?;
+                let mut params = vec![self.parse_type(cursor, keyspace)?];
+                while cursor.eat_symbol(",") {
+                    params.push(self.parse_type(cursor, keyspace)?);
+                }
+                cursor.expect_symbol(">")?;
+                match count {
+                    Some(count) if params.len() != count => Err(format!(
+                        "`{}` takes {} type parameters, got {}",
+                        name,
+                        count,
+                        params.len()
+                    )),
+                    _ => Ok(params),
+                }
+            };
+            match name.as_str() {
+                "frozen" => return Ok(parse_params(Some(1))?.remove(0)),
+                "list" => return Ok(CqlType::List(Box::new(parse_params(Some(1))?.remove(0)))),
+                "set" => return Ok(CqlType::Set(Box::new(parse_params(Some(1))?.remove(0)))),
+                "map" => {
+                    let mut params = parse_params(Some(2))?;
+                    let value = params.pop().unwrap();
+                    let key = params.pop().unwrap();
+                    return Ok(CqlType::Map(Box::new(key), Box::new(value)));
+                }
+                "tuple" => return Ok(CqlType::Tuple(parse_params(None)?)),
+                _ => {}
+            }
+            if let Some(native) = NativeType::from_name(&name) {
+                return Ok(CqlType::Native(native));
+            }
+        }
+        let keyspace = type_keyspace.as_deref().unwrap_or(keyspace);
+        if self.udts.contains(&(keyspace.to_owned(), name.clone())) {
+            Ok(CqlType::Udt)
+        } else {
+            Err(format!("unknown type `{}`", name))
+        }
+    }
+}
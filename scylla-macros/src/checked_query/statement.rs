@@ -0,0 +1,643 @@
+use super::lexer::{tokenize, Cursor, Token, TokenKind};
+use super::schema::{Column, ColumnKind, CqlType, NativeType, Schema, Table};
+
+// Type of a bind marker or of a returned column.
+#[derive(Clone, Debug)]
+pub(super) struct ValueType {
+    pub(super) typ: CqlType,
+    pub(super) nullable: bool,
+}
+
+impl ValueType {
+    fn new(typ: CqlType) -> Self {
+        Self {
+            typ,
+            nullable: false,
+        }
+    }
+
+    fn nullable(typ: CqlType) -> Self {
+        Self {
+            typ,
+            nullable: true,
+        }
+    }
+
+    fn native(typ: NativeType) -> Self {
+        Self::new(CqlType::Native(typ))
+    }
+}
+
+// Types of the bind markers of a statement, in order, and of the columns it returns.
+pub(super) struct CheckedStatement {
+    pub(super) bind_markers: Vec<ValueType>,
+    pub(super) columns: Vec<ValueType>,
+}
+
+pub(super) fn check_statement(
+    statement: &str,
+    schema: &Schema,
+    default_keyspace: Option<&str>,
+) -> Result<CheckedStatement, String> {
+    let tokens = tokenize(statement)?;
+    let mut checker = Checker {
+        cursor: Cursor::new(&tokens),
+        schema,
+        default_keyspace,
+        bind_markers: Vec::new(),
+    };
+    let columns = checker.check_statement()?;
+    checker.cursor.eat_symbol(";");
+    if !checker.cursor.is_at_end() {
+        return Err(checker.cursor.unexpected("the end of the statement"));
+    }
+    Ok(CheckedStatement {
+        bind_markers: checker.bind_markers,
+        columns,
+    })
+}
+
+// A selector of a SELECT statement, before the table is known.
+enum Selector {
+    Wildcard,
+    Column(String),
+    Count,
+    WriteTime(String),
+    Ttl(String),
+    Token(Vec<String>),
+    Aggregate(String),
+}
+
+struct Checker<'t, 'a, 's> {
+    cursor: Cursor<'t, 'a>,
+    schema: &'s Schema,
+    default_keyspace: Option<&'s str>,
+    bind_markers: Vec<ValueType>,
+}
+
+impl<'s> Checker<'_, '_, 's> {
+    fn check_statement(&mut self) -> Result<Vec<ValueType>, String> {
+        if self.cursor.eat_keyword("select") {
+            self.check_select()
+        } else if self.cursor.eat_keyword("insert") {
+            self.check_insert()?;
+            Ok(Vec::new())
+        } else if self.cursor.eat_keyword("update") {
+            self.check_update()?;
+            Ok(Vec::new())
+        } else if self.cursor.eat_keyword("delete") {
+            self.check_delete()?;
+            Ok(Vec::new())
+        } else {
+            Err("only SELECT, INSERT, UPDATE and DELETE statements are supported".to_owned())
+        }
+    }
+
+    fn parse_table(&mut self) -> Result<&'s Table, String> {
+        let (keyspace, name) = self.cursor.parse_qualified_name()?;
+        self.schema
+            .table(keyspace.as_deref(), &name, self.default_keyspace)
+    }
+
+    fn check_select(&mut self) -> Result<Vec<ValueType>, String> {
+        let json = self.cursor.eat_keyword("json");
+        self.cursor.eat_keyword("distinct");
+
+        let mut selectors = Vec::new();
+        if self.cursor.eat_symbol("*") {
+            selectors.push(Selector::Wildcard);
+        } else {
+            loop {
+                selectors.push(self.parse_selector()?);
+                if self.cursor.eat_keyword("as") {
+                    self.cursor.parse_name()?;
+                }
+                if !self.cursor.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+
+        self.cursor.expect_keyword("from")?;
+        let table = self.parse_table()?;
+
+        let mut columns = Vec::new();
+        for selector in selectors {
+            match selector {
+                Selector::Wildcard => columns.extend(table.columns.iter().map(Self::column_value)),
+                Selector::Column(name) => columns.push(Self::column_value(table.column(&name)?)),
+                Selector::Count => columns.push(ValueType::native(NativeType::BigInt)),
+                Selector::WriteTime(name) => {
+                    table.column(&name)?;
+                    columns.push(ValueType::nullable(CqlType::Native(NativeType::BigInt)));
+                }
+                Selector::Ttl(name) => {
+                    table.column(&name)?;
+                    columns.push(ValueType::nullable(CqlType::Native(NativeType::Int)));
+                }
+                Selector::Token(names) => {
+                    self.check_partition_key(table, &names)?;
+                    columns.push(ValueType::native(NativeType::BigInt));
+                }
+                Selector::Aggregate(name) => {
+                    columns.push(ValueType::nullable(table.column(&name)?.typ.clone()));
+                }
+            }
+        }
+
+        if self.cursor.eat_keyword("where") {
+            self.check_relations(table)?;
+        }
+        if self.cursor.eat_keywords(&["group", "by"]) {
+            loop {
+                table.column(&self.cursor.parse_name()?)?;
+                if !self.cursor.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.cursor.eat_keywords(&["order", "by"]) {
+            loop {
+                table.column(&self.cursor.parse_name()?)?;
+                let _ = self.cursor.eat_keyword("asc") || self.cursor.eat_keyword("desc");
+                if !self.cursor.eat_symbol(",") {
+                    break;
+                }
+            }
+        }
+        if self.cursor.eat_keywords(&["per", "partition", "limit"]) {
+            self.check_term(ValueType::native(NativeType::Int))?;
+        }
+        if self.cursor.eat_keyword("limit") {
+            self.check_term(ValueType::native(NativeType::Int))?;
+        }
+        self.cursor.eat_keywords(&["allow", "filtering"]);
+        self.cursor.eat_keywords(&["bypass", "cache"]);
+        if self.cursor.eat_keyword("using") {
+            self.check_using_options()?;
+        }
+
+        if json {
+            Ok(vec![ValueType::native(NativeType::Text)])
+        } else {
+            Ok(columns)
+        }
+    }
+
+    fn parse_selector(&mut self) -> Result<Selector, String> {
+        let is_call = self.cursor.peek_nth(1).is_some_and(|t| t.is_symbol("("))
+            && self
+                .cursor
+                .peek()
+                .is_some_and(|t| t.kind == TokenKind::Word);
+        if !is_call {
+            return Ok(Selector::Column(self.cursor.parse_name()?));
+        }
+
+        let function = self.cursor.parse_name()?;
+        self.cursor.expect_symbol("(")?;
+        let selector = match function.as_str() {
+            "count" => {
+                // Either `count(*)`, `count(1)` or `count(column)`.
+                self.cursor.next();
+                Selector::Count
+            }
+            "writetime" => Selector::WriteTime(self.cursor.parse_name()?),
+            "ttl" => Selector::Ttl(self.cursor.parse_name()?),
+            "token" => {
+                let mut names = vec![self.cursor.parse_name()?];
+                while self.cursor.eat_symbol(",") {
+                    names.push(self.cursor.parse_name()?);
+                }
+                Selector::Token(names)
+            }
+            "min" | "max" | "sum" | "avg" => Selector::Aggregate(self.cursor.parse_name()?),
+            _ => return Err(format!("unsupported selector function `{}`", function)),
+        };
+        self.cursor.expect_symbol(")")?;
+        Ok(selector)
+    }
+
+    fn column_value(column: &Column) -> ValueType {
+        ValueType {
+            typ: column.typ.clone(),
+            nullable: !column.is_primary_key(),
+        }
+    }
+
+    fn check_insert(&mut self) -> Result<(), String> {
+        self.cursor.expect_keyword("into")?;
+        let table = self.parse_table()?;
+
+        if self.cursor.eat_keyword("json") {
+            self.check_term(ValueType::native(NativeType::Text))?;
+            let _ = self.cursor.eat_keywords(&["default", "null"])
+                || self.cursor.eat_keywords(&["default", "unset"]);
+        } else {
+            let names = self.cursor.parse_name_list()?;
+            let mut columns: Vec<&Column> = Vec::with_capacity(names.len());
+            for name in &names {
+                let column = table.column(name)?;
+                if columns.iter().any(|c| c.name == column.name) {
+                    return Err(format!("column `{}` is inserted more than once", name));
+                }
+                columns.push(column);
+            }
+            if let Some(missing) = table
+                .columns
+                .iter()
+                .find(|c| c.is_primary_key() && !names.contains(&c.name))
+            {
+                return Err(format!(
+                    "missing the value of primary key column `{}`",
+                    missing.name
+                ));
+            }
+
+            self.cursor.expect_keyword("values")?;
+            self.cursor.expect_symbol("(")?;
+            for (idx, column) in columns.iter().enumerate() {
+                if idx > 0 {
+                    self.cursor.expect_symbol(",")?;
+                }
+                self.check_term(Self::column_value(column))?;
+            }
+            if self.cursor.peek_symbol(",") {
+                return Err(format!(
+                    "more values than the {} inserted columns",
+                    columns.len()
+                ));
+            }
+            self.cursor.expect_symbol(")")?;
+        }
+
+        self.cursor.eat_keywords(&["if", "not", "exists"]);
+        if self.cursor.eat_keyword("using") {
+            self.check_using_options()?;
+        }
+        Ok(())
+    }
+
+    fn check_update(&mut self) -> Result<(), String> {
+        let table = self.parse_table()?;
+        if self.cursor.eat_keyword("using") {
+            self.check_using_options()?;
+        }
+
+        self.cursor.expect_keyword("set")?;
+        loop {
+            self.check_assignment(table)?;
+            if !self.cursor.eat_symbol(",") {
+                break;
+            }
+        }
+
+        self.cursor.expect_keyword("where")?;
+        self.check_relations(table)?;
+        self.check_conditions(table)
+    }
+
+    fn check_assignment(&mut self, table: &Table) -> Result<(), String> {
+        let column = self.non_key_column(table)?;
+        if self.cursor.eat_symbol("[") {
+            let (key, value) = Self::element_types(column)?;
+            self.check_term(ValueType::new(key))?;
+            self.cursor.expect_symbol("]")?;
+            self.cursor.expect_symbol("=")?;
+            return self.check_term(ValueType::nullable(value));
+        }
+        self.cursor.expect_symbol("=")?;
+
+        let is_self_reference = self
+            .cursor
+            .peek()
+            .and_then(Token::name)
+            .is_some_and(|name| name == column.name)
+            && self
+                .cursor
+                .peek_nth(1)
+                .is_some_and(|token| token.is_symbol("+") || token.is_symbol("-"));
+        if is_self_reference {
+            // `c = c + ?` or `c = c - ?`.
+            self.cursor.next();
+            let is_removal = self.cursor.next().unwrap().is_symbol("-");
+            let typ = match &column.typ {
+                CqlType::Map(key, _) if is_removal => CqlType::Set(key.clone()),
+                typ => typ.clone(),
+            };
+            self.check_term(ValueType::new(typ))
+        } else {
+            let value = if self.cursor.peek_symbol("?") || self.cursor.peek_symbol(":") {
+                ValueType::nullable(column.typ.clone())
+            } else {
+                ValueType::new(column.typ.clone())
+            };
+            let bind_marker_count = self.bind_markers.len();
+            self.check_term(value)?;
+            if self.cursor.eat_symbol("+") {
+                // `c = ? + c`, prepending to a list.
+                let name = self.cursor.parse_name()?;
+                if name != column.name {
+                    return Err(format!(
+                        "expected `{}` after `+`, found `{}`",
+                        column.name, name
+                    ));
+                }
+                if let Some(marker) = self.bind_markers.get_mut(bind_marker_count) {
+                    marker.nullable = false;
+                }
+            }
+            Ok(())
+        }
+    }
+
+    fn check_delete(&mut self) -> Result<(), String> {
+        // The deleted columns precede the table, so they are checked afterwards.
+        let mut deleted = Vec::new();
+        while !self.cursor.peek_keyword("from") {
+            let name = self.cursor.parse_name()?;
+            let element = if self.cursor.peek_symbol("[") {
+                let start = self.bind_markers.len();
+                self.cursor.expect_symbol("[")?;
+                self.check_term(ValueType::native(NativeType::Int))?;
+                self.cursor.expect_symbol("]")?;
+                Some(start)
+            } else {
+                None
+            };
+            deleted.push((name, element));
+            if !self.cursor.eat_symbol(",") {
+                break;
+            }
+        }
+        self.cursor.expect_keyword("from")?;
+        let table = self.parse_table()?;
+
+        for (name, element) in deleted {
+            let column = table.column(&name)?;
+            if column.is_primary_key() {
+                return Err(format!("cannot delete primary key column `{}`", name));
+            }
+            if let Some(idx) = element {
+                let (key, _) = Self::element_types(column)?;
+                if let Some(marker) = self.bind_markers.get_mut(idx) {
+                    marker.typ = key;
+                }
+            }
+        }
+
+        if self.cursor.eat_keyword("using") {
+            self.check_using_options()?;
+        }
+        self.cursor.expect_keyword("where")?;
+        self.check_relations(table)?;
+        self.check_conditions(table)
+    }
+
+    // Parses a column which can be modified.
+    fn non_key_column<'t>(&mut self, table: &'t Table) -> Result<&'t Column, String> {
+        let column = table.column(&self.cursor.parse_name()?)?;
+        if column.is_primary_key() {
+            return Err(format!(
+                "primary key column `{}` cannot be modified",
+                column.name
+            ));
+        }
+        Ok(column)
+    }
+
+    // Types of the index or key, and of the value, of an element of a collection.
+    fn element_types(column: &Column) -> Result<(CqlType, CqlType), String> {
+        match &column.typ {
+            CqlType::List(elem) => Ok((CqlType::Native(NativeType::Int), (**elem).clone())),
+            CqlType::Map(key, value) => Ok(((**key).clone(), (**value).clone())),
+            _ => Err(format!(
+                "column `{}` is neither a list nor a map",
+                column.name
+            )),
+        }
+    }
+
+    fn check_conditions(&mut self, table: &Table) -> Result<(), String> {
+        if !self.cursor.eat_keyword("if") {
+            return Ok(());
+        }
+        if self.cursor.eat_keyword("exists") {
+            return Ok(());
+        }
+        loop {
+            let column = self.non_key_column(table)?;
+            let typ = if self.cursor.eat_symbol("[") {
+                let (key, value) = Self::element_types(column)?;
+                self.check_term(ValueType::new(key))?;
+                self.cursor.expect_symbol("]")?;
+                value
+            } else {
+                column.typ.clone()
+            };
+            if self.cursor.eat_keyword("in") {
+                self.check_in_values(typ)?;
+            } else {
+                self.parse_operator()?;
+                self.check_term(ValueType::new(typ))?;
+            }
+            if !self.cursor.eat_keyword("and") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn check_using_options(&mut self) -> Result<(), String> {
+        loop {
+            let typ = if self.cursor.eat_keyword("ttl") {
+                NativeType::Int
+            } else if self.cursor.eat_keyword("timestamp") {
+                NativeType::BigInt
+            } else if self.cursor.eat_keyword("timeout") {
+                NativeType::Duration
+            } else {
+                return Err(self.cursor.unexpected("`TTL`, `TIMESTAMP` or `TIMEOUT`"));
+            };
+            self.check_term(ValueType::native(typ))?;
+            if !self.cursor.eat_keyword("and") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn check_relations(&mut self, table: &Table) -> Result<(), String> {
+        loop {
+            self.check_relation(table)?;
+            if !self.cursor.eat_keyword("and") {
+                return Ok(());
+            }
+        }
+    }
+
+    fn check_relation(&mut self, table: &Table) -> Result<(), String> {
+        if self.cursor.peek_keyword("token")
+            && self.cursor.peek_nth(1).is_some_and(|t| t.is_symbol("("))
+        {
+            self.cursor.next();
+            let names = self.cursor.parse_name_list()?;
+            let partition_key = self.check_partition_key(table, &names)?;
+            self.parse_operator()?;
+            if self.cursor.eat_keyword("token") {
+                self.cursor.expect_symbol("(")?;
+                for (idx, column) in partition_key.iter().enumerate() {
+                    if idx > 0 {
+                        self.cursor.expect_symbol(",")?;
+                    }
+                    self.check_term(ValueType::new(column.typ.clone()))?;
+                }
+                self.cursor.expect_symbol(")")?;
+                Ok(())
+            } else {
+                self.check_term(ValueType::native(NativeType::BigInt))
+            }
+        } else if self.cursor.peek_symbol("(") {
+            // A multi-column relation, e.g. `(a, b) > (?, ?)`.
+            let names = self.cursor.parse_name_list()?;
+            let types = names
+                .iter()
+                .map(|name| Ok(table.column(name)?.typ.clone()))
+                .collect::<Result<Vec<_>, String>>()?;
+            let tuple = CqlType::Tuple(types.clone());
+            if self.cursor.eat_keyword("in") {
+                self.check_in_values(tuple)
+            } else {
+                self.parse_operator()?;
+                if self.cursor.eat_symbol("(") {
+                    for (idx, typ) in types.into_iter().enumerate() {
+                        if idx > 0 {
+                            self.cursor.expect_symbol(",")?;
+                        }
+                        self.check_term(ValueType::new(typ))?;
+                    }
+                    self.cursor.expect_symbol(")")
+                } else {
+                    self.check_term(ValueType::new(tuple))
+                }
+            }
+        } else {
+            let column = table.column(&self.cursor.parse_name()?)?;
+            if self.cursor.eat_keyword("in") {
+                self.check_in_values(column.typ.clone())
+            } else if self.cursor.eat_keyword("contains") {
+                let typ = match (&column.typ, self.cursor.eat_keyword("key")) {
+                    (CqlType::Map(key, _), true) => (**key).clone(),
+                    (CqlType::Map(_, value), false) => (**value).clone(),
+                    (CqlType::List(elem) | CqlType::Set(elem), false) => (**elem).clone(),
+                    _ => {
+                        return Err(format!(
+                            "column `{}` is not a collection, or not a map for CONTAINS KEY",
+                            column.name
+                        ))
+                    }
+                };
+                self.check_term(ValueType::new(typ))
+            } else if self.cursor.eat_keyword("like") {
+                self.check_term(ValueType::new(column.typ.clone()))
+            } else if self.cursor.eat_keywords(&["is", "not", "null"]) {
+                Ok(())
+            } else {
+                self.parse_operator()?;
+                self.check_term(ValueType::new(column.typ.clone()))
+            }
+        }
+    }
+
+    // Checks that the columns are the partition key, in order, and returns them.
+    fn check_partition_key<'t>(
+        &self,
+        table: &'t Table,
+        names: &[String],
+    ) -> Result<Vec<&'t Column>, String> {
+        let partition_key: Vec<&Column> = table
+            .columns
+            .iter()
+            .filter(|column| column.kind == ColumnKind::PartitionKey)
+            .collect();
+        if !partition_key.iter().map(|c| &c.name).eq(names.iter()) {
+            return Err(format!(
+                "the arguments of `token` must be the partition key of table `{}`",
+                table
+            ));
+        }
+        Ok(partition_key)
+    }
+
+    // Checks the values of `IN`, either a single bind marker for all of them
+    // or a parenthesized list.
+    fn check_in_values(&mut self, typ: CqlType) -> Result<(), String> {
+        if self.cursor.eat_symbol("(") {
+            if self.cursor.eat_symbol(")") {
+                return Ok(());
+            }
+            loop {
+                self.check_term(ValueType::new(typ.clone()))?;
+                if !self.cursor.eat_symbol(",") {
+                    break;
+                }
+            }
+            self.cursor.expect_symbol(")")
+        } else {
+            self.check_term(ValueType::new(CqlType::List(Box::new(typ))))
+        }
+    }
+
+    fn parse_operator(&mut self) -> Result<(), String> {
+        for op in ["=", "<", ">", "<=", ">=", "!="] {
+            if self.cursor.eat_symbol(op) {
+                return Ok(());
+            }
+        }
+        Err(self.cursor.unexpected("an operator"))
+    }
+
+    // Checks a term, which is either a bind marker of the given type or a literal.
+    fn check_term(&mut self, value: ValueType) -> Result<(), String> {
+        if self.cursor.eat_symbol("?") {
+            self.bind_markers.push(value);
+            return Ok(());
+        }
+        if self.cursor.peek_symbol(":") {
+            self.cursor.next();
+            self.cursor.parse_name()?;
+            self.bind_markers.push(value);
+            return Ok(());
+        }
+
+        // A literal: a number, a string, a constant like `true` or `null`, a UUID,
+        // a function call like `now()` or a collection, tuple or UDT literal.
+        let _ = self.cursor.eat_symbol("-") || self.cursor.eat_symbol("+");
+        let has_bind_markers = match self.cursor.peek() {
+            Some(token) if ["(", "[", "{"].contains(&token.text) => self.cursor.skip_group()?,
+            Some(token) if token.kind != TokenKind::Symbol => {
+                self.cursor.next();
+                if self.cursor.peek_symbol("(") {
+                    self.cursor.skip_group()?
+                } else {
+                    // Continuation of a number or a UUID, e.g. `1.5` or `5e1f...-...`.
+                    while [".", "-"].iter().any(|s| self.cursor.peek_symbol(s))
+                        && self
+                            .cursor
+                            .peek_nth(1)
+                            .is_some_and(|t| t.kind == TokenKind::Word)
+                    {
+                        self.cursor.next();
+                        self.cursor.next();
+                    }
+                    false
+                }
+            }
+            _ => return Err(self.cursor.unexpected("a bind marker or a literal")),
+        };
+        if has_bind_markers {
+            return Err(
+                "bind markers inside literals and function calls are not supported".to_owned(),
+            );
+        }
+        Ok(())
+    }
+}
@@ -18,6 +18,12 @@ trait DeserializeCommonStructAttrs {
             None => parse_quote!(scylla::_macro_internal),
         }
     }
+
+    /// Whether the fields are matched solely by their order,
+    /// which allows them to be unnamed.
+    fn positional(&self) -> bool {
+        false
+    }
 }
 
 /// Provides access to attributes that are common to DeserializeValue
@@ -28,6 +34,10 @@ trait DeserializeCommonFieldAttrs {
 
     /// The type of the field, i.e. what this field deserializes to.
     fn deserialize_target(&self) -> &syn::Type;
+
+    /// The module given in the `with` attribute, whose functions
+    /// are used instead of the DeserializeValue implementation.
+    fn with(&self) -> Option<&syn::Path>;
 }
 
 /// A structure helpful in implementing DeserializeValue and DeserializeRow.
@@ -57,15 +67,19 @@ where
     ) -> Result<Self, syn::Error> {
         let attrs = Attrs::from_attributes(&input.attrs)?;
 
-        // TODO: support structs with unnamed fields.
-        // A few things to consider:
-        // - such support would necessarily require `enforce_order` and `skip_name_checks` attributes to be passed,
-        // - either:
-        //      - the inner code would have to represent unnamed fields differently and handle the errors differently,
-        //      - or we could use `.0, .1` or `0`, `1` as names for consecutive fields, making representation and error handling uniform.
-        let fields = crate::parser::parse_named_fields(input, trait_name)
-            .unwrap_or_else(|err| panic!("{}", err))
-            .named
+        // Unnamed fields are only supported by positional structs,
+        // which don't need the names of the fields.
+        let struct_fields = if attrs.positional() {
+            match crate::parser::parse_struct_fields(input, trait_name)? {
+                crate::parser::StructFields::Named(fields) => &fields.named,
+                crate::parser::StructFields::Unnamed(fields) => &fields.unnamed,
+            }
+        } else {
+            &crate::parser::parse_named_fields(input, trait_name)
+                .unwrap_or_else(|err| panic!("{}", err))
+                .named
+        };
+        let fields = struct_fields
             .iter()
             .map(Field::from_field)
             .collect::<Result<_, _>>()?;
@@ -94,6 +108,28 @@ where
         &self.fields
     }
 
+    /// The function type checking the given field.
+    fn field_type_check_fn(&self, field: &Field) -> syn::ExprPath {
+        self.field_fn(field, parse_quote!(type_check))
+    }
+
+    /// The function deserializing the given field.
+    fn field_deserialize_fn(&self, field: &Field) -> syn::ExprPath {
+        self.field_fn(field, parse_quote!(deserialize))
+    }
+
+    fn field_fn(&self, field: &Field, fn_name: syn::Ident) -> syn::ExprPath {
+        match field.with() {
+            Some(with) => parse_quote!(#with::#fn_name),
+            None => {
+                let macro_internal = self.attrs.macro_internal_path();
+                let (frame_lifetime, metadata_lifetime) = self.constraint_lifetimes();
+                let typ = field.deserialize_target();
+                parse_quote!(<#typ as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::#fn_name)
+            }
+        }
+    }
+
     fn generate_impl(
         &self,
         trait_: syn::Path,
@@ -101,24 +137,35 @@ where
     ) -> syn::ItemImpl {
         let (frame_lifetime, metadata_lifetime) = self.constraint_lifetimes();
         let (_, ty_generics, _) = self.generics.split_for_impl();
-        let impl_generics = &self.generics.params;
+
+        // The impl introduces the two lifetimes of the trait before the parameters
+        // of the struct. `split_for_impl` strips the defaults of type parameters,
+        // which are not allowed in impls.
+        let mut generics = self.generics.clone();
+        generics.params.insert(0, parse_quote!(#metadata_lifetime));
+        generics.params.insert(0, parse_quote!(#frame_lifetime));
 
         let macro_internal = self.attrs.macro_internal_path();
         let struct_name = &self.name;
+        let constraint_trait = &self.constraint_trait;
+        let constraint_trait: syn::Path = parse_quote!(
+            #macro_internal::#constraint_trait<#frame_lifetime, #metadata_lifetime>
+        );
         let predicates = generate_lifetime_constraints_for_impl(
             &self.generics,
-            self.constraint_trait.clone(),
+            constraint_trait,
             frame_lifetime,
         )
         .chain(generate_default_constraints(&self.fields));
+        generics.make_where_clause().predicates.extend(predicates);
+        let (impl_generics, _, where_clause) = generics.split_for_impl();
         let trait_: syn::Path = parse_quote!(#macro_internal::#trait_);
         let items = items.into_iter();
 
         parse_quote! {
             #[automatically_derived]
-            impl<#frame_lifetime, #metadata_lifetime, #impl_generics>
-                #trait_<#frame_lifetime, #metadata_lifetime> for #struct_name #ty_generics
-            where #(#predicates),*
+            impl #impl_generics #trait_<#frame_lifetime, #metadata_lifetime> for #struct_name #ty_generics
+            #where_clause
             {
                 #(#items)*
             }
@@ -161,10 +208,10 @@ fn generate_lifetime_constraints_for_impl<'a>(
     });
 
     // For each type parameter T, constrain it like this:
-    //     T: DeserializeValue<'lifetime>,
+    //     T: DeserializeValue<'lifetime, 'lifetime_>,
     let type_constraints = generics.type_params().map(move |t| {
         let t_ident = &t.ident;
-        parse_quote!(#t_ident: #trait_full_name<#constraint_lifetime>)
+        parse_quote!(#t_ident: #trait_full_name)
     });
 
     lifetime_constraints.chain(type_constraints)
@@ -172,7 +219,7 @@ fn generate_lifetime_constraints_for_impl<'a>(
 
 /// Generates a pair of new lifetime parameters, with a different name to any of the
 /// existing generic lifetimes.
-fn generate_pair_of_unique_lifetimes_for_impl(
+pub(crate) fn generate_pair_of_unique_lifetimes_for_impl(
     generics: &syn::Generics,
 ) -> (syn::Lifetime, syn::Lifetime) {
     let mut constraint_lifetime_name = "'lifetime".to_string();
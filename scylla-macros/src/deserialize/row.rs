@@ -5,7 +5,7 @@ use proc_macro2::Span;
 use syn::ext::IdentExt;
 use syn::parse_quote;
 
-use crate::Flavor;
+use crate::{Flavor, RenameAll};
 
 use super::{DeserializeCommonFieldAttrs, DeserializeCommonStructAttrs};
 
@@ -24,12 +24,27 @@ struct StructAttrs {
     // This annotation only works if `enforce_order` is specified.
     #[darling(default)]
     skip_name_checks: bool,
+
+    // If set, then the names of the columns are the names of the fields
+    // converted to this case convention. Fields with `rename` are not affected.
+    #[darling(default)]
+    rename_all: Option<RenameAll>,
+
+    // If true, then the columns are matched to the fields solely by their
+    // order, as with `enforce_order` and `skip_name_checks`. Required for
+    // tuple structs, whose fields have no names.
+    #[darling(default)]
+    positional: bool,
 }
 
 impl DeserializeCommonStructAttrs for StructAttrs {
     fn crate_path(&self) -> Option<&syn::Path> {
         self.crate_path.as_ref()
     }
+
+    fn positional(&self) -> bool {
+        self.positional
+    }
 }
 
 #[derive(FromField)]
@@ -46,6 +61,22 @@ struct Field {
     #[darling(default)]
     rename: Option<String>,
 
+    // If true, then the field is a struct deriving DeserializeRow itself,
+    // and its fields are deserialized from the columns of this row,
+    // as if they were fields of this struct.
+    #[darling(default)]
+    flatten: bool,
+
+    // If set, then the field is deserialized with the `type_check`
+    // and `deserialize` functions of this module instead of its
+    // DeserializeValue implementation.
+    #[darling(default)]
+    with: Option<syn::Path>,
+
+    // The `rename_all` attribute of the struct.
+    #[darling(skip)]
+    rename_all: Option<RenameAll>,
+
     ident: Option<syn::Ident>,
     ty: syn::Type,
 }
@@ -58,12 +89,16 @@ impl DeserializeCommonFieldAttrs for Field {
     fn deserialize_target(&self) -> &syn::Type {
         &self.ty
     }
+
+    fn with(&self) -> Option<&syn::Path> {
+        self.with.as_ref()
+    }
 }
 
 // derive(DeserializeRow) for the new DeserializeRow trait
 pub(crate) fn deserialize_row_derive(
     tokens_input: proc_macro::TokenStream,
-) -> Result<syn::ItemImpl, syn::Error> {
+) -> Result<proc_macro2::TokenStream, syn::Error> {
     let input = syn::parse(tokens_input)?;
 
     let implemented_trait: syn::Path = parse_quote! { DeserializeRow };
@@ -75,21 +110,91 @@ pub(crate) fn deserialize_row_derive(
         .unraw()
         .to_string();
     let constraining_trait = parse_quote! { DeserializeValue };
-    let s = StructDesc::new(&input, &implemented_trait_name, constraining_trait)?;
+    let mut s = StructDesc::new(&input, &implemented_trait_name, constraining_trait)?;
+    for field in s.fields.iter_mut() {
+        field.rename_all = s.attrs.rename_all;
+    }
 
     validate_attrs(&s.attrs, &s.fields)?;
 
+    // Positional structs are deserialized like `enforce_order` structs
+    // with `skip_name_checks`.
+    if s.attrs.positional {
+        s.attrs.flavor = Flavor::EnforceOrder;
+        s.attrs.skip_name_checks = true;
+    }
+
     let items = [
         s.generate_type_check_method().into(),
         s.generate_deserialize_method().into(),
     ];
 
-    Ok(s.generate_impl(implemented_trait, items))
+    let mut deserialize_row_impl = s.generate_impl(implemented_trait, items);
+    s.add_flattened_fields_constraints(&mut deserialize_row_impl);
+
+    // In the `match_by_name` flavor, the struct can be flattened into other structs,
+    // so the column-by-column deserialization is exposed as a separate trait.
+    let by_name_impl = (s.attrs.flavor == Flavor::MatchByName).then(|| {
+        let mut by_name_impl = s.generate_impl(
+            parse_quote! { DeserializeRowByName },
+            ByNameGenerator(&s).generate(),
+        );
+        s.add_flattened_fields_constraints(&mut by_name_impl);
+        by_name_impl
+    });
+
+    Ok(quote::quote! {
+        #deserialize_row_impl
+        #by_name_impl
+    })
 }
 
 fn validate_attrs(attrs: &StructAttrs, fields: &[Field]) -> Result<(), darling::Error> {
     let mut errors = darling::Error::accumulator();
 
+    if attrs.positional {
+        // Names are not used at all by positional structs
+        if attrs.rename_all.is_some() {
+            let error = darling::Error::custom(
+                "attribute <rename_all> doesn't make sense with <positional> attribute",
+            );
+            errors.push(error);
+        }
+        for field in fields {
+            if field.rename.is_some() || field.flatten {
+                let err = darling::Error::custom(
+                    "<rename> and <flatten> annotations don't make sense with <positional> attribute",
+                )
+                .with_span(&field.ident);
+                errors.push(err);
+            }
+        }
+        return errors.finish();
+    }
+
+    for field in fields.iter().filter(|f| f.flatten) {
+        // Flattening relies on matching columns to fields by name
+        if attrs.flavor != Flavor::MatchByName {
+            let err =
+                darling::Error::custom("<flatten> annotations require <match_by_name> flavor")
+                    .with_span(&field.ident);
+            errors.push(err);
+        }
+        if field.skip || field.rename.is_some() {
+            let err = darling::Error::custom(
+                "<flatten> annotations conflict with <skip> and <rename> annotations",
+            )
+            .with_span(&field.ident);
+            errors.push(err);
+        }
+        if field.with.is_some() {
+            let err =
+                darling::Error::custom("<flatten> annotations conflict with <with> annotations")
+                    .with_span(&field.ident);
+            errors.push(err);
+        }
+    }
+
     if attrs.skip_name_checks {
         // Skipping name checks is only available in enforce_order mode
         if attrs.flavor != Flavor::EnforceOrder {
@@ -98,7 +203,13 @@ fn validate_attrs(attrs: &StructAttrs, fields: &[Field]) -> Result<(), darling::
             errors.push(error);
         }
 
-        // <rename> annotations don't make sense with skipped name checks
+        // <rename_all> and <rename> annotations don't make sense with skipped name checks
+        if attrs.rename_all.is_some() {
+            let error = darling::Error::custom(
+                "attribute <rename_all> doesn't make sense with <skip_name_checks> attribute",
+            );
+            errors.push(error);
+        }
         for field in fields {
             if field.rename.is_some() {
                 let err = darling::Error::custom(
@@ -110,8 +221,10 @@ fn validate_attrs(attrs: &StructAttrs, fields: &[Field]) -> Result<(), darling::
         }
     } else {
         // Detect name collisions caused by `rename`.
+        // Collisions with the columns of flattened fields can only be detected
+        // in the runtime, by the type check.
         let mut used_names = HashMap::<String, &Field>::new();
-        for field in fields {
+        for field in fields.iter().filter(|f| !f.flatten) {
             let column_name = field.column_name();
             if let Some(other_field) = used_names.get(&column_name) {
                 let other_field_ident = other_field.ident.as_ref().unwrap();
@@ -137,7 +250,13 @@ impl Field {
     fn column_name(&self) -> String {
         match self.rename.as_ref() {
             Some(rename) => rename.to_owned(),
-            None => self.ident.as_ref().unwrap().unraw().to_string(),
+            None => {
+                let name = self.ident.as_ref().unwrap().unraw().to_string();
+                match self.rename_all {
+                    Some(rename_all) => rename_all.apply_to_field(&name),
+                    None => name,
+                }
+            }
         }
     }
 
@@ -150,6 +269,29 @@ impl Field {
 type StructDesc = super::StructDescForDeserialize<StructAttrs, Field>;
 
 impl StructDesc {
+    fn by_name_trait(&self) -> syn::Path {
+        let macro_internal = self.struct_attrs().macro_internal_path();
+        let (frame_lifetime, metadata_lifetime) = self.constraint_lifetimes();
+        parse_quote!(#macro_internal::DeserializeRowByName<#frame_lifetime, #metadata_lifetime>)
+    }
+
+    // Requires the types of flattened fields to be deserializable by name.
+    fn add_flattened_fields_constraints(&self, item: &mut syn::ItemImpl) {
+        let by_name = self.by_name_trait();
+        let predicates =
+            self.fields()
+                .iter()
+                .filter(|f| f.flatten)
+                .map(|f| -> syn::WherePredicate {
+                    let typ = f.deserialize_target();
+                    parse_quote!(#typ: #by_name)
+                });
+        item.generics
+            .make_where_clause()
+            .predicates
+            .extend(predicates);
+    }
+
     fn generate_type_check_method(&self) -> syn::ImplItemFn {
         match self.attrs.flavor {
             Flavor::MatchByName => TypeCheckUnorderedGenerator(self).generate(),
@@ -202,7 +344,6 @@ impl TypeCheckAssumeOrderGenerator<'_> {
         // of the columns correspond fields' names/types.
 
         let macro_internal = self.0.struct_attrs().macro_internal_path();
-        let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
 
         let required_fields_iter = || {
             self.0
@@ -221,8 +362,8 @@ impl TypeCheckAssumeOrderGenerator<'_> {
                 self.generate_name_verification(field_idx, col_idx, field, fidents)
             });
 
-        let required_fields_deserializers =
-            required_fields_iter().map(|(_, f)| f.deserialize_target());
+        let required_fields_type_checks =
+            required_fields_iter().map(|(_, f)| self.0.field_type_check_fn(f));
         let numbers = 0usize..;
 
         parse_quote! {
@@ -238,7 +379,7 @@ impl TypeCheckAssumeOrderGenerator<'_> {
                             #name_verifications
 
                             // Verify the type
-                            <#required_fields_deserializers as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::type_check(#required_fields_idents.typ())
+                            #required_fields_type_checks(#required_fields_idents.typ())
                                 .map_err(|err| #macro_internal::mk_row_typck_err::<Self>(
                                     column_types_iter(),
                                     #macro_internal::DeserBuiltinRowTypeCheckErrorKind::ColumnTypeCheckFailed {
@@ -275,11 +416,11 @@ impl DeserializeAssumeOrderGenerator<'_> {
         }
 
         let macro_internal = self.0.struct_attrs().macro_internal_path();
-        let cql_name_literal = field.cql_name_literal();
-        let deserializer = field.deserialize_target();
-        let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
+        let deserialize = self.0.field_deserialize_fn(field);
 
-        let name_check: Option<syn::Stmt> = (!self.0.struct_attrs().skip_name_checks).then(|| parse_quote! {
+        let name_check: Option<syn::Stmt> = (!self.0.struct_attrs().skip_name_checks).then(|| {
+            let cql_name_literal = field.cql_name_literal();
+            parse_quote! {
             if col.spec.name() != #cql_name_literal {
                 panic!(
                     "Typecheck should have prevented this scenario - field-column name mismatch! Rust field name {}, CQL column name {}",
@@ -287,6 +428,7 @@ impl DeserializeAssumeOrderGenerator<'_> {
                     col.spec.name()
                 );
             }
+            }
         });
 
         parse_quote!(
@@ -297,7 +439,7 @@ impl DeserializeAssumeOrderGenerator<'_> {
 
                 #name_check
 
-                <#deserializer as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::deserialize(col.spec.typ(), col.slice)
+                #deserialize(col.spec.typ(), col.slice)
                     .map_err(|err| #macro_internal::mk_row_deser_err::<Self>(
                         #macro_internal::BuiltinRowDeserializationErrorKind::ColumnDeserializationFailed {
                             column_index: #field_index,
@@ -314,7 +456,13 @@ impl DeserializeAssumeOrderGenerator<'_> {
         let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
 
         let fields = self.0.fields();
-        let field_idents = fields.iter().map(|f| f.ident.as_ref().unwrap());
+        // Tuple struct fields are initialized by their indices, e.g. `Self { 0: ... }`.
+        let field_members = fields.iter().enumerate().map(|(idx, f)| -> syn::Member {
+            match &f.ident {
+                Some(ident) => syn::Member::Named(ident.clone()),
+                None => syn::Member::Unnamed(idx.into()),
+            }
+        });
         let field_finalizers = fields
             .iter()
             .enumerate()
@@ -326,7 +474,7 @@ impl DeserializeAssumeOrderGenerator<'_> {
                 mut row: #macro_internal::ColumnIterator<#frame_lifetime, #metadata_lifetime>,
             ) -> ::std::result::Result<Self, #macro_internal::DeserializationError> {
                 ::std::result::Result::Ok(Self {
-                    #(#field_idents: #field_finalizers,)*
+                    #(#field_members: #field_finalizers,)*
                 })
             }
         }
@@ -336,132 +484,40 @@ impl DeserializeAssumeOrderGenerator<'_> {
 struct TypeCheckUnorderedGenerator<'sd>(&'sd StructDesc);
 
 impl TypeCheckUnorderedGenerator<'_> {
-    // An identifier for a bool variable that represents whether given
-    // field was already visited during type check
-    fn visited_flag_variable(field: &Field) -> syn::Ident {
-        quote::format_ident!("visited_{}", field.ident.as_ref().unwrap().unraw())
-    }
-
-    // Generates a declaration of a "visited" flag for the purpose of type check.
-    // We generate it even if the flag is not required in order to protect
-    // from fields appearing more than once
-    fn generate_visited_flag_decl(field: &Field) -> Option<syn::Stmt> {
-        (!field.skip).then(|| {
-            let visited_flag = Self::visited_flag_variable(field);
-            parse_quote! {
-                let mut #visited_flag = false;
-            }
-        })
-    }
-
-    // Generates code that, given variable `typ`, type-checks given field
-    fn generate_type_check(&self, field: &Field) -> Option<syn::Block> {
-        (!field.skip).then(|| {
-            let macro_internal = self.0.struct_attrs().macro_internal_path();
-            let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
-            let visited_flag = Self::visited_flag_variable(field);
-            let typ = field.deserialize_target();
-            let cql_name_literal = field.cql_name_literal();
-            let decrement_if_required: Option::<syn::Stmt> = field.is_required().then(|| parse_quote! {
-                remaining_required_fields -= 1;
-            });
-
-            parse_quote! {
-                {
-                    if !#visited_flag {
-                        <#typ as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::type_check(spec.typ())
-                            .map_err(|err| {
-                                #macro_internal::mk_row_typck_err::<Self>(
-                                    column_types_iter(),
-                                    #macro_internal::DeserBuiltinRowTypeCheckErrorKind::ColumnTypeCheckFailed {
-                                        column_index,
-                                        column_name: <_ as ::std::borrow::ToOwned>::to_owned(#cql_name_literal),
-                                        err,
-                                    }
-                                )
-                            })?;
-                        #visited_flag = true;
-                        #decrement_if_required
-                    } else {
-                        return ::std::result::Result::Err(
-                            #macro_internal::mk_row_typck_err::<Self>(
-                                column_types_iter(),
-                                #macro_internal::DeserBuiltinRowTypeCheckErrorKind::DuplicatedColumn {
-                                    column_index,
-                                    column_name: #cql_name_literal,
-                                }
-                            )
-                        )
-                    }
-                }
-            }
-        })
-    }
-
-    // Generates code that appends the flag name if it is missing.
-    // The generated code is used to construct a nice error message.
-    fn generate_append_name(field: &Field) -> Option<syn::Block> {
-        field.is_required().then(|| {
-            let visited_flag = Self::visited_flag_variable(field);
-            let cql_name_literal = field.cql_name_literal();
-            parse_quote! {
-                {
-                    if !#visited_flag {
-                        missing_fields.push(#cql_name_literal);
-                    }
-                }
-            }
-        })
-    }
-
     fn generate(&self) -> syn::ImplItemFn {
         let macro_internal = self.0.struct_attrs().macro_internal_path();
-
-        let fields = self.0.fields();
-        let visited_field_declarations = fields.iter().flat_map(Self::generate_visited_flag_decl);
-        let type_check_blocks = fields.iter().flat_map(|f| self.generate_type_check(f));
-        let append_name_blocks = fields.iter().flat_map(Self::generate_append_name);
-        let nonskipped_field_names = fields
-            .iter()
-            .filter(|f| !f.skip)
-            .map(|f| f.cql_name_literal());
-        let field_count_lit = fields.iter().filter(|f| f.is_required()).count();
+        let by_name = self.0.by_name_trait();
 
         parse_quote! {
             fn type_check(
                 specs: &[#macro_internal::ColumnSpec],
             ) -> ::std::result::Result<(), #macro_internal::TypeCheckError> {
-                // Counts down how many required fields are remaining
-                let mut remaining_required_fields: ::std::primitive::usize = #field_count_lit;
-
-                // For each required field, generate a "visited" boolean flag
-                #(#visited_field_declarations)*
-
                 let column_types_iter = || specs.iter().map(|spec| ::std::clone::Clone::clone(spec.typ()).into_owned());
 
+                let mut state = <Self as #by_name>::new_type_check_state();
+
                 for (column_index, spec) in specs.iter().enumerate() {
-                    // Pattern match on the name and verify that the type is correct.
-                    match spec.name() {
-                        #(#nonskipped_field_names => #type_check_blocks,)*
-                        _unknown => {
-                            return ::std::result::Result::Err(
-                                #macro_internal::mk_row_typck_err::<Self>(
-                                    column_types_iter(),
-                                    #macro_internal::DeserBuiltinRowTypeCheckErrorKind::ColumnWithUnknownName {
-                                        column_index,
-                                        column_name: <_ as ::std::borrow::ToOwned>::to_owned(spec.name())
-                                    }
-                                )
+                    // Find the field corresponding to the column and verify that the type is correct.
+                    let known = <Self as #by_name>::type_check_column(&mut state, column_index, spec)
+                        .map_err(|kind| #macro_internal::mk_row_typck_err::<Self>(column_types_iter(), kind))?;
+                    if !known {
+                        return ::std::result::Result::Err(
+                            #macro_internal::mk_row_typck_err::<Self>(
+                                column_types_iter(),
+                                #macro_internal::DeserBuiltinRowTypeCheckErrorKind::ColumnWithUnknownName {
+                                    column_index,
+                                    column_name: <_ as ::std::borrow::ToOwned>::to_owned(spec.name())
+                                }
                             )
-                        }
+                        );
                     }
                 }
 
-                if remaining_required_fields > 0 {
-                    // If there are some missing required fields, generate an error
-                    // which contains missing field names
-                    let mut missing_fields = ::std::vec::Vec::<&'static str>::with_capacity(remaining_required_fields);
-                    #(#append_name_blocks)*
+                // If there are some missing required fields, generate an error
+                // which contains missing field names
+                let mut missing_fields = ::std::vec::Vec::<&'static str>::new();
+                <Self as #by_name>::missing_columns(&state, &mut missing_fields);
+                if !missing_fields.is_empty() {
                     return ::std::result::Result::Err(
                         #macro_internal::mk_row_typck_err::<Self>(
                             column_types_iter(),
@@ -469,7 +525,7 @@ impl TypeCheckUnorderedGenerator<'_> {
                                 column_names: missing_fields
                             }
                         )
-                    )
+                    );
                 }
 
                 ::std::result::Result::Ok(())
@@ -481,127 +537,298 @@ impl TypeCheckUnorderedGenerator<'_> {
 struct DeserializeUnorderedGenerator<'sd>(&'sd StructDesc);
 
 impl DeserializeUnorderedGenerator<'_> {
-    // An identifier for a variable that is meant to store the parsed variable
-    // before being ultimately moved to the struct on deserialize
-    fn deserialize_field_variable(field: &Field) -> syn::Ident {
-        quote::format_ident!("f_{}", field.ident.as_ref().unwrap().unraw())
-    }
+    fn generate(&self) -> syn::ImplItemFn {
+        let macro_internal = self.0.struct_attrs().macro_internal_path();
+        let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
+        let by_name = self.0.by_name_trait();
 
-    // Generates an expression which produces a value ready to be put into a field
-    // of the target structure
-    fn generate_finalize_field(&self, field: &Field) -> syn::Expr {
-        if field.skip {
-            // Skipped fields are initialized with Default::default()
-            return parse_quote! {
-                ::std::default::Default::default()
-            };
-        }
+        // TODO: Allow collecting unrecognized fields into some special field
 
-        let deserialize_field = Self::deserialize_field_variable(field);
-        let cql_name_literal = field.cql_name_literal();
         parse_quote! {
-            #deserialize_field.unwrap_or_else(|| panic!(
-                "column {} missing in DB row - type check should have prevented this!",
-                #cql_name_literal
-            ))
+            fn deserialize(
+                #[allow(unused_mut)]
+                mut row: #macro_internal::ColumnIterator<#frame_lifetime, #metadata_lifetime>,
+            ) -> ::std::result::Result<Self, #macro_internal::DeserializationError> {
+                // Temporary storage for the fields' values.
+                let mut partial = <Self as #by_name>::new_partial();
+
+                for col in row {
+                    let col = col.map_err(#macro_internal::row_deser_error_replace_rust_name::<Self>)?;
+                    let known = <Self as #by_name>::deserialize_column(&mut partial, &col)
+                        .map_err(#macro_internal::mk_row_deser_err::<Self>)?;
+                    if !known {
+                        unreachable!("Typecheck should have prevented this scenario! Unknown column name: {}", col.spec.name());
+                    }
+                }
+
+                // Create the final struct out of the temporary storage.
+                ::std::result::Result::Ok(<Self as #by_name>::finish(partial))
+            }
         }
     }
+}
 
-    // Generated code that performs deserialization when the raw field
-    // is being processed
-    fn generate_deserialization(&self, column_index: usize, field: &Field) -> syn::Expr {
-        assert!(!field.skip);
-        let macro_internal = self.0.struct_attrs().macro_internal_path();
-        let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
-        let deserialize_field = Self::deserialize_field_variable(field);
-        let deserializer = field.deserialize_target();
+// Generates the impl of DeserializeRowByName, which processes the columns one by one.
+// The state of the type check is a tuple of "visited" flags, and the partially
+// deserialized row is a tuple of optional values, both with an element per
+// non-skipped field. Flattened fields are represented by the state of their type.
+// Columns whose names don't match any regular field are passed to the flattened
+// fields, in order.
+struct ByNameGenerator<'sd>(&'sd StructDesc);
+
+impl ByNameGenerator<'_> {
+    // Non-skipped fields, along with the index of their element in the state tuples.
+    fn state_fields(&self) -> impl Iterator<Item = (syn::Index, &Field)> {
+        self.0
+            .fields()
+            .iter()
+            .filter(|f| !f.skip)
+            .enumerate()
+            .map(|(idx, f)| (syn::Index::from(idx), f))
+    }
 
-        parse_quote! {
-            {
-                assert!(
-                    #deserialize_field.is_none(),
-                    "duplicated column {} - type check should have prevented this!",
-                    stringify!(#deserialize_field)
-                );
+    fn regular_fields(&self) -> impl Iterator<Item = (syn::Index, &Field)> {
+        self.state_fields().filter(|(_, f)| !f.flatten)
+    }
 
-                #deserialize_field = ::std::option::Option::Some(
-                    <#deserializer as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::deserialize(col.spec.typ(), col.slice)
+    fn flattened_fields(&self) -> impl Iterator<Item = (syn::Index, &Field)> {
+        self.state_fields().filter(|(_, f)| f.flatten)
+    }
+
+    fn generate_type_check_column(&self) -> syn::ImplItemFn {
+        let macro_internal = self.0.struct_attrs().macro_internal_path();
+        let by_name = self.0.by_name_trait();
+
+        let field_names = self.regular_fields().map(|(_, f)| f.cql_name_literal());
+        let type_check_blocks = self.regular_fields().map(|(idx, field)| -> syn::Block {
+            let type_check = self.0.field_type_check_fn(field);
+            let cql_name_literal = field.cql_name_literal();
+            parse_quote! {
+                {
+                    if state.#idx {
+                        return ::std::result::Result::Err(
+                            #macro_internal::DeserBuiltinRowTypeCheckErrorKind::DuplicatedColumn {
+                                column_index,
+                                column_name: #cql_name_literal,
+                            }
+                        );
+                    }
+                    #type_check(spec.typ())
                         .map_err(|err| {
-                            #macro_internal::mk_row_deser_err::<Self>(
-                                #macro_internal::BuiltinRowDeserializationErrorKind::ColumnDeserializationFailed {
-                                    column_index: #column_index,
-                                    column_name: <_ as std::borrow::ToOwned>::to_owned(col.spec.name()),
-                                    err,
-                                }
-                            )
-                        })?
-                );
+                            #macro_internal::DeserBuiltinRowTypeCheckErrorKind::ColumnTypeCheckFailed {
+                                column_index,
+                                column_name: <_ as ::std::borrow::ToOwned>::to_owned(#cql_name_literal),
+                                err,
+                            }
+                        })?;
+                    state.#idx = true;
+                    ::std::result::Result::Ok(true)
+                }
+            }
+        });
+        let flattened_idxs = self.flattened_fields().map(|(idx, _)| idx);
+        let flattened_types = self.flattened_fields().map(|(_, f)| f.deserialize_target());
+
+        parse_quote! {
+            #[allow(unused_variables)]
+            fn type_check_column(
+                state: &mut Self::TypeCheckState,
+                column_index: ::std::primitive::usize,
+                spec: &#macro_internal::ColumnSpec,
+            ) -> ::std::result::Result<::std::primitive::bool, #macro_internal::DeserBuiltinRowTypeCheckErrorKind> {
+                match spec.name() {
+                    #(#field_names => #type_check_blocks,)*
+                    _ => {
+                        #(
+                            if <#flattened_types as #by_name>::type_check_column(&mut state.#flattened_idxs, column_index, spec)? {
+                                return ::std::result::Result::Ok(true);
+                            }
+                        )*
+                        ::std::result::Result::Ok(false)
+                    }
+                }
             }
         }
     }
 
-    // Generate a declaration of a variable that temporarily keeps
-    // the deserialized value
-    fn generate_deserialize_field_decl(field: &Field) -> Option<syn::Stmt> {
-        (!field.skip).then(|| {
-            let deserialize_field = Self::deserialize_field_variable(field);
-            parse_quote! {
-                let mut #deserialize_field = ::std::option::Option::None;
+    fn generate_missing_columns(&self) -> syn::ImplItemFn {
+        let by_name = self.0.by_name_trait();
+
+        let appends = self.state_fields().map(|(idx, field)| -> syn::Stmt {
+            if field.flatten {
+                let typ = field.deserialize_target();
+                parse_quote! {
+                    <#typ as #by_name>::missing_columns(&state.#idx, missing);
+                }
+            } else {
+                let cql_name_literal = field.cql_name_literal();
+                parse_quote! {
+                    if !state.#idx {
+                        missing.push(#cql_name_literal);
+                    }
+                }
             }
-        })
+        });
+
+        parse_quote! {
+            #[allow(unused_variables)]
+            fn missing_columns(
+                state: &Self::TypeCheckState,
+                missing: &mut ::std::vec::Vec<&'static ::std::primitive::str>,
+            ) {
+                #(#appends)*
+            }
+        }
     }
 
-    fn generate(&self) -> syn::ImplItemFn {
+    fn generate_deserialize_column(&self) -> syn::ImplItemFn {
         let macro_internal = self.0.struct_attrs().macro_internal_path();
         let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
-        let fields = self.0.fields();
-
-        let deserialize_field_decls = fields
-            .iter()
-            .flat_map(Self::generate_deserialize_field_decl);
-        let deserialize_blocks = fields
-            .iter()
-            .filter(|f| !f.skip)
-            .enumerate()
-            .map(|(col_idx, f)| self.generate_deserialization(col_idx, f));
-        let field_idents = fields.iter().map(|f| f.ident.as_ref().unwrap());
-        let nonskipped_field_names = fields
-            .iter()
-            .filter(|&f| (!f.skip))
-            .map(|f| f.cql_name_literal());
+        let by_name = self.0.by_name_trait();
 
-        let field_finalizers = fields.iter().map(|f| self.generate_finalize_field(f));
+        let field_names = self.regular_fields().map(|(_, f)| f.cql_name_literal());
+        let deserialize_blocks = self.regular_fields().map(|(idx, field)| -> syn::Block {
+            let deserialize = self.0.field_deserialize_fn(field);
+            let cql_name_literal = field.cql_name_literal();
+            parse_quote! {
+                {
+                    assert!(
+                        partial.#idx.is_none(),
+                        "duplicated column {} - type check should have prevented this!",
+                        #cql_name_literal
+                    );
 
-        // TODO: Allow collecting unrecognized fields into some special field
+                    partial.#idx = ::std::option::Option::Some(
+                        #deserialize(column.spec.typ(), column.slice)
+                            .map_err(|err| {
+                                #macro_internal::BuiltinRowDeserializationErrorKind::ColumnDeserializationFailed {
+                                    column_index: column.index,
+                                    column_name: <_ as std::borrow::ToOwned>::to_owned(column.spec.name()),
+                                    err,
+                                }
+                            })?
+                    );
+                    ::std::result::Result::Ok(true)
+                }
+            }
+        });
+        let flattened_idxs = self.flattened_fields().map(|(idx, _)| idx);
+        let flattened_types = self.flattened_fields().map(|(_, f)| f.deserialize_target());
 
         parse_quote! {
-            fn deserialize(
-                #[allow(unused_mut)]
-                mut row: #macro_internal::ColumnIterator<#frame_lifetime, #metadata_lifetime>,
-            ) -> ::std::result::Result<Self, #macro_internal::DeserializationError> {
+            #[allow(unused_variables)]
+            fn deserialize_column(
+                partial: &mut Self::Partial,
+                column: &#macro_internal::RawColumn<#frame_lifetime, #metadata_lifetime>,
+            ) -> ::std::result::Result<::std::primitive::bool, #macro_internal::BuiltinRowDeserializationErrorKind> {
+                match column.spec.name() {
+                    #(#field_names => #deserialize_blocks,)*
+                    _ => {
+                        #(
+                            if <#flattened_types as #by_name>::deserialize_column(&mut partial.#flattened_idxs, column)? {
+                                return ::std::result::Result::Ok(true);
+                            }
+                        )*
+                        ::std::result::Result::Ok(false)
+                    }
+                }
+            }
+        }
+    }
 
-                // Generate fields that will serve as temporary storage
-                // for the fields' values. Those are of type Option<FieldType>.
-                #(#deserialize_field_decls)*
+    fn generate_finish(&self) -> syn::ImplItemFn {
+        let by_name = self.0.by_name_trait();
 
-                for col in row {
-                    let col = col.map_err(#macro_internal::row_deser_error_replace_rust_name::<Self>)?;
-                    // Pattern match on the field name and deserialize.
-                    match col.spec.name() {
-                        #(#nonskipped_field_names => #deserialize_blocks,)*
-                        unknown => unreachable!("Typecheck should have prevented this scenario! Unknown column name: {}", unknown),
-                    }
+        let mut state_idxs = self.state_fields().map(|(idx, _)| idx);
+        let field_idents = self.0.fields().iter().map(|f| f.ident.as_ref().unwrap());
+        let field_finalizers = self.0.fields().iter().map(|field| -> syn::Expr {
+            if field.skip {
+                // Skipped fields are initialized with Default::default()
+                return parse_quote!(::std::default::Default::default());
+            }
+
+            let idx = state_idxs.next().unwrap();
+            if field.flatten {
+                let typ = field.deserialize_target();
+                parse_quote!(<#typ as #by_name>::finish(partial.#idx))
+            } else {
+                let cql_name_literal = field.cql_name_literal();
+                parse_quote! {
+                    partial.#idx.unwrap_or_else(|| panic!(
+                        "column {} missing in DB row - type check should have prevented this!",
+                        #cql_name_literal
+                    ))
                 }
+            }
+        });
 
-                // Create the final struct. The finalizer expressions convert
-                // the temporary storage fields to the final field values.
-                // For example, if a field is missing but marked as
-                // `default_when_null` it will create a default value, otherwise
-                // it will report an error.
-                Ok(Self {
+        parse_quote! {
+            #[allow(unused_variables)]
+            fn finish(partial: Self::Partial) -> Self {
+                Self {
                     #(#field_idents: #field_finalizers,)*
-                })
+                }
             }
         }
     }
+
+    fn generate(&self) -> Vec<syn::ImplItem> {
+        let by_name = self.0.by_name_trait();
+
+        let state_types = self.state_fields().map(|(_, field)| -> syn::Type {
+            let typ = field.deserialize_target();
+            if field.flatten {
+                parse_quote!(<#typ as #by_name>::TypeCheckState)
+            } else {
+                parse_quote!(::std::primitive::bool)
+            }
+        });
+        let state_inits = self.state_fields().map(|(_, field)| -> syn::Expr {
+            let typ = field.deserialize_target();
+            if field.flatten {
+                parse_quote!(<#typ as #by_name>::new_type_check_state())
+            } else {
+                parse_quote!(false)
+            }
+        });
+        let partial_types = self.state_fields().map(|(_, field)| -> syn::Type {
+            let typ = field.deserialize_target();
+            if field.flatten {
+                parse_quote!(<#typ as #by_name>::Partial)
+            } else {
+                parse_quote!(::std::option::Option<#typ>)
+            }
+        });
+        let partial_inits = self.state_fields().map(|(_, field)| -> syn::Expr {
+            let typ = field.deserialize_target();
+            if field.flatten {
+                parse_quote!(<#typ as #by_name>::new_partial())
+            } else {
+                parse_quote!(::std::option::Option::None)
+            }
+        });
+
+        vec![
+            parse_quote!(type TypeCheckState = (#(#state_types,)*);),
+            parse_quote!(type Partial = (#(#partial_types,)*);),
+            parse_quote! {
+                // The state is an empty tuple if there are no fields.
+                #[allow(clippy::unused_unit)]
+                fn new_type_check_state() -> Self::TypeCheckState {
+                    (#(#state_inits,)*)
+                }
+            },
+            self.generate_type_check_column().into(),
+            self.generate_missing_columns().into(),
+            parse_quote! {
+                // The state is an empty tuple if there are no fields.
+                #[allow(clippy::unused_unit)]
+                fn new_partial() -> Self::Partial {
+                    (#(#partial_inits,)*)
+                }
+            },
+            self.generate_deserialize_column().into(),
+            self.generate_finish().into(),
+        ]
+    }
 }
@@ -5,7 +5,7 @@ use proc_macro::TokenStream;
 use proc_macro2::Span;
 use syn::{ext::IdentExt, parse_quote};
 
-use crate::Flavor;
+use crate::{Flavor, RenameAll};
 
 use super::{DeserializeCommonFieldAttrs, DeserializeCommonStructAttrs};
 
@@ -31,6 +31,21 @@ struct StructAttrs {
     // they will be ignored. With true, an error will be raised.
     #[darling(default)]
     forbid_excess_udt_fields: bool,
+
+    // If set, then the names of the UDT fields are the names of the struct fields
+    // converted to this case convention. Fields with `rename` are not affected.
+    #[darling(default)]
+    rename_all: Option<RenameAll>,
+
+    // If set, then the type checking code will verify that the name of the UDT
+    // is the same, so that UDTs with the same fields are not confused.
+    #[darling(default)]
+    udt_name: Option<String>,
+
+    // If set, then the type checking code will verify that the UDT is defined
+    // in this keyspace.
+    #[darling(default)]
+    keyspace: Option<String>,
 }
 
 impl DeserializeCommonStructAttrs for StructAttrs {
@@ -40,7 +55,7 @@ impl DeserializeCommonStructAttrs for StructAttrs {
 }
 
 #[derive(FromField)]
-#[darling(attributes(scylla))]
+#[darling(attributes(scylla), and_then = Self::resolve_default)]
 struct Field {
     // If true, then the field is not parsed at all, but it is initialized
     // with Default::default() instead. All other attributes are ignored.
@@ -59,30 +74,66 @@ struct Field {
     #[darling(default)]
     default_when_null: bool,
 
+    // Shorthand for both `allow_missing` and `default_when_null`: the field
+    // is initialized to Default::default() whenever the database has no value
+    // for it, e.g. after ALTER TYPE has added/removed fields.
+    #[darling(default)]
+    default: bool,
+
     // If set, then deserializes from the UDT field with this particular name
     // instead of the Rust field name.
     #[darling(default)]
     rename: Option<String>,
 
+    // If set, then the field is deserialized with the `type_check`
+    // and `deserialize` functions of this module instead of its
+    // DeserializeValue implementation.
+    #[darling(default)]
+    with: Option<syn::Path>,
+
+    // The `rename_all` attribute of the struct.
+    #[darling(skip)]
+    rename_all: Option<RenameAll>,
+
     ident: Option<syn::Ident>,
     ty: syn::Type,
 }
 
+impl Field {
+    fn resolve_default(mut self) -> darling::Result<Self> {
+        if self.default {
+            self.default_when_missing = true;
+            self.default_when_null = true;
+        }
+        Ok(self)
+    }
+}
+
 impl DeserializeCommonFieldAttrs for Field {
     fn needs_default(&self) -> bool {
-        self.skip || self.default_when_missing
+        self.skip || self.default_when_missing || self.default_when_null
     }
 
     fn deserialize_target(&self) -> &syn::Type {
         &self.ty
     }
+
+    fn with(&self) -> Option<&syn::Path> {
+        self.with.as_ref()
+    }
 }
 
 // derive(DeserializeValue) for the DeserializeValue trait
 pub(crate) fn deserialize_value_derive(
     tokens_input: TokenStream,
 ) -> Result<syn::ItemImpl, syn::Error> {
-    let input = syn::parse(tokens_input)?;
+    let input: syn::DeriveInput = syn::parse(tokens_input)?;
+    if crate::transparent::is_transparent(&input.attrs) {
+        return crate::transparent::derive_deserialize_value(&input);
+    }
+    if let syn::Data::Enum(data) = &input.data {
+        return crate::enum_value::derive_deserialize_value(&input, data);
+    }
 
     let implemented_trait: syn::Path = parse_quote!(DeserializeValue);
     let implemented_trait_name = implemented_trait
@@ -93,7 +144,10 @@ pub(crate) fn deserialize_value_derive(
         .unraw()
         .to_string();
     let constraining_trait = implemented_trait.clone();
-    let s = StructDesc::new(&input, &implemented_trait_name, constraining_trait)?;
+    let mut s = StructDesc::new(&input, &implemented_trait_name, constraining_trait)?;
+    for field in s.fields.iter_mut() {
+        field.rename_all = s.attrs.rename_all;
+    }
 
     validate_attrs(&s.attrs, s.fields())?;
 
@@ -137,7 +191,13 @@ fn validate_attrs(attrs: &StructAttrs, fields: &[Field]) -> Result<(), darling::
             errors.push(error);
         }
 
-        // <rename> annotations don't make sense with skipped name checks
+        // <rename_all> and <rename> annotations don't make sense with skipped name checks
+        if attrs.rename_all.is_some() {
+            let error = darling::Error::custom(
+                "attribute <rename_all> doesn't make sense with <skip_name_checks> attribute",
+            );
+            errors.push(error);
+        }
         for field in fields {
             if field.rename.is_some() {
                 let err = darling::Error::custom(
@@ -176,7 +236,13 @@ impl Field {
     fn udt_field_name(&self) -> String {
         match self.rename.as_ref() {
             Some(rename) => rename.to_owned(),
-            None => self.ident.as_ref().unwrap().unraw().to_string(),
+            None => {
+                let name = self.ident.as_ref().unwrap().unraw().to_string();
+                match self.rename_all {
+                    Some(rename_all) => rename_all.apply_to_field(&name),
+                    None => name,
+                }
+            }
         }
     }
 
@@ -190,11 +256,39 @@ type StructDesc = super::StructDescForDeserialize<StructAttrs, Field>;
 
 impl StructDesc {
     /// Generates an expression which extracts the UDT fields or returns an error.
+    /// If the name or the keyspace of the UDT is specified, it is verified as well.
     fn generate_extract_fields_from_type(&self, typ_expr: syn::Expr) -> syn::Expr {
         let macro_internal = &self.struct_attrs().macro_internal_path();
+
+        let mut mismatches: Vec<syn::Expr> = Vec::new();
+        if let Some(udt_name) = &self.struct_attrs().udt_name {
+            mismatches.push(parse_quote!(<str as ::std::cmp::PartialEq>::ne(type_name, #udt_name)));
+        }
+        if let Some(keyspace) = &self.struct_attrs().keyspace {
+            mismatches.push(parse_quote!(<str as ::std::cmp::PartialEq>::ne(keyspace, #keyspace)));
+        }
+        let name_check: Option<syn::Stmt> = (!mismatches.is_empty()).then(|| {
+            parse_quote! {
+                if #(#mismatches)||* {
+                    return ::std::result::Result::Err(
+                        #macro_internal::mk_value_typck_err::<Self>(
+                            #typ_expr,
+                            #macro_internal::DeserUdtTypeCheckErrorKind::NameMismatch {
+                                keyspace: <_ as ::std::clone::Clone>::clone(keyspace).into_owned(),
+                                type_name: <_ as ::std::clone::Clone>::clone(type_name).into_owned(),
+                            },
+                        )
+                    );
+                }
+            }
+        });
+
         parse_quote!(
             match #typ_expr {
-                #macro_internal::ColumnType::UserDefinedType { field_types, .. } => field_types,
+                #macro_internal::ColumnType::UserDefinedType { field_types, type_name, keyspace } => {
+                    #name_check
+                    field_types
+                }
                 other => return ::std::result::Result::Err(
                     #macro_internal::mk_value_typck_err::<Self>(
                         &other,
@@ -226,9 +320,8 @@ impl TypeCheckAssumeOrderGenerator<'_> {
     // Generates name and type validation for given Rust struct's field.
     fn generate_field_validation(&self, rust_field_idx: usize, field: &Field) -> syn::Expr {
         let macro_internal = self.0.struct_attrs().macro_internal_path();
-        let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
         let rust_field_name = field.cql_name_literal();
-        let rust_field_typ = field.deserialize_target();
+        let type_check = self.0.field_type_check_fn(field);
         let default_when_missing = field.default_when_missing;
         let skip_name_checks = self.0.attrs.skip_name_checks;
 
@@ -293,7 +386,7 @@ impl TypeCheckAssumeOrderGenerator<'_> {
                     #name_verification
 
                     // Verify the type
-                    <#rust_field_typ as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::type_check(cql_field_typ)
+                    #type_check(cql_field_typ)
                         .map_err(|err| #macro_internal::mk_value_typck_err::<Self>(
                             typ,
                             #macro_internal::DeserUdtTypeCheckErrorKind::FieldTypeCheckFailed {
@@ -409,14 +502,13 @@ impl DeserializeAssumeOrderGenerator<'_> {
 
         let macro_internal = self.0.struct_attrs().macro_internal_path();
         let cql_name_literal = field.cql_name_literal();
-        let deserializer = field.deserialize_target();
-        let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
+        let deserialize_fn = self.0.field_deserialize_fn(field);
         let default_when_missing = field.default_when_missing;
         let default_when_null = field.default_when_null;
         let skip_name_checks = self.0.attrs.skip_name_checks;
 
         let deserialize: syn::Expr = parse_quote! {
-            <#deserializer as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::deserialize(cql_field_typ, value)
+            #deserialize_fn(cql_field_typ, value)
                 .map_err(|err| #macro_internal::mk_value_deser_err::<Self>(
                     typ,
                     #macro_internal::UdtDeserializationErrorKind::FieldDeserializationFailed {
@@ -589,9 +681,8 @@ impl TypeCheckUnorderedGenerator<'_> {
     fn generate_type_check(&self, field: &Field) -> Option<syn::Block> {
         (!field.skip).then(|| {
             let macro_internal = self.0.struct_attrs().macro_internal_path();
-            let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
             let visited_flag = Self::visited_flag_variable(field);
-            let typ = field.deserialize_target();
+            let type_check = self.0.field_type_check_fn(field);
             let cql_name_literal = field.cql_name_literal();
             let decrement_if_required: Option<syn::Stmt> = field
                 .is_required()
@@ -600,7 +691,7 @@ impl TypeCheckUnorderedGenerator<'_> {
             parse_quote! {
                 {
                     if !#visited_flag {
-                        <#typ as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::type_check(cql_field_typ)
+                        #type_check(cql_field_typ)
                             .map_err(|err| #macro_internal::mk_value_typck_err::<Self>(
                                 typ,
                                 #macro_internal::DeserUdtTypeCheckErrorKind::FieldTypeCheckFailed {
@@ -767,13 +858,12 @@ impl DeserializeUnorderedGenerator<'_> {
     fn generate_deserialization(&self, field: &Field) -> Option<syn::Expr> {
         (!field.skip).then(|| {
             let macro_internal = self.0.struct_attrs().macro_internal_path();
-            let (frame_lifetime, metadata_lifetime) = self.0.constraint_lifetimes();
             let deserialize_field = Self::deserialize_field_variable(field);
             let cql_name_literal = field.cql_name_literal();
-            let deserializer = field.deserialize_target();
+            let deserialize = self.0.field_deserialize_fn(field);
 
             let do_deserialize: syn::Expr = parse_quote! {
-                <#deserializer as #macro_internal::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::deserialize(cql_field_typ, value)
+                #deserialize(cql_field_typ, value)
                 .map_err(|err| #macro_internal::mk_value_deser_err::<Self>(
                     typ,
                     #macro_internal::UdtDeserializationErrorKind::FieldDeserializationFailed {
@@ -0,0 +1,54 @@
+use proc_macro::TokenStream;
+use quote::ToTokens;
+use syn::punctuated::Punctuated;
+use syn::{parse_quote, Token};
+
+// derive(ScyllaEntity): both serialization and deserialization of a row,
+// or of a UDT if the struct is marked with `#[scylla(udt)]`.
+//
+// The input is passed on to the respective derives, so that they are generated
+// from the same attributes. The attributes which are specific to deserialization
+// are ignored by the serialization derives.
+pub(crate) fn derive_scylla_entity(
+    tokens_input: TokenStream,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
+    let mut input: syn::DeriveInput = syn::parse(tokens_input)?;
+    let is_udt = take_udt_flag(&mut input.attrs)?;
+    let tokens_input = || -> TokenStream { input.to_token_stream().into() };
+
+    if is_udt {
+        let serialize_impl = crate::serialize::value::derive_serialize_value(tokens_input())?;
+        let deserialize_impl = crate::deserialize::value::deserialize_value_derive(tokens_input())?;
+        Ok(quote::quote! {
+            #serialize_impl
+            #deserialize_impl
+        })
+    } else {
+        let serialize_impl = crate::serialize::row::derive_serialize_row(tokens_input())?;
+        let deserialize_impl = crate::deserialize::row::deserialize_row_derive(tokens_input())?;
+        Ok(quote::quote! {
+            #serialize_impl
+            #deserialize_impl
+        })
+    }
+}
+
+// Removes the `udt` flag, which isn't understood by the other derives,
+// from the `#[scylla(...)]` attributes and returns whether it was present.
+fn take_udt_flag(attrs: &mut [syn::Attribute]) -> Result<bool, syn::Error> {
+    let mut is_udt = false;
+    for attr in attrs
+        .iter_mut()
+        .filter(|attr| attr.path().is_ident("scylla"))
+    {
+        let metas = attr.parse_args_with(Punctuated::<syn::Meta, Token![,]>::parse_terminated)?;
+        let is_udt_flag =
+            |meta: &syn::Meta| matches!(meta, syn::Meta::Path(path) if path.is_ident("udt"));
+        if metas.iter().any(is_udt_flag) {
+            is_udt = true;
+            let other_metas = metas.into_iter().filter(|meta| !is_udt_flag(meta));
+            *attr = parse_quote!(#[scylla(#(#other_metas),*)]);
+        }
+    }
+    Ok(is_udt)
+}
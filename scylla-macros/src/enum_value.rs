@@ -0,0 +1,397 @@
+//! Support for deriving `SerializeValue` and `DeserializeValue` on fieldless enums.
+//!
+//! Such an enum is mapped either to a text column (each variant is represented
+//! by its name) or to an integer column (each variant is represented by its
+//! discriminant).
+
+use std::collections::HashMap;
+
+use darling::{FromAttributes, FromMeta};
+use syn::{ext::IdentExt, parse_quote, DataEnum, Fields};
+
+/// How the enum is represented in the database.
+#[derive(Copy, Clone, PartialEq, Eq, Default)]
+enum EnumRepr {
+    #[default]
+    Text,
+    Int,
+}
+
+impl FromMeta for EnumRepr {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        match value {
+            "text" => Ok(Self::Text),
+            "int" => Ok(Self::Int),
+            _ => Err(darling::Error::unknown_value(value)),
+        }
+    }
+}
+
+#[derive(FromAttributes)]
+#[darling(attributes(scylla))]
+struct EnumAttrs {
+    #[darling(rename = "crate")]
+    crate_path: Option<syn::Path>,
+
+    #[darling(default)]
+    repr: EnumRepr,
+}
+
+#[derive(FromAttributes)]
+#[darling(attributes(scylla))]
+struct VariantAttrs {
+    // If set, then the variant is represented by this string
+    // instead of the Rust variant name.
+    rename: Option<String>,
+}
+
+struct Variant {
+    ident: syn::Ident,
+    attrs: VariantAttrs,
+}
+
+impl Variant {
+    // The string representing this variant in the `text` representation.
+    fn cql_name(&self) -> String {
+        match &self.attrs.rename {
+            Some(name) => name.clone(),
+            None => self.ident.unraw().to_string(),
+        }
+    }
+}
+
+struct EnumDesc {
+    name: syn::Ident,
+    generics: syn::Generics,
+    attrs: EnumAttrs,
+    variants: Vec<Variant>,
+}
+
+impl EnumDesc {
+    fn new(
+        input: &syn::DeriveInput,
+        data: &DataEnum,
+        trait_name: &str,
+    ) -> Result<Self, syn::Error> {
+        let attrs = EnumAttrs::from_attributes(&input.attrs)?;
+
+        let mut errors = darling::Error::accumulator();
+        if data.variants.is_empty() {
+            errors.push(
+                darling::Error::custom(format!(
+                    "derive({}) requires the enum to have at least one variant",
+                    trait_name
+                ))
+                .with_span(&input.ident),
+            );
+        }
+
+        let mut variants = Vec::with_capacity(data.variants.len());
+        for variant in data.variants.iter() {
+            if !matches!(variant.fields, Fields::Unit) {
+                errors.push(
+                    darling::Error::custom(format!(
+                        "derive({}) works only for enums whose variants have no fields",
+                        trait_name
+                    ))
+                    .with_span(&variant.ident),
+                );
+                continue;
+            }
+            if let Some(variant_attrs) =
+                errors.handle(VariantAttrs::from_attributes(&variant.attrs))
+            {
+                variants.push(Variant {
+                    ident: variant.ident.clone(),
+                    attrs: variant_attrs,
+                });
+            }
+        }
+
+        match attrs.repr {
+            EnumRepr::Text => {
+                // Detect name collisions caused by `rename`.
+                let mut used_names = HashMap::<String, &Variant>::new();
+                for variant in variants.iter() {
+                    let cql_name = variant.cql_name();
+                    if let Some(other_variant) = used_names.get(&cql_name) {
+                        let msg = format!(
+                            "the name `{cql_name}` used by this variant is already used by variant `{}`",
+                            other_variant.ident
+                        );
+                        errors.push(darling::Error::custom(msg).with_span(&variant.ident));
+                    } else {
+                        used_names.insert(cql_name, variant);
+                    }
+                }
+            }
+            EnumRepr::Int => {
+                // In the integer representation, variants are identified by
+                // their discriminants, so names are irrelevant.
+                for variant in variants.iter() {
+                    if variant.attrs.rename.is_some() {
+                        errors.push(
+                            darling::Error::custom(
+                                "the `rename` attribute doesn't make sense with `repr = \"int\"`",
+                            )
+                            .with_span(&variant.ident),
+                        );
+                    }
+                }
+            }
+        }
+        errors.finish()?;
+
+        Ok(Self {
+            name: input.ident.clone(),
+            generics: input.generics.clone(),
+            attrs,
+            variants,
+        })
+    }
+
+    fn crate_path(&self) -> syn::Path {
+        self.attrs
+            .crate_path
+            .as_ref()
+            .map(|p| parse_quote!(#p::_macro_internal))
+            .unwrap_or_else(|| parse_quote!(::scylla::_macro_internal))
+    }
+
+    // The CQL types that the enum can be represented as.
+    fn accepted_types(&self) -> syn::Expr {
+        let crate_path = self.crate_path();
+        match self.attrs.repr {
+            EnumRepr::Text => parse_quote! {
+                &[#crate_path::ColumnType::Ascii, #crate_path::ColumnType::Text]
+            },
+            EnumRepr::Int => parse_quote! {
+                &[
+                    #crate_path::ColumnType::TinyInt,
+                    #crate_path::ColumnType::SmallInt,
+                    #crate_path::ColumnType::Int,
+                    #crate_path::ColumnType::BigInt,
+                ]
+            },
+        }
+    }
+
+    fn generate_serialize_value(&self) -> syn::ItemImpl {
+        let crate_path = self.crate_path();
+        let enum_name = &self.name;
+        let (impl_generics, ty_generics, where_clause) = self.generics.split_for_impl();
+        let accepted_types = self.accepted_types();
+        let idents = self.variants.iter().map(|v| &v.ident);
+
+        let write_value: syn::Expr = match self.attrs.repr {
+            EnumRepr::Text => {
+                let names = self.variants.iter().map(|v| v.cql_name());
+                parse_quote! {{
+                    let name: &'static str = match self {
+                        #(Self::#idents => #names,)*
+                    };
+                    match typ {
+                        #crate_path::ColumnType::Ascii | #crate_path::ColumnType::Text => {}
+                        _ => return ::std::result::Result::Err(mk_typck_err()),
+                    }
+                    writer.set_value(name.as_bytes())
+                }}
+            }
+            EnumRepr::Int => {
+                let values = self.variants.iter().map(|v| {
+                    let ident = &v.ident;
+                    let value: syn::Expr = parse_quote!(Self::#ident as i64);
+                    value
+                });
+                parse_quote! {{
+                    let discriminant: i64 = match self {
+                        #(Self::#idents => #values,)*
+                    };
+                    let mk_overflow_err = || mk_ser_err(
+                        #crate_path::BuiltinTypeSerializationErrorKind::ValueOverflow,
+                    );
+                    match typ {
+                        #crate_path::ColumnType::TinyInt => {
+                            let v = <i8 as ::std::convert::TryFrom<i64>>::try_from(discriminant)
+                                .map_err(|_| mk_overflow_err())?;
+                            writer.set_value(&v.to_be_bytes())
+                        }
+                        #crate_path::ColumnType::SmallInt => {
+                            let v = <i16 as ::std::convert::TryFrom<i64>>::try_from(discriminant)
+                                .map_err(|_| mk_overflow_err())?;
+                            writer.set_value(&v.to_be_bytes())
+                        }
+                        #crate_path::ColumnType::Int => {
+                            let v = <i32 as ::std::convert::TryFrom<i64>>::try_from(discriminant)
+                                .map_err(|_| mk_overflow_err())?;
+                            writer.set_value(&v.to_be_bytes())
+                        }
+                        #crate_path::ColumnType::BigInt => writer.set_value(&discriminant.to_be_bytes()),
+                        _ => return ::std::result::Result::Err(mk_typck_err()),
+                    }
+                }}
+            }
+        };
+
+        parse_quote! {
+            #[automatically_derived]
+            impl #impl_generics #crate_path::SerializeValue for #enum_name #ty_generics #where_clause {
+                fn serialize<'b>(
+                    &self,
+                    typ: &#crate_path::ColumnType,
+                    writer: #crate_path::CellWriter<'b>,
+                ) -> ::std::result::Result<#crate_path::WrittenCellProof<'b>, #crate_path::SerializationError> {
+                    let mk_typck_err = || -> #crate_path::SerializationError {
+                        #crate_path::SerializationError::new(
+                            #crate_path::BuiltinTypeTypeCheckError {
+                                rust_name: ::std::any::type_name::<Self>(),
+                                got: <_ as ::std::clone::Clone>::clone(typ).into_owned(),
+                                kind: #crate_path::BuiltinTypeTypeCheckErrorKind::MismatchedType {
+                                    expected: #accepted_types,
+                                },
+                            }
+                        )
+                    };
+                    let mk_ser_err = |kind: #crate_path::BuiltinTypeSerializationErrorKind| -> #crate_path::SerializationError {
+                        #crate_path::SerializationError::new(
+                            #crate_path::BuiltinTypeSerializationError {
+                                rust_name: ::std::any::type_name::<Self>(),
+                                got: <_ as ::std::clone::Clone>::clone(typ).into_owned(),
+                                kind,
+                            }
+                        )
+                    };
+                    let result = #write_value;
+                    result.map_err(|_| mk_ser_err(
+                        #crate_path::BuiltinTypeSerializationErrorKind::SizeOverflow,
+                    ))
+                }
+            }
+        }
+    }
+
+    fn generate_deserialize_value(&self) -> syn::ItemImpl {
+        let crate_path = self.crate_path();
+        let enum_name = &self.name;
+        let (frame_lifetime, metadata_lifetime) =
+            crate::deserialize::generate_pair_of_unique_lifetimes_for_impl(&self.generics);
+        let (_, ty_generics, where_clause) = self.generics.split_for_impl();
+        let impl_generics = &self.generics.params;
+        let accepted_types = self.accepted_types();
+        let idents = self.variants.iter().map(|v| &v.ident);
+
+        let type_check: syn::Expr = match self.attrs.repr {
+            EnumRepr::Text => parse_quote! {
+                <&#frame_lifetime str as #crate_path::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::type_check(typ)
+                    .map_err(#crate_path::value_typck_error_replace_rust_name::<Self>)
+            },
+            EnumRepr::Int => parse_quote! {
+                match typ {
+                    #crate_path::ColumnType::TinyInt
+                    | #crate_path::ColumnType::SmallInt
+                    | #crate_path::ColumnType::Int
+                    | #crate_path::ColumnType::BigInt => ::std::result::Result::Ok(()),
+                    _ => ::std::result::Result::Err(#crate_path::mk_value_typck_err::<Self>(
+                        typ,
+                        #crate_path::DeserBuiltinTypeTypeCheckErrorKind::MismatchedType {
+                            expected: #accepted_types,
+                        },
+                    )),
+                }
+            },
+        };
+
+        let deserialize: syn::Expr = match self.attrs.repr {
+            EnumRepr::Text => {
+                let names = self.variants.iter().map(|v| v.cql_name());
+                parse_quote! {{
+                    let name = <&#frame_lifetime str as #crate_path::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::deserialize(typ, v)
+                        .map_err(#crate_path::value_deser_error_replace_rust_name::<Self>)?;
+                    match name {
+                        #(#names => ::std::result::Result::Ok(Self::#idents),)*
+                        _ => ::std::result::Result::Err(#crate_path::mk_value_deser_err::<Self>(
+                            typ,
+                            #crate_path::BuiltinTypeDeserializationErrorKind::UnknownVariant {
+                                value: <str as ::std::borrow::ToOwned>::to_owned(name),
+                            },
+                        )),
+                    }
+                }}
+            }
+            EnumRepr::Int => {
+                let deserialize_as = |int_type: syn::Type| -> syn::Expr {
+                    parse_quote! {
+                        <#int_type as #crate_path::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::deserialize(typ, v)
+                            .map(i64::from)
+                            .map_err(#crate_path::value_deser_error_replace_rust_name::<Self>)?
+                    }
+                };
+                let from_tinyint = deserialize_as(parse_quote!(i8));
+                let from_smallint = deserialize_as(parse_quote!(i16));
+                let from_int = deserialize_as(parse_quote!(i32));
+                let from_bigint = deserialize_as(parse_quote!(i64));
+                let values = self.variants.iter().map(|v| {
+                    let ident = &v.ident;
+                    let value: syn::Expr = parse_quote!(Self::#ident as i64);
+                    value
+                });
+                parse_quote! {{
+                    let discriminant: i64 = match typ {
+                        #crate_path::ColumnType::TinyInt => #from_tinyint,
+                        #crate_path::ColumnType::SmallInt => #from_smallint,
+                        #crate_path::ColumnType::Int => #from_int,
+                        _ => #from_bigint,
+                    };
+                    #(
+                        if discriminant == #values {
+                            return ::std::result::Result::Ok(Self::#idents);
+                        }
+                    )*
+                    ::std::result::Result::Err(#crate_path::mk_value_deser_err::<Self>(
+                        typ,
+                        #crate_path::BuiltinTypeDeserializationErrorKind::UnknownVariant {
+                            value: ::std::string::ToString::to_string(&discriminant),
+                        },
+                    ))
+                }}
+            }
+        };
+
+        parse_quote! {
+            #[automatically_derived]
+            impl<#frame_lifetime, #metadata_lifetime, #impl_generics>
+                #crate_path::DeserializeValue<#frame_lifetime, #metadata_lifetime> for #enum_name #ty_generics
+            #where_clause
+            {
+                fn type_check(
+                    typ: &#crate_path::ColumnType,
+                ) -> ::std::result::Result<(), #crate_path::TypeCheckError> {
+                    #type_check
+                }
+
+                fn deserialize(
+                    typ: &#metadata_lifetime #crate_path::ColumnType<#metadata_lifetime>,
+                    v: ::std::option::Option<#crate_path::FrameSlice<#frame_lifetime>>,
+                ) -> ::std::result::Result<Self, #crate_path::DeserializationError> {
+                    #deserialize
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn derive_serialize_value(
+    input: &syn::DeriveInput,
+    data: &DataEnum,
+) -> Result<syn::ItemImpl, syn::Error> {
+    let desc = EnumDesc::new(input, data, "SerializeValue")?;
+    Ok(desc.generate_serialize_value())
+}
+
+pub(crate) fn derive_deserialize_value(
+    input: &syn::DeriveInput,
+    data: &DataEnum,
+) -> Result<syn::ItemImpl, syn::Error> {
+    let desc = EnumDesc::new(input, data, "DeserializeValue")?;
+    Ok(desc.generate_deserialize_value())
+}
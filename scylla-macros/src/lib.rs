@@ -1,10 +1,14 @@
 use darling::{FromMeta, ToTokens};
 use proc_macro::TokenStream;
 
+mod checked_query;
+mod entity;
+mod enum_value;
 mod from_row;
 mod from_user_type;
 mod into_user_type;
 mod parser;
+mod transparent;
 mod value_list;
 
 // Flavor of serialization/deserialization macros ({De,S}erialize{Value,Row}).
@@ -25,6 +29,71 @@ impl FromMeta for Flavor {
     }
 }
 
+// Case convention of CQL names, applied to the names of Rust fields
+// by the `rename_all` attribute of ({De,S}erialize{Value,Row}).
+#[derive(Copy, Clone, PartialEq, Eq)]
+enum RenameAll {
+    Lower,
+    Upper,
+    Pascal,
+    Camel,
+    Snake,
+    ScreamingSnake,
+    Kebab,
+    ScreamingKebab,
+}
+
+impl FromMeta for RenameAll {
+    fn from_string(value: &str) -> darling::Result<Self> {
+        match value {
+            "lowercase" => Ok(Self::Lower),
+            "UPPERCASE" => Ok(Self::Upper),
+            "PascalCase" => Ok(Self::Pascal),
+            "camelCase" => Ok(Self::Camel),
+            "snake_case" => Ok(Self::Snake),
+            "SCREAMING_SNAKE_CASE" => Ok(Self::ScreamingSnake),
+            "kebab-case" => Ok(Self::Kebab),
+            "SCREAMING-KEBAB-CASE" => Ok(Self::ScreamingKebab),
+            _ => Err(darling::Error::unknown_value(value)),
+        }
+    }
+}
+
+impl RenameAll {
+    // Converts the name of a Rust field, which is assumed to be in snake_case.
+    fn apply_to_field(self, field: &str) -> String {
+        match self {
+            Self::Lower | Self::Snake => field.to_owned(),
+            Self::Upper | Self::ScreamingSnake => field.to_ascii_uppercase(),
+            Self::Pascal => {
+                let mut pascal = String::with_capacity(field.len());
+                let mut capitalize = true;
+                for ch in field.chars() {
+                    if ch == '_' {
+                        capitalize = true;
+                    } else if capitalize {
+                        pascal.push(ch.to_ascii_uppercase());
+                        capitalize = false;
+                    } else {
+                        pascal.push(ch);
+                    }
+                }
+                pascal
+            }
+            Self::Camel => {
+                let pascal = Self::Pascal.apply_to_field(field);
+                let mut chars = pascal.chars();
+                match chars.next() {
+                    Some(first) => first.to_ascii_lowercase().to_string() + chars.as_str(),
+                    None => pascal,
+                }
+            }
+            Self::Kebab => field.replace('_', "-"),
+            Self::ScreamingKebab => Self::ScreamingSnake.apply_to_field(field).replace('_', "-"),
+        }
+    }
+}
+
 mod serialize;
 
 /// Documentation for this macro can only be found
@@ -102,3 +171,25 @@ pub fn deserialize_value_derive(tokens_input: TokenStream) -> TokenStream {
         Err(err) => err.into_compile_error().into(),
     }
 }
+
+/// Documentation for this macro can only be found
+/// in `scylla` crate - not in scylla-macros nor in scylla-cql.
+/// This is because of rustdocs limitations that are hard to explain here.
+#[proc_macro_derive(ScyllaEntity, attributes(scylla))]
+pub fn scylla_entity_derive(tokens_input: TokenStream) -> TokenStream {
+    match entity::derive_scylla_entity(tokens_input) {
+        Ok(tokens) => tokens.into_token_stream().into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
+
+/// Documentation for this macro can only be found
+/// in `scylla` crate - not in scylla-macros nor in scylla-cql.
+/// This is because of rustdocs limitations that are hard to explain here.
+#[proc_macro]
+pub fn checked_query(tokens_input: TokenStream) -> TokenStream {
+    match checked_query::checked_query(tokens_input) {
+        Ok(tokens) => tokens.into_token_stream().into(),
+        Err(err) => err.into_compile_error().into(),
+    }
+}
@@ -2,10 +2,9 @@ use std::collections::HashMap;
 
 use darling::FromAttributes;
 use proc_macro::TokenStream;
-use proc_macro2::Span;
 use syn::parse_quote;
 
-use crate::Flavor;
+use crate::{Flavor, RenameAll};
 
 #[derive(FromAttributes)]
 #[darling(attributes(scylla))]
@@ -22,6 +21,17 @@ struct Attributes {
     // This annotation only works if `enforce_order` flavor is specified.
     #[darling(default)]
     skip_name_checks: bool,
+
+    // If set, then the names of the columns are the names of the fields
+    // converted to this case convention. Fields with `rename` are not affected.
+    #[darling(default)]
+    rename_all: Option<RenameAll>,
+
+    // If true, then the columns are matched to the fields solely by their
+    // order, as with `enforce_order` and `skip_name_checks`. Required for
+    // tuple structs, whose fields have no names.
+    #[darling(default)]
+    positional: bool,
 }
 
 impl Attributes {
@@ -34,16 +44,39 @@ impl Attributes {
 }
 
 struct Field {
-    ident: syn::Ident,
+    // The name of the field, or its index in tuple structs.
+    member: syn::Member,
     ty: syn::Type,
     attrs: FieldAttributes,
+    // The `rename_all` attribute of the struct.
+    rename_all: Option<RenameAll>,
 }
 
 impl Field {
     fn column_name(&self) -> String {
         match &self.attrs.rename {
             Some(name) => name.clone(),
-            None => self.ident.to_string(),
+            None => match &self.member {
+                syn::Member::Named(ident) => match self.rename_all {
+                    Some(rename_all) => rename_all.apply_to_field(&ident.to_string()),
+                    None => ident.to_string(),
+                },
+                syn::Member::Unnamed(index) => index.index.to_string(),
+            },
+        }
+    }
+
+    // The function serializing the field: the `serialize` function of the module
+    // given in the `with` attribute, the helper serializing `None` as unset
+    // for `unset_if_none` fields, or the `SerializeValue` implementation.
+    fn serialize_fn(&self, crate_path: &syn::Path) -> syn::ExprPath {
+        match &self.attrs.with {
+            Some(with) => parse_quote!(#with::serialize),
+            None if self.attrs.unset_if_none => parse_quote!(#crate_path::serialize_unset_if_none),
+            None => {
+                let ty = &self.ty;
+                parse_quote!(<#ty as #crate_path::SerializeValue>::serialize)
+            }
         }
     }
 }
@@ -59,6 +92,21 @@ struct FieldAttributes {
     // All other attributes are ignored.
     #[darling(default)]
     skip: bool,
+
+    // If true, then the field is a struct deriving SerializeRow itself,
+    // and its fields are serialized to the columns of this row,
+    // as if they were fields of this struct.
+    #[darling(default)]
+    flatten: bool,
+
+    // If set, then the field is serialized with the `serialize` function
+    // of this module instead of its SerializeValue implementation.
+    with: Option<syn::Path>,
+
+    // If true, then the field must be an `Option`, and `None` is serialized
+    // as an unset value instead of null.
+    #[darling(default)]
+    unset_if_none: bool,
 }
 
 struct Context {
@@ -66,33 +114,54 @@ struct Context {
     fields: Vec<Field>,
 }
 
-pub(crate) fn derive_serialize_row(tokens_input: TokenStream) -> Result<syn::ItemImpl, syn::Error> {
+pub(crate) fn derive_serialize_row(
+    tokens_input: TokenStream,
+) -> Result<proc_macro2::TokenStream, syn::Error> {
     let input: syn::DeriveInput = syn::parse(tokens_input)?;
     let struct_name = input.ident.clone();
-    let named_fields = crate::parser::parse_named_fields(&input, "SerializeRow")?;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
     let attributes = Attributes::from_attributes(&input.attrs)?;
+    // Only positional structs can have unnamed fields.
+    let struct_fields = if attributes.positional {
+        match crate::parser::parse_struct_fields(&input, "SerializeRow")? {
+            crate::parser::StructFields::Named(fields) => &fields.named,
+            crate::parser::StructFields::Unnamed(fields) => &fields.unnamed,
+        }
+    } else {
+        &crate::parser::parse_named_fields(&input, "SerializeRow")?.named
+    };
 
     let crate_path = attributes.crate_path();
     let implemented_trait: syn::Path = parse_quote!(#crate_path::SerializeRow);
 
-    let fields = named_fields
-        .named
+    let fields = struct_fields
         .iter()
-        .map(|f| {
+        .enumerate()
+        .map(|(idx, f)| {
             FieldAttributes::from_attributes(&f.attrs).map(|attrs| Field {
-                ident: f.ident.clone().unwrap(),
+                member: match &f.ident {
+                    Some(ident) => syn::Member::Named(ident.clone()),
+                    None => syn::Member::Unnamed(idx.into()),
+                },
                 ty: f.ty.clone(),
                 attrs,
+                rename_all: attributes.rename_all,
             })
         })
         // Filter the fields now instead of at the places that use them later
         // as it's less error prone - we just filter in one place instead of N places.
         .filter(|f| f.as_ref().map(|f| !f.attrs.skip).unwrap_or(true))
         .collect::<Result<_, _>>()?;
-    let ctx = Context { attributes, fields };
+    let mut ctx = Context { attributes, fields };
     ctx.validate(&input.ident)?;
 
+    // Positional structs are serialized like `enforce_order` structs
+    // with `skip_name_checks`.
+    if ctx.attributes.positional {
+        ctx.attributes.flavor = Flavor::EnforceOrder;
+        ctx.attributes.skip_name_checks = true;
+    }
+
     let gen: Box<dyn Generator> = match ctx.attributes.flavor {
         Flavor::MatchByName => Box::new(ColumnSortingGenerator { ctx: &ctx }),
         Flavor::EnforceOrder => Box::new(ColumnOrderedGenerator { ctx: &ctx }),
@@ -101,20 +170,70 @@ pub(crate) fn derive_serialize_row(tokens_input: TokenStream) -> Result<syn::Ite
     let serialize_item = gen.generate_serialize();
     let is_empty_item = gen.generate_is_empty();
 
-    let res = parse_quote! {
+    let mut where_clause = where_clause.cloned().unwrap_or_else(|| parse_quote!(where));
+    where_clause
+        .predicates
+        .extend(ctx.generate_flattened_fields_constraints());
+
+    // In the `match_by_name` flavor, the struct can be flattened into other structs,
+    // so the column-by-column serialization is exposed as a separate trait.
+    let by_name_impl = (ctx.attributes.flavor == Flavor::MatchByName).then(|| {
+        let by_name_trait: syn::Path = parse_quote!(#crate_path::SerializeRowByName);
+        let by_name_items = ColumnSortingGenerator { ctx: &ctx }.generate_by_name_items();
+        quote::quote! {
+            #[automatically_derived]
+            impl #impl_generics #by_name_trait for #struct_name #ty_generics #where_clause {
+                #(#by_name_items)*
+            }
+        }
+    });
+
+    Ok(quote::quote! {
         #[automatically_derived]
         impl #impl_generics #implemented_trait for #struct_name #ty_generics #where_clause {
             #serialize_item
             #is_empty_item
         }
-    };
-    Ok(res)
+        #by_name_impl
+    })
 }
 
 impl Context {
     fn validate(&self, struct_ident: &syn::Ident) -> Result<(), syn::Error> {
         let mut errors = darling::Error::accumulator();
 
+        for field in self.fields.iter() {
+            if field.attrs.unset_if_none && field.attrs.with.is_some() {
+                let err = darling::Error::custom(
+                    "the `unset_if_none` annotations conflict with `with` annotations",
+                )
+                .with_span(&field.member);
+                errors.push(err);
+            }
+        }
+
+        if self.attributes.positional {
+            // Names are not used at all by positional structs
+            if self.attributes.rename_all.is_some() {
+                let err = darling::Error::custom(
+                    "the `rename_all` attribute doesn't make sense with `positional` attribute",
+                )
+                .with_span(struct_ident);
+                errors.push(err);
+            }
+            for field in self.fields.iter() {
+                if field.attrs.rename.is_some() || field.attrs.flatten {
+                    let err = darling::Error::custom(
+                        "the `rename` and `flatten` annotations don't make sense with `positional` attribute",
+                    )
+                    .with_span(&field.member);
+                    errors.push(err);
+                }
+            }
+            errors.finish()?;
+            return Ok(());
+        }
+
         if self.attributes.skip_name_checks {
             // Skipping name checks is only available in enforce_order mode
             if self.attributes.flavor != Flavor::EnforceOrder {
@@ -125,26 +244,66 @@ impl Context {
                 errors.push(err);
             }
 
-            // `rename` annotations don't make sense with skipped name checks
+            // `rename_all` and `rename` annotations don't make sense with skipped name checks
+            if self.attributes.rename_all.is_some() {
+                let err = darling::Error::custom(
+                    "the `rename_all` attribute doesn't make sense with `skip_name_checks` attribute",
+                )
+                .with_span(struct_ident);
+                errors.push(err);
+            }
             for field in self.fields.iter() {
                 if field.attrs.rename.is_some() {
                     let err = darling::Error::custom(
                         "the `rename` annotations don't make sense with `skip_name_checks` attribute",
                     )
-                    .with_span(&field.ident);
+                    .with_span(&field.member);
                     errors.push(err);
                 }
             }
         }
 
-        // Check for name collisions
+        for field in self.fields.iter().filter(|f| f.attrs.flatten) {
+            // Flattening relies on matching columns to fields by name
+            if self.attributes.flavor != Flavor::MatchByName {
+                let err = darling::Error::custom(
+                    "the `flatten` annotations are only allowed with the `match_by_name` flavor",
+                )
+                .with_span(&field.member);
+                errors.push(err);
+            }
+            if field.attrs.rename.is_some() {
+                let err = darling::Error::custom(
+                    "the `flatten` annotations conflict with `rename` annotations",
+                )
+                .with_span(&field.member);
+                errors.push(err);
+            }
+            if field.attrs.with.is_some() {
+                let err = darling::Error::custom(
+                    "the `flatten` annotations conflict with `with` annotations",
+                )
+                .with_span(&field.member);
+                errors.push(err);
+            }
+            if field.attrs.unset_if_none {
+                let err = darling::Error::custom(
+                    "the `flatten` annotations conflict with `unset_if_none` annotations",
+                )
+                .with_span(&field.member);
+                errors.push(err);
+            }
+        }
+
+        // Check for name collisions. Collisions with the columns of flattened fields
+        // can only be detected in the runtime.
         let mut used_names = HashMap::<String, &Field>::new();
-        for field in self.fields.iter() {
+        for field in self.fields.iter().filter(|f| !f.attrs.flatten) {
             let column_name = field.column_name();
             if let Some(other_field) = used_names.get(&column_name) {
-                let other_field_ident = &other_field.ident;
-                let msg = format!("the column / bind marker name `{column_name}` used by this struct field is already used by field `{other_field_ident}`");
-                let err = darling::Error::custom(msg).with_span(&field.ident);
+                let other_field_ident = &other_field.member;
+                let msg = format!("the column / bind marker name `{column_name}` used by this struct field is already used by field `{}`", quote::quote!(#other_field_ident));
+                let err = darling::Error::custom(msg).with_span(&field.member);
                 errors.push(err);
             } else {
                 used_names.insert(column_name, field);
@@ -155,6 +314,19 @@ impl Context {
         Ok(())
     }
 
+    // Requires the types of flattened fields to be serializable by name.
+    fn generate_flattened_fields_constraints(&self) -> Vec<syn::WherePredicate> {
+        let crate_path = self.attributes.crate_path();
+        self.fields
+            .iter()
+            .filter(|f| f.attrs.flatten)
+            .map(|f| {
+                let typ = &f.ty;
+                parse_quote!(#typ: #crate_path::SerializeRow + #crate_path::SerializeRowByName)
+            })
+            .collect()
+    }
+
     fn generate_mk_typck_err(&self) -> syn::Stmt {
         let crate_path = self.attributes.crate_path();
         parse_quote! {
@@ -195,99 +367,132 @@ struct ColumnSortingGenerator<'a> {
     ctx: &'a Context,
 }
 
-impl Generator for ColumnSortingGenerator<'_> {
-    fn generate_serialize(&self) -> syn::TraitItemFn {
-        // Need to:
-        // - Check that all required columns are there and no more
-        // - Check that the column types match
-        let mut statements: Vec<syn::Stmt> = Vec::new();
-
-        let crate_path = self.ctx.attributes.crate_path();
-
-        let rust_field_idents = self
-            .ctx
+impl ColumnSortingGenerator<'_> {
+    fn regular_fields(&self) -> impl Iterator<Item = (syn::Index, &Field)> {
+        self.ctx
             .fields
             .iter()
-            .map(|f| f.ident.clone())
-            .collect::<Vec<_>>();
-        let rust_field_names = self
-            .ctx
+            .enumerate()
+            .filter(|(_, f)| !f.attrs.flatten)
+            .map(|(idx, f)| (syn::Index::from(idx), f))
+    }
+
+    fn flattened_fields(&self) -> impl Iterator<Item = (syn::Index, &Field)> {
+        self.ctx
             .fields
             .iter()
-            .map(|f| f.column_name())
-            .collect::<Vec<_>>();
-        let udt_field_names = rust_field_names.clone(); // For now, it's the same
-        let field_types = self.ctx.fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+            .enumerate()
+            .filter(|(_, f)| f.attrs.flatten)
+            .map(|(idx, f)| (syn::Index::from(idx), f))
+    }
 
-        // Declare a helper lambda for creating errors
-        statements.push(self.ctx.generate_mk_typck_err());
-        statements.push(self.ctx.generate_mk_ser_err());
+    // Generates the items of the SerializeRowByName impl. Its state is a tuple
+    // with an element per field: a "visited" flag for regular fields,
+    // and the state of the field's type for flattened ones. Columns whose names
+    // don't match any regular field are passed to the flattened fields, in order.
+    fn generate_by_name_items(&self) -> Vec<syn::ImplItem> {
+        let crate_path = self.ctx.attributes.crate_path();
 
-        // Generate a "visited" flag for each field
-        let visited_flag_names = rust_field_idents
-            .iter()
-            .map(|s| syn::Ident::new(&format!("visited_flag_{}", s), Span::call_site()))
-            .collect::<Vec<_>>();
-        statements.extend::<Vec<_>>(parse_quote! {
-            #(let mut #visited_flag_names = false;)*
+        let state_types = self.ctx.fields.iter().map(|f| -> syn::Type {
+            let typ = &f.ty;
+            if f.attrs.flatten {
+                parse_quote!(<#typ as #crate_path::SerializeRowByName>::State)
+            } else {
+                parse_quote!(::std::primitive::bool)
+            }
         });
-
-        // Generate a variable that counts down visited fields.
-        let field_count = self.ctx.fields.len();
-        statements.push(parse_quote! {
-            let mut remaining_count = #field_count;
+        let state_inits = self.ctx.fields.iter().map(|f| -> syn::Expr {
+            let typ = &f.ty;
+            if f.attrs.flatten {
+                parse_quote!(<#typ as #crate_path::SerializeRowByName>::new_state())
+            } else {
+                parse_quote!(false)
+            }
         });
 
-        // Generate a loop over the fields and a `match` block to match on
-        // the field name.
-        statements.push(parse_quote! {
-            for spec in ctx.columns() {
-                match spec.name() {
-                    #(
-                        #udt_field_names => {
-                            let sub_writer = #crate_path::RowWriter::make_cell_writer(writer);
-                            match <#field_types as #crate_path::SerializeValue>::serialize(&self.#rust_field_idents, spec.typ(), sub_writer) {
-                                ::std::result::Result::Ok(_proof) => {}
-                                ::std::result::Result::Err(err) => {
-                                    return ::std::result::Result::Err(mk_ser_err(
-                                        #crate_path::BuiltinRowSerializationErrorKind::ColumnSerializationFailed {
-                                            name: <_ as ::std::borrow::ToOwned>::to_owned(spec.name()),
-                                            err,
-                                        }
-                                    ));
-                                }
-                            }
-                            if !#visited_flag_names {
-                                #visited_flag_names = true;
-                                remaining_count -= 1;
-                            }
-                        }
-                    )*
-                    _ => return ::std::result::Result::Err(mk_typck_err(
-                        #crate_path::BuiltinRowTypeCheckErrorKind::NoColumnWithName {
-                            name: <_ as ::std::borrow::ToOwned>::to_owned(spec.name()),
-                        }
-                    )),
+        let (regular_idxs, regular_fields): (Vec<_>, Vec<_>) = self.regular_fields().unzip();
+        let column_names = regular_fields.iter().map(|f| f.column_name());
+        let regular_field_idents = regular_fields.iter().map(|f| &f.member);
+        let regular_field_serializers = regular_fields.iter().map(|f| f.serialize_fn(&crate_path));
+        let (flattened_idxs, flattened_fields): (Vec<_>, Vec<_>) = self.flattened_fields().unzip();
+        let flattened_field_idents = flattened_fields.iter().map(|f| &f.member);
+        let flattened_field_types = flattened_fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+
+        let missing_checks = self.ctx.fields.iter().enumerate().map(|(idx, f)| -> syn::Stmt {
+            let idx = syn::Index::from(idx);
+            if f.attrs.flatten {
+                let typ = &f.ty;
+                parse_quote! {
+                    if let ::std::option::Option::Some(name) = <#typ as #crate_path::SerializeRowByName>::missing_column(&state.#idx) {
+                        return ::std::option::Option::Some(name);
+                    }
+                }
+            } else {
+                let column_name = f.column_name();
+                parse_quote! {
+                    if !state.#idx {
+                        return ::std::option::Option::Some(#column_name);
+                    }
                 }
             }
         });
 
-        // Finally, check that all fields were consumed.
-        // If there are some missing fields, return an error
-        statements.push(parse_quote! {
-            if remaining_count > 0 {
-                #(
-                    if !#visited_flag_names {
-                        return ::std::result::Result::Err(mk_typck_err(
-                            #crate_path::BuiltinRowTypeCheckErrorKind::ValueMissingForColumn {
-                                name: <_ as ::std::string::ToString>::to_string(#rust_field_names),
+        vec![
+            parse_quote!(type State = (#(#state_types,)*);),
+            parse_quote! {
+                // The state is an empty tuple if there are no fields.
+                #[allow(clippy::unused_unit)]
+                fn new_state() -> Self::State {
+                    (#(#state_inits,)*)
+                }
+            },
+            parse_quote! {
+                #[allow(unused_variables)]
+                fn serialize_column<'b>(
+                    &self,
+                    state: &mut Self::State,
+                    spec: &#crate_path::ColumnSpec,
+                    writer: &mut #crate_path::RowWriter<'b>,
+                ) -> ::std::result::Result<::std::primitive::bool, #crate_path::SerializationError> {
+                    match spec.name() {
+                        #(
+                            #column_names => {
+                                let sub_writer = #crate_path::RowWriter::make_cell_writer(writer);
+                                #regular_field_serializers(&self.#regular_field_idents, spec.typ(), sub_writer)?;
+                                state.#regular_idxs = true;
+                                ::std::result::Result::Ok(true)
                             }
-                        ));
+                        )*
+                        _ => {
+                            #(
+                                if <#flattened_field_types as #crate_path::SerializeRowByName>::serialize_column(&self.#flattened_field_idents, &mut state.#flattened_idxs, spec, writer)? {
+                                    return ::std::result::Result::Ok(true);
+                                }
+                            )*
+                            ::std::result::Result::Ok(false)
+                        }
                     }
-                )*
-                ::std::unreachable!()
-            }
-        });
+                }
+            },
+            parse_quote! {
+                #[allow(unused_variables)]
+                fn missing_column(state: &Self::State) -> ::std::option::Option<&'static ::std::primitive::str> {
+                    #(#missing_checks)*
+                    ::std::option::Option::None
+                }
+            },
+        ]
+    }
+}
+
+impl Generator for ColumnSortingGenerator<'_> {
+    fn generate_serialize(&self) -> syn::TraitItemFn {
+        // Need to:
+        // - Check that all required columns are there and no more
+        // - Check that the column types match
+        let crate_path = self.ctx.attributes.crate_path();
+        let mk_typck_err = self.ctx.generate_mk_typck_err();
+        let mk_ser_err = self.ctx.generate_mk_ser_err();
 
         parse_quote! {
             fn serialize<'b>(
@@ -295,14 +500,61 @@ impl Generator for ColumnSortingGenerator<'_> {
                 ctx: &#crate_path::RowSerializationContext,
                 writer: &mut #crate_path::RowWriter<'b>,
             ) -> ::std::result::Result<(), #crate_path::SerializationError> {
-                #(#statements)*
+                // Declare helper lambdas for creating errors
+                #mk_typck_err
+                #mk_ser_err
+
+                let mut state = <Self as #crate_path::SerializeRowByName>::new_state();
+
+                // Serialize the field matching each column.
+                for spec in ctx.columns() {
+                    match <Self as #crate_path::SerializeRowByName>::serialize_column(self, &mut state, spec, writer) {
+                        ::std::result::Result::Ok(true) => {}
+                        ::std::result::Result::Ok(false) => {
+                            return ::std::result::Result::Err(mk_typck_err(
+                                #crate_path::BuiltinRowTypeCheckErrorKind::NoColumnWithName {
+                                    name: <_ as ::std::borrow::ToOwned>::to_owned(spec.name()),
+                                }
+                            ));
+                        }
+                        ::std::result::Result::Err(err) => {
+                            return ::std::result::Result::Err(mk_ser_err(
+                                #crate_path::BuiltinRowSerializationErrorKind::ColumnSerializationFailed {
+                                    name: <_ as ::std::borrow::ToOwned>::to_owned(spec.name()),
+                                    err,
+                                }
+                            ));
+                        }
+                    }
+                }
+
+                // Finally, check that all fields were consumed.
+                if let ::std::option::Option::Some(name) = <Self as #crate_path::SerializeRowByName>::missing_column(&state) {
+                    return ::std::result::Result::Err(mk_typck_err(
+                        #crate_path::BuiltinRowTypeCheckErrorKind::ValueMissingForColumn {
+                            name: <_ as ::std::string::ToString>::to_string(name),
+                        }
+                    ));
+                }
+
                 ::std::result::Result::Ok(())
             }
         }
     }
 
     fn generate_is_empty(&self) -> syn::TraitItemFn {
-        let is_empty = self.ctx.fields.is_empty();
+        let crate_path = self.ctx.attributes.crate_path();
+        // The row is empty if all of its fields are flattened empty rows.
+        let is_empty: syn::Expr = if self.regular_fields().next().is_some() {
+            parse_quote!(false)
+        } else {
+            let flattened = self.flattened_fields().map(|(_, f)| -> syn::Expr {
+                let member = &f.member;
+                let typ = &f.ty;
+                parse_quote!(<#typ as #crate_path::SerializeRow>::is_empty(&self.#member))
+            });
+            parse_quote!(true #(&& #flattened)*)
+        };
         parse_quote! {
             #[inline]
             fn is_empty(&self) -> bool {
@@ -335,9 +587,9 @@ impl Generator for ColumnOrderedGenerator<'_> {
 
         // Serialize each field
         for field in self.ctx.fields.iter() {
-            let rust_field_ident = &field.ident;
+            let rust_field_ident = &field.member;
             let rust_field_name = field.column_name();
-            let typ = &field.ty;
+            let serialize = field.serialize_fn(&crate_path);
             let name_check_expression: syn::Expr = if !self.ctx.attributes.skip_name_checks {
                 parse_quote! { spec.name() == #rust_field_name }
             } else {
@@ -348,7 +600,7 @@ impl Generator for ColumnOrderedGenerator<'_> {
                     Some(spec) => {
                         if #name_check_expression {
                             let cell_writer = #crate_path::RowWriter::make_cell_writer(writer);
-                            match <#typ as #crate_path::SerializeValue>::serialize(&self.#rust_field_ident, spec.typ(), cell_writer) {
+                            match #serialize(&self.#rust_field_ident, spec.typ(), cell_writer) {
                                 Ok(_proof) => {},
                                 Err(err) => {
                                     return ::std::result::Result::Err(mk_ser_err(
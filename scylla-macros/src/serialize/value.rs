@@ -4,7 +4,7 @@ use darling::FromAttributes;
 use proc_macro::TokenStream;
 use syn::parse_quote;
 
-use crate::Flavor;
+use crate::{Flavor, RenameAll};
 
 #[derive(FromAttributes)]
 #[darling(attributes(scylla))]
@@ -30,6 +30,21 @@ struct Attributes {
     // the DB will interpret them as NULLs anyway.
     #[darling(default)]
     forbid_excess_udt_fields: bool,
+
+    // If set, then the names of the UDT fields are the names of the struct fields
+    // converted to this case convention. Fields with `rename` are not affected.
+    #[darling(default)]
+    rename_all: Option<RenameAll>,
+
+    // If set, then the type checking code will verify that the name of the UDT
+    // is the same, so that UDTs with the same fields are not confused.
+    #[darling(default)]
+    udt_name: Option<String>,
+
+    // If set, then the type checking code will verify that the UDT is defined
+    // in this keyspace.
+    #[darling(default)]
+    keyspace: Option<String>,
 }
 
 impl Attributes {
@@ -45,13 +60,18 @@ struct Field {
     ident: syn::Ident,
     ty: syn::Type,
     attrs: FieldAttributes,
+    // The `rename_all` attribute of the struct.
+    rename_all: Option<RenameAll>,
 }
 
 impl Field {
     fn field_name(&self) -> String {
         match &self.attrs.rename {
             Some(name) => name.clone(),
-            None => self.ident.to_string(),
+            None => match self.rename_all {
+                Some(rename_all) => rename_all.apply_to_field(&self.ident.to_string()),
+                None => self.ident.to_string(),
+            },
         }
     }
 
@@ -60,10 +80,22 @@ impl Field {
     fn is_required(&self) -> bool {
         !self.attrs.skip && !self.attrs.ignore_missing
     }
+
+    // The function serializing the field: the `serialize` function of the module
+    // given in the `with` attribute, or the `SerializeValue` implementation.
+    fn serialize_fn(&self, crate_path: &syn::Path) -> syn::ExprPath {
+        match &self.attrs.with {
+            Some(with) => parse_quote!(#with::serialize),
+            None => {
+                let ty = &self.ty;
+                parse_quote!(<#ty as #crate_path::SerializeValue>::serialize)
+            }
+        }
+    }
 }
 
 #[derive(FromAttributes)]
-#[darling(attributes(scylla))]
+#[darling(attributes(scylla), and_then = Self::resolve_default)]
 struct FieldAttributes {
     // If set, then serializes from the UDT field with this particular name
     // instead of the Rust field name.
@@ -84,6 +116,24 @@ struct FieldAttributes {
     #[darling(default)]
     #[darling(rename = "default_when_null")]
     _default_when_null: bool,
+
+    // Implies `allow_missing`. The `default_when_null` part is used
+    // for deserialization only.
+    #[darling(default)]
+    default: bool,
+
+    // If set, then the field is serialized with the `serialize` function
+    // of this module instead of its SerializeValue implementation.
+    with: Option<syn::Path>,
+}
+
+impl FieldAttributes {
+    fn resolve_default(mut self) -> darling::Result<Self> {
+        if self.default {
+            self.ignore_missing = true;
+        }
+        Ok(self)
+    }
 }
 
 struct Context {
@@ -95,6 +145,12 @@ pub(crate) fn derive_serialize_value(
     tokens_input: TokenStream,
 ) -> Result<syn::ItemImpl, syn::Error> {
     let input: syn::DeriveInput = syn::parse(tokens_input)?;
+    if crate::transparent::is_transparent(&input.attrs) {
+        return crate::transparent::derive_serialize_value(&input);
+    }
+    if let syn::Data::Enum(data) = &input.data {
+        return crate::enum_value::derive_serialize_value(&input, data);
+    }
     let struct_name = input.ident.clone();
     let named_fields = crate::parser::parse_named_fields(&input, "SerializeValue")?;
     let (impl_generics, ty_generics, where_clause) = input.generics.split_for_impl();
@@ -111,6 +167,7 @@ pub(crate) fn derive_serialize_value(
                 ident: f.ident.clone().unwrap(),
                 ty: f.ty.clone(),
                 attrs,
+                rename_all: attributes.rename_all,
             })
         })
         // Filter the fields now instead of at the places that use them later
@@ -172,7 +229,14 @@ impl Context {
                 errors.push(error);
             }
 
-            // `rename` annotations don't make sense with skipped name checks
+            // `rename_all` and `rename` annotations don't make sense with skipped name checks
+            if self.attributes.rename_all.is_some() {
+                let err = darling::Error::custom(
+                    "the `rename_all` attribute doesn't make sense with `skip_name_checks` attribute",
+                )
+                .with_span(struct_ident);
+                errors.push(err);
+            }
             for field in self.fields.iter() {
                 if field.attrs.rename.is_some() {
                     let err = darling::Error::custom(
@@ -215,6 +279,34 @@ impl Context {
         }
     }
 
+    // Generates a check of the name and keyspace of the UDT, if they are
+    // specified by the attributes.
+    fn generate_udt_name_check(&self) -> Option<syn::Stmt> {
+        let crate_path = self.attributes.crate_path();
+
+        let mut mismatches: Vec<syn::Expr> = Vec::new();
+        if let Some(udt_name) = &self.attributes.udt_name {
+            mismatches.push(parse_quote!(<str as ::std::cmp::PartialEq>::ne(type_name, #udt_name)));
+        }
+        if let Some(keyspace) = &self.attributes.keyspace {
+            mismatches.push(parse_quote!(<str as ::std::cmp::PartialEq>::ne(keyspace, #keyspace)));
+        }
+        if mismatches.is_empty() {
+            return None;
+        }
+
+        Some(parse_quote! {
+            if #(#mismatches)||* {
+                return ::std::result::Result::Err(mk_typck_err(
+                    #crate_path::UdtTypeCheckErrorKind::NameMismatch {
+                        keyspace: <_ as ::std::clone::Clone>::clone(keyspace).into_owned(),
+                        type_name: <_ as ::std::clone::Clone>::clone(type_name).into_owned(),
+                    }
+                ));
+            }
+        })
+    }
+
     fn generate_mk_typck_err(&self) -> syn::Stmt {
         let crate_path = self.attributes.crate_path();
         parse_quote! {
@@ -280,7 +372,12 @@ impl Generator for FieldSortingGenerator<'_> {
         let rust_field_ignore_missing_flags =
             self.ctx.fields.iter().map(|f| f.attrs.ignore_missing);
         let udt_field_names = rust_field_names.clone(); // For now, it's the same
-        let field_types = self.ctx.fields.iter().map(|f| &f.ty).collect::<Vec<_>>();
+        let field_serializers = self
+            .ctx
+            .fields
+            .iter()
+            .map(|f| f.serialize_fn(&crate_path))
+            .collect::<Vec<_>>();
 
         let missing_rust_field_expression: syn::Expr =
             if self.ctx.attributes.forbid_excess_udt_fields {
@@ -326,6 +423,7 @@ impl Generator for FieldSortingGenerator<'_> {
             self.ctx
                 .generate_udt_type_match(parse_quote!(#crate_path::UdtTypeCheckErrorKind::NotUdt)),
         );
+        statements.extend(self.ctx.generate_udt_name_check());
 
         fn make_visited_flag_ident(field_name: &syn::Ident) -> syn::Ident {
             syn::Ident::new(&format!("visited_flag_{}", field_name), field_name.span())
@@ -383,7 +481,7 @@ impl Generator for FieldSortingGenerator<'_> {
                         #udt_field_names => {
                             #serialize_missing_nulls_statement
                             let sub_builder = #crate_path::CellValueBuilder::make_sub_writer(&mut builder);
-                            match <#field_types as #crate_path::SerializeValue>::serialize(&self.#rust_field_idents, field_type, sub_builder) {
+                            match #field_serializers(&self.#rust_field_idents, field_type, sub_builder) {
                                 ::std::result::Result::Ok(_proof) => {}
                                 ::std::result::Result::Err(err) => {
                                     return ::std::result::Result::Err(mk_ser_err(
@@ -464,6 +562,7 @@ impl Generator for FieldOrderedGenerator<'_> {
             self.ctx
                 .generate_udt_type_match(parse_quote!(#crate_path::UdtTypeCheckErrorKind::NotUdt)),
         );
+        statements.extend(self.ctx.generate_udt_name_check());
 
         // Turn the cell writer into a value builder
         statements.push(parse_quote! {
@@ -480,7 +579,7 @@ impl Generator for FieldOrderedGenerator<'_> {
             let rust_field_ident = &field.ident;
             let rust_field_name = field.field_name();
             let field_can_be_ignored = field.attrs.ignore_missing;
-            let typ = &field.ty;
+            let serialize = field.serialize_fn(&crate_path);
             let name_check_expression: syn::Expr = if !self.ctx.attributes.skip_name_checks {
                 parse_quote! { field_name == #rust_field_name }
             } else {
@@ -494,7 +593,7 @@ impl Generator for FieldOrderedGenerator<'_> {
                             field_iter.next();
 
                             let sub_builder = #crate_path::CellValueBuilder::make_sub_writer(&mut builder);
-                            match <#typ as #crate_path::SerializeValue>::serialize(&self.#rust_field_ident, typ, sub_builder) {
+                            match #serialize(&self.#rust_field_ident, typ, sub_builder) {
                                 Ok(_proof) => {},
                                 Err(err) => {
                                     return ::std::result::Result::Err(mk_ser_err(
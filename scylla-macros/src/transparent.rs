@@ -0,0 +1,188 @@
+//! Support for `#[scylla(transparent)]`, which makes `SerializeValue` and
+//! `DeserializeValue` derived on a single-field struct delegate to the field.
+
+use darling::FromAttributes;
+use syn::{parse_quote, Fields};
+
+#[derive(FromAttributes)]
+#[darling(attributes(scylla))]
+struct TransparentAttrs {
+    #[darling(rename = "crate")]
+    crate_path: Option<syn::Path>,
+
+    // Always set, as the attribute is what selects this implementation.
+    #[darling(rename = "transparent")]
+    _transparent: bool,
+}
+
+/// Checks whether `#[scylla(transparent)]` is among the attributes.
+///
+/// Malformed attributes are not reported here; they are reported
+/// by the regular attribute parsing.
+pub(crate) fn is_transparent(attrs: &[syn::Attribute]) -> bool {
+    let mut transparent = false;
+    for attr in attrs.iter().filter(|attr| attr.path().is_ident("scylla")) {
+        let _ = attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("transparent") {
+                transparent = true;
+            } else if meta.input.peek(syn::Token![=]) {
+                meta.value()?.parse::<syn::Expr>()?;
+            }
+            Ok(())
+        });
+    }
+    transparent
+}
+
+struct TransparentDesc {
+    name: syn::Ident,
+    generics: syn::Generics,
+    attrs: TransparentAttrs,
+    // `0` for a tuple struct, the field's name otherwise.
+    member: syn::Member,
+    field_ty: syn::Type,
+}
+
+impl TransparentDesc {
+    fn new(input: &syn::DeriveInput, trait_name: &str) -> Result<Self, syn::Error> {
+        let attrs = TransparentAttrs::from_attributes(&input.attrs)?;
+
+        let create_err_msg = || {
+            format!(
+                "derive({}) with `transparent` works only for structs with exactly one field",
+                trait_name
+            )
+        };
+        let field = match &input.data {
+            syn::Data::Struct(data) => match &data.fields {
+                Fields::Named(syn::FieldsNamed { named: fields, .. })
+                | Fields::Unnamed(syn::FieldsUnnamed {
+                    unnamed: fields, ..
+                }) if fields.len() == 1 => fields.first().unwrap(),
+                _ => return Err(syn::Error::new_spanned(data.struct_token, create_err_msg())),
+            },
+            syn::Data::Enum(e) => {
+                return Err(syn::Error::new_spanned(e.enum_token, create_err_msg()))
+            }
+            syn::Data::Union(u) => {
+                return Err(syn::Error::new_spanned(u.union_token, create_err_msg()))
+            }
+        };
+
+        // The field is (de)serialized exactly like the inner type,
+        // so none of the field attributes would have any effect.
+        if let Some(attr) = field
+            .attrs
+            .iter()
+            .find(|attr| attr.path().is_ident("scylla"))
+        {
+            return Err(syn::Error::new_spanned(
+                attr,
+                "field attributes are not supported with `transparent`",
+            ));
+        }
+
+        let member = match &field.ident {
+            Some(ident) => syn::Member::Named(ident.clone()),
+            None => syn::Member::Unnamed(0.into()),
+        };
+
+        Ok(Self {
+            name: input.ident.clone(),
+            generics: input.generics.clone(),
+            attrs,
+            member,
+            field_ty: field.ty.clone(),
+        })
+    }
+
+    fn crate_path(&self) -> syn::Path {
+        self.attrs
+            .crate_path
+            .as_ref()
+            .map(|p| parse_quote!(#p::_macro_internal))
+            .unwrap_or_else(|| parse_quote!(::scylla::_macro_internal))
+    }
+
+    fn generate_serialize_value(&self) -> syn::ItemImpl {
+        let crate_path = self.crate_path();
+        let struct_name = &self.name;
+        let field_ty = &self.field_ty;
+        let member = &self.member;
+        let (impl_generics, ty_generics, _) = self.generics.split_for_impl();
+        let mut where_clause = self.generics.clone().make_where_clause().clone();
+        where_clause
+            .predicates
+            .push(parse_quote!(#field_ty: #crate_path::SerializeValue));
+
+        parse_quote! {
+            #[automatically_derived]
+            impl #impl_generics #crate_path::SerializeValue for #struct_name #ty_generics #where_clause {
+                fn serialize<'b>(
+                    &self,
+                    typ: &#crate_path::ColumnType,
+                    writer: #crate_path::CellWriter<'b>,
+                ) -> ::std::result::Result<#crate_path::WrittenCellProof<'b>, #crate_path::SerializationError> {
+                    <#field_ty as #crate_path::SerializeValue>::serialize(&self.#member, typ, writer)
+                }
+            }
+        }
+    }
+
+    fn generate_deserialize_value(&self) -> syn::ItemImpl {
+        let crate_path = self.crate_path();
+        let struct_name = &self.name;
+        let field_ty = &self.field_ty;
+        let member = &self.member;
+        let (frame_lifetime, metadata_lifetime) =
+            crate::deserialize::generate_pair_of_unique_lifetimes_for_impl(&self.generics);
+        let (_, ty_generics, _) = self.generics.split_for_impl();
+        let impl_generics = &self.generics.params;
+
+        let mut where_clause = self.generics.clone().make_where_clause().clone();
+        // The deserialized value may borrow from the frame.
+        let lifetimes = self.generics.lifetimes().map(|l| &l.lifetime);
+        where_clause.predicates.extend(
+            lifetimes.map(|l| -> syn::WherePredicate { parse_quote!(#frame_lifetime: #l) }),
+        );
+        where_clause.predicates.push(parse_quote!(
+            #field_ty: #crate_path::DeserializeValue<#frame_lifetime, #metadata_lifetime>
+        ));
+
+        parse_quote! {
+            #[automatically_derived]
+            impl<#frame_lifetime, #metadata_lifetime, #impl_generics>
+                #crate_path::DeserializeValue<#frame_lifetime, #metadata_lifetime> for #struct_name #ty_generics
+            #where_clause
+            {
+                fn type_check(
+                    typ: &#crate_path::ColumnType,
+                ) -> ::std::result::Result<(), #crate_path::TypeCheckError> {
+                    <#field_ty as #crate_path::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::type_check(typ)
+                }
+
+                fn deserialize(
+                    typ: &#metadata_lifetime #crate_path::ColumnType<#metadata_lifetime>,
+                    v: ::std::option::Option<#crate_path::FrameSlice<#frame_lifetime>>,
+                ) -> ::std::result::Result<Self, #crate_path::DeserializationError> {
+                    <#field_ty as #crate_path::DeserializeValue<#frame_lifetime, #metadata_lifetime>>::deserialize(typ, v)
+                        .map(|inner| Self { #member: inner })
+                }
+            }
+        }
+    }
+}
+
+pub(crate) fn derive_serialize_value(
+    input: &syn::DeriveInput,
+) -> Result<syn::ItemImpl, syn::Error> {
+    let desc = TransparentDesc::new(input, "SerializeValue")?;
+    Ok(desc.generate_serialize_value())
+}
+
+pub(crate) fn derive_deserialize_value(
+    input: &syn::DeriveInput,
+) -> Result<syn::ItemImpl, syn::Error> {
+    let desc = TransparentDesc::new(input, "DeserializeValue")?;
+    Ok(desc.generate_deserialize_value())
+}
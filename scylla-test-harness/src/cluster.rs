@@ -0,0 +1,307 @@
+use std::net::{IpAddr, SocketAddr};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use scylla::SessionBuilder;
+use tracing::info;
+
+use crate::container::{
+    docker, spawn_detached, wait_for_cql, ContainerConfig, DatabaseFlavor, CQL_PORT,
+};
+use crate::errors::HarnessError;
+
+static NETWORK_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// The state of a node of a [`DbCluster`], as last changed by the harness.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NodeState {
+    /// The node is running and accepts CQL connections.
+    Up,
+    /// The container is stopped; the node is still a member of the cluster.
+    Stopped,
+    /// The node has left the cluster and its container is removed.
+    Decommissioned,
+}
+
+/// A node of a [`DbCluster`].
+#[derive(Debug)]
+pub struct ClusterNode {
+    id: String,
+    cql_address: SocketAddr,
+    state: NodeState,
+}
+
+impl ClusterNode {
+    /// The address under which the node's CQL port is reachable.
+    ///
+    /// Note that the address may change when the node is restarted.
+    pub fn cql_address(&self) -> SocketAddr {
+        self.cql_address
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn state(&self) -> NodeState {
+        self.state
+    }
+}
+
+/// A cluster of database nodes, each running in its own container.
+///
+/// The nodes are connected to a dedicated docker network and are addressed by
+/// their IPs in that network, because the driver discovers peers by the
+/// addresses they advertise. This requires the host to be able to reach the
+/// network directly, as is the case with docker on Linux.
+///
+/// Nodes are identified by their index, in the order in which they were added
+/// to the cluster. Topology changes are performed one at a time, and a method
+/// changing the topology returns once the change has taken effect.
+///
+/// ```no_run
+/// # use scylla_test_harness::{ContainerConfig, DbCluster};
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let mut cluster = DbCluster::start(ContainerConfig::scylla(), 3).await?;
+/// let session = cluster.session_builder().build().await?;
+///
+/// cluster.stop_node(1).await?;
+/// // ... check that the application copes with a node being down ...
+/// cluster.start_node(1).await?;
+///
+/// cluster.decommission_node(2).await?;
+/// cluster.stop().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct DbCluster {
+    config: ContainerConfig,
+    network: String,
+    nodes: Vec<ClusterNode>,
+    removed: bool,
+}
+
+impl DbCluster {
+    /// Creates a cluster with the given number of nodes and waits until
+    /// all of them accept CQL connections.
+    ///
+    /// The nodes are bootstrapped one by one, so starting a cluster takes
+    /// roughly `nodes` times as long as starting a single container.
+    pub async fn start(config: ContainerConfig, nodes: usize) -> Result<Self, HarnessError> {
+        let network = unique_network_name();
+        docker(&["network", "create", &network]).await?;
+        info!("Created network {}", network);
+
+        // From now on the network is owned by `Self`, so that it is cleaned up
+        // even if starting some of the nodes fails.
+        let mut cluster = Self {
+            config,
+            network,
+            nodes: Vec::with_capacity(nodes),
+            removed: false,
+        };
+        for _ in 0..nodes {
+            cluster.add_node().await?;
+        }
+        Ok(cluster)
+    }
+
+    pub fn nodes(&self) -> &[ClusterNode] {
+        &self.nodes
+    }
+
+    /// # Panics
+    ///
+    /// Panics if there is no node with the given index.
+    pub fn node(&self, idx: usize) -> &ClusterNode {
+        &self.nodes[idx]
+    }
+
+    pub fn flavor(&self) -> DatabaseFlavor {
+        self.config.flavor()
+    }
+
+    /// A [`SessionBuilder`] with all nodes that are currently up as known nodes.
+    pub fn session_builder(&self) -> SessionBuilder {
+        self.nodes
+            .iter()
+            .filter(|node| node.state == NodeState::Up)
+            .fold(SessionBuilder::new(), |builder, node| {
+                builder.known_node_addr(node.cql_address)
+            })
+    }
+
+    /// Bootstraps a new node, waits until it accepts CQL connections
+    /// and returns its index.
+    ///
+    /// The first node that is up serves as the seed.
+    pub async fn add_node(&mut self) -> Result<usize, HarnessError> {
+        let seed = self
+            .nodes
+            .iter()
+            .find(|node| node.state == NodeState::Up)
+            .map(|node| node.cql_address.ip().to_string());
+
+        let seeds_env;
+        let mut run_args = vec!["run", "-d", "--network", &self.network];
+        if let (DatabaseFlavor::Cassandra, Some(seed)) = (self.config.flavor(), &seed) {
+            seeds_env = format!("CASSANDRA_SEEDS={}", seed);
+            run_args.extend(["-e", seeds_env.as_str()]);
+        }
+        run_args.push(self.config.image());
+        run_args.extend(self.config.args().iter().map(String::as_str));
+        if let (DatabaseFlavor::Scylla, Some(seed)) = (self.config.flavor(), &seed) {
+            run_args.extend(["--seeds", seed.as_str()]);
+        }
+
+        let id = docker(&run_args).await?;
+        info!("Started {:?} cluster node {}", self.config.flavor(), id);
+
+        // Register the node before waiting for it, so that it is removed
+        // together with the cluster if it never becomes ready.
+        let cql_address = SocketAddr::new(container_ip(&id).await?, CQL_PORT);
+        self.nodes.push(ClusterNode {
+            id,
+            cql_address,
+            state: NodeState::Up,
+        });
+
+        wait_for_cql(cql_address, self.config.startup_timeout()).await?;
+        Ok(self.nodes.len() - 1)
+    }
+
+    /// Gracefully stops the node. It remains a member of the cluster
+    /// and can be brought back with [`DbCluster::start_node`].
+    pub async fn stop_node(&mut self, idx: usize) -> Result<(), HarnessError> {
+        let node = &mut self.nodes[idx];
+        docker(&["stop", &node.id]).await?;
+        node.state = NodeState::Stopped;
+        info!("Stopped cluster node {}", node.id);
+        Ok(())
+    }
+
+    /// Kills the node without giving it a chance to shut down gracefully,
+    /// like a crash or a power outage would. It can be brought back with
+    /// [`DbCluster::start_node`].
+    pub async fn kill_node(&mut self, idx: usize) -> Result<(), HarnessError> {
+        let node = &mut self.nodes[idx];
+        docker(&["kill", &node.id]).await?;
+        node.state = NodeState::Stopped;
+        info!("Killed cluster node {}", node.id);
+        Ok(())
+    }
+
+    /// Starts a previously stopped node and waits until it accepts CQL connections.
+    pub async fn start_node(&mut self, idx: usize) -> Result<(), HarnessError> {
+        let timeout = self.config.startup_timeout();
+        let node = &mut self.nodes[idx];
+        docker(&["start", &node.id]).await?;
+        // Docker does not guarantee that a restarted container keeps its IP.
+        node.cql_address = SocketAddr::new(container_ip(&node.id).await?, CQL_PORT);
+        node.state = NodeState::Up;
+        info!("Started cluster node {}", node.id);
+        wait_for_cql(node.cql_address, timeout).await
+    }
+
+    /// Makes the node leave the cluster, streaming its data to the remaining
+    /// nodes, and removes its container. The node has to be up.
+    pub async fn decommission_node(&mut self, idx: usize) -> Result<(), HarnessError> {
+        let node = &mut self.nodes[idx];
+        docker(&["exec", &node.id, "nodetool", "decommission"]).await?;
+        docker(&["rm", "-f", &node.id]).await?;
+        node.state = NodeState::Decommissioned;
+        info!("Decommissioned cluster node {}", node.id);
+        Ok(())
+    }
+
+    /// Removes all containers of the cluster and its network.
+    pub async fn stop(mut self) -> Result<(), HarnessError> {
+        self.removed = true;
+        for node in self.nodes.iter() {
+            if node.state != NodeState::Decommissioned {
+                docker(&["rm", "-f", &node.id]).await?;
+            }
+        }
+        docker(&["network", "rm", &self.network]).await.map(|_| ())
+    }
+}
+
+impl Drop for DbCluster {
+    fn drop(&mut self) {
+        if self.removed {
+            return;
+        }
+        // The network can only be removed once its containers are gone, so both
+        // steps are run by a single shell in the background.
+        let ids = self
+            .nodes
+            .iter()
+            .filter(|node| node.state != NodeState::Decommissioned)
+            .map(|node| node.id.as_str());
+        spawn_detached(
+            std::process::Command::new("sh")
+                .args([
+                    "-c",
+                    r#"network=$1; shift; [ $# -eq 0 ] || docker rm -f "$@"; docker network rm "$network""#,
+                    "sh",
+                    &self.network,
+                ])
+                .args(ids),
+        );
+    }
+}
+
+fn unique_network_name() -> String {
+    format!(
+        "scylla_test_harness_{}_{}",
+        std::process::id(),
+        NETWORK_COUNTER.fetch_add(1, Ordering::SeqCst)
+    )
+}
+
+// Returns the IP of the container in the (only) network it is connected to.
+async fn container_ip(id: &str) -> Result<IpAddr, HarnessError> {
+    let output = docker(&[
+        "inspect",
+        "-f",
+        "{{range .NetworkSettings.Networks}}{{.IPAddress}}{{end}}",
+        id,
+    ])
+    .await?;
+    parse_container_ip(&output)
+}
+
+fn parse_container_ip(output: &str) -> Result<IpAddr, HarnessError> {
+    output
+        .trim()
+        .parse()
+        .map_err(|_| HarnessError::BadContainerAddress(output.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{parse_container_ip, unique_network_name};
+    use crate::setup_tracing;
+    use crate::HarnessError;
+    use std::net::{IpAddr, Ipv4Addr};
+
+    #[test]
+    fn container_ip_parsing() {
+        setup_tracing();
+        assert_eq!(
+            parse_container_ip("172.18.0.2\n").unwrap(),
+            IpAddr::V4(Ipv4Addr::new(172, 18, 0, 2))
+        );
+        // A stopped container is not connected to any network.
+        assert!(matches!(
+            parse_container_ip(""),
+            Err(HarnessError::BadContainerAddress(_))
+        ));
+    }
+
+    #[test]
+    fn network_names_are_unique() {
+        setup_tracing();
+        assert_ne!(unique_network_name(), unique_network_name());
+    }
+}
@@ -0,0 +1,273 @@
+use std::net::SocketAddr;
+use std::process::Stdio;
+use std::time::Duration;
+
+use scylla::transport::errors::NewSessionError;
+use scylla::SessionBuilder;
+use tokio::process::Command;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+use crate::errors::HarnessError;
+
+/// The port on which the database listens for CQL connections inside the container.
+pub(crate) const CQL_PORT: u16 = 9042;
+
+/// The database that is run in the container.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DatabaseFlavor {
+    Scylla,
+    Cassandra,
+}
+
+/// Describes how a database container should be started.
+#[derive(Debug, Clone)]
+pub struct ContainerConfig {
+    flavor: DatabaseFlavor,
+    image: String,
+    args: Vec<String>,
+    startup_timeout: Duration,
+}
+
+impl ContainerConfig {
+    /// A single-shard Scylla node with a small memory footprint, suitable for tests.
+    pub fn scylla() -> Self {
+        Self {
+            flavor: DatabaseFlavor::Scylla,
+            image: "scylladb/scylla".to_owned(),
+            args: [
+                "--smp",
+                "1",
+                "--memory",
+                "512M",
+                "--overprovisioned",
+                "1",
+                "--skip-wait-for-gossip-to-settle",
+                "0",
+                "--ring-delay-ms",
+                "0",
+            ]
+            .into_iter()
+            .map(String::from)
+            .collect(),
+            startup_timeout: Duration::from_secs(120),
+        }
+    }
+
+    /// A Cassandra node with the image's default settings.
+    pub fn cassandra() -> Self {
+        Self {
+            flavor: DatabaseFlavor::Cassandra,
+            image: "cassandra".to_owned(),
+            args: Vec::new(),
+            // Cassandra takes noticeably longer to boot than Scylla.
+            startup_timeout: Duration::from_secs(180),
+        }
+    }
+
+    /// Uses the given image (e.g. a pinned version) instead of the default one.
+    pub fn with_image(mut self, image: impl Into<String>) -> Self {
+        self.image = image.into();
+        self
+    }
+
+    /// Replaces the arguments passed to the database process.
+    pub fn with_args(mut self, args: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.args = args.into_iter().map(Into::into).collect();
+        self
+    }
+
+    /// Sets how long to wait for the node to accept CQL connections.
+    pub fn with_startup_timeout(mut self, timeout: Duration) -> Self {
+        self.startup_timeout = timeout;
+        self
+    }
+
+    pub fn flavor(&self) -> DatabaseFlavor {
+        self.flavor
+    }
+
+    pub fn image(&self) -> &str {
+        &self.image
+    }
+
+    pub(crate) fn args(&self) -> &[String] {
+        &self.args
+    }
+
+    pub(crate) fn startup_timeout(&self) -> Duration {
+        self.startup_timeout
+    }
+}
+
+/// A running database container.
+///
+/// The container is removed when [`DbContainer::stop`] is called, which tests
+/// should await at the end. If it is dropped without being stopped (e.g. because
+/// a test panicked), [`Drop`] starts removing it in the background on a
+/// best-effort basis, without blocking the dropping thread.
+#[derive(Debug)]
+pub struct DbContainer {
+    id: String,
+    flavor: DatabaseFlavor,
+    cql_address: SocketAddr,
+    removed: bool,
+}
+
+impl DbContainer {
+    /// Starts a container and waits until it accepts CQL connections.
+    pub async fn start(config: ContainerConfig) -> Result<Self, HarnessError> {
+        let publish = format!("127.0.0.1::{}", CQL_PORT);
+        let mut run_args = vec!["run", "-d", "--rm", "-p", &publish, &config.image];
+        run_args.extend(config.args.iter().map(String::as_str));
+
+        let id = docker(&run_args).await?;
+        info!("Started {:?} container {}", config.flavor, id);
+
+        // From now on the container is owned by `Self`, so that it is cleaned up
+        // even if waiting for readiness fails.
+        let mut container = Self {
+            id,
+            flavor: config.flavor,
+            cql_address: SocketAddr::from(([127, 0, 0, 1], CQL_PORT)),
+            removed: false,
+        };
+
+        let port_mapping = docker(&["port", &container.id, &format!("{}/tcp", CQL_PORT)]).await?;
+        container.cql_address = parse_port_mapping(&port_mapping)?;
+
+        container.wait_for_cql(config.startup_timeout).await?;
+        Ok(container)
+    }
+
+    /// The address on the host under which the node's CQL port is reachable.
+    pub fn cql_address(&self) -> SocketAddr {
+        self.cql_address
+    }
+
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    pub fn flavor(&self) -> DatabaseFlavor {
+        self.flavor
+    }
+
+    /// A [`SessionBuilder`] with the container's node as the only known node.
+    pub fn session_builder(&self) -> SessionBuilder {
+        SessionBuilder::new().known_node_addr(self.cql_address)
+    }
+
+    /// Repeatedly tries to open a session until it succeeds or the timeout passes.
+    pub async fn wait_for_cql(&self, timeout: Duration) -> Result<(), HarnessError> {
+        wait_for_cql(self.cql_address, timeout).await
+    }
+
+    /// Stops and removes the container.
+    pub async fn stop(mut self) -> Result<(), HarnessError> {
+        self.removed = true;
+        docker(&["rm", "-f", &self.id]).await.map(|_| ())
+    }
+}
+
+impl Drop for DbContainer {
+    fn drop(&mut self) {
+        if self.removed {
+            return;
+        }
+        spawn_detached(std::process::Command::new("docker").args(["rm", "-f", &self.id]));
+    }
+}
+
+// Starts a cleanup command without waiting for it to finish. There is no async
+// drop and the runtime may be shutting down already, so cleanup in `Drop` is
+// left to a child process, which outlives the test binary if needed.
+pub(crate) fn spawn_detached(command: &mut std::process::Command) {
+    let result = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn();
+    if let Err(err) = result {
+        warn!("Failed to run {:?}: {}", command, err);
+    }
+}
+
+// Repeatedly tries to open a session to the given node until it succeeds
+// or the timeout passes.
+pub(crate) async fn wait_for_cql(
+    address: SocketAddr,
+    timeout: Duration,
+) -> Result<(), HarnessError> {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let last_error: NewSessionError =
+            match SessionBuilder::new().known_node_addr(address).build().await {
+                Ok(_) => {
+                    debug!("Node at {} accepts CQL connections", address);
+                    return Ok(());
+                }
+                Err(err) => err,
+            };
+        if Instant::now() >= deadline {
+            return Err(HarnessError::ReadinessTimeout {
+                address,
+                timeout,
+                last_error: Box::new(last_error),
+            });
+        }
+        debug!("Node at {} is not ready yet: {}", address, last_error);
+        tokio::time::sleep(Duration::from_secs(1)).await;
+    }
+}
+
+// Runs a docker command and returns its trimmed standard output.
+pub(crate) async fn docker(args: &[&str]) -> Result<String, HarnessError> {
+    let output = Command::new("docker")
+        .args(args)
+        .output()
+        .await
+        .map_err(HarnessError::DockerUnavailable)?;
+    if !output.status.success() {
+        return Err(HarnessError::DockerCommandFailed {
+            command: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+        });
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_owned())
+}
+
+// `docker port` prints one mapping per line, e.g. "127.0.0.1:49153".
+// Newer versions may print IPv6 mappings as well; the first IPv4 one is picked.
+fn parse_port_mapping(output: &str) -> Result<SocketAddr, HarnessError> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().parse::<SocketAddr>().ok())
+        .find(SocketAddr::is_ipv4)
+        .ok_or_else(|| HarnessError::BadPortMapping(output.to_owned()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::parse_port_mapping;
+    use crate::setup_tracing;
+    use crate::HarnessError;
+    use std::net::SocketAddr;
+
+    #[test]
+    fn port_mapping_parsing() {
+        setup_tracing();
+        assert_eq!(
+            parse_port_mapping("127.0.0.1:49153").unwrap(),
+            "127.0.0.1:49153".parse::<SocketAddr>().unwrap()
+        );
+        assert_eq!(
+            parse_port_mapping("[::1]:49154\n127.0.0.1:49153\n").unwrap(),
+            "127.0.0.1:49153".parse::<SocketAddr>().unwrap()
+        );
+        assert!(matches!(
+            parse_port_mapping("Error: No public port '9042/tcp' published"),
+            Err(HarnessError::BadPortMapping(_))
+        ));
+    }
+}
@@ -0,0 +1,38 @@
+use std::net::SocketAddr;
+use std::time::Duration;
+
+use scylla::transport::errors::{NewSessionError, QueryError};
+use thiserror::Error;
+
+/// An error that occurred while orchestrating the test environment.
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum HarnessError {
+    /// The `docker` binary could not be run.
+    #[error("Failed to run docker: {0}")]
+    DockerUnavailable(std::io::Error),
+
+    /// A docker command exited with a non-zero status.
+    #[error("Command `docker {command}` failed: {stderr}")]
+    DockerCommandFailed { command: String, stderr: String },
+
+    /// The output of `docker port` could not be parsed into an address.
+    #[error("Unexpected port mapping reported by docker: {0:?}")]
+    BadPortMapping(String),
+
+    /// The address of a container in its network could not be determined.
+    #[error("Unexpected container address reported by docker: {0:?}")]
+    BadContainerAddress(String),
+
+    /// The database did not start accepting CQL connections in time.
+    #[error("Node at {address} did not become ready for CQL within {timeout:?}, last error: {last_error}")]
+    ReadinessTimeout {
+        address: SocketAddr,
+        timeout: Duration,
+        last_error: Box<NewSessionError>,
+    },
+
+    /// A statement issued by the harness failed.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+}
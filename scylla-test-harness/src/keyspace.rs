@@ -0,0 +1,98 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use scylla::Session;
+use tracing::debug;
+
+use crate::errors::HarnessError;
+
+static UNIQUE_COUNTER: AtomicUsize = AtomicUsize::new(0);
+
+/// Generates a keyspace name that is unique within the process and,
+/// with high probability, across processes running at the same time.
+pub fn unique_keyspace_name() -> String {
+    let cnt = UNIQUE_COUNTER.fetch_add(1, Ordering::SeqCst);
+    format!(
+        "test_harness_{}_{}_{}",
+        SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs(),
+        std::process::id(),
+        cnt
+    )
+}
+
+/// A keyspace created for the purpose of a single test.
+///
+/// Call [`TestKeyspace::drop_keyspace`] at the end of the test to remove it.
+/// A keyspace that is not dropped explicitly is left in the cluster - it is
+/// harmless apart from taking space, and may help debugging a failed test.
+#[derive(Debug)]
+pub struct TestKeyspace {
+    name: String,
+}
+
+impl TestKeyspace {
+    /// Creates a keyspace with a unique name and replication factor 1.
+    pub async fn create(session: &Session) -> Result<Self, HarnessError> {
+        Self::create_with_replication(
+            session,
+            "{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}",
+        )
+        .await
+    }
+
+    /// Creates a keyspace with a unique name and the given replication map,
+    /// e.g. `{'class': 'SimpleStrategy', 'replication_factor': 3}`.
+    pub async fn create_with_replication(
+        session: &Session,
+        replication: &str,
+    ) -> Result<Self, HarnessError> {
+        let name = unique_keyspace_name();
+        session
+            .query_unpaged(
+                format!(
+                    "CREATE KEYSPACE {} WITH REPLICATION = {}",
+                    name, replication
+                ),
+                &[],
+            )
+            .await?;
+        session.await_schema_agreement().await?;
+        debug!("Created test keyspace {}", name);
+        Ok(Self { name })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Drops the keyspace together with all its tables and types.
+    pub async fn drop_keyspace(self, session: &Session) -> Result<(), HarnessError> {
+        session
+            .query_unpaged(format!("DROP KEYSPACE IF EXISTS {}", self.name), &[])
+            .await?;
+        debug!("Dropped test keyspace {}", self.name);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::unique_keyspace_name;
+    use crate::setup_tracing;
+
+    #[test]
+    fn keyspace_names_are_unique_and_valid() {
+        setup_tracing();
+        let a = unique_keyspace_name();
+        let b = unique_keyspace_name();
+        assert_ne!(a, b);
+        for name in [a, b] {
+            // Keyspace names are limited to 48 alphanumeric characters and underscores.
+            assert!(name.len() <= 48, "{name} is too long");
+            assert!(name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_'));
+        }
+    }
+}
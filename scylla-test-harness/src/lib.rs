@@ -0,0 +1,44 @@
+//! Helpers for writing hermetic integration tests against Scylla or Cassandra.
+//!
+//! The harness starts a database node in a Docker container, waits until it
+//! accepts CQL connections and creates throwaway keyspaces with unique names,
+//! so that a test does not depend on (or interfere with) any shared cluster.
+//!
+//! ```no_run
+//! # use scylla_test_harness::{ContainerConfig, DbContainer, TestKeyspace};
+//! # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+//! let container = DbContainer::start(ContainerConfig::scylla()).await?;
+//! let session = container.session_builder().build().await?;
+//!
+//! let keyspace = TestKeyspace::create(&session).await?;
+//! session.use_keyspace(keyspace.name(), true).await?;
+//! // ... the test itself ...
+//!
+//! keyspace.drop_keyspace(&session).await?;
+//! container.stop().await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! Tests of failover handling can use [`DbCluster`] instead, which runs several
+//! nodes and allows stopping, restarting and decommissioning them.
+//!
+//! The `docker` binary has to be available in `PATH`.
+
+mod cluster;
+mod container;
+mod errors;
+mod keyspace;
+
+pub use cluster::{ClusterNode, DbCluster, NodeState};
+pub use container::{ContainerConfig, DatabaseFlavor, DbContainer};
+pub use errors::HarnessError;
+pub use keyspace::{unique_keyspace_name, TestKeyspace};
+
+#[cfg(test)]
+pub(crate) fn setup_tracing() {
+    let _ = tracing_subscriber::fmt::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::from_default_env())
+        .with_writer(tracing_subscriber::fmt::TestWriter::new())
+        .try_init();
+}
@@ -0,0 +1,370 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use futures::TryStreamExt;
+use scylla_cql::frame::value::{CqlTimestamp, CqlTimeuuid};
+use scylla_cql::types::deserialize::TypeCheckError;
+use thiserror::Error;
+use uuid::Uuid;
+
+use crate::prepared_statement::PreparedStatement;
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::{IntoRowsResultError, MaybeFirstRowError};
+use crate::Session;
+
+/// Persists the progress of a CDC consumer, so that it can resume exactly
+/// where it stopped, e.g. after a redeploy.
+///
+/// The log of a table is split into streams, and the set of streams changes
+/// with every CDC generation, identified by the time it starts at. Therefore,
+/// the progress is tracked per generation:
+/// - the consumer records the generation it processes with [`save_generation`],
+/// - for each stream of that generation, it records the time of the last consumed
+///   change with [`save_checkpoint`].
+///
+/// Upon a restart, the consumer loads the generation and the checkpoints of its streams,
+/// and reads each stream starting after its checkpoint (`"cdc$time" > ?`), or from
+/// the start of the generation if the stream has no checkpoint. It should move on to
+/// the next generation only once all streams are consumed up to the start of the next one.
+///
+/// [`save_generation`]: CheckpointStore::save_generation
+/// [`save_checkpoint`]: CheckpointStore::save_checkpoint
+#[async_trait]
+pub trait CheckpointStore: Send + Sync {
+    /// Loads the start of the generation the consumer processes,
+    /// or `None` if it has not started yet.
+    async fn load_generation(&self) -> Result<Option<CqlTimestamp>, CheckpointError>;
+
+    /// Records that the consumer has moved on to the generation starting at the given time.
+    /// Checkpoints of the earlier generations are no longer needed and may be dropped.
+    async fn save_generation(&self, generation: CqlTimestamp) -> Result<(), CheckpointError>;
+
+    /// Loads the checkpoints of the streams of the given generation, by stream id.
+    async fn load_checkpoints(
+        &self,
+        generation: CqlTimestamp,
+    ) -> Result<HashMap<Vec<u8>, CqlTimeuuid>, CheckpointError>;
+
+    /// Records that the changes in the stream have been consumed up to,
+    /// and including, the change at the given time.
+    async fn save_checkpoint(
+        &self,
+        generation: CqlTimestamp,
+        stream_id: &[u8],
+        time: CqlTimeuuid,
+    ) -> Result<(), CheckpointError>;
+}
+
+/// A [`CheckpointStore`] keeping the checkpoints in a table,
+/// one partition per consumer.
+///
+/// The table has to be created beforehand, see [`TableCheckpointStore::create_table_query`].
+///
+/// The checkpoints and the generation only move forward: each of them is written with
+/// the time it points to as the timestamp of the write, so the database keeps the latest one
+/// even if an earlier one is saved afterwards, e.g. by a task which fell behind.
+/// For the same reason, to consume the log again from the start, use a new consumer name
+/// instead of deleting the checkpoints.
+///
+/// ```rust
+/// # use scylla::Session;
+/// # use scylla::cdc::{CheckpointStore, TableCheckpointStore};
+/// # use std::error::Error;
+/// # use std::sync::Arc;
+/// # async fn check_only_compiles(session: Arc<Session>) -> Result<(), Box<dyn Error>> {
+/// session
+///     .query_unpaged(TableCheckpointStore::create_table_query("ks.cdc_checkpoints"), &[])
+///     .await?;
+/// let store = TableCheckpointStore::new(session, "ks.cdc_checkpoints", "indexer").await?;
+///
+/// if let Some(generation) = store.load_generation().await? {
+///     for (stream_id, time) in store.load_checkpoints(generation).await? {
+///         // ... resume reading the stream after `time` ...
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct TableCheckpointStore {
+    session: Arc<Session>,
+    consumer: String,
+    load_generation: PreparedStatement,
+    save_generation: PreparedStatement,
+    drop_generations: PreparedStatement,
+    load_checkpoints: PreparedStatement,
+    save_checkpoint: PreparedStatement,
+}
+
+impl TableCheckpointStore {
+    /// Returns the statement creating a table which can store checkpoints.
+    pub fn create_table_query(table: &str) -> String {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (consumer text, generation timestamp, \
+             stream_id blob, time timeuuid, current_generation timestamp STATIC, \
+             PRIMARY KEY (consumer, generation, stream_id))",
+            table
+        )
+    }
+
+    /// Prepares the statements managing the checkpoints of the given consumer
+    /// in the given table, which may be qualified with a keyspace name.
+    pub async fn new(
+        session: Arc<Session>,
+        table: &str,
+        consumer: impl Into<String>,
+    ) -> Result<Self, CheckpointError> {
+        let load_generation = session
+            .prepare(format!(
+                "SELECT current_generation FROM {} WHERE consumer = ? LIMIT 1",
+                table
+            ))
+            .await?;
+        let save_generation = session
+            .prepare(format!(
+                "UPDATE {} USING TIMESTAMP ? SET current_generation = ? WHERE consumer = ?",
+                table
+            ))
+            .await?;
+        let drop_generations = session
+            .prepare(format!(
+                "DELETE FROM {} WHERE consumer = ? AND generation < ?",
+                table
+            ))
+            .await?;
+        let load_checkpoints = session
+            .prepare(format!(
+                "SELECT stream_id, time FROM {} WHERE consumer = ? AND generation = ?",
+                table
+            ))
+            .await?;
+        let save_checkpoint = session
+            .prepare(format!(
+                "UPDATE {} USING TIMESTAMP ? SET time = ? \
+                 WHERE consumer = ? AND generation = ? AND stream_id = ?",
+                table
+            ))
+            .await?;
+
+        Ok(Self {
+            session,
+            consumer: consumer.into(),
+            load_generation,
+            save_generation,
+            drop_generations,
+            load_checkpoints,
+            save_checkpoint,
+        })
+    }
+
+    pub fn consumer(&self) -> &str {
+        &self.consumer
+    }
+}
+
+#[async_trait]
+impl CheckpointStore for TableCheckpointStore {
+    async fn load_generation(&self) -> Result<Option<CqlTimestamp>, CheckpointError> {
+        let row = self
+            .session
+            .execute_unpaged(&self.load_generation, (&self.consumer,))
+            .await?
+            .into_rows_result()?
+            .maybe_first_row::<(Option<CqlTimestamp>,)>()?;
+        Ok(row.and_then(|(generation,)| generation))
+    }
+
+    async fn save_generation(&self, generation: CqlTimestamp) -> Result<(), CheckpointError> {
+        self.session
+            .execute_unpaged(
+                &self.save_generation,
+                (generation.0 * 1000, generation, &self.consumer),
+            )
+            .await?;
+        self.session
+            .execute_unpaged(&self.drop_generations, (&self.consumer, generation))
+            .await?;
+        Ok(())
+    }
+
+    async fn load_checkpoints(
+        &self,
+        generation: CqlTimestamp,
+    ) -> Result<HashMap<Vec<u8>, CqlTimeuuid>, CheckpointError> {
+        let mut checkpoints = HashMap::new();
+        let mut pager = self
+            .session
+            .execute_iter(self.load_checkpoints.clone(), (&self.consumer, generation))
+            .await?
+            .rows_stream::<(Vec<u8>, Option<CqlTimeuuid>)>()?;
+        while let Some((stream_id, time)) = pager.try_next().await? {
+            if let Some(time) = time {
+                checkpoints.insert(stream_id, time);
+            }
+        }
+        Ok(checkpoints)
+    }
+
+    async fn save_checkpoint(
+        &self,
+        generation: CqlTimestamp,
+        stream_id: &[u8],
+        time: CqlTimeuuid,
+    ) -> Result<(), CheckpointError> {
+        let write_timestamp = timeuuid_micros(time).ok_or(CheckpointError::NotTimeBased(time))?;
+        self.session
+            .execute_unpaged(
+                &self.save_checkpoint,
+                (write_timestamp, time, &self.consumer, generation, stream_id),
+            )
+            .await?;
+        Ok(())
+    }
+}
+
+// Microseconds since the Unix epoch of a time-based UUID.
+fn timeuuid_micros(time: CqlTimeuuid) -> Option<i64> {
+    let (seconds, nanos) = Uuid::from(time).get_timestamp()?.to_unix();
+    Some(seconds as i64 * 1_000_000 + (nanos / 1000) as i64)
+}
+
+/// An error returned by a [`CheckpointStore`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum CheckpointError {
+    /// Executing one of the statements failed.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// A statement returned a result of other kind than Rows.
+    #[error("Failed to convert the result into rows result: {0}")]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// The rows returned by a statement have unexpected types.
+    #[error("Failed to type check the rows: {0}")]
+    TypeCheckFailed(#[from] TypeCheckError),
+
+    /// A row returned by a statement failed to deserialize.
+    #[error("Failed to deserialize the row: {0}")]
+    RowDeserializationFailed(#[from] MaybeFirstRowError),
+
+    /// The time of a checkpoint is not a time-based (version 1) UUID.
+    #[error("The checkpoint time {0} is not a time-based UUID")]
+    NotTimeBased(CqlTimeuuid),
+
+    /// An error of a custom [`CheckpointStore`] implementation.
+    #[error(transparent)]
+    Other(Box<dyn Error + Send + Sync>),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{timeuuid_micros, CheckpointStore, TableCheckpointStore};
+    use crate::test_utils::{
+        create_new_session_builder, setup_tracing, unique_keyspace_name, PerformDDL,
+    };
+    use scylla_cql::frame::value::{CqlTimestamp, CqlTimeuuid};
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    use uuid::Uuid;
+
+    // A version 1 UUID of the given number of microseconds since the Unix epoch.
+    fn timeuuid(micros: u64) -> CqlTimeuuid {
+        // The number of 100ns intervals between the Gregorian and the Unix epoch.
+        let ticks = 0x01B2_1DD2_1381_4000 + micros * 10;
+        Uuid::from_fields(
+            ticks as u32,
+            (ticks >> 32) as u16,
+            0x1000 | (ticks >> 48) as u16,
+            &[0x80, 0, 0, 0, 0, 0, 0, 1],
+        )
+        .into()
+    }
+
+    #[test]
+    fn create_table_query() {
+        setup_tracing();
+        assert_eq!(
+            TableCheckpointStore::create_table_query("ks.checkpoints"),
+            "CREATE TABLE IF NOT EXISTS ks.checkpoints (consumer text, generation timestamp, \
+             stream_id blob, time timeuuid, current_generation timestamp STATIC, \
+             PRIMARY KEY (consumer, generation, stream_id))"
+        );
+    }
+
+    #[test]
+    fn write_timestamps() {
+        setup_tracing();
+        assert_eq!(timeuuid_micros(timeuuid(0)), Some(0));
+        assert_eq!(
+            timeuuid_micros(timeuuid(1_700_000_000_123_456)),
+            Some(1_700_000_000_123_456)
+        );
+        assert_eq!(timeuuid_micros(Uuid::nil().into()), None);
+    }
+
+    #[tokio::test]
+    async fn save_and_load() {
+        setup_tracing();
+        let session = Arc::new(create_new_session_builder().build().await.unwrap());
+        let ks = unique_keyspace_name();
+        session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+        let table = format!("{}.checkpoints", ks);
+        session
+            .ddl(TableCheckpointStore::create_table_query(&table))
+            .await
+            .unwrap();
+
+        let store = TableCheckpointStore::new(session.clone(), &table, "consumer")
+            .await
+            .unwrap();
+        assert_eq!(store.load_generation().await.unwrap(), None);
+
+        let first = CqlTimestamp(1_700_000_000_000);
+        store.save_generation(first).await.unwrap();
+        assert_eq!(store.load_generation().await.unwrap(), Some(first));
+
+        let micros = first.0 as u64 * 1000;
+        store
+            .save_checkpoint(first, b"a", timeuuid(micros + 2000))
+            .await
+            .unwrap();
+        store
+            .save_checkpoint(first, b"b", timeuuid(micros + 1000))
+            .await
+            .unwrap();
+        // A checkpoint older than the saved one is ignored.
+        store
+            .save_checkpoint(first, b"a", timeuuid(micros + 1000))
+            .await
+            .unwrap();
+        assert_eq!(
+            store.load_checkpoints(first).await.unwrap(),
+            HashMap::from([
+                (b"a".to_vec(), timeuuid(micros + 2000)),
+                (b"b".to_vec(), timeuuid(micros + 1000)),
+            ])
+        );
+        assert!(store
+            .save_checkpoint(first, b"a", Uuid::nil().into())
+            .await
+            .is_err());
+
+        // Moving on to the next generation drops the checkpoints of the previous one.
+        let second = CqlTimestamp(first.0 + 60_000);
+        store.save_generation(second).await.unwrap();
+        assert_eq!(store.load_generation().await.unwrap(), Some(second));
+        assert!(store.load_checkpoints(first).await.unwrap().is_empty());
+
+        // An older generation is ignored.
+        store.save_generation(first).await.unwrap();
+        assert_eq!(store.load_generation().await.unwrap(), Some(second));
+
+        // Other consumers are independent.
+        let other = TableCheckpointStore::new(session, &table, "other")
+            .await
+            .unwrap();
+        assert_eq!(other.load_generation().await.unwrap(), None);
+    }
+}
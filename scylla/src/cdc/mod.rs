@@ -0,0 +1,54 @@
+//! Typed access to the rows of Scylla's CDC (Change Data Capture) log tables.
+//!
+//! Every table with CDC enabled has a log table, named `<table>_scylla_cdc_log`,
+//! describing the changes made to the base table. Apart from the columns of the
+//! base table, each log row has `cdc$` metadata columns: the stream it belongs to,
+//! the time of the change, the kind of operation, and a `cdc$deleted_<column>` flag
+//! for every non-key column, telling apart a column set to null from an unchanged one.
+//!
+//! [`CdcRow<T>`] deserializes such a row, mapping the base table columns into
+//! the user's row type `T` (e.g. one with `#[derive(DeserializeRow)]`) and the
+//! metadata columns into typed fields. The statement selecting the rows has to put
+//! the columns in the order expected by [`CdcRow`], see [`select_query`].
+//!
+//! If pre-images and post-images are enabled for the table, they are stored as
+//! separate log rows next to the rows describing the change itself.
+//! [`group_changes`] collects them into a single [`CdcChange`].
+//!
+//! ```rust
+//! # use scylla::Session;
+//! # use std::error::Error;
+//! # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+//! use scylla::cdc::{self, CdcRow, OperationType};
+//! use scylla::DeserializeRow;
+//!
+//! #[derive(DeserializeRow)]
+//! struct User {
+//!     id: i32,
+//!     name: Option<String>,
+//!     email: Option<String>,
+//! }
+//!
+//! let query = format!(
+//!     "{} WHERE \"cdc$stream_id\" = ?",
+//!     cdc::select_query(&cdc::log_table_name("ks.users"), &["id"], &["name", "email"]),
+//! );
+//! # let stream_id: Vec<u8> = Vec::new();
+//! let result = session.query_unpaged(query, (stream_id,)).await?.into_rows_result()?;
+//! for row in result.rows::<CdcRow<User>>()? {
+//!     let row = row?;
+//!     if row.operation == OperationType::RowUpdate && row.changed_columns.is_changed(2) {
+//!         println!("User {} changed their email to {:?}", row.data.id, row.data.email);
+//!     }
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+mod checkpoint;
+mod row;
+pub use checkpoint::{CheckpointError, CheckpointStore, TableCheckpointStore};
+pub use row::{
+    group_changes, log_table_name, select_query, CdcChange, CdcDeserializationError, CdcRow,
+    CdcTypeCheckError, ChangedColumns, OperationType,
+};
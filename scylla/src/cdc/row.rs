@@ -0,0 +1,612 @@
+use std::fmt::Write;
+
+use scylla_cql::frame::response::result::{ColumnSpec, ColumnType};
+use scylla_cql::frame::value::CqlTimeuuid;
+use scylla_cql::types::deserialize::row::{ColumnIterator, DeserializeRow, RawColumn};
+use scylla_cql::types::deserialize::value::DeserializeValue;
+use scylla_cql::types::deserialize::{DeserializationError, TypeCheckError};
+use thiserror::Error;
+
+const DELETED_PREFIX: &str = "cdc$deleted_";
+
+// The metadata columns, in the order in which `CdcRow` expects them.
+const METADATA_COLUMNS: [(&str, ColumnType<'static>); 6] = [
+    ("cdc$stream_id", ColumnType::Blob),
+    ("cdc$time", ColumnType::Timeuuid),
+    ("cdc$batch_seq_no", ColumnType::Int),
+    ("cdc$operation", ColumnType::TinyInt),
+    ("cdc$ttl", ColumnType::BigInt),
+    ("cdc$end_of_batch", ColumnType::Boolean),
+];
+
+/// Returns the name of the CDC log table of the given table,
+/// which may be qualified with a keyspace name.
+pub fn log_table_name(base_table: &str) -> String {
+    format!("{}_scylla_cdc_log", base_table)
+}
+
+/// Returns a `SELECT` statement (without the `WHERE` clause) reading the rows
+/// of the given log table in the form expected by [`CdcRow`].
+///
+/// `key_columns` are the primary key columns of the base table, `value_columns`
+/// the other columns, for which `cdc$deleted_` flags are selected as well.
+/// The columns of the base table are selected in the given order, keys first.
+pub fn select_query(log_table: &str, key_columns: &[&str], value_columns: &[&str]) -> String {
+    let mut columns = METADATA_COLUMNS
+        .iter()
+        .map(|(name, _)| quote(name))
+        .collect::<Vec<_>>();
+    columns.extend(
+        value_columns
+            .iter()
+            .map(|column| quote(&format!("{}{}", DELETED_PREFIX, column))),
+    );
+    columns.extend(key_columns.iter().chain(value_columns).map(|c| quote(c)));
+
+    let mut query = String::from("SELECT ");
+    query.push_str(&columns.join(", "));
+    let _ = write!(query, " FROM {}", log_table);
+    query
+}
+
+fn quote(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// The kind of operation described by a CDC log row.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum OperationType {
+    /// The state of the row before the change.
+    PreImage,
+    RowUpdate,
+    RowInsert,
+    RowDelete,
+    PartitionDelete,
+    RowRangeDeleteInclusiveLeftBound,
+    RowRangeDeleteExclusiveLeftBound,
+    RowRangeDeleteInclusiveRightBound,
+    RowRangeDeleteExclusiveRightBound,
+    /// The state of the row after the change.
+    PostImage,
+}
+
+impl OperationType {
+    /// Checks whether the row describes a change, as opposed to a pre-image or post-image.
+    pub fn is_delta(&self) -> bool {
+        !matches!(self, Self::PreImage | Self::PostImage)
+    }
+}
+
+impl TryFrom<i8> for OperationType {
+    type Error = CdcDeserializationError;
+
+    fn try_from(value: i8) -> Result<Self, Self::Error> {
+        Ok(match value {
+            0 => Self::PreImage,
+            1 => Self::RowUpdate,
+            2 => Self::RowInsert,
+            3 => Self::RowDelete,
+            4 => Self::PartitionDelete,
+            5 => Self::RowRangeDeleteInclusiveLeftBound,
+            6 => Self::RowRangeDeleteExclusiveLeftBound,
+            7 => Self::RowRangeDeleteInclusiveRightBound,
+            8 => Self::RowRangeDeleteExclusiveRightBound,
+            9 => Self::PostImage,
+            _ => return Err(CdcDeserializationError::UnknownOperation(value)),
+        })
+    }
+}
+
+/// A bitmap of the base table columns changed by a CDC log row,
+/// indexed by the positions of the columns in the row type.
+///
+/// A column is changed if it was given a value, or if it was deleted, i.e. set to null.
+/// Only columns whose `cdc$deleted_` flag is selected are tracked; key columns never are.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ChangedColumns {
+    changed: Vec<u64>,
+    deleted: Vec<u64>,
+}
+
+impl ChangedColumns {
+    fn set(bitmap: &mut Vec<u64>, idx: usize) {
+        if bitmap.len() <= idx / 64 {
+            bitmap.resize(idx / 64 + 1, 0);
+        }
+        bitmap[idx / 64] |= 1 << (idx % 64);
+    }
+
+    fn get(bitmap: &[u64], idx: usize) -> bool {
+        bitmap
+            .get(idx / 64)
+            .is_some_and(|word| word & (1 << (idx % 64)) != 0)
+    }
+
+    /// Checks whether the column at `idx` was changed.
+    pub fn is_changed(&self, idx: usize) -> bool {
+        Self::get(&self.changed, idx)
+    }
+
+    /// Checks whether the column at `idx` was deleted, i.e. set to null.
+    pub fn is_deleted(&self, idx: usize) -> bool {
+        Self::get(&self.deleted, idx)
+    }
+
+    /// Returns the positions of the changed columns, in ascending order.
+    pub fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        self.changed
+            .iter()
+            .enumerate()
+            .flat_map(|(word_idx, word)| {
+                (0..64)
+                    .filter(move |bit| word & (1 << bit) != 0)
+                    .map(move |bit| word_idx * 64 + bit)
+            })
+    }
+}
+
+/// A row of a CDC log table, with the columns of the base table deserialized into `T`.
+///
+/// The row has to consist of the metadata columns, in the order of the columns
+/// selected by [`select_query`], followed by any number of `cdc$deleted_<column>`
+/// flags and then the columns deserialized into `T`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CdcRow<T> {
+    /// The stream the row belongs to.
+    pub stream_id: Vec<u8>,
+    /// The time of the change. All rows describing a single change have the same time.
+    pub time: CqlTimeuuid,
+    /// The position of the row among the rows describing a single change.
+    pub batch_seq_no: i32,
+    pub operation: OperationType,
+    /// The TTL set by the change, if any.
+    pub ttl: Option<i64>,
+    /// Set on the last row describing a change.
+    pub end_of_batch: bool,
+    pub changed_columns: ChangedColumns,
+    /// The columns of the base table. In delta rows, unchanged columns are null.
+    pub data: T,
+}
+
+impl<'frame, 'metadata, T> DeserializeRow<'frame, 'metadata> for CdcRow<T>
+where
+    T: DeserializeRow<'frame, 'metadata>,
+{
+    fn type_check(specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        for (idx, (name, typ)) in METADATA_COLUMNS.iter().enumerate() {
+            match specs.get(idx) {
+                Some(spec) if spec.name() == *name && spec.typ() == typ => {}
+                spec => {
+                    return Err(TypeCheckError::new(
+                        CdcTypeCheckError::MetadataColumnMismatch {
+                            expected: name,
+                            found: spec.map(|spec| spec.name().to_owned()),
+                        },
+                    ))
+                }
+            }
+        }
+
+        let flags = deleted_flags_count(&specs[METADATA_COLUMNS.len()..]);
+        let (flags, data) = specs[METADATA_COLUMNS.len()..].split_at(flags);
+        for flag in flags {
+            let column = &flag.name()[DELETED_PREFIX.len()..];
+            if flag.typ() != &ColumnType::Boolean {
+                return Err(TypeCheckError::new(
+                    CdcTypeCheckError::DeletedFlagNotBoolean(flag.name().to_owned()),
+                ));
+            }
+            if !data.iter().any(|spec| spec.name() == column) {
+                return Err(TypeCheckError::new(
+                    CdcTypeCheckError::DeletedFlagWithoutColumn(column.to_owned()),
+                ));
+            }
+        }
+        T::type_check(data)
+    }
+
+    fn deserialize(
+        mut row: ColumnIterator<'frame, 'metadata>,
+    ) -> Result<Self, DeserializationError> {
+        let stream_id = next_value(&mut row)?;
+        let time = next_value(&mut row)?;
+        let batch_seq_no = next_value(&mut row)?;
+        let operation = OperationType::try_from(next_value::<i8>(&mut row)?)
+            .map_err(DeserializationError::new)?;
+        let ttl = next_value(&mut row)?;
+        let end_of_batch = next_value::<Option<bool>>(&mut row)?.unwrap_or(false);
+
+        // Names of the tracked columns, and whether they were deleted.
+        let mut flags = Vec::new();
+        while let Some(column) = row.clone().next() {
+            let column = column?;
+            let Some(name) = column.spec.name().strip_prefix(DELETED_PREFIX) else {
+                break;
+            };
+            row.next();
+            flags.push((name, value_of::<Option<bool>>(column)?.unwrap_or(false)));
+        }
+
+        let mut changed_columns = ChangedColumns::default();
+        for (idx, column) in row.clone().enumerate() {
+            let column = column?;
+            let Some((_, deleted)) = flags.iter().find(|(name, _)| *name == column.spec.name())
+            else {
+                continue;
+            };
+            if *deleted {
+                ChangedColumns::set(&mut changed_columns.deleted, idx);
+            }
+            if *deleted || column.slice.is_some() {
+                ChangedColumns::set(&mut changed_columns.changed, idx);
+            }
+        }
+
+        Ok(Self {
+            stream_id,
+            time,
+            batch_seq_no,
+            operation,
+            ttl,
+            end_of_batch,
+            changed_columns,
+            data: T::deserialize(row)?,
+        })
+    }
+}
+
+fn deleted_flags_count(specs: &[ColumnSpec]) -> usize {
+    specs
+        .iter()
+        .take_while(|spec| spec.name().starts_with(DELETED_PREFIX))
+        .count()
+}
+
+fn value_of<'frame, 'metadata, V>(
+    column: RawColumn<'frame, 'metadata>,
+) -> Result<V, DeserializationError>
+where
+    V: DeserializeValue<'frame, 'metadata>,
+{
+    V::deserialize(column.spec.typ(), column.slice)
+}
+
+fn next_value<'frame, 'metadata, V>(
+    row: &mut ColumnIterator<'frame, 'metadata>,
+) -> Result<V, DeserializationError>
+where
+    V: DeserializeValue<'frame, 'metadata>,
+{
+    let column = row
+        .next()
+        .ok_or_else(|| DeserializationError::new(CdcDeserializationError::TooFewColumns))??;
+    value_of(column)
+}
+
+/// All rows of a CDC log table describing a single change,
+/// i.e. having the same stream and time.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct CdcChange<T> {
+    /// The state of the row before the change, if pre-images are enabled.
+    pub preimage: Option<CdcRow<T>>,
+    /// The rows describing the change itself, ordered by their `batch_seq_no`.
+    pub deltas: Vec<CdcRow<T>>,
+    /// The state of the row after the change, if post-images are enabled.
+    pub postimage: Option<CdcRow<T>>,
+}
+
+impl<T> CdcChange<T> {
+    /// The time of the change.
+    pub fn time(&self) -> CqlTimeuuid {
+        self.rows()
+            .next()
+            .expect("a change consists of at least one row")
+            .time
+    }
+
+    /// Iterates over all rows of the change, in the order they were stored in.
+    pub fn rows(&self) -> impl Iterator<Item = &CdcRow<T>> {
+        self.preimage
+            .iter()
+            .chain(&self.deltas)
+            .chain(self.postimage.as_ref())
+    }
+}
+
+/// Groups the rows of a CDC log table, read in the order of their clustering key,
+/// into changes. Consecutive rows with the same stream and time form a single change.
+///
+/// If a change has more than one pre-image or post-image, which happens when
+/// a single batch modifies several rows, each of them starts a new change.
+pub fn group_changes<T>(rows: impl IntoIterator<Item = CdcRow<T>>) -> Vec<CdcChange<T>> {
+    let mut changes: Vec<CdcChange<T>> = Vec::new();
+    for row in rows {
+        let change = match changes.last_mut() {
+            Some(change)
+                if change.rows().next().is_some_and(|first| {
+                    first.stream_id == row.stream_id && first.time == row.time
+                }) && can_append(change, &row) =>
+            {
+                change
+            }
+            _ => {
+                changes.push(CdcChange {
+                    preimage: None,
+                    deltas: Vec::new(),
+                    postimage: None,
+                });
+                changes.last_mut().unwrap()
+            }
+        };
+        match row.operation {
+            OperationType::PreImage => change.preimage = Some(row),
+            OperationType::PostImage => change.postimage = Some(row),
+            _ => change.deltas.push(row),
+        }
+    }
+    changes
+}
+
+// Checks whether the row can be a part of the change, given the order
+// in which the rows are stored: pre-image, deltas, post-image.
+fn can_append<T>(change: &CdcChange<T>, row: &CdcRow<T>) -> bool {
+    if change.postimage.is_some() {
+        return false;
+    }
+    match row.operation {
+        OperationType::PreImage => change.preimage.is_none() && change.deltas.is_empty(),
+        _ => true,
+    }
+}
+
+/// An error returned by type checking of [`CdcRow`].
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum CdcTypeCheckError {
+    /// The row does not start with the expected metadata columns.
+    #[error("Expected the CDC metadata column {expected}, found {found:?}")]
+    MetadataColumnMismatch {
+        expected: &'static str,
+        found: Option<String>,
+    },
+
+    /// A `cdc$deleted_` flag has other type than boolean.
+    #[error("The CDC column {0} is not a boolean")]
+    DeletedFlagNotBoolean(String),
+
+    /// A `cdc$deleted_` flag was selected for a column which was not.
+    #[error("The deletion flag of column {0} was selected, but the column was not")]
+    DeletedFlagWithoutColumn(String),
+}
+
+/// An error returned by deserialization of [`CdcRow`].
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum CdcDeserializationError {
+    /// The row ended before all metadata columns were read.
+    #[error("The row has too few columns")]
+    TooFewColumns,
+
+    /// The `cdc$operation` column holds an unknown operation.
+    #[error("Unknown CDC operation: {0}")]
+    UnknownOperation(i8),
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::BytesMut;
+    use scylla_cql::frame::response::result::{
+        ColumnSpec, ColumnType, RawMetadataAndRawRows, ResultMetadata, TableSpec,
+    };
+    use scylla_cql::frame::types;
+    use scylla_cql::frame::value::CqlTimeuuid;
+    use scylla_macros::DeserializeRow;
+
+    use super::{
+        group_changes, log_table_name, select_query, CdcDeserializationError, CdcRow,
+        CdcTypeCheckError, OperationType,
+    };
+    use crate::test_utils::setup_tracing;
+    use crate::transport::query_result::QueryResult;
+
+    #[derive(DeserializeRow, Debug, Clone, PartialEq, Eq)]
+    #[scylla(crate = crate)]
+    struct Data {
+        pk: i32,
+        a: Option<i32>,
+        b: Option<String>,
+    }
+
+    const TABLE_SPEC: TableSpec<'static> = TableSpec::borrowed("ks", "t_scylla_cdc_log");
+
+    fn specs(flags: &[&str]) -> Vec<ColumnSpec<'static>> {
+        let mut specs = super::METADATA_COLUMNS
+            .iter()
+            .map(|(name, typ)| ColumnSpec::borrowed(name, typ.clone(), TABLE_SPEC))
+            .collect::<Vec<_>>();
+        for flag in flags {
+            specs.push(ColumnSpec::owned(
+                format!("cdc$deleted_{}", flag),
+                ColumnType::Boolean,
+                TABLE_SPEC,
+            ));
+        }
+        specs.push(ColumnSpec::borrowed("pk", ColumnType::Int, TABLE_SPEC));
+        specs.push(ColumnSpec::borrowed("a", ColumnType::Int, TABLE_SPEC));
+        specs.push(ColumnSpec::borrowed("b", ColumnType::Text, TABLE_SPEC));
+        specs
+    }
+
+    struct LogRow {
+        time: u128,
+        seq: i32,
+        operation: i8,
+        deleted: [Option<bool>; 2],
+        a: Option<i32>,
+        b: Option<&'static str>,
+    }
+
+    fn query_result(rows: &[LogRow]) -> QueryResult {
+        let specs = specs(&["a", "b"]);
+        let mut buf = BytesMut::new();
+        for row in rows {
+            let cells: [Option<Vec<u8>>; 11] = [
+                Some(vec![1, 2]),
+                Some(row.time.to_be_bytes().to_vec()),
+                Some(row.seq.to_be_bytes().to_vec()),
+                Some(row.operation.to_be_bytes().to_vec()),
+                None,
+                Some(vec![0]),
+                row.deleted[0].map(|d| vec![d as u8]),
+                row.deleted[1].map(|d| vec![d as u8]),
+                Some(7_i32.to_be_bytes().to_vec()),
+                row.a.map(|a| a.to_be_bytes().to_vec()),
+                row.b.map(|b| b.as_bytes().to_vec()),
+            ];
+            for cell in cells {
+                types::write_bytes_opt(cell, &mut buf).unwrap();
+            }
+        }
+        let metadata = ResultMetadata::new_for_test(specs.len(), specs);
+        let raw_rows =
+            RawMetadataAndRawRows::new_for_test(None, Some(metadata), false, rows.len(), &buf)
+                .unwrap();
+        QueryResult::new(Some(raw_rows), None, Vec::new())
+    }
+
+    fn time(n: u128) -> CqlTimeuuid {
+        CqlTimeuuid::from_bytes(n.to_be_bytes())
+    }
+
+    #[test]
+    fn query_building() {
+        setup_tracing();
+        assert_eq!(
+            select_query(&log_table_name("ks.t"), &["pk"], &["a", "B"]),
+            "SELECT \"cdc$stream_id\", \"cdc$time\", \"cdc$batch_seq_no\", \"cdc$operation\", \
+             \"cdc$ttl\", \"cdc$end_of_batch\", \"cdc$deleted_a\", \"cdc$deleted_B\", \
+             \"pk\", \"a\", \"B\" FROM ks.t_scylla_cdc_log"
+        );
+    }
+
+    #[test]
+    fn row_deserialization() {
+        setup_tracing();
+        let result = query_result(&[
+            LogRow {
+                time: 1,
+                seq: 0,
+                operation: 0,
+                deleted: [None, None],
+                a: Some(1),
+                b: Some("x"),
+            },
+            LogRow {
+                time: 1,
+                seq: 1,
+                operation: 1,
+                deleted: [None, Some(true)],
+                a: Some(2),
+                b: None,
+            },
+            LogRow {
+                time: 1,
+                seq: 2,
+                operation: 9,
+                deleted: [None, None],
+                a: Some(2),
+                b: None,
+            },
+            LogRow {
+                time: 2,
+                seq: 0,
+                operation: 2,
+                deleted: [Some(false), None],
+                a: None,
+                b: Some("y"),
+            },
+        ])
+        .into_rows_result()
+        .unwrap();
+        let rows = result
+            .rows::<CdcRow<Data>>()
+            .unwrap()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+
+        let update = &rows[1];
+        assert_eq!(update.stream_id, [1, 2]);
+        assert_eq!(update.time, time(1));
+        assert_eq!(update.batch_seq_no, 1);
+        assert_eq!(update.operation, OperationType::RowUpdate);
+        assert_eq!(update.ttl, None);
+        assert!(!update.end_of_batch);
+        assert_eq!(
+            update.data,
+            Data {
+                pk: 7,
+                a: Some(2),
+                b: None
+            }
+        );
+        // The key column is not tracked.
+        assert_eq!(update.changed_columns.iter().collect::<Vec<_>>(), [1, 2]);
+        assert!(!update.changed_columns.is_deleted(1));
+        assert!(update.changed_columns.is_deleted(2));
+
+        assert_eq!(rows[3].changed_columns.iter().collect::<Vec<_>>(), [2]);
+
+        let changes = group_changes(rows.clone());
+        assert_eq!(changes.len(), 2);
+        assert_eq!(changes[0].time(), time(1));
+        assert_eq!(changes[0].preimage.as_ref(), Some(&rows[0]));
+        assert_eq!(changes[0].deltas, [rows[1].clone()]);
+        assert_eq!(changes[0].postimage.as_ref(), Some(&rows[2]));
+        assert_eq!(changes[1].preimage, None);
+        assert_eq!(changes[1].deltas, [rows[3].clone()]);
+        assert_eq!(changes[1].rows().count(), 1);
+    }
+
+    #[test]
+    fn type_check_errors() {
+        setup_tracing();
+        let check = |specs: &[ColumnSpec]| {
+            let err = <CdcRow<Data> as super::DeserializeRow>::type_check(specs).unwrap_err();
+            err.downcast_ref::<CdcTypeCheckError>().cloned()
+        };
+
+        let mut specs = specs(&["a"]);
+        assert!(<CdcRow<Data> as super::DeserializeRow>::type_check(&specs).is_ok());
+
+        assert!(matches!(
+            check(&specs[1..]),
+            Some(CdcTypeCheckError::MetadataColumnMismatch { expected: "cdc$stream_id", found: Some(found) })
+                if found == "cdc$time"
+        ));
+
+        specs[6] = ColumnSpec::borrowed("cdc$deleted_c", ColumnType::Boolean, TABLE_SPEC);
+        assert!(matches!(
+            check(&specs),
+            Some(CdcTypeCheckError::DeletedFlagWithoutColumn(column)) if column == "c"
+        ));
+
+        specs[6] = ColumnSpec::borrowed("cdc$deleted_a", ColumnType::Int, TABLE_SPEC);
+        assert!(matches!(
+            check(&specs),
+            Some(CdcTypeCheckError::DeletedFlagNotBoolean(_))
+        ));
+
+        // Errors of the row type are passed through.
+        specs[6] = ColumnSpec::borrowed("cdc$deleted_a", ColumnType::Boolean, TABLE_SPEC);
+        specs.pop();
+        assert!(check(&specs).is_none());
+
+        assert!(matches!(
+            OperationType::try_from(10),
+            Err(CdcDeserializationError::UnknownOperation(10))
+        ));
+    }
+}
@@ -8,10 +8,26 @@ use openssl::{
     error::ErrorStack,
     ssl::{SslContext, SslMethod, SslVerifyMode},
 };
+use thiserror::Error;
 use tracing::warn;
 use uuid::Uuid;
 
 use crate::transport::connection::{ConnectionConfig, SslConfig};
+use crate::transport::errors::NewSessionError;
+
+/// Error that occurred while connecting to the cluster described by a cloud config,
+/// with [`SessionBuilder::build_from_cloud_config`](crate::SessionBuilder::build_from_cloud_config).
+#[non_exhaustive]
+#[derive(Debug, Error)]
+pub enum CloudSessionError {
+    /// The cloud config couldn't be read.
+    #[error(transparent)]
+    Config(#[from] CloudConfigError),
+
+    /// The session couldn't connect to the cluster.
+    #[error(transparent)]
+    NewSession(#[from] NewSessionError),
+}
 
 pub(crate) fn set_ssl_config_for_scylla_cloud_host(
     host_id: Option<Uuid>,
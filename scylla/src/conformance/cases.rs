@@ -0,0 +1,181 @@
+use std::collections::{BTreeMap, BTreeSet};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr};
+
+use scylla_cql::frame::response::result::ColumnType;
+use scylla_cql::frame::value::{
+    CqlDate, CqlDecimal, CqlDuration, CqlTime, CqlTimestamp, CqlTimeuuid, CqlVarint,
+};
+use uuid::Uuid;
+
+use super::ConformanceCase;
+
+/// Returns the cases of all the CQL types supported by the driver,
+/// using the Rust types the driver provides for them.
+///
+/// Each type is checked with one or more values, including the edge cases
+/// of its range, nulls and values nested in collections and tuples.
+///
+/// Counters are not covered, as they can't be set to an arbitrary value.
+pub fn builtin_cases() -> Vec<ConformanceCase> {
+    use ColumnType as T;
+
+    fn list(elem: ColumnType<'static>) -> ColumnType<'static> {
+        T::List(Box::new(elem))
+    }
+
+    vec![
+        ConformanceCase::new("boolean", "boolean", T::Boolean, true),
+        ConformanceCase::new("tinyint_min", "tinyint", T::TinyInt, i8::MIN),
+        ConformanceCase::new("smallint_max", "smallint", T::SmallInt, i16::MAX),
+        ConformanceCase::new("int_min", "int", T::Int, i32::MIN),
+        ConformanceCase::new("int_null", "int", T::Int, None::<i32>),
+        ConformanceCase::new("bigint_max", "bigint", T::BigInt, i64::MAX),
+        ConformanceCase::new("float", "float", T::Float, 1.5_f32),
+        ConformanceCase::new("float_infinity", "float", T::Float, f32::INFINITY),
+        ConformanceCase::new("double_max", "double", T::Double, f64::MAX),
+        ConformanceCase::new(
+            "double_negative_infinity",
+            "double",
+            T::Double,
+            f64::NEG_INFINITY,
+        ),
+        ConformanceCase::new("ascii", "ascii", T::Ascii, "ascii".to_owned()),
+        ConformanceCase::new("text", "text", T::Text, "zażółć gęślą jaźń".to_owned()),
+        ConformanceCase::new("text_empty", "text", T::Text, String::new()),
+        ConformanceCase::new("text_null", "text", T::Text, None::<String>),
+        ConformanceCase::new("blob", "blob", T::Blob, vec![0x00_u8, 0x01, 0xfe, 0xff]),
+        ConformanceCase::new("blob_empty", "blob", T::Blob, Vec::<u8>::new()),
+        ConformanceCase::new(
+            "uuid",
+            "uuid",
+            T::Uuid,
+            Uuid::from_u128(0x6f7c_1f0d_3b4e_4a8e_9d1c_5b2a_7e3f_1c20),
+        ),
+        ConformanceCase::new(
+            "timeuuid",
+            "timeuuid",
+            T::Timeuuid,
+            CqlTimeuuid::from(Uuid::from_u128(0x8e14_e760_7fa8_11eb_bc66_0000_0000_0001)),
+        ),
+        ConformanceCase::new(
+            "inet_v4",
+            "inet",
+            T::Inet,
+            IpAddr::V4(Ipv4Addr::new(192, 168, 0, 1)),
+        ),
+        ConformanceCase::new(
+            "inet_v6",
+            "inet",
+            T::Inet,
+            IpAddr::V6(Ipv6Addr::new(0x2001, 0xdb8, 0, 0, 0, 0, 0, 1)),
+        ),
+        ConformanceCase::new("date_epoch", "date", T::Date, CqlDate(1 << 31)),
+        ConformanceCase::new("date", "date", T::Date, CqlDate((1 << 31) + 19_723)),
+        ConformanceCase::new(
+            "time_max",
+            "time",
+            T::Time,
+            CqlTime(24 * 60 * 60 * 1_000_000_000 - 1),
+        ),
+        ConformanceCase::new(
+            "timestamp",
+            "timestamp",
+            T::Timestamp,
+            CqlTimestamp(1_700_000_000_123),
+        ),
+        ConformanceCase::new(
+            "timestamp_before_epoch",
+            "timestamp",
+            T::Timestamp,
+            CqlTimestamp(-1),
+        ),
+        ConformanceCase::new(
+            "duration",
+            "duration",
+            T::Duration,
+            CqlDuration {
+                months: 1,
+                days: 2,
+                nanoseconds: 3_000_000_000,
+            },
+        ),
+        ConformanceCase::new(
+            "duration_negative",
+            "duration",
+            T::Duration,
+            CqlDuration {
+                months: -1,
+                days: -2,
+                nanoseconds: -3,
+            },
+        ),
+        ConformanceCase::new(
+            "varint_large",
+            "varint",
+            T::Varint,
+            // 2^64
+            CqlVarint::from_signed_bytes_be(vec![0x01, 0, 0, 0, 0, 0, 0, 0, 0]),
+        ),
+        ConformanceCase::new(
+            "varint_negative",
+            "varint",
+            T::Varint,
+            // -129
+            CqlVarint::from_signed_bytes_be(vec![0xff, 0x7f]),
+        ),
+        ConformanceCase::new(
+            "decimal",
+            "decimal",
+            T::Decimal,
+            // 123.45
+            CqlDecimal::from_signed_be_bytes_and_exponent(vec![0x30, 0x39], 2),
+        ),
+        ConformanceCase::new(
+            "decimal_negative",
+            "decimal",
+            T::Decimal,
+            // -123.45
+            CqlDecimal::from_signed_be_bytes_and_exponent(vec![0xcf, 0xc7], 2),
+        ),
+        ConformanceCase::new("list", "list<int>", list(T::Int), vec![3_i32, 1, 2]),
+        ConformanceCase::new(
+            "frozen_list",
+            "frozen<list<text>>",
+            list(T::Text),
+            vec!["a".to_owned(), "b".to_owned()],
+        ),
+        ConformanceCase::new(
+            "set",
+            "set<text>",
+            T::Set(Box::new(T::Text)),
+            BTreeSet::from(["x".to_owned(), "y".to_owned()]),
+        ),
+        ConformanceCase::new(
+            "map",
+            "map<int, text>",
+            T::Map(Box::new(T::Int), Box::new(T::Text)),
+            BTreeMap::from([(-1_i32, "minus one".to_owned()), (1, "one".to_owned())]),
+        ),
+        ConformanceCase::new(
+            "map_of_lists",
+            "map<text, frozen<list<bigint>>>",
+            T::Map(Box::new(T::Text), Box::new(list(T::BigInt))),
+            BTreeMap::from([("a".to_owned(), vec![1_i64, 2]), ("b".to_owned(), vec![])]),
+        ),
+        ConformanceCase::new(
+            "tuple",
+            "frozen<tuple<int, text, boolean>>",
+            T::Tuple(vec![T::Int, T::Text, T::Boolean]),
+            (7_i32, "seven".to_owned(), None::<bool>),
+        ),
+        ConformanceCase::new(
+            "list_of_tuples",
+            "list<frozen<tuple<int, text>>>",
+            list(T::Tuple(vec![T::Int, T::Text])),
+            vec![(1_i32, "one".to_owned()), (2, "two".to_owned())],
+        ),
+    ]
+    .into_iter()
+    .collect::<Result<_, _>>()
+    .expect("The names of the builtin cases are valid")
+}
@@ -0,0 +1,178 @@
+use std::collections::BTreeMap;
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use thiserror::Error;
+
+const NULL: &str = "null";
+
+/// Values of [`ConformanceCase`](super::ConformanceCase)s, identified by the names
+/// of the cases, as returned by a server in a [run](super::ConformanceSuite::run).
+///
+/// The corpus can be saved in a text format, produced by the `Display`
+/// implementation and parsed by the `FromStr` implementation.
+/// Each line holds the name of a case and its value, either hex-encoded
+/// with the `0x` prefix or `null`, separated by a space. Empty lines and lines starting
+/// with `#` are ignored.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RecordedCorpus {
+    values: BTreeMap<String, Option<Vec<u8>>>,
+}
+
+impl RecordedCorpus {
+    /// Creates an empty corpus.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Records the value of a case, replacing the previously recorded one.
+    pub fn insert(&mut self, case: impl Into<String>, value: Option<Vec<u8>>) {
+        self.values.insert(case.into(), value);
+    }
+
+    /// Returns the value of a case, or `None` if it wasn't recorded.
+    pub fn get(&self, case: &str) -> Option<Option<&[u8]>> {
+        self.values.get(case).map(Option::as_deref)
+    }
+
+    /// Returns the number of recorded values.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns whether no values were recorded.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+}
+
+impl Display for RecordedCorpus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (case, value) in &self.values {
+            write!(f, "{} ", case)?;
+            match value {
+                Some(bytes) => {
+                    f.write_str("0x")?;
+                    for byte in bytes {
+                        write!(f, "{:02x}", byte)?;
+                    }
+                }
+                None => f.write_str(NULL)?,
+            }
+            writeln!(f)?;
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for RecordedCorpus {
+    type Err = CorpusParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut corpus = Self::new();
+        for (idx, line) in s.lines().enumerate() {
+            let line_number = idx + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let Some((case, value)) = line.split_once(' ') else {
+                return Err(CorpusParseError::MissingValue { line: line_number });
+            };
+            let value = match value.trim() {
+                NULL => None,
+                value => Some(
+                    value
+                        .strip_prefix("0x")
+                        .and_then(decode_hex)
+                        .ok_or(CorpusParseError::InvalidValue { line: line_number })?,
+                ),
+            };
+            if corpus.values.insert(case.to_owned(), value).is_some() {
+                return Err(CorpusParseError::DuplicateCase {
+                    line: line_number,
+                    case: case.to_owned(),
+                });
+            }
+        }
+        Ok(corpus)
+    }
+}
+
+fn decode_hex(hex: &str) -> Option<Vec<u8>> {
+    if hex.len() % 2 != 0 || !hex.bytes().all(|c| c.is_ascii_hexdigit()) {
+        return None;
+    }
+    hex.as_bytes()
+        .chunks(2)
+        .map(|byte| u8::from_str_radix(std::str::from_utf8(byte).ok()?, 16).ok())
+        .collect()
+}
+
+/// An error returned when parsing a [`RecordedCorpus`].
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CorpusParseError {
+    /// The line holds only the name of a case.
+    #[error("Line {line}: missing the value of the case")]
+    MissingValue { line: usize },
+
+    /// The value is neither `null` nor a hex-encoded string with the `0x` prefix.
+    #[error("Line {line}: the value is neither null nor a hex-encoded string")]
+    InvalidValue { line: usize },
+
+    /// The case was already recorded in one of the previous lines.
+    #[error("Line {line}: duplicate case {case}")]
+    DuplicateCase { line: usize, case: String },
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CorpusParseError, RecordedCorpus};
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn corpus_text_format() {
+        setup_tracing();
+        let mut corpus = RecordedCorpus::new();
+        corpus.insert("int", Some(vec![0x00, 0x00, 0x00, 0x2a]));
+        corpus.insert("blob_empty", Some(vec![]));
+        corpus.insert("int_null", None);
+
+        let text = corpus.to_string();
+        assert_eq!(text, "blob_empty 0x\nint 0x0000002a\nint_null null\n");
+        assert_eq!(text.parse::<RecordedCorpus>().unwrap(), corpus);
+        assert_eq!(corpus.get("int"), Some(Some(&[0x00, 0x00, 0x00, 0x2a][..])));
+        assert_eq!(corpus.get("int_null"), Some(None));
+        assert_eq!(corpus.get("bigint"), None);
+
+        let commented = "# Recorded from Scylla 6.2\n\nint 0x0000002A\n";
+        let parsed = commented.parse::<RecordedCorpus>().unwrap();
+        assert_eq!(parsed.len(), 1);
+        assert_eq!(parsed.get("int"), corpus.get("int"));
+    }
+
+    #[test]
+    fn corpus_parse_errors() {
+        setup_tracing();
+        assert_eq!(
+            "int".parse::<RecordedCorpus>(),
+            Err(CorpusParseError::MissingValue { line: 1 })
+        );
+        assert_eq!(
+            "int 0x00\nbigint 0x0g".parse::<RecordedCorpus>(),
+            Err(CorpusParseError::InvalidValue { line: 2 })
+        );
+        assert_eq!(
+            "int 000".parse::<RecordedCorpus>(),
+            Err(CorpusParseError::InvalidValue { line: 1 })
+        );
+        assert_eq!(
+            "int 0x00\nint null".parse::<RecordedCorpus>(),
+            Err(CorpusParseError::DuplicateCase {
+                line: 2,
+                case: "int".to_owned()
+            })
+        );
+    }
+}
@@ -0,0 +1,554 @@
+//! Wire compatibility tests of serialization and deserialization.
+//!
+//! A [`ConformanceSuite`] consists of cases, each holding a value of some Rust type
+//! together with the CQL type it is stored as. Running the suite against a live
+//! server writes every value to its own column of a table, reads it back and checks that
+//! the value deserialized from the server's response is equal to the original one.
+//! The suite covers all the CQL types supported by the driver (see [`builtin_cases`]),
+//! and can be extended with cases for custom implementations of
+//! [`SerializeValue`] and [`DeserializeValue`].
+//!
+//! The values returned by the server can be saved as a [`RecordedCorpus`],
+//! and verified later without a server, e.g. in the CI of an application
+//! targeting several Scylla/Cassandra versions, or after upgrading the driver.
+//!
+//! ```rust
+//! # use scylla::Session;
+//! # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+//! use scylla::conformance::{ConformanceCase, ConformanceSuite, RecordedCorpus};
+//! use scylla::frame::response::result::ColumnType;
+//!
+//! let mut suite = ConformanceSuite::with_builtin_cases();
+//! suite.add_case(ConformanceCase::new(
+//!     "scores",
+//!     "frozen<list<int>>",
+//!     ColumnType::List(Box::new(ColumnType::Int)),
+//!     vec![1, 2, 3],
+//! )?);
+//!
+//! // Against a live server.
+//! let report = suite.run(session, "ks").await?;
+//! assert!(report.is_success(), "{}", report);
+//! std::fs::write("corpus.txt", report.corpus().to_string())?;
+//!
+//! // Offline, against the recorded values.
+//! let corpus: RecordedCorpus = std::fs::read_to_string("corpus.txt")?.parse()?;
+//! let report = suite.verify_recorded(&corpus);
+//! assert!(report.is_success(), "{}", report);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::{self, Debug, Display};
+
+use scylla_cql::frame::response::result::ColumnType;
+use scylla_cql::types::deserialize::value::DeserializeValue;
+use scylla_cql::types::deserialize::{DeserializationError, FrameSlice, TypeCheckError};
+use scylla_cql::types::serialize::value::SerializeValue;
+use scylla_cql::types::serialize::writers::CellWriter;
+use scylla_cql::types::serialize::SerializationError;
+use thiserror::Error;
+
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::{IntoRowsResultError, SingleRowError};
+use crate::Session;
+
+mod cases;
+mod corpus;
+pub use cases::builtin_cases;
+pub use corpus::{CorpusParseError, RecordedCorpus};
+
+/// The name of the table created by [`ConformanceSuite::run`].
+pub const TABLE_NAME: &str = "wire_conformance";
+
+// A value of a case, with its Rust type erased.
+trait CaseValue: Send + Sync {
+    fn as_serialize_value(&self) -> &dyn SerializeValue;
+
+    // Deserializes the value from `bytes` and compares it to the original value.
+    fn check(&self, typ: &ColumnType, bytes: Option<&[u8]>) -> Result<(), ConformanceFailure>;
+}
+
+struct TypedCaseValue<T>(T);
+
+impl<T> CaseValue for TypedCaseValue<T>
+where
+    T: SerializeValue
+        + for<'frame, 'metadata> DeserializeValue<'frame, 'metadata>
+        + PartialEq
+        + Debug
+        + Send
+        + Sync,
+{
+    fn as_serialize_value(&self) -> &dyn SerializeValue {
+        &self.0
+    }
+
+    fn check(&self, typ: &ColumnType, bytes: Option<&[u8]>) -> Result<(), ConformanceFailure> {
+        T::type_check(typ).map_err(ConformanceFailure::TypeCheck)?;
+        let bytes = bytes.map(bytes::Bytes::copy_from_slice);
+        let value = T::deserialize(typ, bytes.as_ref().map(FrameSlice::new))
+            .map_err(ConformanceFailure::Deserialization)?;
+        if value != self.0 {
+            return Err(ConformanceFailure::Mismatch {
+                expected: format!("{:?}", self.0),
+                actual: format!("{:?}", value),
+            });
+        }
+        Ok(())
+    }
+}
+
+/// A single value checked by a [`ConformanceSuite`].
+pub struct ConformanceCase {
+    name: String,
+    cql_type: String,
+    typ: ColumnType<'static>,
+    value: Box<dyn CaseValue>,
+}
+
+impl ConformanceCase {
+    /// Creates a case of `value` stored in a column of `cql_type`.
+    ///
+    /// `typ` must describe the same type as `cql_type`. It is used to verify
+    /// the case against a [`RecordedCorpus`], without a server.
+    ///
+    /// User defined types are not created by the suite, so the cases of UDTs
+    /// require the type to exist in the keyspace the suite is run in.
+    ///
+    /// Returns an error if `name` is empty or contains whitespace, as it is
+    /// the identifier of the case in a [`RecordedCorpus`].
+    pub fn new<T>(
+        name: impl Into<String>,
+        cql_type: impl Into<String>,
+        typ: ColumnType<'static>,
+        value: T,
+    ) -> Result<Self, InvalidCaseNameError>
+    where
+        T: SerializeValue
+            + for<'frame, 'metadata> DeserializeValue<'frame, 'metadata>
+            + PartialEq
+            + Debug
+            + Send
+            + Sync
+            + 'static,
+    {
+        let name = name.into();
+        if name.is_empty() || name.contains(char::is_whitespace) {
+            return Err(InvalidCaseNameError { name });
+        }
+        Ok(Self {
+            name,
+            cql_type: cql_type.into(),
+            typ,
+            value: Box::new(TypedCaseValue(value)),
+        })
+    }
+
+    /// Returns the name of the case.
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Returns the CQL type of the column the value is stored in.
+    pub fn cql_type(&self) -> &str {
+        &self.cql_type
+    }
+
+    // Checks that the value still serializes, and that the recorded bytes
+    // deserialize to the value.
+    fn verify_recorded(&self, bytes: Option<&[u8]>) -> Result<(), ConformanceFailure> {
+        let mut buf = Vec::new();
+        self.value
+            .as_serialize_value()
+            .serialize(&self.typ, CellWriter::new(&mut buf))
+            .map_err(ConformanceFailure::Serialization)?;
+        self.value.check(&self.typ, bytes)
+    }
+}
+
+impl Debug for ConformanceCase {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ConformanceCase")
+            .field("name", &self.name)
+            .field("cql_type", &self.cql_type)
+            .field("typ", &self.typ)
+            .finish_non_exhaustive()
+    }
+}
+
+/// A set of [`ConformanceCase`]s.
+#[derive(Debug, Default)]
+pub struct ConformanceSuite {
+    cases: Vec<ConformanceCase>,
+}
+
+impl ConformanceSuite {
+    /// Creates a suite without any cases.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Creates a suite with the cases returned by [`builtin_cases`].
+    pub fn with_builtin_cases() -> Self {
+        Self {
+            cases: builtin_cases(),
+        }
+    }
+
+    /// Adds a case to the suite.
+    pub fn add_case(&mut self, case: ConformanceCase) {
+        self.cases.push(case);
+    }
+
+    /// Returns the cases of the suite.
+    pub fn cases(&self) -> &[ConformanceCase] {
+        &self.cases
+    }
+
+    /// Runs the suite against a live server.
+    ///
+    /// The table [`TABLE_NAME`] is dropped and recreated in `keyspace`.
+    /// Each case adds its own column to the table, its value is inserted
+    /// with a prepared statement and selected back, and the value returned
+    /// by the server is recorded in the [corpus](ConformanceReport::corpus) of the report.
+    ///
+    /// Returns an error only if the table could not be created. A failure
+    /// of a single case, e.g. because its type is not supported by the server,
+    /// is reported in its [outcome](CaseOutcome) and doesn't affect the other cases.
+    pub async fn run(
+        &self,
+        session: &Session,
+        keyspace: &str,
+    ) -> Result<ConformanceReport, QueryError> {
+        let table = format!("{}.{}", keyspace, TABLE_NAME);
+        session
+            .query_unpaged(format!("DROP TABLE IF EXISTS {}", table), &[])
+            .await?;
+        session
+            .query_unpaged(format!("CREATE TABLE {} (pk int PRIMARY KEY)", table), &[])
+            .await?;
+
+        let mut report = ConformanceReport::default();
+        for (idx, case) in self.cases.iter().enumerate() {
+            let result = run_case(session, &table, idx, case, &mut report.corpus).await;
+            report.push(case, result);
+        }
+        Ok(report)
+    }
+
+    /// Verifies the suite against the values recorded by a previous [run](Self::run).
+    ///
+    /// Each value must still serialize to its CQL type, and the recorded bytes
+    /// must deserialize to the value.
+    pub fn verify_recorded(&self, corpus: &RecordedCorpus) -> ConformanceReport {
+        let mut report = ConformanceReport::default();
+        for case in &self.cases {
+            let result = match corpus.get(&case.name) {
+                Some(bytes) => case.verify_recorded(bytes),
+                None => Err(ConformanceFailure::NotRecorded),
+            };
+            report.push(case, result);
+        }
+        report
+    }
+}
+
+async fn run_case(
+    session: &Session,
+    table: &str,
+    idx: usize,
+    case: &ConformanceCase,
+    corpus: &mut RecordedCorpus,
+) -> Result<(), ConformanceFailure> {
+    // The number of cases is far from overflowing an int.
+    let pk = idx as i32;
+    session
+        .query_unpaged(
+            format!("ALTER TABLE {} ADD v{} {}", table, idx, case.cql_type),
+            &[],
+        )
+        .await?;
+    let insert = session
+        .prepare(format!(
+            "INSERT INTO {} (pk, v{}) VALUES (?, ?)",
+            table, idx
+        ))
+        .await?;
+    session
+        .execute_unpaged(&insert, (pk, case.value.as_serialize_value()))
+        .await?;
+
+    let result = session
+        .query_unpaged(
+            format!("SELECT v{} FROM {} WHERE pk = ?", idx, table),
+            (pk,),
+        )
+        .await?
+        .into_rows_result()?;
+    let (raw,) = result.single_row::<(RawValue,)>()?;
+    corpus.insert(case.name.clone(), raw.bytes.map(<[u8]>::to_vec));
+    case.value.check(raw.typ, raw.bytes)
+}
+
+// A value as returned by the server, along with its type.
+struct RawValue<'frame, 'metadata> {
+    typ: &'metadata ColumnType<'metadata>,
+    bytes: Option<&'frame [u8]>,
+}
+
+impl<'frame, 'metadata> DeserializeValue<'frame, 'metadata> for RawValue<'frame, 'metadata> {
+    fn type_check(_typ: &ColumnType) -> Result<(), TypeCheckError> {
+        Ok(())
+    }
+
+    fn deserialize(
+        typ: &'metadata ColumnType<'metadata>,
+        v: Option<FrameSlice<'frame>>,
+    ) -> Result<Self, DeserializationError> {
+        Ok(Self {
+            typ,
+            bytes: v.map(|slice| slice.as_slice()),
+        })
+    }
+}
+
+/// The reason of a failure of a [`ConformanceCase`].
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum ConformanceFailure {
+    /// Executing one of the statements failed, e.g. because the server
+    /// doesn't support the type of the case, the value could not be serialized,
+    /// or the server rejected it.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// The select statement returned a result of other kind than Rows.
+    #[error("Failed to convert the result into rows result: {0}")]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// The select statement didn't return a single row.
+    #[error("Failed to read the row: {0}")]
+    SingleRowError(#[from] SingleRowError),
+
+    /// The value could not be serialized to its CQL type.
+    #[error("Failed to serialize the value: {0}")]
+    Serialization(SerializationError),
+
+    /// The Rust type is not compatible with the CQL type.
+    #[error("Failed to type check the value: {0}")]
+    TypeCheck(TypeCheckError),
+
+    /// The value returned by the server could not be deserialized.
+    #[error("Failed to deserialize the value: {0}")]
+    Deserialization(DeserializationError),
+
+    /// The value returned by the server is different from the original value.
+    #[error("Expected {expected}, got {actual}")]
+    Mismatch { expected: String, actual: String },
+
+    /// The corpus doesn't contain a value of the case.
+    #[error("The value is missing from the corpus")]
+    NotRecorded,
+}
+
+/// An error returned by [`ConformanceCase::new`] when the name of the case is invalid.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error("Invalid conformance case name {name:?}: it must be non-empty and contain no whitespace")]
+pub struct InvalidCaseNameError {
+    /// The rejected name.
+    pub name: String,
+}
+
+/// The result of a single [`ConformanceCase`].
+#[derive(Debug, Clone)]
+pub struct CaseOutcome {
+    /// The name of the case.
+    pub name: String,
+    /// The CQL type of the case.
+    pub cql_type: String,
+    /// The result of the case.
+    pub result: Result<(), ConformanceFailure>,
+}
+
+/// The results of all the cases of a [`ConformanceSuite`].
+///
+/// The `Display` implementation lists the failed cases.
+#[derive(Debug, Clone, Default)]
+pub struct ConformanceReport {
+    outcomes: Vec<CaseOutcome>,
+    corpus: RecordedCorpus,
+}
+
+impl ConformanceReport {
+    fn push(&mut self, case: &ConformanceCase, result: Result<(), ConformanceFailure>) {
+        self.outcomes.push(CaseOutcome {
+            name: case.name.clone(),
+            cql_type: case.cql_type.clone(),
+            result,
+        });
+    }
+
+    /// Returns whether all the cases succeeded.
+    pub fn is_success(&self) -> bool {
+        self.outcomes.iter().all(|outcome| outcome.result.is_ok())
+    }
+
+    /// Returns the outcomes of all the cases, in the order of the suite.
+    pub fn outcomes(&self) -> &[CaseOutcome] {
+        &self.outcomes
+    }
+
+    /// Returns the outcomes of the failed cases.
+    pub fn failures(&self) -> impl Iterator<Item = &CaseOutcome> {
+        self.outcomes
+            .iter()
+            .filter(|outcome| outcome.result.is_err())
+    }
+
+    /// Returns the values returned by the server in a [run](ConformanceSuite::run).
+    ///
+    /// It is empty in reports of [`ConformanceSuite::verify_recorded`].
+    pub fn corpus(&self) -> &RecordedCorpus {
+        &self.corpus
+    }
+}
+
+impl Display for ConformanceReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let failures = self.failures().count();
+        write!(
+            f,
+            "{} of {} conformance cases failed",
+            failures,
+            self.outcomes.len()
+        )?;
+        for outcome in self.failures() {
+            if let Err(err) = &outcome.result {
+                write!(f, "\n{} ({}): {}", outcome.name, outcome.cql_type, err)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use scylla_cql::frame::response::result::ColumnType;
+    use scylla_cql::types::serialize::writers::CellWriter;
+
+    use super::{
+        ConformanceCase, ConformanceFailure, ConformanceSuite, InvalidCaseNameError, RecordedCorpus,
+    };
+    use crate::test_utils::{
+        create_new_session_builder, setup_tracing, unique_keyspace_name, PerformDDL,
+    };
+
+    // Records the values as serialized by the driver, which is what
+    // the server returns for all the builtin cases.
+    fn record_locally(suite: &ConformanceSuite) -> RecordedCorpus {
+        let mut corpus = RecordedCorpus::new();
+        for case in suite.cases() {
+            let mut buf = Vec::new();
+            case.value
+                .as_serialize_value()
+                .serialize(&case.typ, CellWriter::new(&mut buf))
+                .unwrap();
+            // Strip the length of the value.
+            let len = i32::from_be_bytes(buf[..4].try_into().unwrap());
+            let bytes = (len >= 0).then(|| buf[4..].to_vec());
+            corpus.insert(case.name(), bytes);
+        }
+        corpus
+    }
+
+    #[test]
+    fn builtin_cases_verify_against_their_serialization() {
+        setup_tracing();
+        let suite = ConformanceSuite::with_builtin_cases();
+        let corpus = record_locally(&suite);
+        let corpus = corpus.to_string().parse().unwrap();
+
+        let report = suite.verify_recorded(&corpus);
+        assert!(report.is_success(), "{}", report);
+        assert_eq!(report.outcomes().len(), suite.cases().len());
+    }
+
+    #[test]
+    fn failures_are_reported() {
+        setup_tracing();
+        let mut suite = ConformanceSuite::new();
+        suite.add_case(ConformanceCase::new("a", "int", ColumnType::Int, 1_i32).unwrap());
+        suite.add_case(ConformanceCase::new("b", "int", ColumnType::Int, 2_i32).unwrap());
+        suite.add_case(ConformanceCase::new("c", "text", ColumnType::Text, 3_i32).unwrap());
+        suite.add_case(ConformanceCase::new("d", "int", ColumnType::Int, 4_i32).unwrap());
+
+        let mut corpus = RecordedCorpus::new();
+        corpus.insert("a", Some(1_i32.to_be_bytes().to_vec()));
+        corpus.insert("b", Some(5_i32.to_be_bytes().to_vec()));
+        corpus.insert("c", Some(3_i32.to_be_bytes().to_vec()));
+
+        let report = suite.verify_recorded(&corpus);
+        assert!(!report.is_success());
+        let failures = report
+            .failures()
+            .map(|outcome| (outcome.name.as_str(), outcome.result.clone().unwrap_err()))
+            .collect::<Vec<_>>();
+        assert_eq!(failures.len(), 3);
+        assert!(matches!(
+            &failures[0],
+            ("b", ConformanceFailure::Mismatch { expected, actual }) if expected == "2" && actual == "5"
+        ));
+        assert!(matches!(
+            failures[1],
+            ("c", ConformanceFailure::Serialization(_))
+        ));
+        assert!(matches!(
+            failures[2],
+            ("d", ConformanceFailure::NotRecorded)
+        ));
+    }
+
+    #[test]
+    fn invalid_case_names() {
+        setup_tracing();
+        for name in ["", "my case", "case\n"] {
+            let err = ConformanceCase::new(name, "int", ColumnType::Int, 1_i32).unwrap_err();
+            assert_eq!(
+                err,
+                InvalidCaseNameError {
+                    name: name.to_owned()
+                }
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn run_against_server() {
+        setup_tracing();
+        let session = create_new_session_builder().build().await.unwrap();
+        let ks = unique_keyspace_name();
+        session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+
+        let mut suite = ConformanceSuite::with_builtin_cases();
+        // The server rejects the column of this case, which must not affect the other cases.
+        suite.add_case(
+            ConformanceCase::new("unknown_type", "no_such_type", ColumnType::Int, 1_i32).unwrap(),
+        );
+
+        let report = suite.run(&session, &ks).await.unwrap();
+        assert_eq!(report.outcomes().len(), suite.cases().len());
+        let failures = report.failures().collect::<Vec<_>>();
+        assert_eq!(failures.len(), 1, "{}", report);
+        assert_eq!(failures[0].name, "unknown_type");
+        assert!(matches!(
+            failures[0].result,
+            Err(ConformanceFailure::QueryError(_))
+        ));
+
+        // The values returned by the server verify without it.
+        let corpus = report.corpus().to_string().parse().unwrap();
+        let report = ConformanceSuite::with_builtin_cases().verify_recorded(&corpus);
+        assert!(report.is_success(), "{}", report);
+    }
+}
@@ -103,10 +103,10 @@ pub mod macros;
 pub use macros::*;
 
 pub mod frame {
-    pub use scylla_cql::frame::{frame_errors, value, Authenticator, Compression};
+    pub use scylla_cql::frame::{frame_errors, value, Authenticator, Compression, ProtocolVersion};
     pub(crate) use scylla_cql::frame::{
-        parse_response_body_extensions, protocol_features, read_response_frame, request,
-        server_event_type, FrameParams, SerializedRequest,
+        parse_response_body_extensions, protocol_features, read_response_frame_with_limit, request,
+        segment, server_event_type, FrameParams, ResponseFrameBody, SerializedRequest,
     };
 
     pub mod types {
@@ -123,7 +123,7 @@ pub mod frame {
 
             pub(crate) use scylla_cql::frame::response::result::*;
             pub use scylla_cql::frame::response::result::{
-                ColumnSpec, ColumnType, CqlValue, PartitionKeyIndex, Row, TableSpec,
+                ColumnIndex, ColumnSpec, ColumnType, CqlValue, PartitionKeyIndex, Row, TableSpec,
             };
         }
     }
@@ -196,12 +196,29 @@ pub mod serialize {
         };
     }
 
+    /// Limits on the size of serialized values, checked before sending requests.
+    pub mod limits {
+        pub use scylla_cql::types::serialize::limits::{ValueSizeLimitError, ValueSizeLimits};
+    }
+
     /// Contains types and traits used for safe serialization of values for a CQL statement.
     pub mod writers {
         pub use scylla_cql::types::serialize::writers::{
             CellOverflowError, CellValueBuilder, CellWriter, RowWriter, WrittenCellProof,
         };
     }
+
+    /// Serializing any type implementing `serde::Serialize` as a CQL value.
+    #[cfg(feature = "serde")]
+    pub mod serde_value {
+        pub use scylla_cql::types::serialize::serde_value::{SerdeValue, SerdeValueError};
+    }
+
+    /// Serializing any type implementing `serde::Serialize` as a row of values.
+    #[cfg(feature = "serde")]
+    pub mod serde_row {
+        pub use scylla_cql::types::serialize::serde_row::{SerdeRow, SerdeRowError};
+    }
 }
 
 /// Deserializing DB response containing CQL query results.
@@ -210,9 +227,14 @@ pub mod deserialize {
         DeserializationError, DeserializeRow, DeserializeValue, FrameSlice, TypeCheckError,
     };
 
+    /// Auditing query results for empty values.
+    pub mod audit {
+        pub use scylla_cql::types::deserialize::audit::{ColumnAudit, EmptyValueAudit};
+    }
+
     /// Deserializing the whole query result contents.
     pub mod result {
-        pub use scylla_cql::types::deserialize::result::TypedRowIterator;
+        pub use scylla_cql::types::deserialize::result::{OwnedTypedRowIterator, TypedRowIterator};
     }
 
     /// Deserializing a row of the query result.
@@ -227,10 +249,27 @@ pub mod deserialize {
     pub mod value {
         pub use scylla_cql::types::deserialize::value::{
             BuiltinDeserializationError, BuiltinDeserializationErrorKind, BuiltinTypeCheckError,
-            BuiltinTypeCheckErrorKind, Emptiable, ListlikeIterator, MapDeserializationErrorKind,
-            MapIterator, MapTypeCheckErrorKind, MaybeEmpty, SetOrListDeserializationErrorKind,
+            BuiltinTypeCheckErrorKind, Custom, CustomTypeDeserializer, Emptiable, EmptyAsDefault,
+            EmptyAsNone, ListlikeIterator, MapDeserializationErrorKind, MapIterator,
+            MapTypeCheckErrorKind, MaybeEmpty, Parsed, SetOrListDeserializationErrorKind,
             SetOrListTypeCheckErrorKind, TupleDeserializationErrorKind, TupleTypeCheckErrorKind,
-            UdtIterator, UdtTypeCheckErrorKind,
+            UdtIterator, UdtTypeCheckErrorKind, Via,
+        };
+    }
+
+    /// Deserializing a CQL value into any type implementing `serde::Deserialize`.
+    #[cfg(feature = "serde")]
+    pub mod serde_value {
+        pub use scylla_cql::types::deserialize::serde_value::{
+            SerdeBridgeError, SerdeValue, SerdeValueError,
+        };
+    }
+
+    /// Deserializing a row into any type implementing `serde::Deserialize`.
+    #[cfg(feature = "serde")]
+    pub mod serde_row {
+        pub use scylla_cql::types::deserialize::serde_row::{
+            RowDeserializer, SerdeRow, SerdeRowError,
         };
     }
 
@@ -252,10 +291,14 @@ pub mod deserialize {
 }
 
 pub mod authentication;
+pub mod cdc;
 #[cfg(feature = "cloud")]
 pub mod cloud;
+#[cfg(feature = "conformance")]
+pub mod conformance;
 
 pub mod history;
+pub mod recipes;
 pub mod routing;
 pub mod statement;
 pub mod tracing;
@@ -274,13 +317,18 @@ pub use statement::query;
 pub use frame::response::cql_to_rust::{self, FromRow};
 
 #[allow(deprecated)]
-pub use transport::caching_session::{CachingSession, GenericCachingSession, LegacyCachingSession};
+pub use transport::caching_session::{
+    CachingSession, DefaultPreparedCacheKeyPolicy, GenericCachingSession, LegacyCachingSession,
+    PreparedCacheConfig, PreparedCacheKey, PreparedCacheKeyPolicy, PreparedCacheMetrics,
+};
 pub use transport::execution_profile::ExecutionProfile;
 #[allow(deprecated)]
 pub use transport::legacy_query_result::LegacyQueryResult;
-pub use transport::query_result::{QueryResult, QueryRowsResult};
+pub use transport::query_result::{LwtResult, QueryResult, QueryRowsResult};
 #[allow(deprecated)]
-pub use transport::session::{IntoTypedRows, LegacySession, Session, SessionConfig};
+pub use transport::session::{
+    IntoTypedRows, LegacySession, QueryPlanInfo, SchemaVersions, Session, SessionConfig,
+};
 pub use transport::session_builder::SessionBuilder;
 
 #[cfg(feature = "cloud")]
@@ -292,4 +340,4 @@ pub use transport::load_balancing;
 pub use transport::retry_policy;
 pub use transport::speculative_execution;
 
-pub use transport::metrics::{Metrics, MetricsError};
+pub use transport::metrics::{LatencySummary, Metrics, MetricsError};
@@ -40,7 +40,8 @@ pub use scylla_cql::macros::IntoUserType;
 /// Derive macro for the [`SerializeValue`](crate::serialize::value::SerializeValue) trait
 /// which serializes given Rust structure as a User Defined Type (UDT).
 ///
-/// At the moment, only structs with named fields are supported.
+/// At the moment, only structs with named fields are supported, apart from
+/// fieldless enums (see [below](#enums)) and `transparent` newtypes.
 ///
 /// Serialization will fail if there are some fields in the Rust struct that don't match
 /// to any of the UDT fields.
@@ -135,6 +136,38 @@ pub use scylla_cql::macros::IntoUserType;
 /// Forces Rust struct to have all the fields present in UDT, otherwise
 /// serialization fails.
 ///
+/// `#[scylla(udt_name = "name")]`, `#[scylla(keyspace = "keyspace_name")]`
+///
+/// By default, any UDT with matching fields is accepted. With these
+/// attributes, type checking also verifies the name of the UDT and/or
+/// the keyspace it is defined in, and fails if they differ, e.g.
+/// `#[scylla(udt_name = "address", keyspace = "ks")]`.
+///
+/// `#[scylla(rename_all = "case_convention")]`
+///
+/// Serializes the fields to the UDT fields named like the Rust fields
+/// converted to the given case convention. Fields with `#[scylla(rename)]`
+/// keep their explicit names.
+/// Possible conventions are `"lowercase"`, `"UPPERCASE"`, `"PascalCase"`,
+/// `"camelCase"`, `"snake_case"`, `"SCREAMING_SNAKE_CASE"`, `"kebab-case"`
+/// and `"SCREAMING-KEBAB-CASE"`. Rust field names are assumed to be in snake_case.
+///
+/// `#[scylla(transparent)]`
+///
+/// Instead of as a UDT, the struct is serialized exactly like its only field.
+/// This is useful for newtypes wrapping a value of a different type:
+///
+/// ```rust
+/// # use scylla::SerializeValue;
+/// # use uuid::Uuid;
+/// #[derive(SerializeValue)]
+/// #[scylla(transparent)]
+/// struct UserId(Uuid);
+/// ```
+///
+/// The struct must have exactly one field, and neither the other struct
+/// attributes (apart from `crate`) nor field attributes can be used with it.
+///
 /// # Field attributes
 ///
 /// `#[scylla(rename = "name_in_the_udt")]`
@@ -146,6 +179,58 @@ pub use scylla_cql::macros::IntoUserType;
 ///
 /// Don't use the field during serialization.
 ///
+/// `#[scylla(default)]`
+///
+/// If the UDT definition does not contain this field, it is not serialized.
+/// This allows a single struct deriving both `SerializeValue` and
+/// `DeserializeValue` to use the attribute of the same name in the latter.
+///
+/// `#[scylla(with = "path::to::module")]`
+///
+/// Serializes the field with the `serialize` function of the given module
+/// instead of the `SerializeValue` implementation of its type, e.g. to store
+/// an enum as a compact int. The function must have the same signature as
+/// `SerializeValue::serialize`, taking the field as the first argument:
+/// `fn serialize<'b>(value: &T, typ: &ColumnType, writer: CellWriter<'b>) -> Result<WrittenCellProof<'b>, SerializationError>`.
+/// The module can also provide the functions used by the attribute of the same
+/// name in the deserialization macros, see [`DeserializeRow`](derive@DeserializeRow#field-attributes).
+///
+/// # Enums
+///
+/// Enums whose variants have no fields can be serialized as well. Depending on
+/// the `repr` attribute, a variant is sent either as a string or as an integer:
+///
+/// ```rust
+/// # use scylla::SerializeValue;
+/// #[derive(SerializeValue)]
+/// enum Status {
+///     Active,
+///     #[scylla(rename = "on_hold")]
+///     OnHold,
+/// }
+///
+/// #[derive(SerializeValue)]
+/// #[scylla(repr = "int")]
+/// enum Priority {
+///     Low = 1,
+///     High = 10,
+/// }
+/// ```
+///
+/// `#[scylla(repr = "text")]` (default)
+///
+/// The variant is serialized to a `text` or `ascii` column as its name.
+/// The name can be changed with the `#[scylla(rename = "name")]` variant attribute.
+///
+/// `#[scylla(repr = "int")]`
+///
+/// The variant is serialized to a `tinyint`, `smallint`, `int` or `bigint`
+/// column as its discriminant. Serialization fails with
+/// [`BuiltinSerializationErrorKind::ValueOverflow`](crate::serialize::value::BuiltinSerializationErrorKind::ValueOverflow)
+/// if the discriminant does not fit in the column type.
+///
+/// The `crate` attribute is supported for enums, too.
+///
 /// ---
 ///
 pub use scylla_cql::macros::SerializeValue;
@@ -235,6 +320,31 @@ pub use scylla_cql::macros::SerializeValue;
 /// OK if i-th Rust struct field has a different name than the column / bind
 /// marker. The values are still being type-checked.
 ///
+/// `#[scylla(positional)]`
+///
+/// Serializes the fields to the columns / bind markers in the order
+/// of the fields, without checking any names, like `flavor = "enforce_order"`
+/// combined with `skip_name_checks`. This allows deriving the trait for tuple
+/// structs, and can't be combined with `rename_all`, `rename` or `flatten`:
+///
+/// ```rust
+/// # use scylla::SerializeRow;
+/// #[derive(SerializeRow)]
+/// #[scylla(positional)]
+/// struct Bounds(i32, i32);
+/// ```
+///
+/// `#[scylla(rename_all = "case_convention")]`
+///
+/// Serializes the fields to the columns / bind markers named like the Rust
+/// fields converted to the given case convention. Fields with `#[scylla(rename)]`
+/// keep their explicit names.
+/// Possible conventions are `"lowercase"`, `"UPPERCASE"`, `"PascalCase"`,
+/// `"camelCase"`, `"snake_case"`, `"SCREAMING_SNAKE_CASE"`, `"kebab-case"`
+/// and `"SCREAMING-KEBAB-CASE"`. Rust field names are assumed to be in snake_case.
+/// The columns of flattened fields are named according to the attributes
+/// of the flattened struct.
+///
 /// # Field attributes
 ///
 /// `#[scylla(rename = "column_or_bind_marker_name")]`
@@ -246,6 +356,59 @@ pub use scylla_cql::macros::SerializeValue;
 ///
 /// Don't use the field during serialization.
 ///
+/// `#[scylla(flatten)]`
+///
+/// _Specific only to the `match_by_name` flavor._
+///
+/// The field must be a struct deriving `SerializeRow` with the `match_by_name`
+/// flavor, and its fields are serialized to the columns / bind markers
+/// of the statement as if they were fields of the outer struct. This allows
+/// to factor a group of columns shared by several tables into a reusable struct:
+///
+/// ```rust
+/// # use scylla::SerializeRow;
+/// #[derive(SerializeRow)]
+/// struct Audit {
+///     created_by: String,
+///     updated_at: i64,
+/// }
+///
+/// #[derive(SerializeRow)]
+/// struct MyValues {
+///     a: i32,
+///     #[scylla(flatten)]
+///     audit: Audit,
+/// }
+/// ```
+///
+/// `#[scylla(with = "path::to::module")]`
+///
+/// Serializes the field with the `serialize` function of the given module
+/// instead of the `SerializeValue` implementation of its type, e.g. to store
+/// an enum as a compact int. The function must have the same signature as
+/// `SerializeValue::serialize`, taking the field as the first argument:
+/// `fn serialize<'b>(value: &T, typ: &ColumnType, writer: CellWriter<'b>) -> Result<WrittenCellProof<'b>, SerializationError>`.
+/// The module can also provide the functions used by the attribute of the same
+/// name in the deserialization macros, see [`DeserializeRow`](derive@DeserializeRow#field-attributes).
+///
+/// `#[scylla(unset_if_none)]`
+///
+/// The field must be an `Option`, and `None` is sent as an unset value
+/// instead of null. Unset columns are left untouched by the statement, so
+/// partial updates don't create tombstones for the fields that are `None`:
+///
+/// ```rust
+/// # use scylla::SerializeRow;
+/// #[derive(SerializeRow)]
+/// struct UserUpdate {
+///     id: i32,
+///     #[scylla(unset_if_none)]
+///     name: Option<String>,
+///     #[scylla(unset_if_none)]
+///     email: Option<String>,
+/// }
+/// ```
+///
 /// ---
 ///
 pub use scylla_cql::macros::SerializeRow;
@@ -254,11 +417,16 @@ pub use scylla_cql::macros::SerializeRow;
 /// which deserializes a User Defined Type with the same layout as the Rust
 /// struct.
 ///
-/// At the moment, only structs with named fields are supported.
+/// At the moment, only structs with named fields are supported, apart from
+/// fieldless enums (see [below](#enums)) and `transparent` newtypes.
 ///
 /// This macro properly supports structs with lifetimes, meaning that you can
 /// deserialize UDTs with fields that borrow memory from the serialized response.
 ///
+/// Structs with type parameters are supported as well. The generated implementation
+/// requires each type parameter to implement `DeserializeValue`, in addition
+/// to the bounds declared on the struct.
+///
 /// # Example
 ///
 /// A UDT defined like this:
@@ -348,6 +516,44 @@ pub use scylla_cql::macros::SerializeRow;
 /// If more strictness is desired, this flag makes sure that no excess fields
 /// are present and forces error in case there are some.
 ///
+/// `#[scylla(udt_name = "name")]`, `#[scylla(keyspace = "keyspace_name")]`
+///
+/// By default, the generated implementation accepts any UDT with matching
+/// fields. With these attributes, it also verifies the name of the UDT and/or
+/// the keyspace it is defined in during the type check phase.
+///
+/// `#[scylla(rename_all = "case_convention")]`
+///
+/// By default, the generated implementation will try to match the Rust fields
+/// to the UDT fields with the same names. This attribute makes it match them
+/// to the UDT fields named like the Rust fields converted to the given case
+/// convention. Fields with `#[scylla(rename)]` keep their explicit names.
+/// Possible conventions are `"lowercase"`, `"UPPERCASE"`, `"PascalCase"`,
+/// `"camelCase"`, `"snake_case"`, `"SCREAMING_SNAKE_CASE"`, `"kebab-case"`
+/// and `"SCREAMING-KEBAB-CASE"`. Rust field names are assumed to be in snake_case.
+///
+/// `#[scylla(transparent)]`
+///
+/// Instead of from a UDT, the struct is deserialized exactly like its only
+/// field, including type checking and borrowing from the frame:
+///
+/// ```rust
+/// # use scylla_cql::macros::DeserializeValue;
+/// # use uuid::Uuid;
+/// #[derive(DeserializeValue)]
+/// # #[scylla(crate = "scylla_cql")]
+/// #[scylla(transparent)]
+/// struct UserId(Uuid);
+///
+/// #[derive(DeserializeValue)]
+/// # #[scylla(crate = "scylla_cql")]
+/// #[scylla(transparent)]
+/// struct Name<'a>(&'a str);
+/// ```
+///
+/// The struct must have exactly one field, and neither the other struct
+/// attributes (apart from `crate`) nor field attributes can be used with it.
+///
 /// ## Field attributes
 ///
 /// `#[scylla(skip)]`
@@ -365,11 +571,60 @@ pub use scylla_cql::macros::SerializeRow;
 /// If the value of the field received from DB is null, the field will be
 /// initialized with `Default::default()`.
 ///
+/// `#[scylla(default)]`
+///
+/// A combination of `allow_missing` and `default_when_null`: the field will be
+/// initialized with `Default::default()` whenever the database does not provide
+/// a value for it. This is useful for evolving UDTs with `ALTER TYPE` - fields can
+/// be added to the Rust struct before they are added to the UDT, and values written
+/// before a field was added to the UDT (which lack it) deserialize successfully.
+///
 /// `#[scylla(rename = "field_name")]`
 ///
 /// By default, the generated implementation will try to match the Rust field
 /// to a UDT field with the same name. This attribute instead allows to match
 /// to a UDT field with provided name.
+///
+/// `#[scylla(with = "path::to::module")]`
+///
+/// Deserializes the field with the functions of the given module instead of
+/// the `DeserializeValue` implementation of its type. The module must provide
+/// `type_check` and `deserialize` functions with the same signatures as those
+/// of `DeserializeValue`, with the type of the field as the deserialized value.
+/// See [`DeserializeRow`](derive@DeserializeRow#field-attributes) for an example.
+///
+/// # Enums
+///
+/// An enum whose variants have no fields is deserialized from a column that
+/// represents the variant either by name or by discriminant:
+///
+/// ```rust
+/// # use scylla_cql::macros::DeserializeValue;
+/// #[derive(DeserializeValue)]
+/// # #[scylla(crate = "scylla_cql")]
+/// enum Status {
+///     Active,
+///     #[scylla(rename = "on_hold")]
+///     OnHold,
+/// }
+///
+/// #[derive(DeserializeValue)]
+/// # #[scylla(crate = "scylla_cql")]
+/// #[scylla(repr = "int")]
+/// enum Priority {
+///     Low = 1,
+///     High = 10,
+/// }
+/// ```
+///
+/// With `#[scylla(repr = "text")]` (default) the enum is deserialized from
+/// a `text` or `ascii` column, matching the value against the variant names
+/// (or their `rename`s) exactly. With `#[scylla(repr = "int")]` it is deserialized
+/// from a `tinyint`, `smallint`, `int` or `bigint` column, matching the value
+/// against the variant discriminants.
+///
+/// A value that does not correspond to any variant results in
+/// [`BuiltinDeserializationErrorKind::UnknownVariant`](crate::deserialize::value::BuiltinDeserializationErrorKind::UnknownVariant).
 pub use scylla_macros::DeserializeValue;
 
 /// Derive macro for the `DeserializeRow` trait that generates an implementation
@@ -380,6 +635,21 @@ pub use scylla_macros::DeserializeValue;
 /// This macro properly supports structs with lifetimes, meaning that you can
 /// deserialize columns that borrow memory from the serialized response.
 ///
+/// Structs with type parameters are supported as well. The generated implementation
+/// requires each type parameter to implement `DeserializeValue`, in addition
+/// to the bounds declared on the struct:
+///
+/// ```rust
+/// # use scylla_cql::macros::DeserializeRow;
+/// #[derive(DeserializeRow)]
+/// # #[scylla(crate = "scylla_cql")]
+/// struct Versioned<'a, T> {
+///     id: &'a str,
+///     version: i32,
+///     payload: T,
+/// }
+/// ```
+///
 /// # Example
 ///
 /// Having a table defined like this:
@@ -416,6 +686,11 @@ pub use scylla_macros::DeserializeValue;
 /// }
 /// ```
 ///
+/// The set of columns is checked strictly: type checking fails if the row
+/// contains a column which doesn't correspond to any field, e.g. because a column
+/// was added to a table queried with `SELECT *`, or if there is no column
+/// for a field which is not skipped.
+///
 /// # Attributes
 ///
 /// The macro supports a number of attributes that customize the generated
@@ -473,6 +748,47 @@ pub use scylla_macros::DeserializeValue;
 /// column into the first field, second column into the second field and so on.
 /// It will still still verify that the column types and field types match.
 ///
+/// `#[scylla(positional)]`
+///
+/// Deserializes the columns into the fields in the order of the fields,
+/// without checking any names, like `flavor = "enforce_order"` combined
+/// with `skip_name_checks`. This allows deriving the trait for tuple structs,
+/// e.g. for queries returning unnamed expressions such as aggregates.
+/// It can't be combined with `rename_all`, `rename` or `flatten`.
+///
+/// ```rust
+/// # use scylla_cql::macros::DeserializeRow;
+/// // Deserializes e.g. the result of `SELECT min(a), max(a) FROM ks.tab`.
+/// #[derive(DeserializeRow)]
+/// # #[scylla(crate = "scylla_cql")]
+/// #[scylla(positional)]
+/// struct Range(Option<i32>, Option<i32>);
+/// ```
+///
+/// `#[scylla(rename_all = "case_convention")]`
+///
+/// By default, the generated implementation will try to match the Rust fields
+/// to the columns with the same names. This attribute makes it match them
+/// to the columns named like the Rust fields converted to the given case
+/// convention. Fields with `#[scylla(rename)]` keep their explicit names.
+/// Possible conventions are `"lowercase"`, `"UPPERCASE"`, `"PascalCase"`,
+/// `"camelCase"`, `"snake_case"`, `"SCREAMING_SNAKE_CASE"`, `"kebab-case"`
+/// and `"SCREAMING-KEBAB-CASE"`. Rust field names are assumed to be in snake_case.
+/// The columns of flattened fields are named according to the attributes
+/// of the flattened struct.
+///
+/// ```rust
+/// # use scylla_cql::macros::DeserializeRow;
+/// // Deserializes from the columns "userId" and "displayName".
+/// #[derive(DeserializeRow)]
+/// # #[scylla(crate = "scylla_cql")]
+/// #[scylla(rename_all = "camelCase")]
+/// struct User {
+///     user_id: i32,
+///     display_name: String,
+/// }
+/// ```
+///
 /// ## Field attributes
 ///
 /// `#[scylla(skip)]`
@@ -485,8 +801,210 @@ pub use scylla_macros::DeserializeValue;
 /// By default, the generated implementation will try to match the Rust field
 /// to a column with the same name. This attribute allows to match to a column
 /// with provided name.
+///
+/// `#[scylla(flatten)]`
+///
+/// _Specific only to the `match_by_name` flavor._
+///
+/// The field must be a struct deriving `DeserializeRow` with the `match_by_name`
+/// flavor, and its fields are deserialized from the columns of the row
+/// as if they were fields of the outer struct. This allows to factor a group
+/// of columns shared by several tables into a reusable struct:
+///
+/// ```rust
+/// # use scylla_cql::macros::DeserializeRow;
+/// #[derive(DeserializeRow)]
+/// # #[scylla(crate = "scylla_cql")]
+/// struct Audit {
+///     created_by: String,
+///     updated_at: i64,
+/// }
+///
+/// #[derive(DeserializeRow)]
+/// # #[scylla(crate = "scylla_cql")]
+/// struct MyRow {
+///     a: i32,
+///     #[scylla(flatten)]
+///     audit: Audit,
+/// }
+/// ```
+///
+/// `#[scylla(with = "path::to::module")]`
+///
+/// Deserializes the field with the functions of the given module instead of
+/// the `DeserializeValue` implementation of its type. The module must provide
+/// `type_check` and `deserialize` functions with the same signatures as those
+/// of `DeserializeValue`, with the type of the field as the deserialized value.
+/// Together with the `serialize` function used by the serialization macros,
+/// it allows to customize the representation of a single column:
+///
+/// ```rust
+/// # use scylla_cql::macros::{DeserializeRow, SerializeRow};
+/// mod priority_as_int {
+///     use scylla_cql::frame::response::result::ColumnType;
+///     use scylla_cql::types::deserialize::value::DeserializeValue;
+///     use scylla_cql::types::deserialize::{DeserializationError, FrameSlice, TypeCheckError};
+///     use scylla_cql::types::serialize::value::SerializeValue;
+///     use scylla_cql::types::serialize::writers::{CellWriter, WrittenCellProof};
+///     use scylla_cql::types::serialize::SerializationError;
+///
+///     use super::Priority;
+///
+///     pub fn serialize<'b>(
+///         priority: &Priority,
+///         typ: &ColumnType,
+///         writer: CellWriter<'b>,
+///     ) -> Result<WrittenCellProof<'b>, SerializationError> {
+///         (*priority as i32).serialize(typ, writer)
+///     }
+///
+///     pub fn type_check(typ: &ColumnType) -> Result<(), TypeCheckError> {
+///         i32::type_check(typ)
+///     }
+///
+///     pub fn deserialize<'frame, 'metadata>(
+///         typ: &'metadata ColumnType<'metadata>,
+///         v: Option<FrameSlice<'frame>>,
+///     ) -> Result<Priority, DeserializationError> {
+///         Ok(match i32::deserialize(typ, v)? {
+///             0 => Priority::Low,
+///             _ => Priority::High,
+///         })
+///     }
+/// }
+///
+/// #[derive(Clone, Copy)]
+/// enum Priority {
+///     Low = 0,
+///     High = 1,
+/// }
+///
+/// #[derive(SerializeRow, DeserializeRow)]
+/// # #[scylla(crate = "scylla_cql")]
+/// struct Task {
+///     id: i32,
+///     #[scylla(with = "priority_as_int")]
+///     priority: Priority,
+/// }
+/// # fn main() {}
+/// ```
 pub use scylla_macros::DeserializeRow;
 
+/// Derive macro implementing both serialization and deserialization
+/// of a struct, from a single set of attributes.
+///
+/// By default, the struct is treated as a row: the macro implements
+/// [`SerializeRow`](crate::serialize::row::SerializeRow) and
+/// [`DeserializeRow`](crate::deserialize::DeserializeRow), exactly as
+/// `#[derive(SerializeRow, DeserializeRow)]` would.
+///
+/// ```rust
+/// # use scylla::ScyllaEntity;
+/// #[derive(ScyllaEntity)]
+/// #[scylla(rename_all = "camelCase")]
+/// struct User {
+///     user_id: i32,
+///     display_name: Option<String>,
+/// }
+/// ```
+///
+/// With the `#[scylla(udt)]` attribute, the struct is treated as a UDT:
+/// the macro implements [`SerializeValue`](crate::serialize::value::SerializeValue) and
+/// [`DeserializeValue`](crate::deserialize::DeserializeValue), exactly as
+/// `#[derive(SerializeValue, DeserializeValue)]` would.
+///
+/// ```rust
+/// # use scylla::ScyllaEntity;
+/// #[derive(ScyllaEntity)]
+/// #[scylla(udt, flavor = "enforce_order")]
+/// struct Address {
+///     street: String,
+///     #[scylla(default)]
+///     zip_code: Option<i32>,
+/// }
+/// ```
+///
+/// All the other attributes are those of the respective derives, see their documentation.
+/// The attributes which only affect deserialization are ignored by serialization.
+pub use scylla_macros::ScyllaEntity;
+
+/// Checks a statement against the database schema at compile time
+/// and infers the Rust types of its bind markers and of its result columns.
+///
+/// The schema is read from a file with CQL statements, e.g. the output
+/// of `DESCRIBE SCHEMA` or a series of migrations. `CREATE TABLE`, `CREATE TYPE`,
+/// `ALTER TABLE` with `ADD` or `DROP`, `DROP TABLE`, `DROP TYPE` and `USE` statements
+/// are taken into account, all the others are ignored. The path of the file,
+/// relative to the directory of the `Cargo.toml` of the crate being compiled, is given
+/// with the `schema` argument or in the `SCYLLA_SCHEMA_PATH` environment variable,
+/// e.g. set in the `[env]` section of `.cargo/config.toml`. The crate is rebuilt when
+/// the file changes, so that the statements are checked against its current contents.
+///
+/// The macro evaluates to a [`CheckedQuery<V, R>`](crate::statement::checked::CheckedQuery),
+/// where `V` is a tuple of the types of the bind markers and `R` is a tuple of the types
+/// of the returned columns, `()` for statements other than `SELECT`. A statement
+/// referring to an unknown table or column, or which is not valid CQL,
+/// fails to compile.
+///
+/// ```rust
+/// # use scylla::{checked_query, Session};
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+/// use scylla::frame::value::CqlTimeuuid;
+///
+/// let insert = checked_query!(
+///     "INSERT INTO ks.messages (channel_id, message_id, body) VALUES (?, ?, ?)",
+/// #   schema = "src/statement/checked_tests.cql",
+/// );
+/// insert
+///     .query_unpaged(session, (1, CqlTimeuuid::from(uuid::Uuid::nil()), Some("hi".to_owned())))
+///     .await?;
+///
+/// let select = checked_query!(
+///     "SELECT message_id, body FROM ks.messages WHERE channel_id = ?",
+/// #   schema = "src/statement/checked_tests.cql",
+/// );
+/// let messages: Vec<(CqlTimeuuid, Option<String>)> = select.query_rows(session, (1,)).await?;
+/// # Ok(())
+/// # }
+/// ```
+///
+/// The types of bind markers and columns follow the CQL types:
+/// - native types map to the Rust types listed in [Data Types](https://rust-driver.docs.scylladb.com/stable/data-types/data-types.html),
+///   e.g. `String` for `text`, `CqlTimestamp` for `timestamp` and `Uuid` for `uuid`,
+/// - lists and sets map to `Vec`, maps to `HashMap` and tuples to Rust tuples,
+/// - UDTs map to [`CqlValue`](crate::frame::response::result::CqlValue).
+///
+/// As the keys of a `HashMap` must implement `Hash` and `Eq`, statements with maps whose keys
+/// contain `float`, `double`, `duration`, UDTs or maps fail to compile. Single elements
+/// of such maps, e.g. `m[?]`, can still be bound.
+///
+/// Returned columns other than those of the primary key, as well as values assigned
+/// to them by `INSERT` and `UPDATE` statements, are wrapped in `Option`, as they may be null.
+/// `SELECT *` returns the partition key and clustering columns, followed by the
+/// static columns and then the regular ones, each sorted by name.
+///
+/// # Arguments
+///
+/// `schema = "path/to/schema.cql"`
+///
+/// The path of the schema file, overriding the `SCYLLA_SCHEMA_PATH` environment variable.
+///
+/// `keyspace = "ks"`
+///
+/// The keyspace of tables which are not qualified with one in the statement.
+///
+/// `crate = path::to::scylla`
+///
+/// By default, the code generated by the macro refers to the `scylla` crate.
+/// This argument allows to refer to it under a different path.
+///
+/// # Limitations
+///
+/// Only `SELECT`, `INSERT`, `UPDATE` and `DELETE` statements are supported.
+/// Statements may have at most 16 bind markers and return at most 16 columns.
+/// Bind markers inside collection literals and function calls are not supported.
+pub use scylla_macros::checked_query;
+
 /// #[derive(ValueList)] allows to pass struct as a list of values for a query
 ///
 /// ---
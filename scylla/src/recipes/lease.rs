@@ -0,0 +1,314 @@
+use std::time::{Duration, Instant};
+
+use scylla_cql::frame::response::result::CqlValue;
+use scylla_cql::frame::types::Consistency;
+use thiserror::Error;
+
+use super::{applied_flag, first_row_value};
+use crate::prepared_statement::PreparedStatement;
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::{IntoRowsResultError, MaybeFirstRowError, QueryRowsResult};
+use crate::Session;
+
+const HOLDER_COLUMN: &str = "holder";
+
+/// Distributed leases, i.e. locks which expire unless they are renewed,
+/// stored in a table and managed with lightweight transactions.
+///
+/// A lease is identified by its name and held by at most one holder at a time.
+/// It is acquired with `INSERT ... IF NOT EXISTS USING TTL`, so it disappears
+/// once its TTL passes without a renewal, e.g. because its holder crashed.
+///
+/// As the holder can never be sure that its lease has not expired in the
+/// meantime (e.g. because of a long GC pause), every acquired lease carries
+/// a fencing token. Fencing tokens of consecutive acquisitions of the same
+/// lease are strictly increasing, so a resource protected by the lease can
+/// reject requests carrying a smaller token than one it has already seen.
+///
+/// The token is the write timestamp of the acquiring transaction.
+/// Lightweight transactions on a partition are ordered by their Paxos ballots,
+/// whose timestamps are used as write timestamps, so each acquisition gets
+/// a greater timestamp than all transactions preceding it.
+///
+/// The table has to be created beforehand, see [`LeaseManager::create_table_query`].
+///
+/// ```rust
+/// # use scylla::Session;
+/// # use scylla::recipes::LeaseManager;
+/// # use std::error::Error;
+/// # use std::time::Duration;
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+/// session
+///     .query_unpaged(LeaseManager::create_table_query("ks.leases"), &[])
+///     .await?;
+/// let leases = LeaseManager::new(session, "ks.leases", Duration::from_secs(30)).await?;
+///
+/// if let Some(mut lease) = leases.try_acquire(session, "compaction", "worker-1").await? {
+///     // ... do some work, passing lease.fencing_token() along ...
+///     leases.renew(session, &mut lease).await?;
+///     // ... do some more work ...
+///     leases.release(session, lease).await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct LeaseManager {
+    acquire: PreparedStatement,
+    read: PreparedStatement,
+    renew: PreparedStatement,
+    release: PreparedStatement,
+    ttl: Duration,
+    ttl_secs: i32,
+}
+
+impl LeaseManager {
+    /// Returns the statement creating a table which can store leases.
+    pub fn create_table_query(table: &str) -> String {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (name text PRIMARY KEY, {} text)",
+            table, HOLDER_COLUMN
+        )
+    }
+
+    /// Prepares the statements managing leases stored in the given table,
+    /// which may be qualified with a keyspace name.
+    ///
+    /// The TTL is truncated to whole seconds and has to be at least 1 second.
+    pub async fn new(session: &Session, table: &str, ttl: Duration) -> Result<Self, LeaseError> {
+        let ttl_secs = ttl_to_secs(ttl)?;
+        let acquire = session
+            .prepare(format!(
+                "INSERT INTO {} (name, {}) VALUES (?, ?) IF NOT EXISTS USING TTL ?",
+                table, HOLDER_COLUMN
+            ))
+            .await?;
+        let mut read = session
+            .prepare(format!(
+                "SELECT {0}, writetime({0}) FROM {1} WHERE name = ?",
+                HOLDER_COLUMN, table
+            ))
+            .await?;
+        // The acquiring transaction might not have reached a quorum of replicas yet.
+        read.set_consistency(Consistency::Serial);
+        let renew = session
+            .prepare(format!(
+                "UPDATE {0} USING TTL ? SET {1} = ? WHERE name = ? IF {1} = ?",
+                table, HOLDER_COLUMN
+            ))
+            .await?;
+        let release = session
+            .prepare(format!(
+                "DELETE FROM {} WHERE name = ? IF {} = ?",
+                table, HOLDER_COLUMN
+            ))
+            .await?;
+
+        Ok(Self {
+            acquire,
+            read,
+            renew,
+            release,
+            ttl: Duration::from_secs(ttl_secs as u64),
+            ttl_secs,
+        })
+    }
+
+    pub fn ttl(&self) -> Duration {
+        self.ttl
+    }
+
+    /// Tries to acquire the lease with the given name.
+    ///
+    /// Returns `None` if the lease is currently held, also if it is held by the same holder.
+    pub async fn try_acquire(
+        &self,
+        session: &Session,
+        name: &str,
+        holder: &str,
+    ) -> Result<Option<Lease>, LeaseError> {
+        let started = Instant::now();
+        let result = session
+            .execute_unpaged(&self.acquire, (name, holder, self.ttl_secs))
+            .await?
+            .into_rows_result()?;
+        if !applied_flag(&result)?.ok_or(LeaseError::MissingAppliedColumn)? {
+            return Ok(None);
+        }
+
+        let result = session
+            .execute_unpaged(&self.read, (name,))
+            .await?
+            .into_rows_result()?;
+        match result.maybe_first_row::<(Option<String>, Option<i64>)>()? {
+            Some((Some(current_holder), Some(fencing_token))) if current_holder == holder => {
+                Ok(Some(Lease {
+                    name: name.to_owned(),
+                    holder: holder.to_owned(),
+                    fencing_token,
+                    valid_until: started + self.ttl,
+                }))
+            }
+            // The lease has already expired and possibly was acquired by somebody else.
+            _ => Ok(None),
+        }
+    }
+
+    /// Extends the lease by the TTL, counting from now.
+    ///
+    /// Fails with [`LeaseError::Lost`] if the lease has expired in the meantime.
+    /// The fencing token of the lease stays the same.
+    pub async fn renew(&self, session: &Session, lease: &mut Lease) -> Result<(), LeaseError> {
+        let started = Instant::now();
+        let result = session
+            .execute_unpaged(
+                &self.renew,
+                (self.ttl_secs, &lease.holder, &lease.name, &lease.holder),
+            )
+            .await?
+            .into_rows_result()?;
+        check_still_held(&result, lease)?;
+        lease.valid_until = started + self.ttl;
+        Ok(())
+    }
+
+    /// Releases the lease, so that it can be acquired again right away.
+    ///
+    /// Fails with [`LeaseError::Lost`] if the lease has expired in the meantime.
+    pub async fn release(&self, session: &Session, lease: Lease) -> Result<(), LeaseError> {
+        let result = session
+            .execute_unpaged(&self.release, (&lease.name, &lease.holder))
+            .await?
+            .into_rows_result()?;
+        check_still_held(&result, &lease)
+    }
+}
+
+fn ttl_to_secs(ttl: Duration) -> Result<i32, LeaseError> {
+    match i32::try_from(ttl.as_secs()) {
+        Ok(secs) if secs > 0 => Ok(secs),
+        _ => Err(LeaseError::InvalidTtl(ttl)),
+    }
+}
+
+fn check_still_held(result: &QueryRowsResult, lease: &Lease) -> Result<(), LeaseError> {
+    if applied_flag(result)?.ok_or(LeaseError::MissingAppliedColumn)? {
+        return Ok(());
+    }
+    // The result of a conditional statement which was not applied
+    // contains the current values of the columns in its condition.
+    let current_holder = match result.column_specs().get_by_name(HOLDER_COLUMN) {
+        Some((idx, _)) => match first_row_value(result, idx)? {
+            Some(CqlValue::Text(holder)) => Some(holder),
+            _ => None,
+        },
+        None => None,
+    };
+    Err(LeaseError::Lost {
+        name: lease.name.clone(),
+        current_holder,
+    })
+}
+
+/// A lease acquired with [`LeaseManager::try_acquire`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lease {
+    name: String,
+    holder: String,
+    fencing_token: i64,
+    valid_until: Instant,
+}
+
+impl Lease {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn holder(&self) -> &str {
+        &self.holder
+    }
+
+    /// A token which is greater than the tokens of all previous acquisitions
+    /// of this lease, see [`LeaseManager`].
+    pub fn fencing_token(&self) -> i64 {
+        self.fencing_token
+    }
+
+    /// The moment until which the lease is guaranteed not to expire.
+    ///
+    /// It is computed from the moment the last acquiring or renewing
+    /// request was sent, so it errs on the side of caution.
+    pub fn valid_until(&self) -> Instant {
+        self.valid_until
+    }
+
+    pub fn is_valid(&self) -> bool {
+        Instant::now() < self.valid_until
+    }
+}
+
+/// An error returned by [`LeaseManager`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum LeaseError {
+    /// Executing one of the statements failed.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// A statement returned a result of other kind than Rows.
+    #[error("Failed to convert the result into rows result: {0}")]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// A row returned by one of the statements failed to deserialize.
+    #[error("Failed to deserialize the row: {0}")]
+    RowDeserializationFailed(#[from] MaybeFirstRowError),
+
+    /// The TTL is shorter than 1 second, or does not fit in a CQL `int`.
+    #[error("Invalid lease TTL {0:?}, expected between 1 and {max} seconds", max = i32::MAX)]
+    InvalidTtl(Duration),
+
+    /// The result of a conditional statement has no `[applied]` column.
+    #[error("The result of a conditional statement has no [applied] column")]
+    MissingAppliedColumn,
+
+    /// The lease has expired, and possibly has been acquired by somebody else.
+    #[error("Lease {name} has been lost, its current holder is {current_holder:?}")]
+    Lost {
+        name: String,
+        current_holder: Option<String>,
+    },
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ttl_to_secs, LeaseError, LeaseManager};
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn ttl_validation() {
+        setup_tracing();
+        assert_eq!(ttl_to_secs(Duration::from_secs(30)).unwrap(), 30);
+        assert_eq!(ttl_to_secs(Duration::from_millis(1500)).unwrap(), 1);
+        for invalid in [
+            Duration::ZERO,
+            Duration::from_millis(999),
+            Duration::from_secs(i32::MAX as u64 + 1),
+        ] {
+            assert!(matches!(
+                ttl_to_secs(invalid),
+                Err(LeaseError::InvalidTtl(ttl)) if ttl == invalid
+            ));
+        }
+    }
+
+    #[test]
+    fn create_table_query() {
+        setup_tracing();
+        assert_eq!(
+            LeaseManager::create_table_query("ks.leases"),
+            "CREATE TABLE IF NOT EXISTS ks.leases (name text PRIMARY KEY, holder text)"
+        );
+    }
+}
@@ -0,0 +1,45 @@
+//! Implementations of common usage patterns built on top of the driver.
+//!
+//! Each recipe solves a problem that applications otherwise tend to solve
+//! on their own, often in subtly incorrect ways. The recipes only use the
+//! public API of the driver, so they can also serve as a starting point
+//! for a custom implementation.
+
+use scylla_cql::frame::response::result::{CqlValue, Row};
+
+use crate::transport::query_result::{MaybeFirstRowError, QueryRowsResult};
+
+mod lease;
+mod outbox;
+mod versioned_update;
+pub use lease::{Lease, LeaseError, LeaseManager};
+pub use outbox::{OutboxBatch, OutboxConfig, OutboxConsumer, OutboxError, OutboxEvent};
+pub use versioned_update::{
+    ContentionBackoff, VersionConflict, VersionedUpdate, VersionedUpdateError,
+    VersionedUpdateOutcome, Versions,
+};
+
+const APPLIED_COLUMN: &str = "[applied]";
+
+// Returns the value of the column at `idx` in the first row of the result,
+// or `None` if there are no rows or the value is null.
+fn first_row_value(
+    result: &QueryRowsResult,
+    idx: usize,
+) -> Result<Option<CqlValue>, MaybeFirstRowError> {
+    Ok(result
+        .maybe_first_row::<Row>()?
+        .and_then(|row| row.columns.into_iter().nth(idx).flatten()))
+}
+
+// Returns whether a conditional statement was applied,
+// or `None` if the result is not one of a conditional statement.
+fn applied_flag(result: &QueryRowsResult) -> Result<Option<bool>, MaybeFirstRowError> {
+    let Some((idx, _)) = result.column_specs().get_by_name(APPLIED_COLUMN) else {
+        return Ok(None);
+    };
+    match first_row_value(result, idx)? {
+        Some(CqlValue::Boolean(applied)) => Ok(Some(applied)),
+        _ => Ok(None),
+    }
+}
@@ -0,0 +1,595 @@
+use std::collections::HashMap;
+use std::error::Error;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use scylla_cql::frame::response::result::ColumnSpec;
+use scylla_cql::frame::types::Consistency;
+use scylla_cql::frame::value::CqlTimeuuid;
+use scylla_cql::types::deserialize::row::{ColumnIterator, DeserializeRow};
+use scylla_cql::types::deserialize::value::DeserializeValue;
+use scylla_cql::types::deserialize::{DeserializationError, TypeCheckError};
+use thiserror::Error;
+
+use super::{applied_flag, Lease, LeaseError, LeaseManager};
+use crate::prepared_statement::PreparedStatement;
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::{IntoRowsResultError, MaybeFirstRowError, RowsError};
+use crate::Session;
+
+/// Configuration of an [`OutboxConsumer`].
+///
+/// The outbox table is created by the application. Apart from the columns
+/// describing the events, it has to contain the following columns:
+/// ```text
+/// shard int, bucket bigint, id timeuuid, ..., PRIMARY KEY ((shard, bucket), id)
+/// ```
+/// Producers insert events with an `id` generated at the time of the insertion,
+/// the `bucket` computed with [`OutboxConfig::bucket_at`] from the same time,
+/// and a `shard` in `0..shards`, e.g. computed from a hash of the entity
+/// the event refers to. Events of a shard are handed to the consumer in the
+/// order of their ids.
+///
+/// The progress and lease tables can be created with
+/// [`OutboxConsumer::create_progress_table_query`] and
+/// [`LeaseManager::create_table_query`] respectively.
+#[derive(Debug, Clone)]
+pub struct OutboxConfig {
+    table: String,
+    event_columns: String,
+    progress_table: String,
+    leases_table: String,
+    consumer_id: String,
+    shards: i32,
+    bucket_duration: Duration,
+    grace_period: Duration,
+    batch_size: i32,
+    lease_ttl: Duration,
+}
+
+impl OutboxConfig {
+    /// Creates the configuration with default settings: 16 shards,
+    /// buckets of 10 seconds, a grace period of 5 seconds, batches
+    /// of 100 events and leases of 30 seconds.
+    ///
+    /// - `table` - the outbox table,
+    /// - `event_columns` - comma-separated columns of the outbox table which are
+    ///   deserialized into events,
+    /// - `progress_table` - the table storing the position of consumers in each shard,
+    /// - `leases_table` - the table storing the leases of shards,
+    /// - `consumer_id` - a name of the consumer, unique among the consumers
+    ///   running at the same time.
+    pub fn new(
+        table: impl Into<String>,
+        event_columns: impl Into<String>,
+        progress_table: impl Into<String>,
+        leases_table: impl Into<String>,
+        consumer_id: impl Into<String>,
+    ) -> Self {
+        Self {
+            table: table.into(),
+            event_columns: event_columns.into(),
+            progress_table: progress_table.into(),
+            leases_table: leases_table.into(),
+            consumer_id: consumer_id.into(),
+            shards: 16,
+            bucket_duration: Duration::from_secs(10),
+            grace_period: Duration::from_secs(5),
+            batch_size: 100,
+            lease_ttl: Duration::from_secs(30),
+        }
+    }
+
+    /// Sets the number of shards. Producers have to use the same number.
+    pub fn with_shards(mut self, shards: i32) -> Self {
+        self.shards = shards;
+        self
+    }
+
+    /// Sets the duration of a bucket. Producers have to use the same duration.
+    pub fn with_bucket_duration(mut self, bucket_duration: Duration) -> Self {
+        self.bucket_duration = bucket_duration;
+        self
+    }
+
+    /// Sets how long after its end a bucket is consumed.
+    ///
+    /// It has to exceed the clock skew between the producers and the consumers,
+    /// plus the duration of the insertion of an event. An event inserted into
+    /// a bucket which has already been consumed is never handed to a consumer.
+    pub fn with_grace_period(mut self, grace_period: Duration) -> Self {
+        self.grace_period = grace_period;
+        self
+    }
+
+    /// Sets the maximum number of events in a batch.
+    pub fn with_batch_size(mut self, batch_size: i32) -> Self {
+        self.batch_size = batch_size;
+        self
+    }
+
+    /// Sets the TTL of the leases of shards, see [`LeaseManager`].
+    pub fn with_lease_ttl(mut self, lease_ttl: Duration) -> Self {
+        self.lease_ttl = lease_ttl;
+        self
+    }
+
+    /// Returns the bucket to which events produced at the given time belong.
+    pub fn bucket_at(&self, time: SystemTime) -> i64 {
+        let since_epoch = time.duration_since(UNIX_EPOCH).unwrap_or_default();
+        (since_epoch.as_millis() / self.bucket_duration.as_millis().max(1)) as i64
+    }
+
+    // Whether no more events will be produced into the bucket.
+    fn is_closed(&self, bucket: i64, now: SystemTime) -> bool {
+        let now = now
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_millis() as i128;
+        let bucket_end = (bucket as i128 + 1) * self.bucket_duration.as_millis() as i128;
+        bucket_end + self.grace_period.as_millis() as i128 <= now
+    }
+
+    fn validate(&self) -> Result<(), OutboxError> {
+        if self.shards <= 0 {
+            return Err(OutboxError::InvalidConfig(
+                "the number of shards must be positive",
+            ));
+        }
+        if self.bucket_duration.as_millis() == 0 {
+            return Err(OutboxError::InvalidConfig(
+                "the bucket duration must be at least 1 millisecond",
+            ));
+        }
+        if self.batch_size <= 0 {
+            return Err(OutboxError::InvalidConfig(
+                "the batch size must be positive",
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// An event read from the outbox table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutboxEvent<E> {
+    pub id: CqlTimeuuid,
+    pub event: E,
+}
+
+/// A batch of consecutive events of a shard, handed to the handler
+/// passed to [`OutboxConsumer::poll`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OutboxBatch<E> {
+    pub shard: i32,
+    pub bucket: i64,
+
+    /// The fencing token of the lease of the shard held by the consumer,
+    /// see [`LeaseManager`].
+    pub fencing_token: i64,
+
+    /// Events in the order of their ids.
+    pub events: Vec<OutboxEvent<E>>,
+}
+
+/// Consumes events from a transactional outbox table, with at-least-once semantics.
+///
+/// The outbox table is split into shards, and each shard into time buckets
+/// (see [`OutboxConfig`]). Each shard is consumed by a single consumer at a time:
+/// consumers claim shards with leases, and advance their position in a shard with
+/// lightweight transactions, so that a consumer which lost its lease cannot move
+/// the position of its successor. A bucket is consumed only once it is closed,
+/// i.e. once no more events can be inserted into it.
+///
+/// A batch is acknowledged after the handler returns successfully. If the
+/// handler fails or the consumer crashes, the batch is handed again to the
+/// next consumer of the shard. Handlers thus have to be idempotent.
+///
+/// ```rust
+/// # use scylla::Session;
+/// # use scylla::recipes::{OutboxConfig, OutboxConsumer};
+/// # use std::error::Error;
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+/// let config = OutboxConfig::new(
+///     "ks.outbox",
+///     "kind, payload",
+///     "ks.outbox_progress",
+///     "ks.outbox_leases",
+///     "consumer-1",
+/// );
+/// let mut consumer =
+///     OutboxConsumer::<(String, Vec<u8>)>::new(session, config).await?;
+///
+/// loop {
+///     let handled = consumer
+///         .poll(session, |batch| async move {
+///             for event in batch.events {
+///                 let (kind, payload) = event.event;
+///                 // ... publish the event ...
+///             }
+///             Ok::<_, std::io::Error>(())
+///         })
+///         .await?;
+///     if handled == 0 {
+///         tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+///     }
+/// }
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct OutboxConsumer<E> {
+    config: OutboxConfig,
+    select_first: PreparedStatement,
+    select_next: PreparedStatement,
+    read_progress: PreparedStatement,
+    init_progress: PreparedStatement,
+    advance_progress: PreparedStatement,
+    leases: LeaseManager,
+    held_leases: HashMap<i32, Lease>,
+    _event: PhantomData<fn() -> E>,
+}
+
+impl<E> OutboxConsumer<E>
+where
+    E: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>,
+{
+    /// Returns the statement creating a table which can store the progress of consumers.
+    pub fn create_progress_table_query(table: &str) -> String {
+        format!(
+            "CREATE TABLE IF NOT EXISTS {} (shard int PRIMARY KEY, bucket bigint, last_id timeuuid)",
+            table
+        )
+    }
+
+    /// Prepares the statements used by the consumer.
+    pub async fn new(session: &Session, config: OutboxConfig) -> Result<Self, OutboxError> {
+        config.validate()?;
+        let select_first = session
+            .prepare(format!(
+                "SELECT id, {} FROM {} WHERE shard = ? AND bucket = ? LIMIT ?",
+                config.event_columns, config.table
+            ))
+            .await?;
+        let select_next = session
+            .prepare(format!(
+                "SELECT id, {} FROM {} WHERE shard = ? AND bucket = ? AND id > ? LIMIT ?",
+                config.event_columns, config.table
+            ))
+            .await?;
+        let mut read_progress = session
+            .prepare(format!(
+                "SELECT bucket, last_id FROM {} WHERE shard = ?",
+                config.progress_table
+            ))
+            .await?;
+        // The progress is only modified with lightweight transactions.
+        read_progress.set_consistency(Consistency::Serial);
+        let init_progress = session
+            .prepare(format!(
+                "INSERT INTO {} (shard, bucket) VALUES (?, ?) IF NOT EXISTS",
+                config.progress_table
+            ))
+            .await?;
+        let advance_progress = session
+            .prepare(format!(
+                "UPDATE {} SET bucket = ?, last_id = ? WHERE shard = ? IF bucket = ? AND last_id = ?",
+                config.progress_table
+            ))
+            .await?;
+        let leases = LeaseManager::new(session, &config.leases_table, config.lease_ttl).await?;
+
+        Ok(Self {
+            config,
+            select_first,
+            select_next,
+            read_progress,
+            init_progress,
+            advance_progress,
+            leases,
+            held_leases: HashMap::new(),
+            _event: PhantomData,
+        })
+    }
+
+    pub fn config(&self) -> &OutboxConfig {
+        &self.config
+    }
+
+    /// Hands all events from closed buckets of the shards which this consumer
+    /// manages to claim to `handler`, and returns the number of handled events.
+    ///
+    /// A shard which starts being consumed for the first time is consumed
+    /// starting from the current bucket.
+    ///
+    /// Stops at the first error returned by `handler`, returning it as
+    /// [`OutboxError::Handler`]; the failed batch is handed again on the next poll.
+    pub async fn poll<F, Fut, HE>(
+        &mut self,
+        session: &Session,
+        mut handler: F,
+    ) -> Result<usize, OutboxError>
+    where
+        F: FnMut(OutboxBatch<E>) -> Fut,
+        Fut: Future<Output = Result<(), HE>>,
+        HE: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let mut handled = 0;
+        for shard in 0..self.config.shards {
+            handled += self.poll_shard(session, shard, &mut handler).await?;
+        }
+        Ok(handled)
+    }
+
+    /// Releases the leases of all shards held by the consumer,
+    /// so that other consumers can take them over right away.
+    pub async fn release(&mut self, session: &Session) -> Result<(), OutboxError> {
+        for (_, lease) in self.held_leases.drain() {
+            match self.leases.release(session, lease).await {
+                Ok(()) | Err(LeaseError::Lost { .. }) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        Ok(())
+    }
+
+    async fn poll_shard<F, Fut, HE>(
+        &mut self,
+        session: &Session,
+        shard: i32,
+        handler: &mut F,
+    ) -> Result<usize, OutboxError>
+    where
+        F: FnMut(OutboxBatch<E>) -> Fut,
+        Fut: Future<Output = Result<(), HE>>,
+        HE: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let mut handled = 0;
+        let (mut bucket, mut last_id) = self.read_progress(session, shard).await?;
+        while self.config.is_closed(bucket, SystemTime::now()) {
+            let Some(fencing_token) = self.claim(session, shard).await? else {
+                break;
+            };
+
+            let result = match last_id {
+                None => {
+                    session
+                        .execute_unpaged(
+                            &self.select_first,
+                            (shard, bucket, self.config.batch_size),
+                        )
+                        .await?
+                }
+                Some(last_id) => {
+                    session
+                        .execute_unpaged(
+                            &self.select_next,
+                            (shard, bucket, last_id, self.config.batch_size),
+                        )
+                        .await?
+                }
+            }
+            .into_rows_result()?;
+            let events = result
+                .rows::<OutboxRow<E>>()?
+                .map(|row| row.map(|OutboxRow(event)| event))
+                .collect::<Result<Vec<_>, _>>()?;
+
+            // An exhausted bucket is left for the next one.
+            let (next_bucket, next_last_id) = match events.last() {
+                Some(event) => (bucket, Some(event.id)),
+                None => (bucket + 1, None),
+            };
+            if !events.is_empty() {
+                let count = events.len();
+                handler(OutboxBatch {
+                    shard,
+                    bucket,
+                    fencing_token,
+                    events,
+                })
+                .await
+                .map_err(|err| OutboxError::Handler(err.into()))?;
+                handled += count;
+            }
+
+            let result = session
+                .execute_unpaged(
+                    &self.advance_progress,
+                    (next_bucket, next_last_id, shard, bucket, last_id),
+                )
+                .await?
+                .into_rows_result()?;
+            if !applied_flag(&result)?.ok_or(OutboxError::MissingAppliedColumn)? {
+                // Another consumer has taken over the shard.
+                self.held_leases.remove(&shard);
+                break;
+            }
+            (bucket, last_id) = (next_bucket, next_last_id);
+        }
+        Ok(handled)
+    }
+
+    // Returns the position of the consumers in the shard, initializing it if needed.
+    async fn read_progress(
+        &self,
+        session: &Session,
+        shard: i32,
+    ) -> Result<(i64, Option<CqlTimeuuid>), OutboxError> {
+        loop {
+            let result = session
+                .execute_unpaged(&self.read_progress, (shard,))
+                .await?
+                .into_rows_result()?;
+            if let Some((Some(bucket), last_id)) =
+                result.maybe_first_row::<(Option<i64>, Option<CqlTimeuuid>)>()?
+            {
+                return Ok((bucket, last_id));
+            }
+            // Whether this or a concurrent insertion is applied, the progress is initialized.
+            let initial = self.config.bucket_at(SystemTime::now());
+            session
+                .execute_unpaged(&self.init_progress, (shard, initial))
+                .await?;
+        }
+    }
+
+    // Makes sure that the consumer holds the lease of the shard for at least
+    // half of its TTL, and returns its fencing token. Returns `None` if the
+    // shard is held by another consumer.
+    async fn claim(&mut self, session: &Session, shard: i32) -> Result<Option<i64>, OutboxError> {
+        if let Some(mut lease) = self.held_leases.remove(&shard) {
+            let renew_at = lease.valid_until() - self.leases.ttl() / 2;
+            if Instant::now() < renew_at {
+                let token = lease.fencing_token();
+                self.held_leases.insert(shard, lease);
+                return Ok(Some(token));
+            }
+            match self.leases.renew(session, &mut lease).await {
+                Ok(()) => {
+                    let token = lease.fencing_token();
+                    self.held_leases.insert(shard, lease);
+                    return Ok(Some(token));
+                }
+                // Try to acquire it again, in case nobody else took it.
+                Err(LeaseError::Lost { .. }) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let name = format!("{}/{}", self.config.table, shard);
+        let lease = self
+            .leases
+            .try_acquire(session, &name, &self.config.consumer_id)
+            .await?;
+        Ok(lease.map(|lease| {
+            let token = lease.fencing_token();
+            self.held_leases.insert(shard, lease);
+            token
+        }))
+    }
+}
+
+// A row of the outbox table: the id followed by the event columns.
+struct OutboxRow<E>(OutboxEvent<E>);
+
+#[derive(Error, Debug)]
+#[error("The outbox query result has no id column")]
+struct MissingIdColumn;
+
+impl<'frame, 'metadata, E> DeserializeRow<'frame, 'metadata> for OutboxRow<E>
+where
+    E: DeserializeRow<'frame, 'metadata>,
+{
+    fn type_check(specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        let (id, rest) = specs
+            .split_first()
+            .ok_or_else(|| TypeCheckError::new(MissingIdColumn))?;
+        <CqlTimeuuid as DeserializeValue<'frame, 'metadata>>::type_check(id.typ())?;
+        E::type_check(rest)
+    }
+
+    fn deserialize(
+        mut row: ColumnIterator<'frame, 'metadata>,
+    ) -> Result<Self, DeserializationError> {
+        let id = row
+            .next()
+            .ok_or_else(|| DeserializationError::new(MissingIdColumn))??;
+        let id = <CqlTimeuuid as DeserializeValue<'frame, 'metadata>>::deserialize(
+            id.spec.typ(),
+            id.slice,
+        )?;
+        let event = E::deserialize(row)?;
+        Ok(Self(OutboxEvent { id, event }))
+    }
+}
+
+/// An error returned by [`OutboxConsumer`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum OutboxError {
+    /// The configuration is invalid.
+    #[error("Invalid outbox configuration: {0}")]
+    InvalidConfig(&'static str),
+
+    /// Executing one of the statements failed.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// A statement returned a result of other kind than Rows.
+    #[error("Failed to convert the result into rows result: {0}")]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// The progress row failed to deserialize.
+    #[error("Failed to deserialize the progress: {0}")]
+    ProgressDeserializationFailed(#[from] MaybeFirstRowError),
+
+    /// The outbox table does not match the event type.
+    #[error("Failed to type check the events: {0}")]
+    EventTypeCheckFailed(#[from] RowsError),
+
+    /// An event failed to deserialize.
+    #[error("Failed to deserialize an event: {0}")]
+    EventDeserializationFailed(#[from] DeserializationError),
+
+    /// Acquiring or renewing the lease of a shard failed.
+    #[error(transparent)]
+    LeaseError(#[from] LeaseError),
+
+    /// The result of a conditional statement has no `[applied]` column.
+    #[error("The result of a conditional statement has no [applied] column")]
+    MissingAppliedColumn,
+
+    /// The handler returned an error.
+    #[error("The handler failed: {0}")]
+    Handler(Box<dyn Error + Send + Sync>),
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, UNIX_EPOCH};
+
+    use super::{OutboxConfig, OutboxError};
+    use crate::test_utils::setup_tracing;
+
+    fn config() -> OutboxConfig {
+        OutboxConfig::new("ks.outbox", "payload", "ks.progress", "ks.leases", "c1")
+            .with_bucket_duration(Duration::from_secs(10))
+            .with_grace_period(Duration::from_secs(2))
+    }
+
+    #[test]
+    fn buckets() {
+        setup_tracing();
+        let config = config();
+        assert_eq!(config.bucket_at(UNIX_EPOCH), 0);
+        assert_eq!(
+            config.bucket_at(UNIX_EPOCH + Duration::from_millis(9999)),
+            0
+        );
+        assert_eq!(config.bucket_at(UNIX_EPOCH + Duration::from_secs(10)), 1);
+        assert_eq!(config.bucket_at(UNIX_EPOCH + Duration::from_secs(125)), 12);
+
+        // Bucket 12 spans [120s, 130s) and is closed 2s after its end.
+        assert!(!config.is_closed(12, UNIX_EPOCH + Duration::from_secs(125)));
+        assert!(!config.is_closed(12, UNIX_EPOCH + Duration::from_millis(131_999)));
+        assert!(config.is_closed(12, UNIX_EPOCH + Duration::from_secs(132)));
+    }
+
+    #[test]
+    fn config_validation() {
+        setup_tracing();
+        config().validate().unwrap();
+        for invalid in [
+            config().with_shards(0),
+            config().with_bucket_duration(Duration::from_micros(10)),
+            config().with_batch_size(0),
+        ] {
+            assert!(matches!(
+                invalid.validate(),
+                Err(OutboxError::InvalidConfig(_))
+            ));
+        }
+    }
+}
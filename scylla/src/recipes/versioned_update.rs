@@ -0,0 +1,461 @@
+use std::error::Error;
+use std::time::Duration;
+
+use rand::Rng;
+use scylla_cql::frame::response::result::{ColumnSpec, ColumnType, CqlValue};
+use scylla_cql::types::deserialize::row::DeserializeRow;
+use scylla_cql::types::serialize::row::SerializeRow;
+use thiserror::Error;
+
+use super::{applied_flag, first_row_value};
+use crate::prepared_statement::PreparedStatement;
+use crate::query::Query;
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::{IntoRowsResultError, MaybeFirstRowError, QueryRowsResult};
+use crate::Session;
+
+const DEFAULT_VERSION_COLUMN: &str = "version";
+
+/// Optimistic locking of a single row, based on a version column
+/// and a lightweight transaction.
+///
+/// An update consists of reading the row together with its version,
+/// computing new values, and writing them back with `IF version = ?`,
+/// so that the write is applied only if nobody modified the row in the
+/// meantime. If somebody did, the whole cycle is repeated after a backoff,
+/// up to the number of attempts configured by [`ContentionBackoff`].
+///
+/// The version column has to be of type `bigint`. A null version is treated
+/// as a row that has never been updated; the first update sets the version to 1.
+///
+/// The helper is configured with two statements:
+/// - the read statement, which selects the row to be updated, including the
+///   version column. It is bound with the key passed to [`VersionedUpdate::execute`].
+/// - the write statement, a conditional update which sets the new version and
+///   checks the expected one, e.g. `UPDATE ... SET ..., version = ? WHERE ... IF version = ?`.
+///   It is bound with the values returned by the closure passed to
+///   [`VersionedUpdate::execute`], which receives the versions to be bound.
+///
+/// Reading with a serial consistency (see [`PreparedStatement::set_serial_read`])
+/// makes the read observe all previously applied conditional updates, which
+/// avoids conflicts caused by stale reads.
+///
+/// ```rust
+/// # use scylla::Session;
+/// # use scylla::recipes::VersionedUpdate;
+/// # use std::error::Error;
+/// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+/// let transfer = VersionedUpdate::new(
+///     session,
+///     "SELECT balance, version FROM ks.accounts WHERE id = ?",
+///     "UPDATE ks.accounts SET balance = ?, version = ? WHERE id = ? IF version = ?",
+/// )
+/// .await?;
+///
+/// let account_id = 42_i32;
+/// let outcome = transfer
+///     .execute(
+///         session,
+///         (account_id,),
+///         |(balance, _): (i64, Option<i64>), versions| {
+///             if balance < 100 {
+///                 return Err("insufficient funds");
+///             }
+///             Ok((balance - 100, versions.next, account_id, versions.expected))
+///         },
+///     )
+///     .await?;
+/// println!("Updated to version {} in {} attempts", outcome.version, outcome.attempts);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct VersionedUpdate {
+    read: PreparedStatement,
+    write: PreparedStatement,
+    version_column: String,
+    backoff: ContentionBackoff,
+}
+
+impl VersionedUpdate {
+    /// Prepares the read and write statements, using `version`
+    /// as the name of the version column.
+    pub async fn new(
+        session: &Session,
+        read: impl Into<Query>,
+        write: impl Into<Query>,
+    ) -> Result<Self, VersionedUpdateError> {
+        let read = session.prepare(read).await?;
+        let write = session.prepare(write).await?;
+        Self::from_prepared(read, write, DEFAULT_VERSION_COLUMN)
+    }
+
+    /// Creates the helper out of already prepared statements.
+    ///
+    /// Fails if the read statement does not return a `bigint` column
+    /// of the given name.
+    pub fn from_prepared(
+        read: PreparedStatement,
+        write: PreparedStatement,
+        version_column: impl Into<String>,
+    ) -> Result<Self, VersionedUpdateError> {
+        let version_column = version_column.into();
+        check_version_column(read.get_result_set_col_specs(), &version_column)?;
+        Ok(Self {
+            read,
+            write,
+            version_column,
+            backoff: ContentionBackoff::default(),
+        })
+    }
+
+    /// Sets the strategy of retrying updates which lost a race with a concurrent update.
+    pub fn with_backoff(mut self, backoff: ContentionBackoff) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    pub fn get_backoff(&self) -> &ContentionBackoff {
+        &self.backoff
+    }
+
+    pub fn get_version_column(&self) -> &str {
+        &self.version_column
+    }
+
+    /// Performs the read-modify-write cycle for the row identified by `key`.
+    ///
+    /// `update` is called with the row read by the read statement and the versions
+    /// to be bound to the write statement, and returns the values of the write statement.
+    /// It is called again on every retry, with a freshly read row. Returning an error
+    /// from it aborts the update with [`VersionedUpdateError::Rejected`].
+    ///
+    /// Fails with [`VersionedUpdateError::RowNotFound`] if the read statement returns
+    /// no rows, and with [`VersionedUpdateError::Conflict`] if all attempts lost
+    /// a race with concurrent updates.
+    pub async fn execute<K, R, W, E>(
+        &self,
+        session: &Session,
+        key: K,
+        mut update: impl FnMut(R, Versions) -> Result<W, E>,
+    ) -> Result<VersionedUpdateOutcome, VersionedUpdateError>
+    where
+        K: SerializeRow,
+        R: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>,
+        W: SerializeRow,
+        E: Into<Box<dyn Error + Send + Sync>>,
+    {
+        let mut attempt = 1;
+        loop {
+            let read_result = session
+                .execute_unpaged(&self.read, &key)
+                .await?
+                .into_rows_result()?;
+            let row = read_result
+                .maybe_first_row::<R>()?
+                .ok_or(VersionedUpdateError::RowNotFound)?;
+            let versions = Versions::after(self.version_in(&read_result)?)?;
+
+            let values =
+                update(row, versions).map_err(|err| VersionedUpdateError::Rejected(err.into()))?;
+            let write_result = session
+                .execute_unpaged(&self.write, values)
+                .await?
+                .into_rows_result()?;
+            if applied_flag(&write_result)?.ok_or(VersionedUpdateError::NotConditional)? {
+                return Ok(VersionedUpdateOutcome {
+                    version: versions.next,
+                    attempts: attempt,
+                });
+            }
+
+            // The result of a conditional update which was not applied
+            // contains the current values of the columns in its condition.
+            let conflict = VersionConflict {
+                attempts: attempt,
+                expected: versions.expected,
+                actual: self.version_in(&write_result)?,
+            };
+            if attempt >= self.backoff.max_attempts {
+                return Err(VersionedUpdateError::Conflict(conflict));
+            }
+            tracing::debug!("Retrying versioned update: {}", conflict);
+            tokio::time::sleep(self.backoff.delay(attempt)).await;
+            attempt += 1;
+        }
+    }
+
+    // Returns the version from the first row of the result,
+    // or `None` if there is no such row or column, or the version is null.
+    fn version_in(&self, result: &QueryRowsResult) -> Result<Option<i64>, VersionedUpdateError> {
+        let Some((idx, spec)) = result.column_specs().get_by_name(&self.version_column) else {
+            return Ok(None);
+        };
+        match first_row_value(result, idx)? {
+            None => Ok(None),
+            Some(CqlValue::BigInt(version)) => Ok(Some(version)),
+            Some(_) => Err(VersionedUpdateError::BadVersionColumnType {
+                column: self.version_column.clone(),
+                typ: spec.typ().clone().into_owned(),
+            }),
+        }
+    }
+}
+
+fn check_version_column(
+    specs: &[ColumnSpec<'_>],
+    version_column: &str,
+) -> Result<(), VersionedUpdateError> {
+    let spec = specs
+        .iter()
+        .find(|spec| spec.name() == version_column)
+        .ok_or_else(|| VersionedUpdateError::MissingVersionColumn(version_column.to_owned()))?;
+    match spec.typ() {
+        ColumnType::BigInt => Ok(()),
+        other => Err(VersionedUpdateError::BadVersionColumnType {
+            column: version_column.to_owned(),
+            typ: other.clone().into_owned(),
+        }),
+    }
+}
+
+/// The versions to be bound to the write statement of [`VersionedUpdate`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Versions {
+    /// The version that was read, to be bound to the condition
+    /// (`IF version = ?`). `None` if the version was null.
+    pub expected: Option<i64>,
+
+    /// The version to be written.
+    pub next: i64,
+}
+
+impl Versions {
+    fn after(expected: Option<i64>) -> Result<Self, VersionedUpdateError> {
+        let next = match expected {
+            None => 1,
+            Some(version) => version
+                .checked_add(1)
+                .ok_or(VersionedUpdateError::VersionOverflow)?,
+        };
+        Ok(Self { expected, next })
+    }
+}
+
+/// The result of a successful [`VersionedUpdate::execute`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct VersionedUpdateOutcome {
+    /// The version written by the update.
+    pub version: i64,
+
+    /// The number of read-modify-write cycles it took, including the successful one.
+    pub attempts: usize,
+}
+
+/// Controls how [`VersionedUpdate`] retries updates which lost
+/// a race with a concurrent update.
+///
+/// The delay before the n-th retry is chosen randomly from
+/// `[d / 2, d]`, where `d = min(min_delay * 2^(n-1), max_delay)`.
+/// The randomness prevents clients contending for the same row
+/// from retrying in lockstep.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentionBackoff {
+    max_attempts: usize,
+    min_delay: Duration,
+    max_delay: Duration,
+}
+
+impl ContentionBackoff {
+    /// Creates a backoff which makes at most `max_attempts` attempts in total.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `max_attempts` is 0 or `min_delay` is greater than `max_delay`.
+    pub fn new(max_attempts: usize, min_delay: Duration, max_delay: Duration) -> Self {
+        assert!(max_attempts > 0, "at least one attempt has to be allowed");
+        assert!(
+            min_delay <= max_delay,
+            "min_delay must not be greater than max_delay"
+        );
+        Self {
+            max_attempts,
+            min_delay,
+            max_delay,
+        }
+    }
+
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    // The delay before the retry following the given (1-based) attempt.
+    fn delay(&self, attempt: usize) -> Duration {
+        let exp = u32::try_from(attempt - 1).unwrap_or(u32::MAX).min(31);
+        let upper = self
+            .min_delay
+            .checked_mul(1 << exp)
+            .map_or(self.max_delay, |delay| delay.min(self.max_delay));
+        rand::thread_rng().gen_range(upper / 2..=upper)
+    }
+}
+
+impl Default for ContentionBackoff {
+    /// 5 attempts, with delays between 10 milliseconds and 1 second.
+    fn default() -> Self {
+        Self::new(5, Duration::from_millis(10), Duration::from_secs(1))
+    }
+}
+
+/// An error returned by [`VersionedUpdate`].
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum VersionedUpdateError {
+    /// Executing one of the statements failed.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// A statement returned a result of other kind than Rows.
+    #[error("Failed to convert the result into rows result: {0}")]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// A row returned by one of the statements failed to deserialize.
+    #[error("Failed to deserialize the row: {0}")]
+    RowDeserializationFailed(#[from] MaybeFirstRowError),
+
+    /// The read statement does not return the version column.
+    #[error("The read statement does not return the version column `{0}`")]
+    MissingVersionColumn(String),
+
+    /// The version column is not of type `bigint`.
+    #[error("The version column `{column}` is of type {typ:?}, expected BigInt")]
+    BadVersionColumnType {
+        column: String,
+        typ: ColumnType<'static>,
+    },
+
+    /// The result of the write statement has no `[applied]` column,
+    /// i.e. the statement is not a conditional one.
+    #[error("The write statement is not conditional, its result has no [applied] column")]
+    NotConditional,
+
+    /// The read statement returned no rows.
+    #[error("The row to be updated does not exist")]
+    RowNotFound,
+
+    /// The stored version is the greatest `bigint`, so it can't be incremented.
+    #[error(
+        "The version of the row to be updated can't be incremented, it is {}",
+        i64::MAX
+    )]
+    VersionOverflow,
+
+    /// The update closure returned an error.
+    #[error("The update was rejected: {0}")]
+    Rejected(Box<dyn Error + Send + Sync>),
+
+    /// Every attempt lost a race with a concurrent update.
+    #[error(transparent)]
+    Conflict(VersionConflict),
+}
+
+/// Describes the last conflict of a [`VersionedUpdate`] which ran out of attempts.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+#[error(
+    "Version conflict after {attempts} attempts: expected version {expected:?}, found {actual:?}"
+)]
+#[non_exhaustive]
+pub struct VersionConflict {
+    /// The number of attempts made.
+    pub attempts: usize,
+
+    /// The version the last attempt expected.
+    pub expected: Option<i64>,
+
+    /// The version found by the last attempt. `None` if the version is null,
+    /// or if the row was deleted in the meantime.
+    pub actual: Option<i64>,
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use scylla_cql::frame::response::result::{ColumnSpec, ColumnType, TableSpec};
+
+    use super::{check_version_column, ContentionBackoff, VersionedUpdateError, Versions};
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn versions_are_incremented() {
+        setup_tracing();
+        assert_eq!(
+            Versions::after(None).unwrap(),
+            Versions {
+                expected: None,
+                next: 1
+            }
+        );
+        assert_eq!(
+            Versions::after(Some(41)).unwrap(),
+            Versions {
+                expected: Some(41),
+                next: 42
+            }
+        );
+        assert!(matches!(
+            Versions::after(Some(i64::MAX)),
+            Err(VersionedUpdateError::VersionOverflow)
+        ));
+    }
+
+    #[test]
+    fn version_column_is_checked() {
+        setup_tracing();
+        let table = TableSpec::borrowed("ks", "accounts");
+        let specs = [
+            ColumnSpec::borrowed("balance", ColumnType::BigInt, table.clone()),
+            ColumnSpec::borrowed("version", ColumnType::BigInt, table.clone()),
+            ColumnSpec::borrowed("owner", ColumnType::Text, table),
+        ];
+
+        check_version_column(&specs, "version").unwrap();
+        assert!(matches!(
+            check_version_column(&specs, "revision"),
+            Err(VersionedUpdateError::MissingVersionColumn(column)) if column == "revision"
+        ));
+        assert!(matches!(
+            check_version_column(&specs, "owner"),
+            Err(VersionedUpdateError::BadVersionColumnType {
+                typ: ColumnType::Text,
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn backoff_delays_grow_and_are_capped() {
+        setup_tracing();
+        let backoff =
+            ContentionBackoff::new(100, Duration::from_millis(10), Duration::from_millis(100));
+        for _ in 0..100 {
+            let first = backoff.delay(1);
+            assert!(first >= Duration::from_millis(5) && first <= Duration::from_millis(10));
+            let third = backoff.delay(3);
+            assert!(third >= Duration::from_millis(20) && third <= Duration::from_millis(40));
+            for attempt in [5, 50, 99] {
+                let capped = backoff.delay(attempt);
+                assert!(
+                    capped >= Duration::from_millis(50) && capped <= Duration::from_millis(100)
+                );
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic]
+    fn backoff_requires_an_attempt() {
+        ContentionBackoff::new(0, Duration::ZERO, Duration::ZERO);
+    }
+}
@@ -1,11 +1,18 @@
 use std::borrow::Cow;
 use std::sync::Arc;
 
+use scylla_cql::types::serialize::batch::{BatchValues, BatchValuesIterator};
+use scylla_cql::types::serialize::row::{RowSerializationContext, SerializeRow, SerializedValues};
+use scylla_cql::types::serialize::{RowWriter, SerializationError};
+
+use thiserror::Error;
+
 use crate::history::HistoryListener;
 use crate::retry_policy::RetryPolicy;
 use crate::statement::{prepared_statement::PreparedStatement, query::Query};
 use crate::transport::execution_profile::ExecutionProfileHandle;
 
+use super::lexer;
 use super::StatementConfig;
 use super::{Consistency, SerialConsistency};
 pub use crate::frame::request::batch::BatchType;
@@ -121,6 +128,18 @@ impl Batch {
         self.config.timestamp
     }
 
+    /// Sets the time in seconds since the epoch which the server should consider
+    /// the current time when executing this batch, e.g. for TTL expiration.
+    /// Requires protocol v5, executing the batch on a v4 connection fails.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the current time override for this batch.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
     /// Set the retry policy for this batch, overriding the one from execution profile if not None.
     #[inline]
     pub fn set_retry_policy(&mut self, retry_policy: Option<Arc<dyn RetryPolicy>>) {
@@ -209,6 +228,241 @@ impl<'a: 'b, 'b> From<&'a BatchStatement>
     }
 }
 
+/// A batch of counter updates, executed as a `COUNTER` batch.
+///
+/// Counter and regular updates can't be mixed in a single batch. `CounterBatch` only accepts
+/// statements wrapped in [`CounterStatement`], so appending a regular statement
+/// is a compile-time error rather than an error returned by the database.
+/// It dereferences to [`Batch`], so it can be executed with [`Session::batch`](crate::Session::batch):
+///
+/// ```rust
+/// # use scylla::Session;
+/// # async fn example(session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+/// use scylla::batch::{CounterBatch, CounterStatement};
+/// use scylla::frame::value::Counter;
+///
+/// let increment = session
+///     .prepare("UPDATE ks.views SET count = count + ? WHERE page = ?")
+///     .await?;
+///
+/// let mut batch = CounterBatch::new();
+/// batch.append_statement(CounterStatement::new(increment.clone())?);
+/// batch.append_statement(CounterStatement::new(
+///     "UPDATE ks.views SET count = count + 1 WHERE page = 'home'",
+/// )?);
+///
+/// session.batch(&batch, ((Counter(1), "about"), ())).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CounterBatch {
+    batch: Batch,
+}
+
+impl CounterBatch {
+    /// Creates a new, empty counter batch.
+    pub fn new() -> Self {
+        Self {
+            batch: Batch::new(BatchType::Counter),
+        }
+    }
+
+    /// Creates an empty counter batch with the configuration, e.g. the consistency,
+    /// of an existing batch.
+    pub fn with_config_of(batch: &Batch) -> Self {
+        Self {
+            batch: Batch {
+                batch_type: BatchType::Counter,
+                ..Batch::new_from(batch)
+            },
+        }
+    }
+
+    /// Appends a counter update to the batch.
+    pub fn append_statement(&mut self, statement: CounterStatement) {
+        self.batch.statements.push(statement.0);
+    }
+
+    /// Converts into the underlying [`Batch`].
+    pub fn into_batch(self) -> Batch {
+        self.batch
+    }
+}
+
+impl Default for CounterBatch {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::ops::Deref for CounterBatch {
+    type Target = Batch;
+
+    fn deref(&self) -> &Batch {
+        &self.batch
+    }
+}
+
+/// A statement updating counters, which can be appended to a [`CounterBatch`].
+#[derive(Clone)]
+pub struct CounterStatement(BatchStatement);
+
+impl CounterStatement {
+    /// Marks the statement as a counter update.
+    ///
+    /// Returns an error if the statement is not an `UPDATE` or a `DELETE`.
+    /// Whether it modifies a counter table is only checked by the database.
+    pub fn new(statement: impl Into<BatchStatement>) -> Result<Self, NotCounterStatementError> {
+        let statement = statement.into();
+        let contents = match &statement {
+            BatchStatement::Query(query) => &query.contents,
+            BatchStatement::PreparedStatement(prepared) => prepared.get_statement(),
+        };
+        let is_counter_update = lexer::tokenize(contents)
+            .first()
+            .is_some_and(|first| first.is_keyword("UPDATE") || first.is_keyword("DELETE"));
+        if !is_counter_update {
+            return Err(NotCounterStatementError(contents.to_owned()));
+        }
+        Ok(Self(statement))
+    }
+}
+
+/// An error returned by [`CounterStatement::new`] for a statement
+/// which can't update counters.
+#[derive(Debug, Clone, Error)]
+#[error("Only UPDATE and DELETE statements can be a part of a counter batch: {0}")]
+pub struct NotCounterStatementError(pub String);
+
+/// Builds a [`Batch`] together with its values, binding each statement's values
+/// when the statement is appended.
+///
+/// The values of a prepared statement are serialized and type checked immediately,
+/// so a mismatch is reported for the offending statement rather than
+/// upon executing the whole batch, and the values can't get out of order
+/// with the statements.
+///
+/// ```rust
+/// # use scylla::Session;
+/// # async fn example(session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+/// use scylla::batch::{BatchBuilder, BatchType};
+///
+/// let insert = session
+///     .prepare("INSERT INTO ks.tab (a, b) VALUES (?, ?)")
+///     .await?;
+///
+/// let mut builder = BatchBuilder::new(BatchType::Unlogged);
+/// for (a, b) in [(1, "one"), (2, "two")] {
+///     builder.append_prepared(&insert, (a, b))?;
+/// }
+/// builder.append_query("DELETE FROM ks.tab WHERE a = 3");
+///
+/// let (batch, values) = builder.build();
+/// session.batch(&batch, &values).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct BatchBuilder {
+    batch: Batch,
+    values: Vec<SerializedValues>,
+}
+
+impl BatchBuilder {
+    /// Creates a builder of an empty batch of `batch_type` type.
+    pub fn new(batch_type: BatchType) -> Self {
+        Self {
+            batch: Batch::new(batch_type),
+            values: Vec::new(),
+        }
+    }
+
+    /// Appends a prepared statement with its values.
+    ///
+    /// Returns an error, leaving the batch unchanged, if the values don't match
+    /// the bind markers of the statement.
+    pub fn append_prepared(
+        &mut self,
+        statement: &PreparedStatement,
+        values: impl SerializeRow,
+    ) -> Result<&mut Self, SerializationError> {
+        let ctx = RowSerializationContext::from_prepared(statement.get_prepared_metadata());
+        let values = SerializedValues::from_serializable(&ctx, &values)?;
+        self.batch.append_statement(statement.clone());
+        self.values.push(values);
+        Ok(self)
+    }
+
+    /// Appends an unprepared statement without bind markers.
+    /// Statements with values should be prepared and appended with
+    /// [`append_prepared`](BatchBuilder::append_prepared).
+    pub fn append_query(&mut self, query: impl Into<Query>) -> &mut Self {
+        self.batch.append_statement(query.into());
+        self.values.push(SerializedValues::new());
+        self
+    }
+
+    /// Returns the number of appended statements.
+    pub fn len(&self) -> usize {
+        self.values.len()
+    }
+
+    /// Returns `true` if no statement has been appended.
+    pub fn is_empty(&self) -> bool {
+        self.values.is_empty()
+    }
+
+    /// Returns the batch, which can still be configured, e.g. with
+    /// [`Batch::set_consistency`], and the values to execute it with.
+    pub fn build(self) -> (Batch, SerializedBatchValues) {
+        (self.batch, SerializedBatchValues(self.values))
+    }
+}
+
+/// Values of a batch built with [`BatchBuilder`], serialized in advance.
+#[derive(Debug, Clone)]
+pub struct SerializedBatchValues(Vec<SerializedValues>);
+
+impl BatchValues for SerializedBatchValues {
+    type BatchValuesIter<'r> = SerializedBatchValuesIter<'r>;
+
+    fn batch_values_iter(&self) -> Self::BatchValuesIter<'_> {
+        SerializedBatchValuesIter(self.0.iter())
+    }
+}
+
+/// An iterator over [`SerializedBatchValues`].
+pub struct SerializedBatchValuesIter<'a>(std::slice::Iter<'a, SerializedValues>);
+
+impl<'a> BatchValuesIterator<'a> for SerializedBatchValuesIter<'a> {
+    #[inline]
+    fn serialize_next(
+        &mut self,
+        _ctx: &RowSerializationContext<'_>,
+        writer: &mut RowWriter,
+    ) -> Option<Result<(), SerializationError>> {
+        // The values were checked against the statement when it was appended.
+        let values = self.0.next()?;
+        writer.append_serialize_row(values);
+        Some(Ok(()))
+    }
+
+    #[inline]
+    fn is_empty_next(&mut self) -> Option<bool> {
+        self.0.next().map(SerializedValues::is_empty)
+    }
+
+    #[inline]
+    fn skip_next(&mut self) -> Option<()> {
+        self.0.next().map(|_| ())
+    }
+
+    #[inline]
+    fn count(self) -> usize {
+        self.0.len()
+    }
+}
+
 pub(crate) mod batch_values {
     use scylla_cql::types::serialize::batch::BatchValues;
     use scylla_cql::types::serialize::batch::BatchValuesIterator;
@@ -347,3 +601,53 @@ pub(crate) mod batch_values {
         }
     }
 }
+
+mod doctests {
+    /// ```compile_fail
+    ///
+    /// use scylla::batch::CounterBatch;
+    /// use scylla::query::Query;
+    ///
+    /// let mut batch = CounterBatch::new();
+    /// batch.append_statement(Query::new("INSERT INTO ks.t (a) VALUES (1)"));
+    /// ```
+    fn _test_regular_statement_in_counter_batch() {}
+
+    /// ```compile_fail
+    ///
+    /// use scylla::batch::{Batch, CounterStatement};
+    ///
+    /// let mut batch = Batch::default();
+    /// batch.append_statement(
+    ///     CounterStatement::new("UPDATE ks.t SET c = c + 1 WHERE a = 1").unwrap(),
+    /// );
+    /// ```
+    fn _test_counter_statement_in_regular_batch() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Batch, BatchType, CounterBatch, CounterStatement};
+    use crate::statement::Consistency;
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn counter_batch() {
+        setup_tracing();
+        let mut config = Batch::new(BatchType::Unlogged);
+        config.set_consistency(Consistency::Quorum);
+        let mut batch = CounterBatch::with_config_of(&config);
+        batch.append_statement(
+            CounterStatement::new("UPDATE ks.t SET c = c + 1 WHERE a = 1").unwrap(),
+        );
+        batch.append_statement(
+            CounterStatement::new(" /* comment */ delete c FROM ks.t WHERE a = 2").unwrap(),
+        );
+        assert!(matches!(batch.get_type(), BatchType::Counter));
+        assert_eq!(batch.get_consistency(), Some(Consistency::Quorum));
+        assert_eq!(batch.statements.len(), 2);
+
+        assert!(CounterStatement::new("INSERT INTO ks.t (a, c) VALUES (1, 1)").is_err());
+        assert!(CounterStatement::new("SELECT * FROM ks.t").is_err());
+    }
+}
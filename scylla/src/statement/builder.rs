@@ -0,0 +1,745 @@
+//! Building statements from parts known only at runtime, e.g. the set of
+//! columns to update, without concatenating strings.
+//!
+//! Keyspace, table and column names are validated and quoted when needed,
+//! and all values are passed as bind markers (`?`), in the order in which
+//! the corresponding methods were called. The result is a [`Query`],
+//! which can be executed directly or prepared with [`Session::prepare`](crate::Session::prepare):
+//!
+//! ```rust
+//! # use scylla::Session;
+//! # async fn example(session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+//! use scylla::statement::builder::{select, update};
+//!
+//! let query = select("users")
+//!     .in_keyspace("ks")
+//!     .columns(["name", "email"])
+//!     .where_eq("id")
+//!     .build()?;
+//! assert_eq!(query.contents, "SELECT name, email FROM ks.users WHERE id = ?");
+//! let prepared = session.prepare(query).await?;
+//! session.execute_unpaged(&prepared, (42,)).await?;
+//!
+//! // Only the provided fields are updated.
+//! let mut statement = update("users").in_keyspace("ks");
+//! let mut values: Vec<String> = Vec::new();
+//! for (column, value) in [("name", Some("Bob")), ("email", None)] {
+//!     if let Some(value) = value {
+//!         statement = statement.set(column);
+//!         values.push(value.to_owned());
+//!     }
+//! }
+//! let query = statement.where_eq("id").build()?;
+//! assert_eq!(query.contents, "UPDATE ks.users SET name = ? WHERE id = ?");
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Write;
+
+use thiserror::Error;
+
+use super::query::Query;
+
+/// Creates a builder of a `SELECT` statement reading from the given table.
+pub fn select(table: impl Into<String>) -> SelectBuilder {
+    SelectBuilder {
+        target: Target::new(table.into()),
+        columns: Vec::new(),
+        relations: Vec::new(),
+        order_by: Vec::new(),
+        limit: None,
+        allow_filtering: false,
+    }
+}
+
+/// Creates a builder of an `INSERT` statement writing to the given table.
+pub fn insert_into(table: impl Into<String>) -> InsertBuilder {
+    InsertBuilder {
+        target: Target::new(table.into()),
+        columns: Vec::new(),
+        if_not_exists: false,
+        using: Using::default(),
+    }
+}
+
+/// Creates a builder of an `UPDATE` statement modifying the given table.
+pub fn update(table: impl Into<String>) -> UpdateBuilder {
+    UpdateBuilder {
+        target: Target::new(table.into()),
+        assignments: Vec::new(),
+        relations: Vec::new(),
+        if_exists: false,
+        using: Using::default(),
+    }
+}
+
+/// Creates a builder of a `DELETE` statement deleting from the given table.
+pub fn delete_from(table: impl Into<String>) -> DeleteBuilder {
+    DeleteBuilder {
+        target: Target::new(table.into()),
+        columns: Vec::new(),
+        relations: Vec::new(),
+        if_exists: false,
+        timestamp: None,
+    }
+}
+
+/// An error returned when a statement can't be built from the provided parts.
+#[derive(Debug, Clone, PartialEq, Eq, Error)]
+#[non_exhaustive]
+pub enum StatementBuilderError {
+    /// A keyspace or table name is empty or contains characters other than
+    /// alphanumeric characters and underscores.
+    #[error("Invalid keyspace or table name: {0:?}")]
+    InvalidName(String),
+
+    /// A column name is empty.
+    #[error("Column name must not be empty")]
+    EmptyColumnName,
+
+    /// An `INSERT` without values or an `UPDATE` without assignments.
+    #[error("{0} statement requires at least one column")]
+    NoColumns(&'static str),
+
+    /// An `UPDATE` or `DELETE` without a `WHERE` clause.
+    #[error("{0} statement requires a WHERE clause")]
+    NoWhereClause(&'static str),
+}
+
+/// The order of rows in a `SELECT` statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Order {
+    /// Ascending order.
+    Asc,
+    /// Descending order.
+    Desc,
+}
+
+/// Builder of a `SELECT` statement, created with [`select`].
+#[derive(Debug, Clone)]
+pub struct SelectBuilder {
+    target: Target,
+    columns: Vec<String>,
+    relations: Vec<Relation>,
+    order_by: Vec<(String, Order)>,
+    limit: Option<u32>,
+    allow_filtering: bool,
+}
+
+/// Builder of an `INSERT` statement, created with [`insert_into`].
+#[derive(Debug, Clone)]
+pub struct InsertBuilder {
+    target: Target,
+    columns: Vec<String>,
+    if_not_exists: bool,
+    using: Using,
+}
+
+/// Builder of an `UPDATE` statement, created with [`update`].
+#[derive(Debug, Clone)]
+pub struct UpdateBuilder {
+    target: Target,
+    assignments: Vec<String>,
+    relations: Vec<Relation>,
+    if_exists: bool,
+    using: Using,
+}
+
+/// Builder of a `DELETE` statement, created with [`delete_from`].
+#[derive(Debug, Clone)]
+pub struct DeleteBuilder {
+    target: Target,
+    columns: Vec<String>,
+    relations: Vec<Relation>,
+    if_exists: bool,
+    timestamp: Option<i64>,
+}
+
+// Methods common to all builders.
+macro_rules! impl_target_methods {
+    ($builder:ty) => {
+        impl $builder {
+            /// Sets the keyspace of the table. Without it, the keyspace
+            /// of the session is used.
+            pub fn in_keyspace(mut self, keyspace: impl Into<String>) -> Self {
+                self.target.keyspace = Some(keyspace.into());
+                self
+            }
+        }
+    };
+}
+
+// Methods adding relations to the `WHERE` clause, each of them binding one value.
+macro_rules! impl_where_methods {
+    ($builder:ty) => {
+        impl $builder {
+            /// Adds the `column = ?` relation.
+            pub fn where_eq(self, column: impl Into<String>) -> Self {
+                self.relation(column.into(), "=")
+            }
+
+            /// Adds the `column < ?` relation.
+            pub fn where_lt(self, column: impl Into<String>) -> Self {
+                self.relation(column.into(), "<")
+            }
+
+            /// Adds the `column <= ?` relation.
+            pub fn where_le(self, column: impl Into<String>) -> Self {
+                self.relation(column.into(), "<=")
+            }
+
+            /// Adds the `column > ?` relation.
+            pub fn where_gt(self, column: impl Into<String>) -> Self {
+                self.relation(column.into(), ">")
+            }
+
+            /// Adds the `column >= ?` relation.
+            pub fn where_ge(self, column: impl Into<String>) -> Self {
+                self.relation(column.into(), ">=")
+            }
+
+            /// Adds the `column IN ?` relation. The bound value is a list.
+            pub fn where_in(self, column: impl Into<String>) -> Self {
+                self.relation(column.into(), "IN")
+            }
+
+            fn relation(mut self, column: String, operator: &'static str) -> Self {
+                self.relations.push(Relation { column, operator });
+                self
+            }
+        }
+    };
+}
+
+impl_target_methods!(SelectBuilder);
+impl_target_methods!(InsertBuilder);
+impl_target_methods!(UpdateBuilder);
+impl_target_methods!(DeleteBuilder);
+impl_where_methods!(SelectBuilder);
+impl_where_methods!(UpdateBuilder);
+impl_where_methods!(DeleteBuilder);
+
+impl SelectBuilder {
+    /// Adds a selected column. Without any, all columns are selected (`SELECT *`).
+    pub fn column(mut self, column: impl Into<String>) -> Self {
+        self.columns.push(column.into());
+        self
+    }
+
+    /// Adds selected columns.
+    pub fn columns<S: Into<String>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.columns.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Orders the rows by the given clustering column.
+    pub fn order_by(mut self, column: impl Into<String>, order: Order) -> Self {
+        self.order_by.push((column.into(), order));
+        self
+    }
+
+    /// Limits the number of returned rows.
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Adds `ALLOW FILTERING`. It also permits the statement to be executed despite
+    /// the `ALLOW FILTERING` guardrail, see [`Query::set_allow_filtering_permitted`].
+    pub fn allow_filtering(mut self) -> Self {
+        self.allow_filtering = true;
+        self
+    }
+
+    /// Builds the statement.
+    pub fn build(&self) -> Result<Query, StatementBuilderError> {
+        let mut cql = String::from("SELECT ");
+        if self.columns.is_empty() {
+            cql.push('*');
+        } else {
+            write_list(&mut cql, &self.columns, |cql, column| {
+                write_column(cql, column)
+            })?;
+        }
+        cql.push_str(" FROM ");
+        self.target.write(&mut cql)?;
+        write_where_clause(&mut cql, &self.relations)?;
+        if !self.order_by.is_empty() {
+            cql.push_str(" ORDER BY ");
+            write_list(&mut cql, &self.order_by, |cql, (column, order)| {
+                write_column(cql, column)?;
+                cql.push_str(match order {
+                    Order::Asc => " ASC",
+                    Order::Desc => " DESC",
+                });
+                Ok(())
+            })?;
+        }
+        if let Some(limit) = self.limit {
+            let _ = write!(cql, " LIMIT {}", limit);
+        }
+        if self.allow_filtering {
+            cql.push_str(" ALLOW FILTERING");
+        }
+
+        let mut query = Query::new(cql);
+        query.set_allow_filtering_permitted(self.allow_filtering);
+        Ok(query)
+    }
+}
+
+impl InsertBuilder {
+    /// Adds a column whose value is bound.
+    pub fn value(mut self, column: impl Into<String>) -> Self {
+        self.columns.push(column.into());
+        self
+    }
+
+    /// Adds columns whose values are bound.
+    pub fn values<S: Into<String>>(mut self, columns: impl IntoIterator<Item = S>) -> Self {
+        self.columns.extend(columns.into_iter().map(Into::into));
+        self
+    }
+
+    /// Adds `IF NOT EXISTS`, making the statement a lightweight transaction.
+    pub fn if_not_exists(mut self) -> Self {
+        self.if_not_exists = true;
+        self
+    }
+
+    /// Sets the time to live of the inserted values, in seconds.
+    pub fn using_ttl(mut self, ttl: u32) -> Self {
+        self.using.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the write timestamp, in microseconds.
+    pub fn using_timestamp(mut self, timestamp: i64) -> Self {
+        self.using.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Builds the statement.
+    pub fn build(&self) -> Result<Query, StatementBuilderError> {
+        if self.columns.is_empty() {
+            return Err(StatementBuilderError::NoColumns("INSERT"));
+        }
+        let mut cql = String::from("INSERT INTO ");
+        self.target.write(&mut cql)?;
+        cql.push_str(" (");
+        write_list(&mut cql, &self.columns, |cql, column| {
+            write_column(cql, column)
+        })?;
+        cql.push_str(") VALUES (");
+        write_list(&mut cql, &self.columns, |cql, _| {
+            cql.push('?');
+            Ok(())
+        })?;
+        cql.push(')');
+        if self.if_not_exists {
+            cql.push_str(" IF NOT EXISTS");
+        }
+        self.using.write(&mut cql);
+        Ok(Query::new(cql))
+    }
+}
+
+impl UpdateBuilder {
+    /// Adds the `column = ?` assignment.
+    pub fn set(mut self, column: impl Into<String>) -> Self {
+        self.assignments.push(column.into());
+        self
+    }
+
+    /// Adds `IF EXISTS`, making the statement a lightweight transaction.
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+
+    /// Sets the time to live of the updated values, in seconds.
+    pub fn using_ttl(mut self, ttl: u32) -> Self {
+        self.using.ttl = Some(ttl);
+        self
+    }
+
+    /// Sets the write timestamp, in microseconds.
+    pub fn using_timestamp(mut self, timestamp: i64) -> Self {
+        self.using.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Builds the statement.
+    pub fn build(&self) -> Result<Query, StatementBuilderError> {
+        if self.assignments.is_empty() {
+            return Err(StatementBuilderError::NoColumns("UPDATE"));
+        }
+        if self.relations.is_empty() {
+            return Err(StatementBuilderError::NoWhereClause("UPDATE"));
+        }
+        let mut cql = String::from("UPDATE ");
+        self.target.write(&mut cql)?;
+        self.using.write(&mut cql);
+        cql.push_str(" SET ");
+        write_list(&mut cql, &self.assignments, |cql, column| {
+            write_column(cql, column)?;
+            cql.push_str(" = ?");
+            Ok(())
+        })?;
+        write_where_clause(&mut cql, &self.relations)?;
+        if self.if_exists {
+            cql.push_str(" IF EXISTS");
+        }
+        Ok(Query::new(cql))
+    }
+}
+
+impl DeleteBuilder {
+    /// Adds a column whose value is deleted. Without any, whole rows are deleted.
+    pub fn column(mut self, column: impl Into<String>) -> Self {
+        self.columns.push(column.into());
+        self
+    }
+
+    /// Adds `IF EXISTS`, making the statement a lightweight transaction.
+    pub fn if_exists(mut self) -> Self {
+        self.if_exists = true;
+        self
+    }
+
+    /// Sets the write timestamp, in microseconds.
+    pub fn using_timestamp(mut self, timestamp: i64) -> Self {
+        self.timestamp = Some(timestamp);
+        self
+    }
+
+    /// Builds the statement.
+    pub fn build(&self) -> Result<Query, StatementBuilderError> {
+        if self.relations.is_empty() {
+            return Err(StatementBuilderError::NoWhereClause("DELETE"));
+        }
+        let mut cql = String::from("DELETE ");
+        if !self.columns.is_empty() {
+            write_list(&mut cql, &self.columns, |cql, column| {
+                write_column(cql, column)
+            })?;
+            cql.push(' ');
+        }
+        cql.push_str("FROM ");
+        self.target.write(&mut cql)?;
+        Using {
+            ttl: None,
+            timestamp: self.timestamp,
+        }
+        .write(&mut cql);
+        write_where_clause(&mut cql, &self.relations)?;
+        if self.if_exists {
+            cql.push_str(" IF EXISTS");
+        }
+        Ok(Query::new(cql))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Target {
+    keyspace: Option<String>,
+    table: String,
+}
+
+impl Target {
+    fn new(table: String) -> Self {
+        Self {
+            keyspace: None,
+            table,
+        }
+    }
+
+    fn write(&self, cql: &mut String) -> Result<(), StatementBuilderError> {
+        if let Some(keyspace) = &self.keyspace {
+            write_schema_name(cql, keyspace)?;
+            cql.push('.');
+        }
+        write_schema_name(cql, &self.table)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Relation {
+    column: String,
+    operator: &'static str,
+}
+
+#[derive(Debug, Clone, Default)]
+struct Using {
+    ttl: Option<u32>,
+    timestamp: Option<i64>,
+}
+
+impl Using {
+    fn write(&self, cql: &mut String) {
+        let mut keyword = " USING ";
+        if let Some(ttl) = self.ttl {
+            let _ = write!(cql, "{}TTL {}", keyword, ttl);
+            keyword = " AND ";
+        }
+        if let Some(timestamp) = self.timestamp {
+            let _ = write!(cql, "{}TIMESTAMP {}", keyword, timestamp);
+        }
+    }
+}
+
+fn write_where_clause(
+    cql: &mut String,
+    relations: &[Relation],
+) -> Result<(), StatementBuilderError> {
+    let mut keyword = " WHERE ";
+    for relation in relations {
+        cql.push_str(keyword);
+        write_column(cql, &relation.column)?;
+        let _ = write!(cql, " {} ?", relation.operator);
+        keyword = " AND ";
+    }
+    Ok(())
+}
+
+fn write_list<T>(
+    cql: &mut String,
+    items: &[T],
+    mut write_item: impl FnMut(&mut String, &T) -> Result<(), StatementBuilderError>,
+) -> Result<(), StatementBuilderError> {
+    for (idx, item) in items.iter().enumerate() {
+        if idx > 0 {
+            cql.push_str(", ");
+        }
+        write_item(cql, item)?;
+    }
+    Ok(())
+}
+
+// Keyspace and table names may only consist of alphanumeric characters and underscores.
+// They are quoted if they contain uppercase letters, so that their case is preserved.
+fn write_schema_name(cql: &mut String, name: &str) -> Result<(), StatementBuilderError> {
+    if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') {
+        return Err(StatementBuilderError::InvalidName(name.to_owned()));
+    }
+    write_identifier(cql, name);
+    Ok(())
+}
+
+fn write_column(cql: &mut String, name: &str) -> Result<(), StatementBuilderError> {
+    if name.is_empty() {
+        return Err(StatementBuilderError::EmptyColumnName);
+    }
+    write_identifier(cql, name);
+    Ok(())
+}
+
+// Writes the name as it is, if the database would interpret it unquoted as the same name.
+// Otherwise, encloses it in double quotes, doubling the quotes inside.
+fn write_identifier(cql: &mut String, name: &str) {
+    let is_plain = name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        && !RESERVED_KEYWORDS.contains(&name);
+    if is_plain {
+        cql.push_str(name);
+    } else {
+        cql.push('"');
+        cql.push_str(&name.replace('"', "\"\""));
+        cql.push('"');
+    }
+}
+
+// Keywords which can't be used as unquoted identifiers.
+const RESERVED_KEYWORDS: &[&str] = &[
+    "add",
+    "allow",
+    "alter",
+    "and",
+    "apply",
+    "asc",
+    "authorize",
+    "batch",
+    "begin",
+    "by",
+    "columnfamily",
+    "create",
+    "delete",
+    "desc",
+    "describe",
+    "drop",
+    "entries",
+    "execute",
+    "from",
+    "full",
+    "grant",
+    "if",
+    "in",
+    "index",
+    "infinity",
+    "insert",
+    "into",
+    "is",
+    "keyspace",
+    "limit",
+    "materialized",
+    "mbean",
+    "mbeans",
+    "modify",
+    "nan",
+    "norecursive",
+    "not",
+    "null",
+    "of",
+    "on",
+    "or",
+    "order",
+    "primary",
+    "rename",
+    "replace",
+    "revoke",
+    "schema",
+    "select",
+    "set",
+    "table",
+    "to",
+    "token",
+    "truncate",
+    "unlogged",
+    "unset",
+    "update",
+    "use",
+    "using",
+    "view",
+    "where",
+    "with",
+];
+
+#[cfg(test)]
+mod tests {
+    use super::{delete_from, insert_into, select, update, Order, StatementBuilderError};
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn build_select() {
+        setup_tracing();
+        let query = select("users").build().unwrap();
+        assert_eq!(query.contents, "SELECT * FROM users");
+
+        let query = select("events")
+            .in_keyspace("Analytics")
+            .column("id")
+            .column("Name")
+            .column("select")
+            .where_eq("tenant")
+            .where_ge("day")
+            .where_in("kind")
+            .order_by("day", Order::Desc)
+            .limit(100)
+            .allow_filtering()
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.contents,
+            "SELECT id, \"Name\", \"select\" FROM \"Analytics\".events \
+             WHERE tenant = ? AND day >= ? AND kind IN ? \
+             ORDER BY day DESC LIMIT 100 ALLOW FILTERING"
+        );
+        assert!(query.config.allow_filtering_permitted);
+    }
+
+    #[test]
+    fn build_modifications() {
+        setup_tracing();
+        let query = insert_into("users")
+            .values(["id", "name"])
+            .if_not_exists()
+            .using_ttl(3600)
+            .using_timestamp(1000)
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.contents,
+            "INSERT INTO users (id, name) VALUES (?, ?) IF NOT EXISTS USING TTL 3600 AND TIMESTAMP 1000"
+        );
+
+        let query = update("users")
+            .in_keyspace("ks")
+            .using_ttl(60)
+            .set("name")
+            .set("email")
+            .where_eq("id")
+            .if_exists()
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.contents,
+            "UPDATE ks.users USING TTL 60 SET name = ?, email = ? WHERE id = ? IF EXISTS"
+        );
+
+        let query = delete_from("users")
+            .column("email")
+            .using_timestamp(5)
+            .where_eq("id")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.contents,
+            "DELETE email FROM users USING TIMESTAMP 5 WHERE id = ?"
+        );
+
+        let query = delete_from("users").where_eq("id").build().unwrap();
+        assert_eq!(query.contents, "DELETE FROM users WHERE id = ?");
+    }
+
+    #[test]
+    fn injection_is_prevented() {
+        setup_tracing();
+        let query = select("users")
+            .where_eq("id = 1; DROP TABLE users; --\"")
+            .build()
+            .unwrap();
+        assert_eq!(
+            query.contents,
+            "SELECT * FROM users WHERE \"id = 1; DROP TABLE users; --\"\"\" = ?"
+        );
+
+        assert_eq!(
+            select("users; DROP TABLE users").build().err(),
+            Some(StatementBuilderError::InvalidName(
+                "users; DROP TABLE users".to_owned()
+            ))
+        );
+        assert_eq!(
+            select("users").in_keyspace("ks.x").build().err(),
+            Some(StatementBuilderError::InvalidName("ks.x".to_owned()))
+        );
+        assert_eq!(
+            select("users").column("").build().err(),
+            Some(StatementBuilderError::EmptyColumnName)
+        );
+    }
+
+    #[test]
+    fn incomplete_statements_are_rejected() {
+        setup_tracing();
+        assert_eq!(
+            insert_into("users").build().err(),
+            Some(StatementBuilderError::NoColumns("INSERT"))
+        );
+        assert_eq!(
+            update("users").where_eq("id").build().err(),
+            Some(StatementBuilderError::NoColumns("UPDATE"))
+        );
+        assert_eq!(
+            update("users").set("name").build().err(),
+            Some(StatementBuilderError::NoWhereClause("UPDATE"))
+        );
+        assert_eq!(
+            delete_from("users").build().err(),
+            Some(StatementBuilderError::NoWhereClause("DELETE"))
+        );
+    }
+}
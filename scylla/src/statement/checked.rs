@@ -0,0 +1,393 @@
+//! Statements checked against the database schema at compile time.
+//!
+//! The [`checked_query!`](crate::checked_query) macro parses a statement,
+//! validates it against a schema file and infers the Rust types of its bind
+//! markers and of the columns it returns. The result is a [`CheckedQuery`],
+//! which only accepts values of those types and deserializes rows into them.
+
+use std::marker::PhantomData;
+
+use thiserror::Error;
+
+use super::prepared_statement::PreparedStatement;
+use super::query::Query;
+use crate::deserialize::{DeserializationError, DeserializeRow, TypeCheckError};
+use crate::serialize::row::SerializeRow;
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::{IntoRowsResultError, QueryResult, RowsError};
+use crate::Session;
+
+/// A statement checked against the schema at compile time, created by
+/// [`checked_query!`](crate::checked_query).
+///
+/// `V` is the tuple of the types of the bind markers and `R` is the tuple
+/// of the types of the returned columns, `()` for statements other than `SELECT`.
+/// The types are still checked by the driver upon execution, so that a schema
+/// differing from the one known at compile time results in an error.
+pub struct CheckedQuery<V, R> {
+    query: Query,
+    _phantom: PhantomData<fn(V) -> R>,
+}
+
+impl<V, R> Clone for CheckedQuery<V, R> {
+    fn clone(&self) -> Self {
+        Self {
+            query: self.query.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<V, R> CheckedQuery<V, R> {
+    // Only called by the macro, after checking the statement.
+    #[doc(hidden)]
+    pub fn new_checked(contents: &str) -> Self {
+        Self {
+            query: Query::new(contents),
+            _phantom: PhantomData,
+        }
+    }
+
+    /// Returns the CQL statement.
+    pub fn contents(&self) -> &str {
+        &self.query.contents
+    }
+
+    /// Returns the underlying [`Query`].
+    pub fn query(&self) -> &Query {
+        &self.query
+    }
+
+    /// Returns the underlying [`Query`] mutably, e.g. to set its consistency.
+    pub fn query_mut(&mut self) -> &mut Query {
+        &mut self.query
+    }
+
+    /// Converts into the underlying [`Query`], dropping the types.
+    pub fn into_query(self) -> Query {
+        self.query
+    }
+}
+
+impl<V: SerializeRow, R> CheckedQuery<V, R> {
+    /// Executes the statement with [`Session::query_unpaged`].
+    pub async fn query_unpaged(
+        &self,
+        session: &Session,
+        values: V,
+    ) -> Result<QueryResult, QueryError> {
+        session.query_unpaged(self.query.clone(), values).await
+    }
+
+    /// Executes the statement with [`Session::query_unpaged`] and deserializes
+    /// all the returned rows.
+    pub async fn query_rows(
+        &self,
+        session: &Session,
+        values: V,
+    ) -> Result<Vec<R>, CheckedQueryError>
+    where
+        R: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>,
+    {
+        deserialize_rows(self.query_unpaged(session, values).await?)
+    }
+
+    /// Prepares the statement, keeping its types.
+    pub async fn prepare(
+        &self,
+        session: &Session,
+    ) -> Result<CheckedPreparedStatement<V, R>, QueryError> {
+        Ok(CheckedPreparedStatement {
+            statement: session.prepare(self.query.clone()).await?,
+            _phantom: PhantomData,
+        })
+    }
+}
+
+/// A prepared [`CheckedQuery`].
+pub struct CheckedPreparedStatement<V, R> {
+    statement: PreparedStatement,
+    _phantom: PhantomData<fn(V) -> R>,
+}
+
+impl<V, R> Clone for CheckedPreparedStatement<V, R> {
+    fn clone(&self) -> Self {
+        Self {
+            statement: self.statement.clone(),
+            _phantom: PhantomData,
+        }
+    }
+}
+
+impl<V, R> CheckedPreparedStatement<V, R> {
+    /// Returns the underlying [`PreparedStatement`].
+    pub fn statement(&self) -> &PreparedStatement {
+        &self.statement
+    }
+
+    /// Returns the underlying [`PreparedStatement`] mutably, e.g. to set its consistency.
+    pub fn statement_mut(&mut self) -> &mut PreparedStatement {
+        &mut self.statement
+    }
+
+    /// Converts into the underlying [`PreparedStatement`], dropping the types.
+    pub fn into_statement(self) -> PreparedStatement {
+        self.statement
+    }
+}
+
+impl<V: SerializeRow, R> CheckedPreparedStatement<V, R> {
+    /// Executes the statement with [`Session::execute_unpaged`].
+    pub async fn execute_unpaged(
+        &self,
+        session: &Session,
+        values: V,
+    ) -> Result<QueryResult, QueryError> {
+        session.execute_unpaged(&self.statement, values).await
+    }
+
+    /// Executes the statement with [`Session::execute_unpaged`] and deserializes
+    /// all the returned rows.
+    pub async fn execute_rows(
+        &self,
+        session: &Session,
+        values: V,
+    ) -> Result<Vec<R>, CheckedQueryError>
+    where
+        R: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>,
+    {
+        deserialize_rows(self.execute_unpaged(session, values).await?)
+    }
+}
+
+fn deserialize_rows<R: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>>(
+    result: QueryResult,
+) -> Result<Vec<R>, CheckedQueryError> {
+    let rows = result
+        .into_rows_result()?
+        .into_rows::<R>()
+        .map_err(|RowsError::TypeCheckFailed(err)| err)?;
+    Ok(rows.collect::<Result<_, _>>()?)
+}
+
+/// An error returned when executing a [`CheckedQuery`] and deserializing its rows.
+#[derive(Debug, Error)]
+#[non_exhaustive]
+pub enum CheckedQueryError {
+    /// Failed to execute the statement.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// The response was not of Rows kind, or its metadata could not be deserialized.
+    #[error(transparent)]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// The returned columns don't match the types inferred at compile time,
+    /// e.g. because the schema has changed since.
+    #[error("Type check failed: {0}")]
+    TypeCheckFailed(#[from] TypeCheckError),
+
+    /// Failed to deserialize a row.
+    #[error("Deserialization failed: {0}")]
+    DeserializationFailed(#[from] DeserializationError),
+}
+
+mod doctests {
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "SELECT title FROM ks.messages",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_unknown_column() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "SELECT * FROM ks.channels",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_unknown_table() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "SELECT * FROM messages",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_unqualified_table_without_keyspace() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "INSERT INTO ks.messages (channel_id, body) VALUES (?, ?)",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_insert_missing_primary_key() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "INSERT INTO ks.messages (channel_id, message_id) VALUES (?, ?, ?)",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_insert_value_count_mismatch() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "UPDATE ks.messages SET channel_id = ? WHERE message_id = ?",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_update_primary_key() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "SELECT * FROM ks.messages WHERE token(message_id) > ?",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_token_not_partition_key() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "TRUNCATE ks.messages",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_unsupported_statement() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "SELECT by_value FROM ks.readings",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_map_with_float_keys() {}
+
+    /// ```compile_fail
+    ///
+    /// scylla::checked_query!(
+    ///     "UPDATE ks.readings SET by_address = ? WHERE sensor_id = ?",
+    ///     schema = "src/statement/checked_tests.cql",
+    /// );
+    /// ```
+    fn _test_map_with_udt_keys() {}
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::IpAddr;
+
+    use super::CheckedQuery;
+    use crate::checked_query;
+    use crate::frame::response::result::CqlValue;
+    use crate::frame::value::{Counter, CqlDate, CqlTimestamp, CqlTimeuuid};
+    use crate::test_utils::setup_tracing;
+
+    type MessageRow = (
+        i32,
+        CqlTimeuuid,
+        Option<String>,
+        Option<String>,
+        Option<bool>,
+    );
+    type BodyRow = (Option<String>, Option<i64>, i64);
+    type ScoresRow = (Option<HashMap<String, Vec<i64>>>, Option<CqlValue>);
+    type UpdateUserValues = (i64, Vec<String>, String, Option<Vec<i64>>, uuid::Uuid);
+
+    #[test]
+    fn checked_select() {
+        setup_tracing();
+        let query: CheckedQuery<(i32,), MessageRow> = checked_query!(
+            "SELECT * FROM ks.messages WHERE channel_id = ?",
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+        assert_eq!(
+            query.contents(),
+            "SELECT * FROM ks.messages WHERE channel_id = ?"
+        );
+
+        let _: CheckedQuery<(i32, CqlTimeuuid, i32), BodyRow> = checked_query!(
+            "SELECT body, writetime(body), token(channel_id) FROM messages
+                WHERE channel_id = ? AND message_id > ? LIMIT ?",
+            schema = "src/statement/checked_tests.cql",
+            keyspace = "ks",
+            crate = crate,
+        );
+
+        let _: CheckedQuery<(Vec<i32>, i64), (i64,)> = checked_query!(
+            "SELECT count(*) FROM ks.messages WHERE channel_id IN ? AND token(channel_id) > ?",
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+
+        let _: CheckedQuery<(String, IpAddr), ScoresRow> = checked_query!(
+            r#"SELECT "Scores", address FROM ks.users WHERE tags CONTAINS ? AND last_ip = :ip ALLOW FILTERING"#,
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+
+        let _: CheckedQuery<(), (String,)> = checked_query!(
+            "SELECT JSON * FROM ks.users",
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+    }
+
+    #[test]
+    fn checked_modifications() {
+        setup_tracing();
+        let _: CheckedQuery<(i32, CqlTimeuuid, Option<String>, i32), ()> = checked_query!(
+            "INSERT INTO ks.messages (channel_id, message_id, body) VALUES (?, ?, ?) USING TTL ?",
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+
+        let _: CheckedQuery<UpdateUserValues, ()> = checked_query!(
+            r#"UPDATE ks.users USING TIMESTAMP ? SET tags = tags + ?, "Scores"[?] = ? WHERE id = ?"#,
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+
+        let _: CheckedQuery<(Counter, i32), ()> = checked_query!(
+            "UPDATE ks.views SET count = count + ? WHERE channel_id = ?",
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+
+        let _: CheckedQuery<(String, uuid::Uuid, IpAddr), ()> = checked_query!(
+            r#"DELETE "Scores"[?] FROM ks.users WHERE id = ? IF last_ip = ?"#,
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+    }
+
+    #[test]
+    fn checked_map_keys() {
+        setup_tracing();
+        let _: CheckedQuery<(i32,), (Option<HashMap<CqlDate, f64>>,)> = checked_query!(
+            "SELECT by_day FROM ks.readings WHERE sensor_id = ?",
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+
+        // Elements of a map with float keys are accessed without a HashMap.
+        let _: CheckedQuery<(f64, Option<CqlTimestamp>, i32), ()> = checked_query!(
+            "UPDATE ks.readings SET by_value[?] = ? WHERE sensor_id = ?",
+            schema = "src/statement/checked_tests.cql",
+            crate = crate,
+        );
+    }
+}
@@ -0,0 +1,297 @@
+//! Statements executed in many keyspaces, e.g. in applications keeping
+//! the data of each tenant in a separate keyspace.
+//!
+//! Instead of maintaining a copy of every statement for each keyspace,
+//! write the statement once as a [`KeyspaceTemplate`], with the `{keyspace}`
+//! placeholder in place of the keyspace name, and render it for a [`KeyspaceName`]
+//! upon execution. [`CachingSession`](crate::CachingSession) does this for you
+//! in its `*_in_keyspace` methods, caching a separate prepared statement
+//! for each keyspace:
+//!
+//! ```rust
+//! # use scylla::CachingSession;
+//! # async fn example(session: &CachingSession) -> Result<(), Box<dyn std::error::Error>> {
+//! use scylla::statement::keyspace_template::{KeyspaceName, KeyspaceTemplate};
+//!
+//! let insert = KeyspaceTemplate::new("INSERT INTO {keyspace}.users (id, name) VALUES (?, ?)");
+//! for tenant in ["acme", "globex"] {
+//!     let keyspace = KeyspaceName::new(tenant, false)?;
+//!     session
+//!         .execute_unpaged_in_keyspace(&insert, &keyspace, (1, "alice"))
+//!         .await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use std::borrow::Cow;
+
+use thiserror::Error;
+
+use super::lexer::{self, TokenKind};
+use super::query::Query;
+use crate::transport::connection::VerifiedKeyspaceName;
+use crate::transport::errors::BadKeyspaceName;
+
+/// A valid keyspace name, which can be safely inserted into a statement.
+///
+/// It follows the same rules as the keyspace name passed
+/// to [`Session::use_keyspace`](crate::Session::use_keyspace):
+/// it must consist of up to 48 alphanumeric characters and underscores.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct KeyspaceName(VerifiedKeyspaceName);
+
+impl KeyspaceName {
+    /// Validates the keyspace name.
+    ///
+    /// If `case_sensitive` is true, the name is enclosed in double quotes
+    /// when inserted into a statement, so that its case is preserved.
+    /// Otherwise, the database converts it to lowercase.
+    pub fn new(name: impl Into<String>, case_sensitive: bool) -> Result<Self, BadKeyspaceName> {
+        VerifiedKeyspaceName::new(name.into(), case_sensitive).map(Self)
+    }
+
+    /// Returns the keyspace name, without quotes.
+    pub fn as_str(&self) -> &str {
+        self.0.as_str()
+    }
+
+    /// Returns whether the keyspace name is case sensitive.
+    pub fn is_case_sensitive(&self) -> bool {
+        self.0.is_case_sensitive
+    }
+
+    // The keyspace name as it appears in a CQL statement. The validation
+    // guarantees that the name doesn't need escaping.
+    fn as_cql(&self) -> Cow<'_, str> {
+        if self.is_case_sensitive() {
+            Cow::Owned(format!("\"{}\"", self.as_str()))
+        } else {
+            Cow::Borrowed(self.as_str())
+        }
+    }
+}
+
+/// A statement containing the `{keyspace}` placeholder, rendered into
+/// a [`Query`] for a particular keyspace.
+///
+/// The rendered query has the same configuration (consistency, page size, etc.)
+/// as the query the template was created from.
+///
+/// ```rust
+/// # use scylla::statement::keyspace_template::{KeyspaceName, KeyspaceTemplate};
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let template = KeyspaceTemplate::new("SELECT name FROM {keyspace}.users WHERE id = ?");
+///
+/// let query = template.render(&KeyspaceName::new("acme", false)?);
+/// assert_eq!(query.contents, "SELECT name FROM acme.users WHERE id = ?");
+///
+/// let query = template.render(&KeyspaceName::new("Globex", true)?);
+/// assert_eq!(query.contents, "SELECT name FROM \"Globex\".users WHERE id = ?");
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct KeyspaceTemplate {
+    query: Query,
+}
+
+impl KeyspaceTemplate {
+    /// The placeholder replaced with the keyspace name.
+    pub const PLACEHOLDER: &'static str = "{keyspace}";
+
+    /// Creates a template from a query whose contents contain the placeholder.
+    pub fn new(query: impl Into<Query>) -> Self {
+        Self {
+            query: query.into(),
+        }
+    }
+
+    /// Returns the contents of the template, with the placeholder.
+    pub fn get_contents(&self) -> &str {
+        &self.query.contents
+    }
+
+    /// Returns the query for the given keyspace, with all the occurrences
+    /// of the placeholder replaced with the keyspace name.
+    pub fn render(&self, keyspace: &KeyspaceName) -> Query {
+        let mut query = self.query.clone();
+        query.contents = query
+            .contents
+            .replace(Self::PLACEHOLDER, &keyspace.as_cql());
+        query
+    }
+}
+
+impl From<Query> for KeyspaceTemplate {
+    fn from(query: Query) -> Self {
+        Self::new(query)
+    }
+}
+
+/// The keyspace of a statement could not be set, see [`Query::set_keyspace`].
+#[derive(Debug, Clone, Error)]
+#[non_exhaustive]
+pub enum KeyspaceOverrideError {
+    /// The keyspace can be set only for `SELECT`, `INSERT`, `UPDATE`
+    /// and `DELETE` statements.
+    #[error("The keyspace can't be set for the statement: {0}")]
+    UnsupportedStatement(String),
+
+    /// The table of the statement is already qualified with a keyspace.
+    #[error("The statement already specifies the keyspace of its table: {0}")]
+    AlreadyQualified(String),
+}
+
+/// Qualifies the table of the statement with the keyspace.
+pub(crate) fn qualify_table(
+    cql: &str,
+    keyspace: &KeyspaceName,
+) -> Result<String, KeyspaceOverrideError> {
+    let unsupported = || KeyspaceOverrideError::UnsupportedStatement(cql.to_owned());
+    let tokens = lexer::tokenize(cql);
+    let Some(first) = tokens.first() else {
+        return Err(unsupported());
+    };
+
+    // Index of the token right before the table name.
+    let preceding = if first.is_keyword("SELECT") || first.is_keyword("DELETE") {
+        tokens.iter().position(|t| t.is_top_level_keyword("FROM"))
+    } else if first.is_keyword("INSERT") {
+        tokens.get(1).filter(|t| t.is_keyword("INTO")).map(|_| 1)
+    } else if first.is_keyword("UPDATE") {
+        Some(0)
+    } else {
+        None
+    };
+    let table_idx = preceding.ok_or_else(unsupported)? + 1;
+    let table = tokens
+        .get(table_idx)
+        .filter(|t| matches!(t.kind, TokenKind::Word | TokenKind::QuotedName))
+        .ok_or_else(unsupported)?;
+    if tokens.get(table_idx + 1).is_some_and(|t| t.text == ".") {
+        return Err(KeyspaceOverrideError::AlreadyQualified(cql.to_owned()));
+    }
+
+    Ok(format!(
+        "{}{}.{}",
+        &cql[..table.start],
+        keyspace.as_cql(),
+        &cql[table.start..]
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{KeyspaceName, KeyspaceOverrideError, KeyspaceTemplate};
+    use crate::query::Query;
+    use crate::statement::Consistency;
+    use crate::test_utils::setup_tracing;
+    use crate::transport::errors::BadKeyspaceName;
+
+    #[test]
+    fn keyspace_name_validation() {
+        setup_tracing();
+        let keyspace = KeyspaceName::new("tenant_42", false).unwrap();
+        assert_eq!(keyspace.as_str(), "tenant_42");
+        assert!(!keyspace.is_case_sensitive());
+
+        assert!(matches!(
+            KeyspaceName::new("", false),
+            Err(BadKeyspaceName::Empty)
+        ));
+        assert!(matches!(
+            KeyspaceName::new("acme.users; DROP TABLE x", true),
+            Err(BadKeyspaceName::IllegalCharacter(_, '.'))
+        ));
+        assert!(matches!(
+            KeyspaceName::new("a".repeat(49), false),
+            Err(BadKeyspaceName::TooLong(_, 49))
+        ));
+    }
+
+    #[test]
+    fn rendering_template() {
+        setup_tracing();
+        let mut query =
+            Query::new("BEGIN BATCH INSERT INTO {keyspace}.a (k) VALUES (?); UPDATE {keyspace}.b SET v = 1 WHERE k = ?; APPLY BATCH");
+        query.set_consistency(Consistency::LocalQuorum);
+        let template = KeyspaceTemplate::from(query);
+
+        let rendered = template.render(&KeyspaceName::new("acme", false).unwrap());
+        assert_eq!(
+            rendered.contents,
+            "BEGIN BATCH INSERT INTO acme.a (k) VALUES (?); UPDATE acme.b SET v = 1 WHERE k = ?; APPLY BATCH"
+        );
+        assert_eq!(rendered.get_consistency(), Some(Consistency::LocalQuorum));
+
+        let rendered = template.render(&KeyspaceName::new("Acme", true).unwrap());
+        assert_eq!(
+            rendered.contents,
+            "BEGIN BATCH INSERT INTO \"Acme\".a (k) VALUES (?); UPDATE \"Acme\".b SET v = 1 WHERE k = ?; APPLY BATCH"
+        );
+
+        // The template is left intact.
+        assert!(template
+            .get_contents()
+            .contains(KeyspaceTemplate::PLACEHOLDER));
+    }
+
+    #[test]
+    fn keyspace_override() {
+        setup_tracing();
+        let acme = KeyspaceName::new("acme", false).unwrap();
+        for (cql, expected) in [
+            (
+                "SELECT name FROM users WHERE id = ?",
+                "SELECT name FROM acme.users WHERE id = ?",
+            ),
+            (
+                "select count(*) from \"Users\"",
+                "select count(*) from acme.\"Users\"",
+            ),
+            (
+                "INSERT INTO users (id, name) VALUES (?, ?)",
+                "INSERT INTO acme.users (id, name) VALUES (?, ?)",
+            ),
+            (
+                "UPDATE users USING TTL 10 SET name = ? WHERE id = ?",
+                "UPDATE acme.users USING TTL 10 SET name = ? WHERE id = ?",
+            ),
+            (
+                "DELETE name FROM users WHERE id = ?",
+                "DELETE name FROM acme.users WHERE id = ?",
+            ),
+        ] {
+            let mut query = Query::new(cql);
+            query.set_keyspace(acme.clone()).unwrap();
+            assert_eq!(query.contents, expected);
+            assert_eq!(query.get_keyspace(), Some(&acme));
+        }
+
+        let mut query = Query::new("SELECT * FROM users");
+        query
+            .set_keyspace(KeyspaceName::new("Globex", true).unwrap())
+            .unwrap();
+        assert_eq!(query.contents, "SELECT * FROM \"Globex\".users");
+
+        let mut query = Query::new("SELECT * FROM globex . users");
+        assert!(matches!(
+            query.set_keyspace(acme.clone()),
+            Err(KeyspaceOverrideError::AlreadyQualified(_))
+        ));
+        assert_eq!(query.contents, "SELECT * FROM globex . users");
+        assert_eq!(query.get_keyspace(), None);
+
+        for cql in [
+            "",
+            "TRUNCATE users",
+            "BEGIN BATCH INSERT INTO t (a) VALUES (1); APPLY BATCH",
+        ] {
+            assert!(matches!(
+                Query::new(cql).set_keyspace(acme.clone()),
+                Err(KeyspaceOverrideError::UnsupportedStatement(_))
+            ));
+        }
+    }
+}
@@ -0,0 +1,182 @@
+//! A minimal CQL tokenizer, sufficient for inspecting and amending
+//! statements provided by the user without being fooled by the contents
+//! of string literals, quoted identifiers and comments.
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum TokenKind {
+    /// A keyword, an unquoted identifier or a number.
+    Word,
+    /// An identifier in double quotes.
+    QuotedName,
+    /// A string literal, either in single quotes or in `$$`.
+    String,
+    /// Any other character, e.g. a parenthesis or a bind marker.
+    Symbol,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct Token<'a> {
+    pub(crate) kind: TokenKind,
+    pub(crate) text: &'a str,
+    /// Byte offset of the token in the statement.
+    pub(crate) start: usize,
+    /// Nesting level of parentheses, brackets and braces the token is in.
+    pub(crate) depth: usize,
+}
+
+impl Token<'_> {
+    pub(crate) fn end(&self) -> usize {
+        self.start + self.text.len()
+    }
+
+    /// Checks whether the token is the given (case-insensitive) keyword.
+    pub(crate) fn is_keyword(&self, keyword: &str) -> bool {
+        self.kind == TokenKind::Word && self.text.eq_ignore_ascii_case(keyword)
+    }
+
+    /// Checks whether the token is a keyword outside of any parentheses.
+    pub(crate) fn is_top_level_keyword(&self, keyword: &str) -> bool {
+        self.depth == 0 && self.is_keyword(keyword)
+    }
+}
+
+/// Splits the statement into tokens, skipping whitespace and comments.
+///
+/// Unterminated literals and comments extend to the end of the statement;
+/// the server is the one to report them.
+pub(crate) fn tokenize(cql: &str) -> Vec<Token<'_>> {
+    let bytes = cql.as_bytes();
+    let mut tokens = Vec::new();
+    let mut depth = 0_usize;
+    let mut pos = 0;
+
+    // Returns the position right after the first occurrence of `terminator`
+    // at or after `from`, or the end of the statement.
+    let skip_past = |from: usize, terminator: &str| {
+        cql[from..]
+            .find(terminator)
+            .map_or(cql.len(), |idx| from + idx + terminator.len())
+    };
+
+    while pos < bytes.len() {
+        let rest = &cql[pos..];
+        let c = rest.chars().next().unwrap();
+        let (kind, end) = if c.is_whitespace() {
+            pos += c.len_utf8();
+            continue;
+        } else if rest.starts_with("--") || rest.starts_with("//") {
+            pos = skip_past(pos, "\n");
+            continue;
+        } else if rest.starts_with("/*") {
+            pos = skip_past(pos + 2, "*/");
+            continue;
+        } else if rest.starts_with("$$") {
+            (TokenKind::String, skip_past(pos + 2, "$$"))
+        } else if c == '\'' || c == '"' {
+            // The quote character is escaped by doubling it.
+            let mut end = pos + 1;
+            loop {
+                end = skip_past(end, if c == '\'' { "'" } else { "\"" });
+                if end >= bytes.len() || bytes[end] != c as u8 {
+                    break;
+                }
+                end += 1;
+            }
+            let kind = if c == '\'' {
+                TokenKind::String
+            } else {
+                TokenKind::QuotedName
+            };
+            (kind, end)
+        } else if c.is_alphanumeric() || c == '_' {
+            let len = rest
+                .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+                .unwrap_or(rest.len());
+            (TokenKind::Word, pos + len)
+        } else {
+            (TokenKind::Symbol, pos + c.len_utf8())
+        };
+
+        if matches!(c, ')' | ']' | '}') {
+            depth = depth.saturating_sub(1);
+        }
+        tokens.push(Token {
+            kind,
+            text: &cql[pos..end],
+            start: pos,
+            depth,
+        });
+        if matches!(c, '(' | '[' | '{') {
+            depth += 1;
+        }
+        pos = end;
+    }
+    tokens
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{tokenize, TokenKind};
+    use crate::test_utils::setup_tracing;
+
+    fn kinds_and_texts(cql: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(cql)
+            .into_iter()
+            .map(|token| (token.kind, token.text))
+            .collect()
+    }
+
+    #[test]
+    fn tokenization() {
+        setup_tracing();
+        use TokenKind::*;
+        assert_eq!(
+            kinds_and_texts(
+                "SELECT \"Weird \"\" name\", a -- comment\nFROM ks.t /* WHERE */ WHERE b = 'it''s' AND c = $$a 'b'$$"
+            ),
+            [
+                (Word, "SELECT"),
+                (QuotedName, "\"Weird \"\" name\""),
+                (Symbol, ","),
+                (Word, "a"),
+                (Word, "FROM"),
+                (Word, "ks"),
+                (Symbol, "."),
+                (Word, "t"),
+                (Word, "WHERE"),
+                (Word, "b"),
+                (Symbol, "="),
+                (String, "'it''s'"),
+                (Word, "AND"),
+                (Word, "c"),
+                (Symbol, "="),
+                (String, "$$a 'b'$$"),
+            ]
+        );
+
+        // Unterminated literals extend to the end.
+        assert_eq!(
+            kinds_and_texts("SELECT 'abc"),
+            [(Word, "SELECT"), (String, "'abc")]
+        );
+        assert_eq!(kinds_and_texts("/* abc"), []);
+    }
+
+    #[test]
+    fn nesting_depth() {
+        setup_tracing();
+        let tokens = tokenize("INSERT INTO t (a, b) VALUES (1, {'k': [2]}) USING TTL 1");
+        let depth_of = |text: &str| tokens.iter().find(|t| t.text == text).unwrap().depth;
+        assert_eq!(depth_of("INSERT"), 0);
+        assert_eq!(depth_of("a"), 1);
+        assert_eq!(depth_of("'k'"), 2);
+        assert_eq!(depth_of("2"), 3);
+        assert_eq!(depth_of("]"), 2);
+        assert_eq!(depth_of("USING"), 0);
+        assert!(tokens
+            .iter()
+            .find(|t| t.text == "USING")
+            .unwrap()
+            .is_top_level_keyword("using"));
+    }
+}
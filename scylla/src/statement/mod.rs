@@ -6,12 +6,16 @@ use crate::transport::execution_profile::ExecutionProfileHandle;
 use crate::{history::HistoryListener, retry_policy::RetryPolicy};
 
 pub mod batch;
+pub mod builder;
+pub mod checked;
+pub mod keyspace_template;
+pub(crate) mod lexer;
 pub mod prepared_statement;
 pub mod query;
 
 pub use crate::frame::types::{Consistency, SerialConsistency};
 
-pub use crate::frame::request::query::{PagingState, PagingStateResponse};
+pub use crate::frame::request::query::{PagingState, PagingStateResponse, PagingStateTokenError};
 
 // This is the default common to drivers.
 const DEFAULT_PAGE_SIZE: i32 = 5000;
@@ -26,7 +30,14 @@ pub(crate) struct StatementConfig {
     pub(crate) skip_result_metadata: bool,
     pub(crate) tracing: bool,
     pub(crate) timestamp: Option<i64>,
+    pub(crate) now_in_seconds: Option<i32>,
     pub(crate) request_timeout: Option<Duration>,
+    pub(crate) server_timeout: Option<Duration>,
+    pub(crate) allow_filtering_permitted: bool,
+    pub(crate) result_memory_limit: Option<usize>,
+    pub(crate) adaptive_page_size: Option<AdaptivePageSize>,
+    pub(crate) page_prefetch: Option<PagePrefetch>,
+    pub(crate) resumable_paging: Option<ResumablePaging>,
 
     pub(crate) history_listener: Option<Arc<dyn HistoryListener>>,
 
@@ -40,6 +51,147 @@ impl StatementConfig {
     pub(crate) fn determine_consistency(&self, default_consistency: Consistency) -> Consistency {
         self.consistency.unwrap_or(default_consistency)
     }
+
+    /// Determines the client-side timeout of a request. It never expires
+    /// before the server-side timeout does, so that the response stating
+    /// the server-side timeout is not missed.
+    #[must_use]
+    pub(crate) fn determine_request_timeout(
+        &self,
+        default_timeout: Option<Duration>,
+    ) -> Option<Duration> {
+        let timeout = self.request_timeout.or(default_timeout)?;
+        Some(match self.server_timeout {
+            Some(server_timeout) => timeout.max(server_timeout + SERVER_TIMEOUT_MARGIN),
+            None => timeout,
+        })
+    }
+}
+
+// How much longer than the server-side timeout the client waits for the response.
+const SERVER_TIMEOUT_MARGIN: Duration = Duration::from_millis(500);
+
+/// A serial read was requested for a statement which is not a `SELECT`.
+///
+/// `SERIAL` and `LOCAL_SERIAL` consistencies are only allowed for reads;
+/// the consistency of Paxos for conditional writes is set with
+/// `set_serial_consistency` instead.
+#[derive(Debug, Clone, Error)]
+#[error("Serial reads are only supported for SELECT statements, got: {statement}")]
+pub struct SerialReadError {
+    pub statement: String,
+}
+
+/// Checks whether the CQL statement is a `SELECT`, skipping leading
+/// whitespace and comments.
+pub(crate) fn is_select_statement(cql: &str) -> bool {
+    lexer::tokenize(cql)
+        .first()
+        .is_some_and(|first| first.is_keyword("SELECT"))
+}
+
+/// The kind of a write statement.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum WriteKind {
+    /// An `INSERT` or an `UPDATE`.
+    Upsert,
+    /// A `DELETE`.
+    Delete,
+}
+
+/// Returns the kind of the CQL statement if it is an `INSERT`, `UPDATE` or `DELETE`,
+/// skipping leading whitespace and comments.
+pub(crate) fn write_statement_kind(cql: &str) -> Option<WriteKind> {
+    let tokens = lexer::tokenize(cql);
+    let first = tokens.first()?;
+    if first.is_keyword("INSERT") || first.is_keyword("UPDATE") {
+        Some(WriteKind::Upsert)
+    } else if first.is_keyword("DELETE") {
+        Some(WriteKind::Delete)
+    } else {
+        None
+    }
+}
+
+/// Checks whether the statement contains `ALLOW FILTERING`,
+/// ignoring string literals, quoted names and comments.
+pub(crate) fn contains_allow_filtering(cql: &str) -> bool {
+    lexer::tokenize(cql)
+        .windows(2)
+        .any(|window| window[0].is_keyword("ALLOW") && window[1].is_keyword("FILTERING"))
+}
+
+/// A server-side timeout could not be added to a statement.
+#[derive(Debug, Clone, Error)]
+#[non_exhaustive]
+pub enum ServerTimeoutError {
+    /// `USING TIMEOUT` is supported only by `SELECT`, `INSERT`,
+    /// `UPDATE` and `DELETE` statements.
+    #[error("USING TIMEOUT is not supported by the statement: {0}")]
+    UnsupportedStatement(String),
+
+    /// The statement already specifies a server-side timeout.
+    #[error("The statement already specifies a server-side timeout: {0}")]
+    AlreadySpecified(String),
+
+    /// The server-side timeout has to be at least 1 millisecond.
+    #[error("Invalid server-side timeout: {0:?}, it has to be at least 1 millisecond")]
+    InvalidTimeout(Duration),
+}
+
+/// Adds Scylla's `USING TIMEOUT` clause to the statement.
+pub(crate) fn add_using_timeout(
+    cql: &str,
+    timeout: Duration,
+) -> Result<String, ServerTimeoutError> {
+    if timeout.as_millis() == 0 {
+        return Err(ServerTimeoutError::InvalidTimeout(timeout));
+    }
+    let unsupported = || ServerTimeoutError::UnsupportedStatement(cql.to_owned());
+    let tokens = lexer::tokenize(cql);
+    let Some(first) = tokens.first() else {
+        return Err(unsupported());
+    };
+
+    // `USING TIMEOUT x` or `USING TTL y AND TIMEOUT x`, as opposed to e.g. `... AND timeout = ?`.
+    let already_specified = tokens.windows(3).any(|window| {
+        (window[0].is_top_level_keyword("USING") || window[0].is_top_level_keyword("AND"))
+            && window[1].is_top_level_keyword("TIMEOUT")
+            && window[2].text != "="
+    });
+    if already_specified {
+        return Err(ServerTimeoutError::AlreadySpecified(cql.to_owned()));
+    }
+
+    let clause = format!("TIMEOUT {}ms", timeout.as_millis());
+    let find = |keyword: &str| tokens.iter().find(|t| t.is_top_level_keyword(keyword));
+    let insert_at = |pos: usize, text: &str| format!("{}{}{}", &cql[..pos], text, &cql[pos..]);
+    // The end of the statement, without the optional semicolon.
+    let end = tokens
+        .iter()
+        .rev()
+        .find(|t| t.text != ";")
+        .map_or(cql.len(), |t| t.end());
+
+    let is_kind = |kind: &str| first.is_keyword(kind);
+    let rewritten = if is_kind("SELECT") {
+        insert_at(end, &format!(" USING {}", clause))
+    } else if is_kind("INSERT") || is_kind("UPDATE") || is_kind("DELETE") {
+        // Other options can be specified in the USING clause, e.g. TTL.
+        if let Some(using) = find("USING") {
+            insert_at(using.end(), &format!(" {} AND", clause))
+        } else if is_kind("INSERT") {
+            insert_at(end, &format!(" USING {}", clause))
+        } else {
+            // The USING clause precedes SET in UPDATE, and WHERE in DELETE.
+            let next =
+                find(if is_kind("UPDATE") { "SET" } else { "WHERE" }).ok_or_else(unsupported)?;
+            insert_at(next.start, &format!("USING {} ", clause))
+        }
+    } else {
+        return Err(unsupported());
+    };
+    Ok(rewritten)
 }
 
 #[derive(Debug, Clone, Copy, Error)]
@@ -92,3 +244,443 @@ impl From<PageSize> for i32 {
         page_size.inner()
     }
 }
+
+/// Configuration of adaptive page sizing for paged queries.
+///
+/// When set on a statement, the page size used by `Session::{query,execute}_iter`
+/// is adjusted after each received page, so that pages take about
+/// [`target_page_bytes`](Self::with_target_page_bytes) bytes and are received in about
+/// [`target_page_latency`](Self::with_target_page_latency). The first page is fetched
+/// with the page size of the statement, clamped to the configured bounds.
+///
+/// The page size changes at most twice per page, and it is assumed that the rows
+/// are of similar size. As the database may return fewer rows than requested,
+/// e.g. because it limits the size of a page itself, the estimate is only approximate.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct AdaptivePageSize {
+    min_page_size: PageSize,
+    max_page_size: PageSize,
+    target_page_bytes: usize,
+    target_page_latency: Duration,
+}
+
+impl AdaptivePageSize {
+    const DEFAULT_TARGET_PAGE_BYTES: usize = 512 * 1024;
+    const DEFAULT_TARGET_PAGE_LATENCY: Duration = Duration::from_millis(100);
+
+    /// Creates a new configuration, keeping the page size between the given bounds.
+    /// Pages target 512 KiB and 100 ms by default.
+    ///
+    /// Panics if either of the bounds is nonpositive, or if `min_page_size > max_page_size`.
+    pub fn new(min_page_size: i32, max_page_size: i32) -> Self {
+        let min_page_size: PageSize = min_page_size
+            .try_into()
+            .unwrap_or_else(|err| panic!("AdaptivePageSize::new: {err}"));
+        let max_page_size: PageSize = max_page_size
+            .try_into()
+            .unwrap_or_else(|err| panic!("AdaptivePageSize::new: {err}"));
+        assert!(
+            min_page_size.inner() <= max_page_size.inner(),
+            "AdaptivePageSize::new: min_page_size is greater than max_page_size"
+        );
+        Self {
+            min_page_size,
+            max_page_size,
+            target_page_bytes: Self::DEFAULT_TARGET_PAGE_BYTES,
+            target_page_latency: Self::DEFAULT_TARGET_PAGE_LATENCY,
+        }
+    }
+
+    /// Returns self with the target size of a page, in bytes.
+    pub fn with_target_page_bytes(mut self, bytes: usize) -> Self {
+        self.target_page_bytes = bytes;
+        self
+    }
+
+    /// Returns self with the target time of fetching a page.
+    pub fn with_target_page_latency(mut self, latency: Duration) -> Self {
+        self.target_page_latency = latency;
+        self
+    }
+
+    /// Returns the minimum page size.
+    pub fn get_min_page_size(&self) -> i32 {
+        self.min_page_size.inner()
+    }
+
+    /// Returns the maximum page size.
+    pub fn get_max_page_size(&self) -> i32 {
+        self.max_page_size.inner()
+    }
+
+    /// Returns the target size of a page, in bytes.
+    pub fn get_target_page_bytes(&self) -> usize {
+        self.target_page_bytes
+    }
+
+    /// Returns the target time of fetching a page.
+    pub fn get_target_page_latency(&self) -> Duration {
+        self.target_page_latency
+    }
+
+    /// Clamps the page size to the configured bounds.
+    pub(crate) fn clamp(&self, page_size: PageSize) -> PageSize {
+        PageSize(
+            page_size
+                .inner()
+                .clamp(self.min_page_size.inner(), self.max_page_size.inner()),
+        )
+    }
+
+    /// Computes the size of the next page, given that a page requested with `page_size`
+    /// took `bytes` bytes and was received after `latency`.
+    pub(crate) fn next_page_size(
+        &self,
+        page_size: PageSize,
+        bytes: usize,
+        latency: Duration,
+    ) -> PageSize {
+        // A ratio of a target to an observed value, e.g. 2 if the page took half the target.
+        let ratio = |target: f64, observed: f64| {
+            if observed > 0. {
+                target / observed
+            } else {
+                f64::INFINITY
+            }
+        };
+        let factor = f64::min(
+            ratio(self.target_page_bytes as f64, bytes as f64),
+            ratio(
+                self.target_page_latency.as_secs_f64(),
+                latency.as_secs_f64(),
+            ),
+        )
+        .clamp(0.5, 2.);
+        // The cast saturates, and clamping keeps the size positive.
+        self.clamp(PageSize((page_size.inner() as f64 * factor).round() as i32))
+    }
+}
+
+/// Configuration of prefetching pages by `Session::{query,execute}_iter`.
+///
+/// Pages are fetched in the background, while the rows of the received ones are processed.
+/// Up to [`max_pages`](Self::new) received pages wait to be processed; fetching of further
+/// pages waits until the consumer catches up, which limits memory usage.
+/// Additionally, the pages waiting to be processed together with the page being processed
+/// can be limited to [`max_bytes`](Self::with_max_bytes) bytes. A page larger than
+/// the limit is still received, but only once all the previous pages are processed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PagePrefetch {
+    max_pages: usize,
+    max_bytes: Option<usize>,
+}
+
+impl PagePrefetch {
+    /// Creates a new configuration, prefetching up to `max_pages` pages.
+    ///
+    /// Panics if `max_pages` is 0.
+    pub fn new(max_pages: usize) -> Self {
+        assert!(
+            max_pages > 0,
+            "PagePrefetch::new: max_pages must be positive"
+        );
+        Self {
+            max_pages,
+            max_bytes: None,
+        }
+    }
+
+    /// Returns self with the limit of bytes of prefetched pages.
+    pub fn with_max_bytes(mut self, max_bytes: usize) -> Self {
+        self.max_bytes = Some(max_bytes);
+        self
+    }
+
+    /// Returns the maximum number of prefetched pages.
+    pub fn get_max_pages(&self) -> usize {
+        self.max_pages
+    }
+
+    /// Returns the limit of bytes of prefetched pages, if set.
+    pub fn get_max_bytes(&self) -> Option<usize> {
+        self.max_bytes
+    }
+}
+
+impl Default for PagePrefetch {
+    /// Prefetches a single page, without a limit of bytes.
+    fn default() -> Self {
+        Self::new(1)
+    }
+}
+
+/// Configuration of resuming paged queries after transient failures.
+///
+/// By default, `Session::{query,execute}_iter` fail as soon as fetching a page fails,
+/// after the retries decided by the retry policy. With resumable paging, fetching is
+/// attempted again after a delay, continuing from the last received page, if the failure
+/// is transient, e.g. a timeout, an overloaded node or a broken connection.
+///
+/// Up to [`max_resumptions`](Self::new) resumptions are made in a row, with the delay
+/// doubling after each of them. The count is reset once a page is received.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ResumablePaging {
+    max_resumptions: u32,
+    delay: Duration,
+}
+
+impl ResumablePaging {
+    const DEFAULT_DELAY: Duration = Duration::from_millis(100);
+
+    /// Creates a new configuration, making up to `max_resumptions` resumptions in a row.
+    /// The first resumption is delayed by 100 ms by default.
+    pub fn new(max_resumptions: u32) -> Self {
+        Self {
+            max_resumptions,
+            delay: Self::DEFAULT_DELAY,
+        }
+    }
+
+    /// Returns self with the delay of the first resumption.
+    pub fn with_delay(mut self, delay: Duration) -> Self {
+        self.delay = delay;
+        self
+    }
+
+    /// Returns the maximum number of resumptions in a row.
+    pub fn get_max_resumptions(&self) -> u32 {
+        self.max_resumptions
+    }
+
+    /// Returns the delay of the first resumption.
+    pub fn get_delay(&self) -> Duration {
+        self.delay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{
+        add_using_timeout, contains_allow_filtering, is_select_statement, write_statement_kind,
+        AdaptivePageSize, Consistency, PageSize, SerialConsistency, ServerTimeoutError,
+        StatementConfig, WriteKind,
+    };
+    use crate::query::Query;
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn select_statement_detection() {
+        setup_tracing();
+        for cql in [
+            "SELECT * FROM ks.t",
+            "  select a FROM ks.t WHERE pk = ?",
+            "\n\tSeLeCt\n* FROM ks.t",
+            "-- read the row\nSELECT * FROM ks.t",
+            "/* multi\nline */ /* two */ SELECT * FROM ks.t",
+            "// comment\n  SELECT*FROM ks.t",
+        ] {
+            assert!(is_select_statement(cql), "{cql:?} is a SELECT");
+        }
+
+        for cql in [
+            "",
+            "INSERT INTO ks.t (a) VALUES (1) IF NOT EXISTS",
+            "UPDATE ks.t SET a = 1 WHERE pk = 0 IF a = 0",
+            "-- SELECT\nDELETE FROM ks.t WHERE pk = 0",
+            "/* SELECT * FROM ks.t */",
+            "SELECTED",
+            "sel",
+        ] {
+            assert!(!is_select_statement(cql), "{cql:?} is not a SELECT");
+        }
+    }
+
+    #[test]
+    fn serial_read() {
+        setup_tracing();
+        let mut read = Query::new("SELECT a FROM ks.t WHERE pk = ?");
+        assert!(!read.is_serial_read());
+        read.set_serial_read(SerialConsistency::LocalSerial)
+            .unwrap();
+        assert!(read.is_serial_read());
+        assert_eq!(read.get_consistency(), Some(Consistency::LocalSerial));
+
+        let mut write = Query::new("UPDATE ks.t SET a = 1 WHERE pk = 0 IF a = 0");
+        write.set_consistency(Consistency::One);
+        let err = write
+            .set_serial_read(SerialConsistency::Serial)
+            .unwrap_err();
+        assert_eq!(err.statement, write.contents);
+        assert_eq!(write.get_consistency(), Some(Consistency::One));
+    }
+
+    #[test]
+    fn using_timeout_rewriting() {
+        setup_tracing();
+        let timeout = Duration::from_millis(1500);
+        for (cql, expected) in [
+            (
+                "SELECT * FROM ks.t WHERE pk = ?;",
+                "SELECT * FROM ks.t WHERE pk = ? USING TIMEOUT 1500ms;",
+            ),
+            (
+                "INSERT INTO ks.t (a, b) VALUES (?, 'using') IF NOT EXISTS",
+                "INSERT INTO ks.t (a, b) VALUES (?, 'using') IF NOT EXISTS USING TIMEOUT 1500ms",
+            ),
+            (
+                "insert into ks.t (a) values (?) using ttl 10",
+                "insert into ks.t (a) values (?) using TIMEOUT 1500ms AND ttl 10",
+            ),
+            (
+                "UPDATE ks.t SET a = ? WHERE pk = ?",
+                "UPDATE ks.t USING TIMEOUT 1500ms SET a = ? WHERE pk = ?",
+            ),
+            (
+                "UPDATE ks.t USING TTL 5 SET a = ? WHERE pk = ?",
+                "UPDATE ks.t USING TIMEOUT 1500ms AND TTL 5 SET a = ? WHERE pk = ?",
+            ),
+            (
+                "DELETE a FROM ks.t WHERE pk = ? -- where",
+                "DELETE a FROM ks.t USING TIMEOUT 1500ms WHERE pk = ? -- where",
+            ),
+            (
+                "SELECT * FROM ks.t WHERE timeout = ?",
+                "SELECT * FROM ks.t WHERE timeout = ? USING TIMEOUT 1500ms",
+            ),
+        ] {
+            assert_eq!(add_using_timeout(cql, timeout).unwrap(), expected);
+        }
+
+        for cql in ["", "CREATE TABLE ks.t (a int PRIMARY KEY)", "BEGIN BATCH"] {
+            assert!(matches!(
+                add_using_timeout(cql, timeout),
+                Err(ServerTimeoutError::UnsupportedStatement(_))
+            ));
+        }
+        for cql in [
+            "SELECT * FROM ks.t USING TIMEOUT 1s",
+            "INSERT INTO ks.t (a) VALUES (1) USING TTL 1 AND TIMEOUT 1s",
+        ] {
+            assert!(matches!(
+                add_using_timeout(cql, timeout),
+                Err(ServerTimeoutError::AlreadySpecified(_))
+            ));
+        }
+        assert!(matches!(
+            add_using_timeout("SELECT * FROM ks.t", Duration::from_micros(999)),
+            Err(ServerTimeoutError::InvalidTimeout(_))
+        ));
+    }
+
+    #[test]
+    fn server_timeout_extends_request_timeout() {
+        setup_tracing();
+        let mut query = Query::new("SELECT * FROM ks.t");
+        query
+            .set_server_timeout(Duration::from_micros(2_000_900))
+            .unwrap();
+        assert_eq!(query.get_server_timeout(), Some(Duration::from_secs(2)));
+        assert_eq!(query.contents, "SELECT * FROM ks.t USING TIMEOUT 2000ms");
+
+        let config = &query.config;
+        assert_eq!(
+            config.determine_request_timeout(Some(Duration::from_secs(1))),
+            Some(Duration::from_millis(2500))
+        );
+        assert_eq!(
+            config.determine_request_timeout(Some(Duration::from_secs(10))),
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(config.determine_request_timeout(None), None);
+        assert_eq!(
+            StatementConfig::default().determine_request_timeout(Some(Duration::from_secs(1))),
+            Some(Duration::from_secs(1))
+        );
+    }
+
+    #[test]
+    fn allow_filtering_detection() {
+        setup_tracing();
+        for cql in [
+            "SELECT * FROM ks.t WHERE a = ? ALLOW FILTERING",
+            "select * from ks.t where a = ? allow\n  filtering;",
+            "SELECT * FROM ks.t WHERE a = ? ALLOW /* really */ FILTERING",
+        ] {
+            assert!(contains_allow_filtering(cql), "{cql:?} allows filtering");
+        }
+        for cql in [
+            "SELECT * FROM ks.t WHERE pk = ?",
+            "SELECT * FROM ks.t WHERE a = 'ALLOW FILTERING'",
+            "SELECT * FROM ks.t -- ALLOW FILTERING",
+            "SELECT \"allow\", filtering FROM ks.t",
+        ] {
+            assert!(
+                !contains_allow_filtering(cql),
+                "{cql:?} does not allow filtering"
+            );
+        }
+    }
+
+    #[test]
+    fn write_statement_detection() {
+        setup_tracing();
+        for cql in [
+            "INSERT INTO ks.t (a) VALUES (?)",
+            "  update ks.t SET a = ? WHERE pk = ?",
+        ] {
+            assert_eq!(
+                write_statement_kind(cql),
+                Some(WriteKind::Upsert),
+                "{cql:?}"
+            );
+        }
+        assert_eq!(
+            write_statement_kind("/* cleanup */ DELETE FROM ks.t WHERE pk = ?"),
+            Some(WriteKind::Delete)
+        );
+        for cql in [
+            "SELECT * FROM ks.t",
+            "BEGIN BATCH INSERT INTO ks.t (a) VALUES (?) APPLY BATCH",
+            "-- INSERT\nSELECT * FROM ks.t",
+            "",
+        ] {
+            assert_eq!(write_statement_kind(cql), None, "{cql:?} is not a write");
+        }
+    }
+
+    #[test]
+    fn adaptive_page_size() {
+        setup_tracing();
+        let adaptive = AdaptivePageSize::new(100, 10_000)
+            .with_target_page_bytes(1000)
+            .with_target_page_latency(Duration::from_millis(100));
+        let next = |page_size: i32, bytes: usize, latency_ms: u64| {
+            adaptive
+                .next_page_size(
+                    PageSize::new(page_size).unwrap(),
+                    bytes,
+                    Duration::from_millis(latency_ms),
+                )
+                .inner()
+        };
+
+        // On target.
+        assert_eq!(next(1000, 1000, 100), 1000);
+        // Small and fast pages grow, but at most twice per page.
+        assert_eq!(next(1000, 800, 80), 1250);
+        assert_eq!(next(1000, 10, 1), 2000);
+        assert_eq!(next(1000, 0, 0), 2000);
+        // Either too large or too slow pages shrink.
+        assert_eq!(next(1000, 1250, 10), 800);
+        assert_eq!(next(1000, 10, 125), 800);
+        assert_eq!(next(1000, 100_000, 1), 500);
+        // The bounds are respected.
+        assert_eq!(next(8000, 10, 1), 10_000);
+        assert_eq!(next(150, 100_000, 1), 100);
+
+        assert_eq!(adaptive.clamp(PageSize::default()).inner(), 5000);
+        assert_eq!(adaptive.clamp(PageSize::new(1).unwrap()).inner(), 100);
+    }
+}
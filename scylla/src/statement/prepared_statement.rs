@@ -1,6 +1,7 @@
+use arc_swap::{ArcSwap, ArcSwapOption};
 use bytes::{Bytes, BytesMut};
 use scylla_cql::frame::response::result::{
-    ColumnSpec, PartitionKeyIndex, ResultMetadata, TableSpec,
+    ColumnSpec, ColumnType, PartitionKeyIndex, ResultMetadata, TableSpec,
 };
 use scylla_cql::frame::types::RawValue;
 use scylla_cql::types::serialize::row::{RowSerializationContext, SerializeRow, SerializedValues};
@@ -12,7 +13,10 @@ use std::time::Duration;
 use thiserror::Error;
 use uuid::Uuid;
 
-use super::{PageSize, StatementConfig};
+use super::{
+    is_select_statement, write_statement_kind, AdaptivePageSize, PagePrefetch, PageSize,
+    ResumablePaging, SerialReadError, StatementConfig, WriteKind,
+};
 use crate::frame::response::result::PreparedMetadata;
 use crate::frame::types::{Consistency, SerialConsistency};
 use crate::history::HistoryListener;
@@ -77,13 +81,16 @@ use crate::transport::partitioner::{Partitioner, PartitionerHasher, PartitionerN
 /// clients about prepared statement's metadata update.
 ///
 /// ### Client-side metadata immutability
-/// The decision was made to keep client-side metadata immutable.
+/// The decision was made to keep client-side [`PreparedMetadata`] immutable.
 /// Mainly because of the CQLv4 limitations mentioned above. This means
-/// that metadata is not updated during statement repreparation.
-/// This raises two issues:
-/// * bound values serialization errors - since [`PreparedMetadata`] is not updated
-/// * result deserialization errors - when [`PreparedStatement::set_use_cached_result_metadata`] is enabled,
-///   since [`ResultMetadata`] is not updated
+/// that it is not updated during statement repreparation, which can result
+/// in bound values serialization errors.
+///
+/// The only exception is the result metadata cached for
+/// [`PreparedStatement::set_use_cached_result_metadata`], which is updated upon repreparation,
+/// and, in protocol v5, when the server reports that it has changed.
+/// [`PreparedStatement::get_result_set_col_specs`] still returns the result metadata
+/// received when the statement was prepared.
 ///
 /// So, to mitigate those issues, drop the outdated [`PreparedStatement`] manually
 /// and prepare it again against the new schema.
@@ -103,6 +110,15 @@ pub struct PreparedStatement {
 struct PreparedStatementSharedData {
     metadata: PreparedMetadata,
     result_metadata: Arc<ResultMetadata<'static>>,
+    // Used for deserializing results when the server is asked to skip result metadata.
+    // Initially the same as `result_metadata`, updated when the statement is reprepared.
+    cached_result_metadata: ArcSwap<ResultMetadata<'static>>,
+    // Identifies `cached_result_metadata` in protocol v5, so that the server
+    // can tell when it is outdated.
+    result_metadata_id: ArcSwapOption<Bytes>,
+    // The kind of the write whose timestamp can be tracked for read-your-writes,
+    // if the statement is one. Classified once, when the statement is prepared.
+    tracked_write_kind: Option<WriteKind>,
     statement: String,
 }
 
@@ -130,11 +146,22 @@ impl PreparedStatement {
         page_size: PageSize,
         config: StatementConfig,
     ) -> Self {
+        // Conditional and counter writes can't be given client-side timestamps.
+        let tracked_write_kind = write_statement_kind(&statement).filter(|_| {
+            !is_lwt
+                && !metadata
+                    .col_specs
+                    .iter()
+                    .any(|spec| matches!(spec.typ(), ColumnType::Counter))
+        });
         Self {
             id,
             shared: Arc::new(PreparedStatementSharedData {
                 metadata,
+                cached_result_metadata: ArcSwap::new(result_metadata.clone()),
                 result_metadata,
+                result_metadata_id: ArcSwapOption::empty(),
+                tracked_write_kind,
                 statement,
             }),
             prepare_tracing_ids: Vec::new(),
@@ -210,12 +237,7 @@ impl PreparedStatement {
     ) -> Result<Bytes, PartitionKeyError> {
         let serialized = self.serialize_values(bound_values)?;
         let partition_key = self.extract_partition_key(&serialized)?;
-        let mut buf = BytesMut::new();
-        let mut writer = |chunk: &[u8]| buf.extend_from_slice(chunk);
-
-        partition_key.write_encoded_partition_key(&mut writer)?;
-
-        Ok(buf.freeze())
+        Ok(partition_key.encode()?)
     }
 
     /// Determines which values constitute the partition key and puts them in order.
@@ -323,6 +345,35 @@ impl PreparedStatement {
         self.config.serial_consistency.flatten()
     }
 
+    /// Makes this `SELECT` a serial read by setting its consistency to `SERIAL`
+    /// or `LOCAL_SERIAL`.
+    ///
+    /// A serial read goes through Paxos: it observes all conditional (LWT)
+    /// writes committed before it and completes those that are still in
+    /// progress. This makes it the way to read the results of one's own
+    /// conditional writes. Keep in mind that:
+    /// - it is considerably more expensive than a regular read,
+    /// - `LOCAL_SERIAL` is only linearizable with conditional writes that
+    ///   used `LOCAL_SERIAL` in the same datacenter.
+    ///
+    /// Serial consistency can only be used for reads. For conditional writes,
+    /// use [`PreparedStatement::set_serial_consistency`] instead. An error is returned if
+    /// the statement is not a `SELECT`.
+    pub fn set_serial_read(&mut self, sc: SerialConsistency) -> Result<(), SerialReadError> {
+        if !is_select_statement(self.get_statement()) {
+            return Err(SerialReadError {
+                statement: self.get_statement().to_owned(),
+            });
+        }
+        self.config.consistency = Some(sc.into());
+        Ok(())
+    }
+
+    /// Returns true if the statement's consistency is `SERIAL` or `LOCAL_SERIAL`.
+    pub fn is_serial_read(&self) -> bool {
+        self.config.consistency.is_some_and(|c| c.is_serial())
+    }
+
     /// Sets the idempotence of this statement
     /// A query is idempotent if it can be applied multiple times without changing the result of the initial application
     /// If set to `true` we can be sure that it is idempotent
@@ -358,6 +409,13 @@ impl PreparedStatement {
     /// The driver will cache the result metadata received from the server
     /// after statement preparation and will use it
     /// to deserialize the results of statement execution.
+    /// When the result metadata changes, e.g. because a column was added to the table
+    /// of a `SELECT *` statement, the server sends the new metadata, which replaces
+    /// the cached one.
+    ///
+    /// This option has effect only in CQL protocol v5, which lets the server tell
+    /// that the cached metadata is outdated. In older versions the metadata
+    /// is always received.
     ///
     /// This option is false by default.
     pub fn set_use_cached_result_metadata(&mut self, use_cached_metadata: bool) {
@@ -383,6 +441,18 @@ impl PreparedStatement {
         self.config.timestamp
     }
 
+    /// Sets the time in seconds since the epoch which the server should consider
+    /// the current time when executing this statement, e.g. for TTL expiration.
+    /// Requires protocol v5, executing the statement on a v4 connection fails.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the current time override for this statement.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
     /// Sets the client-side timeout for this statement.
     /// If not None, the driver will stop waiting for the request
     /// to finish after `timeout` passed.
@@ -396,6 +466,87 @@ impl PreparedStatement {
         self.config.request_timeout
     }
 
+    /// Exempts this statement from the `ALLOW FILTERING` guardrail configured in
+    /// the execution profile, see [`AllowFilteringGuardrail`](crate::transport::AllowFilteringGuardrail).
+    /// Use it for statements which are known to filter a small amount of data.
+    pub fn set_allow_filtering_permitted(&mut self, permitted: bool) {
+        self.config.allow_filtering_permitted = permitted;
+    }
+
+    /// Gets whether this statement is exempted from the `ALLOW FILTERING` guardrail.
+    pub fn get_allow_filtering_permitted(&self) -> bool {
+        self.config.allow_filtering_permitted
+    }
+
+    /// Sets the maximum memory the rows of a single result (or a single page of results)
+    /// may take, in bytes. A result exceeding the limit is dropped before any row
+    /// is deserialized, and the request fails with
+    /// [`QueryError::ResultMemoryLimitExceeded`].
+    ///
+    /// The accounted size is the size of the serialized rows and metadata the result holds,
+    /// which is what deserializing rows into borrowed types (e.g. `&str`) takes, while
+    /// deserializing them into owned types (e.g. `String`) takes about as much again.
+    /// A response which is larger than the limit as received is discarded while it is
+    /// being read from the connection, without being buffered. Compressed responses are
+    /// checked again once they are decompressed.
+    ///
+    /// By default, results are not limited.
+    pub fn set_result_memory_limit(&mut self, limit: Option<usize>) {
+        self.config.result_memory_limit = limit;
+    }
+
+    /// Gets the maximum memory a single result may take, in bytes.
+    pub fn get_result_memory_limit(&self) -> Option<usize> {
+        self.config.result_memory_limit
+    }
+
+    /// Enables adjusting the page size of paged queries to the observed size and latency
+    /// of the pages, see [`AdaptivePageSize`]. The page size set with
+    /// [`set_page_size`](Self::set_page_size) is used for the first page.
+    ///
+    /// Only `Session::{query,execute}_iter` adjust the page size.
+    /// By default, the page size is fixed.
+    pub fn set_adaptive_page_size(&mut self, adaptive_page_size: Option<AdaptivePageSize>) {
+        self.config.adaptive_page_size = adaptive_page_size;
+    }
+
+    /// Gets the adaptive page size configuration of the statement, if set.
+    pub fn get_adaptive_page_size(&self) -> Option<AdaptivePageSize> {
+        self.config.adaptive_page_size
+    }
+
+    /// Sets how many pages (and bytes) `Session::{query,execute}_iter` may fetch ahead
+    /// of the page being processed, see [`PagePrefetch`].
+    ///
+    /// By default, [`PagePrefetch::default`] is used.
+    pub fn set_page_prefetch(&mut self, page_prefetch: Option<PagePrefetch>) {
+        self.config.page_prefetch = page_prefetch;
+    }
+
+    /// Gets the page prefetching configuration of the statement, if set.
+    pub fn get_page_prefetch(&self) -> Option<PagePrefetch> {
+        self.config.page_prefetch
+    }
+
+    /// Makes `Session::{query,execute}_iter` resume paging after transient failures,
+    /// instead of failing, see [`ResumablePaging`].
+    ///
+    /// By default, paging is not resumed.
+    pub fn set_resumable_paging(&mut self, resumable_paging: Option<ResumablePaging>) {
+        self.config.resumable_paging = resumable_paging;
+    }
+
+    /// Gets the resumable paging configuration of the statement, if set.
+    pub fn get_resumable_paging(&self) -> Option<ResumablePaging> {
+        self.config.resumable_paging
+    }
+
+    /// Gets the server-side timeout of the statement, set on the [`Query`](crate::query::Query)
+    /// it was prepared from with [`Query::set_server_timeout`](crate::query::Query::set_server_timeout).
+    pub fn get_server_timeout(&self) -> Option<Duration> {
+        self.config.server_timeout
+    }
+
     /// Sets the name of the partitioner used for this statement.
     pub(crate) fn set_partitioner_name(&mut self, partitioner_name: PartitionerName) {
         self.partitioner_name = partitioner_name;
@@ -407,6 +558,12 @@ impl PreparedStatement {
     }
 
     /// Access column specifications of the bind variables of this statement
+    /// Returns the kind of the write if the statement is a write whose timestamp
+    /// can be tracked for read-your-writes.
+    pub(crate) fn tracked_write_kind(&self) -> Option<WriteKind> {
+        self.shared.tracked_write_kind
+    }
+
     pub fn get_variable_col_specs(&self) -> &[ColumnSpec<'static>] {
         &self.shared.metadata.col_specs
     }
@@ -416,9 +573,31 @@ impl PreparedStatement {
         &self.shared.metadata.pk_indexes
     }
 
-    /// Access metadata about the result of prepared statement returned by the database
-    pub(crate) fn get_result_metadata(&self) -> &Arc<ResultMetadata<'static>> {
-        &self.shared.result_metadata
+    /// Access metadata about the result of prepared statement returned by the database.
+    /// It is the most recent metadata, updated upon repreparation.
+    pub(crate) fn get_result_metadata(&self) -> Arc<ResultMetadata<'static>> {
+        self.shared.cached_result_metadata.load_full()
+    }
+
+    /// Replaces the cached result metadata, in this statement and in all its clones.
+    pub(crate) fn update_result_metadata(&self, result_metadata: Arc<ResultMetadata<'static>>) {
+        self.shared.cached_result_metadata.store(result_metadata);
+    }
+
+    /// Id of the cached result metadata, returned by the server in protocol v5.
+    pub(crate) fn get_result_metadata_id(&self) -> Option<Bytes> {
+        self.shared
+            .result_metadata_id
+            .load_full()
+            .as_deref()
+            .cloned()
+    }
+
+    /// Replaces the id of the cached result metadata, in this statement and in all its clones.
+    pub(crate) fn update_result_metadata_id(&self, result_metadata_id: Option<Bytes>) {
+        self.shared
+            .result_metadata_id
+            .store(result_metadata_id.map(Arc::new));
     }
 
     /// Access column specifications of the result set returned after the execution of this statement
@@ -588,6 +767,16 @@ impl<'ps> PartitionKey<'ps> {
         Ok(())
     }
 
+    /// Encodes the partition key the way it is encoded for computing its token.
+    pub(crate) fn encode(&self) -> Result<Bytes, TokenCalculationError> {
+        let mut buf = BytesMut::new();
+        let mut writer = |chunk: &[u8]| buf.extend_from_slice(chunk);
+
+        self.write_encoded_partition_key(&mut writer)?;
+
+        Ok(buf.freeze())
+    }
+
     pub(crate) fn calculate_token(
         &self,
         partitioner_name: &PartitionerName,
@@ -1,4 +1,8 @@
-use super::{PageSize, StatementConfig};
+use super::keyspace_template::{qualify_table, KeyspaceName, KeyspaceOverrideError};
+use super::{
+    add_using_timeout, is_select_statement, AdaptivePageSize, PagePrefetch, PageSize,
+    ResumablePaging, SerialReadError, ServerTimeoutError, StatementConfig,
+};
 use crate::frame::types::{Consistency, SerialConsistency};
 use crate::history::HistoryListener;
 use crate::retry_policy::RetryPolicy;
@@ -15,6 +19,7 @@ pub struct Query {
 
     pub contents: String,
     page_size: PageSize,
+    keyspace: Option<KeyspaceName>,
 }
 
 impl Query {
@@ -23,6 +28,7 @@ impl Query {
         Self {
             contents: query_text.into(),
             page_size: PageSize::default(),
+            keyspace: None,
             config: Default::default(),
         }
     }
@@ -77,6 +83,35 @@ impl Query {
         self.config.serial_consistency.flatten()
     }
 
+    /// Makes this `SELECT` a serial read by setting its consistency to `SERIAL`
+    /// or `LOCAL_SERIAL`.
+    ///
+    /// A serial read goes through Paxos: it observes all conditional (LWT)
+    /// writes committed before it and completes those that are still in
+    /// progress. This makes it the way to read the results of one's own
+    /// conditional writes. Keep in mind that:
+    /// - it is considerably more expensive than a regular read,
+    /// - `LOCAL_SERIAL` is only linearizable with conditional writes that
+    ///   used `LOCAL_SERIAL` in the same datacenter.
+    ///
+    /// Serial consistency can only be used for reads. For conditional writes,
+    /// use [`Query::set_serial_consistency`] instead. An error is returned if
+    /// the statement is not a `SELECT`.
+    pub fn set_serial_read(&mut self, sc: SerialConsistency) -> Result<(), SerialReadError> {
+        if !is_select_statement(&self.contents) {
+            return Err(SerialReadError {
+                statement: self.contents.clone(),
+            });
+        }
+        self.config.consistency = Some(sc.into());
+        Ok(())
+    }
+
+    /// Returns true if the statement's consistency is `SERIAL` or `LOCAL_SERIAL`.
+    pub fn is_serial_read(&self) -> bool {
+        self.config.consistency.is_some_and(|c| c.is_serial())
+    }
+
     /// Sets the idempotence of this statement
     /// A query is idempotent if it can be applied multiple times without changing the result of the initial application
     /// If set to `true` we can be sure that it is idempotent
@@ -116,6 +151,18 @@ impl Query {
         self.config.timestamp
     }
 
+    /// Sets the time in seconds since the epoch which the server should consider
+    /// the current time when executing this statement, e.g. for TTL expiration.
+    /// Requires protocol v5, executing the statement on a v4 connection fails.
+    pub fn set_now_in_seconds(&mut self, now_in_seconds: Option<i32>) {
+        self.config.now_in_seconds = now_in_seconds
+    }
+
+    /// Gets the current time override for this statement.
+    pub fn get_now_in_seconds(&self) -> Option<i32> {
+        self.config.now_in_seconds
+    }
+
     /// Sets the client-side timeout for this statement.
     /// If not None, the driver will stop waiting for the request
     /// to finish after `timeout` passed.
@@ -129,6 +176,126 @@ impl Query {
         self.config.request_timeout
     }
 
+    /// Sets Scylla's server-side timeout for this statement, by adding
+    /// a `USING TIMEOUT` clause to it. The coordinator gives up on the request
+    /// once the timeout passes, instead of after its default timeout.
+    ///
+    /// Only `SELECT`, `INSERT`, `UPDATE` and `DELETE` statements support it,
+    /// and it is only understood by Scylla, so executing the statement against
+    /// another database fails with [`BadQuery::ServerTimeoutNotSupported`].
+    /// The timeout is truncated to whole milliseconds.
+    ///
+    /// The client-side timeout (see [`Query::set_request_timeout`]) is extended
+    /// if needed, so that the driver does not stop waiting for the response
+    /// before the server does.
+    ///
+    /// [`BadQuery::ServerTimeoutNotSupported`]: crate::transport::errors::BadQuery::ServerTimeoutNotSupported
+    pub fn set_server_timeout(&mut self, timeout: Duration) -> Result<(), ServerTimeoutError> {
+        self.contents = add_using_timeout(&self.contents, timeout)?;
+        self.config.server_timeout = Some(Duration::from_millis(timeout.as_millis() as u64));
+        Ok(())
+    }
+
+    /// Gets the server-side timeout set with [`Query::set_server_timeout`].
+    pub fn get_server_timeout(&self) -> Option<Duration> {
+        self.config.server_timeout
+    }
+
+    /// Sets the keyspace of the table the statement operates on, so that it doesn't
+    /// depend on the keyspace set with [`Session::use_keyspace`](crate::Session::use_keyspace).
+    /// This also applies when the statement is prepared.
+    ///
+    /// Version 4 of the CQL protocol can't send the keyspace along with a statement,
+    /// so the table name in the statement is qualified with the keyspace instead.
+    /// Only `SELECT`, `INSERT`, `UPDATE` and `DELETE` statements whose table is
+    /// not qualified yet are supported. On protocol v5 connections, the keyspace
+    /// is sent along with the statement, too.
+    pub fn set_keyspace(&mut self, keyspace: KeyspaceName) -> Result<(), KeyspaceOverrideError> {
+        self.contents = qualify_table(&self.contents, &keyspace)?;
+        self.keyspace = Some(keyspace);
+        Ok(())
+    }
+
+    /// Gets the keyspace set with [`Query::set_keyspace`].
+    pub fn get_keyspace(&self) -> Option<&KeyspaceName> {
+        self.keyspace.as_ref()
+    }
+
+    /// Exempts this statement from the `ALLOW FILTERING` guardrail configured in
+    /// the execution profile, see [`AllowFilteringGuardrail`](crate::transport::AllowFilteringGuardrail).
+    /// Use it for statements which are known to filter a small amount of data.
+    pub fn set_allow_filtering_permitted(&mut self, permitted: bool) {
+        self.config.allow_filtering_permitted = permitted;
+    }
+
+    /// Gets whether this statement is exempted from the `ALLOW FILTERING` guardrail.
+    pub fn get_allow_filtering_permitted(&self) -> bool {
+        self.config.allow_filtering_permitted
+    }
+
+    /// Sets the maximum memory the rows of a single result (or a single page of results)
+    /// may take, in bytes. A result exceeding the limit is dropped before any row
+    /// is deserialized, and the request fails with
+    /// [`QueryError::ResultMemoryLimitExceeded`](crate::transport::errors::QueryError::ResultMemoryLimitExceeded).
+    ///
+    /// The accounted size is the size of the serialized rows and metadata the result holds,
+    /// which is what deserializing rows into borrowed types (e.g. `&str`) takes, while
+    /// deserializing them into owned types (e.g. `String`) takes about as much again.
+    /// A response which is larger than the limit as received is discarded while it is
+    /// being read from the connection, without being buffered. Compressed responses are
+    /// checked again once they are decompressed.
+    ///
+    /// By default, results are not limited.
+    pub fn set_result_memory_limit(&mut self, limit: Option<usize>) {
+        self.config.result_memory_limit = limit;
+    }
+
+    /// Gets the maximum memory a single result may take, in bytes.
+    pub fn get_result_memory_limit(&self) -> Option<usize> {
+        self.config.result_memory_limit
+    }
+
+    /// Enables adjusting the page size of paged queries to the observed size and latency
+    /// of the pages, see [`AdaptivePageSize`]. The page size set with
+    /// [`set_page_size`](Self::set_page_size) is used for the first page.
+    ///
+    /// Only `Session::{query,execute}_iter` adjust the page size.
+    /// By default, the page size is fixed.
+    pub fn set_adaptive_page_size(&mut self, adaptive_page_size: Option<AdaptivePageSize>) {
+        self.config.adaptive_page_size = adaptive_page_size;
+    }
+
+    /// Gets the adaptive page size configuration of the statement, if set.
+    pub fn get_adaptive_page_size(&self) -> Option<AdaptivePageSize> {
+        self.config.adaptive_page_size
+    }
+
+    /// Sets how many pages (and bytes) `Session::{query,execute}_iter` may fetch ahead
+    /// of the page being processed, see [`PagePrefetch`].
+    ///
+    /// By default, [`PagePrefetch::default`] is used.
+    pub fn set_page_prefetch(&mut self, page_prefetch: Option<PagePrefetch>) {
+        self.config.page_prefetch = page_prefetch;
+    }
+
+    /// Gets the page prefetching configuration of the statement, if set.
+    pub fn get_page_prefetch(&self) -> Option<PagePrefetch> {
+        self.config.page_prefetch
+    }
+
+    /// Makes `Session::{query,execute}_iter` resume paging after transient failures,
+    /// instead of failing, see [`ResumablePaging`].
+    ///
+    /// By default, paging is not resumed.
+    pub fn set_resumable_paging(&mut self, resumable_paging: Option<ResumablePaging>) {
+        self.config.resumable_paging = resumable_paging;
+    }
+
+    /// Gets the resumable paging configuration of the statement, if set.
+    pub fn get_resumable_paging(&self) -> Option<ResumablePaging> {
+        self.config.resumable_paging
+    }
+
     /// Set the retry policy for this statement, overriding the one from execution profile if not None.
     #[inline]
     pub fn set_retry_policy(&mut self, retry_policy: Option<Arc<dyn RetryPolicy>>) {
@@ -4,6 +4,7 @@ use scylla_cql::frame::value::CqlTimeuuid;
 use scylla_macros::DeserializeRow;
 use std::collections::HashMap;
 use std::net::IpAddr;
+use std::time::Duration;
 
 /// Tracing info retrieved from `system_traces.sessions`
 /// with all events from `system_traces.events`
@@ -43,6 +44,30 @@ impl TracingInfo {
             .unique()
             .collect()
     }
+
+    /// Returns true if the coordinator has finished writing the trace.
+    /// The duration of the query is written last, after all the events.
+    pub fn is_complete(&self) -> bool {
+        self.duration.is_some()
+    }
+
+    /// Returns the duration of the query, as measured by the coordinator.
+    pub fn elapsed(&self) -> Option<Duration> {
+        micros_to_duration(self.duration)
+    }
+}
+
+impl TracingEvent {
+    /// Returns the time elapsed since the start of the query on the node
+    /// the event happened on.
+    pub fn elapsed(&self) -> Option<Duration> {
+        micros_to_duration(self.source_elapsed)
+    }
+}
+
+// Durations in the tracing tables are in microseconds.
+fn micros_to_duration(micros: Option<i32>) -> Option<Duration> {
+    micros.map(|micros| Duration::from_micros(micros.max(0) as u64))
 }
 
 // A query used to query TracingInfo from system_traces.sessions
@@ -54,3 +79,48 @@ pub(crate) const TRACES_SESSION_QUERY_STR: &str =
 pub(crate) const TRACES_EVENTS_QUERY_STR: &str =
     "SELECT event_id, activity, source, source_elapsed, thread \
     FROM system_traces.events WHERE session_id = ?";
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{TracingEvent, TracingInfo};
+    use crate::frame::value::CqlTimeuuid;
+    use crate::test_utils::setup_tracing;
+
+    fn event(source_elapsed: Option<i32>) -> TracingEvent {
+        TracingEvent {
+            event_id: CqlTimeuuid::from_bytes([0; 16]),
+            activity: None,
+            source: None,
+            source_elapsed,
+            thread: None,
+        }
+    }
+
+    #[test]
+    fn trace_completeness_and_durations() {
+        setup_tracing();
+        let mut trace = TracingInfo {
+            client: None,
+            command: None,
+            coordinator: None,
+            duration: None,
+            parameters: None,
+            request: None,
+            started_at: None,
+            events: vec![event(Some(1500)), event(None)],
+        };
+        assert!(!trace.is_complete());
+        assert_eq!(trace.elapsed(), None);
+
+        trace.duration = Some(2500);
+        assert!(trace.is_complete());
+        assert_eq!(trace.elapsed(), Some(Duration::from_micros(2500)));
+
+        assert_eq!(trace.events[0].elapsed(), Some(Duration::from_micros(1500)));
+        assert_eq!(trace.events[1].elapsed(), None);
+        // Negative durations are clamped to zero.
+        assert_eq!(event(Some(-1)).elapsed(), Some(Duration::ZERO));
+    }
+}
@@ -0,0 +1,249 @@
+//! Spans of request attempts
+//! A session built with [`SessionBuilder::attempt_spans`](super::session_builder::GenericSessionBuilder::attempt_spans)
+//! opens an `INFO` span for each attempt of a request, i.e. each time the request
+//! (or a page of it) is sent to a node, including retries and speculative executions.
+//!
+//! The fields of the spans follow the OpenTelemetry semantic conventions for database
+//! client spans, and the `otel.*` fields understood by `tracing-opentelemetry`,
+//! so that the attempts show up in distributed traces:
+//! - `otel.name`, `otel.kind`, `otel.status_code`, `otel.status_message`,
+//! - `db.system`, `db.namespace`, `db.collection.name`, `db.operation.name`,
+//! - `db.cassandra.consistency_level`, `db.cassandra.coordinator.id`, `db.cassandra.coordinator.dc`,
+//! - `server.address`, `server.port`, `error.type`.
+//!
+//! Driver-specific fields are prefixed with `scylla.`:
+//! - `scylla.shard` - the shard of the connection the attempt was sent on,
+//! - `scylla.statement.fingerprint` - a stable hash of the text of the statement,
+//! - `scylla.attempt` - 0 for the first attempt, n for the n-th retry,
+//! - `scylla.speculative_execution` - 0 for the original execution, n for the n-th speculative one.
+
+use std::fmt::Display;
+
+use scylla_cql::frame::response::result::TableSpec;
+use scylla_cql::frame::types::Consistency;
+use tracing::field::Empty;
+use tracing::info_span;
+
+use super::errors::QueryError;
+use super::metrics_export::error_kind;
+use super::NodeRef;
+use crate::routing::Shard;
+
+/// What the attempt spans of a request know about its statement.
+#[derive(Debug, Clone)]
+pub(crate) struct AttemptSpanStatement {
+    operation: Option<&'static str>,
+    fingerprint: u64,
+    namespace: Option<String>,
+    collection: Option<String>,
+}
+
+impl AttemptSpanStatement {
+    pub(crate) fn new(contents: &str, table: Option<&TableSpec<'_>>) -> Self {
+        Self {
+            operation: operation_name(contents),
+            fingerprint: fingerprint(contents),
+            namespace: table.map(|table| table.ks_name().to_owned()),
+            collection: table.map(|table| table.table_name().to_owned()),
+        }
+    }
+
+    // The namespace and collection of a batch are the ones of its first statement, if known.
+    pub(crate) fn new_batch<'a>(
+        contents: impl IntoIterator<Item = &'a str>,
+        table: Option<&TableSpec<'_>>,
+    ) -> Self {
+        let fingerprint = contents
+            .into_iter()
+            .fold(FNV_OFFSET_BASIS, |hash, statement| {
+                fnv1a(hash, &fingerprint(statement).to_le_bytes())
+            });
+        Self {
+            operation: Some("BATCH"),
+            fingerprint,
+            namespace: table.map(|table| table.ks_name().to_owned()),
+            collection: table.map(|table| table.table_name().to_owned()),
+        }
+    }
+
+    // Following the conventions, the name is `{operation} {namespace}.{collection}`,
+    // with the unknown parts left out.
+    fn span_name(&self) -> impl Display + '_ {
+        struct SpanName<'a>(&'a AttemptSpanStatement);
+        impl Display for SpanName<'_> {
+            fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+                let statement = self.0;
+                let target = statement.namespace.as_deref().map(|namespace| {
+                    (
+                        namespace,
+                        statement.collection.as_deref().unwrap_or_default(),
+                    )
+                });
+                match (statement.operation, target) {
+                    (Some(operation), Some((namespace, collection))) => {
+                        write!(f, "{} {}.{}", operation, namespace, collection)
+                    }
+                    (Some(operation), None) => f.write_str(operation),
+                    (None, Some((namespace, collection))) => {
+                        write!(f, "{}.{}", namespace, collection)
+                    }
+                    (None, None) => f.write_str("scylla"),
+                }
+            }
+        }
+        SpanName(self)
+    }
+
+    /// Opens the span of an attempt sent to the given node and shard.
+    pub(crate) fn attempt_span(
+        &self,
+        node: NodeRef<'_>,
+        shard: Option<Shard>,
+        consistency: Consistency,
+        attempt: usize,
+        speculative_execution: usize,
+    ) -> tracing::Span {
+        info_span!(
+            "Attempt",
+            otel.name = %self.span_name(),
+            otel.kind = "client",
+            otel.status_code = Empty,
+            otel.status_message = Empty,
+            db.system = "cassandra",
+            db.namespace = self.namespace.as_deref(),
+            db.collection.name = self.collection.as_deref(),
+            db.operation.name = self.operation,
+            db.cassandra.consistency_level = consistency_level_name(consistency),
+            db.cassandra.coordinator.id = %node.host_id,
+            db.cassandra.coordinator.dc = node.datacenter.as_deref(),
+            server.address = %node.address.ip(),
+            server.port = node.address.port(),
+            error.type = Empty,
+            scylla.shard = shard,
+            scylla.statement.fingerprint = %format_args!("{:016x}", self.fingerprint),
+            scylla.attempt = attempt,
+            scylla.speculative_execution = speculative_execution,
+        )
+    }
+}
+
+/// Records the outcome of an attempt in its span.
+pub(crate) fn record_attempt_result(span: &tracing::Span, error: Option<&QueryError>) {
+    match error {
+        None => {
+            span.record("otel.status_code", "OK");
+        }
+        Some(error) => {
+            span.record("otel.status_code", "ERROR");
+            span.record("otel.status_message", tracing::field::display(error));
+            span.record("error.type", error_kind(error));
+        }
+    }
+}
+
+// Values of `db.cassandra.consistency_level` from the conventions.
+fn consistency_level_name(consistency: Consistency) -> &'static str {
+    match consistency {
+        Consistency::Any => "any",
+        Consistency::One => "one",
+        Consistency::Two => "two",
+        Consistency::Three => "three",
+        Consistency::Quorum => "quorum",
+        Consistency::All => "all",
+        Consistency::LocalQuorum => "local_quorum",
+        Consistency::EachQuorum => "each_quorum",
+        Consistency::LocalOne => "local_one",
+        Consistency::Serial => "serial",
+        Consistency::LocalSerial => "local_serial",
+    }
+}
+
+// The first keyword of the statement, if it is one of the CQL commands.
+fn operation_name(contents: &str) -> Option<&'static str> {
+    const OPERATIONS: &[&str] = &[
+        "SELECT", "INSERT", "UPDATE", "DELETE", "BATCH", "BEGIN", "TRUNCATE", "CREATE", "ALTER",
+        "DROP", "USE", "GRANT", "REVOKE", "LIST", "DESCRIBE",
+    ];
+    let keyword = contents.split_whitespace().next()?;
+    OPERATIONS
+        .iter()
+        .copied()
+        .find(|operation| operation.eq_ignore_ascii_case(keyword))
+}
+
+const FNV_OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+const FNV_PRIME: u64 = 0x0000_0100_0000_01b3;
+
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, byte| {
+        (hash ^ *byte as u64).wrapping_mul(FNV_PRIME)
+    })
+}
+
+// A 64-bit FNV-1a hash of the statement with its whitespace normalized, so that
+// it is the same across processes and versions of the driver.
+fn fingerprint(contents: &str) -> u64 {
+    let mut hash = FNV_OFFSET_BASIS;
+    for (i, word) in contents.split_whitespace().enumerate() {
+        if i > 0 {
+            hash = fnv1a(hash, b" ");
+        }
+        hash = fnv1a(hash, word.as_bytes());
+    }
+    hash
+}
+
+#[cfg(test)]
+mod tests {
+    use scylla_cql::frame::response::result::TableSpec;
+
+    use super::{fingerprint, operation_name, AttemptSpanStatement};
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn fingerprints() {
+        setup_tracing();
+        // The hash of an empty input is the offset basis, and of "a" is the reference value.
+        assert_eq!(fingerprint(""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fingerprint("a"), 0xaf63_dc4c_8601_ec8c);
+
+        assert_eq!(
+            fingerprint("SELECT * FROM ks.t WHERE a = ?"),
+            fingerprint("  SELECT *\n\tFROM ks.t  WHERE a = ?\n"),
+        );
+        assert_ne!(
+            fingerprint("SELECT * FROM ks.t WHERE a = ?"),
+            fingerprint("SELECT * FROM ks.t WHERE b = ?"),
+        );
+
+        let batch =
+            AttemptSpanStatement::new_batch(["INSERT INTO t (a) VALUES (1)", "DELETE"], None);
+        let reversed =
+            AttemptSpanStatement::new_batch(["DELETE", "INSERT INTO t (a) VALUES (1)"], None);
+        assert_ne!(batch.fingerprint, reversed.fingerprint);
+    }
+
+    #[test]
+    fn span_names() {
+        setup_tracing();
+        assert_eq!(operation_name("select * from t"), Some("SELECT"));
+        assert_eq!(
+            operation_name("\n  Insert INTO t (a) VALUES (1)"),
+            Some("INSERT")
+        );
+        assert_eq!(operation_name("SELECTS"), None);
+        assert_eq!(operation_name(""), None);
+
+        let table = TableSpec::borrowed("ks", "t");
+        let statement = AttemptSpanStatement::new("SELECT a FROM t", Some(&table));
+        assert_eq!(statement.span_name().to_string(), "SELECT ks.t");
+        let statement = AttemptSpanStatement::new("SELECT a FROM ks.t", None);
+        assert_eq!(statement.span_name().to_string(), "SELECT");
+        let statement = AttemptSpanStatement::new("", None);
+        assert_eq!(statement.span_name().to_string(), "scylla");
+        let batch = AttemptSpanStatement::new_batch(["INSERT INTO t (a) VALUES (1)"], None);
+        assert_eq!(batch.span_name().to_string(), "BATCH");
+        let batch = AttemptSpanStatement::new_batch(["INSERT INTO t (a) VALUES (1)"], Some(&table));
+        assert_eq!(batch.span_name().to_string(), "BATCH ks.t");
+    }
+}
@@ -0,0 +1,182 @@
+//! Backpressure signals
+//!
+//! A session configured with `BackpressureThresholds` reports which nodes have too many
+//! requests in flight through a watch channel, see [`Session::backpressure_receiver`](super::session::Session::backpressure_receiver).
+//! Applications can subscribe to it and shed load before requests start timing out.
+//!
+//! A request is in flight from the moment it is assigned to a node, so the count includes
+//! the requests queued on the node's connections, e.g. waiting for a free stream id.
+//! A node becomes overloaded when the number of its requests in flight exceeds
+//! the high watermark, and stops being overloaded when it drops to the low watermark.
+
+use std::collections::HashSet;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use tokio::sync::watch;
+use uuid::Uuid;
+
+/// Thresholds of the number of requests in flight to a node,
+/// which decide whether the node is overloaded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BackpressureThresholds {
+    high_watermark: usize,
+    low_watermark: usize,
+}
+
+impl BackpressureThresholds {
+    /// Creates thresholds with which a node becomes overloaded when it has more than
+    /// `high_watermark` requests in flight. The low watermark is 3/4 of the high one, rounded up.
+    pub fn new(high_watermark: usize) -> Self {
+        Self {
+            high_watermark,
+            low_watermark: high_watermark - high_watermark / 4,
+        }
+    }
+
+    /// Sets the number of requests in flight at which an overloaded node stops being overloaded.
+    /// It is capped at the high watermark.
+    pub fn with_low_watermark(mut self, low_watermark: usize) -> Self {
+        self.low_watermark = low_watermark.min(self.high_watermark);
+        self
+    }
+
+    /// Returns the number of requests in flight above which a node becomes overloaded.
+    pub fn high_watermark(&self) -> usize {
+        self.high_watermark
+    }
+
+    /// Returns the number of requests in flight at which a node stops being overloaded.
+    pub fn low_watermark(&self) -> usize {
+        self.low_watermark
+    }
+}
+
+/// The nodes which are currently overloaded, as published through the watch channel.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct BackpressureState {
+    overloaded_nodes: HashSet<Uuid>,
+}
+
+impl BackpressureState {
+    /// Returns true if any node is overloaded.
+    pub fn is_overloaded(&self) -> bool {
+        !self.overloaded_nodes.is_empty()
+    }
+
+    /// Returns true if the node with the given host id is overloaded.
+    pub fn is_node_overloaded(&self, host_id: Uuid) -> bool {
+        self.overloaded_nodes.contains(&host_id)
+    }
+
+    /// Returns the host ids of the overloaded nodes.
+    pub fn overloaded_nodes(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.overloaded_nodes.iter().copied()
+    }
+}
+
+/// Updates the backpressure state when the number of requests in flight
+/// to some node crosses one of the thresholds.
+#[derive(Debug)]
+pub(crate) struct BackpressureMonitor {
+    thresholds: BackpressureThresholds,
+    sender: watch::Sender<BackpressureState>,
+}
+
+impl BackpressureMonitor {
+    pub(crate) fn new(thresholds: BackpressureThresholds) -> Self {
+        Self {
+            thresholds,
+            sender: watch::Sender::new(BackpressureState::default()),
+        }
+    }
+
+    pub(crate) fn subscribe(&self) -> watch::Receiver<BackpressureState> {
+        self.sender.subscribe()
+    }
+
+    /// Called after the number of requests in flight to a node changed to `in_flight`.
+    /// The counter itself is passed, too, so that concurrent changes are resolved
+    /// according to its latest value.
+    pub(crate) fn in_flight_changed(
+        &self,
+        host_id: Uuid,
+        in_flight: usize,
+        in_flight_counter: &AtomicUsize,
+    ) {
+        // The counter changes by one, so it always passes through these values
+        // when crossing the thresholds. Other values don't need the lock.
+        if Some(in_flight) != self.thresholds.high_watermark.checked_add(1)
+            && in_flight != self.thresholds.low_watermark
+        {
+            return;
+        }
+        self.sender.send_if_modified(|state| {
+            let in_flight = in_flight_counter.load(Ordering::Relaxed);
+            if in_flight > self.thresholds.high_watermark {
+                state.overloaded_nodes.insert(host_id)
+            } else if in_flight <= self.thresholds.low_watermark {
+                state.overloaded_nodes.remove(&host_id)
+            } else {
+                false
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use uuid::Uuid;
+
+    use super::{BackpressureMonitor, BackpressureThresholds};
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn thresholds() {
+        setup_tracing();
+        let thresholds = BackpressureThresholds::new(100);
+        assert_eq!(thresholds.high_watermark(), 100);
+        assert_eq!(thresholds.low_watermark(), 75);
+        assert_eq!(BackpressureThresholds::new(10).low_watermark(), 8);
+        assert_eq!(thresholds.with_low_watermark(200).low_watermark(), 100);
+    }
+
+    #[test]
+    fn overloaded_nodes() {
+        setup_tracing();
+        let monitor =
+            BackpressureMonitor::new(BackpressureThresholds::new(4).with_low_watermark(2));
+        let receiver = monitor.subscribe();
+        let host_id = Uuid::new_v4();
+        let counter = AtomicUsize::new(0);
+
+        let set_in_flight = |target: usize| {
+            while counter.load(Ordering::Relaxed) != target {
+                let in_flight = if counter.load(Ordering::Relaxed) < target {
+                    counter.fetch_add(1, Ordering::Relaxed) + 1
+                } else {
+                    counter.fetch_sub(1, Ordering::Relaxed) - 1
+                };
+                monitor.in_flight_changed(host_id, in_flight, &counter);
+            }
+        };
+
+        set_in_flight(4);
+        assert!(!receiver.borrow().is_overloaded());
+        set_in_flight(5);
+        assert!(receiver.borrow().is_node_overloaded(host_id));
+        assert_eq!(
+            receiver.borrow().overloaded_nodes().collect::<Vec<_>>(),
+            [host_id]
+        );
+
+        // Stays overloaded until the low watermark is reached.
+        set_in_flight(3);
+        assert!(receiver.borrow().is_overloaded());
+        set_in_flight(2);
+        assert!(!receiver.borrow().is_overloaded());
+        set_in_flight(0);
+        assert!(!receiver.borrow().is_overloaded());
+    }
+}
@@ -1,6 +1,7 @@
 use crate::batch::{Batch, BatchStatement};
 use crate::prepared_statement::PreparedStatement;
 use crate::query::Query;
+use crate::statement::keyspace_template::{KeyspaceName, KeyspaceTemplate};
 use crate::statement::{PagingState, PagingStateResponse};
 use crate::transport::errors::QueryError;
 #[allow(deprecated)]
@@ -10,14 +11,16 @@ use crate::transport::partitioner::PartitionerName;
 use crate::LegacyQueryResult;
 use crate::QueryResult;
 use bytes::Bytes;
-use dashmap::DashMap;
 use futures::future::try_join_all;
 use scylla_cql::frame::response::result::{PreparedMetadata, ResultMetadata};
 use scylla_cql::types::serialize::batch::BatchValues;
 use scylla_cql::types::serialize::row::SerializeRow;
 use std::collections::hash_map::RandomState;
-use std::hash::BuildHasher;
-use std::sync::Arc;
+use std::collections::{BTreeMap, HashMap};
+use std::hash::{BuildHasher, Hash};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::{Duration, Instant};
 
 use super::iterator::QueryPager;
 #[allow(deprecated)]
@@ -46,11 +49,9 @@ where
     DeserializationApi: DeserializationApiKind,
 {
     session: GenericSession<DeserializationApi>,
-    /// The prepared statement cache size
-    /// If a prepared statement is added while the limit is reached, the oldest prepared statement
-    /// is removed from the cache
-    max_capacity: usize,
-    cache: DashMap<String, RawPreparedStatementData, S>,
+    config: PreparedCacheConfig,
+    cache: Mutex<LruCache<PreparedCacheKey, Arc<RawPreparedStatementData>, S>>,
+    metrics: PreparedCacheMetrics,
 }
 
 pub type CachingSession<S = RandomState> = GenericCachingSession<CurrentDeserializationApi, S>;
@@ -62,17 +63,124 @@ pub type CachingSession<S = RandomState> = GenericCachingSession<CurrentDeserial
 #[allow(deprecated)]
 pub type LegacyCachingSession<S = RandomState> = GenericCachingSession<LegacyDeserializationApi, S>;
 
+/// Configuration of the prepared statement cache of a [`CachingSession`].
+#[derive(Debug, Clone)]
+pub struct PreparedCacheConfig {
+    /// The maximum number of cached statements. If a statement is added while
+    /// the limit is reached, the least recently used one is removed from the cache.
+    pub max_capacity: usize,
+
+    /// The time after which a cached statement is prepared again, e.g. to pick up
+    /// schema changes. Statements are cached until evicted or invalidated if unset.
+    pub ttl: Option<Duration>,
+
+    /// Determines the key under which statements are cached.
+    pub key_policy: Arc<dyn PreparedCacheKeyPolicy>,
+}
+
+impl PreparedCacheConfig {
+    /// Creates a configuration of a cache holding at most `max_capacity` statements,
+    /// without a TTL and with the [`DefaultPreparedCacheKeyPolicy`].
+    pub fn new(max_capacity: usize) -> Self {
+        Self {
+            max_capacity,
+            ttl: None,
+            key_policy: Arc::new(DefaultPreparedCacheKeyPolicy),
+        }
+    }
+}
+
+/// The key under which a prepared statement is cached.
+/// Statements with equal keys share a single cache entry.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PreparedCacheKey {
+    /// The CQL statement.
+    pub contents: String,
+
+    /// The keyspace used for unqualified table names, if it is a part of the key.
+    pub keyspace: Option<String>,
+
+    /// The label of the execution profile of the statement, if it is a part of the key.
+    /// See [`ExecutionProfile::into_handle_with_label`](crate::transport::ExecutionProfile::into_handle_with_label).
+    pub profile: Option<String>,
+}
+
+/// Determines the key under which [`CachingSession`] caches a prepared statement.
+///
+/// Statements which are prepared differently, e.g. for different tenants,
+/// must have different keys.
+pub trait PreparedCacheKeyPolicy: std::fmt::Debug + Send + Sync {
+    /// Returns the key of the statement. `keyspace` is the current keyspace of the session.
+    fn key(&self, query: &Query, keyspace: Option<&str>) -> PreparedCacheKey;
+}
+
+/// The default [`PreparedCacheKeyPolicy`], which keys statements by their contents,
+/// the current keyspace of the session and the label of their execution profile.
+///
+/// The keyspace is a part of the key because it determines the tables referenced
+/// by unqualified names, so the same statement is prepared again
+/// after [`Session::use_keyspace`](GenericSession::use_keyspace).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct DefaultPreparedCacheKeyPolicy;
+
+impl PreparedCacheKeyPolicy for DefaultPreparedCacheKeyPolicy {
+    fn key(&self, query: &Query, keyspace: Option<&str>) -> PreparedCacheKey {
+        PreparedCacheKey {
+            contents: query.contents.clone(),
+            keyspace: keyspace.map(str::to_owned),
+            profile: query
+                .get_execution_profile_handle()
+                .and_then(|handle| handle.label())
+                .map(str::to_owned),
+        }
+    }
+}
+
+/// Counters of the prepared statement cache of a [`CachingSession`].
+#[derive(Debug, Default)]
+pub struct PreparedCacheMetrics {
+    hits: AtomicU64,
+    misses: AtomicU64,
+    evictions: AtomicU64,
+    expirations: AtomicU64,
+}
+
+impl PreparedCacheMetrics {
+    /// Returns the number of statements found in the cache.
+    pub fn get_hits(&self) -> u64 {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of statements which had to be prepared,
+    /// including the ones whose cache entry has expired.
+    pub fn get_misses(&self) -> u64 {
+        self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of statements removed from the cache because it was full.
+    pub fn get_evictions(&self) -> u64 {
+        self.evictions.load(Ordering::Relaxed)
+    }
+
+    /// Returns the number of statements removed from the cache because their TTL has passed.
+    pub fn get_expirations(&self) -> u64 {
+        self.expirations.load(Ordering::Relaxed)
+    }
+}
+
 impl<DeserApi, S> GenericCachingSession<DeserApi, S>
 where
     S: Default + BuildHasher + Clone,
     DeserApi: DeserializationApiKind,
 {
     pub fn from(session: GenericSession<DeserApi>, cache_size: usize) -> Self {
-        Self {
-            session,
-            max_capacity: cache_size,
-            cache: Default::default(),
-        }
+        Self::with_config(session, PreparedCacheConfig::new(cache_size))
+    }
+
+    /// Builds a [`CachingSession`] from a [`Session`](GenericSession)
+    /// and a configuration of the cache.
+    pub fn with_config(session: GenericSession<DeserApi>, config: PreparedCacheConfig) -> Self {
+        Self::with_config_and_hasher(session, config, Default::default())
     }
 }
 
@@ -84,10 +192,21 @@ where
     /// Builds a [`CachingSession`] from a [`Session`](GenericSession), a cache size,
     /// and a [`BuildHasher`], using a customer hasher.
     pub fn with_hasher(session: GenericSession<DeserApi>, cache_size: usize, hasher: S) -> Self {
+        Self::with_config_and_hasher(session, PreparedCacheConfig::new(cache_size), hasher)
+    }
+
+    /// Builds a [`CachingSession`] from a [`Session`](GenericSession), a configuration
+    /// of the cache and a [`BuildHasher`], using a customer hasher.
+    pub fn with_config_and_hasher(
+        session: GenericSession<DeserApi>,
+        config: PreparedCacheConfig,
+        hasher: S,
+    ) -> Self {
         Self {
             session,
-            max_capacity: cache_size,
-            cache: DashMap::with_hasher(hasher),
+            config,
+            cache: Mutex::new(LruCache::with_hasher(hasher)),
+            metrics: PreparedCacheMetrics::default(),
         }
     }
 }
@@ -135,6 +254,46 @@ where
             .await
     }
 
+    /// Does the same thing as [`CachingSession::execute_unpaged`](GenericCachingSession::execute_unpaged)
+    /// for the statement rendered from the template for the given keyspace.
+    /// The statement is prepared and cached separately for each keyspace.
+    /// See [`keyspace_template`](crate::statement::keyspace_template) for details.
+    pub async fn execute_unpaged_in_keyspace(
+        &self,
+        template: &KeyspaceTemplate,
+        keyspace: &KeyspaceName,
+        values: impl SerializeRow,
+    ) -> Result<QueryResult, QueryError> {
+        self.execute_unpaged(template.render(keyspace), values)
+            .await
+    }
+
+    /// Does the same thing as [`CachingSession::execute_iter`](GenericCachingSession::execute_iter)
+    /// for the statement rendered from the template for the given keyspace.
+    /// The statement is prepared and cached separately for each keyspace.
+    pub async fn execute_iter_in_keyspace(
+        &self,
+        template: &KeyspaceTemplate,
+        keyspace: &KeyspaceName,
+        values: impl SerializeRow,
+    ) -> Result<QueryPager, QueryError> {
+        self.execute_iter(template.render(keyspace), values).await
+    }
+
+    /// Does the same thing as [`CachingSession::execute_single_page`](GenericCachingSession::execute_single_page)
+    /// for the statement rendered from the template for the given keyspace.
+    /// The statement is prepared and cached separately for each keyspace.
+    pub async fn execute_single_page_in_keyspace(
+        &self,
+        template: &KeyspaceTemplate,
+        keyspace: &KeyspaceName,
+        values: impl SerializeRow,
+        paging_state: PagingState,
+    ) -> Result<(QueryResult, PagingStateResponse), QueryError> {
+        self.execute_single_page(template.render(keyspace), values, paging_state)
+            .await
+    }
+
     /// Does the same thing as [`Session::batch`](GenericSession::batch) but uses the
     /// prepared statement cache.\
     /// Prepares batch using [`CachingSession::prepare_batch`](GenericCachingSession::prepare_batch)
@@ -272,62 +431,208 @@ where
         query: impl Into<Query>,
     ) -> Result<PreparedStatement, QueryError> {
         let query = query.into();
-
-        if let Some(raw) = self.cache.get(&query.contents) {
-            let page_size = query.get_validated_page_size();
-            let mut stmt = PreparedStatement::new(
-                raw.id.clone(),
-                raw.is_confirmed_lwt,
-                raw.metadata.clone(),
-                raw.result_metadata.clone(),
-                query.contents,
-                page_size,
-                query.config,
-            );
-            stmt.set_partitioner_name(raw.partitioner_name.clone());
-            Ok(stmt)
-        } else {
-            let query_contents = query.contents.clone();
-            let prepared = self.session.prepare(query).await?;
-
-            if self.max_capacity == self.cache.len() {
-                // Cache is full, remove the first entry
-                // Don't hold a reference into the map (that's why the to_string() is called)
-                // This is because the documentation of the remove fn tells us that it may deadlock
-                // when holding some sort of reference into the map
-                let query = self.cache.iter().next().map(|c| c.key().to_string());
-
-                // Don't inline this: https://stackoverflow.com/questions/69873846/an-owned-value-is-still-references-somehow
-                if let Some(q) = query {
-                    self.cache.remove(&q);
-                }
+        let key = self.cache_key(&query);
+
+        let lookup = self.lock_cache().get(&key, self.config.ttl);
+        match lookup {
+            CacheLookup::Hit(raw) => {
+                self.metrics.hits.fetch_add(1, Ordering::Relaxed);
+                let page_size = query.get_validated_page_size();
+                let mut stmt = PreparedStatement::new(
+                    raw.id.clone(),
+                    raw.is_confirmed_lwt,
+                    raw.metadata.clone(),
+                    raw.result_metadata.clone(),
+                    query.contents,
+                    page_size,
+                    query.config,
+                );
+                stmt.set_partitioner_name(raw.partitioner_name.clone());
+                return Ok(stmt);
+            }
+            CacheLookup::Expired => {
+                self.metrics.expirations.fetch_add(1, Ordering::Relaxed);
             }
+            CacheLookup::Miss => {}
+        }
+        self.metrics.misses.fetch_add(1, Ordering::Relaxed);
+
+        let prepared = self.session.prepare(query).await?;
+        let raw = RawPreparedStatementData {
+            id: prepared.get_id().clone(),
+            is_confirmed_lwt: prepared.is_confirmed_lwt(),
+            metadata: prepared.get_prepared_metadata().clone(),
+            result_metadata: prepared.get_result_metadata(),
+            partitioner_name: prepared.get_partitioner_name().clone(),
+        };
+        let evicted = self
+            .lock_cache()
+            .insert(key, Arc::new(raw), self.config.max_capacity);
+        self.metrics
+            .evictions
+            .fetch_add(evicted as u64, Ordering::Relaxed);
+
+        Ok(prepared)
+    }
 
-            let raw = RawPreparedStatementData {
-                id: prepared.get_id().clone(),
-                is_confirmed_lwt: prepared.is_confirmed_lwt(),
-                metadata: prepared.get_prepared_metadata().clone(),
-                result_metadata: prepared.get_result_metadata().clone(),
-                partitioner_name: prepared.get_partitioner_name().clone(),
-            };
-            self.cache.insert(query_contents, raw);
+    /// Removes the statement from the cache, so that it is prepared again
+    /// the next time it is executed. Returns `true` if the statement was cached.
+    pub fn invalidate(&self, query: &Query) -> bool {
+        let key = self.cache_key(query);
+        self.lock_cache().remove(&key)
+    }
 
-            Ok(prepared)
-        }
+    /// Removes all the statements from the cache.
+    pub fn invalidate_all(&self) {
+        self.lock_cache().clear();
+    }
+
+    /// Returns the number of cached statements, including the expired ones
+    /// which have not been looked up since they expired.
+    pub fn cache_len(&self) -> usize {
+        self.lock_cache().len()
+    }
+
+    /// Returns the counters of the prepared statement cache.
+    pub fn get_cache_metrics(&self) -> &PreparedCacheMetrics {
+        &self.metrics
     }
 
     pub fn get_max_capacity(&self) -> usize {
-        self.max_capacity
+        self.config.max_capacity
     }
 
     pub fn get_session(&self) -> &GenericSession<DeserApi> {
         &self.session
     }
+
+    fn cache_key(&self, query: &Query) -> PreparedCacheKey {
+        let keyspace = self.session.get_keyspace();
+        self.config
+            .key_policy
+            .key(query, keyspace.as_deref().map(String::as_str))
+    }
+
+    fn lock_cache(
+        &self,
+    ) -> MutexGuard<'_, LruCache<PreparedCacheKey, Arc<RawPreparedStatementData>, S>> {
+        // The cache is consistent after each operation, so it can be used after a panic.
+        self.cache.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+enum CacheLookup<V> {
+    Hit(V),
+    Expired,
+    Miss,
+}
+
+/// A map evicting the least recently used entry when it is full.
+#[derive(Debug)]
+struct LruCache<K, V, S> {
+    entries: HashMap<K, LruEntry<V>, S>,
+    /// Keys ordered from the least recently used one.
+    recency: BTreeMap<u64, K>,
+    next_tick: u64,
+}
+
+#[derive(Debug)]
+struct LruEntry<V> {
+    value: V,
+    inserted_at: Instant,
+    tick: u64,
+}
+
+impl<K, V, S> LruCache<K, V, S>
+where
+    K: Eq + Hash + Clone,
+    V: Clone,
+    S: BuildHasher,
+{
+    fn with_hasher(hasher: S) -> Self {
+        Self {
+            entries: HashMap::with_hasher(hasher),
+            recency: BTreeMap::new(),
+            next_tick: 0,
+        }
+    }
+
+    fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    fn tick(&mut self) -> u64 {
+        let tick = self.next_tick;
+        self.next_tick += 1;
+        tick
+    }
+
+    /// Returns the value and marks it as the most recently used one.
+    /// An entry older than `ttl` is removed instead.
+    fn get(&mut self, key: &K, ttl: Option<Duration>) -> CacheLookup<V> {
+        let tick = self.tick();
+        let Some(entry) = self.entries.get_mut(key) else {
+            return CacheLookup::Miss;
+        };
+        if ttl.is_some_and(|ttl| entry.inserted_at.elapsed() >= ttl) {
+            self.remove(key);
+            return CacheLookup::Expired;
+        }
+        let key = self.recency.remove(&entry.tick).unwrap();
+        self.recency.insert(tick, key);
+        entry.tick = tick;
+        CacheLookup::Hit(entry.value.clone())
+    }
+
+    /// Inserts the value, evicting the least recently used entries if the cache is full.
+    /// Returns the number of evicted entries.
+    fn insert(&mut self, key: K, value: V, capacity: usize) -> usize {
+        if capacity == 0 {
+            return 0;
+        }
+        self.remove(&key);
+        let mut evicted = 0;
+        while self.entries.len() >= capacity {
+            let Some((_, oldest)) = self.recency.pop_first() else {
+                break;
+            };
+            self.entries.remove(&oldest);
+            evicted += 1;
+        }
+        let tick = self.tick();
+        self.recency.insert(tick, key.clone());
+        self.entries.insert(
+            key,
+            LruEntry {
+                value,
+                inserted_at: Instant::now(),
+                tick,
+            },
+        );
+        evicted
+    }
+
+    fn remove(&mut self, key: &K) -> bool {
+        match self.entries.remove(key) {
+            Some(entry) => {
+                self.recency.remove(&entry.tick);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.recency.clear();
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use super::{CacheLookup, LruCache, PreparedCacheConfig};
     use crate::query::Query;
+    use crate::statement::keyspace_template::{KeyspaceName, KeyspaceTemplate};
     use crate::statement::PagingState;
     use crate::test_utils::{
         create_new_session_builder, scylla_supports_tablets, setup_tracing, PerformDDL,
@@ -342,7 +647,9 @@ mod tests {
     };
     use futures::TryStreamExt;
     use scylla_cql::frame::response::result::Row;
+    use std::collections::hash_map::RandomState;
     use std::collections::BTreeSet;
+    use std::time::Duration;
 
     async fn new_for_test(with_tablet_support: bool) -> Session {
         let session = create_new_session_builder()
@@ -390,15 +697,20 @@ mod tests {
             .unwrap();
 
         // Clear the cache because it now contains an insert
-        assert_eq!(session.cache.len(), 1);
+        assert_eq!(session.cache_len(), 1);
 
-        session.cache.clear();
+        session.invalidate_all();
 
         session
     }
 
+    fn is_cached(session: &CachingSession, contents: &str) -> bool {
+        let key = session.cache_key(&Query::new(contents));
+        session.lock_cache().entries.contains_key(&key)
+    }
+
     /// Test that when the cache is full and a different query comes in, that query will be added
-    /// to the cache and a random query is removed
+    /// to the cache and the least recently used query is removed
     #[tokio::test]
     async fn test_full() {
         setup_tracing();
@@ -421,16 +733,13 @@ mod tests {
             .await
             .unwrap();
 
-        assert_eq!(2, session.cache.len());
-
-        // This query should be in the cache
-        assert!(session.cache.get(last_query).is_some());
-
-        // Either the first or middle query should be removed
-        let first_query_removed = session.cache.get(first_query).is_none();
-        let middle_query_removed = session.cache.get(middle_query).is_none();
+        assert_eq!(2, session.cache_len());
 
-        assert!(first_query_removed || middle_query_removed);
+        // The first query was used least recently, so it should be removed
+        assert!(!is_cached(&session, first_query));
+        assert!(is_cached(&session, middle_query));
+        assert!(is_cached(&session, last_query));
+        assert_eq!(1, session.get_cache_metrics().get_evictions());
     }
 
     /// Checks that the same prepared statement is reused when executing the same query twice
@@ -444,7 +753,7 @@ mod tests {
             .unwrap();
         let result_rows = result.into_rows_result().unwrap();
 
-        assert_eq!(1, session.cache.len());
+        assert_eq!(1, session.cache_len());
         assert_eq!(1, result_rows.rows_num());
 
         let result = session
@@ -454,8 +763,127 @@ mod tests {
 
         let result_rows = result.into_rows_result().unwrap();
 
-        assert_eq!(1, session.cache.len());
+        assert_eq!(1, session.cache_len());
         assert_eq!(1, result_rows.rows_num());
+        assert_eq!(1, session.get_cache_metrics().get_hits());
+    }
+
+    /// Checks that invalidated and expired statements are prepared again
+    #[tokio::test]
+    async fn test_invalidation_and_ttl() {
+        setup_tracing();
+        let config = PreparedCacheConfig {
+            ttl: Some(Duration::from_millis(100)),
+            ..PreparedCacheConfig::new(10)
+        };
+        let session: CachingSession = CachingSession::with_config(new_for_test(true).await, config);
+        let query = Query::new("select * from test_table");
+
+        session.execute_unpaged(query.clone(), &[]).await.unwrap();
+        assert!(session.invalidate(&query));
+        assert!(!session.invalidate(&query));
+        session.execute_unpaged(query.clone(), &[]).await.unwrap();
+        assert_eq!(2, session.get_cache_metrics().get_misses());
+
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        session.execute_unpaged(query.clone(), &[]).await.unwrap();
+        assert_eq!(1, session.get_cache_metrics().get_expirations());
+        assert_eq!(3, session.get_cache_metrics().get_misses());
+        assert_eq!(0, session.get_cache_metrics().get_hits());
+    }
+
+    /// Checks that the current keyspace of the session is a part of the cache key
+    #[tokio::test]
+    async fn test_keyspace_in_cache_key() {
+        setup_tracing();
+        let session: CachingSession = CachingSession::from(new_for_test(true).await, 10);
+        let other_keyspace = new_for_test(true).await.get_keyspace().unwrap();
+
+        session
+            .execute_unpaged("select * from test_table", &[])
+            .await
+            .unwrap();
+        session
+            .get_session()
+            .use_keyspace(other_keyspace.as_str(), false)
+            .await
+            .unwrap();
+        session
+            .execute_unpaged("select * from test_table", &[])
+            .await
+            .unwrap();
+        assert_eq!(2, session.cache_len());
+    }
+
+    #[test]
+    fn test_lru_cache() {
+        setup_tracing();
+        let mut cache = LruCache::with_hasher(RandomState::new());
+        assert_eq!(0, cache.insert("a", 1, 2));
+        assert_eq!(0, cache.insert("b", 2, 2));
+        assert!(matches!(cache.get(&"a", None), CacheLookup::Hit(1)));
+
+        // "b" is the least recently used entry.
+        assert_eq!(1, cache.insert("c", 3, 2));
+        assert!(matches!(cache.get(&"b", None), CacheLookup::Miss));
+        assert!(matches!(cache.get(&"a", None), CacheLookup::Hit(1)));
+        assert!(matches!(cache.get(&"c", None), CacheLookup::Hit(3)));
+
+        // Replacing an entry doesn't evict anything.
+        assert_eq!(0, cache.insert("a", 4, 2));
+        assert!(matches!(cache.get(&"a", None), CacheLookup::Hit(4)));
+
+        assert!(matches!(
+            cache.get(&"a", Some(Duration::ZERO)),
+            CacheLookup::Expired
+        ));
+        assert!(matches!(cache.get(&"a", None), CacheLookup::Miss));
+        assert_eq!(1, cache.len());
+
+        assert!(cache.remove(&"c"));
+        assert_eq!(0, cache.len());
+        assert!(cache.recency.is_empty());
+
+        // A cache of zero capacity holds nothing.
+        assert_eq!(0, cache.insert("a", 1, 0));
+        assert_eq!(0, cache.len());
+    }
+
+    /// Checks that a template is prepared separately for each keyspace
+    #[tokio::test]
+    async fn test_execute_unpaged_in_keyspace_cached() {
+        setup_tracing();
+        let session: CachingSession = CachingSession::from(new_for_test(true).await, 4);
+        let other_session = new_for_test(true).await;
+        let keyspaces = [
+            session.get_session().get_keyspace().unwrap(),
+            other_session.get_keyspace().unwrap(),
+        ]
+        .map(|ks| KeyspaceName::new(ks.as_str(), false).unwrap());
+
+        let insert =
+            KeyspaceTemplate::new("insert into {keyspace}.test_table (a, b) values (?, ?)");
+        let select = KeyspaceTemplate::new("select b from {keyspace}.test_table where a = ?");
+        for (b, keyspace) in keyspaces.iter().enumerate() {
+            session
+                .execute_unpaged_in_keyspace(&insert, keyspace, (1, b as i32))
+                .await
+                .unwrap();
+        }
+        assert_eq!(2, session.cache_len());
+
+        for (b, keyspace) in keyspaces.iter().enumerate() {
+            let (read_b,) = session
+                .execute_unpaged_in_keyspace(&select, keyspace, (1,))
+                .await
+                .unwrap()
+                .into_rows_result()
+                .unwrap()
+                .single_row::<(i32,)>()
+                .unwrap();
+            assert_eq!(b as i32, read_b);
+        }
+        assert_eq!(4, session.cache_len());
     }
 
     /// Checks that caching works with execute_iter
@@ -464,7 +892,7 @@ mod tests {
         setup_tracing();
         let session = create_caching_session().await;
 
-        assert!(session.cache.is_empty());
+        assert_eq!(0, session.cache_len());
 
         let iter = session
             .execute_iter("select * from test_table", &[])
@@ -482,7 +910,7 @@ mod tests {
             .len();
 
         assert_eq!(1, rows);
-        assert_eq!(1, session.cache.len());
+        assert_eq!(1, session.cache_len());
     }
 
     /// Checks that caching works with execute_single_page
@@ -491,14 +919,14 @@ mod tests {
         setup_tracing();
         let session = create_caching_session().await;
 
-        assert!(session.cache.is_empty());
+        assert_eq!(0, session.cache_len());
 
         let (result, _paging_state) = session
             .execute_single_page("select * from test_table", &[], PagingState::start())
             .await
             .unwrap();
 
-        assert_eq!(1, session.cache.len());
+        assert_eq!(1, session.cache_len());
         assert_eq!(1, result.into_rows_result().unwrap().rows_num());
     }
 
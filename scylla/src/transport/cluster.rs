@@ -30,7 +30,8 @@ use super::locator::tablets::{RawTablet, Tablet, TabletsInfo};
 use super::node::{InternalKnownNode, NodeAddr};
 use super::NodeRef;
 
-use super::locator::ReplicaLocator;
+use super::load_balancing::RoutingInfo;
+use super::locator::{ReplicaLocator, ReplicaSet};
 use super::partitioner::calculate_token_for_partition_key;
 use super::topology::Strategy;
 
@@ -170,6 +171,7 @@ impl Cluster {
             control_connection_repair_sender,
             pool_config.connection_config.clone(),
             pool_config.keepalive_interval,
+            pool_config.reconnection_policy.clone(),
             server_events_sender,
             keyspaces_to_fetch,
             fetch_schema_metadata,
@@ -269,6 +271,12 @@ impl ClusterData {
         }
     }
 
+    pub(crate) async fn wait_until_all_pools_are_full(&self) {
+        for node in self.locator.unique_nodes_in_global_ring().iter() {
+            node.wait_until_pool_full().await;
+        }
+    }
+
     /// Creates new ClusterData using information about topology held in `metadata`.
     /// Uses provided `known_peers` hashmap to recycle nodes if possible.
     pub(crate) async fn new(
@@ -454,6 +462,26 @@ impl ClusterData {
         Ok(self.get_token_endpoints(keyspace, table, token))
     }
 
+    /// Access to replicas of the data targeted by a statement, as seen by a load balancing policy.
+    /// The replicas are computed using the replication strategy of the statement's keyspace
+    /// (or its tablets) and can be restricted to a single datacenter.
+    /// Returns `None` if the statement has no token or table, or its keyspace is unknown.
+    pub fn get_replicas<'a>(
+        &'a self,
+        routing_info: &RoutingInfo<'_>,
+        datacenter: Option<&'a str>,
+    ) -> Option<ReplicaSet<'a>> {
+        let token = routing_info.token?;
+        let table_spec = routing_info.table?;
+        let keyspace = self.keyspaces.get(table_spec.ks_name())?;
+        Some(self.replica_locator().replicas_for_token(
+            token,
+            &keyspace.strategy,
+            datacenter,
+            table_spec,
+        ))
+    }
+
     /// Access replica location info
     pub fn replica_locator(&self) -> &ReplicaLocator {
         &self.locator
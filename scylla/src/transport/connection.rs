@@ -1,6 +1,8 @@
 use bytes::Bytes;
 use futures::{future::RemoteHandle, FutureExt};
-use scylla_cql::frame::frame_errors::CqlResponseParseError;
+use scylla_cql::frame::frame_errors::{
+    CqlRequestSerializationError, CqlResponseParseError, FrameHeaderParseError,
+};
 use scylla_cql::frame::request::options::{self, Options};
 use scylla_cql::frame::request::CqlRequestKind;
 use scylla_cql::frame::response::result::{ResultMetadata, TableSpec};
@@ -8,23 +10,27 @@ use scylla_cql::frame::response::Error;
 use scylla_cql::frame::response::{self, error};
 use scylla_cql::frame::types::SerialConsistency;
 use scylla_cql::types::serialize::batch::{BatchValues, BatchValuesIterator};
+use scylla_cql::types::serialize::limits::{LimitCheckingRawBatchValues, ValueSizeLimits};
 use scylla_cql::types::serialize::raw_batch::RawBatchValuesAdapter;
 use scylla_cql::types::serialize::row::{RowSerializationContext, SerializedValues};
 use socket2::{SockRef, TcpKeepalive};
 use tokio::io::{split, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader, BufWriter};
 use tokio::net::{TcpSocket, TcpStream};
-use tokio::sync::{mpsc, oneshot};
+use tokio::sync::{mpsc, oneshot, Notify};
 use tokio::time::Instant;
-use tracing::{debug, error, trace, warn};
+use tracing::{debug, error, trace, warn, Instrument};
 use uuid::Uuid;
 
 use std::borrow::Cow;
+use std::num::NonZeroU64;
 #[cfg(feature = "ssl")]
 use std::pin::Pin;
-use std::sync::atomic::AtomicU64;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize};
 use std::time::Duration;
 #[cfg(feature = "ssl")]
 use tokio_openssl::SslStream;
+#[cfg(feature = "rustls")]
+use tokio_rustls::TlsConnector;
 
 #[cfg(feature = "ssl")]
 pub(crate) use ssl_config::SslConfig;
@@ -49,9 +55,14 @@ use std::{
 use super::errors::{ProtocolError, SchemaVersionFetchError, UseKeyspaceProtocolError};
 use super::iterator::QueryPager;
 use super::locator::tablets::{RawTablet, TabletParsingError};
+use super::metrics::Metrics;
+use super::node::resolve_hostname;
+use super::proxy::ProxyConfig;
 use super::query_result::QueryResult;
 use super::session::AddressTranslator;
+use super::sni_proxy::SniProxyConfig;
 use super::topology::{PeerEndpoint, UntranslatedEndpoint, UntranslatedPeer};
+use super::warnings::WarningHandler;
 use super::NodeAddr;
 #[cfg(feature = "cloud")]
 use crate::cloud::CloudConfig;
@@ -62,8 +73,9 @@ use crate::frame::{
     self,
     request::{self, batch, execute, query, register, SerializableRequest},
     response::{event::Event, result, NonErrorResponse, Response, ResponseOpcode},
+    segment::{self, SegmentedFrameReader},
     server_event_type::EventType,
-    FrameParams, SerializedRequest,
+    FrameParams, ProtocolVersion, ResponseFrameBody, SerializedRequest,
 };
 use crate::query::Query;
 use crate::routing::ShardInfo;
@@ -74,19 +86,68 @@ use crate::transport::Compression;
 // Queries for schema agreement
 const LOCAL_VERSION: &str = "SELECT schema_version FROM system.local WHERE key='local'";
 
-// FIXME: Make this constants configurable
 // The term "orphan" refers to stream ids, that were allocated for a {request, response} that no
 // one is waiting anymore (due to cancellation of `Connection::send_request`). Old orphan refers to
 // a stream id that is orphaned for a long time. This long time is defined below
-// (`OLD_AGE_ORPHAN_THRESHOLD`). Connection that has a big number (`OLD_ORPHAN_COUNT_THRESHOLD`)
-// of old orphans is shut down (and created again by a connection management layer).
-const OLD_ORPHAN_COUNT_THRESHOLD: usize = 1024;
+// (`OLD_AGE_ORPHAN_THRESHOLD`). Connection that has more old orphans than
+// `ConnectionConfig::max_orphaned_stream_ids` (by default `DEFAULT_MAX_ORPHANED_STREAM_IDS`)
+// is shut down (and created again by a connection management layer). Before that happens,
+// the connection pool replaces connections with half as many orphans gracefully.
+pub(crate) const DEFAULT_MAX_ORPHANED_STREAM_IDS: usize = 1024;
 const OLD_AGE_ORPHAN_THRESHOLD: std::time::Duration = std::time::Duration::from_secs(1);
 
+/// The number of stream ids available on a single connection in CQL protocol v4 and newer.
+/// Stream ids are signed 16-bit integers and negative ones are reserved for server events.
+pub(crate) const MAX_STREAM_IDS_PER_CONNECTION: u16 = i16::MAX as u16 + 1;
+
+// Defaults of the sizing of the pools of serialization buffers, see `BufferPool`.
+pub(crate) const DEFAULT_SERIALIZATION_BUFFER_POOL_SIZE: usize = 64;
+pub(crate) const DEFAULT_SERIALIZATION_BUFFER_MAX_CAPACITY: usize = 64 * 1024;
+
+/// The delay which the connection waits for more requests to be queued,
+/// before it flushes the requests which are already written to the socket.
+/// See [`SessionBuilder::write_coalescing_delay`](crate::transport::session_builder::GenericSessionBuilder::write_coalescing_delay).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum WriteCoalescingDelay {
+    /// The task writing to the socket yields to the runtime, which was empirically
+    /// measured to delay it by 1-300µs. The yield is repeated as long as new requests
+    /// are queued in the meantime.
+    #[default]
+    SmallNondeterministic,
+
+    /// The task writing to the socket waits the given number of milliseconds once,
+    /// and then flushes all the requests queued before and during the delay.
+    /// The delay is only as precise as the timers of the runtime.
+    Milliseconds(NonZeroU64),
+}
+
+/// Decides what happens with a request that is to be sent on a connection
+/// that has no free stream ids left.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StreamIdExhaustionPolicy {
+    /// The request fails immediately with [`QueryError::UnableToAllocStreamId`].
+    /// The retry policy then decides whether to try it on another node.
+    #[default]
+    Error,
+
+    /// The request waits on the connection until some in-flight request
+    /// completes and its stream id is freed. The request timeout (if any)
+    /// still applies to the time spent waiting.
+    Queue,
+
+    /// The connection pool prefers connections to the same node that still
+    /// have free stream ids when picking a connection for a request.
+    /// If all connections are saturated, the request fails as with [`Self::Error`].
+    Spill,
+}
+
 pub(crate) struct Connection {
     _worker_handle: RemoteHandle<()>,
 
     connect_address: SocketAddr,
+    connected_at: tokio::time::Instant,
     config: ConnectionConfig,
     features: ConnectionFeatures,
     router_handle: Arc<RouterHandle>,
@@ -104,6 +165,42 @@ struct RouterHandle {
     // pushing values in a synchronous way (without an `.await`), which is
     // needed for pushing values in `Drop` implementations.
     orphan_notification_sender: mpsc::UnboundedSender<RequestId>,
+
+    // Number of stream ids that are currently allocated on the connection.
+    // Updated by the router, read by the connection pool to detect saturation.
+    stream_ids_in_use: Arc<AtomicUsize>,
+
+    // Number of stream ids which are orphaned. Updated by the router,
+    // read by the connection pool to replace connections with too many orphans.
+    orphaned_stream_ids: Arc<AtomicUsize>,
+
+    // Number of requests submitted to the connection which don't have a stream id yet,
+    // i.e. wait in the queue of the writer or for a stream id to be freed.
+    queued_requests: Arc<AtomicUsize>,
+
+    // Buffers which requests are serialized into. The writer returns them
+    // to the pool after sending the requests.
+    buffer_pool: Arc<BufferPool>,
+
+    // Limits checked against each serialized request before sending it.
+    value_size_limits: ValueSizeLimits,
+
+    // Version of the protocol spoken on the connection, chosen when opening it.
+    protocol_version: ProtocolVersion,
+    segment_framing: Arc<SegmentFraming>,
+
+    // Request bodies smaller than this are sent uncompressed.
+    compression_min_size: usize,
+    metrics: Arc<Metrics>,
+}
+
+// Protocol v5 switches to sending frames in segments once the STARTUP handshake
+// is over. The state is shared by the reader, which notices the end of the handshake,
+// the writer and `open_connection`, which negotiates the compression of segments.
+#[derive(Default)]
+struct SegmentFraming {
+    enabled: AtomicBool,
+    compressed: AtomicBool,
 }
 
 impl RouterHandle {
@@ -117,14 +214,42 @@ impl RouterHandle {
         request: &impl SerializableRequest,
         compression: Option<Compression>,
         tracing: bool,
+        result_memory_limit: Option<usize>,
     ) -> Result<TaskResponse, RequestError> {
-        let serialized_request = SerializedRequest::make(request, compression, tracing)?;
+        // Protocol v5 compresses segments instead of frames.
+        let compression = compression.filter(|_| self.protocol_version < ProtocolVersion::V5);
+        let serialized_request = SerializedRequest::make_versioned_in(
+            request,
+            self.protocol_version,
+            compression,
+            self.compression_min_size,
+            tracing,
+            self.buffer_pool.take(),
+        )?;
+        if compression.is_some() {
+            if serialized_request.is_compressed() {
+                self.metrics.log_compressed_request(
+                    serialized_request.get_uncompressed_body_len(),
+                    serialized_request.get_body_len(),
+                );
+            } else {
+                self.metrics.inc_compression_skipped_requests();
+            }
+        }
+        if let Err(err) = self
+            .value_size_limits
+            .check_request_size(serialized_request.get_data().len())
+        {
+            self.buffer_pool.put_back(serialized_request.into_buffer());
+            return Err(CqlRequestSerializationError::from(err).into());
+        }
         let request_id = self.allocate_request_id();
 
         let (response_sender, receiver) = oneshot::channel();
         let response_handler = ResponseHandler {
             response_sender,
             request_id,
+            result_memory_limit,
         };
 
         // Dropping `notifier` (before calling `notifier.disable()`) will send a notification to
@@ -136,6 +261,7 @@ impl RouterHandle {
             .send(Task {
                 serialized_request,
                 response_handler,
+                queued: QueuedRequestGuard::new(self.queued_requests.clone()),
             })
             .await
             .map_err(|_| -> BrokenConnectionError {
@@ -166,6 +292,9 @@ type RequestId = u64;
 struct ResponseHandler {
     response_sender: oneshot::Sender<Result<TaskResponse, RequestError>>,
     request_id: RequestId,
+    // The body of a response larger than this is discarded by the reader
+    // without being buffered.
+    result_memory_limit: Option<usize>,
 }
 
 // Used to notify `Connection::orphaner` about `Connection::send_request`
@@ -204,6 +333,24 @@ impl Drop for OrphanhoodNotifier<'_> {
 struct Task {
     serialized_request: SerializedRequest,
     response_handler: ResponseHandler,
+    queued: QueuedRequestGuard,
+}
+
+// Counts a request as queued on the connection until it is dropped,
+// which happens once the request gets a stream id or is abandoned.
+struct QueuedRequestGuard(Arc<AtomicUsize>);
+
+impl QueuedRequestGuard {
+    fn new(counter: Arc<AtomicUsize>) -> Self {
+        counter.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Self(counter)
+    }
+}
+
+impl Drop for QueuedRequestGuard {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+    }
 }
 
 struct TaskResponse {
@@ -248,6 +395,23 @@ impl QueryResponse {
     pub(crate) fn into_query_result(self) -> Result<QueryResult, QueryError> {
         self.into_non_error_query_response()?.into_query_result()
     }
+
+    /// Fails if the rows of the response take more memory than the given limit.
+    ///
+    /// Rows are deserialized from the frame lazily, so its size bounds the memory
+    /// taken by rows deserialized into borrowed types.
+    pub(crate) fn check_memory_limit(&self, limit: Option<usize>) -> Result<(), UserRequestError> {
+        let (Some(limit), Response::Result(result::Result::Rows((rows, _)))) =
+            (limit, &self.response)
+        else {
+            return Ok(());
+        };
+        let size = rows.metadata_and_rows_bytes_size();
+        if size > limit {
+            return Err(UserRequestError::ResultMemoryLimitExceeded { size, limit });
+        }
+        Ok(())
+    }
 }
 
 impl NonErrorQueryResponse {
@@ -563,10 +727,15 @@ impl<'id: 'map, 'map> SelfIdentity<'id> {
 #[derive(Clone)]
 pub(crate) struct ConnectionConfig {
     pub(crate) compression: Option<Compression>,
+    // Tried in order if the node doesn't support `compression`.
+    pub(crate) compression_fallbacks: Vec<Compression>,
+    pub(crate) compression_min_size: usize,
     pub(crate) tcp_nodelay: bool,
     pub(crate) tcp_keepalive_interval: Option<Duration>,
     #[cfg(feature = "ssl")]
     pub(crate) ssl_config: Option<SslConfig>,
+    #[cfg(feature = "rustls")]
+    pub(crate) rustls_config: Option<Arc<rustls::ClientConfig>>,
     pub(crate) connect_timeout: std::time::Duration,
     // should be Some only in control connections,
     pub(crate) event_sender: Option<mpsc::Sender<Event>>,
@@ -575,31 +744,61 @@ pub(crate) struct ConnectionConfig {
     pub(crate) cloud_config: Option<Arc<CloudConfig>>,
     pub(crate) authenticator: Option<Arc<dyn AuthenticatorProvider>>,
     pub(crate) address_translator: Option<Arc<dyn AddressTranslator>>,
+    pub(crate) sni_proxy: Option<Arc<SniProxyConfig>>,
+    pub(crate) proxy: Option<Arc<ProxyConfig>>,
+    // Set per node if connections go through an SNI proxy. It is sent as SNI,
+    // and the certificate presented by the proxy is verified against it.
+    pub(crate) tls_server_name: Option<String>,
+    pub(crate) warning_handler: Option<Arc<dyn WarningHandler>>,
     pub(crate) enable_write_coalescing: bool,
+    pub(crate) write_coalescing_delay: WriteCoalescingDelay,
+    pub(crate) decoding_offload_threshold: Option<usize>,
 
     pub(crate) keepalive_interval: Option<Duration>,
     pub(crate) keepalive_timeout: Option<Duration>,
     pub(crate) tablet_sender: Option<mpsc::Sender<(TableSpec<'static>, RawTablet)>>,
 
     pub(crate) identity: SelfIdentity<'static>,
+
+    pub(crate) stream_id_limit: u16,
+    pub(crate) stream_id_exhaustion_policy: StreamIdExhaustionPolicy,
+    pub(crate) max_orphaned_stream_ids: usize,
+    pub(crate) metrics: Arc<Metrics>,
+
+    pub(crate) serialization_buffer_pool_size: usize,
+    pub(crate) serialization_buffer_max_capacity: usize,
+
+    pub(crate) value_size_limits: ValueSizeLimits,
+
+    pub(crate) protocol_version: ProtocolVersion,
 }
 
 impl Default for ConnectionConfig {
     fn default() -> Self {
         Self {
             compression: None,
+            compression_fallbacks: Vec::new(),
+            compression_min_size: 0,
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
             event_sender: None,
             #[cfg(feature = "ssl")]
             ssl_config: None,
+            #[cfg(feature = "rustls")]
+            rustls_config: None,
             connect_timeout: std::time::Duration::from_secs(5),
             default_consistency: Default::default(),
             authenticator: None,
             address_translator: None,
+            sni_proxy: None,
+            proxy: None,
+            tls_server_name: None,
+            warning_handler: None,
             #[cfg(feature = "cloud")]
             cloud_config: None,
             enable_write_coalescing: true,
+            write_coalescing_delay: Default::default(),
+            decoding_offload_threshold: None,
 
             // Note: this is different than SessionConfig default values.
             keepalive_interval: None,
@@ -608,22 +807,36 @@ impl Default for ConnectionConfig {
             tablet_sender: None,
 
             identity: SelfIdentity::default(),
+
+            stream_id_limit: MAX_STREAM_IDS_PER_CONNECTION,
+            stream_id_exhaustion_policy: StreamIdExhaustionPolicy::default(),
+            max_orphaned_stream_ids: DEFAULT_MAX_ORPHANED_STREAM_IDS,
+            metrics: Arc::new(Metrics::new()),
+
+            serialization_buffer_pool_size: DEFAULT_SERIALIZATION_BUFFER_POOL_SIZE,
+            serialization_buffer_max_capacity: DEFAULT_SERIALIZATION_BUFFER_MAX_CAPACITY,
+
+            value_size_limits: ValueSizeLimits::default(),
+
+            protocol_version: ProtocolVersion::default(),
         }
     }
 }
 
 impl ConnectionConfig {
-    #[cfg(feature = "ssl")]
-    fn is_ssl(&self) -> bool {
+    pub(crate) fn is_ssl(&self) -> bool {
         #[cfg(feature = "cloud")]
         if self.cloud_config.is_some() {
             return true;
         }
-        self.ssl_config.is_some()
-    }
-
-    #[cfg(not(feature = "ssl"))]
-    fn is_ssl(&self) -> bool {
+        #[cfg(feature = "ssl")]
+        if self.ssl_config.is_some() {
+            return true;
+        }
+        #[cfg(feature = "rustls")]
+        if self.rustls_config.is_some() {
+            return true;
+        }
         false
     }
 }
@@ -640,12 +853,20 @@ impl Connection {
         source_port: Option<u16>,
         config: ConnectionConfig,
     ) -> Result<(Self, ErrorReceiver), ConnectionError> {
-        let stream_connector = match source_port {
-            Some(p) => {
-                tokio::time::timeout(config.connect_timeout, connect_with_source_port(addr, p))
+        let stream_connector = match &config.proxy {
+            Some(proxy) => {
+                tokio::time::timeout(config.connect_timeout, connect_through_proxy(proxy, addr))
                     .await
             }
-            None => tokio::time::timeout(config.connect_timeout, TcpStream::connect(addr)).await,
+            None => match source_port {
+                Some(p) => {
+                    tokio::time::timeout(config.connect_timeout, connect_with_source_port(addr, p))
+                        .await
+                }
+                None => {
+                    tokio::time::timeout(config.connect_timeout, TcpStream::connect(addr)).await
+                }
+            },
         };
         let stream = match stream_connector {
             Ok(stream) => stream?,
@@ -669,6 +890,18 @@ impl Connection {
             submit_channel: sender,
             request_id_generator: AtomicU64::new(0),
             orphan_notification_sender,
+            stream_ids_in_use: Arc::new(AtomicUsize::new(0)),
+            orphaned_stream_ids: Arc::new(AtomicUsize::new(0)),
+            queued_requests: Arc::new(AtomicUsize::new(0)),
+            buffer_pool: Arc::new(BufferPool::new(
+                config.serialization_buffer_pool_size,
+                config.serialization_buffer_max_capacity,
+            )),
+            value_size_limits: config.value_size_limits,
+            protocol_version: config.protocol_version,
+            segment_framing: Default::default(),
+            compression_min_size: config.compression_min_size,
+            metrics: config.metrics.clone(),
         });
 
         let _worker_handle = Self::run_router(
@@ -687,6 +920,7 @@ impl Connection {
             config,
             features: Default::default(),
             connect_address: addr,
+            connected_at: tokio::time::Instant::now(),
             router_handle,
         };
 
@@ -754,7 +988,7 @@ impl Connection {
         };
 
         let req_result = self
-            .send_request(&request::Startup { options }, false, false, None)
+            .send_request(&request::Startup { options }, false, false, None, None)
             .await;
 
         // Extract the response to STARTUP request and tidy up the errors.
@@ -787,6 +1021,8 @@ impl Connection {
                         )))
                     }
                 },
+                // Setup requests have no result memory limit.
+                RequestError::ResultMemoryLimitExceeded { .. } => unreachable!(),
                 RequestError::BrokenConnection(e) => return Err(err(e.into())),
                 RequestError::UnableToAllocStreamId => {
                     return Err(err(ConnectionSetupRequestErrorKind::UnableToAllocStreamId))
@@ -805,7 +1041,7 @@ impl Connection {
         };
 
         let req_result = self
-            .send_request(&request::Options {}, false, false, None)
+            .send_request(&request::Options {}, false, false, None, None)
             .await;
 
         // Extract the supported options and tidy up the errors.
@@ -833,6 +1069,8 @@ impl Connection {
                         )))
                     }
                 },
+                // Setup requests have no result memory limit.
+                RequestError::ResultMemoryLimitExceeded { .. } => unreachable!(),
                 RequestError::BrokenConnection(e) => return Err(err(e.into())),
                 RequestError::UnableToAllocStreamId => {
                     return Err(err(ConnectionSetupRequestErrorKind::UnableToAllocStreamId))
@@ -847,14 +1085,17 @@ impl Connection {
         &self,
         query: &Query,
     ) -> Result<PreparedStatement, UserRequestError> {
+        let keyspace = self.statement_keyspace(query);
         let query_response = self
             .send_request(
                 &request::Prepare {
                     query: &query.contents,
+                    keyspace: keyspace.as_deref(),
                 },
                 true,
                 query.config.tracing,
                 None,
+                None,
             )
             .await?;
 
@@ -862,17 +1103,21 @@ impl Connection {
             Response::Error(error::Error { error, reason }) => {
                 return Err(UserRequestError::DbError(error, reason))
             }
-            Response::Result(result::Result::Prepared(p)) => PreparedStatement::new(
-                p.id,
-                self.features
-                    .protocol_features
-                    .prepared_flags_contain_lwt_mark(p.prepared_metadata.flags as u32),
-                p.prepared_metadata,
-                Arc::new(p.result_metadata),
-                query.contents.clone(),
-                query.get_validated_page_size(),
-                query.config.clone(),
-            ),
+            Response::Result(result::Result::Prepared(p)) => {
+                let prepared_statement = PreparedStatement::new(
+                    p.id,
+                    self.features
+                        .protocol_features
+                        .prepared_flags_contain_lwt_mark(p.prepared_metadata.flags as u32),
+                    p.prepared_metadata,
+                    Arc::new(p.result_metadata),
+                    query.contents.clone(),
+                    query.get_validated_page_size(),
+                    query.config.clone(),
+                );
+                prepared_statement.update_result_metadata_id(p.result_metadata_id);
+                prepared_statement
+            }
             _ => {
                 return Err(UserRequestError::UnexpectedResponse(
                     query_response.response.to_response_kind(),
@@ -902,10 +1147,40 @@ impl Connection {
                 reprepared_id: reprepared.get_id().clone().into(),
             })
         } else {
+            // The result metadata might have changed along with the schema.
+            previous_prepared.update_result_metadata(reprepared.get_result_metadata());
+            previous_prepared.update_result_metadata_id(reprepared.get_result_metadata_id());
             Ok(())
         }
     }
 
+    // In protocol v5 the server attaches the new result metadata and its id
+    // when the id sent in EXECUTE is outdated. They replace the cached ones,
+    // so that the following executions can skip the metadata again.
+    fn update_result_metadata_from_response(
+        prepared_statement: &PreparedStatement,
+        rows: &result::RawMetadataAndRawRows,
+    ) {
+        match rows.changed_metadata() {
+            Some(Ok((result_metadata_id, result_metadata))) => {
+                debug!(
+                    "Connection::execute: Result metadata changed - updating the cached one for statement with id {:?}",
+                    prepared_statement.get_id()
+                );
+                prepared_statement.update_result_metadata(Arc::new(result_metadata));
+                prepared_statement.update_result_metadata_id(Some(result_metadata_id));
+            }
+            Some(Err(err)) => {
+                warn!(
+                    "Failed to parse the changed result metadata of statement with id {:?}: {}",
+                    prepared_statement.get_id(),
+                    err
+                );
+            }
+            None => {}
+        }
+    }
+
     pub(crate) async fn authenticate_response(
         &self,
         response: Option<Vec<u8>>,
@@ -915,7 +1190,13 @@ impl Connection {
         };
 
         let req_result = self
-            .send_request(&request::AuthResponse { response }, false, false, None)
+            .send_request(
+                &request::AuthResponse { response },
+                false,
+                false,
+                None,
+                None,
+            )
             .await;
 
         // Extract non-error response to AUTH_RESPONSE request and tidy up errors.
@@ -953,6 +1234,8 @@ impl Connection {
                         )))
                     }
                 },
+                // Setup requests have no result memory limit.
+                RequestError::ResultMemoryLimitExceeded { .. } => unreachable!(),
                 RequestError::BrokenConnection(e) => return Err(err(e.into())),
                 RequestError::UnableToAllocStreamId => {
                     return Err(err(ConnectionSetupRequestErrorKind::UnableToAllocStreamId))
@@ -1057,12 +1340,21 @@ impl Connection {
                 paging_state,
                 skip_metadata: false,
                 timestamp: query.get_timestamp(),
+                keyspace: self.statement_keyspace(query),
+                now_in_seconds: query.config.now_in_seconds,
             },
         };
 
         let response = self
-            .send_request(&query_frame, true, query.config.tracing, None)
+            .send_request(
+                &query_frame,
+                true,
+                query.config.tracing,
+                None,
+                query.config.result_memory_limit,
+            )
             .await?;
+        response.check_memory_limit(query.config.result_memory_limit)?;
 
         Ok(response)
     }
@@ -1094,44 +1386,64 @@ impl Connection {
                 .config
                 .determine_consistency(self.config.default_consistency),
             prepared.config.serial_consistency.flatten(),
+            prepared.get_timestamp(),
             None,
             PagingState::start(),
         )
         .await
     }
 
+    #[allow(clippy::too_many_arguments)]
     pub(crate) async fn execute_raw_with_consistency(
         &self,
         prepared_statement: &PreparedStatement,
         values: &SerializedValues,
         consistency: Consistency,
         serial_consistency: Option<SerialConsistency>,
+        timestamp: Option<i64>,
         page_size: Option<PageSize>,
         paging_state: PagingState,
     ) -> Result<QueryResponse, UserRequestError> {
+        self.config
+            .value_size_limits
+            .check_values(
+                &RowSerializationContext::from_prepared(prepared_statement.get_prepared_metadata()),
+                values,
+            )
+            .map_err(CqlRequestSerializationError::from)?;
+
+        // Only protocol v5 lets the server tell that the cached result metadata
+        // is outdated, so skipping the metadata is not safe in older versions.
+        let use_cached_metadata = prepared_statement.get_use_cached_result_metadata()
+            && self.config.protocol_version >= ProtocolVersion::V5;
+
         let execute_frame = execute::Execute {
             id: prepared_statement.get_id().to_owned(),
+            result_metadata_id: prepared_statement.get_result_metadata_id(),
             parameters: query::QueryParameters {
                 consistency,
                 serial_consistency,
                 values: Cow::Borrowed(values),
                 page_size: page_size.map(Into::into),
-                timestamp: prepared_statement.get_timestamp(),
-                skip_metadata: prepared_statement.get_use_cached_result_metadata(),
+                timestamp,
+                skip_metadata: use_cached_metadata,
                 paging_state,
+                keyspace: None,
+                now_in_seconds: prepared_statement.config.now_in_seconds,
             },
         };
 
-        let cached_metadata = prepared_statement
-            .get_use_cached_result_metadata()
-            .then(|| prepared_statement.get_result_metadata());
+        // Evaluated before each request, as repreparation may update the cached metadata.
+        let cached_metadata =
+            || use_cached_metadata.then(|| prepared_statement.get_result_metadata());
 
         let query_response = self
             .send_request(
                 &execute_frame,
                 true,
                 prepared_statement.config.tracing,
-                cached_metadata,
+                cached_metadata().as_ref(),
+                prepared_statement.config.result_memory_limit,
             )
             .await?;
 
@@ -1144,36 +1456,50 @@ impl Connection {
             }
         }
 
-        match &query_response.response {
+        let needs_repreparation = match &query_response.response {
             Response::Error(frame::response::Error {
                 error: DbError::Unprepared { statement_id },
                 ..
             }) => {
                 debug!("Connection::execute: Got DbError::Unprepared - repreparing statement with id {:?}", statement_id);
-                // Repreparation of a statement is needed
-                self.reprepare(prepared_statement.get_statement(), prepared_statement)
-                    .await?;
-                let new_response = self
-                    .send_request(
-                        &execute_frame,
-                        true,
-                        prepared_statement.config.tracing,
-                        cached_metadata,
-                    )
-                    .await?;
+                true
+            }
+            Response::Result(result::Result::Rows((rows, _))) => {
+                Self::update_result_metadata_from_response(prepared_statement, rows);
+                false
+            }
+            _ => false,
+        };
 
-                if let Some(spec) = prepared_statement.get_table_spec() {
-                    if let Err(e) = self.update_tablets_from_response(spec, &new_response).await {
-                        tracing::warn!(
-                            "Error while parsing tablet info from custom payload: {}",
-                            e
-                        );
-                    }
-                }
+        if needs_repreparation {
+            // Repreparation of a statement is needed
+            self.reprepare(prepared_statement.get_statement(), prepared_statement)
+                .await?;
+            let new_response = self
+                .send_request(
+                    &execute_frame,
+                    true,
+                    prepared_statement.config.tracing,
+                    cached_metadata().as_ref(),
+                    prepared_statement.config.result_memory_limit,
+                )
+                .await?;
 
-                Ok(new_response)
+            if let Response::Result(result::Result::Rows((rows, _))) = &new_response.response {
+                Self::update_result_metadata_from_response(prepared_statement, rows);
             }
-            _ => Ok(query_response),
+
+            if let Some(spec) = prepared_statement.get_table_spec() {
+                if let Err(e) = self.update_tablets_from_response(spec, &new_response).await {
+                    tracing::warn!("Error while parsing tablet info from custom payload: {}", e);
+                }
+            }
+
+            new_response.check_memory_limit(prepared_statement.config.result_memory_limit)?;
+            Ok(new_response)
+        } else {
+            query_response.check_memory_limit(prepared_statement.config.result_memory_limit)?;
+            Ok(query_response)
         }
     }
 
@@ -1247,7 +1573,11 @@ impl Connection {
             }
         });
 
-        let values = RawBatchValuesAdapter::new(values, contexts);
+        let values = LimitCheckingRawBatchValues::new(
+            RawBatchValuesAdapter::new(values, contexts.clone()),
+            contexts,
+            self.config.value_size_limits,
+        );
 
         let batch_frame = batch::Batch {
             statements: Cow::Borrowed(&batch.statements),
@@ -1256,11 +1586,12 @@ impl Connection {
             consistency,
             serial_consistency,
             timestamp: batch.get_timestamp(),
+            now_in_seconds: batch.config.now_in_seconds,
         };
 
         loop {
             let query_response = self
-                .send_request(&batch_frame, true, batch.config.tracing, None)
+                .send_request(&batch_frame, true, batch.config.tracing, None, None)
                 .await
                 .map_err(UserRequestError::from)?;
 
@@ -1402,7 +1733,10 @@ impl Connection {
         };
 
         // Extract the response and tidy up the errors.
-        match self.send_request(&register_frame, true, false, None).await {
+        match self
+            .send_request(&register_frame, true, false, None, None)
+            .await
+        {
             Ok(r) => match r.response {
                 Response::Ready => Ok(()),
                 Response::Error(Error { error, reason }) => {
@@ -1422,6 +1756,8 @@ impl Connection {
                         e.to_response_kind(),
                     ))),
                 },
+                // Setup requests have no result memory limit.
+                RequestError::ResultMemoryLimitExceeded { .. } => unreachable!(),
                 RequestError::BrokenConnection(e) => Err(err(e.into())),
                 RequestError::UnableToAllocStreamId => {
                     Err(err(ConnectionSetupRequestErrorKind::UnableToAllocStreamId))
@@ -1448,12 +1784,26 @@ impl Connection {
         Ok(version_id)
     }
 
+    // Protocol v5 allows sending the keyspace of a statement along with it.
+    fn statement_keyspace<'q>(&self, query: &'q Query) -> Option<Cow<'q, str>> {
+        if self.config.protocol_version < ProtocolVersion::V5 {
+            return None;
+        }
+        let keyspace = query.get_keyspace()?;
+        Some(if keyspace.is_case_sensitive() {
+            Cow::Borrowed(keyspace.as_str())
+        } else {
+            Cow::Owned(keyspace.as_str().to_lowercase())
+        })
+    }
+
     async fn send_request(
         &self,
         request: &impl SerializableRequest,
         compress: bool,
         tracing: bool,
         cached_metadata: Option<&Arc<ResultMetadata<'static>>>,
+        result_memory_limit: Option<usize>,
     ) -> Result<QueryResponse, RequestError> {
         let compression = if compress {
             self.config.compression
@@ -1463,15 +1813,41 @@ impl Connection {
 
         let task_response = self
             .router_handle
-            .send_request(request, compression, tracing)
+            .send_request(request, compression, tracing, result_memory_limit)
             .await?;
 
-        let response = Self::parse_response(
-            task_response,
-            self.config.compression,
-            &self.features.protocol_features,
-            cached_metadata,
-        )?;
+        let response = match self.config.decoding_offload_threshold {
+            // Decompressing and parsing a large frame may take long enough
+            // to stall other tasks scheduled on the same worker thread.
+            Some(threshold) if task_response.body.len() >= threshold => {
+                let compression = self.config.compression;
+                let features = self.features.protocol_features;
+                let cached_metadata = cached_metadata.cloned();
+                tokio::task::spawn_blocking(move || {
+                    Self::parse_response(
+                        task_response,
+                        compression,
+                        &features,
+                        cached_metadata.as_ref(),
+                    )
+                })
+                .await
+                // Blocking tasks can't be cancelled, so the only possible error is a panic.
+                .unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()))?
+            }
+            _ => Self::parse_response(
+                task_response,
+                self.config.compression,
+                &self.features.protocol_features,
+                cached_metadata,
+            )?,
+        };
+
+        if let Some(warning_handler) = &self.config.warning_handler {
+            for warning in &response.warnings {
+                warning_handler.on_warning(warning, self.connect_address);
+            }
+        }
 
         Ok(response)
     }
@@ -1519,9 +1895,16 @@ impl Connection {
         router_handle: Arc<RouterHandle>,
         node_address: IpAddr,
     ) -> Result<RemoteHandle<()>, std::io::Error> {
+        // Background tasks of the connection log with the tags of the session.
+        let span = config.metrics.user_tags().span();
+
         #[cfg(feature = "ssl")]
         if let Some(ssl_config) = &config.ssl_config {
-            let ssl = ssl_config.new_ssl()?;
+            let mut ssl = ssl_config.new_ssl()?;
+            if let Some(server_name) = &config.tls_server_name {
+                ssl.set_hostname(server_name)?;
+                ssl.param_mut().set_host(server_name)?;
+            }
             let mut stream = SslStream::new(ssl, stream)?;
             let _pin = Pin::new(&mut stream).connect().await;
 
@@ -1534,6 +1917,37 @@ impl Connection {
                 router_handle,
                 node_address,
             )
+            .instrument(span)
+            .remote_handle();
+            tokio::task::spawn(task);
+            return Ok(handle);
+        }
+
+        #[cfg(feature = "rustls")]
+        if let Some(rustls_config) = &config.rustls_config {
+            // Unless connecting through an SNI proxy, the certificate of the node
+            // is verified against its IP address.
+            let server_name = match &config.tls_server_name {
+                Some(server_name) => {
+                    rustls::pki_types::ServerName::try_from(server_name.clone())
+                        .map_err(|err| std::io::Error::new(std::io::ErrorKind::InvalidInput, err))?
+                }
+                None => rustls::pki_types::ServerName::IpAddress(node_address.into()),
+            };
+            let stream = TlsConnector::from(rustls_config.clone())
+                .connect(server_name, stream)
+                .await?;
+
+            let (task, handle) = Self::router(
+                config,
+                stream,
+                receiver,
+                error_sender,
+                orphan_notification_receiver,
+                router_handle,
+                node_address,
+            )
+            .instrument(span)
             .remote_handle();
             tokio::task::spawn(task);
             return Ok(handle);
@@ -1548,6 +1962,7 @@ impl Connection {
             router_handle,
             node_address,
         )
+        .instrument(span)
         .remote_handle();
         tokio::task::spawn(task);
         Ok(handle)
@@ -1575,9 +1990,25 @@ impl Connection {
         // and writer futures are run on the same fiber, and both of them
         // are carefully written in such a way that they do not hold the lock
         // across .await points. Therefore, it should not be too expensive.
-        let handler_map = StdMutex::new(ResponseHandlerMap::new());
-
-        let enable_write_coalescing = config.enable_write_coalescing;
+        let handler_map = StdMutex::new(ResponseHandlerMap::new(
+            StreamIdSet::new(
+                config.stream_id_limit,
+                router_handle.stream_ids_in_use.clone(),
+            ),
+            router_handle.orphaned_stream_ids.clone(),
+        ));
+        // Used to wake up the writer waiting for a free stream id,
+        // see `StreamIdExhaustionPolicy::Queue`.
+        let stream_id_freed = Notify::new();
+
+        let write_coalescing_delay = config
+            .enable_write_coalescing
+            .then_some(config.write_coalescing_delay);
+        let stream_id_exhaustion_policy = config.stream_id_exhaustion_policy;
+        let max_orphaned_stream_ids = config.max_orphaned_stream_ids;
+        let metrics = config.metrics.clone();
+        let buffer_pool = router_handle.buffer_pool.clone();
+        let segment_framing = router_handle.segment_framing.clone();
 
         let k = Self::keepaliver(
             router_handle,
@@ -1589,15 +2020,27 @@ impl Connection {
         let r = Self::reader(
             BufReader::with_capacity(8192, read_half),
             &handler_map,
+            &stream_id_freed,
+            &segment_framing,
             config,
         );
         let w = Self::writer(
             BufWriter::with_capacity(8192, write_half),
             &handler_map,
+            &stream_id_freed,
             receiver,
-            enable_write_coalescing,
+            write_coalescing_delay,
+            stream_id_exhaustion_policy,
+            &metrics,
+            &buffer_pool,
+            &segment_framing,
+        );
+        let o = Self::orphaner(
+            &handler_map,
+            orphan_notification_receiver,
+            max_orphaned_stream_ids,
+            &metrics,
         );
-        let o = Self::orphaner(&handler_map, orphan_notification_receiver);
 
         let result = futures::try_join!(r, w, o, k);
 
@@ -1622,16 +2065,57 @@ impl Connection {
     async fn reader(
         mut read_half: (impl AsyncRead + Unpin),
         handler_map: &StdMutex<ResponseHandlerMap>,
+        stream_id_freed: &Notify,
+        segment_framing: &SegmentFraming,
         config: ConnectionConfig,
     ) -> Result<(), BrokenConnectionError> {
+        let mut segmented_reader: Option<SegmentedFrameReader> = None;
         loop {
-            let (params, opcode, body) = frame::read_response_frame(&mut read_half)
-                .await
-                .map_err(BrokenConnectionErrorKind::FrameHeaderParseError)?;
-            let response = TaskResponse {
-                params,
-                opcode,
-                body,
+            // We are guaranteed here that handler_map will not be locked
+            // by anybody else, so we can do try_lock().unwrap()
+            let max_body_len = |params: &FrameParams| {
+                handler_map
+                    .try_lock()
+                    .unwrap()
+                    .result_memory_limit(params.stream)
+            };
+            let frame = match &mut segmented_reader {
+                Some(segmented_reader) => {
+                    segmented_reader
+                        .read_response_frame_with_limit(&mut read_half, max_body_len)
+                        .await
+                }
+                None => frame::read_response_frame_with_limit(&mut read_half, max_body_len).await,
+            };
+            let (params, opcode, body) =
+                frame.map_err(BrokenConnectionErrorKind::FrameHeaderParseError)?;
+
+            if segmented_reader.is_none()
+                && config.protocol_version >= ProtocolVersion::V5
+                && matches!(opcode, ResponseOpcode::Ready | ResponseOpcode::Authenticate)
+            {
+                // The server sends everything after the response to STARTUP in segments.
+                // The flag is set before the response is passed on, so that the next
+                // request is written in segments, too.
+                segment_framing
+                    .enabled
+                    .store(true, std::sync::atomic::Ordering::Release);
+                segmented_reader = Some(SegmentedFrameReader::new(
+                    segment_framing
+                        .compressed
+                        .load(std::sync::atomic::Ordering::Acquire),
+                ));
+            }
+
+            let response = match body {
+                ResponseFrameBody::Received(body) => Ok(TaskResponse {
+                    params,
+                    opcode,
+                    body,
+                }),
+                ResponseFrameBody::Discarded { len, limit } => {
+                    Err(RequestError::ResultMemoryLimitExceeded { size: len, limit })
+                }
             };
 
             match params.stream.cmp(&-1) {
@@ -1642,7 +2126,10 @@ impl Connection {
                     continue;
                 }
                 Ordering::Equal => {
-                    if let Some(event_sender) = config.event_sender.as_ref() {
+                    // Events are never discarded, as there is no limit for them.
+                    if let (Some(event_sender), Ok(response)) =
+                        (config.event_sender.as_ref(), response)
+                    {
                         Self::handle_event(response, config.compression, event_sender)
                             .await
                             .map_err(BrokenConnectionErrorKind::CqlEventHandlingError)?
@@ -1658,6 +2145,7 @@ impl Connection {
                 let mut handler_map_guard = handler_map.try_lock().unwrap();
                 handler_map_guard.lookup(params.stream)
             };
+            stream_id_freed.notify_one();
 
             use HandlerLookupResult::*;
             match handler_lookup_res {
@@ -1665,7 +2153,7 @@ impl Connection {
                     // Don't care if sending of the response fails. This must
                     // mean that the receiver side was impatient and is not
                     // waiting for the result anymore.
-                    let _ = handler.response_sender.send(Ok(response));
+                    let _ = handler.response_sender.send(response);
                 }
                 Missing => {
                     // Unsolicited frame. This should not happen and indicates
@@ -1684,60 +2172,150 @@ impl Connection {
         }
     }
 
-    fn alloc_stream_id(
+    async fn alloc_stream_id(
+        write_half: &mut (impl AsyncWrite + Unpin),
         handler_map: &StdMutex<ResponseHandlerMap>,
-        response_handler: ResponseHandler,
-    ) -> Option<i16> {
-        // We are guaranteed here that handler_map will not be locked
-        // by anybody else, so we can do try_lock().unwrap()
-        let mut handler_map_guard = handler_map.try_lock().unwrap();
-        match handler_map_guard.allocate(response_handler) {
-            Ok(stream_id) => Some(stream_id),
-            Err(response_handler) => {
-                error!("Could not allocate stream id");
-                let _ = response_handler
-                    .response_sender
-                    .send(Err(RequestError::UnableToAllocStreamId));
-                None
+        stream_id_freed: &Notify,
+        mut response_handler: ResponseHandler,
+        stream_id_exhaustion_policy: StreamIdExhaustionPolicy,
+        metrics: &Metrics,
+    ) -> Result<Option<i16>, BrokenConnectionError> {
+        let mut exhaustion_reported = false;
+        loop {
+            // We are guaranteed here that handler_map will not be locked
+            // by anybody else, so we can do try_lock().unwrap()
+            let allocation_result = handler_map.try_lock().unwrap().allocate(response_handler);
+            match allocation_result {
+                Ok(stream_id) => return Ok(Some(stream_id)),
+                Err(handler) => {
+                    if !exhaustion_reported {
+                        metrics.inc_stream_id_exhaustions();
+                        exhaustion_reported = true;
+                    }
+                    if stream_id_exhaustion_policy != StreamIdExhaustionPolicy::Queue
+                        || handler.response_sender.is_closed()
+                    {
+                        error!("Could not allocate stream id");
+                        let _ = handler
+                            .response_sender
+                            .send(Err(RequestError::UnableToAllocStreamId));
+                        return Ok(None);
+                    }
+                    trace!("All stream ids are in use, waiting for one to be freed");
+                    response_handler = handler;
+                    // Requests which hold the stream ids may still sit in the write
+                    // buffer. Unless they reach the server, no response will ever
+                    // free a stream id.
+                    write_half
+                        .flush()
+                        .await
+                        .map_err(BrokenConnectionErrorKind::WriteError)?;
+                    // A stream id freed by the reader while flushing is not missed,
+                    // as `notify_one` stores a permit when nobody is waiting yet.
+                    stream_id_freed.notified().await;
+                }
             }
         }
     }
 
+    #[allow(clippy::too_many_arguments)]
     async fn writer(
         mut write_half: (impl AsyncWrite + Unpin),
         handler_map: &StdMutex<ResponseHandlerMap>,
+        stream_id_freed: &Notify,
         mut task_receiver: mpsc::Receiver<Task>,
-        enable_write_coalescing: bool,
+        write_coalescing_delay: Option<WriteCoalescingDelay>,
+        stream_id_exhaustion_policy: StreamIdExhaustionPolicy,
+        metrics: &Metrics,
+        buffer_pool: &BufferPool,
+        segment_framing: &SegmentFraming,
     ) -> Result<(), BrokenConnectionError> {
+        let mut segments = Vec::new();
         // When the Connection object is dropped, the sender half
         // of the channel will be dropped, this task will return an error
         // and the whole worker will be stopped
         while let Some(mut task) = task_receiver.recv().await {
             let mut num_requests = 0;
             let mut total_sent = 0;
-            while let Some(stream_id) = Self::alloc_stream_id(handler_map, task.response_handler) {
-                let mut req = task.serialized_request;
-                req.set_stream(stream_id);
-                let req_data: &[u8] = req.get_data();
-                total_sent += req_data.len();
-                num_requests += 1;
-                write_half
-                    .write_all(req_data)
-                    .await
-                    .map_err(BrokenConnectionErrorKind::WriteError)?;
+            let mut waited_for_more_requests = false;
+            loop {
+                if task.response_handler.response_sender.is_closed() {
+                    // Nobody waits for the response anymore, e.g. because of a timeout,
+                    // so sending the request would only make the database do needless work.
+                    // Requests which have already been sent can't be cancelled,
+                    // as the protocol has no means to do it.
+                    trace!(
+                        "Not sending request with request_id = {}, it has been cancelled",
+                        task.response_handler.request_id
+                    );
+                    metrics.inc_cancelled_requests();
+                    buffer_pool.put_back(task.serialized_request.into_buffer());
+                } else {
+                    let Some(stream_id) = Self::alloc_stream_id(
+                        &mut write_half,
+                        handler_map,
+                        stream_id_freed,
+                        task.response_handler,
+                        stream_id_exhaustion_policy,
+                        metrics,
+                    )
+                    .await?
+                    else {
+                        break;
+                    };
+                    drop(task.queued);
+                    let mut req = task.serialized_request;
+                    req.set_stream(stream_id);
+                    let req_data: &[u8] = req.get_data();
+                    total_sent += req_data.len();
+                    num_requests += 1;
+                    let data = if segment_framing
+                        .enabled
+                        .load(std::sync::atomic::Ordering::Acquire)
+                    {
+                        segments.clear();
+                        segment::write_segments(
+                            req_data,
+                            segment_framing
+                                .compressed
+                                .load(std::sync::atomic::Ordering::Acquire),
+                            &mut segments,
+                        );
+                        &segments[..]
+                    } else {
+                        req_data
+                    };
+                    write_half
+                        .write_all(data)
+                        .await
+                        .map_err(BrokenConnectionErrorKind::WriteError)?;
+                    buffer_pool.put_back(req.into_buffer());
+                }
                 task = match task_receiver.try_recv() {
                     Ok(t) => t,
-                    Err(_) if enable_write_coalescing => {
-                        // Yielding was empirically tested to inject a 1-300µs delay,
-                        // much better than tokio::time::sleep's 1ms granularity.
-                        // Also, yielding in a busy system let's the queue catch up with new items.
-                        tokio::task::yield_now().await;
+                    Err(_) => {
+                        match write_coalescing_delay {
+                            Some(WriteCoalescingDelay::SmallNondeterministic) => {
+                                // Yielding was empirically tested to inject a 1-300µs delay,
+                                // much better than tokio::time::sleep's 1ms granularity.
+                                // Also, yielding in a busy system let's the queue catch up with new items.
+                                tokio::task::yield_now().await;
+                            }
+                            // Waiting again after each new request could postpone
+                            // the flush indefinitely, so the delay is applied once.
+                            Some(WriteCoalescingDelay::Milliseconds(delay))
+                                if !waited_for_more_requests =>
+                            {
+                                tokio::time::sleep(Duration::from_millis(delay.get())).await;
+                                waited_for_more_requests = true;
+                            }
+                            _ => break,
+                        }
                         match task_receiver.try_recv() {
                             Ok(t) => t,
                             Err(_) => break,
                         }
                     }
-                    Err(_) => break,
                 }
             }
             trace!("Sending {} requests; {} bytes", num_requests, total_sent);
@@ -1752,11 +2330,13 @@ impl Connection {
 
     // This task receives notifications from `OrphanhoodNotifier`s and tries to
     // mark streams as orphaned. It also checks count of old orphans periodically.
-    // After an ald orphan threshold is reached, `orphaner` returns an error
+    // After an old orphan threshold is reached, `orphaner` returns an error
     // causing the connection to break.
     async fn orphaner(
         handler_map: &StdMutex<ResponseHandlerMap>,
         mut orphan_receiver: mpsc::UnboundedReceiver<RequestId>,
+        max_orphaned_stream_ids: usize,
+        metrics: &Metrics,
     ) -> Result<(), BrokenConnectionError> {
         let mut interval = tokio::time::interval(OLD_AGE_ORPHAN_THRESHOLD);
         loop {
@@ -1766,7 +2346,7 @@ impl Connection {
                     // by anybody else, so we can do try_lock().unwrap()
                     let handler_map_guard = handler_map.try_lock().unwrap();
                     let old_orphan_count = handler_map_guard.old_orphans_count();
-                    if old_orphan_count > OLD_ORPHAN_COUNT_THRESHOLD {
+                    if old_orphan_count > max_orphaned_stream_ids {
                         warn!(
                             "Too many old orphaned stream ids: {}",
                             old_orphan_count,
@@ -1780,7 +2360,9 @@ impl Connection {
                         request_id,
                     );
                     let mut handler_map_guard = handler_map.try_lock().unwrap(); // Same as above
-                    handler_map_guard.orphan(request_id);
+                    if handler_map_guard.orphan(request_id) {
+                        metrics.inc_orphaned_requests();
+                    }
                 }
                 else => { break }
             }
@@ -1799,7 +2381,7 @@ impl Connection {
             router_handle: &RouterHandle,
         ) -> Result<(), BrokenConnectionError> {
             router_handle
-                .send_request(&Options, None, false)
+                .send_request(&Options, None, false, None)
                 .await
                 .map(|_| ())
                 .map_err(|q_err| BrokenConnectionErrorKind::KeepaliveQueryError(q_err).into())
@@ -1901,6 +2483,17 @@ impl Connection {
         &self.features.shard_info
     }
 
+    // Scylla-specific extensions, e.g. `USING TIMEOUT`, are recognized by Scylla
+    // nodes only. Only Scylla nodes provide sharding information or advertise
+    // Scylla's protocol extensions.
+    pub(crate) fn is_to_scylla(&self) -> bool {
+        let protocol_features = &self.features.protocol_features;
+        self.features.shard_info.is_some()
+            || protocol_features.rate_limit_error.is_some()
+            || protocol_features.lwt_optimization_meta_bit_mask.is_some()
+            || protocol_features.tablets_v1_supported
+    }
+
     pub(crate) fn get_shard_aware_port(&self) -> Option<u16> {
         self.features.shard_aware_port
     }
@@ -1909,6 +2502,62 @@ impl Connection {
         self.features = features;
     }
 
+    /// Returns true if all stream ids of this connection are currently in use,
+    /// i.e. a new request would not be sent immediately.
+    pub(crate) fn is_stream_id_saturated(&self) -> bool {
+        let in_use = self
+            .router_handle
+            .stream_ids_in_use
+            .load(std::sync::atomic::Ordering::Relaxed);
+        in_use >= effective_stream_id_limit(self.config.stream_id_limit)
+    }
+
+    /// Returns the number of requests sent on this connection and not answered yet.
+    pub(crate) fn get_in_flight_requests_count(&self) -> usize {
+        self.router_handle
+            .stream_ids_in_use
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Returns the number of requests waiting to be sent on this connection,
+    /// e.g. because all its stream ids are in use.
+    pub(crate) fn get_queued_requests_count(&self) -> usize {
+        self.router_handle
+            .queued_requests
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Returns true if so many stream ids of this connection are orphaned, i.e. their
+    /// requests were abandoned before the responses arrived, that the connection
+    /// should be replaced before it is closed for having too many orphans.
+    pub(crate) fn has_many_orphaned_stream_ids(&self) -> bool {
+        let orphaned = self
+            .router_handle
+            .orphaned_stream_ids
+            .load(std::sync::atomic::Ordering::Relaxed);
+        orphaned > 0 && orphaned >= self.config.max_orphaned_stream_ids / 2
+    }
+
+    /// Returns the number of orphaned stream ids of this connection.
+    pub(crate) fn get_orphaned_stream_ids_count(&self) -> usize {
+        self.router_handle
+            .orphaned_stream_ids
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
+    /// Returns the time elapsed since the connection was established.
+    pub(crate) fn get_age(&self) -> Duration {
+        self.connected_at.elapsed()
+    }
+
+    /// Returns the number of requests sent on this connection so far, including
+    /// the requests sent by the driver itself, e.g. keepalives.
+    pub(crate) fn get_sent_requests_count(&self) -> u64 {
+        self.router_handle
+            .request_id_generator
+            .load(std::sync::atomic::Ordering::Relaxed)
+    }
+
     pub(crate) fn get_connect_address(&self) -> SocketAddr {
         self.connect_address
     }
@@ -1984,10 +2633,60 @@ async fn maybe_translated_addr(
 /// - registers for all event types using REGISTER request (if this is control connection).
 ///
 /// At the beginning, translates node's address, if it is subject to address translation.
+///
+/// If the node doesn't support the protocol version from the config,
+/// the connection is opened again with protocol v4.
 pub(crate) async fn open_connection(
     endpoint: UntranslatedEndpoint,
     source_port: Option<u16>,
     config: &ConnectionConfig,
+) -> Result<(Connection, ErrorReceiver), ConnectionError> {
+    match open_connection_with_version(endpoint.clone(), source_port, config).await {
+        Err(err)
+            if config.protocol_version > ProtocolVersion::V4
+                && is_protocol_version_rejection(&err) =>
+        {
+            debug!(
+                "Protocol {} was rejected by the node ({}), falling back to {}",
+                config.protocol_version,
+                err,
+                ProtocolVersion::V4
+            );
+            let config = ConnectionConfig {
+                protocol_version: ProtocolVersion::V4,
+                ..config.clone()
+            };
+            open_connection_with_version(endpoint, source_port, &config).await
+        }
+        result => result,
+    }
+}
+
+// A node which doesn't support the requested protocol version responds to the first
+// request with a protocol error, or with a frame of a version the driver can't read.
+fn is_protocol_version_rejection(err: &ConnectionError) -> bool {
+    let ConnectionError::ConnectionSetupRequestError(err) = err else {
+        return false;
+    };
+    if !matches!(err.request_kind, CqlRequestKind::Options) {
+        return false;
+    }
+    match &err.error {
+        ConnectionSetupRequestErrorKind::DbError(DbError::ProtocolError, _) => true,
+        ConnectionSetupRequestErrorKind::BrokenConnection(err) => matches!(
+            err.downcast_ref::<BrokenConnectionErrorKind>(),
+            Some(BrokenConnectionErrorKind::FrameHeaderParseError(
+                FrameHeaderParseError::VersionNotSupported(_)
+            ))
+        ),
+        _ => false,
+    }
+}
+
+async fn open_connection_with_version(
+    endpoint: UntranslatedEndpoint,
+    source_port: Option<u16>,
+    config: &ConnectionConfig,
 ) -> Result<(Connection, ErrorReceiver), ConnectionError> {
     /* Translate the address, if applicable. */
     let addr = maybe_translated_addr(endpoint, config.address_translator.as_deref()).await?;
@@ -2020,7 +2719,8 @@ pub(crate) async fn open_connection(
         .and_then(|p| p.parse::<u16>().ok());
 
     // Parse nonstandard protocol extensions.
-    let protocol_features = ProtocolFeatures::parse_from_supported(&supported.options);
+    let mut protocol_features = ProtocolFeatures::parse_from_supported(&supported.options);
+    protocol_features.protocol_version = config.protocol_version;
 
     // At the beginning, Connection assumes no sharding and no protocol extensions;
     // now that we know them, let's turn them on in the driver.
@@ -2045,22 +2745,31 @@ pub(crate) async fn open_connection(
     config.identity.add_startup_options(&mut options);
 
     // Optional compression.
-    if let Some(compression) = &config.compression {
-        let compression_str = compression.as_str();
-        if supported_compression.iter().any(|c| c == compression_str) {
+    if config.compression.is_some() {
+        connection.config.compression = negotiate_compression(
+            config
+                .compression
+                .iter()
+                .chain(&config.compression_fallbacks),
+            &supported_compression,
+            config.protocol_version,
+        );
+        if let Some(compression) = connection.config.compression {
             // Compression is reported to be supported by the server,
             // request it from the server
             options.insert(
                 Cow::Borrowed(options::COMPRESSION),
-                Cow::Borrowed(compression_str),
+                Cow::Borrowed(compression.as_str()),
+            );
+            connection.router_handle.segment_framing.compressed.store(
+                config.protocol_version >= ProtocolVersion::V5,
+                std::sync::atomic::Ordering::Release,
             );
         } else {
             // Fall back to no compression
             tracing::warn!(
-                "Requested compression <{}> is not supported by the cluster. Falling back to no compression",
-                compression_str
+                "None of the requested compression algorithms is supported by the cluster. Falling back to no compression",
             );
-            connection.config.compression = None;
         }
     }
 
@@ -2086,6 +2795,25 @@ pub(crate) async fn open_connection(
     Ok((connection, error_receiver))
 }
 
+// Picks the first of the requested compression algorithms which is supported by the node.
+// Segments of protocol v5 can only be compressed with lz4.
+fn negotiate_compression<'a>(
+    requested: impl IntoIterator<Item = &'a Compression>,
+    supported: &[String],
+    protocol_version: ProtocolVersion,
+) -> Option<Compression> {
+    requested.into_iter().copied().find(|compression| {
+        if protocol_version >= ProtocolVersion::V5 && *compression != Compression::Lz4 {
+            debug!(
+                "Compression <{}> is not supported by protocol {}",
+                compression, protocol_version
+            );
+            return false;
+        }
+        supported.iter().any(|c| c == compression.as_str())
+    })
+}
+
 async fn perform_authenticate(
     connection: &mut Connection,
     authenticate: &Authenticate,
@@ -2135,6 +2863,17 @@ async fn perform_authenticate(
     Ok(())
 }
 
+// Connects to the proxy and asks it to connect to `addr`.
+async fn connect_through_proxy(
+    proxy: &ProxyConfig,
+    addr: SocketAddr,
+) -> Result<TcpStream, std::io::Error> {
+    let proxy_addr = resolve_hostname(proxy.address()).await?;
+    let mut stream = TcpStream::connect(proxy_addr).await?;
+    proxy.establish_tunnel(&mut stream, addr).await?;
+    Ok(stream)
+}
+
 async fn connect_with_source_port(
     addr: SocketAddr,
     source_port: u16,
@@ -2162,25 +2901,33 @@ async fn connect_with_source_port(
 struct OrphanageTracker {
     orphans: HashMap<i16, Instant>,
     by_orphaning_times: BTreeSet<(Instant, i16)>,
+    // Shared with the connection, mirrors the number of orphans.
+    count: Arc<AtomicUsize>,
 }
 
 impl OrphanageTracker {
-    fn new() -> Self {
+    fn new(count: Arc<AtomicUsize>) -> Self {
         Self {
             orphans: HashMap::new(),
             by_orphaning_times: BTreeSet::new(),
+            count,
         }
     }
 
     fn insert(&mut self, stream_id: i16) {
         let now = Instant::now();
-        self.orphans.insert(stream_id, now);
+        if self.orphans.insert(stream_id, now).is_none() {
+            self.count
+                .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        }
         self.by_orphaning_times.insert((now, stream_id));
     }
 
     fn remove(&mut self, stream_id: i16) {
         if let Some(time) = self.orphans.remove(&stream_id) {
             self.by_orphaning_times.remove(&(time, stream_id));
+            self.count
+                .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
         }
     }
 
@@ -2214,12 +2961,12 @@ enum HandlerLookupResult {
 }
 
 impl ResponseHandlerMap {
-    fn new() -> Self {
+    fn new(stream_set: StreamIdSet, orphan_count: Arc<AtomicUsize>) -> Self {
         Self {
-            stream_set: StreamIdSet::new(),
+            stream_set,
             handlers: HashMap::new(),
             request_to_stream: HashMap::new(),
-            orphanage_tracker: OrphanageTracker::new(),
+            orphanage_tracker: OrphanageTracker::new(orphan_count),
         }
     }
 
@@ -2236,9 +2983,18 @@ impl ResponseHandlerMap {
         }
     }
 
+    // Returns the limit on the size of the response to the request which holds
+    // the stream_id, if any.
+    fn result_memory_limit(&self, stream_id: i16) -> Option<usize> {
+        self.handlers
+            .get(&stream_id)
+            .and_then(|handler| handler.result_memory_limit)
+    }
+
     // Orphan stream_id (associated with this request_id) by moving it to
-    // `orphanage_tracker`, and freeing its handler
-    fn orphan(&mut self, request_id: RequestId) {
+    // `orphanage_tracker`, and freeing its handler. Returns false if the request
+    // has no stream_id, i.e. it has not been sent or its response has already arrived.
+    fn orphan(&mut self, request_id: RequestId) -> bool {
         if let Some(stream_id) = self.request_to_stream.get(&request_id) {
             debug!(
                 "Orphaning stream_id = {} associated with request_id = {}",
@@ -2247,6 +3003,9 @@ impl ResponseHandlerMap {
             self.orphanage_tracker.insert(*stream_id);
             self.handlers.remove(stream_id);
             self.request_to_stream.remove(&request_id);
+            true
+        } else {
+            false
         }
     }
 
@@ -2284,15 +3043,30 @@ impl ResponseHandlerMap {
     }
 }
 
+fn effective_stream_id_limit(configured_limit: u16) -> usize {
+    configured_limit.clamp(1, MAX_STREAM_IDS_PER_CONNECTION) as usize
+}
+
 struct StreamIdSet {
     used_bitmap: Box<[u64]>,
+    limit: usize,
+    in_use: Arc<AtomicUsize>,
 }
 
 impl StreamIdSet {
-    fn new() -> Self {
-        const BITMAP_SIZE: usize = (i16::MAX as usize + 1) / 64;
+    // Creates a set of `limit` stream ids (0..limit). The limit is clamped
+    // to the number of stream ids allowed by the protocol.
+    fn new(limit: u16, in_use: Arc<AtomicUsize>) -> Self {
+        let limit = effective_stream_id_limit(limit);
+        let mut used_bitmap = vec![0u64; (limit + 63) / 64].into_boxed_slice();
+        // Ids past the limit in the last block are marked as permanently used.
+        if limit % 64 != 0 {
+            *used_bitmap.last_mut().unwrap() = !0u64 << (limit % 64);
+        }
         Self {
-            used_bitmap: vec![0; BITMAP_SIZE].into_boxed_slice(),
+            used_bitmap,
+            limit,
+            in_use,
         }
     }
 
@@ -2302,6 +3076,8 @@ impl StreamIdSet {
                 let off = block.trailing_ones();
                 *block |= 1u64 << off;
                 let stream_id = off as i16 + block_id as i16 * 64;
+                self.in_use
+                    .fetch_add(1, std::sync::atomic::Ordering::Relaxed);
                 return Some(stream_id);
             }
         }
@@ -2309,9 +3085,52 @@ impl StreamIdSet {
     }
 
     fn free(&mut self, stream_id: i16) {
-        let block_id = stream_id as usize / 64;
+        if stream_id < 0 || stream_id as usize >= self.limit {
+            return;
+        }
+        let block = &mut self.used_bitmap[stream_id as usize / 64];
         let off = stream_id as usize % 64;
-        self.used_bitmap[block_id] &= !(1 << off);
+        if *block & (1 << off) != 0 {
+            *block &= !(1 << off);
+            self.in_use
+                .fetch_sub(1, std::sync::atomic::Ordering::Relaxed);
+        }
+    }
+}
+
+// A pool of buffers for serializing requests, so that sending a request
+// doesn't need to allocate a new buffer. At most `max_buffers` buffers are kept,
+// and buffers which grew beyond `max_buffer_capacity` are freed instead of
+// being returned to the pool, so that a few huge requests don't pin memory.
+struct BufferPool {
+    buffers: StdMutex<Vec<Vec<u8>>>,
+    max_buffers: usize,
+    max_buffer_capacity: usize,
+}
+
+impl BufferPool {
+    fn new(max_buffers: usize, max_buffer_capacity: usize) -> Self {
+        Self {
+            buffers: StdMutex::new(Vec::new()),
+            max_buffers,
+            max_buffer_capacity,
+        }
+    }
+
+    // Returns a buffer from the pool, or a new one if the pool is empty.
+    fn take(&self) -> Vec<u8> {
+        self.buffers.lock().unwrap().pop().unwrap_or_default()
+    }
+
+    fn put_back(&self, mut buffer: Vec<u8>) {
+        if buffer.capacity() > self.max_buffer_capacity {
+            return;
+        }
+        buffer.clear();
+        let mut buffers = self.buffers.lock().unwrap();
+        if buffers.len() < self.max_buffers {
+            buffers.push(buffer);
+        }
     }
 }
 
@@ -2379,22 +3198,27 @@ impl VerifiedKeyspaceName {
 #[cfg(test)]
 mod tests {
     use assert_matches::assert_matches;
+    use bytes::Bytes;
     use scylla_cql::frame::protocol_features::{
         LWT_OPTIMIZATION_META_BIT_MASK_KEY, SCYLLA_LWT_ADD_METADATA_MARK_EXTENSION,
     };
     use scylla_cql::frame::types;
     use scylla_proxy::{
         Condition, Node, Proxy, Reaction, RequestFrame, RequestOpcode, RequestReaction,
-        RequestRule, ResponseFrame, ShardAwareness,
+        RequestRule, ResponseFrame, ResponseOpcode, RunningProxy, ShardAwareness,
     };
 
     use tokio::select;
     use tokio::sync::mpsc;
 
-    use super::ConnectionConfig;
+    use super::{ConnectionConfig, QueryResponse, StreamIdExhaustionPolicy, WriteCoalescingDelay};
+    use crate::frame::response::result::{self, RawMetadataAndRawRows};
+    use crate::frame::response::Response;
     use crate::query::Query;
+    use crate::statement::PagingStateResponse;
     use crate::test_utils::setup_tracing;
     use crate::transport::connection::open_connection;
+    use crate::transport::errors::UserRequestError;
     use crate::transport::node::ResolvedContactPoint;
     use crate::transport::topology::UntranslatedEndpoint;
     use crate::utils::test_utils::{unique_keyspace_name, PerformDDL};
@@ -2402,9 +3226,38 @@ mod tests {
     use futures::{StreamExt, TryStreamExt};
     use std::collections::HashMap;
     use std::net::SocketAddr;
+    use std::num::NonZeroU64;
     use std::sync::Arc;
     use std::time::Duration;
 
+    #[test]
+    fn result_memory_limit() {
+        setup_tracing();
+        let response = |result| QueryResponse {
+            response: Response::Result(result),
+            tracing_id: None,
+            warnings: Vec::new(),
+            custom_payload: None,
+        };
+
+        // An empty rows result takes 4 bytes for the rows count.
+        let rows = response(result::Result::Rows((
+            RawMetadataAndRawRows::mock_empty(),
+            PagingStateResponse::NoMorePages,
+        )));
+        rows.check_memory_limit(None).unwrap();
+        rows.check_memory_limit(Some(4)).unwrap();
+        assert_matches!(
+            rows.check_memory_limit(Some(3)),
+            Err(UserRequestError::ResultMemoryLimitExceeded { size: 4, limit: 3 })
+        );
+
+        // Results other than rows are not limited.
+        response(result::Result::Void)
+            .check_memory_limit(Some(0))
+            .unwrap();
+    }
+
     // Just like resolve_hostname in session.rs
     async fn resolve_hostname(hostname: &str) -> SocketAddr {
         match tokio::net::lookup_host(hostname).await {
@@ -2553,7 +3406,7 @@ mod tests {
                 .unwrap();
         }
 
-        let subtest = |enable_coalescing: bool, ks: String| async move {
+        let subtest = |enable_coalescing: bool, delay: WriteCoalescingDelay, ks: String| async move {
             let (connection, _) = super::open_connection(
                 UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
                     address: addr,
@@ -2562,6 +3415,7 @@ mod tests {
                 None,
                 &ConnectionConfig {
                     enable_write_coalescing: enable_coalescing,
+                    write_coalescing_delay: delay,
                     ..ConnectionConfig::default()
                 },
             )
@@ -2629,8 +3483,19 @@ mod tests {
             assert_eq!(results, expected);
         };
 
-        subtest(true, ks.clone()).await;
-        subtest(false, ks.clone()).await;
+        subtest(
+            true,
+            WriteCoalescingDelay::SmallNondeterministic,
+            ks.clone(),
+        )
+        .await;
+        subtest(
+            true,
+            WriteCoalescingDelay::Milliseconds(NonZeroU64::new(1).unwrap()),
+            ks.clone(),
+        )
+        .await;
+        subtest(false, Default::default(), ks.clone()).await;
     }
 
     // Returns the sum of integral numbers in the range [0..n)
@@ -2800,4 +3665,315 @@ mod tests {
 
         let _ = proxy.finish().await;
     }
+
+    // Forges a RESULT response of kind Void to the given request.
+    fn forged_void_result(frame: RequestFrame) -> ResponseFrame {
+        ResponseFrame {
+            params: frame.params.for_response(),
+            opcode: ResponseOpcode::Result,
+            body: Bytes::from_static(&[0, 0, 0, 1]),
+        }
+    }
+
+    // Runs a proxy which performs the handshake by itself, without any node behind it.
+    // Requests after the handshake are handled by `rules`.
+    async fn run_forging_proxy(proxy_addr: SocketAddr, rules: Vec<RequestRule>) -> RunningProxy {
+        let handshake_rules = [
+            RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Options),
+                RequestReaction::forge_response(Arc::new(|frame: RequestFrame| {
+                    ResponseFrame::forged_supported(frame.params, &HashMap::new()).unwrap()
+                })),
+            ),
+            RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Startup),
+                RequestReaction::forge_response(Arc::new(|frame: RequestFrame| {
+                    ResponseFrame::forged_ready(frame.params)
+                })),
+            ),
+        ];
+
+        Proxy::builder()
+            .with_node(
+                Node::builder()
+                    .proxy_address(proxy_addr)
+                    .request_rules(handshake_rules.into_iter().chain(rules).collect())
+                    .build_dry_mode(),
+            )
+            .build()
+            .run()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(20000)]
+    async fn stream_id_exhaustion_queue_flushes_pending_requests() {
+        setup_tracing();
+
+        let proxy_addr = SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042);
+        let proxy = run_forging_proxy(
+            proxy_addr,
+            vec![RequestRule(
+                Condition::RequestOpcode(RequestOpcode::Query),
+                RequestReaction::forge_response_with_delay(
+                    Duration::from_millis(50),
+                    Arc::new(forged_void_result),
+                ),
+            )],
+        )
+        .await;
+
+        let config = ConnectionConfig {
+            stream_id_limit: 4,
+            stream_id_exhaustion_policy: StreamIdExhaustionPolicy::Queue,
+            ..Default::default()
+        };
+        let metrics = config.metrics.clone();
+        let (conn, _error_receiver) = open_connection(
+            UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+                address: proxy_addr,
+                datacenter: None,
+            }),
+            None,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        // A burst above the limit: the last request waits for a stream id, which
+        // is freed only once the requests ahead of it have reached the server.
+        let results = futures::future::join_all(
+            (0..5).map(|i| conn.query_unpaged(format!("INSERT INTO t (a) VALUES ({})", i))),
+        )
+        .await;
+        for result in results {
+            result.unwrap();
+        }
+        assert_eq!(metrics.get_stream_id_exhaustions_num(), 1);
+
+        let _ = proxy.finish().await;
+    }
+
+    #[tokio::test]
+    #[ntest::timeout(20000)]
+    async fn dropped_requests_are_cancelled_or_orphaned() {
+        setup_tracing();
+
+        let proxy_addr = SocketAddr::new(scylla_proxy::get_exclusive_local_address(), 9042);
+        let (skipped_tx, mut skipped_rx) = mpsc::unbounded_channel();
+        let proxy = run_forging_proxy(
+            proxy_addr,
+            vec![
+                RequestRule(
+                    Condition::RequestOpcode(RequestOpcode::Query)
+                        .and(Condition::BodyContainsCaseSensitive(Box::new(*b"slow"))),
+                    RequestReaction::forge_response_with_delay(
+                        Duration::from_millis(500),
+                        Arc::new(forged_void_result),
+                    ),
+                ),
+                RequestRule(
+                    Condition::RequestOpcode(RequestOpcode::Query)
+                        .and(Condition::BodyContainsCaseSensitive(Box::new(*b"skipped"))),
+                    RequestReaction::forge_response(Arc::new(forged_void_result))
+                        .with_feedback_when_performed(skipped_tx),
+                ),
+                RequestRule(
+                    Condition::RequestOpcode(RequestOpcode::Query),
+                    RequestReaction::forge_response(Arc::new(forged_void_result)),
+                ),
+            ],
+        )
+        .await;
+
+        // With a single stream id, requests wait in the queue of the writer
+        // until the slow request gets its response.
+        let config = ConnectionConfig {
+            stream_id_limit: 1,
+            stream_id_exhaustion_policy: StreamIdExhaustionPolicy::Queue,
+            ..Default::default()
+        };
+        let metrics = config.metrics.clone();
+        let (conn, _error_receiver) = open_connection(
+            UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+                address: proxy_addr,
+                datacenter: None,
+            }),
+            None,
+            &config,
+        )
+        .await
+        .unwrap();
+
+        let (slow, queued, skipped) = tokio::join!(
+            // Dropped after it has been written: its stream id is orphaned.
+            tokio::time::timeout(
+                Duration::from_millis(100),
+                conn.query_unpaged("INSERT INTO slow (a) VALUES (1)")
+            ),
+            conn.query_unpaged("INSERT INTO queued (a) VALUES (1)"),
+            // Dropped while waiting in the queue: it is never written.
+            tokio::time::timeout(
+                Duration::from_millis(100),
+                conn.query_unpaged("INSERT INTO skipped (a) VALUES (1)")
+            ),
+        );
+        slow.unwrap_err();
+        queued.unwrap();
+        skipped.unwrap_err();
+
+        assert_eq!(metrics.get_orphaned_requests_num(), 1);
+        assert_eq!(metrics.get_cancelled_requests_num(), 1);
+
+        // The connection is still usable, and the skipped request never reached the proxy.
+        conn.query_unpaged("INSERT INTO t (a) VALUES (1)")
+            .await
+            .unwrap();
+        let _ = proxy.finish().await;
+        assert!(skipped_rx.try_recv().is_err());
+    }
+
+    #[test]
+    fn stream_id_set_respects_limit() {
+        use super::{StreamIdSet, MAX_STREAM_IDS_PER_CONNECTION};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let in_use = Arc::new(AtomicUsize::new(0));
+        let mut set = StreamIdSet::new(100, in_use.clone());
+        let allocated: Vec<i16> = std::iter::from_fn(|| set.allocate()).collect();
+        assert_eq!(allocated, (0..100).collect::<Vec<i16>>());
+        assert_eq!(in_use.load(Ordering::Relaxed), 100);
+
+        // Freeing ids outside of the table or not allocated ones is a no-op.
+        set.free(100);
+        set.free(-1);
+        assert_eq!(in_use.load(Ordering::Relaxed), 100);
+        assert_eq!(set.allocate(), None);
+
+        set.free(42);
+        assert_eq!(in_use.load(Ordering::Relaxed), 99);
+        assert_eq!(set.allocate(), Some(42));
+
+        // Limits beyond the protocol maximum are clamped.
+        let mut set = StreamIdSet::new(u16::MAX, Arc::new(AtomicUsize::new(0)));
+        let count = std::iter::from_fn(|| set.allocate()).count();
+        assert_eq!(count, MAX_STREAM_IDS_PER_CONNECTION as usize);
+    }
+
+    #[test]
+    fn orphaned_stream_ids_are_counted() {
+        use super::{ResponseHandler, ResponseHandlerMap, StreamIdSet};
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let orphaned = Arc::new(AtomicUsize::new(0));
+        let mut map = ResponseHandlerMap::new(
+            StreamIdSet::new(100, Arc::new(AtomicUsize::new(0))),
+            orphaned.clone(),
+        );
+        let mut receivers = Vec::new();
+        let stream_ids: Vec<i16> = (0..3)
+            .map(|request_id| {
+                let (response_sender, receiver) = tokio::sync::oneshot::channel();
+                receivers.push(receiver);
+                map.allocate(ResponseHandler {
+                    response_sender,
+                    request_id,
+                    result_memory_limit: (request_id != 1).then_some(10),
+                })
+                .ok()
+                .unwrap()
+            })
+            .collect();
+        assert_eq!(map.result_memory_limit(stream_ids[0]), Some(10));
+        assert_eq!(map.result_memory_limit(stream_ids[1]), None);
+
+        assert!(map.orphan(0));
+        // The response to an orphaned request is not limited anymore.
+        assert_eq!(map.result_memory_limit(stream_ids[0]), None);
+        assert_eq!(map.result_memory_limit(stream_ids[2]), Some(10));
+        assert!(map.orphan(1));
+        // Orphaning the same request again doesn't count it twice.
+        assert!(!map.orphan(1));
+        assert_eq!(orphaned.load(Ordering::Relaxed), 2);
+
+        // A late response frees the orphaned stream id.
+        map.lookup(stream_ids[0]);
+        assert_eq!(orphaned.load(Ordering::Relaxed), 1);
+        map.lookup(stream_ids[2]);
+        assert_eq!(orphaned.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    fn buffer_pool_reuses_buffers() {
+        use super::BufferPool;
+
+        let pool = BufferPool::new(2, 1024);
+        let mut buffer = pool.take();
+        buffer.extend_from_slice(&[1, 2, 3]);
+        let ptr = buffer.as_ptr();
+        pool.put_back(buffer);
+
+        // The returned buffer is reused, and cleared.
+        let buffer = pool.take();
+        assert_eq!(buffer.as_ptr(), ptr);
+        assert!(buffer.is_empty());
+        assert!(buffer.capacity() >= 3);
+
+        // Too large buffers are not kept.
+        pool.put_back(Vec::with_capacity(2048));
+        assert_eq!(pool.take().capacity(), 0);
+
+        // At most `max_buffers` buffers are kept.
+        for _ in 0..3 {
+            pool.put_back(Vec::with_capacity(16));
+        }
+        assert_eq!(pool.buffers.lock().unwrap().len(), 2);
+
+        // A pool of size 0 disables pooling.
+        let pool = BufferPool::new(0, 1024);
+        pool.put_back(Vec::with_capacity(16));
+        assert_eq!(pool.take().capacity(), 0);
+    }
+
+    #[test]
+    fn compression_negotiation() {
+        use super::negotiate_compression;
+        use crate::transport::{Compression, ProtocolVersion};
+
+        setup_tracing();
+        let supported = vec!["snappy".to_owned(), "lz4".to_owned()];
+        let negotiate = |requested: &[Compression], version| {
+            negotiate_compression(requested, &supported, version)
+        };
+
+        // The first supported algorithm is picked.
+        #[cfg(feature = "zstd")]
+        assert_eq!(
+            negotiate(
+                &[Compression::Zstd, Compression::Snappy],
+                ProtocolVersion::V4
+            ),
+            Some(Compression::Snappy)
+        );
+        assert_eq!(
+            negotiate(
+                &[Compression::Lz4, Compression::Snappy],
+                ProtocolVersion::V4
+            ),
+            Some(Compression::Lz4)
+        );
+        #[cfg(feature = "zstd")]
+        assert_eq!(negotiate(&[Compression::Zstd], ProtocolVersion::V4), None);
+
+        // Protocol v5 only allows lz4.
+        assert_eq!(
+            negotiate(
+                &[Compression::Snappy, Compression::Lz4],
+                ProtocolVersion::V5
+            ),
+            Some(Compression::Lz4)
+        );
+        assert_eq!(negotiate(&[Compression::Snappy], ProtocolVersion::V5), None);
+    }
 }
@@ -5,13 +5,20 @@ use crate::routing::{Shard, ShardCount, Sharder};
 use crate::transport::errors::{
     BrokenConnectionErrorKind, ConnectionError, ConnectionPoolError, QueryError,
 };
+use crate::transport::metrics_export;
 use crate::transport::{
     connection,
-    connection::{Connection, ConnectionConfig, ErrorReceiver, VerifiedKeyspaceName},
+    connection::{
+        Connection, ConnectionConfig, ErrorReceiver, StreamIdExhaustionPolicy, VerifiedKeyspaceName,
+    },
 };
 
+use super::backpressure::BackpressureMonitor;
 #[cfg(feature = "cloud")]
 use super::node::resolve_hostname;
+use super::reconnection_policy::{
+    ExponentialReconnectionPolicy, ReconnectionPolicy, ReconnectionSchedule,
+};
 
 #[cfg(feature = "cloud")]
 use super::node::ResolvedContactPoint;
@@ -22,13 +29,13 @@ use arc_swap::ArcSwap;
 use futures::{future::RemoteHandle, stream::FuturesUnordered, Future, FutureExt, StreamExt};
 use rand::Rng;
 use std::convert::TryInto;
-use std::num::NonZeroUsize;
+use std::num::{NonZeroU64, NonZeroUsize};
 use std::pin::Pin;
-use std::sync::{Arc, RwLock, Weak};
+use std::sync::{Arc, Mutex, RwLock, Weak};
 use std::time::Duration;
 
-use tokio::sync::{broadcast, mpsc, Notify};
-use tracing::{debug, error, trace, warn};
+use tokio::sync::{broadcast, mpsc, watch, Notify};
+use tracing::{debug, error, trace, warn, Instrument};
 
 /// The target size of a per-node connection pool.
 #[derive(Debug, Clone, Copy)]
@@ -54,12 +61,91 @@ impl Default for PoolSize {
     }
 }
 
+/// Tells whether the connections to a node are opened to its shard-aware port,
+/// which makes them land on the intended shards deterministically.
+///
+/// The first connection to a node is always opened to the regular port,
+/// and it tells whether the node advertises the shard-aware port.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ShardAwarePortStatus {
+    /// No connection to the shard-aware port has been attempted yet,
+    /// e.g. because the pool was filled with the first connection.
+    Unknown,
+
+    /// Using the shard-aware port is disabled, either in the session configuration
+    /// or because the pool size is [`PoolSize::PerHost`].
+    Disabled,
+
+    /// The node is not sharded (e.g. it is a Cassandra node),
+    /// or it doesn't advertise the shard-aware port.
+    NotAdvertised,
+
+    /// The last connection opened to the shard-aware port landed on the intended shard.
+    Working,
+
+    /// The last connection opened to the shard-aware port landed on a different shard.
+    /// This happens if the source port of the connection is changed on the way,
+    /// e.g. by NAT.
+    ShardMismatch,
+
+    /// The last attempt to connect to the shard-aware port failed, e.g. because
+    /// the port is blocked by a firewall, so the regular port was used instead.
+    Unreachable,
+}
+
+/// State of the connection pool of a node, see [`Node::pool_state`](super::Node::pool_state).
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct PoolState {
+    /// Number of connections in the pool, including the ones which are being replaced
+    /// (e.g. after reaching their maximum lifetime).
+    pub open_connections: usize,
+
+    /// Number of connections the pool tries to keep open, according to its [`PoolSize`].
+    /// For the per-shard size, it is known only after the first connection tells
+    /// the number of shards of the node.
+    pub target_connections: usize,
+
+    /// Number of the refills of the pool which were delayed by the reconnection policy
+    /// because opening a connection had failed.
+    pub reconnect_attempts: u64,
+
+    /// The last error with which opening or using a connection failed, if any.
+    /// It is kept after the pool recovers.
+    pub last_error: Option<ConnectionError>,
+
+    /// Number of shards of the node, or `None` if the node is not sharded
+    /// or no connection to it has been opened yet.
+    pub shard_count: Option<usize>,
+
+    /// Number of shards with at least one connection in the pool.
+    /// For nodes which are not sharded, it is 1 if the pool has any connection.
+    pub connected_shards: usize,
+}
+
+impl PoolState {
+    /// Returns true if the pool has all the connections it tries to keep open.
+    pub fn is_full(&self) -> bool {
+        self.open_connections >= self.target_connections
+    }
+
+    /// Returns true if the pool has a connection to every shard of the node.
+    pub fn covers_all_shards(&self) -> bool {
+        self.connected_shards >= self.shard_count.unwrap_or(1)
+    }
+}
+
 #[derive(Clone)]
 pub(crate) struct PoolConfig {
     pub(crate) connection_config: ConnectionConfig,
     pub(crate) pool_size: PoolSize,
     pub(crate) can_use_shard_aware_port: bool,
     pub(crate) keepalive_interval: Option<Duration>,
+    pub(crate) max_connection_lifetime: Option<Duration>,
+    pub(crate) max_requests_per_connection: Option<NonZeroU64>,
+    pub(crate) reconnection_policy: Arc<dyn ReconnectionPolicy>,
+    pub(crate) backpressure_monitor: Option<Arc<BackpressureMonitor>>,
 }
 
 impl Default for PoolConfig {
@@ -69,6 +155,10 @@ impl Default for PoolConfig {
             pool_size: Default::default(),
             can_use_shard_aware_port: true,
             keepalive_interval: None,
+            max_connection_lifetime: None,
+            max_requests_per_connection: None,
+            reconnection_policy: Arc::new(ExponentialReconnectionPolicy::default()),
+            backpressure_monitor: None,
         }
     }
 }
@@ -154,7 +244,12 @@ pub(crate) struct NodeConnectionPool {
     use_keyspace_request_sender: mpsc::Sender<UseKeyspaceRequest>,
     _refiller_handle: Arc<RemoteHandle<()>>,
     pool_updated_notify: Arc<Notify>,
+    pool_full_receiver: watch::Receiver<bool>,
+    shard_aware_port_status: Arc<Mutex<ShardAwarePortStatus>>,
+    pool_state: Arc<Mutex<PoolState>>,
     endpoint: Arc<RwLock<UntranslatedEndpoint>>,
+    // If true, connections with no free stream ids are avoided when possible.
+    spill_on_stream_id_exhaustion: bool,
 }
 
 impl std::fmt::Debug for NodeConnectionPool {
@@ -168,12 +263,13 @@ impl std::fmt::Debug for NodeConnectionPool {
 impl NodeConnectionPool {
     pub(crate) fn new(
         endpoint: UntranslatedEndpoint,
-        #[allow(unused_mut)] mut pool_config: PoolConfig, // `mut` needed only with "cloud" feature
+        mut pool_config: PoolConfig,
         current_keyspace: Option<VerifiedKeyspaceName>,
         pool_empty_notifier: broadcast::Sender<()>,
     ) -> Self {
         let (use_keyspace_request_sender, use_keyspace_request_receiver) = mpsc::channel(1);
         let pool_updated_notify = Arc::new(Notify::new());
+        let (pool_full_sender, pool_full_receiver) = watch::channel(false);
 
         #[cfg(feature = "cloud")]
         if pool_config.connection_config.cloud_config.is_some() {
@@ -200,18 +296,38 @@ impl NodeConnectionPool {
             );
         }
 
+        if let Some(sni_proxy) = &pool_config.connection_config.sni_proxy {
+            let host_id = match &endpoint {
+                UntranslatedEndpoint::ContactPoint(_) => None,
+                UntranslatedEndpoint::Peer(PeerEndpoint { host_id, .. }) => Some(*host_id),
+            };
+            pool_config.connection_config.tls_server_name = Some(sni_proxy.server_name(host_id));
+        }
+
         let arced_endpoint = Arc::new(RwLock::new(endpoint));
+        let spill_on_stream_id_exhaustion =
+            pool_config.connection_config.stream_id_exhaustion_policy
+                == StreamIdExhaustionPolicy::Spill;
+
+        // The refiller logs with the tags of the session.
+        let span = pool_config.connection_config.metrics.user_tags().span();
 
         let refiller = PoolRefiller::new(
             arced_endpoint.clone(),
             pool_config,
             current_keyspace,
             pool_updated_notify.clone(),
+            pool_full_sender,
             pool_empty_notifier,
         );
 
         let conns = refiller.get_shared_connections();
-        let (fut, refiller_handle) = refiller.run(use_keyspace_request_receiver).remote_handle();
+        let shard_aware_port_status = refiller.shard_aware_port_status.clone();
+        let pool_state = refiller.pool_state.clone();
+        let (fut, refiller_handle) = refiller
+            .run(use_keyspace_request_receiver)
+            .instrument(span)
+            .remote_handle();
         tokio::spawn(fut);
 
         Self {
@@ -219,7 +335,11 @@ impl NodeConnectionPool {
             use_keyspace_request_sender,
             _refiller_handle: Arc::new(refiller_handle),
             pool_updated_notify,
+            pool_full_receiver,
+            shard_aware_port_status,
+            pool_state,
             endpoint: arced_endpoint,
+            spill_on_stream_id_exhaustion,
         }
     }
 
@@ -241,9 +361,7 @@ impl NodeConnectionPool {
     ) -> Result<Arc<Connection>, ConnectionPoolError> {
         trace!(shard = shard, "Selecting connection for shard");
         self.with_connections(|pool_conns| match pool_conns {
-            PoolConnections::NotSharded(conns) => {
-                Self::choose_random_connection_from_slice(conns).unwrap()
-            }
+            PoolConnections::NotSharded(conns) => self.choose_connection_from_slice(conns),
             PoolConnections::Sharded {
                 connections,
                 sharder
@@ -256,7 +374,7 @@ impl NodeConnectionPool {
                         error!("The provided shard number: {} does not fit u16! Using 0 as the shard number. Check your LoadBalancingPolicy implementation.", shard);
                         0
                     });
-                Self::connection_for_shard_helper(shard, sharder.nr_shards, connections.as_slice())
+                self.connection_for_shard_helper(shard, sharder.nr_shards, connections.as_slice())
             }
         })
     }
@@ -264,30 +382,69 @@ impl NodeConnectionPool {
     pub(crate) fn random_connection(&self) -> Result<Arc<Connection>, ConnectionPoolError> {
         trace!("Selecting random connection");
         self.with_connections(|pool_conns| match pool_conns {
-            PoolConnections::NotSharded(conns) => {
-                Self::choose_random_connection_from_slice(conns).unwrap()
-            }
+            PoolConnections::NotSharded(conns) => self.choose_connection_from_slice(conns),
             PoolConnections::Sharded {
                 sharder,
                 connections,
             } => {
                 let shard: u16 = rand::thread_rng().gen_range(0..sharder.nr_shards.get());
-                Self::connection_for_shard_helper(shard, sharder.nr_shards, connections.as_slice())
+                self.connection_for_shard_helper(shard, sharder.nr_shards, connections.as_slice())
             }
         })
     }
 
-    // Tries to get a connection to given shard, if it's broken returns any working connection
+    // Chooses a random connection from a non-empty slice, avoiding connections
+    // with no free stream ids if so configured.
+    fn choose_connection_from_slice(&self, v: &[Arc<Connection>]) -> Arc<Connection> {
+        if self.spill_on_stream_id_exhaustion {
+            if let Some(conn) = Self::choose_random_unsaturated_connection_from_slice(v) {
+                return conn;
+            }
+            trace!("All connections have exhausted their stream ids");
+        }
+        Self::choose_random_connection_from_slice(v).unwrap()
+    }
+
+    // Tries to get a connection to given shard, if it's broken returns any working connection.
+    // If spilling on stream id exhaustion is enabled, saturated connections are treated
+    // like broken ones, unless all of them are saturated.
     fn connection_for_shard_helper(
+        &self,
         shard: u16,
         nr_shards: ShardCount,
         shard_conns: &[Vec<Arc<Connection>>],
     ) -> Arc<Connection> {
+        if self.spill_on_stream_id_exhaustion {
+            if let Some(conn) = Self::choose_connection_for_shard(
+                shard,
+                nr_shards,
+                shard_conns,
+                Self::choose_random_unsaturated_connection_from_slice,
+            ) {
+                return conn;
+            }
+            trace!("All connections have exhausted their stream ids");
+        }
+
+        Self::choose_connection_for_shard(
+            shard,
+            nr_shards,
+            shard_conns,
+            Self::choose_random_connection_from_slice,
+        )
+        .expect("could not find any connection in supposedly non-empty pool")
+    }
+
+    fn choose_connection_for_shard(
+        shard: u16,
+        nr_shards: ShardCount,
+        shard_conns: &[Vec<Arc<Connection>>],
+        choose_from_slice: impl Fn(&[Arc<Connection>]) -> Option<Arc<Connection>>,
+    ) -> Option<Arc<Connection>> {
         // Try getting the desired connection
-        if let Some(conn) = Self::choose_random_connection_from_slice(&shard_conns[shard as usize])
-        {
+        if let Some(conn) = choose_from_slice(&shard_conns[shard as usize]) {
             trace!(shard = shard, "Found connection for the target shard");
-            return conn;
+            return Some(conn);
         }
 
         // If this fails try getting any other in random order
@@ -298,19 +455,17 @@ impl NodeConnectionPool {
             let idx = rand::thread_rng().gen_range(0..shards_to_try.len());
             let shard = shards_to_try.swap_remove(idx);
 
-            if let Some(conn) =
-                Self::choose_random_connection_from_slice(&shard_conns[shard as usize])
-            {
+            if let Some(conn) = choose_from_slice(&shard_conns[shard as usize]) {
                 trace!(
                     orig_shard = orig_shard,
                     shard = shard,
                     "Choosing connection for a different shard"
                 );
-                return conn;
+                return Some(conn);
             }
         }
 
-        unreachable!("could not find any connection in supposedly non-empty pool")
+        None
     }
 
     pub(crate) async fn use_keyspace(
@@ -345,6 +500,23 @@ impl NodeConnectionPool {
         }
     }
 
+    // Waits until the pool has all the connections required by its size.
+    // If the node is down, this may never happen.
+    pub(crate) async fn wait_until_full(&self) {
+        let mut pool_full_receiver = self.pool_full_receiver.clone();
+        // The sender lives in the refiller, which is stopped only
+        // after the pool is dropped.
+        let _ = pool_full_receiver.wait_for(|is_full| *is_full).await;
+    }
+
+    pub(crate) fn shard_aware_port_status(&self) -> ShardAwarePortStatus {
+        *self.shard_aware_port_status.lock().unwrap()
+    }
+
+    pub(crate) fn pool_state(&self) -> PoolState {
+        self.pool_state.lock().unwrap().clone()
+    }
+
     pub(crate) fn get_working_connections(
         &self,
     ) -> Result<Vec<Arc<Connection>>, ConnectionPoolError> {
@@ -356,6 +528,22 @@ impl NodeConnectionPool {
         })
     }
 
+    // Starts at a random position and picks the first connection with free stream ids,
+    // wrapping around the end of the slice.
+    fn choose_random_unsaturated_connection_from_slice(
+        v: &[Arc<Connection>],
+    ) -> Option<Arc<Connection>> {
+        if v.is_empty() {
+            return None;
+        }
+        let start = rand::thread_rng().gen_range(0..v.len());
+        v[start..]
+            .iter()
+            .chain(&v[..start])
+            .find(|conn| !conn.is_stream_id_saturated())
+            .cloned()
+    }
+
     fn choose_random_connection_from_slice(v: &[Arc<Connection>]) -> Option<Arc<Connection>> {
         trace!(
             connections = v
@@ -393,38 +581,9 @@ impl NodeConnectionPool {
 
 const EXCESS_CONNECTION_BOUND_PER_SHARD_MULTIPLIER: usize = 10;
 
-// TODO: Make it configurable through a policy (issue #184)
-const MIN_FILL_BACKOFF: Duration = Duration::from_millis(50);
-const MAX_FILL_BACKOFF: Duration = Duration::from_secs(10);
-const FILL_BACKOFF_MULTIPLIER: u32 = 2;
-
-// A simple exponential strategy for pool fill backoffs.
-struct RefillDelayStrategy {
-    current_delay: Duration,
-}
-
-impl RefillDelayStrategy {
-    fn new() -> Self {
-        Self {
-            current_delay: MIN_FILL_BACKOFF,
-        }
-    }
-
-    fn get_delay(&self) -> Duration {
-        self.current_delay
-    }
-
-    fn on_successful_fill(&mut self) {
-        self.current_delay = MIN_FILL_BACKOFF;
-    }
-
-    fn on_fill_error(&mut self) {
-        self.current_delay = std::cmp::min(
-            MAX_FILL_BACKOFF,
-            self.current_delay * FILL_BACKOFF_MULTIPLIER,
-        );
-    }
-}
+// How often the pool checks whether its connections should be recycled,
+// e.g. because they are too old or have too many orphaned stream ids.
+const RECYCLING_CHECK_INTERVAL: Duration = Duration::from_secs(1);
 
 struct PoolRefiller {
     // Following information identify the pool and do not change
@@ -445,7 +604,7 @@ struct PoolRefiller {
     // set to false when refilling starts.
     had_error_since_last_refill: bool,
 
-    refill_delay_strategy: RefillDelayStrategy,
+    reconnection_schedule: Box<dyn ReconnectionSchedule>,
 
     // Receives information about connections becoming ready, i.e. newly connected
     // or after its keyspace was correctly set.
@@ -476,6 +635,15 @@ struct PoolRefiller {
     // Signaled when the connection pool is updated
     pool_updated_notify: Arc<Notify>,
 
+    // Tells whether the pool has all the connections required by its size
+    pool_full_sender: watch::Sender<bool>,
+
+    // Outcome of the last attempt to use the shard-aware port, for diagnostics
+    shard_aware_port_status: Arc<Mutex<ShardAwarePortStatus>>,
+
+    // Snapshot of the state of the pool, for diagnostics and health checks
+    pool_state: Arc<Mutex<PoolState>>,
+
     // Signaled when the connection pool becomes empty
     pool_empty_notifier: broadcast::Sender<()>,
 }
@@ -492,12 +660,20 @@ impl PoolRefiller {
         pool_config: PoolConfig,
         current_keyspace: Option<VerifiedKeyspaceName>,
         pool_updated_notify: Arc<Notify>,
+        pool_full_sender: watch::Sender<bool>,
         pool_empty_notifier: broadcast::Sender<()>,
     ) -> Self {
         // At the beginning, we assume the node does not have any shards
         // and assume that the node is a Cassandra node
         let conns = vec![Vec::new()];
         let shared_conns = Arc::new(ArcSwap::new(Arc::new(MaybePoolConnections::Initializing)));
+        let reconnection_schedule = pool_config.reconnection_policy.new_schedule();
+        let pool_state = PoolState {
+            target_connections: match pool_config.pool_size {
+                PoolSize::PerHost(target) | PoolSize::PerShard(target) => target.get(),
+            },
+            ..Default::default()
+        };
 
         Self {
             endpoint,
@@ -510,7 +686,7 @@ impl PoolRefiller {
             conns,
 
             had_error_since_last_refill: false,
-            refill_delay_strategy: RefillDelayStrategy::new(),
+            reconnection_schedule,
 
             ready_connections: FuturesUnordered::new(),
             connection_errors: FuturesUnordered::new(),
@@ -520,6 +696,9 @@ impl PoolRefiller {
             current_keyspace,
 
             pool_updated_notify,
+            pool_full_sender,
+            shard_aware_port_status: Arc::new(Mutex::new(ShardAwarePortStatus::Unknown)),
+            pool_state: Arc::new(Mutex::new(pool_state)),
             pool_empty_notifier,
         }
     }
@@ -544,6 +723,7 @@ impl PoolRefiller {
 
         let mut next_refill_time = tokio::time::Instant::now();
         let mut refill_scheduled = true;
+        let mut recycling_check = tokio::time::interval(RECYCLING_CHECK_INTERVAL);
 
         loop {
             tokio::select! {
@@ -573,6 +753,10 @@ impl PoolRefiller {
                     }
                 }
 
+                // Connections which need recycling are not counted as filling the pool,
+                // so the refill scheduled below opens their replacements.
+                _ = recycling_check.tick() => {}
+
                 req = use_keyspace_request_receiver.recv() => {
                     if let Some(req) = req {
                         debug!("[{}] Requested keyspace change: {}", self.endpoint_description(), req.keyspace_name.as_str());
@@ -592,12 +776,16 @@ impl PoolRefiller {
 
             // Schedule refilling here
             if !refill_scheduled && self.need_filling() {
-                if self.had_error_since_last_refill {
-                    self.refill_delay_strategy.on_fill_error();
+                let delay = if self.had_error_since_last_refill {
+                    self.pool_state.lock().unwrap().reconnect_attempts += 1;
+                    metrics_export::record_reconnect_attempt(
+                        self.endpoint_description(),
+                        self.pool_config.connection_config.metrics.user_tags(),
+                    );
+                    self.reconnection_schedule.next_delay_after_failure()
                 } else {
-                    self.refill_delay_strategy.on_successful_fill();
-                }
-                let delay = self.refill_delay_strategy.get_delay();
+                    self.reconnection_schedule.next_delay_after_success()
+                };
                 debug!(
                     "[{}] Scheduling next refill in {} ms",
                     self.endpoint_description(),
@@ -616,13 +804,44 @@ impl PoolRefiller {
 
     fn is_full(&self) -> bool {
         match self.pool_config.pool_size {
-            PoolSize::PerHost(target) => self.active_connection_count() >= target.get(),
-            PoolSize::PerShard(target) => {
-                self.conns.iter().all(|conns| conns.len() >= target.get())
-            }
+            PoolSize::PerHost(target) => self.lasting_connection_count() >= target.get(),
+            PoolSize::PerShard(target) => self
+                .conns
+                .iter()
+                .all(|conns| self.count_lasting_connections(conns) >= target.get()),
         }
     }
 
+    // Returns true if the connection has reached its maximum lifetime or
+    // request count. Such connection keeps serving requests until a new
+    // connection takes its place in the pool.
+    fn needs_recycling(&self, connection: &Connection) -> bool {
+        self.pool_config
+            .max_connection_lifetime
+            .is_some_and(|lifetime| connection.get_age() >= lifetime)
+            || self
+                .pool_config
+                .max_requests_per_connection
+                .is_some_and(|max_requests| {
+                    connection.get_sent_requests_count() >= max_requests.get()
+                })
+            || connection.has_many_orphaned_stream_ids()
+    }
+
+    fn count_lasting_connections(&self, conns: &[Arc<Connection>]) -> usize {
+        conns
+            .iter()
+            .filter(|conn| !self.needs_recycling(conn))
+            .count()
+    }
+
+    fn lasting_connection_count(&self) -> usize {
+        self.conns
+            .iter()
+            .map(|conns| self.count_lasting_connections(conns))
+            .sum::<usize>()
+    }
+
     fn is_empty(&self) -> bool {
         self.conns.iter().all(|conns| conns.is_empty())
     }
@@ -657,8 +876,16 @@ impl PoolRefiller {
             // Only use the shard-aware port if we have a PerShard strategy
             if let PoolSize::PerShard(target) = self.pool_config.pool_size {
                 // Try to fill up each shard up to `target` connections
-                for (shard_id, shard_conns) in self.conns.iter().enumerate() {
-                    let to_open_count = target.get().saturating_sub(shard_conns.len());
+                let to_open_counts: Vec<usize> = self
+                    .conns
+                    .iter()
+                    .map(|conns| {
+                        target
+                            .get()
+                            .saturating_sub(self.count_lasting_connections(conns))
+                    })
+                    .collect();
+                for (shard_id, to_open_count) in to_open_counts.into_iter().enumerate() {
                     if to_open_count == 0 {
                         continue;
                     }
@@ -679,12 +906,16 @@ impl PoolRefiller {
         // to achieve the target connection count.
         let to_open_count = match self.pool_config.pool_size {
             PoolSize::PerHost(target) => {
-                target.get().saturating_sub(self.active_connection_count())
+                target.get().saturating_sub(self.lasting_connection_count())
             }
             PoolSize::PerShard(target) => self
                 .conns
                 .iter()
-                .map(|conns| target.get().saturating_sub(conns.len()))
+                .map(|conns| {
+                    target
+                        .get()
+                        .saturating_sub(self.count_lasting_connections(conns))
+                })
                 .sum::<usize>(),
         };
         // When connecting to Scylla through non-shard-aware port,
@@ -723,6 +954,7 @@ impl PoolRefiller {
                         self.endpoint_description(),
                         err,
                     );
+                    self.set_shard_aware_port_status(ShardAwarePortStatus::Unreachable);
                     self.start_opening_connection(None);
                 } else {
                     // Encountered an error while connecting to the non-shard-aware
@@ -734,6 +966,7 @@ impl PoolRefiller {
                         self.endpoint_description(),
                         err,
                     );
+                    self.pool_state.lock().unwrap().last_error = Some(err.clone());
 
                     // If all connection attempts in this fill attempt failed
                     // and the pool is empty, report this error.
@@ -759,6 +992,26 @@ impl PoolRefiller {
                     self.shard_aware_port = connection.get_shard_aware_port();
                 }
 
+                match evt.requested_shard {
+                    Some(requested_shard) if requested_shard as usize == shard_id => {
+                        self.set_shard_aware_port_status(ShardAwarePortStatus::Working)
+                    }
+                    Some(_) => {
+                        self.set_shard_aware_port_status(ShardAwarePortStatus::ShardMismatch)
+                    }
+                    None if !self.pool_config.can_use_shard_aware_port
+                        || matches!(self.pool_config.pool_size, PoolSize::PerHost(_)) =>
+                    {
+                        self.set_shard_aware_port_status(ShardAwarePortStatus::Disabled)
+                    }
+                    None if self.sharder.is_none() || self.shard_aware_port.is_none() => {
+                        self.set_shard_aware_port_status(ShardAwarePortStatus::NotAdvertised)
+                    }
+                    // Connections to the regular port are opened also as a fallback,
+                    // so they don't change the status.
+                    None => {}
+                }
+
                 // Before the connection can be put to the pool, we need
                 // to make sure that it uses appropriate keyspace
                 if let Some(keyspace) = &self.current_keyspace {
@@ -780,8 +1033,10 @@ impl PoolRefiller {
                 // Decide if the connection can be accepted, according to
                 // the pool filling strategy
                 let can_be_accepted = match self.pool_config.pool_size {
-                    PoolSize::PerHost(target) => self.active_connection_count() < target.get(),
-                    PoolSize::PerShard(target) => self.conns[shard_id].len() < target.get(),
+                    PoolSize::PerHost(target) => self.lasting_connection_count() < target.get(),
+                    PoolSize::PerShard(target) => {
+                        self.count_lasting_connections(&self.conns[shard_id]) < target.get()
+                    }
                 };
 
                 if can_be_accepted {
@@ -802,6 +1057,7 @@ impl PoolRefiller {
                     self.connection_errors
                         .push(wait_for_error(Arc::downgrade(&conn), error_receiver).boxed());
                     self.conns[shard_id].push(conn);
+                    self.retire_recycled_connection(shard_id);
 
                     self.update_shared_conns(None);
                 } else if evt.requested_shard.is_some() {
@@ -922,7 +1178,13 @@ impl PoolRefiller {
         // If we operate in the serverless Cloud, then we substitute every node's address
         // with the address of the proxy in the datacenter that the node resides in.
         // As this may may involve resolving a hostname, the whole operation is async.
-        let endpoint_fut = self.maybe_translate_for_serverless(endpoint);
+        // Similarly, if connections go through an SNI proxy, the address of the proxy is used.
+        let endpoint_fut = match self.pool_config.connection_config.sni_proxy.clone() {
+            Some(sni_proxy) => {
+                async move { sni_proxy.translate_endpoint(endpoint).await }.left_future()
+            }
+            None => self.maybe_translate_for_serverless(endpoint).right_future(),
+        };
 
         let fut = match (self.sharder.clone(), self.shard_aware_port, shard) {
             (Some(sharder), Some(port), Some(shard)) => async move {
@@ -1006,15 +1268,36 @@ impl PoolRefiller {
 
         // Make the connection list available
         self.shared_conns.store(new_conns);
+        let pool_state = {
+            let mut pool_state = self.pool_state.lock().unwrap();
+            pool_state.open_connections = self.active_connection_count();
+            pool_state.target_connections = self.target_connection_count();
+            pool_state.shard_count = self
+                .sharder
+                .as_ref()
+                .map(|sharder| sharder.nr_shards.get() as usize);
+            pool_state.connected_shards =
+                self.conns.iter().filter(|conns| !conns.is_empty()).count();
+            pool_state.clone()
+        };
+        metrics_export::record_pool_state(
+            self.endpoint_description(),
+            self.pool_config.connection_config.metrics.user_tags(),
+            &pool_state,
+        );
 
         // Notify potential waiters
         self.pool_updated_notify.notify_waiters();
+        let is_full = self.is_full();
+        self.pool_full_sender
+            .send_if_modified(|was_full| std::mem::replace(was_full, is_full) != is_full);
     }
 
     // Removes given connection from the pool. It looks both into active
     // connections and excess connections.
     fn remove_connection(&mut self, connection: Arc<Connection>, last_error: ConnectionError) {
         let ptr = Arc::as_ptr(&connection);
+        self.pool_state.lock().unwrap().last_error = Some(last_error.clone());
 
         let maybe_remove_in_vec = |v: &mut Vec<Arc<Connection>>| -> bool {
             let maybe_idx = v
@@ -1151,10 +1434,58 @@ impl PoolRefiller {
         );
     }
 
+    // Removes a connection which needs recycling from the pool, after a new connection
+    // has been added to the given shard. Requests which are already sent on the removed
+    // connection complete, and the connection is closed once they do.
+    // In the PerHost mode, the new connection can replace a connection to any shard.
+    fn retire_recycled_connection(&mut self, shard_id: usize) {
+        let shards_to_check = match self.pool_config.pool_size {
+            PoolSize::PerShard(_) => shard_id..shard_id + 1,
+            PoolSize::PerHost(_) => 0..self.conns.len(),
+        };
+        let found = shards_to_check
+            .flat_map(|shard| (0..self.conns[shard].len()).map(move |idx| (shard, idx)))
+            .filter(|&(shard, idx)| self.needs_recycling(&self.conns[shard][idx]))
+            // Prefer the connection to the same shard.
+            .min_by_key(|&(shard, _)| shard != shard_id);
+        if let Some((shard, idx)) = found {
+            let conn = self.conns[shard].swap_remove(idx);
+            debug!(
+                "[{}] Recycling connection {:p} to shard {}, which is {} ms old, has sent {} requests and has {} orphaned stream ids",
+                self.endpoint_description(),
+                Arc::as_ptr(&conn),
+                shard,
+                conn.get_age().as_millis(),
+                conn.get_sent_requests_count(),
+                conn.get_orphaned_stream_ids_count(),
+            );
+        }
+    }
+
+    fn set_shard_aware_port_status(&self, status: ShardAwarePortStatus) {
+        let mut current_status = self.shard_aware_port_status.lock().unwrap();
+        if *current_status != status {
+            debug!(
+                "[{}] Shard-aware port status changed from {:?} to {:?}",
+                self.endpoint_description(),
+                *current_status,
+                status,
+            );
+            *current_status = status;
+        }
+    }
+
     fn active_connection_count(&self) -> usize {
         self.conns.iter().map(Vec::len).sum::<usize>()
     }
 
+    fn target_connection_count(&self) -> usize {
+        match self.pool_config.pool_size {
+            PoolSize::PerHost(target) => target.get(),
+            PoolSize::PerShard(target) => target.get() * self.conns.len(),
+        }
+    }
+
     fn excess_connection_limit(&self) -> usize {
         match self.pool_config.pool_size {
             PoolSize::PerShard(_) => {
@@ -11,6 +11,12 @@ use crate::{
 /// when it believes that the initial CL is reachable.
 /// Behaviour based on [DataStax Java Driver]\
 ///(<https://docs.datastax.com/en/drivers/java/3.11/com/datastax/driver/core/policies/DowngradingConsistencyRetryPolicy.html>)
+///
+/// **Beware**: this policy affects correctness. A retried read may not see a preceding write
+/// made at the initially requested consistency level, and some write timeouts are ignored
+/// once the write reached a single replica. Every downgrade is logged at the debug level.
+/// See [the book](https://rust-driver.docs.scylladb.com/stable/retry-policy/downgrading-consistency.html)
+/// for the exact rules.
 #[derive(Debug)]
 pub struct DowngradingConsistencyRetryPolicy;
 
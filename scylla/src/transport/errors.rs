@@ -10,6 +10,7 @@ use std::{
     net::{AddrParseError, IpAddr, SocketAddr},
     num::ParseIntError,
     sync::Arc,
+    time::Duration,
 };
 
 #[allow(deprecated)]
@@ -39,6 +40,25 @@ use super::iterator::NextRowError;
 #[allow(deprecated)]
 use super::legacy_query_result::IntoLegacyQueryResultError;
 use super::query_result::{IntoRowsResultError, SingleRowError};
+use super::session::SchemaVersions;
+
+/// A request was rejected by the request throttler of the session,
+/// see [`RequestThrottler`](crate::transport::throttler::RequestThrottler).
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum OverloadedError {
+    /// There are too many requests in flight.
+    #[error("Too many requests in flight, the limit is {max_in_flight}")]
+    TooManyInFlightRequests { max_in_flight: usize },
+
+    /// Too many requests were sent recently.
+    #[error("Too many requests sent, the limit is {max_per_second} per second")]
+    RateLimitExceeded { max_per_second: u32 },
+
+    /// A custom throttler rejected the request.
+    #[error("{0}")]
+    Custom(Arc<dyn Error + Send + Sync>),
+}
 
 /// Error that occurred during query execution
 #[derive(Error, Debug, Clone)]
@@ -106,6 +126,20 @@ pub enum QueryError {
     #[error("Request timeout: {0}")]
     RequestTimeout(String),
 
+    /// The rows of the result take more memory than the limit set on the statement,
+    /// see [`Query::set_result_memory_limit`](crate::query::Query::set_result_memory_limit).
+    #[error("Result takes {size} bytes, which exceeds the memory limit of {limit} bytes")]
+    ResultMemoryLimitExceeded {
+        /// Memory the rows of the result take, in bytes.
+        size: usize,
+        /// The limit, in bytes.
+        limit: usize,
+    },
+
+    /// The request was rejected by the request throttler of the session.
+    #[error("Request rejected by the request throttler: {0}")]
+    Overloaded(#[from] OverloadedError),
+
     // TODO: This should not belong here, but it requires changes to error types
     // returned in async iterator API. This should be handled in separate PR.
     // The reason this needs to be included is that topology.rs makes use of iter API and returns QueryError.
@@ -157,6 +191,9 @@ impl From<UserRequestError> for QueryError {
             }
             UserRequestError::BodyExtensionsParseError(e) => e.into(),
             UserRequestError::UnableToAllocStreamId => QueryError::UnableToAllocStreamId,
+            UserRequestError::ResultMemoryLimitExceeded { size, limit } => {
+                QueryError::ResultMemoryLimitExceeded { size, limit }
+            }
             UserRequestError::RepreparedIdChanged {
                 statement,
                 expected_id,
@@ -188,6 +225,10 @@ impl From<QueryError> for NewSessionError {
             QueryError::BrokenConnection(e) => NewSessionError::BrokenConnection(e),
             QueryError::UnableToAllocStreamId => NewSessionError::UnableToAllocStreamId,
             QueryError::RequestTimeout(msg) => NewSessionError::RequestTimeout(msg),
+            QueryError::ResultMemoryLimitExceeded { size, limit } => {
+                NewSessionError::ResultMemoryLimitExceeded { size, limit }
+            }
+            QueryError::Overloaded(e) => NewSessionError::Overloaded(e),
             #[allow(deprecated)]
             QueryError::IntoLegacyQueryResultError(e) => {
                 NewSessionError::IntoLegacyQueryResultError(e)
@@ -209,6 +250,27 @@ impl From<response::Error> for QueryError {
     }
 }
 
+/// Error returned by [`Session::await_schema_agreement_with`](crate::Session::await_schema_agreement_with).
+#[derive(Error, Debug, Clone)]
+#[non_exhaustive]
+pub enum SchemaAgreementError {
+    /// Failed to fetch the schema versions.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// The nodes did not agree on the schema version before the timeout.
+    #[error(
+        "Schema agreement not reached in {timeout:?}, schema versions: {}",
+        versions.as_ref().map_or_else(|| "unknown".to_owned(), |versions| versions.to_string())
+    )]
+    Timeout {
+        /// The timeout that passed.
+        timeout: Duration,
+        /// The schema versions fetched last, if any were fetched before the timeout.
+        versions: Option<SchemaVersions>,
+    },
+}
+
 /// Error that occurred during session creation
 #[derive(Error, Debug, Clone)]
 #[non_exhaustive]
@@ -285,6 +347,20 @@ pub enum NewSessionError {
     #[error("Client timeout: {0}")]
     RequestTimeout(String),
 
+    /// The rows of the result take more memory than the limit set on the statement,
+    /// see [`Query::set_result_memory_limit`](crate::query::Query::set_result_memory_limit).
+    #[error("Result takes {size} bytes, which exceeds the memory limit of {limit} bytes")]
+    ResultMemoryLimitExceeded {
+        /// Memory the rows of the result take, in bytes.
+        size: usize,
+        /// The limit, in bytes.
+        limit: usize,
+    },
+
+    /// The request was rejected by the request throttler of the session.
+    #[error("Request rejected by the request throttler: {0}")]
+    Overloaded(#[from] OverloadedError),
+
     // TODO: This should not belong here, but it requires changes to error types
     // returned in async iterator API. This should be handled in separate PR.
     // The reason this needs to be included is that topology.rs makes use of iter API and returns QueryError.
@@ -598,6 +674,16 @@ pub enum BadQuery {
     #[error("Number of Queries in Batch Statement supplied is {0} which has exceeded the max value of 65,535")]
     TooManyQueriesInBatchStatement(usize),
 
+    /// A server-side timeout was set on a statement, but the node it was about to be
+    /// sent to does not support it. Only Scylla supports `USING TIMEOUT`.
+    #[error("Server-side timeout (USING TIMEOUT) is supported only by Scylla nodes")]
+    ServerTimeoutNotSupported,
+
+    /// The statement contains `ALLOW FILTERING`, which is rejected by the guardrail
+    /// configured in the execution profile, see [`AllowFilteringGuardrail`](crate::transport::AllowFilteringGuardrail).
+    #[error("Statement rejected by the ALLOW FILTERING guardrail: {0}")]
+    AllowFilteringRejected(String),
+
     /// Other reasons of bad query
     #[error("{0}")]
     Other(String),
@@ -933,6 +1019,8 @@ pub(crate) enum UserRequestError {
         expected_id: Vec<u8>,
         reprepared_id: Vec<u8>,
     },
+    #[error("Result takes {size} bytes, which exceeds the memory limit of {limit} bytes")]
+    ResultMemoryLimitExceeded { size: usize, limit: usize },
 }
 
 impl From<response::error::Error> for UserRequestError {
@@ -955,6 +1043,9 @@ impl From<RequestError> for UserRequestError {
             },
             RequestError::BrokenConnection(e) => e.into(),
             RequestError::UnableToAllocStreamId => UserRequestError::UnableToAllocStreamId,
+            RequestError::ResultMemoryLimitExceeded { size, limit } => {
+                UserRequestError::ResultMemoryLimitExceeded { size, limit }
+            }
         }
     }
 }
@@ -988,6 +1079,16 @@ pub enum RequestError {
     /// Driver was unable to allocate a stream id to execute a request on.
     #[error("Unable to allocate a stream id")]
     UnableToAllocStreamId,
+
+    /// The response is larger than the limit set on the statement, and was
+    /// discarded without being buffered.
+    #[error("Response takes {size} bytes, which exceeds the memory limit of {limit} bytes")]
+    ResultMemoryLimitExceeded {
+        /// The size of the response body, in bytes.
+        size: usize,
+        /// The limit, in bytes.
+        limit: usize,
+    },
 }
 
 impl From<ResponseParseError> for RequestError {
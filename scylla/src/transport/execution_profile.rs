@@ -167,14 +167,15 @@ use scylla_cql::{frame::types::SerialConsistency, Consistency};
 
 use crate::{
     load_balancing::LoadBalancingPolicy, retry_policy::RetryPolicy,
-    speculative_execution::SpeculativeExecutionPolicy,
+    speculative_execution::SpeculativeExecutionPolicy, transport::UserTags,
 };
 
 pub(crate) mod defaults {
     use crate::load_balancing::{self, LoadBalancingPolicy};
     use crate::retry_policy::{DefaultRetryPolicy, RetryPolicy};
     use crate::speculative_execution::SpeculativeExecutionPolicy;
-    use crate::transport::execution_profile::ExecutionProfileInner;
+    use crate::transport::execution_profile::{AllowFilteringGuardrail, ExecutionProfileInner};
+    use crate::transport::UserTags;
     use scylla_cql::frame::types::SerialConsistency;
     use scylla_cql::Consistency;
     use std::sync::Arc;
@@ -197,6 +198,12 @@ pub(crate) mod defaults {
     pub(crate) fn speculative_execution_policy() -> Option<Arc<dyn SpeculativeExecutionPolicy>> {
         None
     }
+    pub(crate) fn allow_filtering_guardrail() -> AllowFilteringGuardrail {
+        AllowFilteringGuardrail::Off
+    }
+    pub(crate) fn user_tags() -> UserTags {
+        UserTags::new()
+    }
 
     impl Default for ExecutionProfileInner {
         fn default() -> Self {
@@ -207,6 +214,9 @@ pub(crate) mod defaults {
                 load_balancing_policy: load_balancing_policy(),
                 retry_policy: retry_policy(),
                 speculative_execution_policy: speculative_execution_policy(),
+                allow_filtering_guardrail: allow_filtering_guardrail(),
+                user_tags: user_tags(),
+                overrides: None,
             }
         }
     }
@@ -235,6 +245,8 @@ pub struct ExecutionProfileBuilder {
     load_balancing_policy: Option<Arc<dyn LoadBalancingPolicy>>,
     retry_policy: Option<Arc<dyn RetryPolicy>>,
     speculative_execution_policy: Option<Option<Arc<dyn SpeculativeExecutionPolicy>>>,
+    allow_filtering_guardrail: Option<AllowFilteringGuardrail>,
+    user_tags: Option<UserTags>,
 }
 
 impl ExecutionProfileBuilder {
@@ -348,6 +360,46 @@ impl ExecutionProfileBuilder {
         self
     }
 
+    /// Sets how statements containing `ALLOW FILTERING` are treated,
+    /// see [`AllowFilteringGuardrail`].
+    /// The default is [`AllowFilteringGuardrail::Off`].
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::transport::{AllowFilteringGuardrail, ExecutionProfile};
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let profile: ExecutionProfile = ExecutionProfile::builder()
+    ///     .allow_filtering_guardrail(AllowFilteringGuardrail::Reject)
+    ///     .build();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn allow_filtering_guardrail(mut self, guardrail: AllowFilteringGuardrail) -> Self {
+        self.allow_filtering_guardrail = Some(guardrail);
+        self
+    }
+
+    /// Attaches a tag to the requests executed with this profile.
+    /// The tags of the profile override the tags of the session with the same keys,
+    /// see [`UserTags`]. By default, there are no tags.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::transport::ExecutionProfile;
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let profile: ExecutionProfile = ExecutionProfile::builder()
+    ///     .user_tag("workload", "reporting")
+    ///     .build();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn user_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.user_tags
+            .get_or_insert_with(defaults::user_tags)
+            .insert(key, value);
+        self
+    }
+
     /// Builds the ExecutionProfile after setting all the options.
     ///
     /// # Example
@@ -378,8 +430,45 @@ impl ExecutionProfileBuilder {
             speculative_execution_policy: self
                 .speculative_execution_policy
                 .unwrap_or_else(defaults::speculative_execution_policy),
+            allow_filtering_guardrail: self
+                .allow_filtering_guardrail
+                .unwrap_or_else(defaults::allow_filtering_guardrail),
+            user_tags: self.user_tags.unwrap_or_else(defaults::user_tags),
+            overrides: None,
         }))
     }
+
+    /// Builds an ExecutionProfile which inherits the options that were not set
+    /// from the default execution profile of the session executing the statement.
+    ///
+    /// This is meant for profiles assigned to statements, which only need to differ
+    /// from the session's profile in a few options, e.g. the consistency,
+    /// and should follow any changes of the remaining ones.
+    /// When used as the default profile of a session, the options which were not set
+    /// take the default values, as with [build](ExecutionProfileBuilder::build).
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::query::Query;
+    /// # use scylla::statement::Consistency;
+    /// # use scylla::transport::ExecutionProfile;
+    /// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// // The timeouts, policies etc. are the ones of the session's default profile.
+    /// let handle = ExecutionProfile::builder()
+    ///     .consistency(Consistency::All)
+    ///     .build_inheriting()
+    ///     .into_handle_with_label("strong-reads".to_owned());
+    ///
+    /// let mut query = Query::new("SELECT * FROM ks.table");
+    /// query.set_execution_profile_handle(Some(handle));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build_inheriting(self) -> ExecutionProfile {
+        let mut inner = Arc::into_inner(self.clone().build().0).unwrap();
+        inner.overrides = Some(self);
+        ExecutionProfile(Arc::new(inner))
+    }
 }
 
 impl Default for ExecutionProfileBuilder {
@@ -407,11 +496,24 @@ pub(crate) struct ExecutionProfileInner {
     pub(crate) load_balancing_policy: Arc<dyn LoadBalancingPolicy>,
     pub(crate) retry_policy: Arc<dyn RetryPolicy>,
     pub(crate) speculative_execution_policy: Option<Arc<dyn SpeculativeExecutionPolicy>>,
+
+    pub(crate) allow_filtering_guardrail: AllowFilteringGuardrail,
+
+    pub(crate) user_tags: UserTags,
+
+    /// The options set explicitly on a profile created with
+    /// [ExecutionProfileBuilder::build_inheriting]. The remaining ones are inherited
+    /// from the session's default profile.
+    pub(crate) overrides: Option<ExecutionProfileBuilder>,
 }
 
 impl ExecutionProfileInner {
     /// Creates a builder having all options set to the same as set in this ExecutionProfileInner.
+    /// For an inheriting profile, only the options which were set explicitly are set.
     pub(crate) fn to_builder(&self) -> ExecutionProfileBuilder {
+        if let Some(overrides) = &self.overrides {
+            return overrides.clone();
+        }
         ExecutionProfileBuilder {
             request_timeout: Some(self.request_timeout),
             consistency: Some(self.consistency),
@@ -419,6 +521,8 @@ impl ExecutionProfileInner {
             load_balancing_policy: Some(self.load_balancing_policy.clone()),
             retry_policy: Some(self.retry_policy.clone()),
             speculative_execution_policy: Some(self.speculative_execution_policy.clone()),
+            allow_filtering_guardrail: Some(self.allow_filtering_guardrail),
+            user_tags: Some(self.user_tags.clone()),
         }
     }
 }
@@ -437,6 +541,8 @@ impl ExecutionProfile {
             load_balancing_policy: None,
             retry_policy: None,
             speculative_execution_policy: None,
+            allow_filtering_guardrail: None,
+            user_tags: None,
         }
     }
 
@@ -487,6 +593,43 @@ impl ExecutionProfile {
     pub fn get_speculative_execution_policy(&self) -> Option<&Arc<dyn SpeculativeExecutionPolicy>> {
         self.0.speculative_execution_policy.as_ref()
     }
+
+    /// Gets the `ALLOW FILTERING` guardrail mode associated with this profile.
+    pub fn get_allow_filtering_guardrail(&self) -> AllowFilteringGuardrail {
+        self.0.allow_filtering_guardrail
+    }
+
+    /// Gets the user tags associated with this profile.
+    pub fn get_user_tags(&self) -> &UserTags {
+        &self.0.user_tags
+    }
+}
+
+/// Determines how the driver treats statements containing `ALLOW FILTERING`.
+///
+/// `ALLOW FILTERING` lets the server scan whole partitions, or even the whole
+/// table, in order to find the requested rows. While it is fine for small
+/// tables or rare maintenance tasks, such a statement finding its way into
+/// a hot path can bring a production cluster to its knees. The guardrail
+/// helps to catch such statements early, e.g. in tests.
+///
+/// Statements that are known to be fine can be exempted from the guardrail with
+/// [`Query::set_allow_filtering_permitted`](crate::query::Query::set_allow_filtering_permitted)
+/// or [`PreparedStatement::set_allow_filtering_permitted`](crate::prepared_statement::PreparedStatement::set_allow_filtering_permitted).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[non_exhaustive]
+pub enum AllowFilteringGuardrail {
+    /// Statements containing `ALLOW FILTERING` are executed as any others.
+    #[default]
+    Off,
+
+    /// Statements containing `ALLOW FILTERING` are executed, but a warning is logged.
+    Warn,
+
+    /// Statements containing `ALLOW FILTERING` fail with
+    /// [`BadQuery::AllowFilteringRejected`](crate::transport::errors::BadQuery::AllowFilteringRejected)
+    /// without being sent.
+    Reject,
 }
 
 /// A handle that points to an ExecutionProfile.
@@ -508,6 +651,46 @@ impl ExecutionProfileHandle {
         self.0 .0.load_full()
     }
 
+    /// Returns the profile to execute a statement with, given the handle assigned
+    /// to the statement and the default handle of the session.
+    pub(crate) fn resolve(
+        statement_handle: Option<&ExecutionProfileHandle>,
+        session_handle: &ExecutionProfileHandle,
+    ) -> Arc<ExecutionProfileInner> {
+        let Some(statement_handle) = statement_handle else {
+            return session_handle.access();
+        };
+        let profile = statement_handle.access();
+        let Some(overrides) = &profile.overrides else {
+            return profile;
+        };
+        let base = session_handle.access();
+        let overrides = overrides.clone();
+        Arc::new(ExecutionProfileInner {
+            request_timeout: overrides.request_timeout.unwrap_or(base.request_timeout),
+            consistency: overrides.consistency.unwrap_or(base.consistency),
+            serial_consistency: overrides
+                .serial_consistency
+                .unwrap_or(base.serial_consistency),
+            load_balancing_policy: overrides
+                .load_balancing_policy
+                .unwrap_or_else(|| base.load_balancing_policy.clone()),
+            retry_policy: overrides
+                .retry_policy
+                .unwrap_or_else(|| base.retry_policy.clone()),
+            speculative_execution_policy: overrides
+                .speculative_execution_policy
+                .unwrap_or_else(|| base.speculative_execution_policy.clone()),
+            allow_filtering_guardrail: overrides
+                .allow_filtering_guardrail
+                .unwrap_or(base.allow_filtering_guardrail),
+            user_tags: overrides
+                .user_tags
+                .unwrap_or_else(|| base.user_tags.clone()),
+            overrides: None,
+        })
+    }
+
     /// Creates a builder having all options set to the same as set in the ExecutionProfile pointed by this handle.
     pub fn pointee_to_builder(&self) -> ExecutionProfileBuilder {
         self.0 .0.load().to_builder()
@@ -518,9 +701,70 @@ impl ExecutionProfileHandle {
         ExecutionProfile(self.access())
     }
 
+    /// Returns the label of the handle, see [ExecutionProfile::into_handle_with_label].
+    pub fn label(&self) -> Option<&str> {
+        self.0 .1.as_deref()
+    }
+
     /// Makes the handle point to a new execution profile.
     /// All entities (queries/Session) holding this handle will reflect the change.
     pub fn map_to_another_profile(&mut self, profile: ExecutionProfile) {
         self.0 .0.store(profile.0)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use scylla_cql::frame::types::SerialConsistency;
+    use scylla_cql::Consistency;
+
+    use super::{ExecutionProfile, ExecutionProfileHandle};
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn inheriting_profile() {
+        setup_tracing();
+        let mut session_handle = ExecutionProfile::builder()
+            .consistency(Consistency::One)
+            .serial_consistency(None)
+            .request_timeout(Some(Duration::from_secs(5)))
+            .build()
+            .into_handle();
+        let statement_handle = ExecutionProfile::builder()
+            .consistency(Consistency::All)
+            .build_inheriting()
+            .into_handle_with_label("strong".to_owned());
+
+        let profile = ExecutionProfileHandle::resolve(Some(&statement_handle), &session_handle);
+        assert_eq!(profile.consistency, Consistency::All);
+        assert_eq!(profile.serial_consistency, None);
+        assert_eq!(profile.request_timeout, Some(Duration::from_secs(5)));
+
+        // Changes of the session's profile are followed.
+        session_handle.map_to_another_profile(
+            ExecutionProfile::builder()
+                .serial_consistency(Some(SerialConsistency::Serial))
+                .build(),
+        );
+        let profile = ExecutionProfileHandle::resolve(Some(&statement_handle), &session_handle);
+        assert_eq!(profile.consistency, Consistency::All);
+        assert_eq!(profile.serial_consistency, Some(SerialConsistency::Serial));
+
+        // A profile built with `build` doesn't inherit anything.
+        let statement_handle = ExecutionProfile::builder()
+            .consistency(Consistency::All)
+            .build()
+            .into_handle();
+        let profile = ExecutionProfileHandle::resolve(Some(&statement_handle), &session_handle);
+        assert_eq!(
+            profile.serial_consistency,
+            Some(SerialConsistency::LocalSerial)
+        );
+
+        // Without a profile of the statement, the session's one is used.
+        let profile = ExecutionProfileHandle::resolve(None, &session_handle);
+        assert_eq!(profile.serial_consistency, Some(SerialConsistency::Serial));
+    }
+}
@@ -0,0 +1,155 @@
+//! Cluster health
+//! [`Session::cluster_health`](super::session::Session::cluster_health) returns a snapshot
+//! of the state of the connections to all the nodes known to the session, which can be
+//! used e.g. by the readiness probe of a service: whether the nodes are up, how many
+//! connections their pools have, how many requests are in flight to them, and the last
+//! connection error.
+
+use std::sync::Arc;
+
+use uuid::Uuid;
+
+use super::cluster::ClusterData;
+use super::connection_pool::PoolState;
+use super::node::{Node, NodeAddr};
+
+/// The health of all the nodes known to a session, see
+/// [`Session::cluster_health`](super::session::Session::cluster_health).
+#[derive(Debug, Clone)]
+pub struct ClusterHealth {
+    nodes: Vec<NodeHealth>,
+}
+
+impl ClusterHealth {
+    pub(crate) fn new(cluster_data: &ClusterData) -> Self {
+        Self {
+            nodes: cluster_data
+                .get_nodes_info()
+                .iter()
+                .map(NodeHealth::new)
+                .collect(),
+        }
+    }
+
+    /// Returns the health of each node known to the session.
+    pub fn nodes(&self) -> &[NodeHealth] {
+        &self.nodes
+    }
+
+    /// Returns true if the session can send requests, i.e. any enabled node
+    /// is up and has an open connection.
+    pub fn is_ready(&self) -> bool {
+        self.nodes.iter().any(NodeHealth::is_connected)
+    }
+
+    /// Returns true if any enabled node in the given datacenter is up and has an open connection.
+    pub fn is_datacenter_ready(&self, datacenter: &str) -> bool {
+        self.nodes
+            .iter()
+            .filter(|node| node.datacenter.as_deref() == Some(datacenter))
+            .any(NodeHealth::is_connected)
+    }
+
+    /// Returns the enabled nodes which are down or have no open connections.
+    pub fn unavailable_nodes(&self) -> impl Iterator<Item = &NodeHealth> {
+        self.nodes
+            .iter()
+            .filter(|node| node.pool.is_some() && !node.is_connected())
+    }
+}
+
+/// The health of a single node, see [`ClusterHealth`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub struct NodeHealth {
+    pub host_id: Uuid,
+    pub address: NodeAddr,
+    pub datacenter: Option<String>,
+
+    /// True if the node is marked as down by the cluster.
+    pub is_down: bool,
+
+    /// Number of requests sent to the node and not answered yet.
+    pub in_flight: usize,
+
+    /// The state of the connection pool of the node, or `None` if the node
+    /// is disabled by the host filter.
+    pub pool: Option<PoolState>,
+}
+
+impl NodeHealth {
+    fn new(node: &Arc<Node>) -> Self {
+        Self {
+            host_id: node.host_id,
+            address: node.address,
+            datacenter: node.datacenter.clone(),
+            is_down: node.is_down(),
+            in_flight: node.load().in_flight,
+            pool: node.pool_state(),
+        }
+    }
+
+    /// Returns true if the node is enabled, up, and has an open connection.
+    pub fn is_connected(&self) -> bool {
+        !self.is_down
+            && self
+                .pool
+                .as_ref()
+                .is_some_and(|pool| pool.open_connections > 0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use uuid::Uuid;
+
+    use super::{ClusterHealth, NodeHealth};
+    use crate::test_utils::setup_tracing;
+    use crate::transport::connection_pool::PoolState;
+    use crate::transport::NodeAddr;
+
+    fn node(datacenter: &str, is_down: bool, pool: Option<PoolState>) -> NodeHealth {
+        NodeHealth {
+            host_id: Uuid::new_v4(),
+            address: NodeAddr::Translatable(SocketAddr::from(([127, 0, 0, 1], 9042))),
+            datacenter: Some(datacenter.to_owned()),
+            is_down,
+            in_flight: 0,
+            pool,
+        }
+    }
+
+    fn pool(open_connections: usize) -> Option<PoolState> {
+        Some(PoolState {
+            open_connections,
+            target_connections: 2,
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn readiness() {
+        setup_tracing();
+        let health = ClusterHealth {
+            nodes: vec![
+                node("dc1", false, pool(2)),
+                node("dc1", true, pool(1)),
+                node("dc2", false, pool(0)),
+                // Disabled by the host filter.
+                node("dc3", false, None),
+            ],
+        };
+        assert!(health.is_ready());
+        assert!(health.is_datacenter_ready("dc1"));
+        assert!(!health.is_datacenter_ready("dc2"));
+        assert!(!health.is_datacenter_ready("dc3"));
+        assert_eq!(health.unavailable_nodes().count(), 2);
+
+        let health = ClusterHealth {
+            nodes: vec![node("dc1", true, pool(2)), node("dc2", false, pool(0))],
+        };
+        assert!(!health.is_ready());
+    }
+}
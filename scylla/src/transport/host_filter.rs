@@ -5,7 +5,7 @@
 //! by the [`Session`](crate::transport::session::Session) to determine whether
 //! connections should be opened to a given node or not.
 
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::io::Error;
 use std::net::{SocketAddr, ToSocketAddrs};
 
@@ -82,3 +82,165 @@ impl HostFilter for DcHostFilter {
         peer.datacenter.as_ref() == Some(&self.local_dc)
     }
 }
+
+/// Accepts nodes from any of the given DCs.
+pub struct DcAllowListHostFilter {
+    allowed_dcs: HashSet<String>,
+}
+
+impl DcAllowListHostFilter {
+    /// Creates a new `DcAllowListHostFilter` that accepts nodes only from
+    /// the DCs in `allowed_dcs`.
+    pub fn new(allowed_dcs: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        Self {
+            allowed_dcs: allowed_dcs.into_iter().map(Into::into).collect(),
+        }
+    }
+}
+
+impl HostFilter for DcAllowListHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        peer.datacenter
+            .as_ref()
+            .is_some_and(|dc| self.allowed_dcs.contains(dc))
+    }
+}
+
+/// Accepts nodes from any of the given racks. Racks are identified by
+/// the names of both their DC and themselves, as rack names are often
+/// repeated across DCs.
+pub struct RackAllowListHostFilter {
+    // Maps DCs to their allowed racks.
+    allowed_racks: HashMap<String, HashSet<String>>,
+}
+
+impl RackAllowListHostFilter {
+    /// Creates a new `RackAllowListHostFilter` that accepts nodes only from
+    /// the racks in `allowed_racks`, given as `(dc, rack)` pairs.
+    pub fn new<D, R>(allowed_racks: impl IntoIterator<Item = (D, R)>) -> Self
+    where
+        D: Into<String>,
+        R: Into<String>,
+    {
+        let mut racks_by_dc: HashMap<String, HashSet<String>> = HashMap::new();
+        for (dc, rack) in allowed_racks {
+            racks_by_dc
+                .entry(dc.into())
+                .or_default()
+                .insert(rack.into());
+        }
+        Self {
+            allowed_racks: racks_by_dc,
+        }
+    }
+}
+
+impl HostFilter for RackAllowListHostFilter {
+    fn accept(&self, peer: &Peer) -> bool {
+        match (&peer.datacenter, &peer.rack) {
+            (Some(dc), Some(rack)) => self
+                .allowed_racks
+                .get(dc)
+                .is_some_and(|racks| racks.contains(rack)),
+            _ => false,
+        }
+    }
+}
+
+/// Accepts nodes whose addresses satisfy the predicate provided
+/// during filter's construction.
+pub struct AddressPredicateHostFilter<F> {
+    predicate: F,
+}
+
+impl<F> AddressPredicateHostFilter<F>
+where
+    F: Fn(SocketAddr) -> bool + Send + Sync,
+{
+    /// Creates a new `AddressPredicateHostFilter` which accepts the nodes
+    /// whose addresses (before address translation) satisfy `predicate`.
+    pub fn new(predicate: F) -> Self {
+        Self { predicate }
+    }
+}
+
+impl<F> HostFilter for AddressPredicateHostFilter<F>
+where
+    F: Fn(SocketAddr) -> bool + Send + Sync,
+{
+    fn accept(&self, peer: &Peer) -> bool {
+        match peer.address {
+            super::NodeAddr::Translatable(addr) => (self.predicate)(addr),
+            // Like in `AllowListHostFilter`, such nodes were either contact points,
+            // or were already accepted by the filter.
+            super::NodeAddr::Untranslatable(_) => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+
+    use uuid::Uuid;
+
+    use super::{
+        AddressPredicateHostFilter, AllowListHostFilter, DcAllowListHostFilter, HostFilter,
+        RackAllowListHostFilter,
+    };
+    use crate::test_utils::setup_tracing;
+    use crate::transport::topology::Peer;
+    use crate::transport::NodeAddr;
+
+    fn peer(address: &str, dc: Option<&str>, rack: Option<&str>) -> Peer {
+        Peer {
+            host_id: Uuid::new_v4(),
+            address: NodeAddr::Translatable(address.parse().unwrap()),
+            tokens: Vec::new(),
+            datacenter: dc.map(String::from),
+            rack: rack.map(String::from),
+        }
+    }
+
+    #[test]
+    fn dc_allow_list() {
+        setup_tracing();
+        let filter = DcAllowListHostFilter::new(["dc1", "dc2"]);
+        assert!(filter.accept(&peer("10.0.0.1:9042", Some("dc1"), None)));
+        assert!(filter.accept(&peer("10.0.0.2:9042", Some("dc2"), None)));
+        assert!(!filter.accept(&peer("10.0.0.3:9042", Some("dc3"), None)));
+        assert!(!filter.accept(&peer("10.0.0.4:9042", None, None)));
+    }
+
+    #[test]
+    fn rack_allow_list() {
+        setup_tracing();
+        let filter = RackAllowListHostFilter::new([("dc1", "rack1"), ("dc2", "rack2")]);
+        assert!(filter.accept(&peer("10.0.0.1:9042", Some("dc1"), Some("rack1"))));
+        assert!(filter.accept(&peer("10.0.0.2:9042", Some("dc2"), Some("rack2"))));
+        assert!(!filter.accept(&peer("10.0.0.3:9042", Some("dc2"), Some("rack1"))));
+        assert!(!filter.accept(&peer("10.0.0.4:9042", Some("dc1"), None)));
+    }
+
+    #[test]
+    fn address_filters() {
+        setup_tracing();
+        let allow_list = AllowListHostFilter::new(["10.0.0.1:9042"]).unwrap();
+        let predicate = AddressPredicateHostFilter::new(|addr: SocketAddr| addr.ip().is_loopback());
+        for (address, allowed_by_list, allowed_by_predicate) in [
+            ("10.0.0.1:9042", true, false),
+            ("127.0.0.1:9042", false, true),
+            ("10.0.0.2:9042", false, false),
+        ] {
+            let peer = peer(address, None, None);
+            assert_eq!(allow_list.accept(&peer), allowed_by_list);
+            assert_eq!(predicate.accept(&peer), allowed_by_predicate);
+        }
+
+        // Contact points are always accepted.
+        let mut contact_point = peer("10.0.0.2:9042", None, None);
+        contact_point.address = NodeAddr::Untranslatable(contact_point.address.into_inner());
+        assert!(allow_list.accept(&contact_point));
+        assert!(predicate.accept(&contact_point));
+    }
+}
@@ -1,5 +1,6 @@
 //! Iterators over rows returned by paged queries
 
+use std::borrow::Cow;
 use std::future::Future;
 use std::net::SocketAddr;
 use std::ops::ControlFlow;
@@ -17,11 +18,13 @@ use scylla_cql::types::deserialize::{DeserializationError, TypeCheckError};
 use scylla_cql::types::serialize::row::SerializedValues;
 use std::result::Result;
 use thiserror::Error;
-use tokio::sync::mpsc;
+use tokio::sync::{mpsc, OwnedSemaphorePermit, Semaphore};
 
+use super::attempt_span::{self, AttemptSpanStatement};
 use super::execution_profile::ExecutionProfileInner;
 use super::query_result::ColumnSpecs;
 use super::session::RequestSpan;
+use super::user_tags::UserTags;
 #[allow(deprecated)]
 use crate::cql_to_rust::{FromRow, FromRowError};
 
@@ -31,14 +34,20 @@ use crate::frame::response::{
     result::{ColumnSpec, Row},
 };
 use crate::history::{self, HistoryListener};
+use crate::routing::Shard;
 use crate::statement::{prepared_statement::PreparedStatement, query::Query};
-use crate::statement::{Consistency, PagingState, SerialConsistency};
+use crate::statement::{
+    AdaptivePageSize, Consistency, PagePrefetch, PageSize, PagingState, ResumablePaging,
+    SerialConsistency,
+};
 use crate::transport::cluster::ClusterData;
 use crate::transport::connection::{Connection, NonErrorQueryResponse, QueryResponse};
-use crate::transport::errors::{ProtocolError, QueryError, UserRequestError};
+use crate::transport::errors::{BadQuery, DbError, ProtocolError, QueryError, UserRequestError};
 use crate::transport::load_balancing::{self, RoutingInfo};
 use crate::transport::metrics::Metrics;
+use crate::transport::metrics_export;
 use crate::transport::retry_policy::{QueryInfo, RetryDecision, RetrySession};
+use crate::transport::throttler::{RequestThrottler, ThrottlerPermit};
 use crate::transport::NodeRef;
 use tracing::{trace, trace_span, warn, Instrument};
 use uuid::Uuid;
@@ -60,6 +69,48 @@ macro_rules! ready_some_ok {
 struct ReceivedPage {
     rows: RawMetadataAndRawRows,
     tracing_id: Option<Uuid>,
+    // Held until the page is processed, if the bytes of prefetched pages are limited.
+    budget_permit: Option<OwnedSemaphorePermit>,
+}
+
+// Limits the bytes of the pages fetched ahead of the page being processed,
+// see `PagePrefetch::with_max_bytes`.
+struct PageBudget {
+    semaphore: Arc<Semaphore>,
+    max_bytes: u32,
+}
+
+impl PageBudget {
+    fn new(max_bytes: usize) -> Self {
+        let max_bytes = max_bytes.min(Semaphore::MAX_PERMITS).min(u32::MAX as usize) as u32;
+        Self {
+            semaphore: Arc::new(Semaphore::new(max_bytes as usize)),
+            max_bytes,
+        }
+    }
+
+    // Waits until the page fits in the budget. A page larger than the whole budget
+    // takes all of it, so it waits until all the previous pages are processed.
+    async fn acquire(&self, bytes: usize) -> OwnedSemaphorePermit {
+        let permits = bytes.min(self.max_bytes as usize) as u32;
+        self.semaphore
+            .clone()
+            .acquire_many_owned(permits)
+            .await
+            .expect("The page budget semaphore is never closed")
+    }
+}
+
+type PageSender = mpsc::Sender<Result<ReceivedPage, QueryError>>;
+type PageReceiver = mpsc::Receiver<Result<ReceivedPage, QueryError>>;
+
+// Creates the channel through which the pages are sent to QueryPager, bounded
+// according to the prefetching configuration of the statement.
+fn page_channel(prefetch: Option<PagePrefetch>) -> (PageSender, PageReceiver, Option<PageBudget>) {
+    let prefetch = prefetch.unwrap_or_default();
+    let (sender, receiver) = mpsc::channel(prefetch.get_max_pages());
+    let budget = prefetch.get_max_bytes().map(PageBudget::new);
+    (sender, receiver, budget)
 }
 
 pub(crate) struct PreparedIteratorConfig {
@@ -68,6 +119,8 @@ pub(crate) struct PreparedIteratorConfig {
     pub(crate) execution_profile: Arc<ExecutionProfileInner>,
     pub(crate) cluster_data: Arc<ClusterData>,
     pub(crate) metrics: Arc<Metrics>,
+    pub(crate) request_throttler: Option<Arc<dyn RequestThrottler>>,
+    pub(crate) attempt_spans: bool,
 }
 
 // A separate module is used here so that the parent module cannot construct
@@ -118,6 +171,7 @@ mod checked_channel_sender {
             let empty_page = ReceivedPage {
                 rows: RawMetadataAndRawRows::mock_empty(),
                 tracing_id,
+                budget_permit: None,
             };
             self.send(Ok(empty_page)).await
         }
@@ -134,17 +188,31 @@ struct PagerWorker<'a, QueryFunc, SpanCreatorFunc> {
     sender: ProvingSender<Result<ReceivedPage, QueryError>>,
 
     // Closure used to perform a single page query
-    // AsyncFn(Arc<Connection>, Option<Arc<[u8]>>) -> Result<QueryResponse, UserRequestError>
+    // AsyncFn(Arc<Connection>, Consistency, PagingState, PageSize) -> Result<QueryResponse, UserRequestError>
     page_query: QueryFunc,
 
     statement_info: RoutingInfo<'a>,
     query_is_idempotent: bool,
     query_consistency: Consistency,
+    // Whether the statement can only be executed on Scylla, e.g. because of USING TIMEOUT.
+    requires_scylla: bool,
     retry_session: Box<dyn RetrySession>,
     execution_profile: Arc<ExecutionProfileInner>,
     metrics: Arc<Metrics>,
+    request_throttler: Option<Arc<dyn RequestThrottler>>,
+    // Held while the current page is fetched, across all its retries.
+    throttler_permit: Option<ThrottlerPermit>,
 
     paging_state: PagingState,
+    page_size: PageSize,
+    adaptive_page_size: Option<AdaptivePageSize>,
+    page_budget: Option<PageBudget>,
+
+    resumable_paging: Option<ResumablePaging>,
+    consecutive_resumptions: u32,
+
+    // Number of the attempt to fetch the current page, 0 for the first one.
+    page_attempt: usize,
 
     history_listener: Option<Arc<dyn HistoryListener>>,
     current_query_id: Option<history::QueryId>,
@@ -156,22 +224,65 @@ struct PagerWorker<'a, QueryFunc, SpanCreatorFunc> {
 
 impl<QueryFunc, QueryFut, SpanCreator> PagerWorker<'_, QueryFunc, SpanCreator>
 where
-    QueryFunc: Fn(Arc<Connection>, Consistency, PagingState) -> QueryFut,
+    QueryFunc: Fn(Arc<Connection>, Consistency, PagingState, PageSize) -> QueryFut,
     QueryFut: Future<Output = Result<QueryResponse, UserRequestError>>,
     SpanCreator: Fn() -> RequestSpan,
 {
     // Contract: this function MUST send at least one item through self.sender
     async fn work(mut self, cluster_data: Arc<ClusterData>) -> PageSendAttemptedProof {
+        self.log_query_start();
+
+        let last_error = loop {
+            let error = match self.work_with_plan(&cluster_data).await {
+                Ok(proof) => return proof,
+                Err(error) => error,
+            };
+            match self.resumable_paging {
+                Some(resumable_paging)
+                    if self.consecutive_resumptions < resumable_paging.get_max_resumptions()
+                        && is_transient(&error) =>
+                {
+                    let delay = resumable_paging
+                        .get_delay()
+                        .saturating_mul(2_u32.saturating_pow(self.consecutive_resumptions));
+                    self.consecutive_resumptions += 1;
+                    warn!(
+                        error = %error,
+                        "Fetching a page failed, resuming paging in {:?}", delay
+                    );
+                    tokio::time::sleep(delay).await;
+                    // The paging state of the last received page is kept,
+                    // so paging continues where it stopped.
+                    self.retry_session.reset();
+                }
+                _ => break error,
+            }
+        };
+
+        // Send last_error to QueryPager - query failed fully
+        self.log_query_error(&last_error);
+        let (proof, _) = self.sender.send(Err(last_error)).await;
+        proof
+    }
+
+    // Fetches the remaining pages using a single query plan, until the plan is exhausted
+    // or the retry policy decides not to retry.
+    //
+    // Contract: this function must either:
+    // - Return an error
+    // - Return Ok but have attempted to send a page via self.sender
+    async fn work_with_plan(
+        &mut self,
+        cluster_data: &Arc<ClusterData>,
+    ) -> Result<PageSendAttemptedProof, QueryError> {
         let load_balancer = self.execution_profile.load_balancing_policy.clone();
         let statement_info = self.statement_info.clone();
         let query_plan =
-            load_balancing::Plan::new(load_balancer.as_ref(), &statement_info, &cluster_data);
+            load_balancing::Plan::new(load_balancer.as_ref(), &statement_info, cluster_data);
 
         let mut last_error: QueryError = QueryError::EmptyPlan;
         let mut current_consistency: Consistency = self.query_consistency;
 
-        self.log_query_start();
-
         'nodes_in_plan: for (node, shard) in query_plan {
             let span =
                 trace_span!(parent: &self.parent_span, "Executing query", node = %node.address);
@@ -204,12 +315,18 @@ where
                     .await;
 
                 last_error = match queries_result {
+                    // Rejected by the throttler, or the node is not Scylla,
+                    // there is nothing to retry.
+                    Err(
+                        error @ (QueryError::Overloaded(_)
+                        | QueryError::BadQuery(BadQuery::ServerTimeoutNotSupported)),
+                    ) => return Err(error),
                     Ok(proof) => {
                         trace!(parent: &span, "Query succeeded");
                         // query_pages returned Ok, so we are guaranteed
                         // that it attempted to send at least one page
                         // through self.sender and we can safely return now.
-                        return proof;
+                        return Ok(proof);
                     }
                     Err(error) => {
                         trace!(
@@ -237,11 +354,15 @@ where
                 match retry_decision {
                     RetryDecision::RetrySameNode(cl) => {
                         self.metrics.inc_retries_num();
+                        metrics_export::record_retry(node, &self.user_tags());
+                        self.page_attempt += 1;
                         current_consistency = cl.unwrap_or(current_consistency);
                         continue 'same_node_retries;
                     }
                     RetryDecision::RetryNextNode(cl) => {
                         self.metrics.inc_retries_num();
+                        metrics_export::record_retry(node, &self.user_tags());
+                        self.page_attempt += 1;
                         current_consistency = cl.unwrap_or(current_consistency);
                         continue 'nodes_in_plan;
                     }
@@ -255,16 +376,13 @@ where
                         // we must attempt to send something because
                         // the iterator expects it.
                         let (proof, _) = self.sender.send_empty_page(None).await;
-                        return proof;
+                        return Ok(proof);
                     }
                 };
             }
         }
 
-        // Send last_error to QueryPager - query failed fully
-        self.log_query_error(&last_error);
-        let (proof, _) = self.sender.send(Err(last_error)).await;
-        proof
+        Err(last_error)
     }
 
     // Given a working connection query as many pages as possible until the first error.
@@ -278,8 +396,17 @@ where
         consistency: Consistency,
         node: NodeRef<'_>,
     ) -> Result<PageSendAttemptedProof, QueryError> {
+        if self.requires_scylla && !connection.is_to_scylla() {
+            return Err(BadQuery::ServerTimeoutNotSupported.into());
+        }
         loop {
+            if self.throttler_permit.is_none() {
+                if let Some(throttler) = &self.request_throttler {
+                    self.throttler_permit = Some(throttler.acquire().await?);
+                }
+            }
             let request_span = (self.span_creator)();
+            request_span.record_user_tags(&self.user_tags());
             match self
                 .query_one_page(connection, consistency, node, &request_span)
                 .instrument(request_span.span().clone())
@@ -307,10 +434,23 @@ where
         );
         self.log_attempt_start(connection.get_connect_address());
 
-        let query_response =
-            (self.page_query)(connection.clone(), consistency, self.paging_state.clone())
-                .await
-                .and_then(QueryResponse::into_non_error_query_response);
+        let connection_shard = connection
+            .get_shard_info()
+            .as_ref()
+            .map(|info| info.shard as Shard);
+        let attempt_span =
+            request_span.attempt_span(node, connection_shard, consistency, self.page_attempt, 0);
+
+        let in_flight_request = node.start_request(connection);
+        let query_response = (self.page_query)(
+            connection.clone(),
+            consistency,
+            self.paging_state.clone(),
+            self.page_size,
+        )
+        .instrument(attempt_span.clone())
+        .await
+        .and_then(QueryResponse::into_non_error_query_response);
 
         let elapsed = query_start.elapsed();
 
@@ -323,7 +463,17 @@ where
                 tracing_id,
                 ..
             }) => {
-                let _ = self.metrics.log_query_latency(elapsed.as_millis() as u64);
+                self.throttler_permit = None;
+                in_flight_request.succeeded();
+                attempt_span::record_attempt_result(&attempt_span, None);
+                self.metrics.log_query_latency(elapsed);
+                metrics_export::record_request_success(
+                    node,
+                    connection_shard,
+                    &self.user_tags(),
+                    elapsed,
+                );
+                self.page_attempt = 0;
                 self.log_attempt_success();
                 self.log_query_success();
                 self.execution_profile
@@ -332,7 +482,31 @@ where
 
                 request_span.record_raw_rows_fields(&rows);
 
-                let received_page = ReceivedPage { rows, tracing_id };
+                if let Some(adaptive_page_size) = self.adaptive_page_size {
+                    let page_size = adaptive_page_size.next_page_size(
+                        self.page_size,
+                        rows.metadata_and_rows_bytes_size(),
+                        elapsed,
+                    );
+                    if page_size != self.page_size {
+                        trace!(
+                            old_page_size = self.page_size.inner(),
+                            new_page_size = page_size.inner(),
+                            "Adjusting page size"
+                        );
+                        self.page_size = page_size;
+                    }
+                }
+
+                let budget_permit = match &self.page_budget {
+                    Some(budget) => Some(budget.acquire(rows.metadata_and_rows_bytes_size()).await),
+                    None => None,
+                };
+                let received_page = ReceivedPage {
+                    rows,
+                    tracing_id,
+                    budget_permit,
+                };
 
                 // Send next page to QueryPager
                 let (proof, res) = self.sender.send(Ok(received_page)).await;
@@ -353,13 +527,21 @@ where
 
                 // Query succeeded, reset retry policy for future retries
                 self.retry_session.reset();
+                self.consecutive_resumptions = 0;
                 self.log_query_start();
 
                 Ok(ControlFlow::Continue(()))
             }
             Err(err) => {
                 let err = err.into();
+                attempt_span::record_attempt_result(&attempt_span, Some(&err));
                 self.metrics.inc_failed_paged_queries();
+                metrics_export::record_request_failure(
+                    node,
+                    connection_shard,
+                    &self.user_tags(),
+                    &err,
+                );
                 self.execution_profile
                     .load_balancing_policy
                     .on_query_failure(&self.statement_info, elapsed, node, &err);
@@ -372,6 +554,7 @@ where
             }) => {
                 // We have most probably sent a modification statement (e.g. INSERT or UPDATE),
                 // so let's return an empty iterator as suggested in #631.
+                attempt_span::record_attempt_result(&attempt_span, None);
 
                 // We must attempt to send something because the iterator expects it.
                 let (proof, _) = self.sender.send_empty_page(tracing_id).await;
@@ -381,6 +564,13 @@ where
                 self.metrics.inc_failed_paged_queries();
                 let err =
                     ProtocolError::UnexpectedResponse(response.response.to_response_kind()).into();
+                attempt_span::record_attempt_result(&attempt_span, Some(&err));
+                metrics_export::record_request_failure(
+                    node,
+                    connection_shard,
+                    &self.user_tags(),
+                    &err,
+                );
                 self.execution_profile
                     .load_balancing_policy
                     .on_query_failure(&self.statement_info, elapsed, node, &err);
@@ -389,6 +579,13 @@ where
         }
     }
 
+    // The tags of the session merged with the tags of the execution profile.
+    fn user_tags(&self) -> Cow<'_, UserTags> {
+        self.metrics
+            .user_tags()
+            .merged_with(&self.execution_profile.user_tags)
+    }
+
     fn log_query_start(&mut self) {
         let history_listener: &dyn HistoryListener = match &self.history_listener {
             Some(hl) => &**hl,
@@ -470,6 +667,28 @@ where
     }
 }
 
+// Whether fetching a page may succeed when attempted again later,
+// e.g. once the node is no longer overloaded.
+fn is_transient(error: &QueryError) -> bool {
+    matches!(
+        error,
+        QueryError::BrokenConnection(_)
+            | QueryError::ConnectionPoolError(_)
+            | QueryError::UnableToAllocStreamId
+            | QueryError::RequestTimeout(_)
+            | QueryError::DbError(
+                DbError::Unavailable { .. }
+                    | DbError::Overloaded
+                    | DbError::IsBootstrapping
+                    | DbError::ReadTimeout { .. }
+                    | DbError::ReadFailure { .. }
+                    | DbError::ServerError
+                    | DbError::RateLimitReached { .. },
+                _
+            )
+    )
+}
+
 /// A massively simplified version of the PagerWorker. It does not have
 /// any complicated logic related to retries, it just fetches pages from
 /// a single connection.
@@ -505,6 +724,7 @@ where
                         .send(Ok(ReceivedPage {
                             rows,
                             tracing_id: response.tracing_id,
+                            budget_permit: None,
                         }))
                         .await;
 
@@ -557,6 +777,7 @@ where
 /// [Row] is not the intended target type.
 pub struct QueryPager {
     current_page: RawRowLendingIterator,
+    current_page_budget_permit: Option<OwnedSemaphorePermit>,
     page_receiver: mpsc::Receiver<Result<ReceivedPage, QueryError>>,
     tracing_ids: Vec<Uuid>,
 }
@@ -622,6 +843,9 @@ impl QueryPager {
     ) -> Poll<Option<Result<(), QueryError>>> {
         let mut s = self.as_mut();
 
+        // The current page is exhausted, so it no longer takes the budget
+        // of prefetched pages. Releasing it lets the worker send the next page.
+        s.current_page_budget_permit = None;
         let received_page = ready_some_ok!(Pin::new(&mut s.page_receiver).poll_recv(cx));
 
         // TODO: see my other comment next to QueryError::NextRowError
@@ -632,6 +856,7 @@ impl QueryPager {
                 NextRowError::NextPageError(NextPageError::ResultMetadataParseError(err))
             })?;
         s.current_page = RawRowLendingIterator::new(raw_rows_with_deserialized_metadata);
+        s.current_page_budget_permit = received_page.budget_permit;
 
         if let Some(tracing_id) = received_page.tracing_id {
             s.tracing_ids.push(tracing_id);
@@ -681,8 +906,10 @@ impl QueryPager {
         execution_profile: Arc<ExecutionProfileInner>,
         cluster_data: Arc<ClusterData>,
         metrics: Arc<Metrics>,
+        request_throttler: Option<Arc<dyn RequestThrottler>>,
+        attempt_spans: bool,
     ) -> Result<Self, QueryError> {
-        let (sender, receiver) = mpsc::channel(1);
+        let (sender, receiver, page_budget) = page_channel(query.config.page_prefetch);
 
         let consistency = query
             .config
@@ -693,7 +920,10 @@ impl QueryPager {
             .serial_consistency
             .unwrap_or(execution_profile.serial_consistency);
 
-        let page_size = query.get_validated_page_size();
+        let adaptive_page_size = query.config.adaptive_page_size;
+        let page_size = adaptive_page_size.map_or(query.get_validated_page_size(), |adaptive| {
+            adaptive.clamp(query.get_validated_page_size())
+        });
 
         let routing_info = RoutingInfo {
             consistency,
@@ -713,7 +943,8 @@ impl QueryPager {
 
             let page_query = |connection: Arc<Connection>,
                               consistency: Consistency,
-                              paging_state: PagingState| {
+                              paging_state: PagingState,
+                              page_size: PageSize| {
                 async move {
                     connection
                         .query_raw_with_consistency(
@@ -729,8 +960,11 @@ impl QueryPager {
 
             let query_ref = &query;
 
+            let attempt_span_statement =
+                attempt_spans.then(|| AttemptSpanStatement::new(&query_ref.contents, None));
             let span_creator = move || {
-                let span = RequestSpan::new_query(&query_ref.contents);
+                let span = RequestSpan::new_query(&query_ref.contents)
+                    .with_attempt_spans(attempt_span_statement.clone());
                 span.record_request_size(0);
                 span
             };
@@ -741,10 +975,19 @@ impl QueryPager {
                 statement_info: routing_info,
                 query_is_idempotent: query.config.is_idempotent,
                 query_consistency: consistency,
+                requires_scylla: query.config.server_timeout.is_some(),
                 retry_session,
                 execution_profile,
                 metrics,
+                request_throttler,
+                throttler_permit: None,
                 paging_state: PagingState::start(),
+                page_size,
+                adaptive_page_size,
+                page_budget,
+                resumable_paging: query.config.resumable_paging,
+                consecutive_resumptions: 0,
+                page_attempt: 0,
                 history_listener: query.config.history_listener.clone(),
                 current_query_id: None,
                 current_attempt_id: None,
@@ -761,7 +1004,7 @@ impl QueryPager {
     pub(crate) async fn new_for_prepared_statement(
         config: PreparedIteratorConfig,
     ) -> Result<Self, QueryError> {
-        let (sender, receiver) = mpsc::channel(1);
+        let (sender, receiver, page_budget) = page_channel(config.prepared.config.page_prefetch);
 
         let consistency = config
             .prepared
@@ -774,7 +1017,11 @@ impl QueryPager {
             .serial_consistency
             .unwrap_or(config.execution_profile.serial_consistency);
 
-        let page_size = config.prepared.get_validated_page_size();
+        let adaptive_page_size = config.prepared.config.adaptive_page_size;
+        let page_size = adaptive_page_size
+            .map_or(config.prepared.get_validated_page_size(), |adaptive| {
+                adaptive.clamp(config.prepared.get_validated_page_size())
+            });
 
         let retry_session = config
             .prepared
@@ -811,13 +1058,15 @@ impl QueryPager {
 
             let page_query = |connection: Arc<Connection>,
                               consistency: Consistency,
-                              paging_state: PagingState| async move {
+                              paging_state: PagingState,
+                              page_size: PageSize| async move {
                 connection
                     .execute_raw_with_consistency(
                         prepared_ref,
                         values_ref,
                         consistency,
                         serial_consistency,
+                        prepared_ref.get_timestamp(),
                         Some(page_size),
                         paging_state,
                     )
@@ -841,12 +1090,16 @@ impl QueryPager {
                     None
                 };
 
+            let attempt_span_statement = config
+                .attempt_spans
+                .then(|| AttemptSpanStatement::new(prepared_ref.get_statement(), table_spec));
             let span_creator = move || {
                 let span = RequestSpan::new_prepared(
                     partition_key.as_ref().map(|pk| pk.iter()),
                     token,
                     serialized_values_size,
-                );
+                )
+                .with_attempt_spans(attempt_span_statement.clone());
                 if let Some(replicas) = replicas.as_ref() {
                     span.record_replicas(replicas);
                 }
@@ -859,10 +1112,19 @@ impl QueryPager {
                 statement_info,
                 query_is_idempotent: config.prepared.config.is_idempotent,
                 query_consistency: consistency,
+                requires_scylla: config.prepared.config.server_timeout.is_some(),
                 retry_session,
                 execution_profile: config.execution_profile,
                 metrics: config.metrics,
+                request_throttler: config.request_throttler,
+                throttler_permit: None,
                 paging_state: PagingState::start(),
+                page_size,
+                adaptive_page_size,
+                page_budget,
+                resumable_paging: config.prepared.config.resumable_paging,
+                consecutive_resumptions: 0,
+                page_attempt: 0,
                 history_listener: config.prepared.config.history_listener.clone(),
                 current_query_id: None,
                 current_attempt_id: None,
@@ -925,6 +1187,7 @@ impl QueryPager {
                         &values,
                         consistency,
                         serial_consistency,
+                        prepared.get_timestamp(),
                         Some(page_size),
                         paging_state,
                     )
@@ -955,6 +1218,7 @@ impl QueryPager {
 
         Ok(Self {
             current_page: RawRowLendingIterator::new(raw_rows_with_deserialized_metadata),
+            current_page_budget_permit: page_received.budget_permit,
             page_receiver: receiver,
             tracing_ids: if let Some(tracing_id) = page_received.tracing_id {
                 vec![tracing_id]
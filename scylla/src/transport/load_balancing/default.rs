@@ -121,6 +121,14 @@ pub struct DefaultPolicy {
     /// If no preferred DC is set, this has no effect.
     permit_dc_failover: bool,
 
+    /// Whether statements with LOCAL_* consistencies may be sent to remote nodes, too.
+    /// If DC failover is not permitted, this has no effect.
+    permit_dc_failover_for_local_consistency: bool,
+
+    /// The maximal number of non-replica nodes of each remote DC in plans.
+    /// If DC failover is not permitted, this has no effect.
+    max_remote_nodes_per_dc: Option<usize>,
+
     /// A predicate that a target (node + shard) must satisfy in order to be picked.
     /// This was introduced to make latency awareness cleaner.
     /// - if latency awareness is disabled, then `pick_predicate` is just `Self::is_alive()`;
@@ -150,6 +158,11 @@ impl fmt::Debug for DefaultPolicy {
             .field("preferences", &self.preferences)
             .field("is_token_aware", &self.is_token_aware)
             .field("permit_dc_failover", &self.permit_dc_failover)
+            .field(
+                "permit_dc_failover_for_local_consistency",
+                &self.permit_dc_failover_for_local_consistency,
+            )
+            .field("max_remote_nodes_per_dc", &self.max_remote_nodes_per_dc)
             .field("latency_awareness", &self.latency_awareness)
             .field("fixed_seed", &self.fixed_seed)
             .finish_non_exhaustive()
@@ -298,8 +311,12 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
         let all_nodes = cluster.replica_locator().unique_nodes_in_global_ring();
         // If a datacenter failover is possible, loosen restriction about locality.
         if self.is_datacenter_failover_possible(&routing_info) {
-            let maybe_remote_node_picked =
-                self.pick_node(all_nodes, |node| (self.pick_predicate)(node, None));
+            let maybe_remote_node_picked = self
+                .limit_remote_nodes(
+                    self.round_robin_nodes(all_nodes, |node| (self.pick_predicate)(node, None)),
+                    cluster,
+                )
+                .next();
             if let Some(alive_maybe_remote_node) = maybe_remote_node_picked {
                 return Some((alive_maybe_remote_node, None));
             }
@@ -316,8 +333,12 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
 
         // If a datacenter failover is possible, loosen restriction about locality.
         if self.is_datacenter_failover_possible(&routing_info) {
-            let maybe_down_maybe_remote_node_picked =
-                self.pick_node(all_nodes, |node| node.is_enabled());
+            let maybe_down_maybe_remote_node_picked = self
+                .limit_remote_nodes(
+                    self.round_robin_nodes(all_nodes, |node| node.is_enabled()),
+                    cluster,
+                )
+                .next();
             if let Some(down_but_enabled_maybe_remote_node) = maybe_down_maybe_remote_node_picked {
                 return Some((down_but_enabled_maybe_remote_node, None));
             }
@@ -452,8 +473,10 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
 
         // If a datacenter failover is possible, loosen restriction about locality.
         let maybe_remote_nodes = if self.is_datacenter_failover_possible(&routing_info) {
-            let robinned_all_nodes =
-                self.round_robin_nodes(all_nodes, |node| Self::is_alive(node, None));
+            let robinned_all_nodes = self.limit_remote_nodes(
+                self.round_robin_nodes(all_nodes, |node| Self::is_alive(node, None)),
+                cluster,
+            );
 
             Either::Left(robinned_all_nodes.map(|node| (node, None)))
         } else {
@@ -469,9 +492,7 @@ or refrain from preferring datacenters (which may ban all other datacenters, if
         // If a datacenter failover is possible, loosen restriction about locality.
         let maybe_down_nodes = if self.is_datacenter_failover_possible(&routing_info) {
             Either::Left(
-                all_nodes
-                    .iter()
-                    .filter(|node| node.is_enabled())
+                self.limit_remote_nodes(all_nodes.iter().filter(|node| node.is_enabled()), cluster)
                     .map(|node| (node, None)),
             )
         } else {
@@ -877,6 +898,36 @@ impl DefaultPolicy {
         Self::randomly_rotated_nodes(nodes).filter(move |node| predicate(node))
     }
 
+    /// Wraps a given iterator of nodes by skipping the nodes of remote datacenters
+    /// which are not among the first `max_remote_nodes_per_dc` nodes of their datacenter.
+    fn limit_remote_nodes<'a>(
+        &'a self,
+        nodes: impl Iterator<Item = NodeRef<'a>>,
+        cluster: &'a ClusterData,
+    ) -> impl Iterator<Item = NodeRef<'a>> {
+        nodes.filter(move |node| {
+            let Some(max_remote_nodes) = self.max_remote_nodes_per_dc else {
+                return true;
+            };
+            let datacenter = node.datacenter.as_deref();
+            if datacenter == self.preferences.datacenter() {
+                return true;
+            }
+            let datacenter_nodes = match datacenter {
+                Some(datacenter) => cluster
+                    .replica_locator()
+                    .unique_nodes_in_datacenter_ring(datacenter)
+                    .unwrap_or(&[]),
+                // Nodes without a known datacenter don't form any datacenter ring.
+                None => return max_remote_nodes > 0,
+            };
+            datacenter_nodes
+                .iter()
+                .take(max_remote_nodes)
+                .any(|used_node| used_node.host_id == node.host_id)
+        })
+    }
+
     /// Wraps a given iterator by shuffling its contents.
     fn shuffle<'a>(
         &self,
@@ -905,7 +956,7 @@ impl DefaultPolicy {
     fn is_datacenter_failover_possible(&self, routing_info: &ProcessedRoutingInfo) -> bool {
         self.preferences.datacenter().is_some()
             && self.permit_dc_failover
-            && !routing_info.local_consistency
+            && (!routing_info.local_consistency || self.permit_dc_failover_for_local_consistency)
     }
 }
 
@@ -915,6 +966,8 @@ impl Default for DefaultPolicy {
             preferences: NodeLocationPreference::Any,
             is_token_aware: true,
             permit_dc_failover: false,
+            permit_dc_failover_for_local_consistency: false,
+            max_remote_nodes_per_dc: None,
             pick_predicate: Box::new(Self::is_alive),
             latency_awareness: None,
             fixed_seed: None,
@@ -941,6 +994,8 @@ pub struct DefaultPolicyBuilder {
     preferences: NodeLocationPreference,
     is_token_aware: bool,
     permit_dc_failover: bool,
+    permit_dc_failover_for_local_consistency: bool,
+    max_remote_nodes_per_dc: Option<usize>,
     latency_awareness: Option<LatencyAwarenessBuilder>,
     enable_replica_shuffle: bool,
 }
@@ -952,6 +1007,8 @@ impl DefaultPolicyBuilder {
             preferences: NodeLocationPreference::Any,
             is_token_aware: true,
             permit_dc_failover: false,
+            permit_dc_failover_for_local_consistency: false,
+            max_remote_nodes_per_dc: None,
             latency_awareness: None,
             enable_replica_shuffle: true,
         }
@@ -974,6 +1031,8 @@ impl DefaultPolicyBuilder {
             preferences: self.preferences,
             is_token_aware: self.is_token_aware,
             permit_dc_failover: self.permit_dc_failover,
+            permit_dc_failover_for_local_consistency: self.permit_dc_failover_for_local_consistency,
+            max_remote_nodes_per_dc: self.max_remote_nodes_per_dc,
             pick_predicate,
             latency_awareness,
             fixed_seed: (!self.enable_replica_shuffle).then(|| {
@@ -1073,6 +1132,29 @@ impl DefaultPolicyBuilder {
         self
     }
 
+    /// Sets whether statements with a LOCAL_ONE, LOCAL_QUORUM or LOCAL_SERIAL consistency
+    /// may be sent to remote nodes when datacenter failover is permitted.
+    ///
+    /// This is disabled by default, because such statements are then executed
+    /// with a local consistency of the remote datacenter, which the application
+    /// may not expect. It only has effect if `permit_dc_failover` is enabled.
+    pub fn permit_dc_failover_for_local_consistency(mut self, permit: bool) -> Self {
+        self.permit_dc_failover_for_local_consistency = permit;
+        self
+    }
+
+    /// Sets the maximal number of nodes of each remote datacenter which are used
+    /// in plans when datacenter failover is permitted, apart from the replicas
+    /// owning the data. The first nodes of each datacenter in the ring order are used.
+    ///
+    /// By default, all nodes of remote datacenters are used. Limiting them
+    /// keeps the failover traffic from spreading over a whole remote datacenter.
+    /// It only has effect if `permit_dc_failover` is enabled.
+    pub fn max_remote_nodes_per_dc(mut self, max_nodes: usize) -> Self {
+        self.max_remote_nodes_per_dc = Some(max_nodes);
+        self
+    }
+
     /// Latency awareness is a mechanism that penalises nodes whose measured
     /// recent average latency classifies it as falling behind the others.
     ///
@@ -1151,8 +1233,8 @@ impl<'a> TokenWithStrategy<'a> {
 }
 
 #[cfg(test)]
-mod tests {
-    use std::collections::HashMap;
+pub(crate) mod tests {
+    use std::collections::{HashMap, HashSet};
 
     use scylla_cql::{frame::types::SerialConsistency, Consistency};
     use tracing::info;
@@ -1539,6 +1621,54 @@ mod tests {
         .await;
     }
 
+    #[tokio::test]
+    async fn test_default_policy_with_structured_dc_failover() {
+        setup_tracing();
+        let cluster = mock_cluster_data_for_token_unaware_tests().await;
+
+        let policy = DefaultPolicy {
+            preferences: NodeLocationPreference::Datacenter("eu".to_owned()),
+            permit_dc_failover: true,
+            max_remote_nodes_per_dc: Some(1),
+            ..Default::default()
+        };
+        let mut remote_nodes = HashSet::new();
+        for _ in 0..32 {
+            let plan =
+                get_plan_and_collect_node_identifiers(&policy, &EMPTY_ROUTING_INFO, &cluster);
+            assert_eq!(plan.len(), 4);
+            let mut local_nodes = plan[..3].to_vec();
+            local_nodes.sort_unstable();
+            assert_eq!(local_nodes, [1, 2, 3]);
+            remote_nodes.insert(plan[3]);
+        }
+        // Always the same remote node is used.
+        assert_eq!(remote_nodes.len(), 1);
+        assert!(remote_nodes.is_subset(&HashSet::from([4, 5])));
+
+        let local_routing_info = RoutingInfo {
+            consistency: Consistency::LocalQuorum,
+            ..EMPTY_ROUTING_INFO
+        };
+        let policy = DefaultPolicy {
+            preferences: NodeLocationPreference::Datacenter("eu".to_owned()),
+            permit_dc_failover: true,
+            permit_dc_failover_for_local_consistency: true,
+            ..Default::default()
+        };
+        let expected_groups = ExpectedGroupsBuilder::new()
+            .group([1, 2, 3]) // pick + fallback local nodes
+            .group([4, 5]) // fallback remote nodes, permitted despite local consistency
+            .build();
+        test_default_policy_with_given_cluster_and_routing_info(
+            &policy,
+            &cluster,
+            &local_routing_info,
+            &expected_groups,
+        )
+        .await;
+    }
+
     #[tokio::test]
     async fn test_default_policy_with_token_aware_statements() {
         setup_tracing();
@@ -2846,6 +2976,7 @@ mod latency_awareness {
                 | QueryError::ConnectionPoolError(_)
                 | QueryError::EmptyPlan
                 | QueryError::UnableToAllocStreamId
+                | QueryError::Overloaded(_)
                 | QueryError::DbError(DbError::IsBootstrapping, _)
                 | QueryError::DbError(DbError::Unavailable { .. }, _)
                 | QueryError::DbError(DbError::Unprepared { .. }, _)
@@ -2862,6 +2993,7 @@ mod latency_awareness {
                 | QueryError::ProtocolError(_)
                 | QueryError::TimeoutError
                 | QueryError::RequestTimeout(_)
+                | QueryError::ResultMemoryLimitExceeded { .. }
                 | QueryError::NextRowError(_)
                 | QueryError::IntoLegacyQueryResultError(_) => true,
             }
@@ -3197,6 +3329,8 @@ mod latency_awareness {
             DefaultPolicy {
                 preferences: NodeLocationPreference::Datacenter("eu".to_owned()),
                 permit_dc_failover: true,
+                permit_dc_failover_for_local_consistency: false,
+                max_remote_nodes_per_dc: None,
                 is_token_aware: true,
                 pick_predicate,
                 latency_awareness: Some(latency_awareness),
@@ -0,0 +1,163 @@
+use std::sync::Arc;
+
+use super::{FallbackPlan, LoadBalancingPolicy, NodeRef, RoutingInfo};
+use crate::{routing::Shard, transport::errors::QueryError, transport::ClusterData};
+
+type TargetPredicate = dyn Fn(NodeRef<'_>, &RoutingInfo<'_>) -> bool + Send + Sync;
+
+/// A policy which composes another policy with a custom node predicate.
+///
+/// Plans are computed by the wrapped policy (usually [`DefaultPolicy`](super::DefaultPolicy),
+/// so that token and datacenter awareness are kept) and the nodes rejected by the predicate
+/// are removed from them. Feedback about executed queries is passed to the wrapped policy.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use scylla::load_balancing::{DefaultPolicy, FilteringPolicy};
+/// // Do not send analytics queries to the nodes of the "hot" rack.
+/// let policy = FilteringPolicy::new(DefaultPolicy::builder().build(), |node, _routing_info| {
+///     node.rack.as_deref() != Some("hot")
+/// });
+/// ```
+pub struct FilteringPolicy {
+    inner: Arc<dyn LoadBalancingPolicy>,
+    predicate: Box<TargetPredicate>,
+}
+
+impl FilteringPolicy {
+    /// Creates a policy which uses the plans of `inner` restricted to the nodes
+    /// for which `predicate` returns true.
+    pub fn new(
+        inner: Arc<dyn LoadBalancingPolicy>,
+        predicate: impl Fn(NodeRef<'_>, &RoutingInfo<'_>) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            inner,
+            predicate: Box::new(predicate),
+        }
+    }
+
+    /// Returns the wrapped policy.
+    pub fn inner(&self) -> &Arc<dyn LoadBalancingPolicy> {
+        &self.inner
+    }
+}
+
+impl std::fmt::Debug for FilteringPolicy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("FilteringPolicy")
+            .field("inner", &self.inner)
+            .finish_non_exhaustive()
+    }
+}
+
+impl LoadBalancingPolicy for FilteringPolicy {
+    fn pick<'a>(
+        &'a self,
+        query: &'a RoutingInfo,
+        cluster: &'a ClusterData,
+    ) -> Option<(NodeRef<'a>, Option<Shard>)> {
+        match self.inner.pick(query, cluster) {
+            Some((node, shard)) if (self.predicate)(node, query) => Some((node, shard)),
+            // The first target was rejected, so take the first accepted one from the rest of the plan.
+            _ => self.fallback(query, cluster).next(),
+        }
+    }
+
+    fn fallback<'a>(
+        &'a self,
+        query: &'a RoutingInfo,
+        cluster: &'a ClusterData,
+    ) -> FallbackPlan<'a> {
+        Box::new(
+            self.inner
+                .fallback(query, cluster)
+                .filter(move |(node, _shard)| (self.predicate)(node, query)),
+        )
+    }
+
+    fn on_query_success(
+        &self,
+        query: &RoutingInfo,
+        latency: std::time::Duration,
+        node: NodeRef<'_>,
+    ) {
+        self.inner.on_query_success(query, latency, node)
+    }
+
+    fn on_query_failure(
+        &self,
+        query: &RoutingInfo,
+        latency: std::time::Duration,
+        node: NodeRef<'_>,
+        error: &QueryError,
+    ) {
+        self.inner.on_query_failure(query, latency, node, error)
+    }
+
+    fn name(&self) -> String {
+        format!("FilteringPolicy({})", self.inner.name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::FilteringPolicy;
+    use crate::load_balancing::default::tests::framework::{
+        get_plan_and_collect_node_identifiers, mock_cluster_data_for_token_aware_tests,
+    };
+    use crate::load_balancing::default::tests::EMPTY_ROUTING_INFO;
+    use crate::load_balancing::{DefaultPolicy, LoadBalancingPolicy, RoutingInfo};
+    use crate::routing::Token;
+    use crate::test_utils::setup_tracing;
+    use crate::transport::locator::test::{A, B, C, D, E, F, G, TABLE_NTS_RF_3};
+
+    #[tokio::test]
+    async fn filtering_policy_skips_rejected_nodes() {
+        setup_tracing();
+        let cluster = mock_cluster_data_for_token_aware_tests().await;
+        let policy = FilteringPolicy::new(
+            DefaultPolicy::builder()
+                .prefer_datacenter("eu".to_owned())
+                .permit_dc_failover(true)
+                .build(),
+            |node, _| node.address.port() != A && node.address.port() != G,
+        );
+        let routing_info = RoutingInfo {
+            token: Some(Token::new(160)),
+            table: Some(TABLE_NTS_RF_3),
+            ..EMPTY_ROUTING_INFO
+        };
+
+        for _ in 0..16 {
+            let plan = get_plan_and_collect_node_identifiers(&policy, &routing_info, &cluster);
+            let mut sorted = plan.clone();
+            sorted.sort_unstable();
+            assert_eq!(sorted, [B, C, D, E, F]);
+
+            let (node, _) = policy.pick(&routing_info, &cluster).unwrap();
+            assert!(![A, G].contains(&node.address.port()));
+        }
+        assert_eq!(policy.name(), "FilteringPolicy(DefaultPolicy)");
+    }
+
+    #[tokio::test]
+    async fn replicas_are_available_to_custom_policies() {
+        setup_tracing();
+        let cluster = mock_cluster_data_for_token_aware_tests().await;
+        let routing_info = RoutingInfo {
+            token: Some(Token::new(160)),
+            table: Some(TABLE_NTS_RF_3),
+            ..EMPTY_ROUTING_INFO
+        };
+
+        let replicas = cluster.get_replicas(&routing_info, None).unwrap();
+        let eu_replicas = cluster.get_replicas(&routing_info, Some("eu")).unwrap();
+        assert!(eu_replicas.len() < replicas.len());
+        assert!(eu_replicas
+            .into_iter()
+            .all(|(node, _)| node.datacenter.as_deref() == Some("eu")));
+
+        assert!(cluster.get_replicas(&EMPTY_ROUTING_INFO, None).is_none());
+    }
+}
@@ -12,8 +12,10 @@ use scylla_cql::frame::{response::result::TableSpec, types};
 use std::time::Duration;
 
 mod default;
+mod filtering;
 mod plan;
 pub use default::{DefaultPolicy, DefaultPolicyBuilder, LatencyAwarenessBuilder};
+pub use filtering::FilteringPolicy;
 pub use plan::Plan;
 
 /// Represents info about statement that can be used by load balancing policies.
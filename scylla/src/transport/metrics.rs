@@ -1,6 +1,7 @@
-use histogram::Histogram;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::transport::UserTags;
 
 const ORDER_TYPE: Ordering = Ordering::Relaxed;
 
@@ -28,7 +29,15 @@ pub struct Metrics {
     errors_iter_num: AtomicU64,
     queries_iter_num: AtomicU64,
     retries_num: AtomicU64,
-    histogram: Arc<Mutex<Histogram>>,
+    stream_id_exhaustions_num: AtomicU64,
+    cancelled_requests_num: AtomicU64,
+    orphaned_requests_num: AtomicU64,
+    compressed_requests_num: AtomicU64,
+    compression_skipped_requests_num: AtomicU64,
+    compression_input_bytes: AtomicU64,
+    compression_output_bytes: AtomicU64,
+    latencies: LatencyHistogram,
+    user_tags: UserTags,
 }
 
 impl Metrics {
@@ -39,10 +48,32 @@ impl Metrics {
             errors_iter_num: AtomicU64::new(0),
             queries_iter_num: AtomicU64::new(0),
             retries_num: AtomicU64::new(0),
-            histogram: Arc::new(Mutex::new(Histogram::new())),
+            stream_id_exhaustions_num: AtomicU64::new(0),
+            cancelled_requests_num: AtomicU64::new(0),
+            orphaned_requests_num: AtomicU64::new(0),
+            compressed_requests_num: AtomicU64::new(0),
+            compression_skipped_requests_num: AtomicU64::new(0),
+            compression_input_bytes: AtomicU64::new(0),
+            compression_output_bytes: AtomicU64::new(0),
+            latencies: LatencyHistogram::default(),
+            user_tags: UserTags::new(),
+        }
+    }
+
+    /// Creates metrics of a session with the given tags.
+    pub(crate) fn with_user_tags(user_tags: UserTags) -> Self {
+        Self {
+            user_tags,
+            ..Self::new()
         }
     }
 
+    /// Returns the tags of the session, which can be used to label the metrics
+    /// when exporting them. See [`UserTags`].
+    pub fn user_tags(&self) -> &UserTags {
+        &self.user_tags
+    }
+
     /// Increments counter for errors that occurred in nonpaged queries.
     pub(crate) fn inc_failed_nonpaged_queries(&self) {
         self.errors_num.fetch_add(1, ORDER_TYPE);
@@ -69,22 +100,50 @@ impl Metrics {
         self.retries_num.fetch_add(1, ORDER_TYPE);
     }
 
+    /// Increments counter measuring how many requests found their connection
+    /// with no free stream ids.
+    pub(crate) fn inc_stream_id_exhaustions(&self) {
+        self.stream_id_exhaustions_num.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Increments counter of requests which were not sent,
+    /// because they had been cancelled before.
+    pub(crate) fn inc_cancelled_requests(&self) {
+        self.cancelled_requests_num.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Increments counter of requests which were cancelled after being sent.
+    pub(crate) fn inc_orphaned_requests(&self) {
+        self.orphaned_requests_num.fetch_add(1, ORDER_TYPE);
+    }
+
+    /// Records a request whose body was compressed from `uncompressed_len`
+    /// to `compressed_len` bytes.
+    pub(crate) fn log_compressed_request(&self, uncompressed_len: usize, compressed_len: usize) {
+        self.compressed_requests_num.fetch_add(1, ORDER_TYPE);
+        self.compression_input_bytes
+            .fetch_add(uncompressed_len as u64, ORDER_TYPE);
+        self.compression_output_bytes
+            .fetch_add(compressed_len as u64, ORDER_TYPE);
+    }
+
+    /// Increments counter of requests sent uncompressed on a compressed connection,
+    /// because they were smaller than the compression threshold.
+    pub(crate) fn inc_compression_skipped_requests(&self) {
+        self.compression_skipped_requests_num
+            .fetch_add(1, ORDER_TYPE);
+    }
+
     /// Saves to histogram latency of completing single query.
     /// For paged queries it should log latency for every page.
-    ///
-    /// # Arguments
-    ///
-    /// * `latency` - time in milliseconds that should be logged
-    pub(crate) fn log_query_latency(&self, latency: u64) -> Result<(), MetricsError> {
-        let mut histogram_unlocked = self.histogram.lock().unwrap();
-        histogram_unlocked.increment(latency)?;
-        Ok(())
+    pub(crate) fn log_query_latency(&self, latency: Duration) {
+        self.latencies.record(latency);
     }
 
     /// Returns average latency in milliseconds
     pub fn get_latency_avg_ms(&self) -> Result<u64, MetricsError> {
-        let histogram_unlocked = self.histogram.lock().unwrap();
-        Ok(histogram_unlocked.mean()?)
+        let mean = self.latencies.mean().ok_or("histogram is empty")?;
+        Ok(mean.as_millis() as u64)
     }
 
     /// Returns latency from histogram for a given percentile
@@ -92,8 +151,19 @@ impl Metrics {
     ///
     /// * `percentile` - float value (0.0 - 100.0)
     pub fn get_latency_percentile_ms(&self, percentile: f64) -> Result<u64, MetricsError> {
-        let histogram_unlocked = self.histogram.lock().unwrap();
-        Ok(histogram_unlocked.percentile(percentile)?)
+        let latency = self
+            .latencies
+            .percentile(percentile)
+            .ok_or("histogram is empty")?;
+        Ok(latency.as_millis() as u64)
+    }
+
+    /// Returns the summary of latencies of all successful requests,
+    /// or `None` if no request has succeeded yet.
+    /// The latencies of single nodes and shards are available through
+    /// [`Node::latency_summary`](crate::transport::Node::latency_summary).
+    pub fn get_latency_summary(&self) -> Option<LatencySummary> {
+        self.latencies.summary()
     }
 
     /// Returns counter for errors occurred in nonpaged queries
@@ -120,4 +190,219 @@ impl Metrics {
     pub fn get_retries_num(&self) -> u64 {
         self.retries_num.load(ORDER_TYPE)
     }
+
+    /// Returns counter measuring how many requests found their connection
+    /// with no free stream ids. Each such request is counted once, regardless
+    /// of the configured [`StreamIdExhaustionPolicy`](crate::transport::StreamIdExhaustionPolicy).
+    pub fn get_stream_id_exhaustions_num(&self) -> u64 {
+        self.stream_id_exhaustions_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter measuring how many requests were cancelled (e.g. because
+    /// their future was dropped or their timeout passed) before being sent,
+    /// so they were not sent at all.
+    pub fn get_cancelled_requests_num(&self) -> u64 {
+        self.cancelled_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter measuring how many requests were cancelled after being sent.
+    /// Their stream ids stay in use until the responses arrive, and the responses are discarded.
+    pub fn get_orphaned_requests_num(&self) -> u64 {
+        self.orphaned_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter measuring how many requests were sent with a compressed body.
+    pub fn get_compressed_requests_num(&self) -> u64 {
+        self.compressed_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns counter measuring how many requests were sent uncompressed on a compressed
+    /// connection, because they were smaller than the configured compression threshold.
+    pub fn get_compression_skipped_requests_num(&self) -> u64 {
+        self.compression_skipped_requests_num.load(ORDER_TYPE)
+    }
+
+    /// Returns the total size in bytes of the request bodies before compression,
+    /// counting only the requests which were compressed.
+    pub fn get_compression_input_bytes(&self) -> u64 {
+        self.compression_input_bytes.load(ORDER_TYPE)
+    }
+
+    /// Returns the total size in bytes of the compressed request bodies.
+    /// Together with [`Metrics::get_compression_input_bytes`] it gives the compression ratio.
+    pub fn get_compression_output_bytes(&self) -> u64 {
+        self.compression_output_bytes.load(ORDER_TYPE)
+    }
+}
+
+/// Summary of the latencies of successful requests, see [`Metrics::get_latency_summary`].
+///
+/// The percentiles are computed from a histogram with a relative error below 7%.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LatencySummary {
+    /// Number of recorded requests.
+    pub count: u64,
+    /// Mean latency.
+    pub mean: Duration,
+    /// Median latency.
+    pub p50: Duration,
+    /// 95th percentile of latencies.
+    pub p95: Duration,
+    /// 99th percentile of latencies.
+    pub p99: Duration,
+    /// Maximal latency.
+    pub max: Duration,
+}
+
+/// A lock-free histogram of latencies, with microsecond resolution and buckets
+/// growing exponentially, like in HDR histograms: each power of two is split
+/// into `SUB_BUCKETS` linear buckets.
+pub(crate) struct LatencyHistogram {
+    buckets: Box<[AtomicU64]>,
+    count: AtomicU64,
+    sum_micros: AtomicU64,
+    max_micros: AtomicU64,
+}
+
+impl LatencyHistogram {
+    const SUB_BUCKET_BITS: u32 = 4;
+    const SUB_BUCKETS: usize = 1 << Self::SUB_BUCKET_BITS;
+    // Latencies above 2^MAX_BITS microseconds (about 12 days) fall into the last bucket.
+    const MAX_BITS: u32 = 40;
+    const BUCKETS: usize =
+        Self::SUB_BUCKETS * (1 + (Self::MAX_BITS - Self::SUB_BUCKET_BITS) as usize);
+
+    pub(crate) fn record(&self, latency: Duration) {
+        let micros = (latency.as_micros() as u64).min((1 << Self::MAX_BITS) - 1);
+        self.buckets[Self::bucket_index(micros)].fetch_add(1, ORDER_TYPE);
+        self.count.fetch_add(1, ORDER_TYPE);
+        self.sum_micros.fetch_add(micros, ORDER_TYPE);
+        self.max_micros.fetch_max(micros, ORDER_TYPE);
+    }
+
+    fn bucket_index(micros: u64) -> usize {
+        if micros < Self::SUB_BUCKETS as u64 {
+            return micros as usize;
+        }
+        let shift = 63 - micros.leading_zeros() - Self::SUB_BUCKET_BITS;
+        let sub_bucket = (micros >> shift) as usize - Self::SUB_BUCKETS;
+        Self::SUB_BUCKETS * (1 + shift as usize) + sub_bucket
+    }
+
+    // The highest latency which falls into the bucket.
+    fn bucket_upper_bound(index: usize) -> u64 {
+        if index < Self::SUB_BUCKETS {
+            return index as u64;
+        }
+        let shift = (index / Self::SUB_BUCKETS - 1) as u32;
+        let sub_bucket = (index % Self::SUB_BUCKETS) as u64;
+        (((Self::SUB_BUCKETS as u64 + sub_bucket) << shift) + (1 << shift)) - 1
+    }
+
+    pub(crate) fn mean(&self) -> Option<Duration> {
+        let count = self.count.load(ORDER_TYPE);
+        (count > 0).then(|| Duration::from_micros(self.sum_micros.load(ORDER_TYPE) / count))
+    }
+
+    /// Returns the given percentile (0.0 - 100.0) of the recorded latencies.
+    pub(crate) fn percentile(&self, percentile: f64) -> Option<Duration> {
+        let counts: Vec<u64> = self.buckets.iter().map(|b| b.load(ORDER_TYPE)).collect();
+        let total: u64 = counts.iter().sum();
+        if total == 0 {
+            return None;
+        }
+        let rank = ((percentile.clamp(0.0, 100.0) / 100.0 * total as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        let index = counts
+            .iter()
+            .position(|&count| {
+                seen += count;
+                seen >= rank
+            })
+            .unwrap_or(Self::BUCKETS - 1);
+        let upper_bound = Self::bucket_upper_bound(index).min(self.max_micros.load(ORDER_TYPE));
+        Some(Duration::from_micros(upper_bound))
+    }
+
+    pub(crate) fn summary(&self) -> Option<LatencySummary> {
+        Some(LatencySummary {
+            count: self.count.load(ORDER_TYPE),
+            mean: self.mean()?,
+            p50: self.percentile(50.0)?,
+            p95: self.percentile(95.0)?,
+            p99: self.percentile(99.0)?,
+            max: Duration::from_micros(self.max_micros.load(ORDER_TYPE)),
+        })
+    }
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            buckets: (0..Self::BUCKETS).map(|_| AtomicU64::new(0)).collect(),
+            count: AtomicU64::new(0),
+            sum_micros: AtomicU64::new(0),
+            max_micros: AtomicU64::new(0),
+        }
+    }
+}
+
+impl std::fmt::Debug for LatencyHistogram {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LatencyHistogram")
+            .field("count", &self.count.load(ORDER_TYPE))
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::LatencyHistogram;
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn buckets_cover_all_latencies() {
+        setup_tracing();
+        let mut previous_upper_bound = None;
+        for index in 0..LatencyHistogram::BUCKETS {
+            let upper_bound = LatencyHistogram::bucket_upper_bound(index);
+            assert_eq!(LatencyHistogram::bucket_index(upper_bound), index);
+            if let Some(previous) = previous_upper_bound {
+                assert_eq!(LatencyHistogram::bucket_index(previous + 1), index);
+            }
+            previous_upper_bound = Some(upper_bound);
+        }
+        assert_eq!(
+            previous_upper_bound,
+            Some((1 << LatencyHistogram::MAX_BITS) - 1)
+        );
+    }
+
+    #[test]
+    fn percentiles() {
+        setup_tracing();
+        let histogram = LatencyHistogram::default();
+        assert!(histogram.summary().is_none());
+
+        for millis in 1..=100 {
+            histogram.record(Duration::from_millis(millis));
+        }
+        let summary = histogram.summary().unwrap();
+        assert_eq!(summary.count, 100);
+        assert_eq!(summary.mean, Duration::from_micros(50500));
+        assert_eq!(summary.max, Duration::from_millis(100));
+
+        // Percentiles are accurate up to the bucket width.
+        let within_error = |got: Duration, expected_millis: u64| {
+            let expected = Duration::from_millis(expected_millis);
+            expected <= got && got <= expected.mul_f64(1.07)
+        };
+        assert!(within_error(summary.p50, 50), "{:?}", summary.p50);
+        assert!(within_error(summary.p95, 95), "{:?}", summary.p95);
+        assert!(within_error(summary.p99, 99), "{:?}", summary.p99);
+        assert_eq!(histogram.percentile(100.0), Some(summary.max));
+    }
 }
@@ -0,0 +1,268 @@
+//! Export of driver metrics through the [`metrics`](https://docs.rs/metrics) facade.
+//! Enabled by the `metrics` feature; without it, all the `record_*` functions here are no-ops.
+//!
+//! The names and labels of the metrics are part of the public API, documented
+//! in [the book](https://rust-driver.docs.scylladb.com/stable/metrics/metrics.html):
+//! - `scylla_request_latency_seconds` (histogram; `node`, `dc`, `shard`),
+//! - `scylla_request_errors_total` (counter; `node`, `dc`, `shard`, `kind`),
+//! - `scylla_retries_total` (counter; `node`, `dc`),
+//! - `scylla_speculative_executions_total` (counter),
+//! - `scylla_pool_connections` (gauge; `node`),
+//! - `scylla_pool_target_connections` (gauge; `node`),
+//! - `scylla_pool_connected_shards` (gauge; `node`),
+//! - `scylla_pool_reconnect_attempts_total` (counter; `node`),
+//! - `scylla_in_flight_requests` (gauge; `node`).
+//!
+//! All of them except `scylla_in_flight_requests` are also labeled with the user tags:
+//! those of the session for the pool metrics, and those of the session merged with
+//! the tags of the execution profile for the request metrics. A tag with the same key
+//! as one of the labels above is skipped.
+
+use super::errors::{DbError, QueryError};
+
+/// A stable, low cardinality name of the error, used as the `kind` label of the metrics
+/// and the `error.type` field of the attempt spans.
+pub(crate) fn error_kind(error: &QueryError) -> &'static str {
+    match error {
+        QueryError::DbError(DbError::Unavailable { .. }, _) => "unavailable",
+        QueryError::DbError(DbError::ReadTimeout { .. }, _) => "read_timeout",
+        QueryError::DbError(DbError::WriteTimeout { .. }, _) => "write_timeout",
+        QueryError::DbError(DbError::ReadFailure { .. }, _) => "read_failure",
+        QueryError::DbError(DbError::WriteFailure { .. }, _) => "write_failure",
+        QueryError::DbError(DbError::Overloaded, _) => "server_overloaded",
+        QueryError::DbError(DbError::IsBootstrapping, _) => "is_bootstrapping",
+        QueryError::DbError(_, _) => "server_error",
+        QueryError::BrokenConnection(_) | QueryError::ConnectionPoolError(_) => "connection",
+        QueryError::TimeoutError | QueryError::RequestTimeout(_) => "client_timeout",
+        QueryError::UnableToAllocStreamId => "stream_id_exhaustion",
+        QueryError::Overloaded(_) => "throttled",
+        _ => "other",
+    }
+}
+
+#[cfg(feature = "metrics")]
+mod imp {
+    use std::sync::Once;
+    use std::time::Duration;
+
+    use metrics::{
+        counter, describe_counter, describe_gauge, describe_histogram, gauge, histogram, Label,
+        Unit,
+    };
+
+    use super::error_kind;
+    use crate::routing::Shard;
+    use crate::transport::connection_pool::PoolState;
+    use crate::transport::errors::QueryError;
+    use crate::transport::{NodeAddr, NodeRef, UserTags};
+
+    const REQUEST_LATENCY: &str = "scylla_request_latency_seconds";
+    const REQUEST_ERRORS: &str = "scylla_request_errors_total";
+    const RETRIES: &str = "scylla_retries_total";
+    const SPECULATIVE_EXECUTIONS: &str = "scylla_speculative_executions_total";
+    const POOL_CONNECTIONS: &str = "scylla_pool_connections";
+    const POOL_TARGET_CONNECTIONS: &str = "scylla_pool_target_connections";
+    const POOL_CONNECTED_SHARDS: &str = "scylla_pool_connected_shards";
+    const POOL_RECONNECT_ATTEMPTS: &str = "scylla_pool_reconnect_attempts_total";
+    const IN_FLIGHT_REQUESTS: &str = "scylla_in_flight_requests";
+
+    /// Registers the descriptions of the metrics in the installed recorder.
+    pub(crate) fn describe() {
+        static DESCRIBED: Once = Once::new();
+        DESCRIBED.call_once(|| {
+            describe_histogram!(
+                REQUEST_LATENCY,
+                Unit::Seconds,
+                "Latency of successful requests sent to a node"
+            );
+            describe_counter!(REQUEST_ERRORS, "Number of requests which failed on a node");
+            describe_counter!(RETRIES, "Number of retries decided by the retry policy");
+            describe_counter!(
+                SPECULATIVE_EXECUTIONS,
+                "Number of started speculative executions"
+            );
+            describe_gauge!(POOL_CONNECTIONS, "Number of open connections to a node");
+            describe_gauge!(
+                POOL_TARGET_CONNECTIONS,
+                "Number of connections the pool of a node tries to keep open"
+            );
+            describe_gauge!(
+                POOL_CONNECTED_SHARDS,
+                "Number of shards of a node with at least one open connection"
+            );
+            describe_counter!(
+                POOL_RECONNECT_ATTEMPTS,
+                "Number of refills of a pool delayed after a failure to connect"
+            );
+            describe_gauge!(
+                IN_FLIGHT_REQUESTS,
+                "Number of requests sent to a node and not answered yet"
+            );
+        });
+    }
+
+    fn node_labels(node: NodeRef<'_>) -> [Label; 2] {
+        [
+            Label::new("node", node.address.to_string()),
+            Label::new("dc", node.datacenter.clone().unwrap_or_default()),
+        ]
+    }
+
+    fn request_labels(node: NodeRef<'_>, shard: Option<Shard>) -> Vec<Label> {
+        let shard = shard.map(|shard| shard.to_string()).unwrap_or_default();
+        let mut labels = node_labels(node).to_vec();
+        labels.push(Label::new("shard", shard));
+        labels
+    }
+
+    // Appends the user tags, skipping those which would override the labels of the driver.
+    pub(super) fn with_user_tags(mut labels: Vec<Label>, user_tags: &UserTags) -> Vec<Label> {
+        let tags = user_tags
+            .iter()
+            .filter(|(key, _)| labels.iter().all(|label| label.key() != *key))
+            .map(|(key, value)| Label::new(key.to_owned(), value.to_owned()))
+            .collect::<Vec<_>>();
+        labels.extend(tags);
+        labels
+    }
+
+    pub(crate) fn record_request_success(
+        node: NodeRef<'_>,
+        shard: Option<Shard>,
+        user_tags: &UserTags,
+        latency: Duration,
+    ) {
+        let labels = with_user_tags(request_labels(node, shard), user_tags);
+        histogram!(REQUEST_LATENCY, labels).record(latency);
+    }
+
+    pub(crate) fn record_request_failure(
+        node: NodeRef<'_>,
+        shard: Option<Shard>,
+        user_tags: &UserTags,
+        error: &QueryError,
+    ) {
+        let mut labels = request_labels(node, shard);
+        labels.push(Label::new("kind", error_kind(error)));
+        counter!(REQUEST_ERRORS, with_user_tags(labels, user_tags)).increment(1);
+    }
+
+    pub(crate) fn record_retry(node: NodeRef<'_>, user_tags: &UserTags) {
+        let labels = with_user_tags(node_labels(node).to_vec(), user_tags);
+        counter!(RETRIES, labels).increment(1);
+    }
+
+    pub(crate) fn record_speculative_execution(user_tags: &UserTags) {
+        counter!(
+            SPECULATIVE_EXECUTIONS,
+            with_user_tags(Vec::new(), user_tags)
+        )
+        .increment(1);
+    }
+
+    pub(crate) fn record_pool_state(node: NodeAddr, user_tags: &UserTags, state: &PoolState) {
+        let labels = with_user_tags(vec![Label::new("node", node.to_string())], user_tags);
+        gauge!(POOL_CONNECTIONS, labels.clone()).set(state.open_connections as f64);
+        gauge!(POOL_TARGET_CONNECTIONS, labels.clone()).set(state.target_connections as f64);
+        gauge!(POOL_CONNECTED_SHARDS, labels).set(state.connected_shards as f64);
+    }
+
+    pub(crate) fn record_reconnect_attempt(node: NodeAddr, user_tags: &UserTags) {
+        let labels = with_user_tags(vec![Label::new("node", node.to_string())], user_tags);
+        counter!(POOL_RECONNECT_ATTEMPTS, labels).increment(1);
+    }
+
+    pub(crate) fn record_in_flight_requests(node: NodeAddr, in_flight: usize) {
+        gauge!(IN_FLIGHT_REQUESTS, "node" => node.to_string()).set(in_flight as f64);
+    }
+}
+
+#[cfg(not(feature = "metrics"))]
+mod imp {
+    use std::time::Duration;
+
+    use crate::routing::Shard;
+    use crate::transport::connection_pool::PoolState;
+    use crate::transport::errors::QueryError;
+    use crate::transport::{NodeAddr, NodeRef, UserTags};
+
+    pub(crate) fn describe() {}
+
+    pub(crate) fn record_request_success(
+        _node: NodeRef<'_>,
+        _shard: Option<Shard>,
+        _user_tags: &UserTags,
+        _latency: Duration,
+    ) {
+    }
+
+    pub(crate) fn record_request_failure(
+        _node: NodeRef<'_>,
+        _shard: Option<Shard>,
+        _user_tags: &UserTags,
+        _error: &QueryError,
+    ) {
+    }
+
+    pub(crate) fn record_retry(_node: NodeRef<'_>, _user_tags: &UserTags) {}
+
+    pub(crate) fn record_speculative_execution(_user_tags: &UserTags) {}
+
+    pub(crate) fn record_pool_state(_node: NodeAddr, _user_tags: &UserTags, _state: &PoolState) {}
+
+    pub(crate) fn record_reconnect_attempt(_node: NodeAddr, _user_tags: &UserTags) {}
+
+    pub(crate) fn record_in_flight_requests(_node: NodeAddr, _in_flight: usize) {}
+}
+
+pub(crate) use imp::*;
+
+#[cfg(test)]
+mod tests {
+    use super::error_kind;
+    use crate::test_utils::setup_tracing;
+    use crate::transport::errors::{DbError, QueryError};
+
+    #[test]
+    fn error_kinds() {
+        setup_tracing();
+        assert_eq!(
+            error_kind(&QueryError::DbError(
+                DbError::Overloaded,
+                "overloaded".to_owned()
+            )),
+            "server_overloaded"
+        );
+        assert_eq!(
+            error_kind(&QueryError::DbError(DbError::SyntaxError, String::new())),
+            "server_error"
+        );
+        assert_eq!(
+            error_kind(&QueryError::UnableToAllocStreamId),
+            "stream_id_exhaustion"
+        );
+        assert_eq!(error_kind(&QueryError::EmptyPlan), "other");
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn user_tags_labels() {
+        use metrics::Label;
+
+        use super::imp::with_user_tags;
+        use crate::transport::UserTags;
+
+        setup_tracing();
+        let tags = UserTags::new()
+            .with("tenant", "acme")
+            .with("node", "overridden");
+        let labels = with_user_tags(vec![Label::new("node", "10.0.0.1:9042")], &tags);
+        assert_eq!(
+            labels,
+            vec![
+                Label::new("node", "10.0.0.1:9042"),
+                Label::new("tenant", "acme")
+            ]
+        );
+    }
+}
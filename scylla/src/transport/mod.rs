@@ -1,3 +1,5 @@
+mod attempt_span;
+pub mod backpressure;
 pub(crate) mod caching_session;
 mod cluster;
 pub(crate) mod connection;
@@ -5,28 +7,38 @@ mod connection_pool;
 pub mod downgrading_consistency_retry_policy;
 pub mod errors;
 pub mod execution_profile;
+pub mod health;
 pub mod host_filter;
 pub mod iterator;
 pub mod legacy_query_result;
 pub mod load_balancing;
 pub mod locator;
 pub(crate) mod metrics;
+mod metrics_export;
 mod node;
 pub mod partitioner;
+pub mod proxy;
 pub mod query_result;
+pub mod read_your_writes;
+pub mod reconnection_policy;
 pub mod retry_policy;
 pub mod session;
 pub mod session_builder;
+pub mod sni_proxy;
 pub mod speculative_execution;
+pub mod throttler;
 pub mod topology;
+pub mod user_tags;
+pub mod warnings;
 
-pub use crate::frame::{Authenticator, Compression};
-pub use connection::SelfIdentity;
-pub use execution_profile::ExecutionProfile;
+pub use crate::frame::{Authenticator, Compression, ProtocolVersion};
+pub use connection::{SelfIdentity, StreamIdExhaustionPolicy, WriteCoalescingDelay};
+pub use execution_profile::{AllowFilteringGuardrail, ExecutionProfile};
 pub use scylla_cql::frame::request::query::{PagingState, PagingStateResponse};
+pub use user_tags::UserTags;
 
 #[cfg(test)]
 mod session_test;
 
 pub use cluster::ClusterData;
-pub use node::{KnownNode, Node, NodeAddr, NodeRef};
+pub use node::{ConnectionLoad, KnownNode, LoadSnapshot, Node, NodeAddr, NodeRef};
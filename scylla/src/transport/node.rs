@@ -5,11 +5,17 @@ use uuid::Uuid;
 
 /// Node represents a cluster node along with it's data and connections
 use crate::routing::{Shard, Sharder};
+use crate::transport::backpressure::BackpressureMonitor;
 use crate::transport::connection::Connection;
 use crate::transport::connection::VerifiedKeyspaceName;
-use crate::transport::connection_pool::{NodeConnectionPool, PoolConfig};
+use crate::transport::connection_pool::{
+    NodeConnectionPool, PoolConfig, PoolState, ShardAwarePortStatus,
+};
 use crate::transport::errors::{ConnectionPoolError, QueryError};
+use crate::transport::metrics::{LatencyHistogram, LatencySummary};
+use crate::transport::metrics_export;
 
+use std::collections::VecDeque;
 use std::fmt::Display;
 use std::io;
 use std::net::IpAddr;
@@ -17,9 +23,10 @@ use std::{
     hash::{Hash, Hasher},
     net::SocketAddr,
     sync::{
-        atomic::{AtomicBool, Ordering},
-        Arc,
+        atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering},
+        Arc, Mutex, RwLock,
     },
+    time::{Duration, Instant},
 };
 
 use super::topology::{PeerEndpoint, UntranslatedEndpoint};
@@ -81,6 +88,8 @@ pub struct Node {
     pool: Option<NodeConnectionPool>,
 
     down_marker: AtomicBool,
+
+    load: Arc<NodeLoadTracker>,
 }
 
 /// A way that Nodes are often passed and accessed in the driver's code.
@@ -104,6 +113,7 @@ impl Node {
         let address = peer.address;
         let datacenter = peer.datacenter.clone();
         let rack = peer.rack.clone();
+        let load = NodeLoadTracker::new(host_id, pool_config.backpressure_monitor.clone());
 
         // We aren't interested in the fact that the pool becomes empty, so we immediately drop the receiving part.
         let (pool_empty_notifier, _) = tokio::sync::broadcast::channel(1);
@@ -123,6 +133,7 @@ impl Node {
             rack,
             pool,
             down_marker: false.into(),
+            load: Arc::new(load),
         }
     }
 
@@ -146,6 +157,7 @@ impl Node {
             rack: node.rack.clone(),
             host_id: node.host_id,
             pool: node.pool.clone(),
+            load: node.load.clone(),
         }
     }
 
@@ -153,6 +165,23 @@ impl Node {
         self.pool.as_ref()?.sharder()
     }
 
+    /// Tells whether the connections to this node are opened to its shard-aware port.
+    /// Returns [`ShardAwarePortStatus::Unknown`] for nodes which are disabled
+    /// by the host filter, as no connections are opened to them.
+    pub fn shard_aware_port_status(&self) -> ShardAwarePortStatus {
+        self.pool
+            .as_ref()
+            .map_or(ShardAwarePortStatus::Unknown, |pool| {
+                pool.shard_aware_port_status()
+            })
+    }
+
+    /// Returns the state of the connection pool of this node,
+    /// or `None` for nodes which are disabled by the host filter.
+    pub fn pool_state(&self) -> Option<PoolState> {
+        self.pool.as_ref().map(NodeConnectionPool::pool_state)
+    }
+
     /// Get a connection targetting the given shard
     /// If such connection is broken, get any random connection to this `Node`
     pub(crate) async fn connection_for_shard(
@@ -173,6 +202,97 @@ impl Node {
         self.pool.is_some()
     }
 
+    /// Returns the load of the node, as observed by this driver instance:
+    /// the number of requests in flight and the average latency.
+    ///
+    /// Reading the load is cheap, so it can be used by load balancing policies
+    /// or admission control layers upon every request.
+    pub fn load(&self) -> LoadSnapshot {
+        self.load.node.snapshot()
+    }
+
+    /// Returns the load of the given shard of the node, as observed by this driver instance.
+    ///
+    /// For nodes which are not sharded, e.g. Cassandra nodes, the load is tracked
+    /// only for the whole node, see [`Node::load`].
+    pub fn shard_load(&self, shard: Shard) -> LoadSnapshot {
+        self.load
+            .shards
+            .read()
+            .unwrap()
+            .get(shard as usize)
+            .map(LoadTracker::snapshot)
+            .unwrap_or_default()
+    }
+
+    /// Returns the summary of latencies of all successful requests to the node,
+    /// or `None` if no request has succeeded yet.
+    pub fn latency_summary(&self) -> Option<LatencySummary> {
+        self.load.node.latencies.summary()
+    }
+
+    /// Returns the summary of latencies of all successful requests to the given shard
+    /// of the node, or `None` if no request to the shard has succeeded yet.
+    ///
+    /// For nodes which are not sharded, e.g. Cassandra nodes, the latencies are tracked
+    /// only for the whole node, see [`Node::latency_summary`].
+    pub fn shard_latency_summary(&self, shard: Shard) -> Option<LatencySummary> {
+        self.load
+            .shards
+            .read()
+            .unwrap()
+            .get(shard as usize)
+            .and_then(|tracker| tracker.latencies.summary())
+    }
+
+    /// Returns the given percentile (0.0 - 100.0) of latencies of the most recent
+    /// successful requests to the node, or `None` if no request has succeeded yet.
+    ///
+    /// Only the latest 1000 requests are taken into account, so the result
+    /// follows changes of the node's performance.
+    pub fn latency_percentile(&self, percentile: f64) -> Option<Duration> {
+        self.load
+            .recent_latencies
+            .lock()
+            .unwrap()
+            .percentile(percentile)
+    }
+
+    /// Returns the load of each working connection to the node: the number of requests
+    /// in flight on it and the number of requests waiting to be sent on it.
+    /// Returns an empty list for nodes which are disabled or have no working connections.
+    pub fn connection_loads(&self) -> Vec<ConnectionLoad> {
+        let Some(Ok(connections)) = self
+            .pool
+            .as_ref()
+            .map(|pool| pool.get_working_connections())
+        else {
+            return Vec::new();
+        };
+        connections
+            .iter()
+            .map(|connection| ConnectionLoad {
+                address: connection.get_connect_address(),
+                shard: connection
+                    .get_shard_info()
+                    .as_ref()
+                    .map(|info| info.shard as Shard),
+                in_flight: connection.get_in_flight_requests_count(),
+                queued: connection.get_queued_requests_count(),
+            })
+            .collect()
+    }
+
+    /// Marks the start of a request sent to the node through the given connection.
+    /// The request is considered in flight until the returned guard is dropped.
+    pub(crate) fn start_request(&self, connection: &Connection) -> InFlightRequest<'_> {
+        let shard = connection
+            .get_shard_info()
+            .as_ref()
+            .map(|info| info.shard as Shard);
+        self.load.start_request(self.address, shard)
+    }
+
     pub(crate) fn change_down_marker(&self, is_down: bool) {
         self.down_marker.store(is_down, Ordering::Relaxed);
     }
@@ -199,6 +319,12 @@ impl Node {
         }
     }
 
+    pub(crate) async fn wait_until_pool_full(&self) {
+        if let Some(pool) = &self.pool {
+            pool.wait_until_full().await;
+        }
+    }
+
     fn get_pool(&self) -> Result<&NodeConnectionPool, ConnectionPoolError> {
         self.pool
             .as_ref()
@@ -220,6 +346,199 @@ impl Hash for Node {
     }
 }
 
+/// Load of a node or of one of its shards, as observed by a driver instance.
+///
+/// See [`Node::load`] and [`Node::shard_load`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LoadSnapshot {
+    /// Number of requests sent and not answered yet.
+    pub in_flight: usize,
+
+    /// Exponentially weighted moving average of latencies of successful requests,
+    /// or `None` if no request has succeeded yet.
+    pub ewma_latency: Option<Duration>,
+}
+
+/// Load of a single connection to a node, see [`Node::connection_loads`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ConnectionLoad {
+    /// Address the connection is opened to.
+    pub address: SocketAddr,
+
+    /// Shard the connection is bound to, or `None` if the node is not sharded.
+    pub shard: Option<Shard>,
+
+    /// Number of requests sent on the connection and not answered yet,
+    /// including the requests sent by the driver itself, e.g. keepalives.
+    pub in_flight: usize,
+
+    /// Number of requests waiting to be sent on the connection,
+    /// e.g. because all its stream ids are in use.
+    pub queued: usize,
+}
+
+/// Tracks the load of a node, both as a whole and per shard.
+#[derive(Debug, Default)]
+pub(crate) struct NodeLoadTracker {
+    host_id: Uuid,
+    // Notified when the number of requests in flight to the node changes.
+    backpressure_monitor: Option<Arc<BackpressureMonitor>>,
+    node: LoadTracker,
+    // Grows lazily, as the shard count is not known until the node is connected to.
+    shards: RwLock<Vec<LoadTracker>>,
+    recent_latencies: Mutex<RecentLatencies>,
+}
+
+impl NodeLoadTracker {
+    fn new(host_id: Uuid, backpressure_monitor: Option<Arc<BackpressureMonitor>>) -> Self {
+        Self {
+            host_id,
+            backpressure_monitor,
+            ..Default::default()
+        }
+    }
+
+    fn start_request(&self, address: NodeAddr, shard: Option<Shard>) -> InFlightRequest<'_> {
+        let in_flight = self.node.in_flight.fetch_add(1, Ordering::Relaxed) + 1;
+        self.in_flight_changed(address, in_flight);
+        if let Some(shard) = shard {
+            self.with_shard(shard, |tracker| {
+                tracker.in_flight.fetch_add(1, Ordering::Relaxed);
+            });
+        }
+        InFlightRequest {
+            tracker: self,
+            address,
+            shard,
+            start: Instant::now(),
+        }
+    }
+
+    fn in_flight_changed(&self, address: NodeAddr, in_flight: usize) {
+        metrics_export::record_in_flight_requests(address, in_flight);
+        if let Some(monitor) = &self.backpressure_monitor {
+            monitor.in_flight_changed(self.host_id, in_flight, &self.node.in_flight);
+        }
+    }
+
+    fn with_shard(&self, shard: Shard, f: impl FnOnce(&LoadTracker)) {
+        let shard = shard as usize;
+        if let Some(tracker) = self.shards.read().unwrap().get(shard) {
+            return f(tracker);
+        }
+        let mut shards = self.shards.write().unwrap();
+        if shards.len() <= shard {
+            shards.resize_with(shard + 1, Default::default);
+        }
+        f(&shards[shard]);
+    }
+}
+
+/// A sliding window of latencies of the most recent successful requests.
+#[derive(Debug, Default)]
+struct RecentLatencies {
+    samples: VecDeque<Duration>,
+}
+
+impl RecentLatencies {
+    const WINDOW_SIZE: usize = 1000;
+
+    fn record(&mut self, latency: Duration) {
+        if self.samples.len() == Self::WINDOW_SIZE {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(latency);
+    }
+
+    fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        let mut sorted: Vec<Duration> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        // Nearest-rank method: the smallest sample not exceeded by `percentile`% of the samples.
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+        Some(sorted[rank.saturating_sub(1)])
+    }
+}
+
+#[derive(Debug, Default)]
+struct LoadTracker {
+    in_flight: AtomicUsize,
+    // Zero if no latency has been recorded yet.
+    ewma_latency_nanos: AtomicU64,
+    latencies: LatencyHistogram,
+}
+
+impl LoadTracker {
+    // The weight of a new sample in the moving average is 1/2^EWMA_SHIFT.
+    const EWMA_SHIFT: u32 = 3;
+
+    fn record_latency(&self, latency: Duration) {
+        self.latencies.record(latency);
+        let sample = (latency.as_nanos() as u64).max(1);
+        let _ =
+            self.ewma_latency_nanos
+                .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |average| {
+                    Some(if average == 0 {
+                        sample
+                    } else {
+                        (average - (average >> Self::EWMA_SHIFT) + (sample >> Self::EWMA_SHIFT))
+                            .max(1)
+                    })
+                });
+    }
+
+    fn snapshot(&self) -> LoadSnapshot {
+        let ewma_latency_nanos = self.ewma_latency_nanos.load(Ordering::Relaxed);
+        LoadSnapshot {
+            in_flight: self.in_flight.load(Ordering::Relaxed),
+            ewma_latency: (ewma_latency_nanos != 0)
+                .then(|| Duration::from_nanos(ewma_latency_nanos)),
+        }
+    }
+}
+
+/// A request in flight to a node. Stops being counted as in flight when dropped.
+pub(crate) struct InFlightRequest<'a> {
+    tracker: &'a NodeLoadTracker,
+    // The address of the node when the request was sent, for the metrics.
+    address: NodeAddr,
+    shard: Option<Shard>,
+    start: Instant,
+}
+
+impl InFlightRequest<'_> {
+    /// Records the latency of the request, which has succeeded.
+    pub(crate) fn succeeded(self) {
+        let latency = self.start.elapsed();
+        self.tracker.node.record_latency(latency);
+        self.tracker
+            .recent_latencies
+            .lock()
+            .unwrap()
+            .record(latency);
+        if let Some(shard) = self.shard {
+            self.tracker
+                .with_shard(shard, |tracker| tracker.record_latency(latency));
+        }
+    }
+}
+
+impl Drop for InFlightRequest<'_> {
+    fn drop(&mut self) {
+        let in_flight = self.tracker.node.in_flight.fetch_sub(1, Ordering::Relaxed) - 1;
+        self.tracker.in_flight_changed(self.address, in_flight);
+        if let Some(shard) = self.shard {
+            self.tracker.with_shard(shard, |tracker| {
+                tracker.in_flight.fetch_sub(1, Ordering::Relaxed);
+            });
+        }
+    }
+}
+
 /// Describes a database server known on `Session` startup.
 ///
 /// The name derives from SessionBuilder's `known_node()` family of methods.
@@ -361,7 +680,80 @@ mod tests {
                 rack,
                 pool: None,
                 down_marker: false.into(),
+                load: Default::default(),
             }
         }
     }
+
+    #[test]
+    fn load_tracking() {
+        crate::test_utils::setup_tracing();
+        let node = Node::new_for_test(None, None, None, None);
+        assert_eq!(node.load(), LoadSnapshot::default());
+
+        let first = node.load.start_request(node.address, Some(2));
+        let second = node.load.start_request(node.address, None);
+        assert_eq!(node.load().in_flight, 2);
+        assert_eq!(node.shard_load(2).in_flight, 1);
+        assert_eq!(node.shard_load(1), LoadSnapshot::default());
+        assert_eq!(node.shard_load(7), LoadSnapshot::default());
+
+        // Failed requests are no longer in flight, but do not affect the latency.
+        drop(second);
+        assert_eq!(node.load().in_flight, 1);
+        assert_eq!(node.load().ewma_latency, None);
+
+        first.succeeded();
+        assert_eq!(node.load().in_flight, 0);
+        assert_eq!(node.shard_load(2).in_flight, 0);
+        assert!(node.load().ewma_latency.is_some());
+        assert!(node.shard_load(2).ewma_latency.is_some());
+        assert!(node.latency_percentile(99.0).is_some());
+        assert_eq!(node.latency_summary().unwrap().count, 1);
+        assert_eq!(node.shard_latency_summary(2).unwrap().count, 1);
+        assert_eq!(node.shard_latency_summary(1), None);
+    }
+
+    #[test]
+    fn ewma_latency() {
+        crate::test_utils::setup_tracing();
+        let tracker = LoadTracker::default();
+
+        tracker.record_latency(Duration::from_millis(80));
+        assert_eq!(
+            tracker.snapshot().ewma_latency,
+            Some(Duration::from_millis(80))
+        );
+
+        // A new sample moves the average by 1/8 of the difference.
+        tracker.record_latency(Duration::from_millis(160));
+        assert_eq!(
+            tracker.snapshot().ewma_latency,
+            Some(Duration::from_millis(90))
+        );
+    }
+
+    #[test]
+    fn recent_latency_percentiles() {
+        crate::test_utils::setup_tracing();
+        let mut latencies = RecentLatencies::default();
+        assert_eq!(latencies.percentile(99.0), None);
+
+        for ms in 1..=100 {
+            latencies.record(Duration::from_millis(ms));
+        }
+        assert_eq!(latencies.percentile(50.0), Some(Duration::from_millis(50)));
+        assert_eq!(latencies.percentile(99.0), Some(Duration::from_millis(99)));
+        assert_eq!(
+            latencies.percentile(100.0),
+            Some(Duration::from_millis(100))
+        );
+        assert_eq!(latencies.percentile(0.0), Some(Duration::from_millis(1)));
+
+        // Only the most recent samples are taken into account.
+        for _ in 0..RecentLatencies::WINDOW_SIZE {
+            latencies.record(Duration::from_millis(5));
+        }
+        assert_eq!(latencies.percentile(99.0), Some(Duration::from_millis(5)));
+    }
 }
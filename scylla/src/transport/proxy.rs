@@ -0,0 +1,381 @@
+//! Tunneling connections to the nodes through a proxy.
+//!
+//! In some networks, the cluster can only be reached through a SOCKS5 or an HTTP proxy.
+//! With a [`ProxyConfig`] set on the session, every connection to a node is opened
+//! to the proxy, which is then asked to connect to the node:
+//! - a SOCKS5 proxy with the `CONNECT` command ([RFC 1928](https://www.rfc-editor.org/rfc/rfc1928)),
+//!   optionally authenticated with username and password ([RFC 1929](https://www.rfc-editor.org/rfc/rfc1929)),
+//! - an HTTP proxy with the `CONNECT` method, optionally authenticated
+//!   with the `Basic` scheme.
+//!
+//! The proxy establishes a single TCP connection to the node, so the driver can't choose
+//! the shard of the connection with the shard-aware port, and connects to shards at random.
+
+use std::fmt;
+use std::io;
+use std::net::SocketAddr;
+
+use base64::{engine::general_purpose, Engine as _};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const SOCKS_VERSION: u8 = 0x05;
+const SOCKS_AUTH_VERSION: u8 = 0x01;
+const SOCKS_NO_AUTH: u8 = 0x00;
+const SOCKS_USERNAME_PASSWORD: u8 = 0x02;
+const SOCKS_CONNECT: u8 = 0x01;
+const SOCKS_IPV4: u8 = 0x01;
+const SOCKS_DOMAIN_NAME: u8 = 0x03;
+const SOCKS_IPV6: u8 = 0x04;
+
+// Limits the size of the response of an HTTP proxy, which is read before
+// the connection starts carrying CQL.
+const MAX_HTTP_RESPONSE_HEADER_SIZE: usize = 8 * 1024;
+
+/// The protocol spoken with the proxy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ProxyKind {
+    /// SOCKS version 5.
+    Socks5,
+    /// HTTP with the `CONNECT` method.
+    HttpConnect,
+}
+
+/// Configuration of a proxy which tunnels the connections to the nodes.
+/// See the [module-level documentation](self) for details.
+#[derive(Clone, PartialEq, Eq)]
+pub struct ProxyConfig {
+    kind: ProxyKind,
+    address: String,
+    credentials: Option<(String, String)>,
+}
+
+impl ProxyConfig {
+    /// Creates a config of the SOCKS5 proxy at `address`, which is a hostname or an IP
+    /// address with a port, e.g. `"proxy.example.com:1080"`.
+    ///
+    /// ```
+    /// # use scylla::transport::proxy::ProxyConfig;
+    /// let config = ProxyConfig::socks5("proxy.example.com:1080").with_credentials("user", "pass");
+    /// ```
+    pub fn socks5(address: impl Into<String>) -> Self {
+        Self::new(ProxyKind::Socks5, address.into())
+    }
+
+    /// Creates a config of the HTTP proxy at `address`, which is a hostname or an IP
+    /// address with a port, e.g. `"proxy.example.com:3128"`.
+    pub fn http_connect(address: impl Into<String>) -> Self {
+        Self::new(ProxyKind::HttpConnect, address.into())
+    }
+
+    fn new(kind: ProxyKind, address: String) -> Self {
+        Self {
+            kind,
+            address,
+            credentials: None,
+        }
+    }
+
+    /// Sets the username and the password to authenticate to the proxy with.
+    pub fn with_credentials(
+        mut self,
+        username: impl Into<String>,
+        password: impl Into<String>,
+    ) -> Self {
+        self.credentials = Some((username.into(), password.into()));
+        self
+    }
+
+    /// Returns the protocol spoken with the proxy.
+    pub fn kind(&self) -> ProxyKind {
+        self.kind
+    }
+
+    /// Returns the address of the proxy.
+    pub fn address(&self) -> &str {
+        &self.address
+    }
+
+    /// Asks the proxy, connected to with `stream`, to connect to `target`.
+    /// When this returns successfully, the stream is connected to the target.
+    pub(crate) async fn establish_tunnel(
+        &self,
+        stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+        target: SocketAddr,
+    ) -> Result<(), io::Error> {
+        let credentials = self
+            .credentials
+            .as_ref()
+            .map(|(username, password)| (username.as_str(), password.as_str()));
+        match self.kind {
+            ProxyKind::Socks5 => socks5_connect(stream, target, credentials).await,
+            ProxyKind::HttpConnect => http_connect(stream, target, credentials).await,
+        }
+    }
+}
+
+// The password is not printed.
+impl fmt::Debug for ProxyConfig {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ProxyConfig")
+            .field("kind", &self.kind)
+            .field("address", &self.address)
+            .field(
+                "username",
+                &self.credentials.as_ref().map(|(username, _)| username),
+            )
+            .finish()
+    }
+}
+
+fn proxy_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::Other, message)
+}
+
+async fn socks5_connect(
+    stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    target: SocketAddr,
+    credentials: Option<(&str, &str)>,
+) -> Result<(), io::Error> {
+    // Negotiate the authentication method.
+    let method = match credentials {
+        Some(_) => SOCKS_USERNAME_PASSWORD,
+        None => SOCKS_NO_AUTH,
+    };
+    stream.write_all(&[SOCKS_VERSION, 1, method]).await?;
+    let mut response = [0u8; 2];
+    stream.read_exact(&mut response).await?;
+    if response[0] != SOCKS_VERSION {
+        return Err(proxy_error(format!(
+            "SOCKS proxy responded with unsupported version {}",
+            response[0]
+        )));
+    }
+    if response[1] != method {
+        return Err(proxy_error(
+            "SOCKS proxy rejected the authentication method".to_owned(),
+        ));
+    }
+
+    if let Some((username, password)) = credentials {
+        let mut request = vec![SOCKS_AUTH_VERSION];
+        for field in [username, password] {
+            let len = u8::try_from(field.len()).map_err(|_| {
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "SOCKS username and password can't be longer than 255 bytes",
+                )
+            })?;
+            request.push(len);
+            request.extend_from_slice(field.as_bytes());
+        }
+        stream.write_all(&request).await?;
+        stream.read_exact(&mut response).await?;
+        if response[1] != 0 {
+            return Err(proxy_error(
+                "SOCKS proxy rejected the credentials".to_owned(),
+            ));
+        }
+    }
+
+    // Request the connection to the target.
+    let mut request = vec![SOCKS_VERSION, SOCKS_CONNECT, 0];
+    match target {
+        SocketAddr::V4(addr) => {
+            request.push(SOCKS_IPV4);
+            request.extend_from_slice(&addr.ip().octets());
+        }
+        SocketAddr::V6(addr) => {
+            request.push(SOCKS_IPV6);
+            request.extend_from_slice(&addr.ip().octets());
+        }
+    }
+    request.extend_from_slice(&target.port().to_be_bytes());
+    stream.write_all(&request).await?;
+
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != 0 {
+        return Err(proxy_error(format!(
+            "SOCKS proxy failed to connect to {} (reply code {})",
+            target, reply[1]
+        )));
+    }
+    // Skip the address which the proxy bound to connect to the target.
+    let address_len = match reply[3] {
+        SOCKS_IPV4 => 4,
+        SOCKS_IPV6 => 16,
+        SOCKS_DOMAIN_NAME => stream.read_u8().await? as usize,
+        other => {
+            return Err(proxy_error(format!(
+                "SOCKS proxy responded with unknown address type {}",
+                other
+            )))
+        }
+    };
+    let mut bound_address = vec![0u8; address_len + 2];
+    stream.read_exact(&mut bound_address).await?;
+    Ok(())
+}
+
+async fn http_connect(
+    stream: &mut (impl AsyncRead + AsyncWrite + Unpin),
+    target: SocketAddr,
+    credentials: Option<(&str, &str)>,
+) -> Result<(), io::Error> {
+    let mut request = format!("CONNECT {target} HTTP/1.1\r\nHost: {target}\r\n");
+    if let Some((username, password)) = credentials {
+        let encoded = general_purpose::STANDARD.encode(format!("{username}:{password}"));
+        request.push_str(&format!("Proxy-Authorization: Basic {encoded}\r\n"));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    // The response is read byte by byte, so that no data sent after it is consumed.
+    let mut response = Vec::new();
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_HTTP_RESPONSE_HEADER_SIZE {
+            return Err(proxy_error(
+                "HTTP proxy sent a too long response".to_owned(),
+            ));
+        }
+        response.push(stream.read_u8().await?);
+    }
+
+    let response = String::from_utf8_lossy(&response);
+    let status_line = response.lines().next().unwrap_or_default();
+    let status = status_line
+        .split_whitespace()
+        .nth(1)
+        .and_then(|status| status.parse::<u16>().ok());
+    match status {
+        Some(200..=299) => Ok(()),
+        _ => Err(proxy_error(format!(
+            "HTTP proxy failed to connect to {}: {}",
+            target, status_line
+        ))),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    use super::ProxyConfig;
+    use crate::test_utils::setup_tracing;
+
+    #[tokio::test]
+    async fn socks5_handshake() {
+        setup_tracing();
+        let target = SocketAddr::from_str("10.0.0.1:9042").unwrap();
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+
+        let proxy_task = tokio::spawn(async move {
+            let mut greeting = [0u8; 3];
+            proxy.read_exact(&mut greeting).await.unwrap();
+            assert_eq!(greeting, [5, 1, 2]);
+            proxy.write_all(&[5, 2]).await.unwrap();
+
+            let mut auth = [0u8; 11];
+            proxy.read_exact(&mut auth).await.unwrap();
+            assert_eq!(&auth, b"\x01\x04user\x04pass");
+            proxy.write_all(&[1, 0]).await.unwrap();
+
+            let mut request = [0u8; 10];
+            proxy.read_exact(&mut request).await.unwrap();
+            assert_eq!(request, [5, 1, 0, 1, 10, 0, 0, 1, 0x23, 0x52]);
+            proxy
+                .write_all(&[5, 0, 0, 1, 127, 0, 0, 1, 0x12, 0x34])
+                .await
+                .unwrap();
+            proxy.write_all(b"CQL").await.unwrap();
+            proxy
+        });
+
+        ProxyConfig::socks5("127.0.0.1:1080")
+            .with_credentials("user", "pass")
+            .establish_tunnel(&mut client, target)
+            .await
+            .unwrap();
+        // The data sent after the reply belongs to the tunneled connection.
+        let mut data = [0u8; 3];
+        client.read_exact(&mut data).await.unwrap();
+        assert_eq!(&data, b"CQL");
+        proxy_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn socks5_connection_refused() {
+        setup_tracing();
+        let target = SocketAddr::from_str("10.0.0.1:9042").unwrap();
+        let (mut client, mut proxy) = tokio::io::duplex(1024);
+
+        let proxy_task = tokio::spawn(async move {
+            let mut greeting = [0u8; 3];
+            proxy.read_exact(&mut greeting).await.unwrap();
+            proxy.write_all(&[5, 0]).await.unwrap();
+            let mut request = [0u8; 10];
+            proxy.read_exact(&mut request).await.unwrap();
+            proxy
+                .write_all(&[5, 5, 0, 1, 0, 0, 0, 0, 0, 0])
+                .await
+                .unwrap();
+            proxy
+        });
+
+        let err = ProxyConfig::socks5("127.0.0.1:1080")
+            .establish_tunnel(&mut client, target)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("reply code 5"));
+        proxy_task.await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn http_connect_handshake() {
+        setup_tracing();
+        let target = SocketAddr::from_str("10.0.0.1:9042").unwrap();
+
+        for (status_line, succeeds) in [
+            ("HTTP/1.1 200 Connection established", true),
+            ("HTTP/1.1 407 Proxy Authentication Required", false),
+        ] {
+            let (mut client, mut proxy) = tokio::io::duplex(1024);
+            let proxy_task = tokio::spawn(async move {
+                let mut request = Vec::new();
+                while !request.ends_with(b"\r\n\r\n") {
+                    request.push(proxy.read_u8().await.unwrap());
+                }
+                assert_eq!(
+                    String::from_utf8(request).unwrap(),
+                    "CONNECT 10.0.0.1:9042 HTTP/1.1\r\nHost: 10.0.0.1:9042\r\n\
+                     Proxy-Authorization: Basic dXNlcjpwYXNz\r\n\r\n"
+                );
+                proxy
+                    .write_all(format!("{status_line}\r\nServer: test\r\n\r\n").as_bytes())
+                    .await
+                    .unwrap();
+                proxy
+            });
+
+            let result = ProxyConfig::http_connect("127.0.0.1:3128")
+                .with_credentials("user", "pass")
+                .establish_tunnel(&mut client, target)
+                .await;
+            assert_eq!(result.is_ok(), succeeds);
+            proxy_task.await.unwrap();
+        }
+    }
+
+    #[test]
+    fn debug_hides_password() {
+        setup_tracing();
+        let config = ProxyConfig::socks5("127.0.0.1:1080").with_credentials("user", "secret");
+        let debug = format!("{:?}", config);
+        assert!(debug.contains("user"));
+        assert!(!debug.contains("secret"));
+    }
+}
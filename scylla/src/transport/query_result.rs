@@ -5,12 +5,18 @@ use uuid::Uuid;
 
 use scylla_cql::frame::frame_errors::ResultMetadataAndRowsCountParseError;
 use scylla_cql::frame::response::result::{
-    ColumnSpec, ColumnType, DeserializedMetadataAndRawRows, RawMetadataAndRawRows, Row, TableSpec,
+    ColumnIndex, ColumnSpec, ColumnType, DeserializedMetadataAndRawRows, RawMetadataAndRawRows,
+    Row, TableSpec,
 };
-use scylla_cql::types::deserialize::result::TypedRowIterator;
-use scylla_cql::types::deserialize::row::DeserializeRow;
+use scylla_cql::types::deserialize::audit::EmptyValueAudit;
+use scylla_cql::types::deserialize::result::{
+    OwnedTypedRowIterator, RawRowLendingIterator, TypedRowIterator,
+};
+use scylla_cql::types::deserialize::row::{ColumnIterator, DeserializeRow};
+use scylla_cql::types::deserialize::value::DeserializeValue;
 use scylla_cql::types::deserialize::{DeserializationError, TypeCheckError};
 
+use super::errors::QueryError;
 #[allow(deprecated)]
 use super::legacy_query_result::{IntoLegacyQueryResultError, LegacyQueryResult};
 
@@ -118,6 +124,13 @@ impl<'res> ColumnSpecs<'res> {
             .map(|(idx, spec)| (idx, ColumnSpecView::new_from_column_spec(spec)))
     }
 
+    /// Builds an index of the columns by name, for accessing columns of many rows
+    /// by name without repeating the lookups performed by [`ColumnSpecs::get_by_name`].
+    #[inline]
+    pub fn column_index(&self) -> ColumnIndex {
+        ColumnIndex::new(self.specs)
+    }
+
     /// Returns iterator over specification of columns returned from the database,
     /// ordered by column order in the response.
     #[inline]
@@ -256,6 +269,38 @@ impl QueryResult {
         })
     }
 
+    /// Parses the result of a conditional statement (a lightweight transaction),
+    /// i.e. the `[applied]` column and the existing row returned when
+    /// the statement was not applied. See [`LwtResult`].
+    ///
+    /// ```rust
+    /// # use scylla::Session;
+    /// # async fn example(session: &Session) -> Result<(), Box<dyn std::error::Error>> {
+    /// use scylla::LwtResult;
+    ///
+    /// let result = session
+    ///     .query_unpaged(
+    ///         "INSERT INTO ks.users (id, name) VALUES (?, ?) IF NOT EXISTS",
+    ///         (1, "Alice"),
+    ///     )
+    ///     .await?
+    ///     .lwt_result::<(i32, String)>()?;
+    /// match result {
+    ///     LwtResult::Applied => println!("Inserted"),
+    ///     LwtResult::NotApplied { existing: (id, name) } => {
+    ///         println!("User {} already exists as {}", id, name)
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn lwt_result<T>(self) -> Result<LwtResult<T>, LwtResultError>
+    where
+        T: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>,
+    {
+        Ok(self.into_rows_result()?.lwt_result::<T>()?)
+    }
+
     /// Transforms itself into the legacy result type, by eagerly deserializing rows
     /// into the Row type. This is inefficient, and should only be used during transition
     /// period to the new API.
@@ -371,6 +416,32 @@ impl QueryRowsResult {
             .map_err(RowsError::TypeCheckFailed)
     }
 
+    /// Returns an iterator over the received rows, which takes ownership of the result.
+    ///
+    /// Unlike the iterator returned by [`QueryRowsResult::rows`], it does not borrow
+    /// from the result, so it can be returned from functions or moved into spawned tasks.
+    /// The rows have to be deserialized into types which don't borrow from the result either.
+    ///
+    /// Returns an error if the rows in the response are of incorrect type.
+    #[inline]
+    pub fn into_rows<R>(self) -> Result<OwnedTypedRowIterator<R>, RowsError>
+    where
+        R: for<'frame, 'metadata> DeserializeRow<'frame, 'metadata>,
+    {
+        OwnedTypedRowIterator::new(RawRowLendingIterator::new(self.raw_rows_with_metadata))
+            .map_err(RowsError::TypeCheckFailed)
+    }
+
+    /// Records the `NULL` and empty cells of the received rows in the given audit.
+    ///
+    /// See [`EmptyValueAudit`] for details.
+    pub fn audit_empty_values(
+        &self,
+        audit: &mut EmptyValueAudit,
+    ) -> Result<(), DeserializationError> {
+        audit.record_rows(self.raw_rows_with_metadata.raw_rows_iter())
+    }
+
     /// Returns `Option<R>` containing the first row of the result.
     ///
     /// Fails when the the rows in the response are of incorrect type,
@@ -434,6 +505,63 @@ impl QueryRowsResult {
         }
     }
 
+    /// Returns the value of the only column of the only received row,
+    /// e.g. of `SELECT count(*) FROM ...`.
+    ///
+    /// Fails if the result has other than one column, or other than one row.
+    /// Fails when the column is of incorrect type, or when the deserialization fails.
+    pub fn single_value<'frame, T: DeserializeValue<'frame, 'frame>>(
+        &'frame self,
+    ) -> Result<T, SingleValueError> {
+        self.check_single_column()?;
+        match self.single_row::<(T,)>() {
+            Ok((value,)) => Ok(value),
+            Err(SingleRowError::UnexpectedRowCount(count)) => {
+                Err(SingleValueError::UnexpectedRowCount(count))
+            }
+            Err(SingleRowError::TypeCheckFailed(err)) => {
+                Err(SingleValueError::TypeCheckFailed(err))
+            }
+            Err(SingleRowError::DeserializationFailed(err)) => {
+                Err(SingleValueError::DeserializationFailed(err))
+            }
+        }
+    }
+
+    /// Returns the value of the only column of the first received row.
+    ///
+    /// Fails if the result has other than one column, or no rows -
+    /// in the latter case with [`SingleValueError::UnexpectedRowCount`] of 0.
+    /// Fails when the column is of incorrect type, or when the deserialization fails.
+    pub fn first_value<'frame, T: DeserializeValue<'frame, 'frame>>(
+        &'frame self,
+    ) -> Result<T, SingleValueError> {
+        self.check_single_column()?;
+        match self.first_row::<(T,)>() {
+            Ok((value,)) => Ok(value),
+            Err(FirstRowError::RowsEmpty) => Err(SingleValueError::UnexpectedRowCount(0)),
+            Err(FirstRowError::TypeCheckFailed(err)) => Err(SingleValueError::TypeCheckFailed(err)),
+            Err(FirstRowError::DeserializationFailed(err)) => {
+                Err(SingleValueError::DeserializationFailed(err))
+            }
+        }
+    }
+
+    fn check_single_column(&self) -> Result<(), SingleValueError> {
+        match self.column_specs().len() {
+            1 => Ok(()),
+            count => Err(SingleValueError::UnexpectedColumnCount(count)),
+        }
+    }
+
+    /// Parses the only received row as the result of a conditional statement.
+    /// See [`QueryResult::lwt_result`].
+    pub fn lwt_result<'frame, T: DeserializeRow<'frame, 'frame>>(
+        &'frame self,
+    ) -> Result<LwtResult<T>, SingleRowError> {
+        self.single_row::<LwtResult<T>>()
+    }
+
     #[cfg(cpp_rust_unstable)]
     pub fn into_inner(self) -> (DeserializedMetadataAndRawRows, Option<Uuid>, Vec<String>) {
         let Self {
@@ -446,6 +574,88 @@ impl QueryRowsResult {
     }
 }
 
+/// The result of a conditional statement (a lightweight transaction).
+///
+/// The database returns the `[applied]` column, followed by the columns of the existing row,
+/// which are deserialized into `T` if the statement was not applied. ScyllaDB returns
+/// these columns also when the statement was applied, but they are ignored then.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LwtResult<T> {
+    /// The condition was met and the statement was applied.
+    Applied,
+
+    /// The condition was not met and the statement was not applied.
+    NotApplied {
+        /// The existing row, or the columns of the condition.
+        existing: T,
+    },
+}
+
+impl<T> LwtResult<T> {
+    /// Returns `true` if the statement was applied.
+    pub fn is_applied(&self) -> bool {
+        matches!(self, Self::Applied)
+    }
+}
+
+const APPLIED_COLUMN: &str = "[applied]";
+
+impl<'frame, 'metadata, T> DeserializeRow<'frame, 'metadata> for LwtResult<T>
+where
+    T: DeserializeRow<'frame, 'metadata>,
+{
+    fn type_check(specs: &[ColumnSpec]) -> Result<(), TypeCheckError> {
+        match specs.split_first() {
+            Some((applied, rest))
+                if applied.name() == APPLIED_COLUMN && applied.typ() == &ColumnType::Boolean =>
+            {
+                // Apache Cassandra returns only the `[applied]` column if the statement was applied.
+                if rest.is_empty() {
+                    Ok(())
+                } else {
+                    T::type_check(rest)
+                }
+            }
+            _ => Err(TypeCheckError::new(NotLwtResultError)),
+        }
+    }
+
+    fn deserialize(
+        mut row: ColumnIterator<'frame, 'metadata>,
+    ) -> Result<Self, DeserializationError> {
+        let applied = row
+            .next()
+            .ok_or_else(|| DeserializationError::new(NotLwtResultError))??;
+        let applied = <bool as DeserializeValue>::deserialize(applied.spec.typ(), applied.slice)?;
+        if applied {
+            Ok(Self::Applied)
+        } else {
+            Ok(Self::NotApplied {
+                existing: T::deserialize(row)?,
+            })
+        }
+    }
+}
+
+/// An error returned when a result parsed as a [`LwtResult`] doesn't start
+/// with the boolean `[applied]` column, i.e. it is not a result of a conditional statement.
+#[derive(Debug, Error, Clone)]
+#[error("The result doesn't start with the boolean [applied] column")]
+pub struct NotLwtResultError;
+
+/// An error returned by [`QueryResult::lwt_result`].
+#[derive(Debug, Error)]
+pub enum LwtResultError {
+    /// The result is not of Rows kind, or its metadata could not be deserialized.
+    #[error(transparent)]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// The result is not a single row of a conditional statement,
+    /// or it could not be deserialized.
+    #[error(transparent)]
+    SingleRowError(#[from] SingleRowError),
+}
+
 /// An error returned by [`QueryResult::into_rows_result`]
 ///
 /// The `ResultNotRows` variant contains original [`QueryResult`],
@@ -514,6 +724,43 @@ pub enum SingleRowError {
     DeserializationFailed(#[from] DeserializationError),
 }
 
+/// An error returned by [`QueryRowsResult::single_value`] and [`QueryRowsResult::first_value`].
+#[derive(Debug, Error, Clone)]
+pub enum SingleValueError {
+    /// Expected one row, but got a different count
+    #[error("Expected a single row, but got {0} rows")]
+    UnexpectedRowCount(usize),
+
+    /// Expected one column, but got a different count
+    #[error("Expected a single column, but got {0} columns")]
+    UnexpectedColumnCount(usize),
+
+    /// Type check failed
+    #[error("Type check failed: {0}")]
+    TypeCheckFailed(#[from] TypeCheckError),
+
+    /// Deserialization failed
+    #[error("Deserialization failed: {0}")]
+    DeserializationFailed(#[from] DeserializationError),
+}
+
+/// An error returned by [`Session::query_single_value`](crate::Session::query_single_value)
+/// and [`Session::execute_single_value`](crate::Session::execute_single_value).
+#[derive(Debug, Error)]
+pub enum SingleValueQueryError {
+    /// Failed to execute the statement.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// The result is not of Rows kind, or its metadata could not be deserialized.
+    #[error(transparent)]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// The result is not a single value, or it could not be deserialized.
+    #[error(transparent)]
+    SingleValueError(#[from] SingleValueError),
+}
+
 /// An error returned by [`QueryResult::result_not_rows`].
 ///
 /// It indicates that response to the request was, unexpectedly, of Rows kind.
@@ -548,6 +795,113 @@ mod tests {
         })
     }
 
+    #[test]
+    fn test_lwt_result() {
+        fn lwt_result(applied: bool, name: Option<&str>) -> QueryResult {
+            let specs = vec![
+                ColumnSpec::borrowed("[applied]", ColumnType::Boolean, TABLE_SPEC),
+                ColumnSpec::borrowed("id", ColumnType::Int, TABLE_SPEC),
+                ColumnSpec::borrowed("name", ColumnType::Text, TABLE_SPEC),
+            ];
+            let mut bytes = BytesMut::new();
+            types::write_bytes(&[applied as u8], &mut bytes).unwrap();
+            types::write_bytes_opt(name.map(|_| 1_i32.to_be_bytes()), &mut bytes).unwrap();
+            types::write_bytes_opt(name, &mut bytes).unwrap();
+            let metadata = ResultMetadata::new_for_test(specs.len(), specs);
+            let raw_rows =
+                RawMetadataAndRawRows::new_for_test(None, Some(metadata), false, 1, &bytes)
+                    .unwrap();
+            QueryResult::new(Some(raw_rows), None, Vec::new())
+        }
+
+        // ScyllaDB returns null columns when the statement was applied.
+        assert_eq!(
+            lwt_result(true, None)
+                .lwt_result::<(i32, String)>()
+                .unwrap(),
+            LwtResult::Applied
+        );
+        assert_eq!(
+            lwt_result(false, Some("Alice"))
+                .lwt_result::<(i32, String)>()
+                .unwrap(),
+            LwtResult::NotApplied {
+                existing: (1, "Alice".to_owned())
+            }
+        );
+        assert_matches!(
+            lwt_result(false, Some("Alice")).lwt_result::<(i32,)>(),
+            Err(LwtResultError::SingleRowError(
+                SingleRowError::TypeCheckFailed(_)
+            ))
+        );
+
+        // Not a result of a conditional statement.
+        let metadata =
+            ResultMetadata::new_for_test(2, column_spec_infinite_iter().take(2).collect());
+        let raw_rows =
+            RawMetadataAndRawRows::new_for_test(None, Some(metadata), false, 0, &[]).unwrap();
+        let err = QueryResult::new(Some(raw_rows), None, Vec::new())
+            .into_rows_result()
+            .unwrap()
+            .lwt_result::<(bool,)>()
+            .unwrap_err();
+        let SingleRowError::TypeCheckFailed(err) = err else {
+            panic!("unexpected error: {:?}", err);
+        };
+        assert!(err.downcast_ref::<NotLwtResultError>().is_some());
+    }
+
+    #[test]
+    fn test_single_value() {
+        fn int_rows(columns: usize, rows: &[i32]) -> QueryRowsResult {
+            let specs = (0..columns)
+                .map(|k| ColumnSpec::owned(format!("col_{}", k), ColumnType::Int, TABLE_SPEC))
+                .collect::<Vec<_>>();
+            let mut bytes = BytesMut::new();
+            for row in rows {
+                for _ in 0..columns {
+                    types::write_bytes(&row.to_be_bytes(), &mut bytes).unwrap();
+                }
+            }
+            let metadata = ResultMetadata::new_for_test(specs.len(), specs);
+            let raw_rows = RawMetadataAndRawRows::new_for_test(
+                None,
+                Some(metadata),
+                false,
+                rows.len(),
+                &bytes,
+            )
+            .unwrap();
+            QueryResult::new(Some(raw_rows), None, Vec::new())
+                .into_rows_result()
+                .unwrap()
+        }
+
+        assert_eq!(int_rows(1, &[42]).single_value::<i32>().unwrap(), 42);
+        assert_eq!(int_rows(1, &[42, 7]).first_value::<i32>().unwrap(), 42);
+        assert_matches!(
+            int_rows(1, &[42, 7]).single_value::<i32>(),
+            Err(SingleValueError::UnexpectedRowCount(2))
+        );
+        assert_matches!(
+            int_rows(1, &[]).single_value::<i32>(),
+            Err(SingleValueError::UnexpectedRowCount(0))
+        );
+        assert_matches!(
+            int_rows(1, &[]).first_value::<i32>(),
+            Err(SingleValueError::UnexpectedRowCount(0))
+        );
+        assert_matches!(
+            int_rows(2, &[42]).single_value::<i32>(),
+            Err(SingleValueError::UnexpectedColumnCount(2))
+        );
+        assert_matches!(
+            int_rows(1, &[42]).single_value::<String>(),
+            Err(SingleValueError::TypeCheckFailed(_))
+        );
+    }
+
     #[test]
     fn test_query_result() {
         fn serialize_cells(cells: impl IntoIterator<Item = Option<impl AsRef<[u8]>>>) -> Bytes {
@@ -646,6 +1000,13 @@ mod tests {
                     }
 
                     assert_matches!(column_specs.get_by_name("ala ma kota"), None);
+
+                    let index = column_specs.column_index();
+                    for (idx, expected_col_spec) in column_spec_infinite_iter().enumerate().take(n)
+                    {
+                        assert_eq!(index.position(expected_col_spec.name()), Some(idx));
+                    }
+                    assert_eq!(index.position("ala ma kota"), None);
                 }
 
                 // By iter
@@ -813,6 +1174,22 @@ mod tests {
                         Err(SingleRowError::UnexpectedRowCount(2))
                     );
                 }
+
+                // Owned iterator
+                {
+                    let other_qr = QueryResult::new(Some(sample_raw_rows(2, 2)), None, Vec::new())
+                        .into_rows_result()
+                        .unwrap();
+                    assert_matches!(
+                        other_qr.into_rows::<(i32, i32)>(),
+                        Err(RowsError::TypeCheckFailed(_))
+                    );
+
+                    let mut rows = qr.into_rows::<(String, bool)>().unwrap();
+                    assert_eq!(rows.rows_remaining(), 2);
+                    // Only the first row is serialized in the sample.
+                    assert_eq!(rows.next().unwrap().unwrap(), ("MOCK".to_owned(), true));
+                }
             }
         }
     }
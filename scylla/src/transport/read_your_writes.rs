@@ -0,0 +1,277 @@
+//! Read-your-writes on top of write timestamp tracking.
+//!
+//! When enabled with [`SessionBuilder::track_write_timestamps`], the session assigns
+//! client-side timestamps to the writes it executes as prepared statements, and remembers
+//! the timestamp of the last write to each of the recently written partitions.
+//! [`Session::execute_read_your_writes`] then repeats a read until it reflects the last write
+//! to the read partition, which provides monotonic reads without using QUORUM everywhere.
+//!
+//! A deletion leaves no write timestamp to read, so after a `DELETE`, a read which
+//! reflects no write at all is considered up to date as well.
+//!
+//! [`SessionBuilder::track_write_timestamps`]: crate::transport::session_builder::GenericSessionBuilder::track_write_timestamps
+//! [`Session::execute_read_your_writes`]: crate::Session::execute_read_your_writes
+
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+use scylla_cql::frame::response::result::TableSpec;
+use thiserror::Error;
+
+use crate::statement::prepared_statement::PartitionKeyError;
+use crate::statement::WriteKind;
+use crate::transport::errors::QueryError;
+use crate::transport::query_result::IntoRowsResultError;
+
+/// Configuration of [`Session::execute_read_your_writes`](crate::Session::execute_read_your_writes).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ReadYourWritesConfig {
+    /// The maximum number of times the read is executed.
+    pub max_attempts: u32,
+
+    /// Delay between the attempts.
+    pub retry_delay: Duration,
+}
+
+impl ReadYourWritesConfig {
+    /// Creates a config with the given number of attempts and delay between them.
+    pub fn new(max_attempts: u32, retry_delay: Duration) -> Self {
+        Self {
+            max_attempts,
+            retry_delay,
+        }
+    }
+}
+
+impl Default for ReadYourWritesConfig {
+    fn default() -> Self {
+        Self::new(5, Duration::from_millis(50))
+    }
+}
+
+/// An error returned by [`Session::execute_read_your_writes`](crate::Session::execute_read_your_writes).
+#[derive(Error, Debug)]
+#[non_exhaustive]
+pub enum ReadYourWritesError {
+    /// Executing the read failed.
+    #[error(transparent)]
+    QueryError(#[from] QueryError),
+
+    /// The partition key of the read could not be computed from the bound values.
+    #[error("Failed to compute the partition key: {0}")]
+    PartitionKeyError(#[from] PartitionKeyError),
+
+    /// The read returned a result of other kind than Rows.
+    #[error("Failed to convert the result into rows result: {0}")]
+    IntoRowsResultError(#[from] IntoRowsResultError),
+
+    /// None of the attempts reflected the last write to the partition.
+    #[error(
+        "The read did not reflect the write at timestamp {expected} after {attempts} attempts \
+         (the latest timestamp read: {read:?})"
+    )]
+    StaleRead {
+        /// The timestamp of the last write to the partition.
+        expected: i64,
+        /// The latest timestamp the read has seen, if any.
+        read: Option<i64>,
+        /// The number of attempts made.
+        attempts: u32,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+struct PartitionRef {
+    keyspace: String,
+    table: String,
+    partition_key: Bytes,
+}
+
+impl PartitionRef {
+    fn new(table: &TableSpec, partition_key: Bytes) -> Self {
+        Self {
+            keyspace: table.ks_name().to_owned(),
+            table: table.table_name().to_owned(),
+            partition_key,
+        }
+    }
+}
+
+/// The last tracked write to a partition.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) struct LastWrite {
+    pub(crate) timestamp: i64,
+    pub(crate) kind: WriteKind,
+}
+
+impl LastWrite {
+    /// Checks whether a read which reflects the write at the given timestamp,
+    /// or no write if `None`, is up to date with this write.
+    pub(crate) fn is_reflected_by(&self, read: Option<i64>) -> bool {
+        match read {
+            Some(read) => read >= self.timestamp,
+            None => self.kind == WriteKind::Delete,
+        }
+    }
+
+    // Of two writes with the same timestamp, the deletion wins.
+    fn is_newer_than(&self, other: &LastWrite) -> bool {
+        (self.timestamp, self.kind == WriteKind::Delete)
+            > (other.timestamp, other.kind == WriteKind::Delete)
+    }
+}
+
+#[derive(Debug, Default)]
+struct TrackedPartitions {
+    writes: HashMap<PartitionRef, LastWrite>,
+    // Partitions in the order they were first written, the oldest are forgotten first.
+    order: VecDeque<PartitionRef>,
+}
+
+/// Remembers the timestamps of the last writes to at most `capacity` partitions.
+#[derive(Debug)]
+pub(crate) struct WriteTimestampTracker {
+    capacity: usize,
+    last_generated: AtomicI64,
+    partitions: Mutex<TrackedPartitions>,
+}
+
+impl WriteTimestampTracker {
+    pub(crate) fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            last_generated: AtomicI64::new(i64::MIN),
+            partitions: Default::default(),
+        }
+    }
+
+    /// Generates a timestamp for a write: microseconds since the Unix epoch,
+    /// strictly increasing even if the clock goes back.
+    pub(crate) fn next_timestamp(&self) -> i64 {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_or(0, |since_epoch| since_epoch.as_micros() as i64);
+        let previous = self
+            .last_generated
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |last| {
+                Some(now.max(last.saturating_add(1)))
+            })
+            .unwrap();
+        now.max(previous.saturating_add(1))
+    }
+
+    pub(crate) fn record(&self, table: &TableSpec, partition_key: Bytes, write: LastWrite) {
+        let partition = PartitionRef::new(table, partition_key);
+        let mut partitions = self.partitions.lock().unwrap();
+        let TrackedPartitions { writes, order } = &mut *partitions;
+        match writes.get_mut(&partition) {
+            Some(last) => {
+                if write.is_newer_than(last) {
+                    *last = write;
+                }
+            }
+            None => {
+                writes.insert(partition.clone(), write);
+                order.push_back(partition);
+                while order.len() > self.capacity {
+                    if let Some(forgotten) = order.pop_front() {
+                        writes.remove(&forgotten);
+                    }
+                }
+            }
+        }
+    }
+
+    pub(crate) fn last_write(&self, table: &TableSpec, partition_key: Bytes) -> Option<LastWrite> {
+        let partition = PartitionRef::new(table, partition_key);
+        self.partitions
+            .lock()
+            .unwrap()
+            .writes
+            .get(&partition)
+            .copied()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bytes::Bytes;
+    use scylla_cql::frame::response::result::TableSpec;
+
+    use super::{LastWrite, WriteTimestampTracker};
+    use crate::statement::WriteKind;
+    use crate::test_utils::setup_tracing;
+
+    fn upsert(timestamp: i64) -> LastWrite {
+        LastWrite {
+            timestamp,
+            kind: WriteKind::Upsert,
+        }
+    }
+
+    fn delete(timestamp: i64) -> LastWrite {
+        LastWrite {
+            timestamp,
+            kind: WriteKind::Delete,
+        }
+    }
+
+    #[test]
+    fn timestamps_are_increasing() {
+        setup_tracing();
+        let tracker = WriteTimestampTracker::new(10);
+        let first = tracker.next_timestamp();
+        let second = tracker.next_timestamp();
+        assert!(first < second);
+    }
+
+    #[test]
+    fn tracking_partitions() {
+        setup_tracing();
+        let tracker = WriteTimestampTracker::new(2);
+        let table = TableSpec::borrowed("ks", "t");
+        let other_table = TableSpec::borrowed("ks", "u");
+        let pk = |key: &'static [u8]| Bytes::from_static(key);
+
+        tracker.record(&table, pk(b"a"), upsert(10));
+        assert_eq!(tracker.last_write(&table, pk(b"a")), Some(upsert(10)));
+        assert_eq!(tracker.last_write(&other_table, pk(b"a")), None);
+
+        // An older write does not move the timestamp back.
+        tracker.record(&table, pk(b"a"), delete(5));
+        assert_eq!(tracker.last_write(&table, pk(b"a")), Some(upsert(10)));
+        tracker.record(&table, pk(b"a"), upsert(20));
+        assert_eq!(tracker.last_write(&table, pk(b"a")), Some(upsert(20)));
+
+        // A deletion wins over a write with the same timestamp.
+        tracker.record(&table, pk(b"a"), delete(20));
+        tracker.record(&table, pk(b"a"), upsert(20));
+        assert_eq!(tracker.last_write(&table, pk(b"a")), Some(delete(20)));
+
+        // The partition written first is forgotten when over capacity.
+        tracker.record(&other_table, pk(b"a"), upsert(30));
+        tracker.record(&table, pk(b"b"), upsert(40));
+        assert_eq!(tracker.last_write(&table, pk(b"a")), None);
+        assert_eq!(tracker.last_write(&other_table, pk(b"a")), Some(upsert(30)));
+        assert_eq!(tracker.last_write(&table, pk(b"b")), Some(upsert(40)));
+    }
+
+    #[test]
+    fn reflected_writes() {
+        setup_tracing();
+        assert!(upsert(10).is_reflected_by(Some(10)));
+        assert!(upsert(10).is_reflected_by(Some(11)));
+        assert!(!upsert(10).is_reflected_by(Some(9)));
+        assert!(!upsert(10).is_reflected_by(None));
+
+        // A read after a deletion reflects it if it finds nothing,
+        // or a write at least as recent as the deletion.
+        assert!(delete(10).is_reflected_by(None));
+        assert!(delete(10).is_reflected_by(Some(10)));
+        assert!(!delete(10).is_reflected_by(Some(9)));
+    }
+}
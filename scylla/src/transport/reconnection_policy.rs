@@ -0,0 +1,202 @@
+//! Reconnection policies\
+//! The connection pool of a node whose connections fail to open, e.g. because the node
+//! is down, retries opening them after a delay. The delays are decided by an object which
+//! implements the `ReconnectionPolicy` trait.
+
+use std::time::Duration;
+
+use rand::Rng;
+
+/// Specifies a policy used to decide how long to wait before retrying
+/// to open the connections of a connection pool.
+pub trait ReconnectionPolicy: std::fmt::Debug + Send + Sync {
+    /// Called for each new connection pool, starts a schedule of delays for the pool.
+    fn new_schedule(&self) -> Box<dyn ReconnectionSchedule>;
+}
+
+/// Used throughout the lifetime of a single connection pool to decide
+/// the delays between attempts to fill it.
+pub trait ReconnectionSchedule: Send + Sync {
+    /// Called after an attempt to fill the pool in which some connection failed to open.
+    /// Returns the delay before the next attempt.
+    fn next_delay_after_failure(&mut self) -> Duration;
+
+    /// Called after an attempt to fill the pool in which all connections were opened,
+    /// but the pool is still not full, e.g. because the connections landed on the shards
+    /// which already had enough connections. Returns the delay before the next attempt.
+    /// The following failures are counted from scratch.
+    fn next_delay_after_success(&mut self) -> Duration;
+}
+
+/// Waits the same, fixed delay before each attempt.
+#[derive(Debug, Clone)]
+pub struct ConstantReconnectionPolicy {
+    /// The delay before each attempt
+    pub delay: Duration,
+}
+
+impl ConstantReconnectionPolicy {
+    pub fn new(delay: Duration) -> Self {
+        Self { delay }
+    }
+}
+
+impl ReconnectionPolicy for ConstantReconnectionPolicy {
+    fn new_schedule(&self) -> Box<dyn ReconnectionSchedule> {
+        Box::new(ConstantReconnectionSchedule { delay: self.delay })
+    }
+}
+
+struct ConstantReconnectionSchedule {
+    delay: Duration,
+}
+
+impl ReconnectionSchedule for ConstantReconnectionSchedule {
+    fn next_delay_after_failure(&mut self) -> Duration {
+        self.delay
+    }
+
+    fn next_delay_after_success(&mut self) -> Duration {
+        self.delay
+    }
+}
+
+/// Doubles the delay after each consecutive failure, starting from `base_delay`
+/// and up to `max_delay`. This is the default policy, with the delays
+/// between 50 milliseconds and 10 seconds.
+#[derive(Debug, Clone)]
+pub struct ExponentialReconnectionPolicy {
+    /// The delay after a successful attempt, and the starting point of the delays after failures
+    pub base_delay: Duration,
+
+    /// The maximum delay
+    pub max_delay: Duration,
+
+    /// If true, each delay is randomized to between half of its value and its full value,
+    /// so that many clients don't retry connecting to a restarted node at the same time.
+    pub jitter: bool,
+}
+
+impl ExponentialReconnectionPolicy {
+    pub fn new(base_delay: Duration, max_delay: Duration) -> Self {
+        Self {
+            base_delay,
+            max_delay,
+            jitter: false,
+        }
+    }
+
+    /// Enables randomizing the delays.
+    pub fn with_jitter(mut self) -> Self {
+        self.jitter = true;
+        self
+    }
+}
+
+impl Default for ExponentialReconnectionPolicy {
+    fn default() -> Self {
+        Self::new(Duration::from_millis(50), Duration::from_secs(10))
+    }
+}
+
+impl ReconnectionPolicy for ExponentialReconnectionPolicy {
+    fn new_schedule(&self) -> Box<dyn ReconnectionSchedule> {
+        Box::new(ExponentialReconnectionSchedule {
+            policy: self.clone(),
+            consecutive_failures: 0,
+        })
+    }
+}
+
+struct ExponentialReconnectionSchedule {
+    policy: ExponentialReconnectionPolicy,
+    consecutive_failures: u32,
+}
+
+impl ExponentialReconnectionSchedule {
+    fn delay(&self) -> Duration {
+        // Saturates instead of overflowing after many failures.
+        let delay = self
+            .policy
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(self.consecutive_failures))
+            .min(self.policy.max_delay);
+        if self.policy.jitter {
+            rand::thread_rng().gen_range(delay / 2..=delay)
+        } else {
+            delay
+        }
+    }
+}
+
+impl ReconnectionSchedule for ExponentialReconnectionSchedule {
+    fn next_delay_after_failure(&mut self) -> Duration {
+        self.consecutive_failures = self.consecutive_failures.saturating_add(1);
+        self.delay()
+    }
+
+    fn next_delay_after_success(&mut self) -> Duration {
+        self.consecutive_failures = 0;
+        self.delay()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use super::{ConstantReconnectionPolicy, ExponentialReconnectionPolicy, ReconnectionPolicy};
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn constant_reconnection_policy() {
+        setup_tracing();
+        let mut schedule = ConstantReconnectionPolicy::new(Duration::from_secs(1)).new_schedule();
+        for _ in 0..3 {
+            assert_eq!(schedule.next_delay_after_failure(), Duration::from_secs(1));
+        }
+        assert_eq!(schedule.next_delay_after_success(), Duration::from_secs(1));
+    }
+
+    #[test]
+    fn exponential_reconnection_policy() {
+        setup_tracing();
+        let mut schedule = ExponentialReconnectionPolicy::default().new_schedule();
+        let delays: Vec<Duration> = (0..10)
+            .map(|_| schedule.next_delay_after_failure())
+            .collect();
+        assert_eq!(
+            delays,
+            [100, 200, 400, 800, 1600, 3200, 6400, 10000, 10000, 10000].map(Duration::from_millis)
+        );
+
+        assert_eq!(
+            schedule.next_delay_after_success(),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            schedule.next_delay_after_failure(),
+            Duration::from_millis(100)
+        );
+
+        // Doesn't overflow after many failures.
+        for _ in 0..100 {
+            schedule.next_delay_after_failure();
+        }
+        assert_eq!(schedule.next_delay_after_failure(), Duration::from_secs(10));
+    }
+
+    #[test]
+    fn exponential_reconnection_policy_with_jitter() {
+        setup_tracing();
+        let mut schedule =
+            ExponentialReconnectionPolicy::new(Duration::from_secs(1), Duration::from_secs(4))
+                .with_jitter()
+                .new_schedule();
+        for expected_max in [2, 4, 4, 4] {
+            let expected_max = Duration::from_secs(expected_max);
+            let delay = schedule.next_delay_after_failure();
+            assert!(expected_max / 2 <= delay && delay <= expected_max);
+        }
+    }
+}
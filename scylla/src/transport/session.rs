@@ -11,18 +11,21 @@ use crate::history;
 use crate::history::HistoryListener;
 pub use crate::transport::errors::TranslationError;
 use crate::transport::errors::{
-    BadQuery, NewSessionError, ProtocolError, QueryError, UserRequestError,
+    BadQuery, NewSessionError, ProtocolError, QueryError, SchemaAgreementError, UserRequestError,
 };
 use crate::utils::pretty::{CommaSeparatedDisplayer, CqlValueDisplayer};
 use arc_swap::ArcSwapOption;
 use async_trait::async_trait;
 use futures::future::join_all;
 use futures::future::try_join_all;
+use futures::{Stream, StreamExt};
 use itertools::{Either, Itertools};
 use scylla_cql::frame::response::result::RawMetadataAndRawRows;
 use scylla_cql::frame::response::result::{deser_cql_value, ColumnSpec};
 use scylla_cql::frame::response::NonErrorResponse;
+use scylla_cql::types::deserialize::value::DeserializeValue;
 use scylla_cql::types::serialize::batch::BatchValues;
+use scylla_cql::types::serialize::limits::ValueSizeLimits;
 use scylla_cql::types::serialize::row::{SerializeRow, SerializedValues};
 use std::borrow::Borrow;
 use std::collections::HashMap;
@@ -30,56 +33,77 @@ use std::fmt::Display;
 use std::future::Future;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
-use std::num::NonZeroU32;
+use std::num::{NonZeroU32, NonZeroU64, NonZeroUsize};
 use std::str::FromStr;
 use std::sync::atomic::AtomicUsize;
 use std::sync::atomic::Ordering;
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::timeout;
-use tracing::{debug, error, trace, trace_span, Instrument};
+use tracing::{debug, error, trace, trace_span, warn, Instrument};
 use uuid::Uuid;
 
+use super::attempt_span::{self, AttemptSpanStatement};
+use super::backpressure::{BackpressureMonitor, BackpressureState, BackpressureThresholds};
 use super::connection::NonErrorQueryResponse;
 use super::connection::QueryResponse;
 #[cfg(feature = "ssl")]
 use super::connection::SslConfig;
 use super::errors::TracingProtocolError;
-use super::execution_profile::{ExecutionProfile, ExecutionProfileHandle, ExecutionProfileInner};
+use super::execution_profile::{
+    AllowFilteringGuardrail, ExecutionProfile, ExecutionProfileHandle, ExecutionProfileInner,
+};
+use super::health::ClusterHealth;
 use super::iterator::QueryPager;
 #[cfg(feature = "cloud")]
 use super::node::CloudEndpoint;
-use super::node::{InternalKnownNode, KnownNode};
+use super::node::{InternalKnownNode, KnownNode, NodeAddr};
 use super::partitioner::PartitionerName;
 use super::query_result::MaybeFirstRowError;
+use super::query_result::QueryRowsResult;
 use super::query_result::RowsError;
+use super::query_result::SingleValueQueryError;
+use super::read_your_writes::{
+    LastWrite, ReadYourWritesConfig, ReadYourWritesError, WriteTimestampTracker,
+};
+use super::reconnection_policy::{ExponentialReconnectionPolicy, ReconnectionPolicy};
+use super::throttler::RequestThrottler;
 use super::topology::UntranslatedPeer;
-use super::{NodeRef, SelfIdentity};
+use super::{NodeRef, SelfIdentity, StreamIdExhaustionPolicy, UserTags, WriteCoalescingDelay};
 use crate::frame::response::result;
-use crate::prepared_statement::PreparedStatement;
+use crate::prepared_statement::{PartitionKeyError, PreparedStatement};
 use crate::query::Query;
 use crate::routing::{Shard, Token};
-use crate::statement::{Consistency, PageSize, PagingState, PagingStateResponse};
+use crate::statement::{
+    contains_allow_filtering, Consistency, PageSize, PagingState, PagingStateResponse,
+};
 use crate::tracing::TracingInfo;
 use crate::transport::cluster::{Cluster, ClusterData, ClusterNeatDebug};
-use crate::transport::connection::{Connection, ConnectionConfig, VerifiedKeyspaceName};
+use crate::transport::connection::{
+    Connection, ConnectionConfig, VerifiedKeyspaceName, DEFAULT_MAX_ORPHANED_STREAM_IDS,
+    DEFAULT_SERIALIZATION_BUFFER_MAX_CAPACITY, DEFAULT_SERIALIZATION_BUFFER_POOL_SIZE,
+    MAX_STREAM_IDS_PER_CONNECTION,
+};
 use crate::transport::connection_pool::PoolConfig;
 use crate::transport::host_filter::HostFilter;
 #[allow(deprecated)]
 use crate::transport::iterator::{LegacyRowIterator, PreparedIteratorConfig};
 use crate::transport::load_balancing::{self, RoutingInfo};
 use crate::transport::metrics::Metrics;
+use crate::transport::metrics_export;
 use crate::transport::node::Node;
+use crate::transport::proxy::ProxyConfig;
 use crate::transport::query_result::QueryResult;
 use crate::transport::retry_policy::{QueryInfo, RetryDecision, RetrySession};
+use crate::transport::sni_proxy::SniProxyConfig;
 use crate::transport::speculative_execution;
-use crate::transport::Compression;
+use crate::transport::warnings::WarningHandler;
+use crate::transport::{Compression, ProtocolVersion};
 use crate::{
     batch::{Batch, BatchStatement},
     statement::StatementConfig,
 };
 
-pub use crate::transport::connection_pool::PoolSize;
+pub use crate::transport::connection_pool::{PoolSize, PoolState, ShardAwarePortStatus};
 
 // This re-export is to preserve backward compatibility.
 // Those items are no longer here not to clutter session.rs with legacy things.
@@ -118,8 +142,18 @@ const TRACING_QUERY_PAGE_SIZE: i32 = 1024;
 /// Please note that the "known nodes" addresses provided while creating the [`Session`]
 /// instance are not translated, only IP address retrieved from or sent by Cassandra nodes
 /// to the driver are.
+///
+/// Static translation rules are supported out of the box: the trait is implemented for
+/// `HashMap<SocketAddr, SocketAddr>` and `HashMap<&'static str, &'static str>`, mapping
+/// untranslated addresses to translated ones.
 #[async_trait]
 pub trait AddressTranslator: Send + Sync {
+    /// Returns the address which the driver should connect to in order to reach
+    /// the given peer. It is called each time a connection to the peer is opened,
+    /// so the translation may change over time, e.g. after a DNS record is updated.
+    ///
+    /// If an error is returned, the connection attempt fails with
+    /// [`ConnectionError::TranslationError`](crate::transport::errors::ConnectionError::TranslationError).
     async fn translate_address(
         &self,
         untranslated_peer: &UntranslatedPeer,
@@ -198,7 +232,12 @@ where
     keyspace_name: Arc<ArcSwapOption<String>>,
     tracing_info_fetch_attempts: NonZeroU32,
     tracing_info_fetch_interval: Duration,
+    tracing_info_fetch_max_interval: Duration,
     tracing_info_fetch_consistency: Consistency,
+    write_timestamps: Option<Arc<WriteTimestampTracker>>,
+    request_throttler: Option<Arc<dyn RequestThrottler>>,
+    backpressure_monitor: Option<Arc<BackpressureMonitor>>,
+    attempt_spans: bool,
     _phantom_deser_api: PhantomData<DeserializationApi>,
 }
 
@@ -247,6 +286,19 @@ pub struct SessionConfig {
     /// Preferred compression algorithm to use on connections.
     /// If it's not supported by database server Session will fall back to no compression.
     pub compression: Option<Compression>,
+
+    /// Compression algorithms tried in order if a node doesn't support [`SessionConfig::compression`].
+    pub compression_fallbacks: Vec<Compression>,
+
+    /// Request bodies smaller than this number of bytes are sent uncompressed,
+    /// as compressing them usually isn't worth the CPU time.
+    /// Doesn't apply to protocol v5, which compresses whole segments.
+    pub compression_min_size: usize,
+
+    /// Version of the CQL protocol to use on connections.
+    /// If it's not supported by a node, connections to it fall back to protocol v4.
+    pub protocol_version: ProtocolVersion,
+
     pub tcp_nodelay: bool,
     pub tcp_keepalive_interval: Option<Duration>,
 
@@ -259,6 +311,11 @@ pub struct SessionConfig {
     #[cfg(feature = "ssl")]
     pub ssl_context: Option<SslContext>,
 
+    /// Provide our Session with TLS using rustls instead of openssl.
+    /// If [`SessionConfig::ssl_context`] is set, too, openssl is used.
+    #[cfg(feature = "rustls")]
+    pub rustls_config: Option<Arc<rustls::ClientConfig>>,
+
     pub authenticator: Option<Arc<dyn AuthenticatorProvider>>,
 
     pub connect_timeout: Duration,
@@ -267,6 +324,10 @@ pub struct SessionConfig {
     /// The default is `PerShard(1)`, which is the recommended setting for Scylla clusters.
     pub connection_pool_size: PoolSize,
 
+    /// Decides how long to wait before retrying to open the connections to a node,
+    /// after they failed to open.
+    pub reconnection_policy: Arc<dyn ReconnectionPolicy>,
+
     /// If true, prevents the driver from connecting to the shard-aware port, even if the node supports it.
     /// Generally, this options is best left as default (false).
     pub disallow_shard_aware_port: bool,
@@ -285,6 +346,14 @@ pub struct SessionConfig {
     /// If `None`, connections are never closed due to lack of response to a keepalive message.
     pub keepalive_timeout: Option<Duration>,
 
+    /// If set, connections older than this are replaced with new ones.
+    /// A connection is closed only after its replacement is established.
+    pub max_connection_lifetime: Option<Duration>,
+
+    /// If set, connections which have sent this many requests are replaced with new ones.
+    /// A connection is closed only after its replacement is established.
+    pub max_requests_per_connection: Option<NonZeroU64>,
+
     /// How often the driver should ask if schema is in agreement.
     pub schema_agreement_interval: Duration,
 
@@ -312,6 +381,18 @@ pub struct SessionConfig {
     /// re-establishing the control connection.
     pub host_filter: Option<Arc<dyn HostFilter>>,
 
+    /// If set, connections to all nodes are opened through the SNI proxy.
+    /// See the [sni_proxy](crate::transport::sni_proxy) module for details.
+    pub sni_proxy: Option<SniProxyConfig>,
+
+    /// If set, connections to all nodes are tunneled through the SOCKS5 or HTTP proxy.
+    /// See the [proxy](crate::transport::proxy) module for details.
+    pub proxy: Option<ProxyConfig>,
+
+    /// Receives the warnings which the database attaches to responses.
+    /// See the [warnings](crate::transport::warnings) module for details.
+    pub warning_handler: Option<Arc<dyn WarningHandler>>,
+
     /// If the driver is to connect to ScyllaCloud, there is a config for it.
     #[cfg(feature = "cloud")]
     pub cloud_config: Option<Arc<CloudConfig>>,
@@ -328,6 +409,38 @@ pub struct SessionConfig {
     /// this option.
     pub enable_write_coalescing: bool,
 
+    /// How long the driver waits for more requests before flushing them to the socket,
+    /// if write coalescing is enabled.
+    pub write_coalescing_delay: WriteCoalescingDelay,
+
+    /// If set, responses whose frames are at least this large (in bytes) are decompressed
+    /// and parsed on a thread dedicated to blocking operations
+    /// (see [`tokio::task::spawn_blocking`]) instead of the task executing the request.
+    /// This keeps large pages from stalling other tasks scheduled on the same worker thread,
+    /// at the cost of a thread switch.
+    ///
+    /// Rows themselves are deserialized lazily when they are accessed,
+    /// so they are deserialized by the task accessing them regardless of this option.
+    pub decoding_offload_threshold: Option<usize>,
+
+    /// The maximum number of buffers kept by each connection for serializing
+    /// requests. Buffers of sent requests are returned to the pool and reused
+    /// by next requests, which saves allocating a buffer per request.
+    /// Setting it to 0 disables pooling.
+    pub serialization_buffer_pool_size: usize,
+
+    /// Buffers whose capacity exceeds this size (in bytes), e.g. because they were
+    /// used for serializing a huge batch, are freed instead of being returned
+    /// to the pool of serialization buffers.
+    pub serialization_buffer_max_capacity: usize,
+
+    /// Limits on the size of values bound to statements and of whole requests,
+    /// checked before the requests are sent. A request exceeding them fails with
+    /// [`CqlRequestSerializationError::ValueSizeLimitExceeded`](scylla_cql::frame::frame_errors::CqlRequestSerializationError::ValueSizeLimitExceeded)
+    /// naming the offending column, instead of being rejected by the database
+    /// after being sent in full. For batches, the error is reported for the offending statement.
+    pub value_size_limits: ValueSizeLimits,
+
     /// Number of attempts to fetch [`TracingInfo`]
     /// in [`Session::get_tracing_info`]. Tracing info
     /// might not be available immediately on queried node - that's why
@@ -340,6 +453,12 @@ pub struct SessionConfig {
     /// the driver performs a few attempts with sleeps in between.
     pub tracing_info_fetch_interval: Duration,
 
+    /// Maximum delay between attempts to fetch [`TracingInfo`]. The delay doubles
+    /// after each attempt, starting from `tracing_info_fetch_interval`, until it reaches
+    /// this value. By default it is equal to `tracing_info_fetch_interval`,
+    /// so the attempts are made at a constant rate.
+    pub tracing_info_fetch_max_interval: Duration,
+
     /// Consistency level of fetching [`TracingInfo`]
     /// in [`Session::get_tracing_info`].
     pub tracing_info_fetch_consistency: Consistency,
@@ -353,6 +472,44 @@ pub struct SessionConfig {
     /// Driver and application self-identifying information,
     /// to be sent to server in STARTUP message.
     pub identity: SelfIdentity<'static>,
+
+    /// Maximum number of concurrent requests (stream ids) on a single connection.
+    /// Values above the protocol limit of 32768 are clamped to it.
+    pub stream_id_limit: u16,
+
+    /// Decides what happens with a request when its connection has no free stream ids.
+    /// See [`StreamIdExhaustionPolicy`] for details.
+    pub stream_id_exhaustion_policy: StreamIdExhaustionPolicy,
+
+    /// Maximum number of stream ids of a single connection which are orphaned,
+    /// i.e. their requests were abandoned (e.g. timed out) before the responses arrived,
+    /// for longer than a second. A connection exceeding it is closed.
+    /// The connection pool replaces connections with half as many orphaned stream ids
+    /// in advance, without interrupting the requests sent on them.
+    pub max_orphaned_stream_ids_per_connection: usize,
+
+    /// If set, the session tracks the timestamps of the last writes to at most
+    /// this number of most recently written partitions, which enables
+    /// [`Session::execute_read_your_writes`].
+    /// See [`read_your_writes`](crate::transport::read_your_writes) for details.
+    pub track_write_timestamps: Option<usize>,
+
+    /// If set, consulted before each request is sent, to delay or reject requests
+    /// when the session is overloaded. See [`RequestThrottler`] for details.
+    pub request_throttler: Option<Arc<dyn RequestThrottler>>,
+
+    /// If set, the session reports the nodes with too many requests in flight,
+    /// see [`Session::backpressure_receiver`].
+    pub backpressure_thresholds: Option<BackpressureThresholds>,
+
+    /// Tags, e.g. the name of a tenant or a workload, attached to the logs
+    /// and the metrics of the session. See [`UserTags`] for details.
+    pub user_tags: UserTags,
+
+    /// If true, the session opens an `INFO` span following the OpenTelemetry conventions
+    /// for database clients for each attempt of a request, see
+    /// [the book](https://rust-driver.docs.scylladb.com/stable/logging/logging.html#attempt-spans).
+    pub attempt_spans: bool,
 }
 
 impl SessionConfig {
@@ -370,6 +527,9 @@ impl SessionConfig {
         SessionConfig {
             known_nodes: Vec::new(),
             compression: None,
+            compression_fallbacks: Vec::new(),
+            compression_min_size: 0,
+            protocol_version: ProtocolVersion::default(),
             tcp_nodelay: true,
             tcp_keepalive_interval: None,
             schema_agreement_interval: Duration::from_millis(200),
@@ -379,27 +539,49 @@ impl SessionConfig {
             keyspace_case_sensitive: false,
             #[cfg(feature = "ssl")]
             ssl_context: None,
+            #[cfg(feature = "rustls")]
+            rustls_config: None,
             authenticator: None,
             connect_timeout: Duration::from_secs(5),
             connection_pool_size: Default::default(),
+            reconnection_policy: Arc::new(ExponentialReconnectionPolicy::default()),
             disallow_shard_aware_port: false,
             keyspaces_to_fetch: Vec::new(),
             fetch_schema_metadata: true,
             keepalive_interval: Some(Duration::from_secs(30)),
             keepalive_timeout: Some(Duration::from_secs(30)),
+            max_connection_lifetime: None,
+            max_requests_per_connection: None,
             schema_agreement_timeout: Duration::from_secs(60),
             schema_agreement_automatic_waiting: true,
             address_translator: None,
             host_filter: None,
+            sni_proxy: None,
+            proxy: None,
+            warning_handler: None,
             refresh_metadata_on_auto_schema_agreement: true,
             #[cfg(feature = "cloud")]
             cloud_config: None,
             enable_write_coalescing: true,
+            write_coalescing_delay: Default::default(),
+            decoding_offload_threshold: None,
+            serialization_buffer_pool_size: DEFAULT_SERIALIZATION_BUFFER_POOL_SIZE,
+            serialization_buffer_max_capacity: DEFAULT_SERIALIZATION_BUFFER_MAX_CAPACITY,
+            value_size_limits: ValueSizeLimits::default(),
             tracing_info_fetch_attempts: NonZeroU32::new(10).unwrap(),
             tracing_info_fetch_interval: Duration::from_millis(3),
+            tracing_info_fetch_max_interval: Duration::from_millis(3),
             tracing_info_fetch_consistency: Consistency::One,
             cluster_metadata_refresh_interval: Duration::from_secs(60),
             identity: SelfIdentity::default(),
+            stream_id_limit: MAX_STREAM_IDS_PER_CONNECTION,
+            stream_id_exhaustion_policy: StreamIdExhaustionPolicy::default(),
+            max_orphaned_stream_ids_per_connection: DEFAULT_MAX_ORPHANED_STREAM_IDS,
+            track_write_timestamps: None,
+            request_throttler: None,
+            backpressure_thresholds: None,
+            user_tags: UserTags::new(),
+            attempt_spans: false,
         }
     }
 
@@ -543,6 +725,41 @@ impl GenericSession<CurrentDeserializationApi> {
         self.do_query_unpaged(&query.into(), values).await
     }
 
+    /// Performs an unpaged query returning a single value, e.g. `SELECT count(*) FROM ...`
+    /// or `SELECT x FROM ... WHERE pk = ?`, and deserializes the value.
+    ///
+    /// Fails unless the result consists of exactly one row with exactly one column.
+    /// See [`QueryRowsResult::single_value`] for details, and [`Session::query_unpaged`]
+    /// for remarks on using non-empty values with unprepared statements.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// let count: i64 = session
+    ///     .query_single_value("SELECT count(*) FROM ks.tab", &[])
+    ///     .await?;
+    /// println!("ks.tab has {} rows", count);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn query_single_value<T>(
+        &self,
+        query: impl Into<Query>,
+        values: impl SerializeRow,
+    ) -> Result<T, SingleValueQueryError>
+    where
+        T: for<'frame, 'metadata> DeserializeValue<'frame, 'metadata>,
+    {
+        Ok(self
+            .query_unpaged(query, values)
+            .await?
+            .into_rows_result()?
+            .single_value::<T>()?)
+    }
+
     /// Queries a single page from the database, optionally continuing from a saved point.
     ///
     /// It is discouraged to use this method with non-empty values argument (`is_empty()` method from `SerializeRow`
@@ -700,6 +917,40 @@ impl GenericSession<CurrentDeserializationApi> {
         self.do_execute_unpaged(prepared, values).await
     }
 
+    /// Executes a prepared statement returning a single value,
+    /// e.g. `SELECT x FROM ... WHERE pk = ?`, and deserializes the value.
+    ///
+    /// Fails unless the result consists of exactly one row with exactly one column.
+    /// See [`QueryRowsResult::single_value`] for details.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// let prepared = session
+    ///     .prepare("SELECT name FROM ks.users WHERE id = ?")
+    ///     .await?;
+    /// let name: String = session.execute_single_value(&prepared, (1_i32,)).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_single_value<T>(
+        &self,
+        prepared: &PreparedStatement,
+        values: impl SerializeRow,
+    ) -> Result<T, SingleValueQueryError>
+    where
+        T: for<'frame, 'metadata> DeserializeValue<'frame, 'metadata>,
+    {
+        Ok(self
+            .execute_unpaged(prepared, values)
+            .await?
+            .into_rows_result()?
+            .single_value::<T>()?)
+    }
+
     /// Executes a prepared statement, restricting results to single page.
     /// Optionally continues fetching results from a saved point.
     ///
@@ -867,6 +1118,155 @@ impl GenericSession<CurrentDeserializationApi> {
         self.do_batch(batch, values).await
     }
 
+    /// Executes a prepared read, repeating it until it reflects the last write
+    /// to the read partition executed by this session.
+    ///
+    /// Requires write timestamp tracking to be enabled with
+    /// [`SessionBuilder::track_write_timestamps`](crate::transport::session_builder::GenericSessionBuilder::track_write_timestamps).
+    /// Only writes executed as prepared statements are tracked.
+    /// If no write to the partition is known, the read is executed once.
+    ///
+    /// `write_timestamp` extracts the timestamp of the latest write reflected by the result,
+    /// typically selected with `WRITETIME(column)`, or returns `None` if the result
+    /// does not reflect any write, e.g. if it is empty. Once it is at least the timestamp
+    /// of the last tracked write, the result is returned. If the last tracked write was
+    /// a `DELETE`, a result which reflects no write is returned as well. Otherwise,
+    /// the read is repeated, possibly reaching another replica, according to `config`.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use scylla::transport::read_your_writes::ReadYourWritesConfig;
+    ///
+    /// let insert = session
+    ///     .prepare("INSERT INTO ks.tab (pk, v) VALUES (?, ?)")
+    ///     .await?;
+    /// session.execute_unpaged(&insert, (1_i32, "value")).await?;
+    ///
+    /// let select = session
+    ///     .prepare("SELECT v, WRITETIME(v) FROM ks.tab WHERE pk = ?")
+    ///     .await?;
+    /// let result = session
+    ///     .execute_read_your_writes(&select, (1_i32,), ReadYourWritesConfig::default(), |rows| {
+    ///         rows.maybe_first_row::<(String, i64)>()
+    ///             .ok()
+    ///             .flatten()
+    ///             .map(|(_, write_time)| write_time)
+    ///     })
+    ///     .await?;
+    /// let (v, _) = result.first_row::<(String, i64)>()?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn execute_read_your_writes(
+        &self,
+        prepared: &PreparedStatement,
+        values: impl SerializeRow,
+        config: ReadYourWritesConfig,
+        write_timestamp: impl Fn(&QueryRowsResult) -> Option<i64>,
+    ) -> Result<QueryRowsResult, ReadYourWritesError> {
+        let last_write = self.last_write(prepared, &values)?;
+        let mut read = None;
+        let mut attempts = 0;
+        loop {
+            attempts += 1;
+            let rows = self
+                .execute_unpaged(prepared, &values)
+                .await?
+                .into_rows_result()?;
+            let Some(last_write) = last_write else {
+                return Ok(rows);
+            };
+            let current = write_timestamp(&rows);
+            if last_write.is_reflected_by(current) {
+                return Ok(rows);
+            }
+            read = read.max(current);
+            if attempts >= config.max_attempts {
+                return Err(ReadYourWritesError::StaleRead {
+                    expected: last_write.timestamp,
+                    read,
+                    attempts,
+                });
+            }
+            tokio::time::sleep(config.retry_delay).await;
+        }
+    }
+
+    /// Executes a prepared statement once for each set of values from a stream,
+    /// running at most `concurrency` executions at a time.
+    ///
+    /// Returns a stream of the results, in the order of the values. A failed execution
+    /// doesn't stop the others, its error is just returned in its place.
+    /// The values are pulled from the input stream only as fast as the results are consumed,
+    /// so arbitrarily long inputs can be processed with bounded memory.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::error::Error;
+    /// # async fn check_only_compiles(session: &Session) -> Result<(), Box<dyn Error>> {
+    /// use futures::{stream, TryStreamExt};
+    /// use std::num::NonZeroUsize;
+    ///
+    /// let insert = session
+    ///     .prepare("INSERT INTO ks.tab (a, b) VALUES(?, ?)")
+    ///     .await?;
+    ///
+    /// let rows = (0..1_000_000).map(|i| (i, i.to_string()));
+    /// session
+    ///     .execute_concurrent(&insert, stream::iter(rows), NonZeroUsize::new(256).unwrap())
+    ///     .try_for_each(|_| async { Ok(()) })
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn execute_concurrent<'a, V>(
+        &'a self,
+        prepared: &'a PreparedStatement,
+        values: impl Stream<Item = V> + 'a,
+        concurrency: NonZeroUsize,
+    ) -> impl Stream<Item = Result<QueryResult, QueryError>> + 'a
+    where
+        V: SerializeRow + 'a,
+    {
+        values
+            .map(move |values| self.execute_unpaged(prepared, values))
+            .buffered(concurrency.get())
+    }
+
+    /// Returns the timestamp of the last write executed by this session to the partition
+    /// targeted by the prepared statement with the given values, if it is known.
+    ///
+    /// Requires write timestamp tracking to be enabled with
+    /// [`SessionBuilder::track_write_timestamps`](crate::transport::session_builder::GenericSessionBuilder::track_write_timestamps),
+    /// otherwise returns `None`.
+    pub fn last_write_timestamp(
+        &self,
+        prepared: &PreparedStatement,
+        values: &impl SerializeRow,
+    ) -> Result<Option<i64>, PartitionKeyError> {
+        Ok(self
+            .last_write(prepared, values)?
+            .map(|last_write| last_write.timestamp))
+    }
+
+    fn last_write(
+        &self,
+        prepared: &PreparedStatement,
+        values: &impl SerializeRow,
+    ) -> Result<Option<LastWrite>, PartitionKeyError> {
+        let (Some(tracker), Some(table_spec)) = (&self.write_timestamps, prepared.get_table_spec())
+        else {
+            return Ok(None);
+        };
+        let partition_key = prepared.compute_partition_key(values)?;
+        Ok(tracker.last_write(table_spec, partition_key))
+    }
+
     /// Creates a new Session instance that shared resources with
     /// the current Session but supports the legacy API.
     ///
@@ -893,7 +1293,12 @@ impl GenericSession<CurrentDeserializationApi> {
             schema_agreement_automatic_waiting: self.schema_agreement_automatic_waiting,
             tracing_info_fetch_attempts: self.tracing_info_fetch_attempts,
             tracing_info_fetch_interval: self.tracing_info_fetch_interval,
+            tracing_info_fetch_max_interval: self.tracing_info_fetch_max_interval,
             tracing_info_fetch_consistency: self.tracing_info_fetch_consistency,
+            write_timestamps: self.write_timestamps.clone(),
+            request_throttler: self.request_throttler.clone(),
+            backpressure_monitor: self.backpressure_monitor.clone(),
+            attempt_spans: self.attempt_spans,
             _phantom_deser_api: PhantomData,
         }
     }
@@ -1003,7 +1408,12 @@ impl GenericSession<LegacyDeserializationApi> {
             schema_agreement_automatic_waiting: self.schema_agreement_automatic_waiting,
             tracing_info_fetch_attempts: self.tracing_info_fetch_attempts,
             tracing_info_fetch_interval: self.tracing_info_fetch_interval,
+            tracing_info_fetch_max_interval: self.tracing_info_fetch_max_interval,
             tracing_info_fetch_consistency: self.tracing_info_fetch_consistency,
+            write_timestamps: self.write_timestamps.clone(),
+            request_throttler: self.request_throttler.clone(),
+            backpressure_monitor: self.backpressure_monitor.clone(),
+            attempt_spans: self.attempt_spans,
             _phantom_deser_api: PhantomData,
         }
     }
@@ -1038,6 +1448,7 @@ where
     /// # }
     /// ```
     pub async fn connect(config: SessionConfig) -> Result<Self, NewSessionError> {
+        metrics_export::describe();
         let known_nodes = config.known_nodes;
 
         #[cfg(feature = "cloud")]
@@ -1071,31 +1482,67 @@ where
 
         let (tablet_sender, tablet_receiver) = tokio::sync::mpsc::channel(TABLET_CHANNEL_SIZE);
 
+        let metrics = Arc::new(Metrics::with_user_tags(config.user_tags));
+
         let connection_config = ConnectionConfig {
             compression: config.compression,
+            compression_fallbacks: config.compression_fallbacks,
+            compression_min_size: config.compression_min_size,
+            protocol_version: config.protocol_version,
             tcp_nodelay: config.tcp_nodelay,
             tcp_keepalive_interval: config.tcp_keepalive_interval,
             #[cfg(feature = "ssl")]
             ssl_config: config.ssl_context.map(SslConfig::new_with_global_context),
+            #[cfg(feature = "rustls")]
+            rustls_config: config.rustls_config,
             authenticator: config.authenticator.clone(),
             connect_timeout: config.connect_timeout,
             event_sender: None,
             default_consistency: Default::default(),
             address_translator: config.address_translator,
+            sni_proxy: config.sni_proxy.clone().map(Arc::new),
+            proxy: config.proxy.clone().map(Arc::new),
+            tls_server_name: None,
+            warning_handler: config.warning_handler,
             #[cfg(feature = "cloud")]
             cloud_config: config.cloud_config,
             enable_write_coalescing: config.enable_write_coalescing,
+            write_coalescing_delay: config.write_coalescing_delay,
+            decoding_offload_threshold: config.decoding_offload_threshold,
             keepalive_interval: config.keepalive_interval,
             keepalive_timeout: config.keepalive_timeout,
             tablet_sender: Some(tablet_sender),
             identity: config.identity,
+            stream_id_limit: config.stream_id_limit,
+            stream_id_exhaustion_policy: config.stream_id_exhaustion_policy,
+            max_orphaned_stream_ids: config.max_orphaned_stream_ids_per_connection,
+            metrics: metrics.clone(),
+            serialization_buffer_pool_size: config.serialization_buffer_pool_size,
+            serialization_buffer_max_capacity: config.serialization_buffer_max_capacity,
+            value_size_limits: config.value_size_limits,
         };
 
+        if connection_config.sni_proxy.is_some() && !connection_config.is_ssl() {
+            warn!("SNI proxy is configured without TLS, so the proxy won't know which node to connect to");
+        }
+
+        let backpressure_monitor = config
+            .backpressure_thresholds
+            .map(|thresholds| Arc::new(BackpressureMonitor::new(thresholds)));
+
         let pool_config = PoolConfig {
             connection_config,
             pool_size: config.connection_pool_size,
-            can_use_shard_aware_port: !config.disallow_shard_aware_port,
+            // The shard-aware port of the nodes is not exposed by an SNI proxy,
+            // and the source port can't be chosen for connections opened by a proxy.
+            can_use_shard_aware_port: !config.disallow_shard_aware_port
+                && config.sni_proxy.is_none()
+                && config.proxy.is_none(),
             keepalive_interval: config.keepalive_interval,
+            max_connection_lifetime: config.max_connection_lifetime,
+            max_requests_per_connection: config.max_requests_per_connection,
+            reconnection_policy: config.reconnection_policy,
+            backpressure_monitor: backpressure_monitor.clone(),
         };
 
         let cluster = Cluster::new(
@@ -1115,7 +1562,7 @@ where
             cluster,
             default_execution_profile_handle,
             schema_agreement_interval: config.schema_agreement_interval,
-            metrics: Arc::new(Metrics::new()),
+            metrics,
             schema_agreement_timeout: config.schema_agreement_timeout,
             schema_agreement_automatic_waiting: config.schema_agreement_automatic_waiting,
             refresh_metadata_on_auto_schema_agreement: config
@@ -1123,7 +1570,14 @@ where
             keyspace_name: Arc::new(ArcSwapOption::default()), // will be set by use_keyspace
             tracing_info_fetch_attempts: config.tracing_info_fetch_attempts,
             tracing_info_fetch_interval: config.tracing_info_fetch_interval,
+            tracing_info_fetch_max_interval: config.tracing_info_fetch_max_interval,
             tracing_info_fetch_consistency: config.tracing_info_fetch_consistency,
+            write_timestamps: config
+                .track_write_timestamps
+                .map(|capacity| Arc::new(WriteTimestampTracker::new(capacity))),
+            request_throttler: config.request_throttler,
+            backpressure_monitor,
+            attempt_spans: config.attempt_spans,
             _phantom_deser_api: PhantomData,
         };
 
@@ -1184,10 +1638,9 @@ where
         page_size: Option<PageSize>,
         paging_state: PagingState,
     ) -> Result<(QueryResult, PagingStateResponse), QueryError> {
-        let execution_profile = query
-            .get_execution_profile_handle()
-            .unwrap_or_else(|| self.get_default_execution_profile_handle())
-            .access();
+        let execution_profile =
+            self.resolve_execution_profile(query.get_execution_profile_handle());
+        check_allow_filtering(&query.contents, &query.config, &execution_profile)?;
 
         let statement_info = RoutingInfo {
             consistency: query
@@ -1201,7 +1654,10 @@ where
             ..Default::default()
         };
 
-        let span = RequestSpan::new_query(&query.contents);
+        let span = RequestSpan::new_query(&query.contents).with_attempt_spans(
+            self.attempt_spans
+                .then(|| AttemptSpanStatement::new(&query.contents, None)),
+        );
         let span_ref = &span;
         let run_query_result = self
             .run_query(
@@ -1243,6 +1699,7 @@ where
                                     &serialized,
                                     consistency,
                                     serial_consistency,
+                                    prepared.get_timestamp(),
                                     page_size,
                                     paging_state_ref.clone(),
                                 )
@@ -1315,10 +1772,9 @@ where
         query: Query,
         values: impl SerializeRow,
     ) -> Result<QueryPager, QueryError> {
-        let execution_profile = query
-            .get_execution_profile_handle()
-            .unwrap_or_else(|| self.get_default_execution_profile_handle())
-            .access();
+        let execution_profile =
+            self.resolve_execution_profile(query.get_execution_profile_handle());
+        check_allow_filtering(&query.contents, &query.config, &execution_profile)?;
 
         if values.is_empty() {
             QueryPager::new_for_query(
@@ -1326,6 +1782,8 @@ where
                 execution_profile,
                 self.cluster.get_data(),
                 self.metrics.clone(),
+                self.request_throttler.clone(),
+                self.attempt_spans,
             )
             .await
         } else {
@@ -1340,6 +1798,8 @@ where
                 execution_profile,
                 cluster_data: self.cluster.get_data(),
                 metrics: self.metrics.clone(),
+                request_throttler: self.request_throttler.clone(),
+                attempt_spans: self.attempt_spans,
             })
             .await
         }
@@ -1485,14 +1945,30 @@ where
         let values_ref = &serialized_values;
         let paging_state_ref = &paging_state;
 
+        // Writes are given client-side timestamps, so that the timestamps can be tracked.
+        let tracked_write = self.write_timestamps.as_ref().and_then(|tracker| {
+            let kind = prepared.tracked_write_kind()?;
+            let timestamp = prepared
+                .get_timestamp()
+                .unwrap_or_else(|| tracker.next_timestamp());
+            Some((tracker, LastWrite { timestamp, kind }))
+        });
+        let timestamp = match tracked_write {
+            Some((_, write)) => Some(write.timestamp),
+            None => prepared.get_timestamp(),
+        };
+
         let (partition_key, token) = prepared
             .extract_partition_key_and_calculate_token(prepared.get_partitioner_name(), values_ref)?
             .unzip();
 
-        let execution_profile = prepared
-            .get_execution_profile_handle()
-            .unwrap_or_else(|| self.get_default_execution_profile_handle())
-            .access();
+        let execution_profile =
+            self.resolve_execution_profile(prepared.get_execution_profile_handle());
+        check_allow_filtering(
+            prepared.get_statement(),
+            &prepared.config,
+            &execution_profile,
+        )?;
 
         let table_spec = prepared.get_table_spec();
 
@@ -1514,7 +1990,10 @@ where
             partition_key.as_ref().map(|pk| pk.iter()),
             token,
             serialized_values.buffer_size(),
-        );
+        )
+        .with_attempt_spans(self.attempt_spans.then(|| {
+            AttemptSpanStatement::new(prepared.get_statement(), prepared.get_table_spec())
+        }));
 
         if !span.span().is_disabled() {
             if let (Some(table_spec), Some(token)) = (statement_info.table, token) {
@@ -1545,6 +2024,7 @@ where
                                 values_ref,
                                 consistency,
                                 serial_consistency,
+                                timestamp,
                                 page_size,
                                 paging_state_ref.clone(),
                             )
@@ -1567,6 +2047,14 @@ where
             RunQueryResult::Completed(response) => response,
         };
 
+        if let (Some((tracker, write)), Some(table_spec), Some(partition_key)) =
+            (tracked_write, table_spec, &partition_key)
+        {
+            if let Ok(partition_key) = partition_key.encode() {
+                tracker.record(table_spec, partition_key, write);
+            }
+        }
+
         self.handle_set_keyspace_response(&response).await?;
         self.handle_auto_await_schema_agreement(&response).await?;
 
@@ -1583,10 +2071,13 @@ where
     ) -> Result<QueryPager, QueryError> {
         let serialized_values = prepared.serialize_values(&values)?;
 
-        let execution_profile = prepared
-            .get_execution_profile_handle()
-            .unwrap_or_else(|| self.get_default_execution_profile_handle())
-            .access();
+        let execution_profile =
+            self.resolve_execution_profile(prepared.get_execution_profile_handle());
+        check_allow_filtering(
+            prepared.get_statement(),
+            &prepared.config,
+            &execution_profile,
+        )?;
 
         QueryPager::new_for_prepared_statement(PreparedIteratorConfig {
             prepared,
@@ -1594,6 +2085,8 @@ where
             execution_profile,
             cluster_data: self.cluster.get_data(),
             metrics: self.metrics.clone(),
+            request_throttler: self.request_throttler.clone(),
+            attempt_spans: self.attempt_spans,
         })
         .await
     }
@@ -1614,10 +2107,8 @@ where
             ));
         }
 
-        let execution_profile = batch
-            .get_execution_profile_handle()
-            .unwrap_or_else(|| self.get_default_execution_profile_handle())
-            .access();
+        let execution_profile =
+            self.resolve_execution_profile(batch.get_execution_profile_handle());
 
         let consistency = batch
             .config
@@ -1648,7 +2139,15 @@ where
             is_confirmed_lwt: false,
         };
 
-        let span = RequestSpan::new_batch();
+        let span = RequestSpan::new_batch().with_attempt_spans(self.attempt_spans.then(|| {
+            AttemptSpanStatement::new_batch(
+                batch.statements.iter().map(|statement| match statement {
+                    BatchStatement::Query(query) => query.contents.as_str(),
+                    BatchStatement::PreparedStatement(prepared) => prepared.get_statement(),
+                }),
+                table_spec,
+            )
+        }));
 
         let run_query_result = self
             .run_query(
@@ -1791,6 +2290,34 @@ where
         Ok(())
     }
 
+    /// Waits until the connection pools to all the nodes known to the session have opened
+    /// all their connections, as configured with [`SessionBuilder::pool_size`](crate::SessionBuilder::pool_size).
+    ///
+    /// A freshly built session is usable as soon as at least one connection to each node
+    /// is open, while the rest of the connections are opened in the background. Services
+    /// which are sensitive to latency can call this before serving traffic, so that the requests
+    /// are not routed to other shards, nor wait for connections which are still being opened.
+    ///
+    /// If a node is down, its pool never becomes full, so wrap the call in a timeout:
+    /// ```rust
+    /// # use scylla::Session;
+    /// # use std::time::Duration;
+    /// # async fn example(session: &Session) {
+    /// if tokio::time::timeout(Duration::from_secs(5), session.wait_until_pools_ready())
+    ///     .await
+    ///     .is_err()
+    /// {
+    ///     println!("Not all connections were opened within 5 seconds");
+    /// }
+    /// # }
+    /// ```
+    pub async fn wait_until_pools_ready(&self) {
+        self.cluster
+            .get_data()
+            .wait_until_all_pools_are_full()
+            .await;
+    }
+
     /// Manually trigger a metadata refresh\
     /// The driver will fetch current nodes in the cluster and update its metadata
     ///
@@ -1807,6 +2334,41 @@ where
         self.metrics.clone()
     }
 
+    /// Returns a receiver of the backpressure signal: the set of nodes which currently
+    /// have more requests in flight than the configured thresholds allow.
+    /// The receiver is notified whenever a node becomes overloaded or stops being overloaded.
+    ///
+    /// Returns `None` if the session was created without
+    /// [`SessionConfig::backpressure_thresholds`].
+    /// See [`backpressure`](crate::transport::backpressure) for details.
+    pub fn backpressure_receiver(&self) -> Option<tokio::sync::watch::Receiver<BackpressureState>> {
+        self.backpressure_monitor
+            .as_ref()
+            .map(|monitor| monitor.subscribe())
+    }
+
+    /// Returns a snapshot of the health of the nodes known to the session: whether they are up,
+    /// the state of their connection pools and the number of requests in flight to them.
+    /// It is cheap enough to be used by readiness probes.
+    /// See [`health`](crate::transport::health) for details.
+    ///
+    /// # Example
+    /// ```rust
+    /// # use scylla::Session;
+    /// # fn check_only_compiles(session: &Session) {
+    /// let health = session.cluster_health();
+    /// if !health.is_ready() {
+    ///     for node in health.unavailable_nodes() {
+    ///         let last_error = node.pool.as_ref().and_then(|pool| pool.last_error.as_ref());
+    ///         println!("{} is unavailable, last error: {:?}", node.address, last_error);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn cluster_health(&self) -> ClusterHealth {
+        ClusterHealth::new(&self.cluster.get_data())
+    }
+
     /// Access cluster data collected by the driver\
     /// Driver collects various information about network topology or schema.
     /// They can be read using this method
@@ -1814,24 +2376,127 @@ where
         self.cluster.get_data()
     }
 
+    /// Describes how executing the prepared statement with the given values would be routed:
+    /// the token of the partition key, the replicas owning it and the load balancing plan.
+    /// Nothing is sent to the cluster, so it's cheap enough to debug hot partitions
+    /// or imbalanced load in production.
+    ///
+    /// The plan is computed by the load balancing policy of the statement's execution profile.
+    /// If the policy shuffles replicas (as [`DefaultPolicy`](crate::load_balancing::DefaultPolicy)
+    /// does by default), each call may return the replicas in a different order.
+    pub fn plan_for(
+        &self,
+        prepared: &PreparedStatement,
+        values: impl SerializeRow,
+    ) -> Result<QueryPlanInfo, QueryError> {
+        let serialized_values = prepared.serialize_values(&values)?;
+        let token = prepared
+            .extract_partition_key_and_calculate_token(
+                prepared.get_partitioner_name(),
+                &serialized_values,
+            )?
+            .map(|(_partition_key, token)| token);
+
+        let execution_profile =
+            self.resolve_execution_profile(prepared.get_execution_profile_handle());
+        let statement_info = RoutingInfo {
+            consistency: prepared
+                .config
+                .consistency
+                .unwrap_or(execution_profile.consistency),
+            serial_consistency: prepared
+                .config
+                .serial_consistency
+                .unwrap_or(execution_profile.serial_consistency),
+            token,
+            table: prepared.get_table_spec(),
+            is_confirmed_lwt: prepared.is_confirmed_lwt(),
+        };
+
+        let cluster_data = self.cluster.get_data();
+        let replicas = cluster_data
+            .get_replicas(&statement_info, None)
+            .map(|replicas| {
+                replicas
+                    .into_iter()
+                    .map(|(node, shard)| (node.clone(), shard))
+                    .collect()
+            })
+            .unwrap_or_default();
+        let plan = load_balancing::Plan::new(
+            execution_profile.load_balancing_policy.as_ref(),
+            &statement_info,
+            &cluster_data,
+        )
+        .map(|(node, shard)| (node.clone(), shard))
+        .collect();
+
+        Ok(QueryPlanInfo {
+            token,
+            replicas,
+            plan,
+        })
+    }
+
     /// Get [`TracingInfo`] of a traced query performed earlier
     ///
+    /// The tracing info is returned as soon as it appears in the tracing tables,
+    /// so it may lack the events recorded later. Use [`Session::get_trace`]
+    /// to wait until the trace is complete.
+    ///
     /// See [the book](https://rust-driver.docs.scylladb.com/stable/tracing/tracing.html)
     /// for more information about query tracing
     pub async fn get_tracing_info(&self, tracing_id: &Uuid) -> Result<TracingInfo, QueryError> {
+        self.fetch_tracing_info(tracing_id, false).await
+    }
+
+    /// Get the complete [`TracingInfo`] of a traced query performed earlier.
+    ///
+    /// Tracing info is written asynchronously, so the driver polls the tracing tables
+    /// until the trace is complete (see [`TracingInfo::is_complete`]), as configured with
+    /// [`SessionBuilder::tracing_info_fetch_attempts`](crate::SessionBuilder::tracing_info_fetch_attempts),
+    /// [`SessionBuilder::tracing_info_fetch_interval`](crate::SessionBuilder::tracing_info_fetch_interval)
+    /// and [`SessionBuilder::tracing_info_fetch_max_interval`](crate::SessionBuilder::tracing_info_fetch_max_interval).
+    /// If the trace is still incomplete after the last attempt, it is returned as it is.
+    ///
+    /// See [the book](https://rust-driver.docs.scylladb.com/stable/tracing/tracing.html)
+    /// for more information about query tracing
+    pub async fn get_trace(&self, tracing_id: &Uuid) -> Result<TracingInfo, QueryError> {
+        self.fetch_tracing_info(tracing_id, true).await
+    }
+
+    async fn fetch_tracing_info(
+        &self,
+        tracing_id: &Uuid,
+        wait_for_completion: bool,
+    ) -> Result<TracingInfo, QueryError> {
+        let attempts = self.tracing_info_fetch_attempts.get();
+        let mut delays = tracing_info_fetch_delays(
+            self.tracing_info_fetch_interval,
+            self.tracing_info_fetch_max_interval,
+        );
+        let mut incomplete = None;
+
         // tracing_info_fetch_attempts is NonZeroU32 so at least one attempt will be made
-        for _ in 0..self.tracing_info_fetch_attempts.get() {
+        for attempt in 1..=attempts {
             let current_try: Option<TracingInfo> = self
                 .try_getting_tracing_info(tracing_id, Some(self.tracing_info_fetch_consistency))
                 .await?;
 
             match current_try {
-                Some(tracing_info) => return Ok(tracing_info),
-                None => tokio::time::sleep(self.tracing_info_fetch_interval).await,
+                Some(tracing_info) if !wait_for_completion || tracing_info.is_complete() => {
+                    return Ok(tracing_info)
+                }
+                Some(tracing_info) => incomplete = Some(tracing_info),
+                None => {}
             };
+            if attempt < attempts {
+                // The sequence of delays never ends.
+                tokio::time::sleep(delays.next().unwrap()).await;
+            }
         }
 
-        Err(ProtocolError::Tracing(TracingProtocolError::EmptyResults).into())
+        incomplete.ok_or_else(|| ProtocolError::Tracing(TracingProtocolError::EmptyResults).into())
     }
 
     /// Gets the name of the keyspace that is currently set, or `None` if no
@@ -1937,6 +2602,12 @@ where
         QueryFut: Future<Output = Result<ResT, QueryError>>,
         ResT: AllowedRunQueryResTType,
     {
+        let user_tags = self
+            .metrics
+            .user_tags()
+            .merged_with(&execution_profile.user_tags);
+        request_span.record_user_tags(&user_tags);
+
         let history_listener_and_id: Option<(&'a dyn HistoryListener, history::QueryId)> =
             statement_config
                 .history_listener
@@ -1946,6 +2617,12 @@ where
         let load_balancer = &execution_profile.load_balancing_policy;
 
         let runner = async {
+            // Held until the request, with all its retries and speculative executions, completes.
+            let _throttler_permit = match &self.request_throttler {
+                Some(throttler) => Some(throttler.acquire().await?),
+                None => None,
+            };
+
             let cluster_data = self.cluster.get_data();
             let query_plan =
                 load_balancing::Plan::new(load_balancer.as_ref(), &statement_info, &cluster_data);
@@ -1980,6 +2657,8 @@ where
 
             match speculative_policy {
                 Some(speculative) if statement_config.is_idempotent => {
+                    let mut query_plan = query_plan.peekable();
+                    let first_node = query_plan.peek().map(|(node, _)| Arc::clone(node));
                     let shared_query_plan = SharedPlan {
                         iter: std::sync::Mutex::new(query_plan),
                     };
@@ -2001,9 +2680,12 @@ where
                                 }
                             });
 
-                        if is_speculative {
-                            request_span.inc_speculative_executions();
-                        }
+                        let speculative_execution = if is_speculative {
+                            metrics_export::record_speculative_execution(&user_tags);
+                            request_span.inc_speculative_executions()
+                        } else {
+                            0
+                        };
 
                         self.execute_query(
                             &shared_query_plan,
@@ -2016,12 +2698,16 @@ where
                                 history_data,
                                 query_info: &statement_info,
                                 request_span,
+                                user_tags: &user_tags,
+                                speculative_execution,
+                                requires_scylla: statement_config.server_timeout.is_some(),
                             },
                         )
                     };
 
                     let context = speculative_execution::Context {
                         metrics: self.metrics.clone(),
+                        node: first_node,
                     };
 
                     speculative_execution::execute(
@@ -2051,6 +2737,9 @@ where
                             history_data,
                             query_info: &statement_info,
                             request_span,
+                            user_tags: &user_tags,
+                            speculative_execution: 0,
+                            requires_scylla: statement_config.server_timeout.is_some(),
                         },
                     )
                     .await
@@ -2059,9 +2748,8 @@ where
             }
         };
 
-        let effective_timeout = statement_config
-            .request_timeout
-            .or(execution_profile.request_timeout);
+        let effective_timeout =
+            statement_config.determine_request_timeout(execution_profile.request_timeout);
         let result = match effective_timeout {
             Some(timeout) => tokio::time::timeout(timeout, runner)
                 .await
@@ -2100,6 +2788,7 @@ where
         let mut current_consistency: Consistency = context
             .consistency_set_on_statement
             .unwrap_or(execution_profile.consistency);
+        let mut attempt: usize = 0;
 
         'nodes_in_plan: for (node, shard) in query_plan {
             let span = trace_span!("Executing query", node = %node.address);
@@ -2118,6 +2807,9 @@ where
                         continue 'nodes_in_plan;
                     }
                 };
+                if context.requires_scylla && !connection.is_to_scylla() {
+                    return Some(Err(BadQuery::ServerTimeoutNotSupported.into()));
+                }
                 context.request_span.record_shard_id(&connection);
 
                 self.metrics.inc_total_nonpaged_queries();
@@ -2130,16 +2822,38 @@ where
                 );
                 let attempt_id: Option<history::AttemptId> =
                     context.log_attempt_start(connection.get_connect_address());
+                let in_flight_request = node.start_request(&connection);
+                let connection_shard = connection
+                    .get_shard_info()
+                    .as_ref()
+                    .map(|info| info.shard as Shard);
+                let attempt_span = context.request_span.attempt_span(
+                    node,
+                    connection_shard,
+                    current_consistency,
+                    attempt,
+                    context.speculative_execution,
+                );
+                attempt += 1;
                 let query_result: Result<ResT, QueryError> =
                     do_query(connection, current_consistency, execution_profile)
                         .instrument(span.clone())
+                        .instrument(attempt_span.clone())
                         .await;
+                attempt_span::record_attempt_result(&attempt_span, query_result.as_ref().err());
 
                 let elapsed = query_start.elapsed();
                 last_error = match query_result {
                     Ok(response) => {
                         trace!(parent: &span, "Query succeeded");
-                        let _ = self.metrics.log_query_latency(elapsed.as_millis() as u64);
+                        in_flight_request.succeeded();
+                        self.metrics.log_query_latency(elapsed);
+                        metrics_export::record_request_success(
+                            node,
+                            connection_shard,
+                            context.user_tags,
+                            elapsed,
+                        );
                         context.log_attempt_success(&attempt_id);
                         execution_profile.load_balancing_policy.on_query_success(
                             context.query_info,
@@ -2155,6 +2869,12 @@ where
                             "Query failed"
                         );
                         self.metrics.inc_failed_nonpaged_queries();
+                        metrics_export::record_request_failure(
+                            node,
+                            connection_shard,
+                            context.user_tags,
+                            &e,
+                        );
                         execution_profile.load_balancing_policy.on_query_failure(
                             context.query_info,
                             elapsed,
@@ -2184,11 +2904,13 @@ where
                 match retry_decision {
                     RetryDecision::RetrySameNode(new_cl) => {
                         self.metrics.inc_retries_num();
+                        metrics_export::record_retry(node, context.user_tags);
                         current_consistency = new_cl.unwrap_or(current_consistency);
                         continue 'same_node_retries;
                     }
                     RetryDecision::RetryNextNode(new_cl) => {
                         self.metrics.inc_retries_num();
+                        metrics_export::record_retry(node, context.user_tags);
                         current_consistency = new_cl.unwrap_or(current_consistency);
                         continue 'nodes_in_plan;
                     }
@@ -2204,36 +2926,95 @@ where
         last_error.map(Result::Err)
     }
 
-    async fn await_schema_agreement_indefinitely(&self) -> Result<Uuid, QueryError> {
-        loop {
-            tokio::time::sleep(self.schema_agreement_interval).await;
-            if let Some(agreed_version) = self.check_schema_agreement().await? {
-                return Ok(agreed_version);
-            }
-        }
-    }
-
+    /// Waits until all the nodes agree on the schema version, checking it every
+    /// [`SessionConfig::schema_agreement_interval`], for up to
+    /// [`SessionConfig::schema_agreement_timeout`]. Returns the agreed version.
+    ///
+    /// On timeout, [`QueryError::RequestTimeout`] is returned, describing the schema
+    /// versions of the nodes. Use [`Session::await_schema_agreement_with`] to get them
+    /// as [`SchemaVersions`].
     pub async fn await_schema_agreement(&self) -> Result<Uuid, QueryError> {
-        timeout(
+        self.await_schema_agreement_with(
+            self.schema_agreement_interval,
             self.schema_agreement_timeout,
-            self.await_schema_agreement_indefinitely(),
         )
         .await
-        .unwrap_or(Err(QueryError::RequestTimeout(
-            "schema agreement not reached in time".to_owned(),
-        )))
+        .map_err(|err| match err {
+            SchemaAgreementError::QueryError(err) => err,
+            err @ SchemaAgreementError::Timeout { .. } => {
+                QueryError::RequestTimeout(err.to_string())
+            }
+        })
     }
 
+    /// Waits until all the nodes agree on the schema version, checking it every `interval`,
+    /// for up to `timeout`. Returns the agreed version.
+    ///
+    /// On timeout, the schema versions fetched last are returned in the error.
+    pub async fn await_schema_agreement_with(
+        &self,
+        interval: Duration,
+        timeout: Duration,
+    ) -> Result<Uuid, SchemaAgreementError> {
+        let mut last_versions = None;
+        let awaiting = async {
+            loop {
+                tokio::time::sleep(interval).await;
+                let versions = self.fetch_schema_versions().await?;
+                if let Some(agreed_version) = versions.agreed_version() {
+                    return Ok(agreed_version);
+                }
+                last_versions = Some(versions);
+            }
+        };
+        match tokio::time::timeout(timeout, awaiting).await {
+            Ok(result) => result,
+            Err(_) => Err(SchemaAgreementError::Timeout {
+                timeout,
+                versions: last_versions,
+            }),
+        }
+    }
+
+    /// Checks once whether all the nodes agree on the schema version.
+    /// Returns the agreed version, or `None` if the nodes disagree.
     pub async fn check_schema_agreement(&self) -> Result<Option<Uuid>, QueryError> {
-        let cluster_data = self.get_cluster_data();
-        let connections_iter = cluster_data.iter_working_connections()?;
+        Ok(self.fetch_schema_versions().await?.agreed_version())
+    }
 
-        let handles = connections_iter.map(|c| async move { c.fetch_schema_version().await });
-        let versions = try_join_all(handles).await?;
+    /// Fetches the schema version of each node which the driver has a working connection to.
+    /// This is a snapshot of the schema agreement, e.g. for health checks.
+    pub async fn fetch_schema_versions(&self) -> Result<SchemaVersions, QueryError> {
+        let cluster_data = self.get_cluster_data();
+        let mut pool_error = None;
+        let connections = cluster_data
+            .get_nodes_info()
+            .iter()
+            .filter_map(|node| match node.get_working_connections() {
+                Ok(connections) => connections
+                    .into_iter()
+                    .next()
+                    .map(|connection| (node.address, connection)),
+                Err(err) => {
+                    pool_error.get_or_insert(err);
+                    None
+                }
+            })
+            .collect::<Vec<_>>();
+        if connections.is_empty() {
+            if let Some(err) = pool_error {
+                return Err(err.into());
+            }
+        }
 
-        let local_version: Uuid = versions[0];
-        let in_agreement = versions.into_iter().all(|v| v == local_version);
-        Ok(in_agreement.then_some(local_version))
+        let handles = connections
+            .into_iter()
+            .map(|(address, connection)| async move {
+                Ok::<_, QueryError>((address, connection.fetch_schema_version().await?))
+            });
+        Ok(SchemaVersions {
+            versions: try_join_all(handles).await?.into_iter().collect(),
+        })
     }
 
     /// Retrieves the handle to execution profile that is used by this session
@@ -2241,6 +3022,98 @@ where
     pub fn get_default_execution_profile_handle(&self) -> &ExecutionProfileHandle {
         &self.default_execution_profile_handle
     }
+
+    // Options which are not set on an inheriting profile of the statement
+    // are taken from the default profile of the session.
+    fn resolve_execution_profile(
+        &self,
+        statement_handle: Option<&ExecutionProfileHandle>,
+    ) -> Arc<ExecutionProfileInner> {
+        ExecutionProfileHandle::resolve(statement_handle, &self.default_execution_profile_handle)
+    }
+}
+
+/// Routing of a statement, computed with [`Session::plan_for`].
+#[derive(Debug, Clone)]
+pub struct QueryPlanInfo {
+    token: Option<Token>,
+    replicas: Vec<(Arc<Node>, Shard)>,
+    plan: Vec<(Arc<Node>, Shard)>,
+}
+
+impl QueryPlanInfo {
+    /// Returns the token of the partition key, or `None` if the statement isn't token-aware.
+    pub fn token(&self) -> Option<Token> {
+        self.token
+    }
+
+    /// Returns the replicas owning the token (empty if the token is unknown).
+    pub fn replicas(&self) -> &[(Arc<Node>, Shard)] {
+        &self.replicas
+    }
+
+    /// Returns the targets in the order in which they would be tried.
+    pub fn plan(&self) -> &[(Arc<Node>, Shard)] {
+        &self.plan
+    }
+}
+
+impl Display for QueryPlanInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let targets = |targets: &[(Arc<Node>, Shard)]| {
+            targets
+                .iter()
+                .map(|(node, shard)| format!("{}/{}", node.address, shard))
+                .join(", ")
+        };
+        match self.token {
+            Some(token) => write!(f, "token: {}", token.value())?,
+            None => write!(f, "token: none")?,
+        }
+        write!(
+            f,
+            ", replicas: [{}], plan: [{}]",
+            targets(&self.replicas),
+            targets(&self.plan)
+        )
+    }
+}
+
+/// Schema versions of the nodes of the cluster, fetched with [`Session::fetch_schema_versions`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SchemaVersions {
+    versions: HashMap<NodeAddr, Uuid>,
+}
+
+impl SchemaVersions {
+    /// Returns the schema version of each node.
+    pub fn versions(&self) -> &HashMap<NodeAddr, Uuid> {
+        &self.versions
+    }
+
+    /// Returns the schema version if all the nodes agree on it.
+    pub fn agreed_version(&self) -> Option<Uuid> {
+        let mut versions = self.versions.values();
+        let first = *versions.next()?;
+        versions.all(|&version| version == first).then_some(first)
+    }
+
+    /// Returns true if all the nodes agree on the schema version.
+    pub fn is_in_agreement(&self) -> bool {
+        self.agreed_version().is_some()
+    }
+}
+
+impl Display for SchemaVersions {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let versions = self
+            .versions
+            .iter()
+            .sorted()
+            .map(|(address, version)| format!("{}: {}", address, version))
+            .join(", ");
+        write!(f, "[{}]", versions)
+    }
 }
 
 // run_query, execute_query, etc have a template type called ResT.
@@ -2265,6 +3138,12 @@ struct ExecuteQueryContext<'a> {
     history_data: Option<HistoryData<'a>>,
     query_info: &'a load_balancing::RoutingInfo<'a>,
     request_span: &'a RequestSpan,
+    // The tags of the session merged with the tags of the execution profile.
+    user_tags: &'a UserTags,
+    // 0 for the original execution, n for the n-th speculative one.
+    speculative_execution: usize,
+    // The statement uses Scylla-specific syntax, i.e. `USING TIMEOUT`.
+    requires_scylla: bool,
 }
 
 struct HistoryData<'a> {
@@ -2320,6 +3199,7 @@ impl ExecuteQueryContext<'_> {
 pub(crate) struct RequestSpan {
     span: tracing::Span,
     speculative_executions: AtomicUsize,
+    attempt_span_statement: Option<AttemptSpanStatement>,
 }
 
 impl RequestSpan {
@@ -2337,11 +3217,13 @@ impl RequestSpan {
             replicas = Empty,
             shard = Empty,
             speculative_executions = Empty,
+            user_tags = Empty,
         );
 
         Self {
             span,
             speculative_executions: 0.into(),
+            attempt_span_statement: None,
         }
     }
 
@@ -2364,6 +3246,7 @@ impl RequestSpan {
             replicas = Empty,
             shard = Empty,
             speculative_executions = Empty,
+            user_tags = Empty,
         );
 
         if let Some(partition_key) = partition_key {
@@ -2381,6 +3264,7 @@ impl RequestSpan {
         Self {
             span,
             speculative_executions: 0.into(),
+            attempt_span_statement: None,
         }
     }
 
@@ -2397,11 +3281,13 @@ impl RequestSpan {
             replicas = Empty,
             shard = Empty,
             speculative_executions = Empty,
+            user_tags = Empty,
         );
 
         Self {
             span,
             speculative_executions: 0.into(),
+            attempt_span_statement: None,
         }
     }
 
@@ -2444,12 +3330,45 @@ impl RequestSpan {
             .record("replicas", tracing::field::display(&ReplicaIps(replicas)));
     }
 
+    pub(crate) fn record_user_tags(&self, user_tags: &UserTags) {
+        if !user_tags.is_empty() {
+            self.span
+                .record("user_tags", tracing::field::display(user_tags));
+        }
+    }
+
     pub(crate) fn record_request_size(&self, size: usize) {
         self.span.record("request_size", size);
     }
 
-    pub(crate) fn inc_speculative_executions(&self) {
-        self.speculative_executions.fetch_add(1, Ordering::Relaxed);
+    /// Returns the number of the started speculative execution, counting from 1.
+    pub(crate) fn inc_speculative_executions(&self) -> usize {
+        self.speculative_executions.fetch_add(1, Ordering::Relaxed) + 1
+    }
+
+    /// Makes the request open a span for each of its attempts if the statement is given,
+    /// see [`attempt_span`](super::attempt_span).
+    pub(crate) fn with_attempt_spans(mut self, statement: Option<AttemptSpanStatement>) -> Self {
+        self.attempt_span_statement = statement;
+        self
+    }
+
+    /// Opens the span of an attempt of the request, or returns a disabled span
+    /// if attempt spans are not enabled for the request.
+    pub(crate) fn attempt_span(
+        &self,
+        node: NodeRef<'_>,
+        shard: Option<Shard>,
+        consistency: Consistency,
+        attempt: usize,
+        speculative_execution: usize,
+    ) -> tracing::Span {
+        match &self.attempt_span_statement {
+            Some(statement) => {
+                statement.attempt_span(node, shard, consistency, attempt, speculative_execution)
+            }
+            None => tracing::Span::none(),
+        }
     }
 
     pub(crate) fn span(&self) -> &tracing::Span {
@@ -2466,6 +3385,36 @@ impl Drop for RequestSpan {
     }
 }
 
+// Applies the `ALLOW FILTERING` guardrail of the execution profile to the statement.
+/// Checks whether the prepared statement is a write whose timestamp can be tracked.
+/// Conditional and counter updates do not accept client-side timestamps.
+fn check_allow_filtering(
+    cql: &str,
+    statement_config: &StatementConfig,
+    execution_profile: &ExecutionProfileInner,
+) -> Result<(), QueryError> {
+    let guardrail = execution_profile.allow_filtering_guardrail;
+    if guardrail == AllowFilteringGuardrail::Off
+        || statement_config.allow_filtering_permitted
+        || !contains_allow_filtering(cql)
+    {
+        return Ok(());
+    }
+    match guardrail {
+        AllowFilteringGuardrail::Reject => {
+            Err(BadQuery::AllowFilteringRejected(cql.to_owned()).into())
+        }
+        _ => {
+            warn!(
+                statement = cql,
+                "Executing a statement with ALLOW FILTERING, which may scan the whole table. \
+                 Use set_allow_filtering_permitted to silence this warning."
+            );
+            Ok(())
+        }
+    }
+}
+
 fn partition_key_displayer<'ps, 'res, 'spec: 'ps>(
     mut pk_values_iter: impl Iterator<Item = (&'ps [u8], &'ps ColumnSpec<'spec>)> + 'res + Clone,
 ) -> impl Display + 'res {
@@ -2481,3 +3430,229 @@ fn partition_key_displayer<'ps, 'res, 'spec: 'ps>(
         }),
     )
 }
+
+// Delays between consecutive attempts to fetch tracing info. They start at
+// `interval` and double after each attempt, until they reach `max_interval`.
+fn tracing_info_fetch_delays(
+    interval: Duration,
+    max_interval: Duration,
+) -> impl Iterator<Item = Duration> {
+    let max_interval = max_interval.max(interval);
+    std::iter::successors(Some(interval), move |delay| {
+        Some(delay.saturating_mul(2).min(max_interval))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use uuid::Uuid;
+
+    use super::{tracing_info_fetch_delays, AddressTranslator, QueryPlanInfo, SchemaVersions};
+    use crate::routing::Token;
+    use crate::test_utils::setup_tracing;
+    use crate::transport::errors::{SchemaAgreementError, TranslationError};
+    use crate::transport::node::{Node, NodeAddr};
+    use crate::transport::topology::UntranslatedPeer;
+
+    fn peer(address: &str) -> UntranslatedPeer {
+        UntranslatedPeer {
+            host_id: Uuid::new_v4(),
+            untranslated_address: SocketAddr::from_str(address).unwrap(),
+            datacenter: None,
+            rack: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn static_address_translation() {
+        setup_tracing();
+        let private = SocketAddr::from_str("10.0.0.1:9042").unwrap();
+        let public = SocketAddr::from_str("203.0.113.1:19042").unwrap();
+        let rules = HashMap::from([(private, public)]);
+
+        assert_eq!(
+            rules
+                .translate_address(&peer("10.0.0.1:9042"))
+                .await
+                .unwrap(),
+            public
+        );
+        assert!(matches!(
+            rules.translate_address(&peer("10.0.0.2:9042")).await,
+            Err(TranslationError::NoRuleForAddress(addr)) if addr == SocketAddr::from_str("10.0.0.2:9042").unwrap()
+        ));
+    }
+
+    #[tokio::test]
+    async fn static_address_translation_from_strings() {
+        setup_tracing();
+        let rules: HashMap<&'static str, &'static str> = HashMap::from([
+            ("10.0.0.1:9042", "203.0.113.1:19042"),
+            ("10.0.0.2:9042", "not an address"),
+        ]);
+
+        assert_eq!(
+            rules
+                .translate_address(&peer("10.0.0.1:9042"))
+                .await
+                .unwrap(),
+            SocketAddr::from_str("203.0.113.1:19042").unwrap()
+        );
+        assert!(matches!(
+            rules.translate_address(&peer("10.0.0.2:9042")).await,
+            Err(TranslationError::InvalidAddressInRule {
+                translated_addr_str: "not an address",
+                ..
+            })
+        ));
+        assert!(matches!(
+            rules.translate_address(&peer("10.0.0.3:9042")).await,
+            Err(TranslationError::NoRuleForAddress(_))
+        ));
+    }
+
+    #[test]
+    fn tracing_info_fetch_backoff() {
+        setup_tracing();
+        let ms = Duration::from_millis;
+        assert_eq!(
+            tracing_info_fetch_delays(ms(5), ms(50))
+                .take(6)
+                .collect::<Vec<_>>(),
+            [ms(5), ms(10), ms(20), ms(40), ms(50), ms(50)]
+        );
+
+        // By default the maximum is equal to the interval, so the delays are constant.
+        assert_eq!(
+            tracing_info_fetch_delays(ms(3), ms(3))
+                .take(3)
+                .collect::<Vec<_>>(),
+            [ms(3); 3]
+        );
+
+        // A maximum below the interval is ignored.
+        assert_eq!(
+            tracing_info_fetch_delays(ms(10), ms(1))
+                .take(2)
+                .collect::<Vec<_>>(),
+            [ms(10); 2]
+        );
+
+        // Doubling saturates instead of overflowing.
+        assert_eq!(
+            tracing_info_fetch_delays(Duration::MAX, Duration::MAX)
+                .take(2)
+                .collect::<Vec<_>>(),
+            [Duration::MAX; 2]
+        );
+    }
+
+    fn schema_versions(versions: &[(&str, u128)]) -> SchemaVersions {
+        SchemaVersions {
+            versions: versions
+                .iter()
+                .map(|&(address, version)| {
+                    (
+                        NodeAddr::Translatable(SocketAddr::from_str(address).unwrap()),
+                        Uuid::from_u128(version),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn schema_versions_agreement() {
+        setup_tracing();
+        let agreed = schema_versions(&[("10.0.0.2:9042", 1), ("10.0.0.1:9042", 1)]);
+        assert!(agreed.is_in_agreement());
+        assert_eq!(agreed.agreed_version(), Some(Uuid::from_u128(1)));
+        assert_eq!(agreed.versions().len(), 2);
+
+        let disagreed = schema_versions(&[
+            ("10.0.0.1:9042", 1),
+            ("10.0.0.2:9042", 2),
+            ("10.0.0.3:9042", 1),
+        ]);
+        assert!(!disagreed.is_in_agreement());
+        assert_eq!(disagreed.agreed_version(), None);
+
+        // Without any versions fetched, there is nothing to agree on.
+        assert_eq!(schema_versions(&[]).agreed_version(), None);
+    }
+
+    #[test]
+    fn schema_versions_display() {
+        setup_tracing();
+        let versions = schema_versions(&[("10.0.0.2:9042", 2), ("10.0.0.1:9042", 1)]);
+        // Nodes are listed in the order of their addresses.
+        assert_eq!(
+            versions.to_string(),
+            "[10.0.0.1:9042: 00000000-0000-0000-0000-000000000001, \
+             10.0.0.2:9042: 00000000-0000-0000-0000-000000000002]"
+        );
+
+        let timeout = SchemaAgreementError::Timeout {
+            timeout: Duration::from_secs(1),
+            versions: Some(versions),
+        };
+        assert_eq!(
+            timeout.to_string(),
+            "Schema agreement not reached in 1s, schema versions: \
+             [10.0.0.1:9042: 00000000-0000-0000-0000-000000000001, \
+             10.0.0.2:9042: 00000000-0000-0000-0000-000000000002]"
+        );
+        let timeout = SchemaAgreementError::Timeout {
+            timeout: Duration::from_millis(500),
+            versions: None,
+        };
+        assert_eq!(
+            timeout.to_string(),
+            "Schema agreement not reached in 500ms, schema versions: unknown"
+        );
+    }
+
+    #[test]
+    fn query_plan_info_display() {
+        setup_tracing();
+        let node = |address: &str| {
+            Arc::new(Node::new_for_test(
+                None,
+                Some(NodeAddr::Translatable(address.parse().unwrap())),
+                None,
+                None,
+            ))
+        };
+        let (a, b) = (node("10.0.0.1:9042"), node("10.0.0.2:9042"));
+
+        let info = QueryPlanInfo {
+            token: Some(Token::new(-42)),
+            replicas: vec![(a.clone(), 3)],
+            plan: vec![(a.clone(), 3), (b.clone(), 0)],
+        };
+        assert_eq!(info.token(), Some(Token::new(-42)));
+        assert_eq!(info.replicas().len(), 1);
+        assert_eq!(info.plan().len(), 2);
+        assert_eq!(
+            info.to_string(),
+            "token: -42, replicas: [10.0.0.1:9042/3], plan: [10.0.0.1:9042/3, 10.0.0.2:9042/0]"
+        );
+
+        // Without a token there are no replicas, but there is still a plan.
+        let info = QueryPlanInfo {
+            token: None,
+            replicas: Vec::new(),
+            plan: vec![(b, 1), (a, 0)],
+        };
+        assert_eq!(
+            info.to_string(),
+            "token: none, replicas: [], plan: [10.0.0.2:9042/1, 10.0.0.1:9042/0]"
+        );
+    }
+}
@@ -1,27 +1,34 @@
 //! SessionBuilder provides an easy way to create new Sessions
 
-use super::connection::SelfIdentity;
+use super::connection::{SelfIdentity, StreamIdExhaustionPolicy, MAX_STREAM_IDS_PER_CONNECTION};
 use super::execution_profile::ExecutionProfileHandle;
 #[allow(deprecated)]
 use super::session::{
     AddressTranslator, CurrentDeserializationApi, GenericSession, LegacyDeserializationApi,
     SessionConfig,
 };
-use super::Compression;
+use super::{Compression, ProtocolVersion, WriteCoalescingDelay};
 
 #[cfg(feature = "cloud")]
-use crate::cloud::{CloudConfig, CloudConfigError};
+use crate::cloud::{CloudConfig, CloudConfigError, CloudSessionError};
 use crate::transport::errors::NewSessionError;
 #[cfg(feature = "cloud")]
 use crate::ExecutionProfile;
 
 use crate::statement::Consistency;
+use crate::transport::backpressure::BackpressureThresholds;
 use crate::transport::connection_pool::PoolSize;
 use crate::transport::host_filter::HostFilter;
+use crate::transport::proxy::ProxyConfig;
+use crate::transport::reconnection_policy::ReconnectionPolicy;
+use crate::transport::sni_proxy::SniProxyConfig;
+use crate::transport::throttler::RequestThrottler;
+use crate::transport::warnings::WarningHandler;
+use scylla_cql::types::serialize::limits::ValueSizeLimits;
 use std::borrow::Borrow;
 use std::marker::PhantomData;
 use std::net::SocketAddr;
-use std::num::NonZeroU32;
+use std::num::{NonZeroU32, NonZeroU64};
 #[cfg(feature = "cloud")]
 use std::path::Path;
 use std::sync::Arc;
@@ -349,6 +356,51 @@ impl GenericSessionBuilder<DefaultMode> {
         self.config.ssl_context = ssl_context;
         self
     }
+
+    /// rustls feature
+    /// Provide SessionBuilder with a ClientConfig from rustls crate that will be
+    /// used to create a TLS connection to the database, for builds which can't link OpenSSL.
+    /// The config determines the trusted root certificates, the client certificate
+    /// and the ALPN protocols. The certificate of a node is verified against its IP address.
+    /// If set to None TLS connection won't be used, unless set with [`ssl_context`](Self::ssl_context),
+    /// which takes precedence.
+    /// Default is None.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::fs::File;
+    /// # use std::io::BufReader;
+    /// # use std::sync::Arc;
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use rustls::pki_types::pem::PemObject;
+    /// # use rustls::pki_types::{CertificateDer, PrivateKeyDer};
+    /// # use rustls::{ClientConfig, RootCertStore};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let mut root_store = RootCertStore::empty();
+    /// for cert in CertificateDer::pem_file_iter("./examples/certs/scylla.crt")? {
+    ///     root_store.add(cert?)?;
+    /// }
+    ///
+    /// let client_certs = CertificateDer::pem_file_iter("./client.crt")?.collect::<Result<_, _>>()?;
+    /// let client_key = PrivateKeyDer::from_pem_file("./client.key")?;
+    /// let mut config = ClientConfig::builder()
+    ///     .with_root_certificates(root_store)
+    ///     .with_client_auth_cert(client_certs, client_key)?;
+    /// config.alpn_protocols = vec![b"cql".to_vec()];
+    ///
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .rustls_config(Some(Arc::new(config)))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "rustls")]
+    pub fn rustls_config(mut self, rustls_config: Option<Arc<rustls::ClientConfig>>) -> Self {
+        self.config.rustls_config = rustls_config;
+        self
+    }
 }
 
 // NOTE: this `impl` block contains configuration options specific for **Cloud** [`Session`].
@@ -378,6 +430,31 @@ impl CloudSessionBuilder {
     }
 }
 
+#[cfg(feature = "cloud")]
+impl GenericSessionBuilder<DefaultMode> {
+    /// Reads the Scylla Cloud config yaml (the connection bundle) from the provided path
+    /// and connects to the cluster described by it. The contact points, TLS with SNI
+    /// and credentials are all taken from the config, and the rest of the options
+    /// have default values.
+    ///
+    /// To change other options, use [`CloudSessionBuilder`] instead.
+    ///
+    /// # Example
+    /// ```no_run
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::build_from_cloud_config("config_data.yaml").await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub async fn build_from_cloud_config(
+        cloud_config: impl AsRef<Path>,
+    ) -> Result<GenericSession<CurrentDeserializationApi>, CloudSessionError> {
+        let session = CloudSessionBuilder::new(cloud_config)?.build().await?;
+        Ok(session)
+    }
+}
+
 // This block contains configuration options that make sense both for Cloud and non-Cloud
 // `Session`s. If an option fit only one of them, it should be put in a specialised block.
 impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
@@ -403,6 +480,82 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Set compression algorithms to try, in order, on connections to nodes
+    /// which don't support the algorithm set with [`compression`](Self::compression).
+    /// The algorithms supported by a node are learned from its response to OPTIONS.
+    /// The default is no fallbacks.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::Compression;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .compression(Some(Compression::Lz4))
+    ///     .compression_fallbacks([Compression::Snappy])
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compression_fallbacks(
+        mut self,
+        compression_fallbacks: impl IntoIterator<Item = Compression>,
+    ) -> Self {
+        self.config.compression_fallbacks = compression_fallbacks.into_iter().collect();
+        self
+    }
+
+    /// Set the minimum size in bytes of a request body to be compressed.
+    /// Smaller requests are sent uncompressed, as compressing them usually isn't worth it.
+    /// The default is 0, which means that all requests are compressed.
+    /// Doesn't apply to protocol v5, which compresses whole segments.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::Compression;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .compression(Some(Compression::Lz4))
+    ///     .compression_min_size(512)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn compression_min_size(mut self, compression_min_size: usize) -> Self {
+        self.config.compression_min_size = compression_min_size;
+        self
+    }
+
+    /// Set the version of the CQL protocol to use.
+    /// The default is protocol v4, the only version supported by ScyllaDB.
+    /// If a node doesn't support the version, connections to it fall back to protocol v4.
+    ///
+    /// Protocol v5 compresses whole segments, which only works with lz4.
+    /// With other compression algorithms, v5 connections aren't compressed.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::ProtocolVersion;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .protocol_version(ProtocolVersion::V5)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn protocol_version(mut self, protocol_version: ProtocolVersion) -> Self {
+        self.config.protocol_version = protocol_version;
+        self
+    }
+
     /// Set the delay for schema agreement check. How often driver should ask if schema is in agreement
     /// The default is 200 milliseconds.
     ///
@@ -624,6 +777,33 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Sets the policy deciding how long to wait before retrying to open the connections
+    /// to a node, after they failed to open, e.g. because the node is down.
+    /// The default is [`ExponentialReconnectionPolicy`](crate::transport::reconnection_policy::ExponentialReconnectionPolicy)
+    /// with delays between 50 milliseconds and 10 seconds.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::sync::Arc;
+    /// # use std::time::Duration;
+    /// use scylla::transport::reconnection_policy::ExponentialReconnectionPolicy;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let policy = ExponentialReconnectionPolicy::new(Duration::from_secs(1), Duration::from_secs(60))
+    ///     .with_jitter();
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .reconnection_policy(Arc::new(policy))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn reconnection_policy(mut self, policy: Arc<dyn ReconnectionPolicy>) -> Self {
+        self.config.reconnection_policy = policy;
+        self
+    }
+
     /// If true, prevents the driver from connecting to the shard-aware port, even if the node supports it.
     ///
     /// _This is a Scylla-specific option_. It has no effect on Cassandra clusters.
@@ -766,6 +946,55 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Sets the maximum lifetime of a connection. Older connections are replaced
+    /// with new ones: a replacement is established first, and then the old connection
+    /// is closed once the requests sent on it complete.
+    ///
+    /// This is useful when the cluster is behind a load balancer or a NAT gateway,
+    /// which drops long-lived connections or balances only new ones.
+    /// By default, connections are not recycled.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .max_connection_lifetime(std::time::Duration::from_secs(3600))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_connection_lifetime(mut self, lifetime: Duration) -> Self {
+        self.config.max_connection_lifetime = Some(lifetime);
+        self
+    }
+
+    /// Sets the maximum number of requests sent on a connection, including the requests
+    /// sent by the driver itself, e.g. keepalives. Connections which have sent this many
+    /// requests are replaced with new ones, in the same way as with
+    /// [`max_connection_lifetime`](Self::max_connection_lifetime).
+    /// By default, connections are not recycled.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::num::NonZeroU64;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .max_requests_per_connection(NonZeroU64::new(1_000_000).unwrap())
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_requests_per_connection(mut self, max_requests: NonZeroU64) -> Self {
+        self.config.max_requests_per_connection = Some(max_requests);
+        self
+    }
+
     /// Sets the timeout for waiting for schema agreement.
     /// By default, the timeout is 60 seconds.
     ///
@@ -838,6 +1067,81 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Makes the session open connections to all nodes through an SNI proxy,
+    /// e.g. an SNI-terminating load balancer. Each connection names its node with SNI,
+    /// and the certificate presented on it is verified against that name.
+    /// TLS must be configured, too.
+    ///
+    /// See the [sni_proxy](crate::transport::sni_proxy) module for details.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::sni_proxy::SniProxyConfig;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("proxy.example.com:9142")
+    ///     .sni_proxy(SniProxyConfig::new("proxy.example.com:9142", "nodes.example.com"))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn sni_proxy(mut self, sni_proxy: SniProxyConfig) -> Self {
+        self.config.sni_proxy = Some(sni_proxy);
+        self
+    }
+
+    /// Makes the session tunnel connections to all nodes through a SOCKS5
+    /// or an HTTP proxy, optionally authenticating to it with username and password.
+    ///
+    /// See the [proxy](crate::transport::proxy) module for details.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::proxy::ProxyConfig;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .proxy(ProxyConfig::socks5("proxy.example.com:1080").with_credentials("user", "pass"))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn proxy(mut self, proxy: ProxyConfig) -> Self {
+        self.config.proxy = Some(proxy);
+        self
+    }
+
+    /// Sets the handler of the warnings which the database attaches to responses,
+    /// e.g. about aggregations without a partition key restriction or reading
+    /// many tombstones. By default, the warnings are only logged.
+    ///
+    /// See the [warnings](crate::transport::warnings) module for details.
+    ///
+    /// # Example
+    /// ```
+    /// # use std::net::SocketAddr;
+    /// # use std::sync::Arc;
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .warning_handler(Arc::new(|warning: &str, node: SocketAddr| {
+    ///         eprintln!("Warning from {node}: {warning}");
+    ///     }))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn warning_handler(mut self, warning_handler: Arc<dyn WarningHandler>) -> Self {
+        self.config.warning_handler = Some(warning_handler);
+        self
+    }
+
     /// Set the refresh metadata on schema agreement flag.
     /// The default is true.
     ///
@@ -916,6 +1220,33 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
+    /// Set the maximum delay between attempts to fetch [TracingInfo](crate::tracing::TracingInfo)
+    /// in [`Session::get_tracing_info`](crate::Session::get_tracing_info) and
+    /// [`Session::get_trace`](crate::Session::get_trace).
+    ///
+    /// The delay doubles after each attempt, starting from the one set with
+    /// [`SessionBuilder::tracing_info_fetch_interval`], until it reaches this value.
+    /// The default is 3 milliseconds, so by default the attempts are made at a constant rate.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::time::Duration;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .tracing_info_fetch_interval(Duration::from_millis(5))
+    ///     .tracing_info_fetch_max_interval(Duration::from_millis(500))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tracing_info_fetch_max_interval(mut self, max_interval: Duration) -> Self {
+        self.config.tracing_info_fetch_max_interval = max_interval;
+        self
+    }
+
     /// Set the consistency level of fetching [TracingInfo](crate::tracing::TracingInfo)
     /// in [`Session::get_tracing_info`](crate::Session::get_tracing_info).
     /// The default is [`Consistency::One`].
@@ -968,62 +1299,398 @@ impl<K: SessionBuilderKind> GenericSessionBuilder<K> {
         self
     }
 
-    /// Set the interval at which the driver refreshes the cluster metadata which contains information
-    /// about the cluster topology as well as the cluster schema.
+    /// Sets how long the driver waits for more requests to be queued on a connection
+    /// before flushing them to the socket, if write coalescing is enabled
+    /// (see [`write_coalescing`](Self::write_coalescing)).
     ///
-    /// The default is 60 seconds.
+    /// By default, the task writing to the socket only yields to the runtime,
+    /// which introduces a delay of a few to a few hundred microseconds.
+    /// Workloads which pipeline many small requests on few connections can trade
+    /// some latency for fewer syscalls by waiting for a fixed number of milliseconds.
     ///
-    /// In the given example, we have set the duration value to 20 seconds, which
-    /// means that the metadata is refreshed every 20 seconds.
     /// # Example
     /// ```
     /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::WriteCoalescingDelay;
+    /// # use std::num::NonZeroU64;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let session: Session = SessionBuilder::new()
-    ///         .known_node("127.0.0.1:9042")
-    ///         .cluster_metadata_refresh_interval(std::time::Duration::from_secs(20))
-    ///         .build()
-    ///         .await?;
-    /// #   Ok(())
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .write_coalescing_delay(WriteCoalescingDelay::Milliseconds(
+    ///         NonZeroU64::new(1).unwrap(),
+    ///     ))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn cluster_metadata_refresh_interval(mut self, interval: Duration) -> Self {
-        self.config.cluster_metadata_refresh_interval = interval;
+    pub fn write_coalescing_delay(mut self, delay: WriteCoalescingDelay) -> Self {
+        self.config.write_coalescing_delay = delay;
         self
     }
 
-    /// Set the custom identity of the driver/application/instance,
-    /// to be sent as options in STARTUP message.
+    /// Offloads decompression and parsing of responses whose frames are at least
+    /// `threshold` bytes large to a thread dedicated to blocking operations,
+    /// so that huge pages don't stall other tasks scheduled on the same worker thread.
+    /// See [`SessionConfig::decoding_offload_threshold`] for details.
     ///
-    /// By default driver name and version are sent;
-    /// application name and version and client id are not sent.
+    /// Responses are decoded by the task executing the request by default.
     ///
     /// # Example
     /// ```
     /// # use scylla::{Session, SessionBuilder};
-    /// # use scylla::transport::SelfIdentity;
     /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-    ///     let (app_major, app_minor, app_patch) = (2, 1, 3);
-    ///     let app_version = format!("{app_major}.{app_minor}.{app_patch}");
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .decoding_offload_threshold(1024 * 1024)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn decoding_offload_threshold(mut self, threshold: usize) -> Self {
+        self.config.decoding_offload_threshold = Some(threshold);
+        self
+    }
+
+    /// Sets the maximum number of buffers kept by each connection for serializing
+    /// requests and reused across requests, so that high request rates don't cause
+    /// an allocation per request. Setting it to 0 disables pooling.
+    /// See [`SessionConfig::serialization_buffer_pool_size`] for details.
     ///
-    ///     let session: Session = SessionBuilder::new()
-    ///         .known_node("127.0.0.1:9042")
-    ///         .custom_identity(
-    ///             SelfIdentity::new()
-    ///                 .with_custom_driver_version("0.13.0-custom_build_17")
-    ///                 .with_application_name("my-app")
-    ///                 .with_application_version(app_version)
-    ///         )
-    ///         .build()
-    ///         .await?;
-    /// #   Ok(())
+    /// The default is 64 buffers.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .serialization_buffer_pool_size(256)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
     /// # }
     /// ```
-    pub fn custom_identity(mut self, identity: SelfIdentity<'static>) -> Self {
-        self.config.identity = identity;
+    pub fn serialization_buffer_pool_size(mut self, size: usize) -> Self {
+        self.config.serialization_buffer_pool_size = size;
         self
     }
-}
+
+    /// Sets the capacity (in bytes) above which serialization buffers are freed
+    /// instead of being returned to the pool, so that serializing a few huge
+    /// requests doesn't keep their memory allocated.
+    /// See [`SessionConfig::serialization_buffer_max_capacity`] for details.
+    ///
+    /// The default is 64 KiB.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .serialization_buffer_max_capacity(1024 * 1024)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn serialization_buffer_max_capacity(mut self, capacity: usize) -> Self {
+        self.config.serialization_buffer_max_capacity = capacity;
+        self
+    }
+
+    /// Sets the limits on the size of bound values and of whole requests, which are
+    /// checked before sending the requests, so that too large values are reported
+    /// with the name of their column instead of being rejected by the database.
+    /// See [`SessionConfig::value_size_limits`] for details.
+    ///
+    /// By default no limits are checked.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// use scylla::serialize::limits::ValueSizeLimits;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .value_size_limits(ValueSizeLimits {
+    ///         max_cell_size: Some(1024 * 1024),
+    ///         max_collection_elements: Some(10_000),
+    ///         max_request_size: Some(16 * 1024 * 1024),
+    ///     })
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn value_size_limits(mut self, limits: ValueSizeLimits) -> Self {
+        self.config.value_size_limits = limits;
+        self
+    }
+
+    /// Set the interval at which the driver refreshes the cluster metadata which contains information
+    /// about the cluster topology as well as the cluster schema.
+    ///
+    /// The default is 60 seconds.
+    ///
+    /// In the given example, we have set the duration value to 20 seconds, which
+    /// means that the metadata is refreshed every 20 seconds.
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let session: Session = SessionBuilder::new()
+    ///         .known_node("127.0.0.1:9042")
+    ///         .cluster_metadata_refresh_interval(std::time::Duration::from_secs(20))
+    ///         .build()
+    ///         .await?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn cluster_metadata_refresh_interval(mut self, interval: Duration) -> Self {
+        self.config.cluster_metadata_refresh_interval = interval;
+        self
+    }
+
+    /// Set the custom identity of the driver/application/instance,
+    /// to be sent as options in STARTUP message.
+    ///
+    /// By default driver name and version are sent;
+    /// application name and version and client id are not sent.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::SelfIdentity;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    ///     let (app_major, app_minor, app_patch) = (2, 1, 3);
+    ///     let app_version = format!("{app_major}.{app_minor}.{app_patch}");
+    ///
+    ///     let session: Session = SessionBuilder::new()
+    ///         .known_node("127.0.0.1:9042")
+    ///         .custom_identity(
+    ///             SelfIdentity::new()
+    ///                 .with_custom_driver_version("0.13.0-custom_build_17")
+    ///                 .with_application_name("my-app")
+    ///                 .with_application_version(app_version)
+    ///         )
+    ///         .build()
+    ///         .await?;
+    /// #   Ok(())
+    /// # }
+    /// ```
+    pub fn custom_identity(mut self, identity: SelfIdentity<'static>) -> Self {
+        self.config.identity = identity;
+        self
+    }
+
+    /// Set the maximum number of concurrent requests on a single connection,
+    /// i.e. the size of its stream id table.
+    ///
+    /// The default (and maximum) is 32768, as allowed by CQL protocol v4.
+    /// Values above it are clamped; 0 is treated as 1.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .stream_id_limit(2048)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream_id_limit(mut self, limit: u16) -> Self {
+        if limit > MAX_STREAM_IDS_PER_CONNECTION || limit == 0 {
+            warn!(
+                "Stream id limit {} is out of range 1..={}, it will be clamped.",
+                limit, MAX_STREAM_IDS_PER_CONNECTION
+            );
+        }
+        self.config.stream_id_limit = limit;
+        self
+    }
+
+    /// Set the behavior of requests that find their connection with no free stream ids.
+    ///
+    /// The default is [`StreamIdExhaustionPolicy::Error`].
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use scylla::transport::StreamIdExhaustionPolicy;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .stream_id_exhaustion_policy(StreamIdExhaustionPolicy::Queue)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn stream_id_exhaustion_policy(mut self, policy: StreamIdExhaustionPolicy) -> Self {
+        self.config.stream_id_exhaustion_policy = policy;
+        self
+    }
+
+    /// Set the maximum number of orphaned stream ids of a single connection.
+    ///
+    /// A stream id is orphaned when its request is abandoned, e.g. because it timed out,
+    /// before the response arrives. It is freed when the late response arrives,
+    /// so a connection accumulating orphans is likely stuck on the server side.
+    /// A connection with more stream ids orphaned for longer than a second is closed.
+    /// Connections with half as many orphaned stream ids are replaced by the connection
+    /// pool in advance, and closed once the requests sent on them complete.
+    ///
+    /// The default is 1024.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .max_orphaned_stream_ids_per_connection(256)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn max_orphaned_stream_ids_per_connection(mut self, max: usize) -> Self {
+        self.config.max_orphaned_stream_ids_per_connection = max;
+        self
+    }
+
+    /// Enables tracking the timestamps of the last writes to at most `capacity`
+    /// most recently written partitions, which is needed by
+    /// [`Session::execute_read_your_writes`](crate::Session::execute_read_your_writes).
+    ///
+    /// With tracking enabled, writes executed as prepared statements are given
+    /// client-side timestamps, unless they have a timestamp set already.
+    /// Tracking is disabled by default.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .track_write_timestamps(10_000)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn track_write_timestamps(mut self, capacity: usize) -> Self {
+        self.config.track_write_timestamps = Some(capacity);
+        self
+    }
+
+    /// Sets the throttler consulted before each request is sent, which can delay
+    /// or reject the requests when the session is overloaded. Rejected requests fail with
+    /// [`QueryError::Overloaded`](crate::transport::errors::QueryError::Overloaded).
+    /// By default, requests are not throttled.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # use std::sync::Arc;
+    /// use scylla::transport::throttler::{ConcurrencyLimitThrottler, OverflowPolicy};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let throttler = ConcurrencyLimitThrottler::new(1024)
+    ///     .with_overflow_policy(OverflowPolicy::Queue);
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .request_throttler(Arc::new(throttler))
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn request_throttler(mut self, throttler: Arc<dyn RequestThrottler>) -> Self {
+        self.config.request_throttler = Some(throttler);
+        self
+    }
+
+    /// Enables reporting the nodes with too many requests in flight through
+    /// [`Session::backpressure_receiver`](crate::Session::backpressure_receiver).
+    /// By default, the backpressure signal is disabled.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// use scylla::transport::backpressure::BackpressureThresholds;
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .backpressure_thresholds(BackpressureThresholds::new(1000).with_low_watermark(500))
+    ///     .build()
+    ///     .await?;
+    ///
+    /// let mut backpressure = session.backpressure_receiver().unwrap();
+    /// tokio::spawn(async move {
+    ///     while backpressure.changed().await.is_ok() {
+    ///         let overloaded = backpressure.borrow().is_overloaded();
+    ///         // Stop or resume accepting work upstream.
+    ///     }
+    /// });
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn backpressure_thresholds(mut self, thresholds: BackpressureThresholds) -> Self {
+        self.config.backpressure_thresholds = Some(thresholds);
+        self
+    }
+
+    /// Attaches a tag, e.g. the name of a tenant or a workload, to the logs
+    /// and the metrics of the session. See [`UserTags`](crate::transport::UserTags) for details.
+    /// By default, there are no tags.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .user_tag("tenant", "acme")
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn user_tag(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.config.user_tags.insert(key, value);
+        self
+    }
+
+    /// Makes the session open an `INFO` span for each attempt of a request, i.e. each time
+    /// a request or a page is sent to a node, including retries and speculative executions.
+    /// The fields of the spans follow the OpenTelemetry semantic conventions for database
+    /// clients, so with `tracing-opentelemetry` the attempts show up in distributed traces,
+    /// see [the book](https://rust-driver.docs.scylladb.com/stable/logging/logging.html#attempt-spans).
+    /// By default, attempt spans are disabled.
+    ///
+    /// # Example
+    /// ```
+    /// # use scylla::{Session, SessionBuilder};
+    /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+    /// let session: Session = SessionBuilder::new()
+    ///     .known_node("127.0.0.1:9042")
+    ///     .attempt_spans(true)
+    ///     .build()
+    ///     .await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn attempt_spans(mut self, enabled: bool) -> Self {
+        self.config.attempt_spans = enabled;
+        self
+    }
+}
 
 /// Creates a [`SessionBuilder`] with default configuration, same as [`SessionBuilder::new`]
 impl Default for SessionBuilder {
@@ -1035,14 +1702,18 @@ impl Default for SessionBuilder {
 #[cfg(test)]
 mod tests {
     use scylla_cql::frame::types::SerialConsistency;
+    use scylla_cql::types::serialize::limits::ValueSizeLimits;
     use scylla_cql::Consistency;
 
     use super::SessionBuilder;
     use crate::test_utils::setup_tracing;
     use crate::transport::execution_profile::{defaults, ExecutionProfile};
     use crate::transport::node::KnownNode;
-    use crate::transport::Compression;
+    use crate::transport::{
+        Compression, ProtocolVersion, StreamIdExhaustionPolicy, WriteCoalescingDelay,
+    };
     use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+    use std::num::{NonZeroU32, NonZeroU64};
     use std::time::Duration;
 
     #[test]
@@ -1130,6 +1801,38 @@ mod tests {
         assert_eq!(builder.config.compression, None);
     }
 
+    #[test]
+    fn compression_negotiation() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.compression_fallbacks.is_empty());
+        assert_eq!(builder.config.compression_min_size, 0);
+
+        builder = builder
+            .compression(Some(Compression::Lz4))
+            .compression_fallbacks([Compression::Snappy])
+            .compression_min_size(512);
+        assert_eq!(builder.config.compression, Some(Compression::Lz4));
+        assert_eq!(
+            builder.config.compression_fallbacks,
+            vec![Compression::Snappy]
+        );
+        assert_eq!(builder.config.compression_min_size, 512);
+    }
+
+    #[test]
+    fn protocol_version() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.protocol_version, ProtocolVersion::V4);
+
+        builder = builder.protocol_version(ProtocolVersion::V5);
+        assert_eq!(builder.config.protocol_version, ProtocolVersion::V5);
+
+        builder = builder.protocol_version(ProtocolVersion::V4);
+        assert_eq!(builder.config.protocol_version, ProtocolVersion::V4);
+    }
+
     #[test]
     fn tcp_nodelay() {
         setup_tracing();
@@ -1143,6 +1846,277 @@ mod tests {
         assert!(builder.config.tcp_nodelay);
     }
 
+    #[cfg(feature = "cloud")]
+    #[tokio::test]
+    async fn build_from_missing_cloud_config() {
+        use crate::cloud::{CloudConfigError, CloudSessionError};
+
+        setup_tracing();
+        let err = SessionBuilder::build_from_cloud_config("nonexistent_cloud_config.yaml")
+            .await
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            CloudSessionError::Config(CloudConfigError::YamlOpen(_))
+        ));
+    }
+
+    #[test]
+    fn write_coalescing() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.enable_write_coalescing);
+        assert_eq!(
+            builder.config.write_coalescing_delay,
+            WriteCoalescingDelay::SmallNondeterministic
+        );
+
+        let delay = WriteCoalescingDelay::Milliseconds(NonZeroU64::new(2).unwrap());
+        builder = builder
+            .write_coalescing(false)
+            .write_coalescing_delay(delay);
+        assert!(!builder.config.enable_write_coalescing);
+        assert_eq!(builder.config.write_coalescing_delay, delay);
+    }
+
+    #[test]
+    fn keepalives() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(
+            builder.config.keepalive_interval,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            builder.config.keepalive_timeout,
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(builder.config.tcp_keepalive_interval, None);
+
+        builder = builder
+            .keepalive_interval(Duration::from_secs(5))
+            .keepalive_timeout(Duration::from_secs(10))
+            .tcp_keepalive_interval(Duration::from_secs(20));
+        assert_eq!(
+            builder.config.keepalive_interval,
+            Some(Duration::from_secs(5))
+        );
+        assert_eq!(
+            builder.config.keepalive_timeout,
+            Some(Duration::from_secs(10))
+        );
+        assert_eq!(
+            builder.config.tcp_keepalive_interval,
+            Some(Duration::from_secs(20))
+        );
+    }
+
+    #[test]
+    fn reconnection_policy() {
+        use crate::transport::reconnection_policy::ConstantReconnectionPolicy;
+        use std::sync::Arc;
+
+        setup_tracing();
+        let builder = SessionBuilder::new();
+        let mut schedule = builder.config.reconnection_policy.new_schedule();
+        assert_eq!(
+            schedule.next_delay_after_success(),
+            Duration::from_millis(50)
+        );
+
+        let builder = builder.reconnection_policy(Arc::new(ConstantReconnectionPolicy::new(
+            Duration::from_secs(3),
+        )));
+        let mut schedule = builder.config.reconnection_policy.new_schedule();
+        assert_eq!(schedule.next_delay_after_failure(), Duration::from_secs(3));
+    }
+
+    #[test]
+    fn connection_recycling() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.max_connection_lifetime, None);
+        assert_eq!(builder.config.max_requests_per_connection, None);
+
+        builder = builder
+            .max_connection_lifetime(Duration::from_secs(600))
+            .max_requests_per_connection(NonZeroU64::new(1000).unwrap());
+        assert_eq!(
+            builder.config.max_connection_lifetime,
+            Some(Duration::from_secs(600))
+        );
+        assert_eq!(
+            builder.config.max_requests_per_connection,
+            NonZeroU64::new(1000)
+        );
+    }
+
+    #[test]
+    fn stream_ids() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.stream_id_limit, 32768);
+        assert_eq!(
+            builder.config.stream_id_exhaustion_policy,
+            StreamIdExhaustionPolicy::Error
+        );
+
+        builder = builder
+            .stream_id_limit(128)
+            .stream_id_exhaustion_policy(StreamIdExhaustionPolicy::Queue);
+        assert_eq!(builder.config.stream_id_limit, 128);
+        assert_eq!(
+            builder.config.stream_id_exhaustion_policy,
+            StreamIdExhaustionPolicy::Queue
+        );
+    }
+
+    #[test]
+    fn decoding_offload_threshold() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.decoding_offload_threshold, None);
+
+        builder = builder.decoding_offload_threshold(1 << 20);
+        assert_eq!(builder.config.decoding_offload_threshold, Some(1 << 20));
+    }
+
+    #[test]
+    fn serialization_buffer_pool() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.serialization_buffer_pool_size, 64);
+        assert_eq!(builder.config.serialization_buffer_max_capacity, 64 * 1024);
+
+        builder = builder
+            .serialization_buffer_pool_size(0)
+            .serialization_buffer_max_capacity(1 << 20);
+        assert_eq!(builder.config.serialization_buffer_pool_size, 0);
+        assert_eq!(builder.config.serialization_buffer_max_capacity, 1 << 20);
+    }
+
+    #[test]
+    fn value_size_limits() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.value_size_limits.is_unlimited());
+
+        let limits = ValueSizeLimits {
+            max_cell_size: Some(1024),
+            max_collection_elements: None,
+            max_request_size: Some(4096),
+        };
+        builder = builder.value_size_limits(limits);
+        assert_eq!(builder.config.value_size_limits, limits);
+    }
+
+    #[test]
+    fn user_tags() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.user_tags.is_empty());
+
+        builder = builder
+            .user_tag("tenant", "acme")
+            .user_tag("workload", "web");
+        assert_eq!(builder.config.user_tags.get("tenant"), Some("acme"));
+        assert_eq!(builder.config.user_tags.get("workload"), Some("web"));
+        assert_eq!(builder.config.user_tags.len(), 2);
+    }
+
+    #[test]
+    fn attempt_spans() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(!builder.config.attempt_spans);
+
+        builder = builder.attempt_spans(true);
+        assert!(builder.config.attempt_spans);
+    }
+
+    #[test]
+    fn max_orphaned_stream_ids_per_connection() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.max_orphaned_stream_ids_per_connection, 1024);
+
+        builder = builder.max_orphaned_stream_ids_per_connection(256);
+        assert_eq!(builder.config.max_orphaned_stream_ids_per_connection, 256);
+    }
+
+    #[test]
+    fn track_write_timestamps() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.track_write_timestamps, None);
+
+        builder = builder.track_write_timestamps(1000);
+        assert_eq!(builder.config.track_write_timestamps, Some(1000));
+    }
+
+    #[test]
+    fn tracing_info_fetch() {
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(
+            builder.config.tracing_info_fetch_attempts,
+            NonZeroU32::new(10).unwrap()
+        );
+        assert_eq!(
+            builder.config.tracing_info_fetch_interval,
+            Duration::from_millis(3)
+        );
+        assert_eq!(
+            builder.config.tracing_info_fetch_max_interval,
+            Duration::from_millis(3)
+        );
+
+        builder = builder
+            .tracing_info_fetch_attempts(NonZeroU32::new(8).unwrap())
+            .tracing_info_fetch_interval(Duration::from_millis(5))
+            .tracing_info_fetch_max_interval(Duration::from_millis(200));
+        assert_eq!(
+            builder.config.tracing_info_fetch_attempts,
+            NonZeroU32::new(8).unwrap()
+        );
+        assert_eq!(
+            builder.config.tracing_info_fetch_interval,
+            Duration::from_millis(5)
+        );
+        assert_eq!(
+            builder.config.tracing_info_fetch_max_interval,
+            Duration::from_millis(200)
+        );
+    }
+
+    #[test]
+    fn request_throttler() {
+        use crate::transport::throttler::ConcurrencyLimitThrottler;
+        use std::sync::Arc;
+
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert!(builder.config.request_throttler.is_none());
+
+        builder = builder.request_throttler(Arc::new(ConcurrencyLimitThrottler::new(10)));
+        assert!(builder.config.request_throttler.is_some());
+    }
+
+    #[test]
+    fn backpressure_thresholds() {
+        use crate::transport::backpressure::BackpressureThresholds;
+
+        setup_tracing();
+        let mut builder = SessionBuilder::new();
+        assert_eq!(builder.config.backpressure_thresholds, None);
+
+        builder = builder.backpressure_thresholds(BackpressureThresholds::new(100));
+        assert_eq!(
+            builder.config.backpressure_thresholds,
+            Some(BackpressureThresholds::new(100))
+        );
+    }
+
     #[test]
     fn use_keyspace() {
         setup_tracing();
@@ -1259,6 +2233,75 @@ mod tests {
         );
     }
 
+    #[test]
+    fn sni_proxy() {
+        use crate::transport::sni_proxy::SniProxyConfig;
+
+        setup_tracing();
+        let builder = SessionBuilder::new();
+        assert_eq!(builder.config.sni_proxy, None);
+
+        let config = SniProxyConfig::new("proxy.example.com:9142", "nodes.example.com");
+        let builder = builder.sni_proxy(config.clone());
+        assert_eq!(builder.config.sni_proxy, Some(config));
+    }
+
+    #[test]
+    fn proxy() {
+        use crate::transport::proxy::ProxyConfig;
+
+        setup_tracing();
+        let builder = SessionBuilder::new();
+        assert_eq!(builder.config.proxy, None);
+
+        let config = ProxyConfig::http_connect("proxy.example.com:3128");
+        let builder = builder.proxy(config.clone());
+        assert_eq!(builder.config.proxy, Some(config));
+    }
+
+    #[test]
+    fn warning_handler() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        setup_tracing();
+        let builder = SessionBuilder::new();
+        assert!(builder.config.warning_handler.is_none());
+
+        let received = Arc::new(AtomicUsize::new(0));
+        let received_clone = received.clone();
+        let builder = builder.warning_handler(Arc::new(move |warning: &str, _: SocketAddr| {
+            assert_eq!(warning, "Aggregation query used without partition key");
+            received_clone.fetch_add(1, Ordering::Relaxed);
+        }));
+
+        let addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(127, 0, 0, 1)), 9042);
+        builder
+            .config
+            .warning_handler
+            .unwrap()
+            .on_warning("Aggregation query used without partition key", addr);
+        assert_eq!(received.load(Ordering::Relaxed), 1);
+    }
+
+    #[test]
+    #[cfg(feature = "rustls")]
+    fn rustls_config() {
+        setup_tracing();
+        let builder = SessionBuilder::new();
+        assert!(builder.config.rustls_config.is_none());
+
+        let mut config = rustls::ClientConfig::builder()
+            .with_root_certificates(rustls::RootCertStore::empty())
+            .with_no_client_auth();
+        config.alpn_protocols = vec![b"cql".to_vec()];
+        let builder = builder.rustls_config(Some(std::sync::Arc::new(config)));
+        assert_eq!(
+            builder.config.rustls_config.unwrap().alpn_protocols,
+            vec![b"cql".to_vec()]
+        );
+    }
+
     #[test]
     fn all_features() {
         setup_tracing();
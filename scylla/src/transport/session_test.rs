@@ -1,4 +1,4 @@
-use crate::batch::{Batch, BatchStatement};
+use crate::batch::{Batch, BatchBuilder, BatchStatement, BatchType};
 use crate::deserialize::DeserializeOwnedValue;
 use crate::prepared_statement::PreparedStatement;
 use crate::query::Query;
@@ -26,7 +26,7 @@ use assert_matches::assert_matches;
 use futures::{FutureExt, StreamExt as _, TryStreamExt};
 use itertools::Itertools;
 use scylla_cql::frame::request::query::{PagingState, PagingStateResponse};
-use scylla_cql::frame::response::result::{ColumnType, Row};
+use scylla_cql::frame::response::result::{ColumnType, CqlValue, Row};
 use scylla_cql::frame::value::CqlVarint;
 use scylla_cql::types::serialize::row::{SerializeRow, SerializedValues};
 use scylla_cql::types::serialize::value::SerializeValue;
@@ -457,8 +457,6 @@ async fn test_batch() {
         .await
         .unwrap();
 
-    // TODO: Add API that supports binding values to statements in batch creation process,
-    // to avoid problem of statements/values count mismatch
     use crate::batch::Batch;
     let mut batch: Batch = Default::default();
     batch.append_statement(&format!("INSERT INTO {}.t_batch (a, b, c) VALUES (?, ?, ?)", ks)[..]);
@@ -537,6 +535,190 @@ async fn test_batch() {
     assert_eq!(results, vec![(4, 20, String::from("foobar"))]);
 }
 
+#[tokio::test]
+async fn test_execute_concurrent() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session
+        .ddl(format!(
+            "CREATE TABLE IF NOT EXISTS {}.t_concurrent (a int primary key, b text)",
+            ks
+        ))
+        .await
+        .unwrap();
+
+    let insert = session
+        .prepare(format!(
+            "INSERT INTO {}.t_concurrent (a, b) VALUES (?, ?)",
+            ks
+        ))
+        .await
+        .unwrap();
+
+    let values = (0..100).map(|a| (a, a.to_string()));
+    let results: Vec<_> = session
+        .execute_concurrent(
+            &insert,
+            futures::stream::iter(values),
+            std::num::NonZeroUsize::new(8).unwrap(),
+        )
+        .collect()
+        .await;
+    assert_eq!(results.len(), 100);
+    assert!(results.iter().all(Result::is_ok));
+
+    // A failed execution doesn't affect the others.
+    let values = vec![
+        (100, CqlValue::Text("100".to_owned())),
+        (101, CqlValue::Int(101)),
+        (102, CqlValue::Text("102".to_owned())),
+    ];
+    let results: Vec<_> = session
+        .execute_concurrent(
+            &insert,
+            futures::stream::iter(values),
+            std::num::NonZeroUsize::new(2).unwrap(),
+        )
+        .collect()
+        .await;
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert!(results[2].is_ok());
+
+    let count: i64 = session
+        .query_unpaged(format!("SELECT COUNT(*) FROM {}.t_concurrent", ks), &[])
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .single_row::<(i64,)>()
+        .unwrap()
+        .0;
+    assert_eq!(count, 102);
+}
+
+#[tokio::test]
+async fn test_cached_result_metadata_outdated() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session
+        .ddl(format!(
+            "CREATE TABLE IF NOT EXISTS {}.t_cached_metadata (a int primary key, b int)",
+            ks
+        ))
+        .await
+        .unwrap();
+    session
+        .query_unpaged(
+            format!("INSERT INTO {}.t_cached_metadata (a, b) VALUES (1, 2)", ks),
+            &[],
+        )
+        .await
+        .unwrap();
+
+    let mut select = session
+        .prepare(format!("SELECT * FROM {}.t_cached_metadata", ks))
+        .await
+        .unwrap();
+    select.set_use_cached_result_metadata(true);
+
+    let (a, b) = session
+        .execute_unpaged(&select, &[])
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .single_row::<(i32, i32)>()
+        .unwrap();
+    assert_eq!((a, b), (1, 2));
+
+    // A new column changes the result of `SELECT *`. In protocol v5 the server sends
+    // the new metadata, in v4 the metadata is never skipped, so the result is correct in both.
+    session
+        .ddl(format!("ALTER TABLE {}.t_cached_metadata ADD c text", ks))
+        .await
+        .unwrap();
+    let (a, b, c) = session
+        .execute_unpaged(&select, &[])
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .single_row::<(i32, i32, Option<String>)>()
+        .unwrap();
+    assert_eq!((a, b, c), (1, 2, None));
+}
+
+#[tokio::test]
+async fn test_batch_builder() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session
+        .ddl(format!(
+            "CREATE TABLE IF NOT EXISTS {}.t_batch_builder (a int, b int, c text, primary key (a, b))",
+            ks
+        ))
+        .await
+        .unwrap();
+
+    let insert = session
+        .prepare(format!(
+            "INSERT INTO {}.t_batch_builder (a, b, c) VALUES (?, ?, ?)",
+            ks
+        ))
+        .await
+        .unwrap();
+
+    let mut builder = BatchBuilder::new(BatchType::Logged);
+    for b in 0..3 {
+        builder.append_prepared(&insert, (1, b, "abc")).unwrap();
+    }
+    builder.append_query(format!(
+        "INSERT INTO {}.t_batch_builder (a, b, c) VALUES (1, 3, 'def')",
+        ks
+    ));
+
+    // Mismatched values are rejected when appended, leaving the batch unchanged.
+    assert!(builder.append_prepared(&insert, (1, 4)).is_err());
+    assert!(builder.append_prepared(&insert, (1, "4", "abc")).is_err());
+    assert_eq!(builder.len(), 4);
+
+    let (batch, values) = builder.build();
+    session.batch(&batch, &values).await.unwrap();
+
+    let results: Vec<(i32, String)> = session
+        .query_unpaged(
+            format!("SELECT b, c FROM {}.t_batch_builder WHERE a = 1", ks),
+            &[],
+        )
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .rows::<(i32, String)>()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    assert_eq!(
+        results,
+        vec![
+            (0, "abc".to_owned()),
+            (1, "abc".to_owned()),
+            (2, "abc".to_owned()),
+            (3, "def".to_owned())
+        ]
+    );
+}
+
 // This is a regression test for #1134.
 #[tokio::test]
 async fn test_batch_to_multiple_tables() {
@@ -573,6 +755,139 @@ async fn test_batch_to_multiple_tables() {
         .unwrap();
 }
 
+#[tokio::test]
+async fn test_batch_with_named_values() {
+    use crate::batch::Batch;
+    use scylla_cql::frame::frame_errors::CqlRequestSerializationError;
+    use scylla_cql::frame::request::batch::{
+        BatchSerializationError, BatchStatementSerializationError,
+    };
+    use scylla_cql::types::serialize::row::{BuiltinTypeCheckError, BuiltinTypeCheckErrorKind};
+
+    #[derive(scylla::SerializeRow)]
+    #[scylla(crate = crate)]
+    struct Row<'a> {
+        c: &'a str,
+        b: i32,
+        a: i32,
+    }
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session
+        .ddl(format!(
+            "CREATE TABLE IF NOT EXISTS {}.t_batch (a int, b int, c text, primary key (a, b))",
+            ks
+        ))
+        .await
+        .unwrap();
+
+    // Unprepared statements with named bind markers, given values by name.
+    let mut batch: Batch = Default::default();
+    batch
+        .append_statement(&format!("INSERT INTO {}.t_batch (a, b, c) VALUES (:a, :b, :c)", ks)[..]);
+    batch
+        .append_statement(&format!("UPDATE {}.t_batch SET c = :c WHERE a = :a AND b = :b", ks)[..]);
+    batch.append_statement(
+        &format!("INSERT INTO {}.t_batch (a, b, c) VALUES (:a, :b, 'ma')", ks)[..],
+    );
+
+    let values = (
+        Row {
+            a: 1,
+            b: 2,
+            c: "ala",
+        },
+        HashMap::from([
+            ("b", CqlValue::Int(3)),
+            ("c", CqlValue::Text("kota".to_owned())),
+            ("a", CqlValue::Int(1)),
+        ]),
+        HashMap::from([("b", 4_i32), ("a", 1_i32)]),
+    );
+    session.batch(&batch, values).await.unwrap();
+
+    let mut results: Vec<(i32, i32, String)> = session
+        .query_unpaged(format!("SELECT a, b, c FROM {}.t_batch", ks), &[])
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .rows::<(i32, i32, String)>()
+        .unwrap()
+        .collect::<Result<_, _>>()
+        .unwrap();
+    results.sort();
+    assert_eq!(
+        results,
+        vec![
+            (1, 2, String::from("ala")),
+            (1, 3, String::from("kota")),
+            (1, 4, String::from("ma"))
+        ]
+    );
+
+    // A missing or an unused named value fails the batch before it is sent,
+    // with the index of the statement.
+    let typeck_err = |err: QueryError| {
+        let QueryError::CqlRequestSerialization(CqlRequestSerializationError::BatchSerialization(
+            BatchSerializationError::StatementSerialization {
+                statement_idx,
+                error: BatchStatementSerializationError::ValuesSerialiation(err),
+            },
+        )) = err
+        else {
+            panic!("unexpected error: {}", err)
+        };
+        let err = err.downcast_ref::<BuiltinTypeCheckError>().unwrap();
+        (statement_idx, err.kind.clone())
+    };
+
+    let missing = (
+        Row { a: 5, b: 6, c: "" },
+        HashMap::from([
+            ("c", CqlValue::Text("".to_owned())),
+            ("a", CqlValue::Int(5)),
+        ]),
+        HashMap::from([("b", 7_i32), ("a", 5_i32)]),
+    );
+    let err = session.batch(&batch, missing).await.unwrap_err();
+    assert_matches!(
+        typeck_err(err),
+        (1, BuiltinTypeCheckErrorKind::ValueMissingForColumn { name }) if name == "b"
+    );
+
+    let unused = (
+        Row { a: 5, b: 6, c: "" },
+        HashMap::from([
+            ("b", CqlValue::Int(6)),
+            ("c", CqlValue::Text("".to_owned())),
+            ("a", CqlValue::Int(5)),
+        ]),
+        HashMap::from([("b", 7_i32), ("a", 5_i32), ("c", 8_i32)]),
+    );
+    let err = session.batch(&batch, unused).await.unwrap_err();
+    assert_matches!(
+        typeck_err(err),
+        (2, BuiltinTypeCheckErrorKind::NoColumnWithName { name }) if name == "c"
+    );
+
+    // Nothing was written by the failed batches.
+    let count: i64 = session
+        .query_unpaged(format!("SELECT COUNT(*) FROM {}.t_batch", ks), &[])
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .single_row::<(i64,)>()
+        .unwrap()
+        .0;
+    assert_eq!(count, 3);
+}
+
 #[tokio::test]
 async fn test_token_calculation() {
     setup_tracing();
@@ -634,6 +949,96 @@ async fn test_token_calculation() {
     }
 }
 
+#[tokio::test]
+async fn test_plan_for() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    // The replica is looked up in the token ring, so tablets are disabled.
+    let mut create_ks = format!(
+        "CREATE KEYSPACE IF NOT EXISTS {ks} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}"
+    );
+    if scylla_supports_tablets(&session).await {
+        create_ks += " AND TABLETS = {'enabled': false}"
+    }
+    session.ddl(create_ks).await.unwrap();
+    session
+        .ddl(format!(
+            "CREATE TABLE IF NOT EXISTS {}.t (a int primary key)",
+            ks
+        ))
+        .await
+        .unwrap();
+    session.await_schema_agreement().await.unwrap();
+    session.refresh_metadata().await.unwrap();
+
+    let handle = ExecutionProfile::builder()
+        .load_balancing_policy(
+            crate::load_balancing::DefaultPolicy::builder()
+                .enable_shuffling_replicas(false)
+                .build(),
+        )
+        .build()
+        .into_handle();
+    let mut prepared = session
+        .prepare(format!("SELECT a FROM {}.t WHERE a = ?", ks))
+        .await
+        .unwrap();
+    prepared.set_execution_profile_handle(Some(handle));
+
+    let plan_info = session.plan_for(&prepared, (1,)).unwrap();
+    let plan = plan_info.plan();
+    assert_eq!(
+        plan.len(),
+        session.get_cluster_data().get_nodes_info().len()
+    );
+
+    // The replica goes first, and with shuffling disabled the plan is stable.
+    let token = prepared.calculate_token(&(1,)).unwrap().unwrap();
+    let replicas = session
+        .get_cluster_data()
+        .get_token_endpoints(&ks, "t", token);
+    assert_eq!(plan[0].0.host_id, replicas[0].0.host_id);
+    for _ in 0..8 {
+        let next_plan_info = session.plan_for(&prepared, (1,)).unwrap();
+        assert_eq!(
+            next_plan_info.plan()[0].0.host_id,
+            plan[0].0.host_id,
+            "the first target changed"
+        );
+    }
+
+    assert_eq!(plan_info.token(), Some(token));
+    assert_eq!(
+        plan_info
+            .replicas()
+            .iter()
+            .map(|(node, shard)| (node.host_id, *shard))
+            .collect::<Vec<_>>(),
+        replicas
+            .iter()
+            .map(|(node, shard)| (node.host_id, *shard))
+            .collect::<Vec<_>>()
+    );
+    assert!(plan_info
+        .to_string()
+        .starts_with(&format!("token: {}", token.value())));
+
+    // Without the partition key there is no token and no replicas, but all nodes are still planned.
+    let unrouted = session
+        .prepare(format!("SELECT a FROM {}.t", ks))
+        .await
+        .unwrap();
+    let plan_info = session.plan_for(&unrouted, ()).unwrap();
+    assert_eq!(plan_info.token(), None);
+    assert!(plan_info.replicas().is_empty());
+    assert_eq!(
+        plan_info.plan().len(),
+        session.get_cluster_data().get_nodes_info().len()
+    );
+}
+
 #[tokio::test]
 async fn test_token_awareness() {
     setup_tracing();
@@ -1104,6 +1509,12 @@ async fn test_get_tracing_info(session: &Session, ks: String) {
     let tracing_info: TracingInfo = session.get_tracing_info(&tracing_id).await.unwrap();
     assert!(!tracing_info.events.is_empty());
     assert!(!tracing_info.nodes().is_empty());
+
+    // The complete trace has the duration of the query
+    let trace: TracingInfo = session.get_trace(&tracing_id).await.unwrap();
+    assert!(trace.is_complete());
+    assert!(trace.elapsed().is_some());
+    assert!(trace.events.len() >= tracing_info.events.len());
 }
 
 async fn test_tracing_query_iter(session: &Session, ks: String) {
@@ -1211,11 +1622,151 @@ async fn assert_in_tracing_table(session: &Session, tracing_uuid: Uuid) {
     panic!("No rows for tracing with this session id!");
 }
 
+#[tokio::test]
+async fn test_wait_until_pools_ready() {
+    use crate::transport::session::PoolSize;
+    use std::num::NonZeroUsize;
+    use std::time::Duration;
+
+    setup_tracing();
+    let session = create_new_session_builder()
+        .pool_size(PoolSize::PerShard(NonZeroUsize::new(2).unwrap()))
+        .build()
+        .await
+        .unwrap();
+    tokio::time::timeout(Duration::from_secs(30), session.wait_until_pools_ready())
+        .await
+        .unwrap();
+
+    for node in session.get_cluster_data().get_nodes_info() {
+        let nr_shards = node
+            .sharder()
+            .map_or(1, |sharder| sharder.nr_shards.get() as usize);
+        assert_eq!(node.get_working_connections().unwrap().len(), 2 * nr_shards);
+    }
+}
+
+#[tokio::test]
+async fn test_shard_aware_port_status() {
+    use crate::transport::session::ShardAwarePortStatus;
+    use std::time::Duration;
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    tokio::time::timeout(Duration::from_secs(30), session.wait_until_pools_ready())
+        .await
+        .unwrap();
+    for node in session.get_cluster_data().get_nodes_info() {
+        // Connections to a node with more than one shard are opened to the shard-aware port,
+        // which is reachable in the test cluster.
+        let expected = match node.sharder() {
+            Some(sharder) if sharder.nr_shards.get() > 1 => ShardAwarePortStatus::Working,
+            Some(_) => ShardAwarePortStatus::Unknown,
+            None => ShardAwarePortStatus::NotAdvertised,
+        };
+        assert_eq!(node.shard_aware_port_status(), expected);
+    }
+
+    let session = create_new_session_builder()
+        .disallow_shard_aware_port(true)
+        .build()
+        .await
+        .unwrap();
+    for node in session.get_cluster_data().get_nodes_info() {
+        assert_eq!(
+            node.shard_aware_port_status(),
+            ShardAwarePortStatus::Disabled
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_cluster_health() {
+    use std::time::Duration;
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    tokio::time::timeout(Duration::from_secs(30), session.wait_until_pools_ready())
+        .await
+        .unwrap();
+
+    let health = session.cluster_health();
+    assert!(health.is_ready());
+    assert_eq!(health.unavailable_nodes().count(), 0);
+    assert_eq!(
+        health.nodes().len(),
+        session.get_cluster_data().get_nodes_info().len()
+    );
+    for node in health.nodes() {
+        let pool = node.pool.as_ref().unwrap();
+        assert!(pool.is_full());
+        assert!(pool.covers_all_shards());
+        assert_eq!(node.in_flight, 0);
+    }
+}
+
+#[tokio::test]
+async fn test_connection_recycling() {
+    use crate::transport::session::PoolSize;
+    use std::num::{NonZeroU64, NonZeroUsize};
+    use std::time::Duration;
+
+    setup_tracing();
+    let session = create_new_session_builder()
+        .pool_size(PoolSize::PerHost(NonZeroUsize::new(1).unwrap()))
+        .max_requests_per_connection(NonZeroU64::new(10).unwrap())
+        .build()
+        .await
+        .unwrap();
+    let node = session.get_cluster_data().get_nodes_info()[0].clone();
+    let first_connection = node.get_working_connections().unwrap()[0].clone();
+
+    // The connection is replaced once the pool notices it has sent too many requests.
+    tokio::time::timeout(Duration::from_secs(10), async {
+        loop {
+            session
+                .query_unpaged("SELECT host_id FROM system.local", &[])
+                .await
+                .unwrap();
+            let connections = node.get_working_connections().unwrap();
+            if connections.len() == 1 && !Arc::ptr_eq(&connections[0], &first_connection) {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(100)).await;
+        }
+    })
+    .await
+    .unwrap();
+
+    // The retired connection stays usable until it is dropped.
+    assert!(first_connection.get_sent_requests_count() >= 10);
+}
+
 #[tokio::test]
 async fn test_await_schema_agreement() {
+    use std::time::Duration;
+
     setup_tracing();
     let session = create_new_session_builder().build().await.unwrap();
-    let _schema_version = session.await_schema_agreement().await.unwrap();
+    let schema_version = session.await_schema_agreement().await.unwrap();
+
+    let versions = session.fetch_schema_versions().await.unwrap();
+    assert!(versions.is_in_agreement());
+    assert_eq!(versions.agreed_version(), Some(schema_version));
+    assert_eq!(
+        versions.versions().len(),
+        session.get_cluster_data().get_nodes_info().len()
+    );
+    assert_eq!(
+        session.check_schema_agreement().await.unwrap(),
+        Some(schema_version)
+    );
+
+    let agreed_version = session
+        .await_schema_agreement_with(Duration::from_millis(10), Duration::from_secs(5))
+        .await
+        .unwrap();
+    assert_eq!(agreed_version, schema_version);
 }
 
 #[tokio::test]
@@ -1401,14 +1952,81 @@ async fn test_prepared_config() {
     setup_tracing();
     let session = create_new_session_builder().build().await.unwrap();
 
-    let mut query = Query::new("SELECT * FROM system_schema.tables");
-    query.set_is_idempotent(true);
-    query.set_page_size(42);
+    let mut query = Query::new("SELECT * FROM system_schema.tables");
+    query.set_is_idempotent(true);
+    query.set_page_size(42);
+
+    let prepared_statement = session.prepare(query).await.unwrap();
+
+    assert!(prepared_statement.get_is_idempotent());
+    assert_eq!(prepared_statement.get_page_size(), 42);
+}
+
+#[tokio::test]
+async fn test_page_prefetch_and_adaptive_page_size() {
+    use crate::statement::{AdaptivePageSize, PagePrefetch};
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session
+        .ddl(format!(
+            "CREATE TABLE IF NOT EXISTS {}.t (a int, b int, c text, primary key (a, b))",
+            ks
+        ))
+        .await
+        .unwrap();
+    let insert = session
+        .prepare(format!("INSERT INTO {}.t (a, b, c) VALUES (0, ?, ?)", ks))
+        .await
+        .unwrap();
+    for b in 0..100 {
+        session
+            .execute_unpaged(&insert, (b, "x".repeat(b as usize)))
+            .await
+            .unwrap();
+    }
+
+    let mut query = Query::new(format!("SELECT b, c FROM {}.t WHERE a = 0", ks));
+    query.set_page_size(7);
+    // Every page is larger than the budget, so pages are fetched one by one.
+    query.set_page_prefetch(Some(PagePrefetch::new(3).with_max_bytes(1)));
+    query.set_adaptive_page_size(Some(
+        AdaptivePageSize::new(1, 50).with_target_page_bytes(1000),
+    ));
+    let prepared = session.prepare(query.clone()).await.unwrap();
+    assert_eq!(prepared.get_page_prefetch(), query.get_page_prefetch());
+    assert_eq!(
+        prepared.get_adaptive_page_size(),
+        query.get_adaptive_page_size()
+    );
 
-    let prepared_statement = session.prepare(query).await.unwrap();
+    let rows: Vec<(i32, String)> = session
+        .query_iter(query, &[])
+        .await
+        .unwrap()
+        .rows_stream()
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 100);
+    assert!(rows
+        .iter()
+        .enumerate()
+        .all(|(b, (row_b, c))| *row_b == b as i32 && c.len() == b));
 
-    assert!(prepared_statement.get_is_idempotent());
-    assert_eq!(prepared_statement.get_page_size(), 42);
+    let rows: Vec<(i32, String)> = session
+        .execute_iter(prepared, &[])
+        .await
+        .unwrap()
+        .rows_stream()
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert_eq!(rows.len(), 100);
 }
 
 fn udt_type_a_def(ks: &str) -> Arc<UserDefinedType> {
@@ -1962,6 +2580,75 @@ async fn test_named_bind_markers() {
     }
 }
 
+#[tokio::test]
+async fn test_named_bind_markers_in_unprepared_statement() {
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = unique_keyspace_name();
+
+    session
+        .ddl(format!("CREATE KEYSPACE {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks))
+        .await
+        .unwrap();
+    session.use_keyspace(ks, false).await.unwrap();
+    session
+        .ddl("CREATE TABLE t (pk int, ck int, v text, PRIMARY KEY (pk, ck))")
+        .await
+        .unwrap();
+
+    #[derive(scylla::SerializeRow)]
+    #[scylla(crate = crate)]
+    struct Row<'a> {
+        v: &'a str,
+        ck: i32,
+        pk: i32,
+    }
+
+    // The fields are matched with the markers by name, regardless of their order.
+    let query = Query::new("INSERT INTO t (pk, ck, v) VALUES (:pk, :ck, :v)");
+    session
+        .query_unpaged(
+            query,
+            Row {
+                v: "abc",
+                ck: 2,
+                pk: 1,
+            },
+        )
+        .await
+        .unwrap();
+
+    #[derive(scylla::SerializeRow)]
+    #[scylla(crate = crate)]
+    struct Key {
+        pk: i32,
+        ck: i32,
+    }
+
+    let (v,) = session
+        .query_unpaged(
+            "SELECT v FROM t WHERE pk = :pk AND ck = :ck",
+            Key { pk: 1, ck: 2 },
+        )
+        .await
+        .unwrap()
+        .into_rows_result()
+        .unwrap()
+        .single_row::<(String,)>()
+        .unwrap();
+    assert_eq!(v, "abc");
+
+    // A marker without a matching field fails serialization, so the statement is not executed.
+    let err = session
+        .query_unpaged(
+            "SELECT v FROM t WHERE pk = :pk AND ck = :other",
+            Key { pk: 1, ck: 2 },
+        )
+        .await
+        .unwrap_err();
+    assert_matches!(err, QueryError::BadQuery(BadQuery::SerializationError(_)));
+}
+
 #[tokio::test]
 async fn test_prepared_partitioner() {
     let session = create_new_session_builder().build().await.unwrap();
@@ -3231,3 +3918,437 @@ async fn test_vector_type_prepared() {
 
     // TODO: Implement and test SELECT statements and bind values (`?`)
 }
+
+// Lightweight transactions are not supported with tablets, so they are disabled
+// in keyspaces used by the recipes.
+async fn create_ks_for_lwt(session: &Session) -> String {
+    let ks = unique_keyspace_name();
+    let mut create_ks = format!("CREATE KEYSPACE {} WITH REPLICATION = {{'class': 'NetworkTopologyStrategy', 'replication_factor': 1}}", ks);
+    if scylla_supports_tablets(session).await {
+        create_ks += " and TABLETS = { 'enabled': false}";
+    }
+    session.ddl(create_ks).await.unwrap();
+    ks
+}
+
+#[tokio::test]
+async fn test_lease() {
+    use crate::recipes::{LeaseError, LeaseManager};
+    use std::time::Duration;
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = create_ks_for_lwt(&session).await;
+    let table = format!("{}.leases", ks);
+    session
+        .ddl(LeaseManager::create_table_query(&table))
+        .await
+        .unwrap();
+    let leases = LeaseManager::new(&session, &table, Duration::from_secs(2))
+        .await
+        .unwrap();
+
+    // Contended acquisition: exactly one holder wins.
+    let holders: Vec<String> = (0..8).map(|i| format!("worker-{}", i)).collect();
+    let attempts = futures::future::join_all(
+        holders
+            .iter()
+            .map(|holder| leases.try_acquire(&session, "lease", holder)),
+    )
+    .await;
+    let mut acquired: Vec<_> = attempts
+        .into_iter()
+        .filter_map(|attempt| attempt.unwrap())
+        .collect();
+    assert_eq!(acquired.len(), 1);
+    let mut first = acquired.pop().unwrap();
+    assert_eq!(first.name(), "lease");
+    assert!(first.is_valid());
+
+    // A held lease can't be acquired again, not even by its holder.
+    let holder = first.holder().to_owned();
+    assert!(leases
+        .try_acquire(&session, "lease", &holder)
+        .await
+        .unwrap()
+        .is_none());
+
+    // Renewal by the holder keeps the fencing token.
+    let token = first.fencing_token();
+    leases.renew(&session, &mut first).await.unwrap();
+    assert_eq!(first.fencing_token(), token);
+
+    // After a release, the lease can be acquired right away.
+    leases.release(&session, first.clone()).await.unwrap();
+    let mut second = leases
+        .try_acquire(&session, "lease", "other")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(second.fencing_token() > token);
+
+    // The previous holder can neither renew nor release the lease anymore.
+    assert_matches!(
+        leases.renew(&session, &mut first).await,
+        Err(LeaseError::Lost { current_holder: Some(current), .. }) if current == "other"
+    );
+    assert_matches!(
+        leases.release(&session, first).await,
+        Err(LeaseError::Lost { current_holder: Some(current), .. }) if current == "other"
+    );
+
+    // Once the TTL passes without a renewal, the lease can be acquired by somebody else.
+    assert!(leases
+        .try_acquire(&session, "lease", "late")
+        .await
+        .unwrap()
+        .is_none());
+    tokio::time::sleep(leases.ttl() + Duration::from_secs(1)).await;
+    assert!(!second.is_valid());
+    let third = leases
+        .try_acquire(&session, "lease", "late")
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(third.fencing_token() > second.fencing_token());
+    assert_matches!(
+        leases.renew(&session, &mut second).await,
+        Err(LeaseError::Lost { current_holder: Some(current), .. }) if current == "late"
+    );
+}
+
+#[tokio::test(flavor = "multi_thread")]
+async fn test_versioned_update() {
+    use crate::recipes::{ContentionBackoff, VersionedUpdate, VersionedUpdateError};
+    use scylla_cql::frame::types::SerialConsistency;
+    use std::convert::Infallible;
+    use std::time::Duration;
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = create_ks_for_lwt(&session).await;
+    session
+        .ddl(format!(
+            "CREATE TABLE {}.accounts (id int PRIMARY KEY, balance bigint, version bigint)",
+            ks
+        ))
+        .await
+        .unwrap();
+    for id in [1_i32, 2] {
+        session
+            .query_unpaged(
+                format!("INSERT INTO {}.accounts (id, balance) VALUES (?, 0)", ks),
+                (id,),
+            )
+            .await
+            .unwrap();
+    }
+
+    let mut read = session
+        .prepare(format!(
+            "SELECT balance, version FROM {}.accounts WHERE id = ?",
+            ks
+        ))
+        .await
+        .unwrap();
+    read.set_serial_read(SerialConsistency::Serial).unwrap();
+    let write = session
+        .prepare(format!(
+            "UPDATE {}.accounts SET balance = ?, version = ? WHERE id = ? IF version = ?",
+            ks
+        ))
+        .await
+        .unwrap();
+    let update = VersionedUpdate::from_prepared(read.clone(), write.clone(), "version")
+        .unwrap()
+        .with_backoff(ContentionBackoff::new(
+            100,
+            Duration::from_millis(1),
+            Duration::from_millis(50),
+        ));
+    let read_account = |id: i32| {
+        let read = &read;
+        let session = &session;
+        async move {
+            session
+                .execute_unpaged(read, (id,))
+                .await
+                .unwrap()
+                .into_rows_result()
+                .unwrap()
+                .single_row::<(i64, Option<i64>)>()
+                .unwrap()
+        }
+    };
+
+    // Concurrent increments are retried on conflicts, so none of them is lost.
+    let outcomes = futures::future::join_all((0..8).map(|_| {
+        update.execute(
+            &session,
+            (1_i32,),
+            |(balance, _): (i64, Option<i64>), versions| {
+                Ok::<_, Infallible>((balance + 1, versions.next, 1_i32, versions.expected))
+            },
+        )
+    }))
+    .await;
+    let mut versions: Vec<i64> = outcomes
+        .into_iter()
+        .map(|outcome| outcome.unwrap().version)
+        .collect();
+    versions.sort_unstable();
+    assert_eq!(versions, (1..=8).collect::<Vec<i64>>());
+    assert_eq!(read_account(1).await, (8, Some(8)));
+
+    // Writes the row in between the read and the write of a versioned update.
+    let conflicting_write = |values: (i64, i64, i32, Option<i64>)| {
+        tokio::task::block_in_place(|| {
+            tokio::runtime::Handle::current().block_on(session.execute_unpaged(&write, values))
+        })
+        .unwrap();
+    };
+
+    // A conflicting update makes the cycle start over with a fresh read.
+    let mut calls = 0;
+    let outcome = update
+        .execute(
+            &session,
+            (2_i32,),
+            |(balance, _): (i64, Option<i64>), versions| {
+                calls += 1;
+                if calls == 1 {
+                    conflicting_write((10, 1, 2, None));
+                }
+                Ok::<_, Infallible>((balance + 1, versions.next, 2_i32, versions.expected))
+            },
+        )
+        .await
+        .unwrap();
+    assert_eq!(calls, 2);
+    assert_eq!(outcome.attempts, 2);
+    assert_eq!(outcome.version, 2);
+    assert_eq!(read_account(2).await, (11, Some(2)));
+
+    // Once the attempts run out, the last conflict is returned.
+    let single_attempt =
+        update
+            .clone()
+            .with_backoff(ContentionBackoff::new(1, Duration::ZERO, Duration::ZERO));
+    let result = single_attempt
+        .execute(
+            &session,
+            (2_i32,),
+            |(balance, _): (i64, Option<i64>), versions| {
+                conflicting_write((0, 3, 2, Some(2)));
+                Ok::<_, Infallible>((balance + 1, versions.next, 2_i32, versions.expected))
+            },
+        )
+        .await;
+    assert_matches!(
+        result,
+        Err(VersionedUpdateError::Conflict(conflict))
+            if conflict.attempts == 1 && conflict.expected == Some(2) && conflict.actual == Some(3)
+    );
+    assert_eq!(read_account(2).await, (0, Some(3)));
+
+    // The greatest version can't be incremented.
+    conflicting_write((0, i64::MAX, 2, Some(3)));
+    let result = update
+        .execute(
+            &session,
+            (2_i32,),
+            |(balance, _): (i64, Option<i64>), versions| {
+                Ok::<_, Infallible>((balance + 1, versions.next, 2_i32, versions.expected))
+            },
+        )
+        .await;
+    assert_matches!(result, Err(VersionedUpdateError::VersionOverflow));
+}
+
+#[tokio::test]
+async fn test_outbox_consumer() {
+    use crate::recipes::{LeaseManager, OutboxConfig, OutboxConsumer};
+    use std::convert::Infallible;
+    use std::sync::Mutex;
+    use std::time::{Duration, SystemTime};
+
+    setup_tracing();
+    let session = create_new_session_builder().build().await.unwrap();
+    let ks = create_ks_for_lwt(&session).await;
+    session
+        .ddl(format!(
+            "CREATE TABLE {}.outbox (shard int, bucket bigint, id timeuuid, payload int, PRIMARY KEY ((shard, bucket), id))",
+            ks
+        ))
+        .await
+        .unwrap();
+    session
+        .ddl(OutboxConsumer::<(i32,)>::create_progress_table_query(
+            &format!("{}.progress", ks),
+        ))
+        .await
+        .unwrap();
+    session
+        .ddl(LeaseManager::create_table_query(&format!("{}.leases", ks)))
+        .await
+        .unwrap();
+
+    let config = |consumer_id: &str| {
+        OutboxConfig::new(
+            format!("{}.outbox", ks),
+            "payload",
+            format!("{}.progress", ks),
+            format!("{}.leases", ks),
+            consumer_id,
+        )
+        .with_shards(2)
+        .with_bucket_duration(Duration::from_secs(1))
+        .with_grace_period(Duration::from_millis(500))
+        .with_batch_size(3)
+    };
+    let mut first = OutboxConsumer::<(i32,)>::new(&session, config("first"))
+        .await
+        .unwrap();
+    let mut second = OutboxConsumer::<(i32,)>::new(&session, config("second"))
+        .await
+        .unwrap();
+
+    // Shards consumed for the first time start at the current bucket.
+    let delivered = Arc::new(Mutex::new(Vec::new()));
+    let handler = |consumer: &'static str| {
+        let delivered = delivered.clone();
+        move |batch: crate::recipes::OutboxBatch<(i32,)>| {
+            let delivered = delivered.clone();
+            async move {
+                delivered.lock().unwrap().extend(
+                    batch
+                        .events
+                        .into_iter()
+                        .map(|event| (consumer, batch.shard, event.event.0)),
+                );
+                Ok::<_, Infallible>(())
+            }
+        }
+    };
+    assert_eq!(first.poll(&session, handler("first")).await.unwrap(), 0);
+
+    let insert = session
+        .prepare(format!(
+            "INSERT INTO {}.outbox (shard, bucket, id, payload) VALUES (?, ?, now(), ?)",
+            ks
+        ))
+        .await
+        .unwrap();
+    let bucket = first.config().bucket_at(SystemTime::now());
+    for payload in 0..20 {
+        session
+            .execute_unpaged(&insert, (payload % 2, bucket, payload))
+            .await
+            .unwrap();
+    }
+    tokio::time::sleep(Duration::from_secs(2)).await;
+
+    // Each shard is claimed by one of the concurrent consumers,
+    // so every row is delivered exactly once.
+    let (first_handled, second_handled) = tokio::join!(
+        first.poll(&session, handler("first")),
+        second.poll(&session, handler("second")),
+    );
+    assert_eq!(first_handled.unwrap() + second_handled.unwrap(), 20);
+    let mut delivered_rows = delivered.lock().unwrap().clone();
+    for shard in 0..2 {
+        let consumers: HashSet<&str> = delivered_rows
+            .iter()
+            .filter(|(_, row_shard, _)| *row_shard == shard)
+            .map(|(consumer, _, _)| *consumer)
+            .collect();
+        assert_eq!(consumers.len(), 1);
+    }
+    delivered_rows.sort_by_key(|(_, _, payload)| *payload);
+    assert_eq!(
+        delivered_rows
+            .iter()
+            .map(|(_, _, payload)| *payload)
+            .collect::<Vec<_>>(),
+        (0..20).collect::<Vec<_>>()
+    );
+
+    // Acknowledged rows are not delivered again, also after the shards change hands.
+    assert_eq!(first.poll(&session, handler("first")).await.unwrap(), 0);
+    assert_eq!(second.poll(&session, handler("second")).await.unwrap(), 0);
+    first.release(&session).await.unwrap();
+    second.release(&session).await.unwrap();
+    let (first_handled, second_handled) = tokio::join!(
+        first.poll(&session, handler("first")),
+        second.poll(&session, handler("second")),
+    );
+    assert_eq!(first_handled.unwrap() + second_handled.unwrap(), 0);
+    assert_eq!(delivered.lock().unwrap().len(), 20);
+}
+
+#[tokio::test]
+async fn test_read_your_writes() {
+    use crate::transport::read_your_writes::ReadYourWritesConfig;
+
+    setup_tracing();
+    let session = create_new_session_builder()
+        .track_write_timestamps(100)
+        .build()
+        .await
+        .unwrap();
+    let ks = unique_keyspace_name();
+    session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 1}}", ks)).await.unwrap();
+    session
+        .ddl(format!(
+            "CREATE TABLE {}.t (pk int PRIMARY KEY, v text)",
+            ks
+        ))
+        .await
+        .unwrap();
+
+    let insert = session
+        .prepare(format!("INSERT INTO {}.t (pk, v) VALUES (?, ?)", ks))
+        .await
+        .unwrap();
+    let delete = session
+        .prepare(format!("DELETE FROM {}.t WHERE pk = ?", ks))
+        .await
+        .unwrap();
+    let select = session
+        .prepare(format!("SELECT v, WRITETIME(v) FROM {}.t WHERE pk = ?", ks))
+        .await
+        .unwrap();
+    let read = || async {
+        session
+            .execute_read_your_writes(&select, (1_i32,), ReadYourWritesConfig::default(), |rows| {
+                rows.maybe_first_row::<(String, i64)>()
+                    .unwrap()
+                    .map(|(_, write_time)| write_time)
+            })
+            .await
+            .unwrap()
+            .maybe_first_row::<(String, i64)>()
+            .unwrap()
+    };
+
+    session
+        .execute_unpaged(&insert, (1_i32, "value"))
+        .await
+        .unwrap();
+    let timestamp = session
+        .last_write_timestamp(&select, &(1_i32,))
+        .unwrap()
+        .unwrap();
+    assert_eq!(read().await, Some(("value".to_owned(), timestamp)));
+
+    // The deleted row can't be read, which reflects the deletion.
+    session.execute_unpaged(&delete, (1_i32,)).await.unwrap();
+    assert!(
+        session
+            .last_write_timestamp(&select, &(1_i32,))
+            .unwrap()
+            .unwrap()
+            > timestamp
+    );
+    assert_eq!(read().await, None);
+}
@@ -0,0 +1,148 @@
+//! Connecting to nodes through an SNI proxy.
+//!
+//! In some deployments, e.g. the serverless Scylla Cloud or clusters hidden
+//! behind an SNI-terminating load balancer, the nodes are not reachable directly.
+//! Instead, all connections are opened to a single proxy endpoint, and the proxy
+//! forwards each TLS connection to the node named by its SNI (Server Name Indication).
+//!
+//! With an [`SniProxyConfig`] set on the session:
+//! - connections to all the nodes fetched from the cluster are opened to the proxy,
+//! - the SNI of a connection to a node is `<host_id>.<node_domain>`, and the SNI
+//!   of connections to the contact points is `<node_domain>`,
+//! - the certificate presented on a connection is verified against its SNI,
+//!   instead of the IP address of the node,
+//! - the shard-aware port is not used, because the proxy doesn't expose it.
+//!
+//! The proxy requires TLS, so the session must be configured with TLS,
+//! using either the `ssl` or the `rustls` feature.
+
+use tracing::warn;
+use uuid::Uuid;
+
+use super::node::resolve_hostname;
+use super::topology::{PeerEndpoint, UntranslatedEndpoint};
+use super::NodeAddr;
+
+/// Configuration of an SNI proxy, through which connections to all nodes are opened.
+/// See the [module-level documentation](self) for details.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SniProxyConfig {
+    proxy_address: String,
+    node_domain: String,
+}
+
+impl SniProxyConfig {
+    /// Creates a config of the proxy at `proxy_address`, which is a hostname or an IP
+    /// address with a port, e.g. `"proxy.example.com:9142"`. The hostname is resolved
+    /// each time a connection is opened.
+    ///
+    /// The nodes are named with subdomains of `node_domain`, named after their host ids.
+    ///
+    /// ```
+    /// # use scylla::transport::sni_proxy::SniProxyConfig;
+    /// let config = SniProxyConfig::new("proxy.example.com:9142", "nodes.example.com");
+    /// ```
+    pub fn new(proxy_address: impl Into<String>, node_domain: impl Into<String>) -> Self {
+        Self {
+            proxy_address: proxy_address.into(),
+            node_domain: node_domain.into(),
+        }
+    }
+
+    /// Returns the address of the proxy.
+    pub fn proxy_address(&self) -> &str {
+        &self.proxy_address
+    }
+
+    /// Returns the domain which the names of the nodes belong to.
+    pub fn node_domain(&self) -> &str {
+        &self.node_domain
+    }
+
+    /// Returns the server name used for connections to the node with the given host id,
+    /// or to a contact point if `host_id` is `None`.
+    pub fn server_name(&self, host_id: Option<Uuid>) -> String {
+        match host_id {
+            Some(host_id) => format!("{}.{}", host_id, self.node_domain),
+            None => self.node_domain.clone(),
+        }
+    }
+
+    // Substitutes the address of a node fetched from the cluster with the address of the proxy.
+    // The contact points are provided by the user, so they are already the address of the proxy.
+    pub(crate) async fn translate_endpoint(
+        &self,
+        mut endpoint: UntranslatedEndpoint,
+    ) -> UntranslatedEndpoint {
+        if let UntranslatedEndpoint::Peer(PeerEndpoint {
+            host_id,
+            ref mut address,
+            ..
+        }) = endpoint
+        {
+            match resolve_hostname(&self.proxy_address).await {
+                Ok(resolved) => *address = NodeAddr::Untranslatable(resolved),
+                Err(err) => warn!(
+                    "Couldn't resolve address {} of the SNI proxy ({}); therefore address \
+                     broadcast by node {} was left as address to open connection to.",
+                    self.proxy_address, err, host_id
+                ),
+            }
+        }
+        endpoint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::SocketAddr;
+    use std::str::FromStr;
+
+    use uuid::Uuid;
+
+    use super::SniProxyConfig;
+    use crate::test_utils::setup_tracing;
+    use crate::transport::node::ResolvedContactPoint;
+    use crate::transport::topology::{PeerEndpoint, UntranslatedEndpoint};
+    use crate::transport::NodeAddr;
+
+    #[test]
+    fn server_names() {
+        setup_tracing();
+        let config = SniProxyConfig::new("127.0.0.1:9142", "nodes.example.com");
+        let host_id = Uuid::from_str("b8ea0e28-0c6b-4a58-a5a6-4ec3d35b5ae6").unwrap();
+        assert_eq!(
+            config.server_name(Some(host_id)),
+            "b8ea0e28-0c6b-4a58-a5a6-4ec3d35b5ae6.nodes.example.com"
+        );
+        assert_eq!(config.server_name(None), "nodes.example.com");
+    }
+
+    #[tokio::test]
+    async fn peers_are_translated_to_proxy() {
+        setup_tracing();
+        let config = SniProxyConfig::new("127.0.0.1:9142", "nodes.example.com");
+        let proxy_address = SocketAddr::from_str("127.0.0.1:9142").unwrap();
+        let node_address = SocketAddr::from_str("10.0.0.1:9042").unwrap();
+
+        let peer = UntranslatedEndpoint::Peer(PeerEndpoint {
+            host_id: Uuid::new_v4(),
+            address: NodeAddr::Translatable(node_address),
+            datacenter: None,
+            rack: None,
+        });
+        assert_eq!(
+            config.translate_endpoint(peer).await.address(),
+            NodeAddr::Untranslatable(proxy_address)
+        );
+
+        let contact_point = UntranslatedEndpoint::ContactPoint(ResolvedContactPoint {
+            address: node_address,
+            datacenter: None,
+        });
+        assert_eq!(
+            config.translate_endpoint(contact_point).await.address(),
+            NodeAddr::Untranslatable(node_address)
+        );
+    }
+}
@@ -9,10 +9,14 @@ use tracing::{trace_span, warn, Instrument};
 use crate::transport::errors::QueryError;
 
 use super::metrics::Metrics;
+use super::node::Node;
 
 /// Context is passed as an argument to `SpeculativeExecutionPolicy` methods
 pub struct Context {
     pub metrics: Arc<Metrics>,
+
+    /// The node which the original request is sent to, if the query plan is not empty.
+    pub node: Option<Arc<Node>>,
 }
 
 /// The policy that decides if the driver will send speculative queries to the
@@ -40,6 +44,11 @@ pub struct SimpleSpeculativeExecutionPolicy {
 
 /// A policy that triggers speculative executions when the request to the current
 /// host is above a given percentile.
+///
+/// The percentile is computed from the latencies of the most recent requests
+/// to the node which the original request is sent to (see [`Node::latency_percentile`]).
+/// If no request to that node has succeeded yet, the latencies of all requests
+/// sent by the session are used instead.
 #[derive(Debug, Clone)]
 pub struct PercentileSpeculativeExecutionPolicy {
     /// The maximum number of speculative executions that will be triggered
@@ -67,6 +76,14 @@ impl SpeculativeExecutionPolicy for PercentileSpeculativeExecutionPolicy {
     }
 
     fn retry_interval(&self, context: &Context) -> Duration {
+        if let Some(latency) = context
+            .node
+            .as_ref()
+            .and_then(|node| node.latency_percentile(self.percentile))
+        {
+            return latency;
+        }
+
         let interval = context.metrics.get_latency_percentile_ms(self.percentile);
         let ms = match interval {
             Ok(d) => d,
@@ -100,7 +117,11 @@ fn can_be_ignored<ResT>(result: &Result<ResT, QueryError>) -> bool {
             | QueryError::BodyExtensionsParseError(_)
             | QueryError::CqlResultParseError(_)
             | QueryError::CqlErrorParseError(_)
-            | QueryError::ProtocolError(_) => false,
+            | QueryError::ProtocolError(_)
+            | QueryError::ResultMemoryLimitExceeded { .. } => false,
+
+            // The throttler is consulted once, before any execution is started.
+            QueryError::Overloaded(_) => false,
 
             // EmptyPlan is not returned by `Session::execute_query`.
             // It is represented by None, which is then transformed
@@ -0,0 +1,274 @@
+//! Request throttling
+//!
+//! A session can be configured with a `RequestThrottler`, which is consulted before
+//! each request is sent and can delay or reject it. This protects both the driver
+//! and the cluster from a caller which issues more requests than they can handle.
+//! Rejected requests fail with [`QueryError::Overloaded`](super::errors::QueryError::Overloaded).
+
+use std::any::Any;
+use std::num::NonZeroU32;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use tokio::sync::Semaphore;
+use tokio::time::Instant;
+
+use super::errors::OverloadedError;
+
+/// Decides whether a request can be sent right away, has to wait, or is rejected.
+///
+/// The throttler is consulted once for each request executed by the session
+/// (including all its retries and speculative executions), and once for each page
+/// fetched by a [`QueryPager`](super::iterator::QueryPager) (including its retries).
+#[async_trait]
+pub trait RequestThrottler: std::fmt::Debug + Send + Sync {
+    /// Called before a request is sent. Resolves once the request is allowed to be sent,
+    /// or to an error if the request should be rejected.
+    /// The returned permit is held until the request completes.
+    async fn acquire(&self) -> Result<ThrottlerPermit, OverloadedError>;
+}
+
+/// Held by a request let through by a [`RequestThrottler`] until the request completes.
+pub struct ThrottlerPermit {
+    _guard: Option<Box<dyn Any + Send + Sync>>,
+}
+
+impl ThrottlerPermit {
+    /// Creates a permit which drops `guard` when the request completes.
+    pub fn new(guard: impl Any + Send + Sync) -> Self {
+        Self {
+            _guard: Some(Box::new(guard)),
+        }
+    }
+
+    /// Creates a permit which doesn't track the completion of the request,
+    /// e.g. for throttlers which only limit the rate of requests.
+    pub fn unguarded() -> Self {
+        Self { _guard: None }
+    }
+}
+
+impl std::fmt::Debug for ThrottlerPermit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ThrottlerPermit").finish_non_exhaustive()
+    }
+}
+
+/// Decides what happens with a request which exceeds the limits of a throttler.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum OverflowPolicy {
+    /// The request fails immediately with [`QueryError::Overloaded`](super::errors::QueryError::Overloaded).
+    #[default]
+    Reject,
+
+    /// The request waits until it fits in the limits. The request timeout (if any)
+    /// still applies to the time spent waiting.
+    Queue,
+}
+
+/// Limits the number of requests in flight.
+#[derive(Debug, Clone)]
+pub struct ConcurrencyLimitThrottler {
+    max_in_flight: usize,
+    overflow_policy: OverflowPolicy,
+    semaphore: Arc<Semaphore>,
+}
+
+impl ConcurrencyLimitThrottler {
+    /// Creates a throttler which lets at most `max_in_flight` requests be in flight at once
+    /// and rejects the requests above the limit.
+    pub fn new(max_in_flight: usize) -> Self {
+        let max_in_flight = max_in_flight.min(Semaphore::MAX_PERMITS);
+        Self {
+            max_in_flight,
+            overflow_policy: OverflowPolicy::default(),
+            semaphore: Arc::new(Semaphore::new(max_in_flight)),
+        }
+    }
+
+    /// Sets what happens with the requests above the limit.
+    pub fn with_overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    /// Returns the number of requests which can be sent before reaching the limit.
+    pub fn available_permits(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+}
+
+#[async_trait]
+impl RequestThrottler for ConcurrencyLimitThrottler {
+    async fn acquire(&self) -> Result<ThrottlerPermit, OverloadedError> {
+        let permit = match self.overflow_policy {
+            OverflowPolicy::Reject => self.semaphore.clone().try_acquire_owned().ok(),
+            OverflowPolicy::Queue => self.semaphore.clone().acquire_owned().await.ok(),
+        };
+        permit
+            .map(ThrottlerPermit::new)
+            .ok_or(OverloadedError::TooManyInFlightRequests {
+                max_in_flight: self.max_in_flight,
+            })
+    }
+}
+
+/// Limits the rate of requests with a token bucket: the bucket holds up to
+/// `burst` tokens, is refilled with `max_per_second` tokens per second,
+/// and each request takes one token.
+#[derive(Debug)]
+pub struct RateLimitThrottler {
+    max_per_second: NonZeroU32,
+    burst: NonZeroU32,
+    overflow_policy: OverflowPolicy,
+    bucket: Mutex<TokenBucket>,
+}
+
+#[derive(Debug)]
+struct TokenBucket {
+    // Negative if the tokens were reserved by requests waiting in the queue.
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RateLimitThrottler {
+    /// Creates a throttler which lets through at most `max_per_second` requests per second
+    /// on average, and at most `burst` requests at once, and rejects the requests above the limit.
+    pub fn new(max_per_second: NonZeroU32, burst: NonZeroU32) -> Self {
+        Self {
+            max_per_second,
+            burst,
+            overflow_policy: OverflowPolicy::default(),
+            bucket: Mutex::new(TokenBucket {
+                tokens: burst.get() as f64,
+                last_refill: Instant::now(),
+            }),
+        }
+    }
+
+    /// Sets what happens with the requests above the limit.
+    pub fn with_overflow_policy(mut self, overflow_policy: OverflowPolicy) -> Self {
+        self.overflow_policy = overflow_policy;
+        self
+    }
+
+    // Takes a token and returns how long the request has to wait for it,
+    // or None if the request is rejected.
+    fn take_token(&self) -> Option<Duration> {
+        let rate = self.max_per_second.get() as f64;
+        let mut bucket = self.bucket.lock().unwrap();
+        let now = Instant::now();
+        let elapsed = now.duration_since(bucket.last_refill).as_secs_f64();
+        bucket.tokens = (bucket.tokens + elapsed * rate).min(self.burst.get() as f64);
+        bucket.last_refill = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            return Some(Duration::ZERO);
+        }
+        match self.overflow_policy {
+            OverflowPolicy::Reject => None,
+            OverflowPolicy::Queue => {
+                let wait = Duration::from_secs_f64((1.0 - bucket.tokens) / rate);
+                bucket.tokens -= 1.0;
+                Some(wait)
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl RequestThrottler for RateLimitThrottler {
+    async fn acquire(&self) -> Result<ThrottlerPermit, OverloadedError> {
+        let wait = self
+            .take_token()
+            .ok_or(OverloadedError::RateLimitExceeded {
+                max_per_second: self.max_per_second.get(),
+            })?;
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
+        }
+        Ok(ThrottlerPermit::unguarded())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::num::NonZeroU32;
+    use std::time::Duration;
+
+    use super::{ConcurrencyLimitThrottler, OverflowPolicy, RateLimitThrottler, RequestThrottler};
+    use crate::test_utils::setup_tracing;
+    use crate::transport::errors::OverloadedError;
+
+    #[tokio::test]
+    async fn concurrency_limit_rejects() {
+        setup_tracing();
+        let throttler = ConcurrencyLimitThrottler::new(2);
+        let first = throttler.acquire().await.unwrap();
+        let _second = throttler.acquire().await.unwrap();
+        assert_eq!(throttler.available_permits(), 0);
+        assert!(matches!(
+            throttler.acquire().await,
+            Err(OverloadedError::TooManyInFlightRequests { max_in_flight: 2 })
+        ));
+
+        // A completed request frees its slot.
+        drop(first);
+        assert!(throttler.acquire().await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn concurrency_limit_queues() {
+        setup_tracing();
+        let throttler =
+            ConcurrencyLimitThrottler::new(1).with_overflow_policy(OverflowPolicy::Queue);
+        let first = throttler.acquire().await.unwrap();
+
+        let queued = tokio::spawn({
+            let throttler = throttler.clone();
+            async move { throttler.acquire().await.map(|_| ()) }
+        });
+        tokio::task::yield_now().await;
+        assert!(!queued.is_finished());
+
+        drop(first);
+        queued.await.unwrap().unwrap();
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_rejects() {
+        setup_tracing();
+        let throttler =
+            RateLimitThrottler::new(NonZeroU32::new(10).unwrap(), NonZeroU32::new(3).unwrap());
+        for _ in 0..3 {
+            throttler.acquire().await.unwrap();
+        }
+        assert!(matches!(
+            throttler.acquire().await,
+            Err(OverloadedError::RateLimitExceeded { max_per_second: 10 })
+        ));
+
+        // The bucket is refilled with one token every 100ms.
+        tokio::time::advance(Duration::from_millis(100)).await;
+        throttler.acquire().await.unwrap();
+        assert!(throttler.acquire().await.is_err());
+    }
+
+    #[tokio::test(start_paused = true)]
+    async fn rate_limit_queues() {
+        setup_tracing();
+        let throttler =
+            RateLimitThrottler::new(NonZeroU32::new(10).unwrap(), NonZeroU32::new(1).unwrap())
+                .with_overflow_policy(OverflowPolicy::Queue);
+        let start = tokio::time::Instant::now();
+        for _ in 0..4 {
+            throttler.acquire().await.unwrap();
+        }
+        // The first request takes the initial token, the others wait 100ms each.
+        let elapsed = start.elapsed();
+        assert!(Duration::from_millis(299) <= elapsed && elapsed <= Duration::from_millis(302));
+    }
+}
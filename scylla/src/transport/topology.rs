@@ -8,6 +8,7 @@ use crate::transport::errors::{DbError, NewSessionError, QueryError};
 use crate::transport::host_filter::HostFilter;
 use crate::transport::iterator::QueryPager;
 use crate::transport::node::resolve_contact_points;
+use crate::transport::reconnection_policy::ReconnectionPolicy;
 use crate::utils::parse::{ParseErrorCause, ParseResult, ParserState};
 
 use futures::future::{self, FutureExt};
@@ -41,6 +42,7 @@ use super::node::{InternalKnownNode, NodeAddr, ResolvedContactPoint};
 pub(crate) struct MetadataReader {
     connection_config: ConnectionConfig,
     keepalive_interval: Option<Duration>,
+    reconnection_policy: Arc<dyn ReconnectionPolicy>,
 
     control_connection_endpoint: UntranslatedEndpoint,
     control_connection: NodeConnectionPool,
@@ -473,6 +475,7 @@ impl MetadataReader {
         control_connection_repair_requester: broadcast::Sender<()>,
         mut connection_config: ConnectionConfig,
         keepalive_interval: Option<Duration>,
+        reconnection_policy: Arc<dyn ReconnectionPolicy>,
         server_event_sender: mpsc::Sender<Event>,
         keyspaces_to_fetch: Vec<String>,
         fetch_schema: bool,
@@ -503,6 +506,7 @@ impl MetadataReader {
             control_connection_endpoint.clone(),
             connection_config.clone(),
             keepalive_interval,
+            reconnection_policy.clone(),
             control_connection_repair_requester.clone(),
         );
 
@@ -510,6 +514,7 @@ impl MetadataReader {
             control_connection_endpoint,
             control_connection,
             keepalive_interval,
+            reconnection_policy,
             connection_config,
             known_peers: initial_peers
                 .into_iter()
@@ -630,6 +635,7 @@ impl MetadataReader {
                 self.control_connection_endpoint.clone(),
                 self.connection_config.clone(),
                 self.keepalive_interval,
+                self.reconnection_policy.clone(),
                 self.control_connection_repair_requester.clone(),
             );
 
@@ -730,6 +736,7 @@ impl MetadataReader {
                         self.control_connection_endpoint.clone(),
                         self.connection_config.clone(),
                         self.keepalive_interval,
+                        self.reconnection_policy.clone(),
                         self.control_connection_repair_requester.clone(),
                     );
                 }
@@ -741,11 +748,13 @@ impl MetadataReader {
         endpoint: UntranslatedEndpoint,
         connection_config: ConnectionConfig,
         keepalive_interval: Option<Duration>,
+        reconnection_policy: Arc<dyn ReconnectionPolicy>,
         refresh_requester: broadcast::Sender<()>,
     ) -> NodeConnectionPool {
         let pool_config = PoolConfig {
             connection_config,
             keepalive_interval,
+            reconnection_policy,
 
             // We want to have only one connection to receive events from
             pool_size: PoolSize::PerHost(NonZeroUsize::new(1).unwrap()),
@@ -753,6 +762,13 @@ impl MetadataReader {
             // The shard-aware port won't be used with PerHost pool size anyway,
             // so explicitly disable it here
             can_use_shard_aware_port: false,
+
+            // Replacing the control connection would interrupt receiving events
+            max_connection_lifetime: None,
+            max_requests_per_connection: None,
+
+            // Requests sent on the control connection don't go through nodes
+            backpressure_monitor: None,
         };
 
         NodeConnectionPool::new(endpoint, pool_config, None, refresh_requester)
@@ -0,0 +1,155 @@
+//! User-defined tags, e.g. the name of a tenant or a workload, attached to the
+//! observability data produced by the driver.
+//!
+//! Tags can be set for the whole session with [`SessionBuilder::user_tag`], and for
+//! particular requests with [`ExecutionProfileBuilder::user_tag`]. Then:
+//! - the tags of the session are recorded in the `user_tags` field of the spans
+//!   which the connections and connection pools of the session log in,
+//! - the tags of the session, overridden by the tags of the execution profile,
+//!   are recorded in the `user_tags` field of the span of every request,
+//! - with the `metrics` feature, the same tags label the request metrics exported
+//!   through the `metrics` crate, and the tags of the session label the pool metrics,
+//! - the tags of the session are available via [`Metrics::user_tags`], e.g. to label
+//!   the metrics of [`Session::get_metrics`] when exporting them by hand.
+//!
+//! [`SessionBuilder::user_tag`]: crate::transport::session_builder::GenericSessionBuilder::user_tag
+//! [`ExecutionProfileBuilder::user_tag`]: crate::transport::execution_profile::ExecutionProfileBuilder::user_tag
+//! [`Metrics::user_tags`]: crate::Metrics::user_tags
+//! [`Session::get_metrics`]: crate::Session::get_metrics
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+
+/// A set of key-value tags, ordered by key.
+///
+/// ```
+/// # use scylla::transport::UserTags;
+/// let tags = UserTags::new()
+///     .with("tenant", "acme")
+///     .with("workload", "billing");
+/// assert_eq!(tags.get("tenant"), Some("acme"));
+/// assert_eq!(tags.to_string(), "tenant=acme,workload=billing");
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct UserTags {
+    tags: BTreeMap<String, String>,
+}
+
+impl UserTags {
+    /// Creates an empty set of tags.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the value of the tag, returning the previous value, if any.
+    pub fn insert(&mut self, key: impl Into<String>, value: impl Into<String>) -> Option<String> {
+        self.tags.insert(key.into(), value.into())
+    }
+
+    /// Sets the value of the tag.
+    pub fn with(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.insert(key, value);
+        self
+    }
+
+    /// Returns the value of the tag.
+    pub fn get(&self, key: &str) -> Option<&str> {
+        self.tags.get(key).map(String::as_str)
+    }
+
+    /// Returns the tags, ordered by key.
+    pub fn iter(&self) -> impl Iterator<Item = (&str, &str)> {
+        self.tags
+            .iter()
+            .map(|(key, value)| (key.as_str(), value.as_str()))
+    }
+
+    /// Returns the number of tags.
+    pub fn len(&self) -> usize {
+        self.tags.len()
+    }
+
+    /// Returns `true` if there are no tags.
+    pub fn is_empty(&self) -> bool {
+        self.tags.is_empty()
+    }
+
+    /// Returns these tags with the values of `overrides` taking precedence.
+    pub(crate) fn merged_with<'a>(&'a self, overrides: &'a UserTags) -> Cow<'a, UserTags> {
+        if overrides.is_empty() {
+            Cow::Borrowed(self)
+        } else if self.is_empty() {
+            Cow::Borrowed(overrides)
+        } else {
+            let mut merged = self.clone();
+            merged.tags.extend(overrides.tags.clone());
+            Cow::Owned(merged)
+        }
+    }
+
+    /// Returns the span the background tasks of connections are run in,
+    /// so that their logs are tagged. It's disabled if there are no tags.
+    pub(crate) fn span(&self) -> tracing::Span {
+        if self.is_empty() {
+            tracing::Span::none()
+        } else {
+            tracing::info_span!("scylla", user_tags = %self)
+        }
+    }
+}
+
+impl Display for UserTags {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut tags = self.iter();
+        if let Some((key, value)) = tags.next() {
+            write!(f, "{}={}", key, value)?;
+            for (key, value) in tags {
+                write!(f, ",{}={}", key, value)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<K, V> FromIterator<(K, V)> for UserTags
+where
+    K: Into<String>,
+    V: Into<String>,
+{
+    fn from_iter<T: IntoIterator<Item = (K, V)>>(iter: T) -> Self {
+        Self {
+            tags: iter
+                .into_iter()
+                .map(|(key, value)| (key.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::UserTags;
+    use crate::test_utils::setup_tracing;
+
+    #[test]
+    fn merging_tags() {
+        setup_tracing();
+        let session_tags: UserTags = [("tenant", "acme"), ("workload", "web")]
+            .into_iter()
+            .collect();
+        let profile_tags = UserTags::new().with("workload", "batch");
+
+        assert_eq!(
+            session_tags.merged_with(&UserTags::new()).as_ref(),
+            &session_tags
+        );
+        assert_eq!(
+            UserTags::new().merged_with(&profile_tags).as_ref(),
+            &profile_tags
+        );
+        let merged = session_tags.merged_with(&profile_tags);
+        assert_eq!(merged.to_string(), "tenant=acme,workload=batch");
+        assert_eq!(UserTags::new().to_string(), "");
+    }
+}
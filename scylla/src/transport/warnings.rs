@@ -0,0 +1,29 @@
+//! Handling of warnings sent by the database.
+//!
+//! The database may attach warnings to its responses, e.g. when a query
+//! aggregates data without restricting the partition key, or reads
+//! many tombstones. The driver logs them, makes them available
+//! with [`QueryResult::warnings`](crate::QueryResult::warnings), and passes them
+//! to the [`WarningHandler`] of the session, if there is one.
+//! The handler also receives the warnings of requests whose results don't
+//! expose them, like the pages fetched by a [`QueryPager`](crate::transport::iterator::QueryPager).
+
+use std::net::SocketAddr;
+
+/// Receives the warnings which the database attached to responses.
+///
+/// It is called on the task which sent the request, so it should not block.
+/// The trait is implemented for closures taking the same arguments as [`WarningHandler::on_warning`].
+pub trait WarningHandler: Send + Sync {
+    /// Called for each warning attached to a response from the node at `node_address`.
+    fn on_warning(&self, warning: &str, node_address: SocketAddr);
+}
+
+impl<F> WarningHandler for F
+where
+    F: Fn(&str, SocketAddr) + Send + Sync,
+{
+    fn on_warning(&self, warning: &str, node_address: SocketAddr) {
+        self(warning, node_address)
+    }
+}
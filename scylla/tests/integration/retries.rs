@@ -232,3 +232,192 @@ async fn speculative_execution_panic_regression_test() {
         Err(err) => panic!("{}", err),
     }
 }
+
+#[tokio::test]
+#[ntest::timeout(30000)]
+#[cfg(not(scylla_cloud_tests))]
+async fn paging_is_resumed_after_transient_failures() {
+    use futures::TryStreamExt;
+    use scylla::statement::ResumablePaging;
+
+    setup_tracing();
+    let res = test_with_3_node_cluster(ShardAwareness::QueryNode, |proxy_uris, translation_map, mut running_proxy| async move {
+        let profile = ExecutionProfile::builder()
+            .retry_policy(Arc::new(FallthroughRetryPolicy))
+            .build();
+        let session: Session = SessionBuilder::new()
+            .known_node(proxy_uris[0].as_str())
+            .address_translator(Arc::new(translation_map))
+            .default_execution_profile_handle(profile.into_handle())
+            .build()
+            .await
+            .unwrap();
+
+        let ks = unique_keyspace_name();
+        session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 3}}", ks)).await.unwrap();
+        session.use_keyspace(ks, false).await.unwrap();
+        session
+            .ddl("CREATE TABLE t (a int, b int, primary key (a, b))")
+            .await
+            .unwrap();
+        for b in 0..100 {
+            session
+                .query_unpaged("INSERT INTO t (a, b) VALUES (0, ?)", (b,))
+                .await
+                .unwrap();
+        }
+
+        let mut q = Query::from("SELECT b FROM t WHERE a = 0");
+        q.set_page_size(10);
+        q.set_resumable_paging(Some(
+            ResumablePaging::new(10).with_delay(Duration::from_millis(1)),
+        ));
+
+        let mut rows = session
+            .query_iter(q, ())
+            .await
+            .unwrap()
+            .rows_stream::<(i32,)>()
+            .unwrap();
+        let mut received = Vec::new();
+        while received.len() < 10 {
+            received.push(rows.try_next().await.unwrap().unwrap().0);
+        }
+
+        // Fetching the next pages fails twice on every node.
+        let forge_error_rule = RequestRule(
+            Condition::RequestOpcode(RequestOpcode::Query)
+                .and(Condition::BodyContainsCaseSensitive(Box::new(*b"FROM t")))
+                .and(Condition::TrueForLimitedTimes(2)),
+            RequestReaction::forge().read_timeout(),
+        );
+        for node in running_proxy.running_nodes.iter_mut() {
+            node.change_request_rules(Some(vec![forge_error_rule.clone()]));
+        }
+
+        while let Some((b,)) = rows.try_next().await.unwrap() {
+            received.push(b);
+        }
+        assert_eq!(received, (0..100).collect::<Vec<_>>());
+
+        running_proxy
+    }).await;
+
+    match res {
+        Ok(()) => (),
+        Err(ProxyError::Worker(WorkerError::DriverDisconnected(_))) => (),
+        Err(err) => panic!("{}", err),
+    }
+}
+
+#[tokio::test]
+#[ntest::timeout(30000)]
+#[cfg(not(scylla_cloud_tests))]
+async fn paging_acquires_throttler_permit_once_per_page() {
+    use futures::TryStreamExt;
+    use scylla::transport::errors::OverloadedError;
+    use scylla::transport::throttler::{RequestThrottler, ThrottlerPermit};
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use tokio::sync::mpsc;
+
+    #[derive(Debug, Default)]
+    struct CountingThrottler {
+        acquired: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl RequestThrottler for CountingThrottler {
+        async fn acquire(&self) -> Result<ThrottlerPermit, OverloadedError> {
+            self.acquired.fetch_add(1, Ordering::Relaxed);
+            Ok(ThrottlerPermit::unguarded())
+        }
+    }
+
+    fn count_received<T>(rx: &mut mpsc::UnboundedReceiver<T>) -> usize {
+        std::iter::from_fn(|| rx.try_recv().ok()).count()
+    }
+
+    setup_tracing();
+    let res = test_with_3_node_cluster(ShardAwareness::QueryNode, |proxy_uris, translation_map, mut running_proxy| async move {
+        let throttler = Arc::new(CountingThrottler::default());
+        let session: Session = SessionBuilder::new()
+            .known_node(proxy_uris[0].as_str())
+            .address_translator(Arc::new(translation_map))
+            .request_throttler(throttler.clone())
+            .build()
+            .await
+            .unwrap();
+
+        let ks = unique_keyspace_name();
+        session.ddl(format!("CREATE KEYSPACE IF NOT EXISTS {} WITH REPLICATION = {{'class' : 'NetworkTopologyStrategy', 'replication_factor' : 3}}", ks)).await.unwrap();
+        session.use_keyspace(ks, false).await.unwrap();
+        session
+            .ddl("CREATE TABLE t (a int, b int, primary key (a, b))")
+            .await
+            .unwrap();
+        for b in 0..30 {
+            session
+                .query_unpaged("INSERT INTO t (a, b) VALUES (0, ?)", (b,))
+                .await
+                .unwrap();
+        }
+
+        // The first page request fails on two of the nodes, so some pages are retried
+        // on another node. Every page request which reaches a node is counted.
+        let (failed_tx, mut failed_rx) = mpsc::unbounded_channel();
+        let (sent_tx, mut sent_rx) = mpsc::unbounded_channel();
+        let select = || {
+            Condition::RequestOpcode(RequestOpcode::Query)
+                .and(Condition::BodyContainsCaseSensitive(Box::new(*b"FROM t")))
+        };
+        let count_rule = RequestRule(
+            select(),
+            RequestReaction::noop().with_feedback_when_performed(sent_tx),
+        );
+        let forge_error_rule = RequestRule(
+            select().and(Condition::TrueForLimitedTimes(1)),
+            RequestReaction::forge()
+                .server_error()
+                .with_feedback_when_performed(failed_tx),
+        );
+        for (i, node) in running_proxy.running_nodes.iter_mut().enumerate() {
+            let mut rules = vec![count_rule.clone()];
+            if i < 2 {
+                rules.insert(0, forge_error_rule.clone());
+            }
+            node.change_request_rules(Some(rules));
+        }
+
+        let acquired_before = throttler.acquired.load(Ordering::Relaxed);
+        let mut q = Query::from("SELECT b FROM t WHERE a = 0");
+        q.set_page_size(10);
+        q.set_is_idempotent(true); // this is to allow retry to fire
+        let received: Vec<(i32,)> = session
+            .query_iter(q, ())
+            .await
+            .unwrap()
+            .rows_stream::<(i32,)>()
+            .unwrap()
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(received.len(), 30);
+
+        let failed = count_received(&mut failed_rx);
+        let pages = count_received(&mut sent_rx);
+        assert_eq!(
+            throttler.acquired.load(Ordering::Relaxed) - acquired_before,
+            pages,
+            "{} page requests failed and were retried",
+            failed
+        );
+
+        running_proxy
+    }).await;
+
+    match res {
+        Ok(()) => (),
+        Err(ProxyError::Worker(WorkerError::DriverDisconnected(_))) => (),
+        Err(err) => panic!("{}", err),
+    }
+}